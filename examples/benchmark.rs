@@ -15,7 +15,7 @@ fn test_main(n_node: usize, _n_edge: usize) {
     for i in 0..n_node {
         let elem = Element::create(
             ShapeKind::Circle(format!("hi_{}", i)),
-            StyleAttr::new(Color::transparent(), 0, None, 0, 0),
+            StyleAttr::new(Color::transparent(), 0., None, 0, 0),
             Orientation::LeftToRight,
             Point::zero(),
         );
@@ -315,11 +315,10 @@ fn test8(offset_x: f64, offset_y: f64, svg: &mut SVGWriter) {
     let t0 = RecordDef::Array(v0);
     let t1 = RecordDef::Array(v1);
     let rec0 = ShapeKind::Record(RecordDef::Array(vec![t0, t1]));
-    let sz = get_shape_size(Orientation::LeftToRight, &rec0, 15, false);
-
     let mut look1 = StyleAttr::simple();
     look1.fill_color = Some(Color::fast("steelblue"));
     look1.line_color = Color::fast("white");
+    let sz = get_shape_size(Orientation::LeftToRight, &rec0, &look1, false);
 
     let mut es0 = Element::create(rec0, look1, Orientation::LeftToRight, sz);
 
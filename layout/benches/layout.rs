@@ -0,0 +1,60 @@
+//! Benchmarks of the layout of large synthetic graphs. Run them with
+//! `cargo bench -p layout-rs`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use layout::backends::svg::SVGWriter;
+use layout::core::base::Orientation;
+use layout::core::geometry::Point;
+use layout::core::style::StyleAttr;
+use layout::core::utils::Rng;
+use layout::std_shapes::shapes::{Arrow, Element, ShapeKind};
+use layout::topo::layout::VisualGraph;
+
+/// \returns a random graph with \p n nodes and \p m edges. Each edge goes from
+/// a node to one of the next \p span nodes, so the graph is mostly acyclic
+/// and the edges cross a few ranks.
+fn random_graph(n: usize, m: usize, span: usize) -> VisualGraph {
+    let mut rng = Rng::new(7);
+    let mut vg = VisualGraph::new(Orientation::TopToBottom);
+    let mut handles = Vec::new();
+    for i in 0..n {
+        let elem = Element::create(
+            ShapeKind::new_box(&format!("n{}", i)),
+            StyleAttr::simple(),
+            Orientation::TopToBottom,
+            Point::new(60., 30.),
+        );
+        handles.push(vg.add_node(elem));
+    }
+    for _ in 0..m {
+        let from = rng.below(n);
+        let to = from + 1 + rng.below(span);
+        if to < n {
+            vg.add_edge(Arrow::simple(""), handles[from], handles[to]);
+        }
+    }
+    vg
+}
+
+fn bench_layout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout");
+    group.sample_size(10);
+    for (n, m) in [(100, 300), (1000, 5000), (10000, 50000)] {
+        let id = BenchmarkId::from_parameter(format!("{}x{}", n, m));
+        group.bench_function(id, |b| {
+            b.iter_batched(
+                || random_graph(n, m, 8),
+                |mut vg| {
+                    let mut svg = SVGWriter::new();
+                    vg.do_it(false, false, false, &mut svg);
+                    svg
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_layout);
+criterion_main!(benches);
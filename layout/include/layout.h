@@ -0,0 +1,34 @@
+/* The C interface of the layout-rs crate, that is built with the `ffi`
+ * feature. The strings that the functions return are released with
+ * layout_free_string(). */
+
+#ifndef LAYOUT_H
+#define LAYOUT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A parsed DOT graph. */
+typedef struct LayoutGraph LayoutGraph;
+
+/* Parse the DOT text 'text'. Returns the graph, that is released with
+ * layout_free(), or NULL on failure. If 'error' is not NULL then it's set to
+ * the error message on failure, and to NULL on success. */
+LayoutGraph *layout_parse_dot(const char *text, char **error);
+
+/* Lay out the graph and draw it. Returns the SVG document, or NULL on
+ * failure. */
+char *layout_render_svg(const LayoutGraph *graph);
+
+/* Release a graph that layout_parse_dot() returned. */
+void layout_free(LayoutGraph *graph);
+
+/* Release a string that the library returned. */
+void layout_free_string(char *str);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LAYOUT_H */
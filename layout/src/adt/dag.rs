@@ -4,7 +4,11 @@
 //! of this data structure may change the leveling of nodes, and the only
 //! guarantee is that the nodes are assigned to some level.
 
-use std::cmp;
+use crate::adt::simplex::{rank_with_network_simplex, SimplexEdge};
+use crate::adt::HashMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp;
 
 /// The Ranked-DAG data structure.
 #[derive(Debug)]
@@ -18,10 +22,46 @@ pub struct DAG {
     /// levels info
     levels: Vec<usize>,
 
+    /// The attributes of the edges that don't use the default attributes.
+    edge_attrs: HashMap<(NodeHandle, NodeHandle), EdgeAttr>,
+
     /// Perform validation checks.
     validate: bool,
 }
 
+/// Constrains the rank of a group of nodes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RankConstraint {
+    /// Place the nodes on the same rank.
+    Same,
+    /// Place the nodes on the first rank.
+    Min,
+    /// Place the nodes on the first rank, and no other node on it.
+    Source,
+    /// Place the nodes on the last rank.
+    Max,
+    /// Place the nodes on the last rank, and no other node on it.
+    Sink,
+}
+
+/// The ranking attributes of an edge.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct EdgeAttr {
+    /// The minimal number of ranks between the ends of the edge.
+    pub min_len: usize,
+    /// How important it is to keep the edge short and straight.
+    pub weight: f64,
+}
+
+impl Default for EdgeAttr {
+    fn default() -> Self {
+        Self {
+            min_len: 1,
+            weight: 1.,
+        }
+    }
+}
+
 /// Used by users to keep track of nodes that are saved in the DAG.
 #[derive(Copy, Clone, Default, PartialEq, PartialOrd, Eq, Ord, Hash, Debug)]
 pub struct NodeHandle {
@@ -88,6 +128,7 @@ impl DAG {
             nodes: Vec::new(),
             ranks: Vec::new(),
             levels: Vec::new(),
+            edge_attrs: HashMap::new(),
             validate: true,
         }
     }
@@ -100,6 +141,7 @@ impl DAG {
         self.nodes.clear();
         self.ranks.clear();
         self.levels.clear();
+        self.edge_attrs.clear();
     }
 
     pub fn iter(&self) -> NodeIterator {
@@ -114,6 +156,28 @@ impl DAG {
         self.nodes[to.idx].predecessors.push(from);
     }
 
+    /// Set the attributes of the edges from \p from to \p to to \p attr.
+    pub fn set_edge_attr(
+        &mut self,
+        from: NodeHandle,
+        to: NodeHandle,
+        attr: EdgeAttr,
+    ) {
+        if attr == EdgeAttr::default() {
+            self.edge_attrs.remove(&(from, to));
+        } else {
+            self.edge_attrs.insert((from, to), attr);
+        }
+    }
+
+    /// \returns the attributes of the edges from \p from to \p to.
+    pub fn edge_attr(&self, from: NodeHandle, to: NodeHandle) -> EdgeAttr {
+        self.edge_attrs
+            .get(&(from, to))
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Remove an edge from \p from to \p to.
     /// \returns True if an edge was removed.
     pub fn remove_edge(&mut self, from: NodeHandle, to: NodeHandle) -> bool {
@@ -190,14 +254,7 @@ impl DAG {
             }
 
             // Check that the graph is a DAG.
-            for (i, node) in self.nodes.iter().enumerate() {
-                let from = NodeHandle::from(i);
-                for dest in node.successors.iter() {
-                    let reachable =
-                        self.is_reachable(*dest, from) && from != *dest;
-                    assert!(!reachable, "We found a cycle!");
-                }
-            }
+            assert!(!self.has_cycle(), "We found a cycle!");
 
             // Make sure that all of the nodes are in ranks.
             assert_eq!(self.count_nodes_in_ranks(), self.len());
@@ -212,47 +269,57 @@ impl DAG {
         self.nodes.is_empty()
     }
 
-    /// \returns True if the node \to is reachable from the node \p from.
-    /// This internal method is used for the verification of the graph.
-    fn is_reachable_inner(
-        &self,
-        from: NodeHandle,
-        to: NodeHandle,
-        visited: &mut Vec<bool>,
-    ) -> bool {
+    /// \returns True if there is a path from \p 'from' to \p 'to'.
+    pub fn is_reachable(&self, from: NodeHandle, to: NodeHandle) -> bool {
         if from == to {
             return true;
         }
 
-        // Don't step into a cycle.
-        if visited[from.idx] {
-            return false;
-        }
-
-        // Push to the dfs stack.
+        // Each node is visited once, because the nodes that were visited
+        // before don't reach the target.
+        let mut visited = vec![false; self.nodes.len()];
+        let mut worklist = vec![from];
         visited[from.idx] = true;
-
-        let from_node = &self.nodes[from.idx];
-        for edge in &from_node.successors {
-            if self.is_reachable_inner(*edge, to, visited) {
-                return true;
+        while let Some(current) = worklist.pop() {
+            for next in self.nodes[current.idx].successors.iter() {
+                if *next == to {
+                    return true;
+                }
+                if !visited[next.idx] {
+                    visited[next.idx] = true;
+                    worklist.push(*next);
+                }
             }
         }
-
-        // Pop from the dfs stack.
-        visited[from.idx] = false;
         false
     }
 
-    /// \returns True if there is a path from \p 'from' to \p 'to'.
-    pub fn is_reachable(&self, from: NodeHandle, to: NodeHandle) -> bool {
-        if from == to {
-            return true;
+    /// \returns True if the graph has a cycle, other than self edges.
+    fn has_cycle(&self) -> bool {
+        // Remove the nodes without predecessors until no node is left, or
+        // until the remaining nodes are on cycles.
+        let mut num_preds: Vec<usize> = vec![0; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            num_preds[i] =
+                node.predecessors.iter().filter(|x| x.idx != i).count();
         }
-
-        let mut visited = Vec::new();
-        visited.resize(self.nodes.len(), false);
-        self.is_reachable_inner(from, to, &mut visited)
+        let mut worklist: Vec<usize> = (0..self.nodes.len())
+            .filter(|x| num_preds[*x] == 0)
+            .collect();
+        let mut removed = 0;
+        while let Some(current) = worklist.pop() {
+            removed += 1;
+            for next in self.nodes[current].successors.iter() {
+                if next.idx == current {
+                    continue;
+                }
+                num_preds[next.idx] -= 1;
+                if num_preds[next.idx] == 0 {
+                    worklist.push(next.idx);
+                }
+            }
+        }
+        removed != self.nodes.len()
     }
 
     /// Return the topological sort order of the nodes in the dag.
@@ -350,6 +417,18 @@ impl DAG {
         self.ranks[level][last_idx] == elem
     }
 
+    /// Replace the ranks of the graph with \p ranks, that must hold each of
+    /// the nodes once.
+    pub fn set_ranks(&mut self, ranks: RankType) {
+        self.ranks = ranks;
+        for (level, row) in self.ranks.iter().enumerate() {
+            for node in row.iter() {
+                self.levels[node.idx] = level;
+            }
+        }
+        self.verify();
+    }
+
     /// Place the element \p elem at the nth level \p level. If the level does
     /// not exist then create it. If \p prepend is set then the node is inserted
     /// at the beginning of the rank. The node must not be in the rank when this
@@ -383,6 +462,240 @@ impl DAG {
         }
     }
 
+    /// Places all of the nodes in ranks (levels), starting from the ranks of
+    /// a previous layout in \p hint. Nodes stay in their previous rank unless
+    /// the edges push them down, and nodes without a hint start at the top.
+    /// Ranks that are left empty, and that no edge crosses, are removed.
+    pub fn recompute_node_ranks_with_hint(&mut self, hint: &[Option<usize>]) {
+        assert!(!self.is_empty(), "Sorting an empty graph");
+        let order = self.topological_sort();
+        let mut levels: Vec<usize> = (0..self.len())
+            .map(|i| hint.get(i).cloned().flatten().unwrap_or(0))
+            .collect();
+        for src in order.iter() {
+            for dest in self.nodes[src.idx].successors.iter() {
+                if src.idx == dest.idx {
+                    continue;
+                }
+                let len = self.edge_attr(*src, *dest).min_len;
+                levels[dest.idx] =
+                    cmp::max(levels[dest.idx], levels[src.idx] + len);
+            }
+        }
+
+        // Find the ranks that hold a node, or that an edge crosses, and
+        // remove the others.
+        let num_levels = levels.iter().max().unwrap() + 1;
+        let mut used = vec![false; num_levels];
+        for (i, node) in self.nodes.iter().enumerate() {
+            used[levels[i]] = true;
+            for dest in node.successors.iter() {
+                for u in used.iter_mut().take(levels[dest.idx]).skip(levels[i])
+                {
+                    *u = true;
+                }
+            }
+        }
+        let mut new_level = Vec::with_capacity(num_levels);
+        let mut next = 0;
+        for u in used {
+            new_level.push(next);
+            if u {
+                next += 1;
+            }
+        }
+
+        self.ranks.clear();
+        for (i, level) in levels.iter().enumerate() {
+            let level = new_level[*level];
+            self.add_element_to_rank(NodeHandle::from(i), level, false);
+        }
+    }
+
+    /// Places all of the nodes in ranks (levels) with the network simplex
+    /// algorithm, that minimizes the total length of the edges, multiplied by
+    /// their weights. Each connected component starts at the first rank.
+    pub fn recompute_node_ranks_with_network_simplex(&mut self) {
+        assert!(!self.is_empty(), "Sorting an empty graph");
+        let mut edges = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            for dest in node.successors.iter() {
+                if dest.idx == i {
+                    continue;
+                }
+                let attr = self.edge_attr(NodeHandle::from(i), *dest);
+                edges.push(SimplexEdge {
+                    tail: i,
+                    head: dest.idx,
+                    min_len: attr.min_len,
+                    weight: attr.weight,
+                });
+            }
+        }
+        let max_iter = self.len() * 4 + 100;
+        let levels = rank_with_network_simplex(self.len(), &edges, max_iter);
+        self.ranks.clear();
+        for (i, level) in levels.iter().enumerate() {
+            self.add_element_to_rank(NodeHandle::from(i), *level, false);
+        }
+    }
+
+    /// Places all of the nodes in ranks (levels), while keeping the rank
+    /// \p constraints. Edges between nodes that must share a rank are ignored.
+    /// Constraints that contradict the edges of the graph, or the constraints
+    /// that come before them, are dropped.
+    /// \returns the indices of the constraints that were dropped.
+    pub fn recompute_node_ranks_with_constraints(
+        &mut self,
+        constraints: &[(RankConstraint, Vec<NodeHandle>)],
+    ) -> Vec<usize> {
+        assert!(!self.is_empty(), "Sorting an empty graph");
+        let order = self.topological_sort();
+        let mut levels = self.compute_levels(&order);
+        let mut accepted = Vec::new();
+        let mut dropped = Vec::new();
+
+        // Add the constraints one at a time, to find the ones that break.
+        for (i, constraint) in constraints.iter().enumerate() {
+            accepted.push(constraint.clone());
+            match self.compute_constrained_levels(&accepted) {
+                Option::Some(lvl) => levels = lvl,
+                Option::None => {
+                    accepted.pop();
+                    dropped.push(i);
+                }
+            }
+        }
+
+        self.ranks.clear();
+        for (i, level) in levels.iter().enumerate() {
+            self.add_element_to_rank(NodeHandle::from(i), *level, false);
+        }
+        dropped
+    }
+
+    /// Computes the level of each node in the graph, such that the nodes in
+    /// each of the groups in \p constraints share a level.
+    /// \returns None if the constraints can't be satisfied.
+    fn compute_constrained_levels(
+        &self,
+        constraints: &[(RankConstraint, Vec<NodeHandle>)],
+    ) -> Option<Vec<usize>> {
+        fn find(group: &mut [usize], mut x: usize) -> usize {
+            while group[x] != x {
+                group[x] = group[group[x]];
+                x = group[x];
+            }
+            x
+        }
+
+        // Merge the nodes that share a rank into groups. All of the nodes
+        // that go on the first rank share a group, and so do the nodes that
+        // go on the last rank.
+        let n = self.len();
+        let mut group: Vec<usize> = (0..n).collect();
+        let mut first: Option<usize> = None;
+        let mut last: Option<usize> = None;
+        let mut is_source = false;
+        let mut is_sink = false;
+        for (kind, nodes) in constraints {
+            let mut lst: Vec<usize> = nodes.iter().map(|x| x.idx).collect();
+            match kind {
+                RankConstraint::Same => {}
+                RankConstraint::Min | RankConstraint::Source => {
+                    lst.extend(first);
+                    first = lst.first().cloned();
+                    is_source |= *kind == RankConstraint::Source;
+                }
+                RankConstraint::Max | RankConstraint::Sink => {
+                    lst.extend(last);
+                    last = lst.first().cloned();
+                    is_sink |= *kind == RankConstraint::Sink;
+                }
+            }
+            for pair in lst.windows(2) {
+                let a = find(&mut group, pair[0]);
+                let b = find(&mut group, pair[1]);
+                group[b] = a;
+            }
+        }
+        let first = first.map(|x| find(&mut group, x));
+        let last = last.map(|x| find(&mut group, x));
+
+        // Construct the graph of the groups. Each edge holds the minimal
+        // number of ranks between the groups.
+        let mut succs: Vec<Vec<(usize, usize)>> = vec![Vec::new(); n];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let from = find(&mut group, i);
+            for dest in node.successors.iter() {
+                let to = find(&mut group, dest.idx);
+                if from != to {
+                    let len =
+                        self.edge_attr(NodeHandle::from(i), *dest).min_len;
+                    succs[from].push((to, len));
+                }
+            }
+        }
+        let groups: Vec<usize> = (0..n).filter(|x| group[*x] == *x).collect();
+
+        // The source goes above all of the other groups, and the sink goes
+        // below all of them.
+        if let (Option::Some(src), true) = (first, is_source) {
+            for g in groups.iter().filter(|g| **g != src) {
+                succs[src].push((*g, 1));
+            }
+        }
+        if let (Option::Some(sink), true) = (last, is_sink) {
+            for g in groups.iter().filter(|g| **g != sink) {
+                succs[*g].push((sink, 1));
+            }
+        }
+
+        // Nothing can go above the first rank or below the last rank.
+        let mut num_preds = vec![0; n];
+        for g in groups.iter() {
+            for (dest, _) in succs[*g].iter() {
+                num_preds[*dest] += 1;
+            }
+        }
+        if first.is_some_and(|x| num_preds[x] > 0) {
+            return None;
+        }
+        if last.is_some_and(|x| !succs[x].is_empty()) {
+            return None;
+        }
+
+        // Assign the levels in topological order. If we can't visit all of
+        // the groups then the constraints created a cycle.
+        let mut group_levels = vec![0; n];
+        let mut worklist: Vec<usize> = groups
+            .iter()
+            .filter(|g| num_preds[**g] == 0)
+            .cloned()
+            .collect();
+        let mut visited = 0;
+        while let Some(current) = worklist.pop() {
+            visited += 1;
+            for (dest, len) in succs[current].iter() {
+                group_levels[*dest] =
+                    cmp::max(group_levels[*dest], group_levels[current] + len);
+                num_preds[*dest] -= 1;
+                if num_preds[*dest] == 0 {
+                    worklist.push(*dest);
+                }
+            }
+        }
+        if visited != groups.len() {
+            return None;
+        }
+
+        if let Option::Some(last) = last {
+            group_levels[last] = *group_levels.iter().max().unwrap();
+        }
+
+        Some((0..n).map(|x| group_levels[find(&mut group, x)]).collect())
+    }
+
     /// \returns the number of nodes that are in ranks.
     /// This is used for verification of the dag.
     fn count_nodes_in_ranks(&self) -> usize {
@@ -454,8 +767,9 @@ impl DAG {
                 if src.idx == dest.idx {
                     continue;
                 }
+                let len = self.edge_attr(*src, *dest).min_len;
                 levels[dest.idx] =
-                    cmp::max(levels[dest.idx], levels[src.idx] + 1);
+                    cmp::max(levels[dest.idx], levels[src.idx] + len);
             }
         }
 
@@ -470,6 +784,104 @@ impl DAG {
     }
 }
 
+/// Keeps a topological order of the nodes of a DAG while edges are added to
+/// it, to tell which new edges would close a cycle without searching the whole
+/// graph. This is the dynamic topological sort of Pearce and Kelly.
+#[derive(Debug)]
+pub struct TopologicalOrder {
+    /// The position of each node in the order.
+    order: Vec<usize>,
+    /// Marks the nodes that the current search visited.
+    visited: Vec<bool>,
+}
+
+impl TopologicalOrder {
+    /// \returns the topological order of the nodes of \p dag.
+    pub fn new(dag: &DAG) -> Self {
+        let mut order = vec![0; dag.len()];
+        for (i, node) in dag.topological_sort().iter().enumerate() {
+            order[node.idx] = i;
+        }
+        let visited = vec![false; dag.len()];
+        Self { order, visited }
+    }
+
+    /// Prepare the order for the edge from \p from to \p to, which is about
+    /// to be added to \p dag.
+    /// \returns False if the edge would close a cycle, because \p from is
+    /// reachable from \p to. The order is not changed in this case.
+    pub fn add_edge(
+        &mut self,
+        dag: &DAG,
+        from: NodeHandle,
+        to: NodeHandle,
+    ) -> bool {
+        let lower = self.order[to.idx];
+        let upper = self.order[from.idx];
+        if from == to || lower > upper {
+            return true;
+        }
+
+        // Find the nodes that the edge makes out of order: the nodes that are
+        // reachable from 'to', and the nodes that reach 'from', between the
+        // positions of the two nodes.
+        let mut forward = Vec::new();
+        let mut cycle = false;
+        let mut worklist = vec![to];
+        self.visited[to.idx] = true;
+        while let Some(node) = worklist.pop() {
+            forward.push(node);
+            for next in dag.successors(node) {
+                if *next == from {
+                    cycle = true;
+                }
+                let pos = self.order[next.idx];
+                if !self.visited[next.idx] && pos < upper {
+                    self.visited[next.idx] = true;
+                    worklist.push(*next);
+                }
+            }
+        }
+        if cycle {
+            for node in forward {
+                self.visited[node.idx] = false;
+            }
+            return false;
+        }
+
+        let mut backward = Vec::new();
+        let mut worklist = vec![from];
+        self.visited[from.idx] = true;
+        while let Some(node) = worklist.pop() {
+            backward.push(node);
+            for prev in dag.predecessors(node) {
+                let pos = self.order[prev.idx];
+                if !self.visited[prev.idx] && pos > lower {
+                    self.visited[prev.idx] = true;
+                    worklist.push(*prev);
+                }
+            }
+        }
+
+        // Move the nodes that reach 'from' before the nodes that are reachable
+        // from 'to', in the positions that the two groups held.
+        forward.sort_by_key(|x| self.order[x.idx]);
+        backward.sort_by_key(|x| self.order[x.idx]);
+        let mut positions: Vec<usize> = forward
+            .iter()
+            .chain(backward.iter())
+            .map(|x| self.order[x.idx])
+            .collect();
+        positions.sort();
+        for (node, pos) in backward.iter().chain(forward.iter()).zip(positions)
+        {
+            self.order[node.idx] = pos;
+            self.visited[node.idx] = false;
+        }
+        true
+    }
+}
+
 impl Default for DAG {
     fn default() -> Self {
         Self::new()
@@ -532,3 +944,117 @@ fn test_rank_api() {
     // The edge should no longer be there!
     assert!(!r2);
 }
+
+#[test]
+fn test_rank_constraints() {
+    let mut g = DAG::new();
+    g.new_nodes(5);
+    let h: Vec<NodeHandle> = (0..5).map(NodeHandle::from).collect();
+
+    // 0 -> 1 -> 2, 0 -> 3, and 4 is not connected.
+    g.add_edge(h[0], h[1]);
+    g.add_edge(h[1], h[2]);
+    g.add_edge(h[0], h[3]);
+
+    let constraints = vec![
+        (RankConstraint::Same, vec![h[2], h[3]]),
+        (RankConstraint::Max, vec![h[4]]),
+    ];
+    let dropped = g.recompute_node_ranks_with_constraints(&constraints);
+    assert!(dropped.is_empty());
+    assert_eq!(g.level(h[2]), 2);
+    assert_eq!(g.level(h[3]), 2);
+    assert_eq!(g.level(h[4]), 2);
+
+    // The source is placed above all of the other nodes.
+    let constraints = vec![(RankConstraint::Source, vec![h[4]])];
+    g.recompute_node_ranks_with_constraints(&constraints);
+    assert_eq!(g.level(h[4]), 0);
+    assert_eq!(g.level(h[0]), 1);
+    assert_eq!(g.row(0).len(), 1);
+
+    // Constraints that contradict the edges are dropped.
+    let constraints = vec![
+        (RankConstraint::Same, vec![h[0], h[2]]),
+        (RankConstraint::Min, vec![h[1]]),
+        (RankConstraint::Sink, vec![h[3]]),
+    ];
+    let dropped = g.recompute_node_ranks_with_constraints(&constraints);
+    assert_eq!(dropped, vec![0, 1]);
+    assert_eq!(g.level(h[3]), 3);
+    g.verify();
+}
+
+#[test]
+fn test_min_len() {
+    let mut g = DAG::new();
+    g.new_nodes(4);
+    let h: Vec<NodeHandle> = (0..4).map(NodeHandle::from).collect();
+
+    // 0 -> 1 is three ranks long, and 2 -> 3 can share a rank.
+    g.add_edge(h[0], h[1]);
+    g.add_edge(h[2], h[3]);
+    let long = EdgeAttr {
+        min_len: 3,
+        weight: 1.,
+    };
+    g.set_edge_attr(h[0], h[1], long);
+    g.set_edge_attr(
+        h[2],
+        h[3],
+        EdgeAttr {
+            min_len: 0,
+            weight: 1.,
+        },
+    );
+    g.recompute_node_ranks();
+    assert_eq!(g.level(h[1]), 3);
+    assert_eq!(g.level(h[3]), g.level(h[2]));
+
+    // The constraints keep the lengths of the edges.
+    let constraints = vec![(RankConstraint::Same, vec![h[1], h[2]])];
+    g.recompute_node_ranks_with_constraints(&constraints);
+    assert_eq!(g.level(h[2]), 3);
+    assert_eq!(g.level(h[3]), 3);
+    assert_eq!(g.edge_attr(h[1], h[0]), EdgeAttr::default());
+}
+
+#[test]
+fn test_rank_hints() {
+    let mut g = DAG::new();
+    g.new_nodes(4);
+    let h: Vec<NodeHandle> = (0..4).map(NodeHandle::from).collect();
+
+    // 0 -> 1, and 2 -> 3. Node 1 was on rank 2, and node 3 is new.
+    g.add_edge(h[0], h[1]);
+    g.add_edge(h[2], h[3]);
+    g.recompute_node_ranks_with_hint(&[Some(0), Some(2), Some(4), None]);
+    assert_eq!(g.level(h[0]), 0);
+    assert_eq!(g.level(h[1]), 2);
+    // The empty rank 3 is removed, and the edges push node 3 down.
+    assert_eq!(g.level(h[2]), 3);
+    assert_eq!(g.level(h[3]), 4);
+    g.verify();
+}
+
+#[test]
+fn test_topological_order() {
+    let mut g = DAG::new();
+    g.new_nodes(4);
+    let h: Vec<NodeHandle> = (0..4).map(NodeHandle::from).collect();
+    let mut order = TopologicalOrder::new(&g);
+
+    // Build the chain 3 -> 2 -> 1 -> 0, against the initial order.
+    for i in (1..4).rev() {
+        assert!(order.add_edge(&g, h[i], h[i - 1]));
+        g.add_edge(h[i], h[i - 1]);
+    }
+
+    // The edges that close a cycle are found.
+    assert!(!order.add_edge(&g, h[0], h[3]));
+    assert!(!order.add_edge(&g, h[1], h[2]));
+    assert!(order.add_edge(&g, h[3], h[0]));
+    for i in 1..4 {
+        assert_eq!(order.order[i] + 1, order.order[i - 1]);
+    }
+}
@@ -3,4 +3,12 @@
 //!
 
 pub mod dag;
+#[cfg(feature = "std")]
 pub mod map;
+pub mod simplex;
+
+// The maps of the layout are hash maps with std, and ordered maps without it.
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{HashMap, HashSet};
@@ -0,0 +1,353 @@
+//! This module implements the network simplex algorithm that assigns ranks to
+//! the nodes of a DAG, as described in "A Technique for Drawing Directed
+//! Graphs" by Gansner et al. The ranks minimize the sum of the lengths of the
+//! edges, multiplied by their weights, while keeping every edge at least as
+//! long as its minimal length.
+
+use alloc::vec;
+/// An edge from \p tail to \p head that must span at least \p min_len ranks.
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimplexEdge {
+    pub tail: usize,
+    pub head: usize,
+    pub min_len: usize,
+    pub weight: f64,
+}
+
+/// The state of the network simplex algorithm: the ranks of the nodes, the
+/// edges that are in the spanning tree, and the numbering of the tree that
+/// tells which nodes are below each tree edge.
+struct Simplex<'a> {
+    edges: &'a [SimplexEdge],
+    rank: Vec<i64>,
+    // The indices of the edges that touch each node.
+    adj: Vec<Vec<usize>>,
+    in_tree: Vec<bool>,
+    // The postorder number of each node, and the lowest number in its
+    // subtree, and the tree edge that connects each node to its parent.
+    lim: Vec<usize>,
+    low: Vec<usize>,
+    parent: Vec<Option<usize>>,
+    // The node that each component of the graph is rooted at.
+    roots: Vec<usize>,
+}
+
+/// \returns the rank of each of the \p n nodes of the DAG with the edges
+/// \p edges. Each connected component starts at rank zero. The algorithm stops
+/// after \p max_iter exchanges of edges, with a valid, but not optimal, result.
+pub fn rank_with_network_simplex(
+    n: usize,
+    edges: &[SimplexEdge],
+    max_iter: usize,
+) -> Vec<usize> {
+    let mut ns = Simplex::new(n, edges);
+    ns.init_rank();
+    ns.feasible_tree();
+    for _ in 0..max_iter {
+        ns.number_tree();
+        let cut = ns.cut_values();
+        let leave = (0..edges.len())
+            .filter(|e| ns.in_tree[*e] && cut[*e] < 0.)
+            .min_by(|a, b| cut[*a].total_cmp(&cut[*b]));
+        let leave = match leave {
+            Option::Some(e) => e,
+            None => break,
+        };
+        let enter = match ns.enter_edge(leave) {
+            Option::Some(f) => f,
+            None => break,
+        };
+        ns.in_tree[leave] = false;
+        ns.in_tree[enter] = true;
+        ns.number_tree();
+        ns.rank_from_tree();
+    }
+    ns.normalize()
+}
+
+impl<'a> Simplex<'a> {
+    fn new(n: usize, edges: &'a [SimplexEdge]) -> Self {
+        let mut adj = vec![Vec::new(); n];
+        for (i, e) in edges.iter().enumerate() {
+            adj[e.tail].push(i);
+            if e.head != e.tail {
+                adj[e.head].push(i);
+            }
+        }
+        Self {
+            edges,
+            rank: vec![0; n],
+            adj,
+            in_tree: vec![false; edges.len()],
+            lim: vec![0; n],
+            low: vec![0; n],
+            parent: vec![None; n],
+            roots: Vec::new(),
+        }
+    }
+
+    fn slack(&self, e: usize) -> i64 {
+        let e = &self.edges[e];
+        self.rank[e.head] - self.rank[e.tail] - e.min_len as i64
+    }
+
+    /// \returns the node at the other end of the edge \p e from \p node.
+    fn other(&self, e: usize, node: usize) -> usize {
+        let e = &self.edges[e];
+        if e.tail == node {
+            e.head
+        } else {
+            e.tail
+        }
+    }
+
+    /// Assign the initial ranks by the longest path from the sources.
+    fn init_rank(&mut self) {
+        let n = self.rank.len();
+        let mut num_preds = vec![0; n];
+        for e in self.edges.iter() {
+            num_preds[e.head] += 1;
+        }
+        let mut queue: Vec<usize> =
+            (0..n).filter(|x| num_preds[*x] == 0).collect();
+        while let Option::Some(node) = queue.pop() {
+            for e in self.adj[node].iter() {
+                let e = &self.edges[*e];
+                if e.tail != node {
+                    continue;
+                }
+                let rank = self.rank[node] + e.min_len as i64;
+                self.rank[e.head] = self.rank[e.head].max(rank);
+                num_preds[e.head] -= 1;
+                if num_preds[e.head] == 0 {
+                    queue.push(e.head);
+                }
+            }
+        }
+        assert!(num_preds.iter().all(|x| *x == 0), "The graph has a cycle");
+    }
+
+    /// Build a spanning tree of tight edges (edges of minimal length) for each
+    /// connected component, by moving the ranks of the parts of the tree.
+    fn feasible_tree(&mut self) {
+        let n = self.rank.len();
+        let mut in_tree_node = vec![false; n];
+        for root in 0..n {
+            if in_tree_node[root] {
+                continue;
+            }
+            self.roots.push(root);
+            in_tree_node[root] = true;
+            let mut tree = vec![root];
+            loop {
+                // Grow the tree along the tight edges.
+                let mut i = 0;
+                while i < tree.len() {
+                    let node = tree[i];
+                    i += 1;
+                    for e in self.adj[node].clone() {
+                        let other = self.other(e, node);
+                        if !in_tree_node[other] && self.slack(e) == 0 {
+                            in_tree_node[other] = true;
+                            self.in_tree[e] = true;
+                            tree.push(other);
+                        }
+                    }
+                }
+
+                // Find the edge with the least slack that leaves the tree.
+                let mut best: Option<(i64, usize)> = None;
+                for node in tree.iter() {
+                    for e in self.adj[*node].iter() {
+                        let other = self.other(*e, *node);
+                        let slack = self.slack(*e);
+                        if !in_tree_node[other]
+                            && best.is_none_or(|b| slack < b.0)
+                        {
+                            best = Some((slack, *e));
+                        }
+                    }
+                }
+                let (slack, e) = match best {
+                    Option::Some(best) => best,
+                    None => break,
+                };
+
+                // Move the tree to make the edge tight.
+                let delta = if in_tree_node[self.edges[e].tail] {
+                    slack
+                } else {
+                    -slack
+                };
+                for node in tree.iter() {
+                    self.rank[*node] += delta;
+                }
+            }
+        }
+    }
+
+    /// Number the nodes of the tree in postorder, and record the tree edge
+    /// that connects each node to its parent.
+    fn number_tree(&mut self) {
+        let n = self.rank.len();
+        let mut visited = vec![false; n];
+        let mut next = 0;
+        for root in self.roots.clone() {
+            // A stack of nodes and the index of their next edge.
+            let mut stack = vec![(root, 0)];
+            visited[root] = true;
+            self.parent[root] = None;
+            self.low[root] = next;
+            while let Option::Some((node, idx)) = stack.pop() {
+                if idx < self.adj[node].len() {
+                    stack.push((node, idx + 1));
+                    let e = self.adj[node][idx];
+                    let other = self.other(e, node);
+                    if self.in_tree[e] && !visited[other] {
+                        visited[other] = true;
+                        self.parent[other] = Some(e);
+                        self.low[other] = next;
+                        stack.push((other, 0));
+                    }
+                    continue;
+                }
+                self.lim[node] = next;
+                next += 1;
+            }
+        }
+    }
+
+    /// \returns true if \p node is in the subtree under \p root.
+    fn in_subtree(&self, node: usize, root: usize) -> bool {
+        self.low[root] <= self.lim[node] && self.lim[node] <= self.lim[root]
+    }
+
+    /// \returns the cut value of each tree edge: the weight of the edges that
+    /// go from the tail component of the tree to the head component, minus
+    /// the weight of the edges that go the other way.
+    fn cut_values(&self) -> Vec<f64> {
+        let n = self.rank.len();
+        // The weight that leaves each subtree, minus the weight that enters
+        // it, is the sum of these values over the nodes of the subtree.
+        let mut value = vec![0.; n];
+        for e in self.edges.iter() {
+            value[e.tail] += e.weight;
+            value[e.head] -= e.weight;
+        }
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by_key(|x| self.lim[*x]);
+        let mut cut = vec![0.; self.edges.len()];
+        for node in order {
+            if let Option::Some(e) = self.parent[node] {
+                let parent = self.other(e, node);
+                value[parent] += value[node];
+                cut[e] = if self.edges[e].tail == node {
+                    value[node]
+                } else {
+                    -value[node]
+                };
+            }
+        }
+        cut
+    }
+
+    /// \returns the non-tree edge with the least slack that goes from the
+    /// head component of the tree edge \p e to its tail component.
+    fn enter_edge(&self, e: usize) -> Option<usize> {
+        let edge = &self.edges[e];
+        // The node below the edge in the tree.
+        let child = if self.parent[edge.tail] == Some(e) {
+            edge.tail
+        } else {
+            edge.head
+        };
+        let tail_is_below = child == edge.tail;
+        let in_tail =
+            |node: usize| self.in_subtree(node, child) == tail_is_below;
+        let root = self.tree_root(child);
+        let in_component = |node: usize| self.in_subtree(node, root);
+
+        (0..self.edges.len())
+            .filter(|f| {
+                let in_tree = self.in_tree[*f];
+                let f = &self.edges[*f];
+                !in_tree
+                    && in_component(f.tail)
+                    && !in_tail(f.tail)
+                    && in_tail(f.head)
+            })
+            .min_by_key(|f| self.slack(*f))
+    }
+
+    /// \returns the root of the tree that \p node is in.
+    fn tree_root(&self, mut node: usize) -> usize {
+        while let Option::Some(e) = self.parent[node] {
+            node = self.other(e, node);
+        }
+        node
+    }
+
+    /// Assign the ranks of the nodes so that all of the tree edges are tight.
+    fn rank_from_tree(&mut self) {
+        let n = self.rank.len();
+        let mut order: Vec<usize> = (0..n).collect();
+        // Parents come before their children, that have a lower number.
+        order.sort_by_key(|x| core::cmp::Reverse(self.lim[*x]));
+        for node in order {
+            if let Option::Some(e) = self.parent[node] {
+                let edge = &self.edges[e];
+                let len = edge.min_len as i64;
+                self.rank[node] = if edge.tail == node {
+                    self.rank[edge.head] - len
+                } else {
+                    self.rank[edge.tail] + len
+                };
+            }
+        }
+    }
+
+    /// \returns the ranks, moved so that each component starts at zero.
+    fn normalize(&self) -> Vec<usize> {
+        let n = self.rank.len();
+        let mut min_rank = vec![i64::MAX; n];
+        let roots: Vec<usize> = (0..n).map(|x| self.tree_root(x)).collect();
+        for (node, root) in roots.iter().enumerate() {
+            min_rank[*root] = min_rank[*root].min(self.rank[node]);
+        }
+        (0..n)
+            .map(|x| (self.rank[x] - min_rank[roots[x]]) as usize)
+            .collect()
+    }
+}
+
+#[test]
+fn test_network_simplex() {
+    let edge = |tail, head| SimplexEdge {
+        tail,
+        head,
+        min_len: 1,
+        weight: 1.,
+    };
+
+    // The longest path places the source '3' on the first rank, and the edge
+    // 3->2 spans two ranks. The optimal ranking moves '3' down.
+    let edges = [edge(0, 1), edge(1, 2), edge(3, 2)];
+    let ranks = rank_with_network_simplex(4, &edges, 100);
+    assert_eq!(ranks, vec![0, 1, 2, 1]);
+
+    // Heavy edges are kept short, and minimal lengths are kept.
+    let mut edges = vec![edge(0, 1), edge(0, 2), edge(1, 3), edge(2, 3)];
+    edges[1].min_len = 3;
+    edges[2].weight = 5.;
+    let ranks = rank_with_network_simplex(4, &edges, 100);
+    assert_eq!(ranks[2], 3);
+    assert_eq!(ranks[3], 4);
+    assert_eq!(ranks[1], 3);
+
+    // Separate components start at rank zero, and lone nodes are allowed.
+    let edges = [edge(0, 1), edge(2, 3), edge(3, 4)];
+    let ranks = rank_with_network_simplex(6, &edges, 100);
+    assert_eq!(ranks, vec![0, 1, 0, 1, 2, 0]);
+}
@@ -0,0 +1,494 @@
+//! Animations of the layout, that show how the placers move the nodes while
+//! they run, as animated GIF or APNG images.
+//!
+//! The graph records the frames of its layout when
+//! VisualGraph::set_record_frames is set. Each frame is drawn with the PNG
+//! backend, and the frames are centered on a canvas that holds all of them.
+//! The last frame is the final drawing, and it stays on the screen longer.
+//!
+//! ```
+//! # use layout::backends::animation::AnimationWriter;
+//! # use layout::gv::{DotParser, GraphBuilder};
+//! let mut graph = DotParser::new("graph { layout=fdp; a -- b -- c; }");
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(&graph.process().unwrap());
+//! let mut vg = gb.get();
+//! vg.set_record_frames(true);
+//! vg.layout(false, false);
+//!
+//! let mut anim = AnimationWriter::new();
+//! anim.add_layout(&vg, 100);
+//! assert!(anim.num_frames() > 2);
+//! let gif: Vec<u8> = anim.finalize_gif();
+//! assert!(gif.starts_with(b"GIF89a"));
+//! ```
+
+use super::png::{write_chunk, PNGWriter};
+use super::scaled::{ScaledBackend, Transform};
+use crate::adt::dag::NodeHandle;
+use crate::core::format::RenderBackend;
+use crate::core::geometry::Point;
+use crate::topo::layout::{LayoutFrame, VisualGraph};
+use std::collections::HashMap;
+
+/// The space around the drawing in the frames.
+const FRAME_MARGIN: f64 = 10.;
+
+/// The time that the final drawing stays on the screen, in milliseconds.
+const FINAL_DELAY_MS: u16 = 2000;
+
+/// The largest number of bits of the LZW codes of GIF images.
+const MAX_CODE_BITS: u32 = 12;
+
+/// Selects the file format of the animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    /// \returns the format of the file \p path: GIF for names that end with
+    /// ".gif", and APNG for the others.
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".gif") {
+            AnimationFormat::Gif
+        } else {
+            AnimationFormat::Apng
+        }
+    }
+}
+
+/// A frame of the animation, with its pixels and the time that it's shown.
+#[derive(Debug, Clone)]
+struct Frame {
+    width: usize,
+    height: usize,
+    // RGBA pixels, row by row.
+    pixels: Vec<[u8; 4]>,
+    delay_ms: u16,
+}
+
+impl Frame {
+    /// \returns the pixel at \p x, \p y, or white for the pixels outside of
+    /// the frame, that is smaller than the animation.
+    fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        if x >= self.width || y >= self.height {
+            return [255, 255, 255, 255];
+        }
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Collects the frames of an animation, and encodes them as GIF or APNG.
+#[derive(Debug, Default)]
+pub struct AnimationWriter {
+    frames: Vec<Frame>,
+}
+
+impl AnimationWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// \returns the number of frames of the animation.
+    pub fn num_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Add the image that \p png drew as the next frame, that is shown for
+    /// \p delay_ms milliseconds.
+    pub fn add_frame(&mut self, png: &PNGWriter, delay_ms: u16) {
+        let (width, height) = png.size();
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (r, g, b, a) = png.pixel(x, y).unwrap();
+                pixels.push([r, g, b, a]);
+            }
+        }
+        self.frames.push(Frame {
+            width,
+            height,
+            pixels,
+            delay_ms,
+        });
+    }
+
+    /// Add the frames that the layout of \p vg recorded, that are shown for
+    /// \p delay_ms milliseconds each, and the final drawing of \p vg.
+    pub fn add_layout(&mut self, vg: &VisualGraph, delay_ms: u16) {
+        // The frames end with the final layout, that is drawn with the edges
+        // and the labels.
+        let frames = vg.frames();
+        let steps = &frames[..frames.len().saturating_sub(1)];
+        let mut boxes: Vec<(Point, Point)> =
+            steps.iter().map(|x| get_frame_bbox(vg, x)).collect();
+        boxes.push(vg.layout_result().bbox);
+
+        let size = boxes.iter().fold(Point::zero(), |acc, (a, b)| {
+            let size = b.sub(*a);
+            Point::new(acc.x.max(size.x), acc.y.max(size.y))
+        });
+        let canvas = size.add(Point::splat(2. * FRAME_MARGIN));
+        let middle = canvas.scale(0.5);
+        for (i, (a, b)) in boxes.iter().enumerate() {
+            let center = a.add(*b).scale(0.5);
+            let transform = Transform {
+                translate: middle.sub(center),
+                ..Transform::default()
+            };
+            let mut png = PNGWriter::new();
+            if let Option::Some(color) = vg.background() {
+                png.set_background(color);
+            }
+            png.grow_canvas(canvas);
+            let mut rb = ScaledBackend::with_transform(&mut png, transform);
+            let delay = match steps.get(i) {
+                Option::Some(frame) => {
+                    vg.draw_frame(frame, &mut rb);
+                    delay_ms
+                }
+                None => {
+                    vg.draw(false, &mut rb);
+                    FINAL_DELAY_MS
+                }
+            };
+            self.add_frame(&png, delay);
+        }
+    }
+
+    /// \returns the size of the animation, that holds all of the frames.
+    fn get_size(&self) -> (usize, usize) {
+        let width = self.frames.iter().map(|x| x.width).max().unwrap_or(0);
+        let height = self.frames.iter().map(|x| x.height).max().unwrap_or(0);
+        (width.max(1), height.max(1))
+    }
+
+    /// Encode the animation in the format \p format. \returns the content of
+    /// the file.
+    pub fn finalize(&self, format: AnimationFormat) -> Vec<u8> {
+        match format {
+            AnimationFormat::Gif => self.finalize_gif(),
+            AnimationFormat::Apng => self.finalize_apng(),
+        }
+    }
+
+    /// Encode the animation as APNG. Viewers that don't support animations
+    /// show the first frame. \returns the content of the PNG file.
+    pub fn finalize_apng(&self) -> Vec<u8> {
+        let (width, height) = self.get_size();
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        // The number of frames, and repeat forever.
+        let mut actl = (self.frames.len() as u32).to_be_bytes().to_vec();
+        actl.extend_from_slice(&0_u32.to_be_bytes());
+        write_chunk(&mut png, b"acTL", &actl);
+
+        // The frame controls and the frame data share the sequence numbers.
+        let mut sequence = 0_u32;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let mut fctl = sequence.to_be_bytes().to_vec();
+            fctl.extend_from_slice(&(width as u32).to_be_bytes());
+            fctl.extend_from_slice(&(height as u32).to_be_bytes());
+            fctl.extend_from_slice(&[0; 8]);
+            fctl.extend_from_slice(&frame.delay_ms.to_be_bytes());
+            fctl.extend_from_slice(&1000_u16.to_be_bytes());
+            // Don't dispose the frame, and replace the pixels.
+            fctl.extend_from_slice(&[0, 0]);
+            write_chunk(&mut png, b"fcTL", &fctl);
+            sequence += 1;
+
+            // Each row starts with the filter type (0 = none).
+            let mut raw = Vec::with_capacity(height * (width * 4 + 1));
+            for y in 0..height {
+                raw.push(0);
+                for x in 0..width {
+                    raw.extend_from_slice(&frame.pixel(x, y));
+                }
+            }
+            let data = miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6);
+            if i == 0 {
+                write_chunk(&mut png, b"IDAT", &data);
+            } else {
+                let mut fdat = sequence.to_be_bytes().to_vec();
+                fdat.extend_from_slice(&data);
+                write_chunk(&mut png, b"fdAT", &fdat);
+                sequence += 1;
+            }
+        }
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Encode the animation as GIF, with a fixed palette of 256 colors.
+    /// \returns the content of the GIF file.
+    pub fn finalize_gif(&self) -> Vec<u8> {
+        let (width, height) = self.get_size();
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&(width as u16).to_le_bytes());
+        gif.extend_from_slice(&(height as u16).to_le_bytes());
+        // A global color table of 256 colors, with 8 bits per channel.
+        gif.extend_from_slice(&[0xf7, 0, 0]);
+        for color in get_palette() {
+            gif.extend_from_slice(&color);
+        }
+        // Repeat forever.
+        gif.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        for frame in self.frames.iter() {
+            // The delay is in hundredths of a second.
+            let delay = frame.delay_ms.div_ceil(10);
+            gif.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+            gif.extend_from_slice(&delay.to_le_bytes());
+            gif.extend_from_slice(&[0, 0]);
+
+            gif.push(0x2c);
+            gif.extend_from_slice(&[0, 0, 0, 0]);
+            gif.extend_from_slice(&(width as u16).to_le_bytes());
+            gif.extend_from_slice(&(height as u16).to_le_bytes());
+            gif.push(0);
+
+            let mut indices = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    indices.push(get_color_index(frame.pixel(x, y)));
+                }
+            }
+            gif.push(8);
+            let data = lzw_encode(&indices, 8);
+            for block in data.chunks(255) {
+                gif.push(block.len() as u8);
+                gif.extend_from_slice(block);
+            }
+            gif.push(0);
+        }
+        gif.push(0x3b);
+        gif
+    }
+}
+
+/// \returns the bounding box of the nodes in the frame \p frame of the
+/// layout of \p vg.
+fn get_frame_bbox(vg: &VisualGraph, frame: &LayoutFrame) -> (Point, Point) {
+    let mut bbox: Option<(Point, Point)> = None;
+    for (i, center) in frame.centers.iter().enumerate() {
+        let node = NodeHandle::new(i);
+        if vg.is_connector(node) {
+            continue;
+        }
+        let half = vg.pos(node).size(false).scale(0.5);
+        let (a, b) = (center.sub(half), center.add(half));
+        bbox = Some(match bbox {
+            Option::Some((x, y)) => (
+                Point::new(x.x.min(a.x), x.y.min(a.y)),
+                Point::new(y.x.max(b.x), y.y.max(b.y)),
+            ),
+            None => (a, b),
+        });
+    }
+    bbox.unwrap_or((Point::zero(), Point::zero()))
+}
+
+/// \returns the colors of the GIF palette: a cube of 6 levels of red, green
+/// and blue, and 40 levels of gray.
+fn get_palette() -> Vec<[u8; 3]> {
+    let mut res = Vec::new();
+    for r in 0..6 {
+        for g in 0..6 {
+            for b in 0..6 {
+                res.push([r * 51, g * 51, b * 51]);
+            }
+        }
+    }
+    for i in 0..40 {
+        let level = (i as f64 * 255. / 39.).round() as u8;
+        res.push([level, level, level]);
+    }
+    res
+}
+
+/// \returns the index of the color of the GIF palette that is closest to the
+/// color \p pixel, after it's blended over white.
+fn get_color_index(pixel: [u8; 4]) -> u8 {
+    let a = pixel[3] as u32;
+    let blend = |c: u8| ((c as u32 * a + 255 * (255 - a)) / 255) as u8;
+    let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    if max - min < 16 {
+        let gray = (r as u32 + g as u32 + b as u32) as f64 / 3.;
+        return 216 + (gray * 39. / 255.).round() as u8;
+    }
+    let level = |c: u8| (c as f64 / 51.).round() as u8;
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// Writes the variable-length codes of the LZW compression of GIF images.
+struct CodeWriter {
+    out: Vec<u8>,
+    acc: u32,
+    num_bits: u32,
+}
+
+impl CodeWriter {
+    /// Write the code \p code with \p bits bits, starting with the lowest bit.
+    fn write(&mut self, code: u16, bits: u32) {
+        self.acc |= (code as u32) << self.num_bits;
+        self.num_bits += bits;
+        while self.num_bits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.num_bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.num_bits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// \returns the LZW compression of the color indices \p indices, with the
+/// minimum code size \p min_bits, as in GIF images. The table of codes starts
+/// over when it's full.
+fn lzw_encode(indices: &[u8], min_bits: u32) -> Vec<u8> {
+    let clear = 1_u16 << min_bits;
+    let end = clear + 1;
+    let mut writer = CodeWriter {
+        out: Vec::new(),
+        acc: 0,
+        num_bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut bits = min_bits + 1;
+
+    // Write the code \p code, and grow the codes when the next code doesn't
+    // fit. The clear code starts over with the short codes.
+    let mut write = |writer: &mut CodeWriter, code: u16, next: u16| {
+        writer.write(code, bits);
+        if code == clear {
+            bits = min_bits + 1;
+        } else if next as u32 >= 1 << bits && bits < MAX_CODE_BITS {
+            bits += 1;
+        }
+    };
+
+    write(&mut writer, clear, next);
+    let mut prefix: Option<u16> = None;
+    for index in indices {
+        let current = match prefix {
+            Option::Some(current) => current,
+            None => {
+                prefix = Some(*index as u16);
+                continue;
+            }
+        };
+        if let Option::Some(code) = table.get(&(current, *index)) {
+            prefix = Some(*code);
+            continue;
+        }
+        write(&mut writer, current, next);
+        if next < 1 << MAX_CODE_BITS {
+            table.insert((current, *index), next);
+            next += 1;
+        } else {
+            write(&mut writer, clear, next);
+            table.clear();
+            next = end + 1;
+        }
+        prefix = Some(*index as u16);
+    }
+    if let Option::Some(current) = prefix {
+        write(&mut writer, current, next);
+    }
+    write(&mut writer, end, next);
+    writer.finish()
+}
+
+/// \returns the color indices of the LZW data \p data, that was compressed
+/// with the minimum code size \p min_bits.
+#[cfg(test)]
+fn lzw_decode(data: &[u8], min_bits: u32) -> Vec<u8> {
+    let clear = 1_usize << min_bits;
+    let mut table: Vec<Vec<u8>> = Vec::new();
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        table.clear();
+        table.extend((0..clear).map(|i| vec![i as u8]));
+        table.push(Vec::new());
+        table.push(Vec::new());
+    };
+    reset(&mut table);
+    let mut bits = min_bits + 1;
+    let (mut acc, mut num_bits, mut pos) = (0_u32, 0, 0);
+    let mut res = Vec::new();
+    let mut prev: Option<Vec<u8>> = None;
+    loop {
+        while num_bits < bits {
+            acc |= (data[pos] as u32) << num_bits;
+            pos += 1;
+            num_bits += 8;
+        }
+        let code = (acc & ((1 << bits) - 1)) as usize;
+        acc >>= bits;
+        num_bits -= bits;
+        if code == clear {
+            reset(&mut table);
+            bits = min_bits + 1;
+            prev = None;
+            continue;
+        }
+        if code == clear + 1 {
+            return res;
+        }
+        let entry = match (&prev, table.get(code)) {
+            (_, Option::Some(entry)) => entry.clone(),
+            (Option::Some(prev), None) => {
+                let mut entry = prev.clone();
+                entry.push(prev[0]);
+                entry
+            }
+            (None, None) => panic!("Invalid code"),
+        };
+        res.extend_from_slice(&entry);
+        if let Option::Some(prev) = prev {
+            if table.len() < 1 << MAX_CODE_BITS {
+                let mut new = prev.clone();
+                new.push(entry[0]);
+                table.push(new);
+            }
+        }
+        if table.len() == 1 << bits && bits < MAX_CODE_BITS {
+            bits += 1;
+        }
+        prev = Some(entry);
+    }
+}
+
+#[test]
+fn test_lzw() {
+    let mut data: Vec<u8> = Vec::new();
+    for i in 0..20000_usize {
+        data.push(((i * 7) % 13 + (i / 1000) * 11) as u8);
+    }
+    let encoded = lzw_encode(&data, 8);
+    assert!(encoded.len() < data.len());
+    assert_eq!(lzw_decode(&encoded, 8), data);
+    assert_eq!(lzw_decode(&lzw_encode(&[1, 1, 1, 1], 8), 8), [1, 1, 1, 1]);
+}
+
+#[test]
+fn test_palette() {
+    assert_eq!(get_palette().len(), 256);
+    assert_eq!(get_color_index([255, 255, 255, 255]), 255);
+    assert_eq!(get_color_index([0, 0, 0, 0]), 255);
+    assert_eq!(get_color_index([255, 0, 0, 255]), 180);
+}
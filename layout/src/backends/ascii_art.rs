@@ -6,15 +6,37 @@ Notes:
 - Coordinates are floating-point; they are rounded to the nearest grid cell.
 - The Y axis grows downward, similar to typical screen coordinates.
 - Colors, stroke width, and most style attributes are ignored for ASCII.
-- Clip regions are recorded but not applied (ASCII backend ignores clipping).
+- The grid grows to fit the content by default. `ASCIIWriter::with_canvas`
+  creates a fixed-size canvas, and `set_viewport_offset` selects which part of
+  the drawing is visible. Content outside of the canvas is cropped.
+- `RenderMode::HalfBlock` and `RenderMode::Braille` draw lines and outlines
+  with sub-cell glyphs (▀ ▄ and ⠿ patterns) that have 1x2 and 2x4 dots per
+  cell, which makes diagonal edges and circles much less blocky.
+- By default the size of a cell is derived from the font size of each draw
+  call. `fit_to_width` (or `fit_to_terminal`) buffers the draw calls and picks
+  one global scale in `finalize`, so that the whole diagram fits in the
+  requested number of columns.
+- Clip regions are applied to the shapes that are drawn with a clip handle.
+  Cells outside of the region, including the corners that fall outside of a
+  rounded region, are left untouched.
+- Outlines use plain ASCII (`+-|`) by default. Selecting `CharSet::Unicode`
+  switches to box-drawing characters (─ │ ┌ ┐ └ ┘ ├ ┤) and arrow heads
+  (▲ ▼ ◄ ►), and merges crossing lines into the matching junction glyphs.
 
 Terminal vs Non-Terminal Behavior:
 - Terminal output: Fills shapes with Unicode block characters (█, ●) when fill_color is specified
 - Terminal colors: Uses ANSI escape codes to color the fill characters when use_colors is enabled
+- Fill modes: `FillMode::Glyph` fills shapes with colored block characters, and
+  `FillMode::Background` sets the background color of the cells (SGR 48) and
+  draws labels and edges on top in a contrasting foreground color
+- Color modes: `ColorMode::Basic8` maps fills to the 8 basic ANSI colors,
+  `ColorMode::Xterm256` uses the 256-color palette (`\x1b[38;5;Nm`) and
+  `ColorMode::TrueColor` preserves the exact RGB value (`\x1b[38;2;r;g;bm`)
 - Non-terminal output: Only draws outlines, no fill characters (useful for plain text files)
 
 Usage Examples:
 ```rust
+# use layout::backends::ascii_art::*;
 // Auto-detect terminal output (colors enabled if terminal detected)
 let mut writer = ASCIIWriter::new();
 
@@ -27,31 +49,300 @@ let mut file_writer = ASCIIWriter::new_with_terminal_setting(false);
 // Control color usage in terminal mode
 let mut writer = ASCIIWriter::new_with_color_setting(true, false);
 writer.set_use_colors(true); // Enable ANSI color codes for fill characters
+
+// Use Unicode box-drawing characters instead of `+-|`
+writer.set_char_set(CharSet::Unicode);
+
+// Preserve the exact fill colors on terminals that support 24-bit color
+writer.set_color_mode(ColorMode::TrueColor);
+
+// Paint node fills as cell backgrounds, to keep the labels readable
+writer.set_fill_mode(FillMode::Background);
+
+// Render into a fixed 80x24 window, scrolled 10 cells to the right
+let mut window = ASCIIWriter::with_canvas(80, 24);
+window.set_viewport_offset(10, 0);
+assert_eq!(window.grid_size(), (80, 24));
+
+// Draw edges and outlines with Braille dots
+writer.set_render_mode(RenderMode::Braille);
+
+// Shrink wide diagrams to the width of the terminal
+let mut fitted = ASCIIWriter::new();
+fitted.fit_to_terminal();
 ```
 
 Color Output Examples:
 - Terminal with colors: `\x1b[31m█\x1b[0m` (red filled block)
+- Terminal with true colors: `\x1b[38;2;255;0;0m█\x1b[0m` (red filled block)
 - Terminal without colors: `█` (plain filled block)
 - Non-terminal: `+--+\n|  |\n+--+` (outline only)
 */
 
 use crate::core::format::{ClipHandle, RenderBackend};
-use crate::core::geometry::Point;
-use crate::core::style::StyleAttr;
+use crate::core::geometry::{flatten_bezier_segments, point_in_polygon, Point};
+use crate::core::style::{ArrowHeadKind, StyleAttr};
 
 // External crates for terminal detection and coloring
-use atty;
 use termcolor::Color;
+#[cfg(not(target_arch = "wasm32"))]
+use terminal_size::{terminal_size, Width};
+
+/// \returns true if the standard output is a terminal. WebAssembly programs
+/// have no terminal.
+pub(crate) fn is_stdout_terminal() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return atty::is(atty::Stream::Stdout);
+    #[cfg(target_arch = "wasm32")]
+    return false;
+}
+
+/// Edges with at least this pen width, such as bold edges, are drawn with
+/// heavier glyphs.
+const HEAVY_LINE_WIDTH: f64 = 2.;
+
+/// Selects the glyphs that are used for drawing outlines, edges and arrow
+/// heads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CharSet {
+    /// Plain ASCII characters, such as `+`, `-` and `|`.
+    #[default]
+    Ascii,
+    /// Unicode box-drawing characters, such as `┌`, `─` and `│`.
+    Unicode,
+}
+
+/// Selects how fill colors are translated to ANSI escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// The 8 basic ANSI colors (SGR 30-37).
+    #[default]
+    Basic8,
+    /// The xterm 256-color palette (SGR 38;5;N).
+    Xterm256,
+    /// 24-bit RGB colors (SGR 38;2;R;G;B).
+    TrueColor,
+}
+
+impl ColorMode {
+    /// Guess the color mode that the terminal supports from the `COLORTERM`
+    /// and `TERM` environment variables.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorMode::TrueColor;
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.contains("256color") {
+            return ColorMode::Xterm256;
+        }
+        ColorMode::Basic8
+    }
+}
+
+/// Selects how filled shapes are painted in terminal mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillMode {
+    /// Fill the shape with block characters in the fill color.
+    #[default]
+    Glyph,
+    /// Set the background color of the cells (SGR 48), and draw the text and
+    /// edges on top in a contrasting foreground color.
+    Background,
+}
+
+/// Selects the resolution that lines and outlines are drawn at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// One glyph per cell.
+    #[default]
+    Cell,
+    /// Two vertical dots per cell, using the half-block characters ▀ ▄ █.
+    HalfBlock,
+    /// 2x4 dots per cell, using the Braille patterns (U+2800 - U+28FF).
+    Braille,
+}
+
+impl RenderMode {
+    /// \returns the number of dots per cell along the x and y axis.
+    fn resolution(&self) -> (isize, isize) {
+        match self {
+            RenderMode::Cell => (1, 1),
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+        }
+    }
+
+    /// \returns the bit that represents the dot at \p dx, \p dy within a
+    /// cell.
+    fn dot_bit(&self, dx: isize, dy: isize) -> u8 {
+        // Braille dots are numbered column by column, with the bottom row
+        // added last: 1 4 / 2 5 / 3 6 / 7 8.
+        const BRAILLE: [[u8; 4]; 2] =
+            [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+        match self {
+            RenderMode::Cell => 0,
+            RenderMode::HalfBlock => 1 << dy,
+            RenderMode::Braille => BRAILLE[dx as usize][dy as usize],
+        }
+    }
+
+    /// \returns the glyph that displays the dots in \p dots.
+    fn dot_glyph(&self, dots: u8) -> char {
+        match self {
+            RenderMode::Cell => ' ',
+            RenderMode::HalfBlock => match dots {
+                1 => '▀',
+                2 => '▄',
+                _ => '█',
+            },
+            RenderMode::Braille => {
+                char::from_u32(0x2800 + dots as u32).unwrap_or(' ')
+            }
+        }
+    }
+}
+
+/// \returns the cells on the line between \p from and \p to, using
+/// Bresenham's algorithm.
+fn bresenham(from: (isize, isize), to: (isize, isize)) -> Vec<(isize, isize)> {
+    let (mut x0, mut y0) = from;
+    let (x1, y1) = to;
+    let dx = (x1 - x0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let dy = -(y1 - y0).abs();
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let mut err = dx + dy;
+
+    let mut res = Vec::new();
+    loop {
+        res.push((x0, y0));
+        if x0 == x1 && y0 == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y0 += sy;
+        }
+    }
+    res
+}
+
+/// A draw call that is buffered until the global scale is known (see
+/// fit_to_width).
+#[derive(Debug, Clone)]
+enum DrawOp {
+    Rect(Point, Point, StyleAttr, Option<ClipHandle>),
+    Line(Point, Point, StyleAttr),
+    Circle(Point, Point, StyleAttr),
+    Polygon(Vec<Point>, StyleAttr, Option<ClipHandle>),
+    Path(Vec<(Point, Point, Point, Point)>, StyleAttr),
+    Text(Point, String, StyleAttr),
+    Arrow(Vec<(Point, Point)>, bool, (bool, bool), StyleAttr, String),
+}
+
+/// A single character cell in the output grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: Option<termcolor::Color>,
+    bg: Option<termcolor::Color>,
+    dots: u8, // sub-cell dots, see RenderMode
+}
+
+impl Cell {
+    fn empty() -> Self {
+        Cell {
+            ch: ' ',
+            fg: None,
+            bg: None,
+            dots: 0,
+        }
+    }
+    fn is_blank(&self) -> bool {
+        self.ch == ' ' && self.bg.is_none() && self.dots == 0
+    }
+    /// \returns the character that represents this cell. Characters that
+    /// were placed in the cell take priority over the sub-cell dots.
+    fn glyph(&self, mode: RenderMode) -> char {
+        if self.ch == ' ' && self.dots != 0 {
+            return mode.dot_glyph(self.dots);
+        }
+        self.ch
+    }
+}
+
+// Bit flags that describe which sides of a cell a box-drawing glyph connects
+// to. Used for merging crossing lines into junction characters.
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// \returns the box-drawing character that connects the sides in \p mask.
+fn box_char(mask: u8) -> char {
+    match mask {
+        m if m == LEFT | RIGHT => '─',
+        m if m == UP | DOWN => '│',
+        m if m == DOWN | RIGHT => '┌',
+        m if m == DOWN | LEFT => '┐',
+        m if m == UP | RIGHT => '└',
+        m if m == UP | LEFT => '┘',
+        m if m == UP | DOWN | RIGHT => '├',
+        m if m == UP | DOWN | LEFT => '┤',
+        m if m == DOWN | LEFT | RIGHT => '┬',
+        m if m == UP | LEFT | RIGHT => '┴',
+        m if m == UP | DOWN | LEFT | RIGHT => '┼',
+        m if m == LEFT || m == RIGHT => '─',
+        _ => '│',
+    }
+}
+
+/// \returns the sides that the box-drawing character \p ch connects to, or
+/// zero if this is not a box-drawing character.
+fn box_mask(ch: char) -> u8 {
+    match ch {
+        '─' => LEFT | RIGHT,
+        '│' => UP | DOWN,
+        '┌' | '╭' => DOWN | RIGHT,
+        '┐' | '╮' => DOWN | LEFT,
+        '└' | '╰' => UP | RIGHT,
+        '┘' | '╯' => UP | LEFT,
+        '├' => UP | DOWN | RIGHT,
+        '┤' => UP | DOWN | LEFT,
+        '┬' => DOWN | LEFT | RIGHT,
+        '┴' => UP | LEFT | RIGHT,
+        '┼' => UP | DOWN | LEFT | RIGHT,
+        _ => 0,
+    }
+}
 
 #[derive(Debug)]
 pub struct ASCIIWriter {
-    grid: Vec<Vec<(char, Option<termcolor::Color>)>>, // char with optional color
+    grid: Vec<Vec<Cell>>, // char with optional colors
     width: usize,
     height: usize,
     scale: f64, // pixels per cell (derived from font size)
-    clips: Vec<(Point, Point, usize)>, // (top-left, size, rounded_px) - not applied
+    clips: Vec<(Point, Point, usize)>, // (top-left, size, rounded_px)
+    active_clip: Option<ClipHandle>, // the clip applied to the current shape
     is_terminal: bool, // whether output is targeted for terminal
-    use_colors: bool,  // whether to use colors in terminal output
+    use_colors: bool, // whether to use colors in terminal output
+    char_set: CharSet, // the glyphs used for outlines and edges
+    color_mode: ColorMode, // how fill colors are encoded
+    fill_mode: FillMode, // how filled shapes are painted
+    render_mode: RenderMode, // the resolution of lines and outlines
+    dash: Vec<usize>, // on/off lengths (in cells or dots) of the current stroke
+    dash_step: usize, // the number of cells or dots drawn in the current stroke
+    canvas: Option<(usize, usize)>, // fixed (cols, rows), or grow as needed
+    offset: (isize, isize), // the cell that maps to the top-left corner
+    fit_width: Option<usize>, // fit the drawing into this many columns
+    fixed_scale: Option<f64>, // overrides the font-derived scale
+    ops: Vec<DrawOp>, // draw calls that are buffered for fitting
 }
 
 impl ASCIIWriter {
@@ -62,8 +353,20 @@ impl ASCIIWriter {
             height: 0,
             scale: 20.0,
             clips: Vec::new(),
-            is_terminal: atty::is(atty::Stream::Stdout),
-            use_colors: atty::is(atty::Stream::Stdout),
+            active_clip: None,
+            is_terminal: is_stdout_terminal(),
+            use_colors: is_stdout_terminal(),
+            char_set: CharSet::Ascii,
+            color_mode: ColorMode::detect(),
+            fill_mode: FillMode::Glyph,
+            render_mode: RenderMode::Cell,
+            dash: Vec::new(),
+            dash_step: 0,
+            canvas: None,
+            offset: (0, 0),
+            fit_width: None,
+            fixed_scale: None,
+            ops: Vec::new(),
         }
     }
 
@@ -74,8 +377,20 @@ impl ASCIIWriter {
             height: 0,
             scale: 6.0,
             clips: Vec::new(),
+            active_clip: None,
             is_terminal,
             use_colors: is_terminal,
+            char_set: CharSet::Ascii,
+            color_mode: ColorMode::Basic8,
+            fill_mode: FillMode::Glyph,
+            render_mode: RenderMode::Cell,
+            dash: Vec::new(),
+            dash_step: 0,
+            canvas: None,
+            offset: (0, 0),
+            fit_width: None,
+            fixed_scale: None,
+            ops: Vec::new(),
         }
     }
 
@@ -86,12 +401,175 @@ impl ASCIIWriter {
             height: 0,
             scale: 6.0,
             clips: Vec::new(),
+            active_clip: None,
             is_terminal,
             use_colors,
+            char_set: CharSet::Ascii,
+            color_mode: ColorMode::Basic8,
+            fill_mode: FillMode::Glyph,
+            render_mode: RenderMode::Cell,
+            dash: Vec::new(),
+            dash_step: 0,
+            canvas: None,
+            offset: (0, 0),
+            fit_width: None,
+            fixed_scale: None,
+            ops: Vec::new(),
         }
     }
 
+    /// Create a writer with a fixed canvas of \p cols by \p rows cells.
+    /// Content that falls outside of the canvas is cropped instead of growing
+    /// the grid. Use set_viewport_offset to scroll the visible window.
+    pub fn with_canvas(cols: usize, rows: usize) -> Self {
+        let mut writer = Self::new();
+        writer.canvas = Some((cols, rows));
+        writer.grid = vec![vec![Cell::empty(); cols]; rows];
+        writer.width = cols;
+        writer.height = rows;
+        writer
+    }
+
+    /// Returns the size of the output grid in (columns, rows).
+    pub fn grid_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the cell coordinate that is rendered at the top-left corner
+    pub fn viewport_offset(&self) -> (isize, isize) {
+        self.offset
+    }
+
+    /// Set the cell coordinate that is rendered at the top-left corner. This
+    /// moves the visible window over the drawing.
+    pub fn set_viewport_offset(&mut self, x: isize, y: isize) {
+        self.offset = (x, y);
+    }
+
+    /// Fit the whole diagram into \p max_cols columns. Instead of deriving
+    /// the scale from the font size of each draw call, the draw calls are
+    /// buffered and a single scale is computed when the output is finalized.
+    pub fn fit_to_width(&mut self, max_cols: usize) {
+        self.fit_width = Some(max_cols.max(1));
+    }
+
+    /// Fit the whole diagram into the width of the terminal. Falls back to
+    /// 80 columns if the terminal size can't be detected.
+    pub fn fit_to_terminal(&mut self) {
+        self.fit_to_width(Self::terminal_width().unwrap_or(80));
+    }
+
+    /// \returns the width of the terminal, in columns. The `COLUMNS`
+    /// environment variable takes precedence over the terminal query.
+    pub fn terminal_width() -> Option<usize> {
+        if let Ok(cols) = std::env::var("COLUMNS") {
+            if let Ok(cols) = cols.parse::<usize>() {
+                return Some(cols);
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        return terminal_size().map(|(Width(w), _)| w as usize);
+        #[cfg(target_arch = "wasm32")]
+        return None;
+    }
+
+    /// Compute the scale that makes the buffered draw calls fit into
+    /// \p max_cols columns. Diagrams that already fit are rendered with the
+    /// largest font size, like in the default mode.
+    fn compute_fit_scale(&self, max_cols: usize) -> f64 {
+        let mut right: f64 = 0.;
+        let mut font: f64 = 1.;
+        for op in &self.ops {
+            let (x, look) = match op {
+                DrawOp::Rect(xy, size, look, _) => (xy.x + size.x, look),
+                DrawOp::Line(a, b, look) => (a.x.max(b.x), look),
+                DrawOp::Circle(xy, size, look) => (xy.x + size.x / 2., look),
+                DrawOp::Polygon(points, look, _) => {
+                    let x = points.iter().map(|p| p.x).fold(0., f64::max);
+                    (x, look)
+                }
+                DrawOp::Path(segments, look) => {
+                    let x = segments
+                        .iter()
+                        .map(|s| s.0.x.max(s.1.x).max(s.2.x).max(s.3.x))
+                        .fold(0., f64::max);
+                    (x, look)
+                }
+                DrawOp::Text(xy, text, look) => {
+                    let half = text.lines().map(|l| l.chars().count()).max();
+                    let half = half.unwrap_or(0) as f64 / 2.;
+                    (xy.x + half * look.font_size as f64, look)
+                }
+                DrawOp::Arrow(path, _, _, look, _) => {
+                    let x = path.iter().map(|p| p.0.x).fold(0., f64::max);
+                    (x, look)
+                }
+            };
+            right = right.max(x);
+            font = font.max(look.font_size as f64);
+        }
+        font.max(right / (max_cols as f64 - 1.).max(1.))
+    }
+
+    /// Render the buffered draw calls into a new writer that has the same
+    /// settings as this one, but uses the fixed scale \p scale.
+    fn replay(&self, scale: f64) -> ASCIIWriter {
+        let mut writer = ASCIIWriter::new_with_color_setting(
+            self.is_terminal,
+            self.use_colors,
+        );
+        writer.char_set = self.char_set;
+        writer.color_mode = self.color_mode;
+        writer.fill_mode = self.fill_mode;
+        writer.render_mode = self.render_mode;
+        writer.offset = self.offset;
+        writer.clips = self.clips.clone();
+        writer.fixed_scale = Some(scale);
+        if let Some((cols, rows)) = self.canvas {
+            writer.canvas = Some((cols, rows));
+            writer.grid = vec![vec![Cell::empty(); cols]; rows];
+            writer.width = cols;
+            writer.height = rows;
+        }
+        for op in &self.ops {
+            match op {
+                DrawOp::Rect(xy, size, look, clip) => {
+                    writer.draw_rect(*xy, *size, look, None, *clip)
+                }
+                DrawOp::Line(a, b, look) => {
+                    writer.draw_line(*a, *b, look, None)
+                }
+                DrawOp::Circle(xy, size, look) => {
+                    writer.draw_circle(*xy, *size, look, None)
+                }
+                DrawOp::Polygon(points, look, clip) => {
+                    writer.draw_polygon(points, look, None, *clip)
+                }
+                DrawOp::Path(segments, look) => {
+                    writer.draw_path(segments, look, None)
+                }
+                DrawOp::Text(xy, text, look) => {
+                    writer.draw_text(*xy, text, look)
+                }
+                DrawOp::Arrow(path, dashed, head, look, text) => {
+                    writer.draw_arrow(path, *dashed, *head, look, None, text)
+                }
+            }
+        }
+        writer
+    }
+
+    /// Update the scale (pixels per cell) for a draw call with the style
+    /// \p look.
+    fn set_scale(&mut self, look: &StyleAttr) {
+        self.scale = self.fixed_scale.unwrap_or(look.font_size as f64);
+    }
+
     pub fn finalize(&self) -> String {
+        if let Some(max_cols) = self.fit_width {
+            let scale = self.compute_fit_scale(max_cols);
+            return self.replay(scale).finalize();
+        }
         if self.is_terminal && self.use_colors {
             self.finalize_with_colors()
         } else {
@@ -104,10 +582,13 @@ impl ASCIIWriter {
         for row in &self.grid {
             // Trim trailing spaces for nicer output.
             let mut end = row.len();
-            while end > 0 && row[end - 1].0 == ' ' {
+            while end > 0 && row[end - 1].glyph(self.render_mode) == ' ' {
                 end -= 1;
             }
-            let line: String = row[..end].iter().map(|(ch, _)| *ch).collect();
+            let line: String = row[..end]
+                .iter()
+                .map(|c| c.glyph(self.render_mode))
+                .collect();
             out.push_str(&line);
             out.push('\n');
         }
@@ -119,27 +600,32 @@ impl ASCIIWriter {
         for row in &self.grid {
             // Trim trailing spaces for nicer output.
             let mut end = row.len();
-            while end > 0 && row[end - 1].0 == ' ' {
+            while end > 0 && row[end - 1].is_blank() {
                 end -= 1;
             }
 
-            let mut current_color: Option<termcolor::Color> = None;
-            for &(ch, color) in &row[..end] {
+            let mut current_color = (None, None);
+            for cell in &row[..end] {
+                let color = (cell.fg, cell.bg);
                 if color != current_color {
-                    if current_color.is_some() {
+                    if current_color != (None, None) {
                         out.push_str("\x1b[0m"); // Reset color
                     }
-                    if let Some(c) = color {
-                        out.push_str(&format!(
-                            "\x1b[{}m",
-                            Self::color_to_ansi(c)
-                        ));
+                    let mut codes = Vec::new();
+                    if let Some(c) = color.0 {
+                        codes.push(Self::color_to_ansi(c));
+                    }
+                    if let Some(c) = color.1 {
+                        codes.push(Self::color_to_ansi_background(c));
+                    }
+                    if !codes.is_empty() {
+                        out.push_str(&format!("\x1b[{}m", codes.join(";")));
                     }
                     current_color = color;
                 }
-                out.push(ch);
+                out.push(cell.glyph(self.render_mode));
             }
-            if current_color.is_some() {
+            if current_color != (None, None) {
                 out.push_str("\x1b[0m"); // Reset color at end of line
             }
             out.push('\n');
@@ -147,17 +633,61 @@ impl ASCIIWriter {
         out
     }
 
-    fn color_to_ansi(color: termcolor::Color) -> u8 {
+    fn color_to_ansi(color: termcolor::Color) -> String {
         match color {
-            Color::Black => 30,
-            Color::Blue => 34,
-            Color::Green => 32,
-            Color::Red => 31,
-            Color::Cyan => 36,
-            Color::Magenta => 35,
-            Color::Yellow => 33,
-            Color::White => 37,
-            _ => 37, // Default to white for other colors
+            Color::Black => "30".to_string(),
+            Color::Blue => "34".to_string(),
+            Color::Green => "32".to_string(),
+            Color::Red => "31".to_string(),
+            Color::Cyan => "36".to_string(),
+            Color::Magenta => "35".to_string(),
+            Color::Yellow => "33".to_string(),
+            Color::White => "37".to_string(),
+            Color::Ansi256(n) => format!("38;5;{}", n),
+            Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+            _ => "37".to_string(), // Default to white for other colors
+        }
+    }
+
+    /// Converts a foreground color code (30-37, 38;...) to the matching
+    /// background color code (40-47, 48;...).
+    fn color_to_ansi_background(color: termcolor::Color) -> String {
+        let fg = Self::color_to_ansi(color);
+        if let Some(rest) = fg.strip_prefix("38;") {
+            return format!("48;{}", rest);
+        }
+        let code: u8 = fg.parse().unwrap_or(37);
+        (code + 10).to_string()
+    }
+
+    /// \returns black or white, depending on which one is readable on top of
+    /// the background \p bg.
+    fn contrasting_color(bg: termcolor::Color) -> termcolor::Color {
+        let (r, g, b) = match bg {
+            Color::Black => (0, 0, 0),
+            Color::Blue => (0, 0, 255),
+            Color::Green => (0, 255, 0),
+            Color::Red => (255, 0, 0),
+            Color::Cyan => (0, 255, 255),
+            Color::Magenta => (255, 0, 255),
+            Color::Yellow => (255, 255, 0),
+            Color::Ansi256(n) if (16..232).contains(&n) => {
+                const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+                let n = (n - 16) as usize;
+                (LEVELS[n / 36], LEVELS[(n / 6) % 6], LEVELS[n % 6])
+            }
+            Color::Ansi256(n) if n >= 232 => {
+                let v = 8 + (n - 232) * 10;
+                (v, v, v)
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+            _ => (255, 255, 255),
+        };
+        let luma = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+        if luma > 128. {
+            Color::Black
+        } else {
+            Color::White
         }
     }
 
@@ -176,18 +706,145 @@ impl ASCIIWriter {
         self.use_colors = use_colors && self.is_terminal;
     }
 
+    /// Returns the character set that is used for outlines and edges
+    pub fn char_set(&self) -> CharSet {
+        self.char_set
+    }
+
+    /// Select the character set that is used for outlines and edges
+    pub fn set_char_set(&mut self, char_set: CharSet) {
+        self.char_set = char_set;
+    }
+
+    /// Returns the mode that is used for encoding fill colors
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// Select the mode that is used for encoding fill colors
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        self.color_mode = color_mode;
+    }
+
+    /// Returns the mode that is used for painting filled shapes
+    pub fn fill_mode(&self) -> FillMode {
+        self.fill_mode
+    }
+
+    /// Select the mode that is used for painting filled shapes
+    pub fn set_fill_mode(&mut self, fill_mode: FillMode) {
+        self.fill_mode = fill_mode;
+    }
+
+    /// Returns the resolution that lines and outlines are drawn at
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Select the resolution that lines and outlines are drawn at
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    fn is_subcell(&self) -> bool {
+        self.render_mode != RenderMode::Cell
+    }
+
+    /// Convert \p p to the coordinates of the sub-cell dot grid.
+    fn to_dot_xy(&self, p: Point) -> (isize, isize) {
+        let (rx, ry) = self.render_mode.resolution();
+        (
+            (p.x * rx as f64 / self.scale).round() as isize,
+            (p.y * ry as f64 / self.scale).round() as isize,
+        )
+    }
+
+    /// Start a stroke that follows the dash pattern of \p look. Dashes are
+    /// at least one cell (or dot) long, so short patterns alternate between
+    /// drawn and empty cells.
+    fn begin_dash(&mut self, look: &StyleAttr) {
+        let unit = self.scale / self.render_mode.resolution().0 as f64;
+        self.dash = look
+            .dash
+            .iter()
+            .map(|len| ((len / unit).round() as usize).max(1))
+            .collect();
+        self.dash_step = 0;
+    }
+
+    fn end_dash(&mut self) {
+        self.dash.clear();
+    }
+
+    /// Advance the current stroke by one cell (or dot). \returns true if the
+    /// cell falls on a dash and should be drawn.
+    fn next_dash_step(&mut self) -> bool {
+        if self.dash.is_empty() {
+            return true;
+        }
+        let total: usize = self.dash.iter().sum();
+        let mut pos = self.dash_step % total;
+        self.dash_step += 1;
+        for (i, len) in self.dash.iter().enumerate() {
+            if pos < *len {
+                // Even entries are dashes and odd entries are gaps.
+                return i % 2 == 0;
+            }
+            pos -= len;
+        }
+        true
+    }
+
+    /// Turn on the sub-cell dot at \p dx, \p dy (in dot coordinates).
+    fn set_dot(&mut self, dx: isize, dy: isize) {
+        if !self.next_dash_step() {
+            return;
+        }
+        let (rx, ry) = self.render_mode.resolution();
+        let (ix, iy) = (dx.div_euclid(rx), dy.div_euclid(ry));
+        let bit = self
+            .render_mode
+            .dot_bit(dx.rem_euclid(rx), dy.rem_euclid(ry));
+        if !self.is_in_clip(ix, iy) {
+            return;
+        }
+        if let Some((x, y)) = self.to_grid(ix, iy) {
+            self.ensure_size(x, y);
+            let cell = &mut self.grid[y][x];
+            // Dots replace the fill glyphs, but not text.
+            if cell.ch == '█' || cell.ch == '●' {
+                cell.ch = ' ';
+            }
+            cell.dots |= bit;
+            if let (None, Some(bg)) = (cell.fg, cell.bg) {
+                cell.fg = Some(Self::contrasting_color(bg));
+            }
+        }
+    }
+
+    /// Draw a line of sub-cell dots between \p from and \p to.
+    fn dot_line(&mut self, from: (isize, isize), to: (isize, isize)) {
+        for (x, y) in bresenham(from, to) {
+            self.set_dot(x, y);
+        }
+    }
+
+    fn is_unicode(&self) -> bool {
+        self.char_set == CharSet::Unicode
+    }
+
     fn ensure_size(&mut self, x: usize, y: usize) {
         if y >= self.height {
             let new_height = y + 1;
             let fill_width = self.width.max(1);
             self.grid
-                .resize_with(new_height, || vec![(' ', None); fill_width]);
+                .resize_with(new_height, || vec![Cell::empty(); fill_width]);
             self.height = new_height;
         }
         if x >= self.width {
             let new_width = x + 1;
             for row in &mut self.grid {
-                row.resize(new_width, (' ', None));
+                row.resize(new_width, Cell::empty());
             }
             self.width = new_width;
         }
@@ -208,6 +865,48 @@ impl ASCIIWriter {
         }
     }
 
+    /// Translate the drawing coordinate \p ix, \p iy to an index in the grid,
+    /// by applying the viewport offset. \returns None if the cell is outside
+    /// of the visible region.
+    fn to_grid(&self, ix: isize, iy: isize) -> Option<(usize, usize)> {
+        let (x, y) =
+            Self::clamp_nonneg(ix - self.offset.0, iy - self.offset.1)?;
+        if let Some((cols, rows)) = self.canvas {
+            if x >= cols || y >= rows {
+                return None;
+            }
+        }
+        Some((x, y))
+    }
+
+    /// \returns True if the cell at \p ix, \p iy is inside the active clip
+    /// region. Rounded regions are approximated by removing the cells that
+    /// fall outside of the circles at the corners.
+    fn is_in_clip(&self, ix: isize, iy: isize) -> bool {
+        let (xy, size, rounded_px) = match self.active_clip {
+            Some(handle) => self.clips[handle],
+            None => return true,
+        };
+        let (x0, y0) = self.to_ixy(xy);
+        let x1 = x0 + (size.x / self.scale).round() as isize - 1;
+        let y1 = y0 + (size.y / self.scale).round() as isize - 1;
+        if ix < x0 || ix > x1 || iy < y0 || iy > y1 {
+            return false;
+        }
+
+        let r = rounded_px as f64 / self.scale;
+        if r < 1.0 {
+            return true;
+        }
+        // Find the center of the nearest corner circle, and check if the cell
+        // is within the radius.
+        let (fx, fy) = (ix as f64, iy as f64);
+        let cx = fx.clamp(x0 as f64 + r, (x1 as f64 - r).max(x0 as f64 + r));
+        let cy = fy.clamp(y0 as f64 + r, (y1 as f64 - r).max(y0 as f64 + r));
+        let (dx, dy) = (fx - cx, fy - cy);
+        dx * dx + dy * dy <= r * r + 0.5
+    }
+
     fn set(&mut self, ix: isize, iy: isize, ch: char) {
         self.set_with_color(ix, iy, ch, None);
     }
@@ -219,9 +918,70 @@ impl ASCIIWriter {
         ch: char,
         color: Option<termcolor::Color>,
     ) {
-        if let Some((x, y)) = Self::clamp_nonneg(ix, iy) {
+        if !self.next_dash_step() || !self.is_in_clip(ix, iy) {
+            return;
+        }
+        if let Some((x, y)) = self.to_grid(ix, iy) {
+            self.ensure_size(x, y);
+            let cell = &mut self.grid[y][x];
+            cell.ch = ch;
+            // Keep the background of the cell, and make sure that glyphs that
+            // are drawn on top of it are readable.
+            cell.fg = match (color, cell.bg) {
+                (None, Some(bg)) => Some(Self::contrasting_color(bg)),
+                _ => color,
+            };
+        }
+    }
+
+    /// Paint the background of the cell at \p ix, \p iy, and erase its
+    /// content.
+    fn set_background(
+        &mut self,
+        ix: isize,
+        iy: isize,
+        color: Option<termcolor::Color>,
+    ) {
+        if !self.is_in_clip(ix, iy) {
+            return;
+        }
+        if let Some((x, y)) = self.to_grid(ix, iy) {
+            self.ensure_size(x, y);
+            self.grid[y][x] = Cell {
+                ch: ' ',
+                fg: None,
+                bg: color,
+                dots: 0,
+            };
+        }
+    }
+
+    /// Paint a single cell of a filled shape, according to the fill mode.
+    fn fill_cell(
+        &mut self,
+        ix: isize,
+        iy: isize,
+        ch: char,
+        color: Option<termcolor::Color>,
+    ) {
+        if self.fill_mode == FillMode::Background && color.is_some() {
+            self.set_background(ix, iy, color);
+        } else {
+            self.set_with_color(ix, iy, ch, color);
+        }
+    }
+
+    /// Place a box-drawing glyph that connects the sides in \p mask. If the
+    /// cell already contains a box-drawing glyph then the two are merged into
+    /// a junction, so crossing lines render as ├ ┤ ┬ ┴ ┼.
+    fn set_box(&mut self, ix: isize, iy: isize, mask: u8) {
+        if !self.is_in_clip(ix, iy) {
+            return;
+        }
+        if let Some((x, y)) = self.to_grid(ix, iy) {
             self.ensure_size(x, y);
-            self.grid[y][x] = (ch, color);
+            let prev = box_mask(self.grid[y][x].ch);
+            self.set_with_color(ix, iy, box_char(prev | mask), None);
         }
     }
 
@@ -249,12 +1009,17 @@ impl ASCIIWriter {
         let dx = p1.x - p0.x;
         let dy = p1.y - p0.y;
 
+        let (h, v, down, up) = match self.char_set {
+            CharSet::Ascii => ('-', '|', '\\', '/'),
+            CharSet::Unicode => ('─', '│', '╲', '╱'),
+        };
+
         // Handle vertical and horizontal lines first
         if dx.abs() < 0.001 {
-            return '|'; // Vertical line
+            return v; // Vertical line
         }
         if dy.abs() < 0.001 {
-            return '-'; // Horizontal line
+            return h; // Horizontal line
         }
 
         // Calculate slope
@@ -268,43 +1033,26 @@ impl ASCIIWriter {
         let going_down_right = (dx > 0.0 && dy > 0.0) || (dx < 0.0 && dy < 0.0);
 
         match abs_angle {
-            a if a < 22.5 => '-', // Nearly horizontal
+            a if a < 22.5 => h, // Nearly horizontal
             a if a < 67.5 => {
                 // Diagonal
                 if going_down_right {
-                    '\\'
+                    down
                 } else {
-                    '/'
+                    up
                 }
             }
-            _ => '|', // Nearly vertical
+            _ => v, // Nearly vertical
         }
     }
 
     fn draw_line_segment(&mut self, p0: Point, p1: Point, ch: char) {
-        let (mut x0, mut y0) = self.to_ixy(p0);
-        let (x1, y1) = self.to_ixy(p1);
-
-        let dx = (x1 - x0).abs();
-        let sx = if x0 < x1 { 1 } else { -1 };
-        let dy = -(y1 - y0).abs();
-        let sy = if y0 < y1 { 1 } else { -1 };
-        let mut err = dx + dy;
-
-        loop {
-            self.set(x0, y0, ch);
-            if x0 == x1 && y0 == y1 {
-                break;
-            }
-            let e2 = 2 * err;
-            if e2 >= dy {
-                err += dy;
-                x0 += sx;
-            }
-            if e2 <= dx {
-                err += dx;
-                y0 += sy;
-            }
+        if self.is_subcell() {
+            self.dot_line(self.to_dot_xy(p0), self.to_dot_xy(p1));
+            return;
+        }
+        for (x, y) in bresenham(self.to_ixy(p0), self.to_ixy(p1)) {
+            self.set(x, y, ch);
         }
     }
 
@@ -314,6 +1062,41 @@ impl ASCIIWriter {
         }
     }
 
+    /// Draw an edge made of straight segments. In ASCII mode all of the
+    /// segments use the same character, and in Unicode mode each segment uses
+    /// a line glyph that follows its slope. Thick edges, with a pen width of
+    /// at least HEAVY_LINE_WIDTH, use heavier glyphs.
+    fn draw_edge_polyline(
+        &mut self,
+        anchors: &[Point],
+        dashed: bool,
+        penwidth: f64,
+    ) {
+        let heavy = penwidth >= HEAVY_LINE_WIDTH;
+        if !self.is_unicode() {
+            let seg_char = match (dashed, heavy) {
+                (true, _) => '.',
+                (false, true) => '#',
+                (false, false) => '*',
+            };
+            self.draw_polyline(anchors, seg_char);
+            return;
+        }
+        for i in 1..anchors.len() {
+            let ch = match self.get_line_char(anchors[i - 1], anchors[i]) {
+                '─' if dashed && heavy => '┅',
+                '│' if dashed && heavy => '┇',
+                '─' if dashed => '┄',
+                '│' if dashed => '┆',
+                _ if dashed => '·',
+                '─' if heavy => '━',
+                '│' if heavy => '┃',
+                ch => ch,
+            };
+            self.draw_line_segment(anchors[i - 1], anchors[i], ch);
+        }
+    }
+
     fn rect_fill(
         &mut self,
         top_left: Point,
@@ -326,7 +1109,7 @@ impl ASCIIWriter {
         let h = (size.y / self.scale).round().max(0.0) as isize;
         for yy in 0..h {
             for xx in 0..w {
-                self.set_with_color(ix + xx, iy + yy, fill, color);
+                self.fill_cell(ix + xx, iy + yy, fill, color);
             }
         }
     }
@@ -340,6 +1123,22 @@ impl ASCIIWriter {
             return;
         }
 
+        if self.is_subcell() {
+            let (rx, ry) = self.render_mode.resolution();
+            let (x0, y0) = (ix * rx, iy * ry);
+            let (x1, y1) = (x0 + w * rx - 1, y0 + h * ry - 1);
+            self.dot_line((x0, y0), (x1, y0));
+            self.dot_line((x1, y0), (x1, y1));
+            self.dot_line((x1, y1), (x0, y1));
+            self.dot_line((x0, y1), (x0, y0));
+            return;
+        }
+
+        if self.is_unicode() {
+            self.rect_outline_unicode(ix, iy, w, h);
+            return;
+        }
+
         // Corners
         self.set(ix, iy, '+');
         self.set(ix + w - 1, iy, '+');
@@ -357,6 +1156,43 @@ impl ASCIIWriter {
         }
     }
 
+    fn rect_outline_unicode(
+        &mut self,
+        ix: isize,
+        iy: isize,
+        w: isize,
+        h: isize,
+    ) {
+        let (x1, y1) = (ix + w - 1, iy + h - 1);
+        if w == 1 || h == 1 {
+            // Degenerate boxes collapse into a single line.
+            for x in ix..=x1 {
+                for y in iy..=y1 {
+                    let mask = if w == 1 { UP | DOWN } else { LEFT | RIGHT };
+                    self.set_box(x, y, mask);
+                }
+            }
+            return;
+        }
+
+        self.set_box(ix, iy, DOWN | RIGHT);
+        self.set_box(x1, iy, DOWN | LEFT);
+        self.set_box(ix, y1, UP | RIGHT);
+        self.set_box(x1, y1, UP | LEFT);
+        for x in (ix + 1)..x1 {
+            self.set_box(x, iy, LEFT | RIGHT);
+        }
+        for y in (iy + 1)..y1 {
+            self.set_box(x1, y, UP | DOWN);
+        }
+        for x in (ix + 1)..x1 {
+            self.set_box(x, y1, LEFT | RIGHT);
+        }
+        for y in (iy + 1)..y1 {
+            self.set_box(ix, y, UP | DOWN);
+        }
+    }
+
     fn ellipse_outline(&mut self, center: Point, size: Point, _ch: char) {
         let a = (size.x / 2.0).max(0.0);
         let b = (size.y / 2.0).max(0.0);
@@ -364,6 +1200,25 @@ impl ASCIIWriter {
             return;
         }
 
+        if self.is_subcell() {
+            // Walk around the ellipse with steps that are smaller than a dot.
+            let (rx, ry) = self.render_mode.resolution();
+            let radius = (a * rx as f64).max(b * ry as f64) / self.scale;
+            let steps = ((radius * 8.).ceil() as usize).max(8);
+            let mut prev = None;
+            for i in 0..=steps {
+                let t = i as f64 / steps as f64 * std::f64::consts::TAU;
+                let p =
+                    Point::new(center.x + a * t.cos(), center.y + b * t.sin());
+                let dot = self.to_dot_xy(p);
+                if let Some(prev) = prev {
+                    self.dot_line(prev, dot);
+                }
+                prev = Some(dot);
+            }
+            return;
+        }
+
         let (cx, cy) = self.to_ixy(center);
         let w = ((a * 2.0) / self.scale).round() as isize;
         let h = ((b * 2.0) / self.scale).round() as isize;
@@ -380,15 +1235,39 @@ impl ASCIIWriter {
         let top = cy - h / 2;
         let bottom = cy + h / 2;
 
+        if self.is_unicode() {
+            for x in (left + 1)..right {
+                self.set(x, top, '─');
+            }
+            for x in (left + 1)..right {
+                self.set(x, bottom, '─');
+            }
+            for y in (top + 1)..bottom {
+                self.set(left, y, '│');
+            }
+            for y in (top + 1)..bottom {
+                self.set(right, y, '│');
+            }
+            self.set(left, top, '╭');
+            self.set(right, top, '╮');
+            self.set(left, bottom, '╰');
+            self.set(right, bottom, '╯');
+            return;
+        }
+
         // Top and bottom horizontal lines
         for x in (left + 1)..right {
             self.set(x, top, '_');
+        }
+        for x in (left + 1)..right {
             self.set(x, bottom, '_');
         }
 
         // Left and right vertical lines
         for y in (top + 1)..bottom {
             self.set(left, y, '|');
+        }
+        for y in (top + 1)..bottom {
             self.set(right, y, '|');
         }
 
@@ -432,7 +1311,37 @@ impl ASCIIWriter {
                 let x0 = ((center.x - span) / self.scale).floor() as isize;
                 let x1 = ((center.x + span) / self.scale).ceil() as isize;
                 for ix in x0..=x1 {
-                    self.set_with_color(ix, iy, ch, color);
+                    self.fill_cell(ix, iy, ch, color);
+                }
+            }
+        }
+    }
+
+    /// Fill the cells whose centers are inside of the polygon \p points.
+    fn polygon_fill(
+        &mut self,
+        points: &[Point],
+        ch: char,
+        color: Option<termcolor::Color>,
+    ) {
+        if points.len() < 3 {
+            return;
+        }
+        let (mut x0, mut y0) = self.to_ixy(points[0]);
+        let (mut x1, mut y1) = (x0, y0);
+        for p in points {
+            let (ix, iy) = self.to_ixy(*p);
+            x0 = x0.min(ix);
+            y0 = y0.min(iy);
+            x1 = x1.max(ix);
+            y1 = y1.max(iy);
+        }
+        for iy in y0..=y1 {
+            for ix in x0..=x1 {
+                let center =
+                    Point::new(ix as f64 * self.scale, iy as f64 * self.scale);
+                if point_in_polygon(center, points) {
+                    self.fill_cell(ix, iy, ch, color);
                 }
             }
         }
@@ -457,17 +1366,60 @@ impl ASCIIWriter {
         }
     }
 
-    fn head_char(dx: f64, dy: f64) -> char {
+    fn head_char(&self, dx: f64, dy: f64) -> char {
+        let (right, left, down, up) = match self.char_set {
+            CharSet::Ascii => ('>', '<', 'v', '^'),
+            CharSet::Unicode => ('►', '◄', '▼', '▲'),
+        };
         if dx.abs() >= dy.abs() {
             if dx >= 0.0 {
-                '>'
+                right
             } else {
-                '<'
+                left
             }
         } else if dy >= 0.0 {
-            'v'
+            down
         } else {
-            '^'
+            up
+        }
+    }
+
+    /// \returns the character that draws the arrow head \p kind at the end of
+    /// a line that points in the direction \p dir, or None if there is no
+    /// head.
+    fn end_char(&self, kind: ArrowHeadKind, dir: Point) -> Option<char> {
+        let is_ascii = matches!(self.char_set, CharSet::Ascii);
+        let pick = |ascii: char, unicode: char| {
+            Some(if is_ascii { ascii } else { unicode })
+        };
+        match kind {
+            ArrowHeadKind::Normal | ArrowHeadKind::Vee => {
+                Some(self.head_char(dir.x, dir.y))
+            }
+            ArrowHeadKind::ONormal => match self.head_char(dir.x, dir.y) {
+                '►' => Some('▷'),
+                '◄' => Some('◁'),
+                '▼' => Some('▽'),
+                '▲' => Some('△'),
+                ch => Some(ch),
+            },
+            ArrowHeadKind::Crow
+            | ArrowHeadKind::CrowTee
+            | ArrowHeadKind::CrowODot => Some(self.head_char(-dir.x, -dir.y)),
+            ArrowHeadKind::Dot => pick('*', '●'),
+            ArrowHeadKind::ODot => pick('o', '○'),
+            ArrowHeadKind::Diamond => pick('#', '◆'),
+            ArrowHeadKind::ODiamond => pick('O', '◇'),
+            ArrowHeadKind::Box => pick('=', '■'),
+            ArrowHeadKind::Tee
+            | ArrowHeadKind::TeeTee
+            | ArrowHeadKind::TeeODot => match self.head_char(dir.x, dir.y) {
+                '>' | '►' => pick('|', '┤'),
+                '<' | '◄' => pick('|', '├'),
+                'v' | '▼' => pick('-', '┴'),
+                _ => pick('-', '┬'),
+            },
+            ArrowHeadKind::None => None,
         }
     }
 }
@@ -485,20 +1437,28 @@ impl RenderBackend for ASCIIWriter {
         size: Point,
         look: &StyleAttr,
         _properties: Option<String>,
-        _clip: Option<ClipHandle>,
+        clip: Option<ClipHandle>,
     ) {
-        self.scale = look.font_size as f64;
+        if self.fit_width.is_some() {
+            self.ops.push(DrawOp::Rect(xy, size, look.clone(), clip));
+            return;
+        }
+        self.set_scale(look);
+        self.active_clip = clip;
         // Fill if requested (only in terminal mode), then outline.
         if look.fill_color.is_some() && self.is_terminal {
             let fill_color = if self.use_colors {
-                Self::style_color_to_term_color(look.fill_color)
+                self.style_color_to_term_color(look.fill_color)
             } else {
                 None
             };
             self.rect_fill(xy, size, '█', fill_color);
         }
         // Always draw outline for rectangles
+        self.begin_dash(look);
         self.rect_outline(xy, size);
+        self.end_dash();
+        self.active_clip = None;
     }
 
     fn draw_line(
@@ -508,10 +1468,16 @@ impl RenderBackend for ASCIIWriter {
         look: &StyleAttr,
         _properties: Option<String>,
     ) {
-        self.scale = look.font_size as f64;
+        if self.fit_width.is_some() {
+            self.ops.push(DrawOp::Line(start, stop, look.clone()));
+            return;
+        }
+        self.set_scale(look);
         // Use slope-appropriate character based on line angle
         let line_char = self.get_line_char(start, stop);
+        self.begin_dash(look);
         self.draw_line_segment(start, stop, line_char);
+        self.end_dash();
     }
 
     fn draw_circle(
@@ -521,22 +1487,114 @@ impl RenderBackend for ASCIIWriter {
         look: &StyleAttr,
         _properties: Option<String>,
     ) {
-        self.scale = look.font_size as f64;
+        if self.fit_width.is_some() {
+            self.ops.push(DrawOp::Circle(xy, size, look.clone()));
+            return;
+        }
+        self.set_scale(look);
         // Fill then outline (only in terminal mode).
         if look.fill_color.is_some() && self.is_terminal {
             let fill_color = if self.use_colors {
-                Self::style_color_to_term_color(look.fill_color)
+                self.style_color_to_term_color(look.fill_color)
             } else {
                 None
             };
             self.ellipse_fill(xy, size, '●', fill_color);
         }
         // Always draw outline for circles
+        self.begin_dash(look);
         self.ellipse_outline(xy, size, 'o');
+        self.end_dash();
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        if self.fit_width.is_some() {
+            self.ops
+                .push(DrawOp::Polygon(points.to_vec(), look.clone(), clip));
+            return;
+        }
+        if points.is_empty() {
+            return;
+        }
+        self.set_scale(look);
+        self.active_clip = clip;
+        // Fill then outline (only in terminal mode).
+        if look.fill_color.is_some() && self.is_terminal {
+            let fill_color = if self.use_colors {
+                self.style_color_to_term_color(look.fill_color)
+            } else {
+                None
+            };
+            self.polygon_fill(points, '█', fill_color);
+        }
+        // Each side uses the line glyph that follows its slope.
+        self.begin_dash(look);
+        for i in 0..points.len() {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            let ch = self.get_line_char(a, b);
+            self.draw_line_segment(a, b, ch);
+        }
+        self.end_dash();
+        self.active_clip = None;
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        if self.fit_width.is_some() {
+            self.ops.push(DrawOp::Path(segments.to_vec(), look.clone()));
+            return;
+        }
+        self.set_scale(look);
+        // Flatten the curves into lines that are about one cell long.
+        let steps = segments
+            .iter()
+            .map(|(p0, p1, p2, p3)| {
+                let len = p0.distance_to(*p1)
+                    + p1.distance_to(*p2)
+                    + p2.distance_to(*p3);
+                (len / self.scale).ceil() as usize
+            })
+            .max()
+            .unwrap_or(1);
+        let polylines = flatten_bezier_segments(segments, steps);
+        if look.fill_color.is_some() && self.is_terminal {
+            let fill_color = if self.use_colors {
+                self.style_color_to_term_color(look.fill_color)
+            } else {
+                None
+            };
+            for polyline in polylines.iter() {
+                self.polygon_fill(polyline, '█', fill_color);
+            }
+        }
+        self.begin_dash(look);
+        for polyline in polylines.iter() {
+            for i in 1..polyline.len() {
+                let (a, b) = (polyline[i - 1], polyline[i]);
+                let ch = self.get_line_char(a, b);
+                self.draw_line_segment(a, b, ch);
+            }
+        }
+        self.end_dash();
     }
 
     fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
-        self.scale = look.font_size as f64;
+        if self.fit_width.is_some() {
+            self.ops
+                .push(DrawOp::Text(xy, text.to_string(), look.clone()));
+            return;
+        }
+        self.set_scale(look);
         self.text_at_center(xy, text);
     }
 
@@ -549,7 +1607,18 @@ impl RenderBackend for ASCIIWriter {
         _properties: Option<String>,
         text: &str,
     ) {
-        self.scale = look.font_size as f64;
+        if self.fit_width.is_some() {
+            let op = DrawOp::Arrow(
+                path.to_vec(),
+                dashed,
+                head,
+                look.clone(),
+                text.to_string(),
+            );
+            self.ops.push(op);
+            return;
+        }
+        self.set_scale(look);
         if path.is_empty() {
             return;
         }
@@ -560,24 +1629,28 @@ impl RenderBackend for ASCIIWriter {
             anchors.push(*a);
         }
 
-        // Draw the polyline connecting anchors. If dashed, use '.' else '*'.
-        let seg_char = if dashed { '.' } else { '*' };
-        self.draw_polyline(&anchors, seg_char);
+        // Draw the polyline connecting anchors.
+        self.begin_dash(look);
+        self.draw_edge_polyline(&anchors, dashed, look.penwidth);
+        self.end_dash();
 
         // Draw arrow heads at start/end if requested.
         if anchors.len() >= 2 {
             let n = anchors.len();
+            let (start_kind, end_kind) = look.arrow_heads;
             if head.0 {
                 let dir = anchors[1].sub(anchors[0]);
-                let hc = Self::head_char(dir.x, dir.y);
-                let (ix, iy) = self.to_ixy(anchors[0]);
-                self.set(ix, iy, hc);
+                if let Option::Some(hc) = self.end_char(start_kind, dir.neg()) {
+                    let (ix, iy) = self.to_ixy(anchors[0]);
+                    self.set(ix, iy, hc);
+                }
             }
             if head.1 {
                 let dir = anchors[n - 1].sub(anchors[n - 2]);
-                let hc = Self::head_char(dir.x, dir.y);
-                let (ix, iy) = self.to_ixy(anchors[n - 1]);
-                self.set(ix, iy, hc);
+                if let Option::Some(hc) = self.end_char(end_kind, dir) {
+                    let (ix, iy) = self.to_ixy(anchors[n - 1]);
+                    self.set(ix, iy, hc);
+                }
             }
         }
 
@@ -601,33 +1674,78 @@ impl RenderBackend for ASCIIWriter {
 
 impl ASCIIWriter {
     fn style_color_to_term_color(
+        &self,
         color: Option<crate::core::color::Color>,
     ) -> Option<termcolor::Color> {
-        color.and_then(|c| {
+        color.map(|c| {
             let rgb = Self::extract_rgb_from_color(&c);
+            match self.color_mode {
+                ColorMode::TrueColor => {
+                    return Color::Rgb(rgb.0, rgb.1, rgb.2);
+                }
+                ColorMode::Xterm256 => {
+                    return Color::Ansi256(Self::rgb_to_xterm256(rgb));
+                }
+                ColorMode::Basic8 => {}
+            }
             // Simple color mapping - could be enhanced with better color matching
             if rgb.0 > 128 && rgb.1 < 128 && rgb.2 < 128 {
-                Some(Color::Red)
+                Color::Red
             } else if rgb.0 < 128 && rgb.1 > 128 && rgb.2 < 128 {
-                Some(Color::Green)
+                Color::Green
             } else if rgb.0 < 128 && rgb.1 < 128 && rgb.2 > 128 {
-                Some(Color::Blue)
+                Color::Blue
             } else if rgb.0 > 128 && rgb.1 > 128 && rgb.2 < 128 {
-                Some(Color::Yellow)
+                Color::Yellow
             } else if rgb.0 > 128 && rgb.1 < 128 && rgb.2 > 128 {
-                Some(Color::Magenta)
+                Color::Magenta
             } else if rgb.0 < 128 && rgb.1 > 128 && rgb.2 > 128 {
-                Some(Color::Cyan)
+                Color::Cyan
             } else if rgb.0 > 200 && rgb.1 > 200 && rgb.2 > 200 {
-                Some(Color::White)
+                Color::White
             } else if rgb.0 < 100 && rgb.1 < 100 && rgb.2 < 100 {
-                Some(Color::Black)
+                Color::Black
             } else {
-                Some(Color::White) // Default
+                Color::White // Default
             }
         })
     }
 
+    /// Map an RGB color to the nearest entry in the xterm 256-color palette.
+    /// The palette has a 6x6x6 color cube (16..231) and a 24-step grayscale
+    /// ramp (232..255). Pick whichever of the two is closer.
+    fn rgb_to_xterm256(rgb: (u8, u8, u8)) -> u8 {
+        const LEVELS: [i32; 6] = [0, 95, 135, 175, 215, 255];
+        let to_cube = |v: u8| -> usize {
+            let v = v as i32;
+            let mut best = 0;
+            for (i, l) in LEVELS.iter().enumerate() {
+                if (v - l).abs() < (v - LEVELS[best]).abs() {
+                    best = i;
+                }
+            }
+            best
+        };
+        let dist = |a: (i32, i32, i32)| -> i32 {
+            let (r, g, b) = (rgb.0 as i32, rgb.1 as i32, rgb.2 as i32);
+            (r - a.0).pow(2) + (g - a.1).pow(2) + (b - a.2).pow(2)
+        };
+
+        let (ri, gi, bi) = (to_cube(rgb.0), to_cube(rgb.1), to_cube(rgb.2));
+        let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+        let cube_idx = 16 + 36 * ri + 6 * gi + bi;
+
+        let avg = (rgb.0 as i32 + rgb.1 as i32 + rgb.2 as i32) / 3;
+        let gray_step = ((avg - 8).max(0) / 10).min(23);
+        let gray = 8 + gray_step * 10;
+
+        if dist((gray, gray, gray)) < dist(cube) {
+            (232 + gray_step) as u8
+        } else {
+            cube_idx as u8
+        }
+    }
+
     fn extract_rgb_from_color(
         color: &crate::core::color::Color,
     ) -> (u8, u8, u8) {
@@ -655,7 +1773,7 @@ mod tests {
         let mut terminal_writer = ASCIIWriter::new_with_terminal_setting(true);
         let style = StyleAttr::new(
             Color::fast("black"),
-            2,
+            2.,
             Some(Color::fast("red")),
             0,
             14,
@@ -722,7 +1840,7 @@ mod tests {
         let mut terminal_writer = ASCIIWriter::new_with_terminal_setting(true);
         let style = StyleAttr::new(
             Color::fast("black"),
-            2,
+            2.,
             Some(Color::fast("green")),
             0,
             14,
@@ -767,7 +1885,7 @@ mod tests {
             ASCIIWriter::new_with_color_setting(true, true);
         let style = StyleAttr::new(
             Color::fast("black"),
-            2,
+            2.,
             Some(Color::fast("red")),
             0,
             14,
@@ -811,4 +1929,342 @@ mod tests {
             plain_output
         );
     }
+
+    #[test]
+    fn test_unicode_char_set() {
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        assert_eq!(writer.char_set(), CharSet::Ascii);
+        writer.set_char_set(CharSet::Unicode);
+        let style = StyleAttr::simple();
+
+        // Two boxes that share an edge should merge into junctions.
+        writer.draw_rect(
+            Point::new(0.0, 0.0),
+            Point::new(75.0, 45.0),
+            &style,
+            None,
+            None,
+        );
+        writer.draw_rect(
+            Point::new(0.0, 30.0),
+            Point::new(75.0, 45.0),
+            &style,
+            None,
+            None,
+        );
+        let path = [
+            (Point::new(150.0, 0.0), Point::new(150.0, 0.0)),
+            (Point::new(150.0, 75.0), Point::new(150.0, 75.0)),
+        ];
+        writer.draw_arrow(&path, false, (false, true), &style, None, "");
+
+        let output = writer.finalize();
+        assert!(output.contains('┌'), "{}", output);
+        assert!(output.contains('├'), "{}", output);
+        assert!(output.contains('┤'), "{}", output);
+        assert!(output.contains('▼'), "{}", output);
+        assert!(!output.contains('+'), "{}", output);
+    }
+
+    #[test]
+    fn test_arrow_head_kinds() {
+        let path = [
+            (Point::new(0.0, 0.0), Point::new(0.0, 0.0)),
+            (Point::new(150.0, 0.0), Point::new(150.0, 0.0)),
+        ];
+        let draw = |kinds: (ArrowHeadKind, ArrowHeadKind)| {
+            let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+            let mut style = StyleAttr::simple();
+            style.arrow_heads = kinds;
+            writer.draw_arrow(&path, false, (true, true), &style, None, "");
+            writer.finalize().trim_end().to_string()
+        };
+
+        // The start head points to the start of the line.
+        let output = draw((ArrowHeadKind::Normal, ArrowHeadKind::Normal));
+        assert!(
+            output.starts_with('<') && output.ends_with('>'),
+            "{}",
+            output
+        );
+        let output = draw((ArrowHeadKind::ODot, ArrowHeadKind::Diamond));
+        assert!(
+            output.starts_with('o') && output.ends_with('#'),
+            "{}",
+            output
+        );
+        let output = draw((ArrowHeadKind::Crow, ArrowHeadKind::Tee));
+        assert!(
+            output.starts_with('>') && output.ends_with('|'),
+            "{}",
+            output
+        );
+        let output = draw((ArrowHeadKind::None, ArrowHeadKind::None));
+        assert!(!output.contains(['<', '>']), "{}", output);
+
+        // The Unicode heads of hollow triangles are hollow.
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        writer.set_char_set(CharSet::Unicode);
+        let mut style = StyleAttr::simple();
+        style.arrow_heads = (ArrowHeadKind::ONormal, ArrowHeadKind::ONormal);
+        writer.draw_arrow(&path, false, (true, true), &style, None, "");
+        let output = writer.finalize().trim_end().to_string();
+        assert!(
+            output.starts_with('◁') && output.ends_with('▷'),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_clip_regions() {
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        let style = StyleAttr::simple();
+        let clip =
+            writer.create_clip(Point::new(0.0, 0.0), Point::new(60.0, 60.0), 0);
+        // A wide box that is clipped to the left part of the canvas.
+        writer.draw_rect(
+            Point::new(0.0, 0.0),
+            Point::new(150.0, 60.0),
+            &style,
+            None,
+            Some(clip),
+        );
+        let output = writer.finalize();
+        let widest = output.lines().map(|l| l.chars().count()).max();
+        assert_eq!(widest, Some(4), "{}", output);
+
+        // Rounded clip regions cut the corners of the shape.
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        let clip = writer.create_clip(
+            Point::new(0.0, 0.0),
+            Point::new(150.0, 150.0),
+            60,
+        );
+        writer.draw_rect(
+            Point::new(0.0, 0.0),
+            Point::new(150.0, 150.0),
+            &style,
+            None,
+            Some(clip),
+        );
+        let output = writer.finalize();
+        assert!(!output.starts_with('+'), "{}", output);
+        assert!(output.contains('-'), "{}", output);
+    }
+
+    #[test]
+    fn test_color_modes() {
+        let style = StyleAttr::new(
+            Color::fast("black"),
+            2.,
+            Some(Color::fast("coral")),
+            0,
+            14,
+        );
+        let render = |mode: ColorMode| {
+            let mut writer = ASCIIWriter::new_with_color_setting(true, true);
+            writer.set_color_mode(mode);
+            writer.draw_rect(
+                Point::new(0.0, 0.0),
+                Point::new(56.0, 56.0),
+                &style,
+                None,
+                None,
+            );
+            writer.finalize().trim_end().to_string()
+        };
+
+        let output = render(ColorMode::TrueColor);
+        assert!(output.contains("\x1b[38;2;255;127;80m"), "{}", output);
+        let output = render(ColorMode::Xterm256);
+        assert!(output.contains("\x1b[38;5;209m"), "{}", output);
+
+        assert_eq!(ASCIIWriter::rgb_to_xterm256((0, 0, 0)), 16);
+        assert_eq!(ASCIIWriter::rgb_to_xterm256((255, 255, 255)), 231);
+        assert_eq!(ASCIIWriter::rgb_to_xterm256((128, 128, 128)), 244);
+    }
+
+    #[test]
+    fn test_background_fill_mode() {
+        let mut writer = ASCIIWriter::new_with_color_setting(true, true);
+        writer.set_fill_mode(FillMode::Background);
+        let style = StyleAttr::new(
+            Color::fast("black"),
+            2.,
+            Some(Color::fast("blue")),
+            0,
+            14,
+        );
+        writer.draw_rect(
+            Point::new(0.0, 0.0),
+            Point::new(84.0, 56.0),
+            &style,
+            None,
+            None,
+        );
+        writer.draw_text(Point::new(42.0, 28.0), "hi", &style);
+
+        let output = writer.finalize();
+        assert!(!output.contains('█'), "{}", output);
+        // Blue background.
+        assert!(output.contains("\x1b[44m "), "{:?}", output);
+        // White text on a blue background.
+        assert!(output.contains("\x1b[37;44mhi"), "{:?}", output);
+    }
+
+    #[test]
+    fn test_fixed_canvas() {
+        let mut writer = ASCIIWriter::with_canvas(10, 3);
+        writer.set_use_colors(false);
+        let style = StyleAttr::simple();
+        writer.set_viewport_offset(2, 0);
+        writer.draw_line(
+            Point::new(0.0, 15.0),
+            Point::new(600.0, 15.0),
+            &style,
+            None,
+        );
+        assert_eq!(writer.grid_size(), (10, 3));
+        let output = writer.finalize_plain();
+        assert_eq!(output, "\n----------\n\n");
+    }
+
+    #[test]
+    fn test_fit_to_width() {
+        let style = StyleAttr::simple();
+        let draw = |writer: &mut ASCIIWriter| {
+            writer.draw_rect(
+                Point::new(0.0, 0.0),
+                Point::new(3000.0, 60.0),
+                &style,
+                None,
+                None,
+            );
+        };
+
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        draw(&mut writer);
+        let widest = |s: String| s.lines().map(|l| l.len()).max().unwrap();
+        assert_eq!(widest(writer.finalize()), 200);
+
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        writer.fit_to_width(40);
+        draw(&mut writer);
+        assert!(widest(writer.finalize()) <= 40);
+    }
+
+    #[test]
+    fn test_subcell_render_modes() {
+        let style = StyleAttr::simple();
+        let render = |mode: RenderMode| {
+            let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+            writer.set_render_mode(mode);
+            writer.draw_line(
+                Point::new(0.0, 0.0),
+                Point::new(150.0, 150.0),
+                &style,
+                None,
+            );
+            writer.draw_circle(
+                Point::new(300.0, 75.0),
+                Point::new(120.0, 120.0),
+                &style,
+                None,
+            );
+            writer.finalize().trim_end().to_string()
+        };
+
+        let output = render(RenderMode::Braille);
+        assert!(output
+            .chars()
+            .any(|c| ('\u{2801}'..='\u{28ff}').contains(&c)));
+        assert!(!output.contains('\\'), "{}", output);
+        let output = render(RenderMode::HalfBlock);
+        assert!(output.contains('▀') || output.contains('▄'), "{}", output);
+        assert!(!output.contains('\\'), "{}", output);
+    }
+
+    #[test]
+    fn test_dash_pattern() {
+        let mut look = StyleAttr::simple();
+        look.set_dash(&[15., 15.]);
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        writer.draw_line(
+            Point::new(0.0, 0.0),
+            Point::new(150.0, 0.0),
+            &look,
+            None,
+        );
+        assert_eq!(writer.finalize(), "- - - - - -\n");
+
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        writer.draw_rect(
+            Point::new(0.0, 0.0),
+            Point::new(150.0, 60.0),
+            &look,
+            None,
+            None,
+        );
+        let output = writer.finalize();
+        assert!(output.contains("- -"), "{}", output);
+    }
+
+    #[test]
+    fn test_polygon() {
+        let look = StyleAttr::simple();
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        let diamond = [
+            Point::new(60.0, 0.0),
+            Point::new(120.0, 60.0),
+            Point::new(60.0, 120.0),
+            Point::new(0.0, 60.0),
+        ];
+        writer.draw_polygon(&diamond, &look, None, None);
+        let output = writer.finalize();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 9, "{}", output);
+        assert_eq!(lines[1].trim(), "/ \\", "{}", output);
+        assert_eq!(lines[4].trim_end(), "/       /", "{}", output);
+        assert_eq!(lines[7].trim(), "\\ /", "{}", output);
+    }
+
+    #[test]
+    fn test_bezier_path() {
+        let look = StyleAttr::simple();
+        let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+        // A quarter of a circle, from the top-left to the bottom-right.
+        let segment = (
+            Point::new(0.0, 0.0),
+            Point::new(66.0, 0.0),
+            Point::new(120.0, 54.0),
+            Point::new(120.0, 120.0),
+        );
+        writer.draw_path(&[segment], &look, None);
+        let output = writer.finalize();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 9, "{}", output);
+        assert!(lines[0].starts_with("---"), "{}", output);
+        assert!(lines[8].ends_with('|'), "{}", output);
+    }
+
+    #[test]
+    fn test_heavy_edges() {
+        let path = [
+            (Point::new(0.0, 0.0), Point::new(0.0, 0.0)),
+            (Point::new(150.0, 0.0), Point::new(150.0, 0.0)),
+        ];
+        let draw = |penwidth: f64, char_set: CharSet| {
+            let mut writer = ASCIIWriter::new_with_terminal_setting(false);
+            writer.set_char_set(char_set);
+            let mut style = StyleAttr::simple();
+            style.penwidth = penwidth;
+            writer.draw_arrow(&path, false, (false, false), &style, None, "");
+            writer.finalize()
+        };
+        assert!(draw(1., CharSet::Unicode).contains('─'));
+        assert!(draw(2.5, CharSet::Unicode).contains('━'));
+        assert!(draw(1., CharSet::Ascii).contains('*'));
+        assert!(draw(3., CharSet::Ascii).contains('#'));
+    }
 }
@@ -0,0 +1,462 @@
+//! EPS rendering backend that writes the draw calls as Encapsulated
+//! PostScript, for publishing toolchains that can't import SVG or PDF.
+//!
+//! The bounding box grows to fit the shapes, like the SVG backend. Text uses
+//! the standard Times-Roman font and is centered with `stringwidth`, so the
+//! document does not need any font metrics or font data.
+//!
+//! ```
+//! # use layout::backends::eps::EPSWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut eps = EPSWriter::new();
+//! let look = StyleAttr::simple();
+//! eps.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! eps.draw_text(Point::new(50., 50.), "a", &look);
+//! assert!(eps.finalize().starts_with("%!PS-Adobe-3.0 EPSF-3.0"));
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::{draw_arrow_heads, ClipHandle, RenderBackend};
+use crate::core::geometry::{cubic_bezier, get_bezier_segments, Point};
+use crate::core::style::StyleAttr;
+use crate::core::utils::format_number as num;
+
+// Procedures that the content of the document uses:
+// x y w h r rrect - : the path of a box with rounded corners.
+// cx cy rx ry ellipse - : the path of an ellipse.
+// x y size (text) ctext - : show the text centered at x, with the baseline y.
+static EPS_PROLOG: &str = "/LayoutDict 16 dict def
+LayoutDict begin
+/rrect {
+  /r exch def /h exch def /w exch def /y exch def /x exch def
+  newpath x r add y moveto
+  x w add y x w add y h add r arcto 4 {pop} repeat
+  x w add y h add x y h add r arcto 4 {pop} repeat
+  x y h add x y r arcto 4 {pop} repeat
+  x y x w add y r arcto 4 {pop} repeat
+  closepath
+} def
+/ellipse {
+  matrix currentmatrix 5 1 roll
+  newpath 4 2 roll translate scale
+  0 0 1 0 360 arc closepath setmatrix
+} def
+/ctext {
+  exch /Times-Roman findfont exch scalefont setfont
+  3 1 roll moveto
+  gsave 1 -1 scale dup stringwidth pop 2 div neg 0 rmoveto show grestore
+} def
+end
+";
+
+/// Escape \p text as a PostScript string. Characters that are not ASCII are
+/// replaced, because the standard fonts don't use the Latin-1 encoding.
+fn escape_string(text: &str) -> String {
+    let mut res = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                res.push('\\');
+                res.push(c);
+            }
+            ' '..='~' => res.push(c),
+            _ => res.push('?'),
+        }
+    }
+    res
+}
+
+#[derive(Debug)]
+pub struct EPSWriter {
+    // The body of the document, in the coordinate system of the SVG backend
+    // (the y axis points down).
+    content: String,
+    view_size: Point,
+    // A list of clip regions: (top-left, size, rounded_px).
+    clips: Vec<(Point, Point, usize)>,
+    // The color of the background.
+    background: Color,
+}
+
+impl EPSWriter {
+    pub fn new() -> EPSWriter {
+        EPSWriter {
+            content: String::new(),
+            view_size: Point::zero(),
+            clips: Vec::new(),
+            background: Color::fast("white"),
+        }
+    }
+
+    /// \returns the content of the EPS file.
+    pub fn finalize(&self) -> String {
+        let (width, height) = (self.view_size.x, self.view_size.y);
+        let mut result = String::new();
+        result.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+        result.push_str(&format!(
+            "%%BoundingBox: 0 0 {} {}\n",
+            width.ceil(),
+            height.ceil()
+        ));
+        result.push_str(&format!(
+            "%%HiResBoundingBox: 0 0 {} {}\n",
+            num(width),
+            num(height)
+        ));
+        result.push_str("%%Creator: layout-rs\n");
+        result.push_str("%%LanguageLevel: 2\n");
+        result.push_str("%%EndComments\n");
+        result.push_str("%%BeginProlog\n");
+        result.push_str(EPS_PROLOG);
+        result.push_str("%%EndProlog\n");
+        result.push_str("LayoutDict begin\ngsave\n");
+        // Flip the y axis, and paint the background. Transparent backgrounds
+        // are not painted.
+        result.push_str(&format!("0 {} translate 1 -1 scale\n", num(height)));
+        let (r, g, b, a) = self.background.to_rgba();
+        if a != 0 {
+            result.push_str(&format!(
+                "{} {} {} setrgbcolor 0 0 {} {} rectfill\n",
+                num(r as f64 / 255.),
+                num(g as f64 / 255.),
+                num(b as f64 / 255.),
+                num(width),
+                num(height)
+            ));
+        }
+        result.push_str(&self.content);
+        result.push_str("grestore\nend\nshowpage\n%%EOF\n");
+        result
+    }
+}
+
+impl Default for EPSWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EPSWriter {
+    // Grow the bounding box to include the point \p point plus some offset
+    // \p size.
+    fn grow_window(&mut self, point: Point, size: Point) {
+        self.view_size.x = self.view_size.x.max(point.x + size.x + 5.);
+        self.view_size.y = self.view_size.y.max(point.y + size.y + 5.);
+    }
+
+    // Set the current color to \p color.
+    fn set_color(&mut self, color: Color) {
+        let (r, g, b, _) = color.to_rgba();
+        self.content.push_str(&format!(
+            "{} {} {} setrgbcolor\n",
+            num(r as f64 / 255.),
+            num(g as f64 / 255.),
+            num(b as f64 / 255.)
+        ));
+    }
+
+    // Fill and stroke the path that \p path emits, according to \p look.
+    // PostScript has no transparency, so fully transparent fills are skipped
+    // and all other colors are opaque.
+    fn paint_path<F: Fn(&mut Self)>(&mut self, look: &StyleAttr, path: F) {
+        if let Some(fill) = look.fill_color {
+            if fill.to_rgba().3 != 0 {
+                self.content.push_str("gsave\n");
+                self.set_color(fill);
+                path(self);
+                self.content.push_str("fill\ngrestore\n");
+            }
+        }
+        if look.penwidth > 0. {
+            let dash: Vec<String> = look.dash.iter().map(|x| num(*x)).collect();
+            self.content.push_str("gsave\n");
+            self.set_color(look.line_color);
+            self.content.push_str(&format!(
+                "{} setlinewidth [{}] 0 setdash\n",
+                num(look.penwidth),
+                dash.join(" ")
+            ));
+            path(self);
+            self.content.push_str("stroke\ngrestore\n");
+        }
+    }
+
+    // Draw the lines of \p text centered at \p xy.
+    fn draw_centered_text(&mut self, xy: Point, text: &str, font_size: usize) {
+        let lines: Vec<&str> = text.lines().collect();
+        let height = (lines.len() * font_size) as f64;
+        self.content.push_str("gsave\n0 0 0 setrgbcolor\n");
+        for (i, line) in lines.iter().enumerate() {
+            let top = xy.y - height / 2. + (i * font_size) as f64;
+            let baseline = top + font_size as f64 * 0.8;
+            self.grow_window(Point::new(xy.x, top), Point::zero());
+            self.content.push_str(&format!(
+                "{} {} {} ({}) ctext\n",
+                num(xy.x),
+                num(baseline),
+                font_size,
+                escape_string(line)
+            ));
+        }
+        self.content.push_str("grestore\n");
+    }
+}
+
+impl RenderBackend for EPSWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.grow_window(xy, size);
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str(&format!(
+                "gsave\n{} {} {} {} {} rrect clip newpath\n",
+                num(xy.x),
+                num(xy.y),
+                num(size.x),
+                num(size.y),
+                rounded_px
+            ));
+        }
+        let r = (look.rounded as f64).min(size.x / 2.).min(size.y / 2.);
+        self.paint_path(look, |eps| {
+            eps.content.push_str(&format!(
+                "{} {} {} {} {} rrect\n",
+                num(xy.x),
+                num(xy.y),
+                num(size.x),
+                num(size.y),
+                num(r)
+            ));
+        });
+        if clip.is_some() {
+            self.content.push_str("grestore\n");
+        }
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(start, Point::zero());
+        self.grow_window(stop, Point::zero());
+        let mut look = look.clone();
+        look.fill_color = None;
+        self.paint_path(&look, |eps| {
+            eps.content.push_str(&format!(
+                "newpath {} {} moveto {} {} lineto\n",
+                num(start.x),
+                num(start.y),
+                num(stop.x),
+                num(stop.y)
+            ));
+        });
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(xy, size);
+        if size.x <= 0. || size.y <= 0. {
+            return;
+        }
+        self.paint_path(look, |eps| {
+            eps.content.push_str(&format!(
+                "{} {} {} {} ellipse\n",
+                num(xy.x),
+                num(xy.y),
+                num(size.x / 2.),
+                num(size.y / 2.)
+            ));
+        });
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        for point in points {
+            self.grow_window(*point, Point::zero());
+        }
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str(&format!(
+                "gsave\n{} {} {} {} {} rrect clip newpath\n",
+                num(xy.x),
+                num(xy.y),
+                num(size.x),
+                num(size.y),
+                rounded_px
+            ));
+        }
+        self.paint_path(look, |eps| {
+            let mut path = String::from("newpath");
+            for (i, p) in points.iter().enumerate() {
+                let op = if i == 0 { "moveto" } else { "lineto" };
+                path.push_str(&format!(" {} {} {}", num(p.x), num(p.y), op));
+            }
+            path.push_str(" closepath\n");
+            eps.content.push_str(&path);
+        });
+        if clip.is_some() {
+            self.content.push_str("grestore\n");
+        }
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        for (p0, p1, p2, p3) in segments.iter() {
+            for p in [p0, p1, p2, p3] {
+                self.grow_window(*p, Point::zero());
+            }
+        }
+        self.paint_path(look, |eps| {
+            let mut path = String::from("newpath\n");
+            let mut last: Option<Point> = None;
+            for (p0, p1, p2, p3) in segments.iter() {
+                if last != Some(*p0) {
+                    path.push_str(&format!(
+                        "{} {} moveto\n",
+                        num(p0.x),
+                        num(p0.y)
+                    ));
+                }
+                path.push_str(&format!(
+                    "{} {} {} {} {} {} curveto\n",
+                    num(p1.x),
+                    num(p1.y),
+                    num(p2.x),
+                    num(p2.y),
+                    num(p3.x),
+                    num(p3.y)
+                ));
+                last = Some(*p3);
+            }
+            eps.content.push_str(&path);
+        });
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.draw_centered_text(xy, text, look.font_size);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        _properties: Option<String>,
+        text: &str,
+    ) {
+        let segments = get_bezier_segments(path);
+        if segments.is_empty() {
+            return;
+        }
+        for point in path {
+            self.grow_window(point.0, Point::zero());
+            self.grow_window(point.1, Point::zero());
+        }
+
+        let mut look = look.clone();
+        look.fill_color = None;
+        if dashed && !look.is_dashed() {
+            look.set_dash(&[5., 5.]);
+        }
+        self.paint_path(&look, |eps| {
+            let start = segments[0].0;
+            let mut path =
+                format!("newpath {} {} moveto\n", num(start.x), num(start.y));
+            for (_, c1, c2, end) in segments.iter() {
+                path.push_str(&format!(
+                    "{} {} {} {} {} {} curveto\n",
+                    num(c1.x),
+                    num(c1.y),
+                    num(c2.x),
+                    num(c2.y),
+                    num(end.x),
+                    num(end.y)
+                ));
+            }
+            eps.content.push_str(&path);
+        });
+
+        draw_arrow_heads(self, path, head, &look);
+
+        if !text.is_empty() {
+            // Place the label above the middle of the path.
+            let (p0, p1, p2, p3) = segments[segments.len() / 2];
+            let t = if segments.len() % 2 == 1 { 0.5 } else { 0. };
+            let mid = cubic_bezier(p0, p1, p2, p3, t);
+            let offset = Point::new(0., look.font_size as f64 / 2.);
+            self.draw_centered_text(mid.sub(offset), text, look.font_size);
+        }
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.background = color;
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.grow_window(xy, Point::zero());
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        let handle = self.clips.len();
+        self.clips.push((xy, size, rounded_px));
+        handle
+    }
+}
+
+#[test]
+fn test_eps_writer() {
+    assert_eq!(escape_string("a(b)\\é"), "a\\(b\\)\\\\?");
+
+    let mut eps = EPSWriter::new();
+    let mut look = StyleAttr::simple();
+    look.set_dash(&[5., 2.5]);
+    eps.draw_rect(
+        Point::new(10., 10.),
+        Point::new(40., 20.5),
+        &look,
+        None,
+        None,
+    );
+    eps.draw_text(Point::new(30., 20.), "hello", &look);
+    let output = eps.finalize();
+    assert!(output.contains("%%BoundingBox: 0 0 55 36\n"));
+    assert!(output.contains("%%HiResBoundingBox: 0 0 55 35.5\n"));
+    assert!(output.contains("2 setlinewidth [5 2.5] 0 setdash\n"));
+    assert!(output.contains("10 10 40 20.5 0 rrect\n"));
+    assert!(output.contains("(hello) ctext\n"));
+    assert!(output.ends_with("showpage\n%%EOF\n"));
+}
@@ -0,0 +1,300 @@
+//! HTML rendering backend that embeds the SVG output in a self-contained web
+//! page, with a small script for exploring large graphs.
+//!
+//! The page supports:
+//! - Panning by dragging, and zooming with the mouse wheel.
+//! - Tooltips on hover, from the DOT `tooltip` attribute.
+//! - Clicking a node to highlight its incident edges (click the background to
+//!   clear), and double-clicking a node or edge to open its `URL`/`href`.
+//!
+//! The script finds the nodes and edges by the `data-node`, `data-from` and
+//! `data-to` properties that the GraphBuilder attaches to the group of each
+//! node and edge.
+//!
+//! ```
+//! # use layout::backends::html::HTMLWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut html = HTMLWriter::new();
+//! let look = StyleAttr::simple();
+//! html.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! assert!(html.finalize().starts_with("<!DOCTYPE html>"));
+//! ```
+
+use super::svg::SVGWriter;
+use crate::core::color::Color;
+use crate::core::format::{ClipHandle, RenderBackend, Stylesheet};
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+use crate::core::utils::escape_xml;
+
+static HTML_STYLE: &str = r#"<style>
+html, body { margin: 0; height: 100%; overflow: hidden; }
+#graph { width: 100%; height: 100%; cursor: grab; }
+#graph svg { width: 100%; height: 100%; }
+#graph.dragging { cursor: grabbing; }
+#graph .dimmed { opacity: 0.2; }
+#graph .highlight path { stroke: #ff7f00; stroke-width: 3; }
+#tooltip { position: fixed; display: none; pointer-events: none;
+  padding: 4px 8px; background: #ffffe0; border: 1px solid #888;
+  font: 13px sans-serif; white-space: pre; }
+</style>"#;
+
+static HTML_SCRIPT: &str = r#"<script>
+(function () {
+  const root = document.getElementById("graph");
+  const svg = root.querySelector("svg");
+  const tooltip = document.getElementById("tooltip");
+  const box = svg.viewBox.baseVal;
+  svg.removeAttribute("width");
+  svg.removeAttribute("height");
+
+  // Zoom around the mouse cursor.
+  root.addEventListener("wheel", function (e) {
+    e.preventDefault();
+    const scale = e.deltaY < 0 ? 0.9 : 1.1;
+    const rect = svg.getBoundingClientRect();
+    const x = box.x + (e.clientX - rect.left) / rect.width * box.width;
+    const y = box.y + (e.clientY - rect.top) / rect.height * box.height;
+    box.x = x - (x - box.x) * scale;
+    box.y = y - (y - box.y) * scale;
+    box.width *= scale;
+    box.height *= scale;
+  }, { passive: false });
+
+  // Pan by dragging.
+  let drag = null;
+  root.addEventListener("mousedown", function (e) {
+    drag = { x: e.clientX, y: e.clientY, moved: false };
+    root.classList.add("dragging");
+  });
+  window.addEventListener("mousemove", function (e) {
+    if (!drag) return;
+    const rect = svg.getBoundingClientRect();
+    box.x -= (e.clientX - drag.x) / rect.width * box.width;
+    box.y -= (e.clientY - drag.y) / rect.height * box.height;
+    drag.moved = drag.moved || e.clientX !== drag.x || e.clientY !== drag.y;
+    drag.x = e.clientX;
+    drag.y = e.clientY;
+  });
+  window.addEventListener("mouseup", function () {
+    root.classList.remove("dragging");
+    setTimeout(function () { drag = null; });
+  });
+
+  // Show the tooltips.
+  svg.querySelectorAll("[data-tooltip]").forEach(function (g) {
+    g.addEventListener("mousemove", function (e) {
+      tooltip.textContent = g.getAttribute("data-tooltip");
+      tooltip.style.display = "block";
+      tooltip.style.left = e.clientX + 12 + "px";
+      tooltip.style.top = e.clientY + 12 + "px";
+    });
+    g.addEventListener("mouseleave", function () {
+      tooltip.style.display = "none";
+    });
+  });
+
+  // Highlight the edges of the node that was clicked.
+  const shapes = svg.querySelectorAll("[data-node], [data-from]");
+  function highlight(name) {
+    shapes.forEach(function (g) {
+      const incident = g.getAttribute("data-from") === name ||
+        g.getAttribute("data-to") === name;
+      const selected = incident || g.getAttribute("data-node") === name;
+      g.classList.toggle("highlight", name !== null && incident);
+      g.classList.toggle("dimmed", name !== null && !selected);
+    });
+  }
+  svg.addEventListener("click", function (e) {
+    if (drag && drag.moved) return;
+    const node = e.target.closest("[data-node]");
+    highlight(node ? node.getAttribute("data-node") : null);
+  });
+  svg.addEventListener("dblclick", function (e) {
+    const g = e.target.closest("[data-url]");
+    if (g) window.open(g.getAttribute("data-url"), "_blank");
+  });
+})();
+</script>"#;
+
+/// A backend that renders the graph into an interactive HTML page.
+#[derive(Debug)]
+pub struct HTMLWriter {
+    svg: SVGWriter,
+    title: String,
+    // The linked stylesheets, that the page loads in its head.
+    stylesheets: Vec<String>,
+}
+
+impl HTMLWriter {
+    pub fn new() -> HTMLWriter {
+        HTMLWriter {
+            svg: SVGWriter::new(),
+            title: String::from("Graph"),
+            stylesheets: Vec::new(),
+        }
+    }
+
+    /// Set the title of the page.
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    /// \returns the content of the HTML page.
+    pub fn finalize(&self) -> String {
+        let svg = self.svg.finalize();
+        // Drop the XML declaration, which is not allowed in HTML.
+        let svg = match svg.find("<svg") {
+            Some(idx) => &svg[idx..],
+            None => &svg[..],
+        };
+
+        let mut result = String::new();
+        result.push_str("<!DOCTYPE html>\n<html>\n<head>\n");
+        result.push_str("<meta charset=\"utf-8\">\n");
+        result
+            .push_str(&format!("<title>{}</title>\n", escape_xml(&self.title)));
+        result.push_str(HTML_STYLE);
+        for href in &self.stylesheets {
+            result.push_str(&format!(
+                "\n<link rel=\"stylesheet\" href=\"{}\">",
+                escape_xml(href)
+            ));
+        }
+        result.push_str("\n</head>\n<body>\n<div id=\"graph\">\n");
+        result.push_str(svg);
+        result.push_str("\n</div>\n<div id=\"tooltip\"></div>\n");
+        result.push_str(HTML_SCRIPT);
+        result.push_str("\n</body>\n</html>\n");
+        result
+    }
+}
+
+impl Default for HTMLWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderBackend for HTMLWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.svg.draw_rect(xy, size, look, properties, clip);
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_line(start, stop, look, properties);
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_circle(xy, size, look, properties);
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.svg.draw_polygon(points, look, properties, clip);
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_path(segments, look, properties);
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.svg.draw_text(xy, text, look);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        properties: Option<String>,
+        text: &str,
+    ) {
+        self.svg
+            .draw_arrow(path, dashed, head, look, properties, text);
+    }
+
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_image(xy, size, href, properties);
+    }
+
+    fn begin_group(&mut self, properties: &str) {
+        self.svg.begin_group(properties);
+    }
+
+    fn end_group(&mut self) {
+        self.svg.end_group();
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        self.svg.begin_layer(name);
+    }
+
+    fn end_layer(&mut self) {
+        self.svg.end_layer();
+    }
+
+    fn add_stylesheet(&mut self, sheet: &Stylesheet) {
+        // The SVG keeps the inline rules. The XML declarations of the linked
+        // sheets are dropped with the prologue, so the page links them.
+        if let Stylesheet::Link(href) = sheet {
+            self.stylesheets.push(href.clone());
+        }
+        self.svg.add_stylesheet(sheet);
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.svg.set_background(color);
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.svg.grow_canvas(xy);
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        self.svg.create_clip(xy, size, rounded_px)
+    }
+}
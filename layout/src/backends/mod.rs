@@ -1,3 +1,15 @@
 //! Defines and keeps the implementation of the rendering backends.
+pub mod animation;
 pub mod ascii_art;
+mod bitmap_font;
+pub mod eps;
+pub mod html;
+pub mod pdf;
+pub mod png;
+pub mod recording;
+pub mod scaled;
 pub mod svg;
+pub mod terminal;
+pub mod tikz;
+pub mod tiles;
+pub mod xdot;
@@ -0,0 +1,649 @@
+//! PDF rendering backend that writes the draw calls as vector operators into
+//! the content stream of a single-page PDF document.
+//!
+//! The page grows to fit the shapes, like the SVG backend. Text uses the
+//! standard Times-Roman font, which every PDF reader provides, so the document
+//! does not need to embed any font data.
+//!
+//! ```
+//! # use layout::backends::pdf::PDFWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut pdf = PDFWriter::new();
+//! let look = StyleAttr::simple();
+//! pdf.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! pdf.draw_text(Point::new(50., 50.), "a", &look);
+//! let bytes: Vec<u8> = pdf.finalize();
+//! assert!(bytes.starts_with(b"%PDF-1.4"));
+//! ```
+
+use crate::backends::tiles::TileConfig;
+use crate::core::color::Color;
+use crate::core::format::{draw_arrow_heads, ClipHandle, RenderBackend};
+use crate::core::geometry::{cubic_bezier, get_bezier_segments, Point};
+use crate::core::style::StyleAttr;
+use crate::core::text::TIMES_WIDTHS;
+use crate::core::utils::format_number as num;
+
+/// The distance of the bezier control points that approximate a quarter of a
+/// circle with the radius 1.
+const KAPPA: f64 = 0.552_284_8;
+
+/// \returns the width of \p text in the Times-Roman font of size \p font_size.
+fn text_width(text: &str, font_size: usize) -> f64 {
+    let mut width = 0;
+    for ch in text.chars() {
+        let idx = ch as usize;
+        width += if (32..127).contains(&idx) {
+            TIMES_WIDTHS[idx - 32] as usize
+        } else {
+            500
+        };
+    }
+    (width * font_size) as f64 / 1000.
+}
+
+/// Escape \p text as a PDF string in the WinAnsi encoding.
+fn escape_string(text: &str) -> String {
+    let mut res = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                res.push('\\');
+                res.push(c);
+            }
+            ' '..='~' => res.push(c),
+            // Latin-1 characters have the same code in WinAnsi.
+            '\u{a0}'..='\u{ff}' => res.push_str(&format!("\\{:o}", c as u32)),
+            _ => res.push('?'),
+        }
+    }
+    res
+}
+
+/// A page of the document: the top-left corner and the size of the part of
+/// the drawing that it shows, and the crop marks on it.
+type Page = (Point, Point, Vec<(Point, Point)>);
+
+#[derive(Debug)]
+pub struct PDFWriter {
+    // The content stream of the page, in the coordinate system of the SVG
+    // backend (the y axis points down).
+    content: String,
+    view_size: Point,
+    // A list of clip regions: (top-left, size, rounded_px).
+    clips: Vec<(Point, Point, usize)>,
+    // The alpha values that have a graphics state in the page resources.
+    alphas: Vec<u8>,
+    // The color of the background.
+    background: Color,
+}
+
+impl PDFWriter {
+    pub fn new() -> PDFWriter {
+        PDFWriter {
+            content: String::new(),
+            view_size: Point::zero(),
+            clips: Vec::new(),
+            alphas: Vec::new(),
+            background: Color::fast("white"),
+        }
+    }
+
+    /// Build the PDF document. \returns the content of the PDF file.
+    pub fn finalize(&self) -> Vec<u8> {
+        self.build(&[(Point::zero(), self.view_size, Vec::new())])
+    }
+
+    /// Build a PDF document with a page for each part of the drawing, that
+    /// starts at the corners \p tiles and has the size and the crop marks of
+    /// \p config. \returns the content of the PDF file.
+    pub fn finalize_tiles(
+        &self,
+        config: &TileConfig,
+        tiles: &[Point],
+    ) -> Vec<u8> {
+        let pages: Vec<_> = tiles
+            .iter()
+            .map(|tile| {
+                let marks = if config.crop_marks {
+                    config.get_crop_marks(*tile)
+                } else {
+                    Vec::new()
+                };
+                (*tile, config.page, marks)
+            })
+            .collect();
+        self.build(&pages)
+    }
+
+    // \returns the content stream of the page that shows the part of the
+    // drawing that starts at \p origin and has the size \p size, with the
+    // lines \p marks on top.
+    fn page_stream(
+        &self,
+        origin: Point,
+        size: Point,
+        marks: &[(Point, Point)],
+    ) -> Vec<u8> {
+        // Flip the y axis and move the origin, and paint the background.
+        // Transparent backgrounds are not painted.
+        let mut stream = format!(
+            "1 0 0 -1 {} {} cm\n",
+            num(-origin.x),
+            num(size.y + origin.y)
+        );
+        let (r, g, b, a) = self.background.to_rgba();
+        if a != 0 {
+            stream.push_str(&format!(
+                "{} {} {} rg\n{} {} {} {} re f\n",
+                num(r as f64 / 255.),
+                num(g as f64 / 255.),
+                num(b as f64 / 255.),
+                num(origin.x),
+                num(origin.y),
+                num(size.x),
+                num(size.y)
+            ));
+        }
+        if marks.is_empty() {
+            stream.push_str(&self.content);
+        } else {
+            stream.push_str(&format!("q\n{}Q\n0 0 0 RG 0.5 w\n", self.content));
+            for (start, stop) in marks {
+                stream.push_str(&format!(
+                    "{} {} m {} {} l S\n",
+                    num(start.x),
+                    num(start.y),
+                    num(stop.x),
+                    num(stop.y)
+                ));
+            }
+        }
+        miniz_oxide::deflate::compress_to_vec_zlib(stream.as_bytes(), 6)
+    }
+
+    // Build a document with a page for each (origin, size, marks) entry of
+    // \p pages. \returns the content of the PDF file.
+    fn build(&self, pages: &[Page]) -> Vec<u8> {
+        let mut states = String::new();
+        for alpha in self.alphas.iter() {
+            let a = num(*alpha as f64 / 255.);
+            states
+                .push_str(&format!("/GS{} << /ca {} /CA {} >> ", alpha, a, a));
+        }
+
+        // The catalog and the page tree come first, then the pages and their
+        // content streams, and the font is last.
+        let font = 3 + 2 * pages.len();
+        let kids: Vec<String> = (0..pages.len())
+            .map(|i| format!("{} 0 R", 3 + 2 * i))
+            .collect();
+        let mut objects: Vec<(String, Option<Vec<u8>>)> = vec![
+            ("<< /Type /Catalog /Pages 2 0 R >>".to_string(), None),
+            (
+                format!(
+                    "<< /Type /Pages /Kids [{}] /Count {} >>",
+                    kids.join(" "),
+                    pages.len()
+                ),
+                None,
+            ),
+        ];
+        for (i, (origin, size, marks)) in pages.iter().enumerate() {
+            let page = format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                /Contents {} 0 R /Resources << /Font << /F1 {} 0 R >> \
+                /ExtGState << {}>> >> >>",
+                num(size.x),
+                num(size.y),
+                4 + 2 * i,
+                font,
+                states
+            );
+            objects.push((page, None));
+            let stream = self.page_stream(*origin, *size, marks);
+            let header = format!(
+                "<< /Length {} /Filter /FlateDecode >>\nstream\n",
+                stream.len()
+            );
+            objects.push((header, Some(stream)));
+        }
+        objects.push((
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Times-Roman \
+            /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            None,
+        ));
+
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, (object, stream)) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object.as_bytes());
+            if let Option::Some(stream) = stream {
+                pdf.extend_from_slice(stream);
+                pdf.extend_from_slice(b"\nendstream");
+            }
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n", objects.len() + 1);
+        trailer.push_str("0000000000 65535 f \n");
+        for offset in offsets {
+            trailer.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        trailer.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        ));
+        pdf.extend_from_slice(trailer.as_bytes());
+        pdf
+    }
+}
+
+impl Default for PDFWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PDFWriter {
+    // Grow the page to include the point \p point plus some offset \p size.
+    fn grow_window(&mut self, point: Point, size: Point) {
+        self.view_size.x = self.view_size.x.max(point.x + size.x + 5.);
+        self.view_size.y = self.view_size.y.max(point.y + size.y + 5.);
+    }
+
+    // Select the graphics state that draws with the alpha of \p color. Opaque
+    // colors use the default state.
+    fn set_alpha(&mut self, color: Color) {
+        let alpha = color.to_rgba().3;
+        if alpha == 255 {
+            return;
+        }
+        if !self.alphas.contains(&alpha) {
+            self.alphas.push(alpha);
+        }
+        self.content.push_str(&format!("/GS{} gs\n", alpha));
+    }
+
+    // Set the stroke color, width and dash pattern of \p look.
+    fn set_stroke(&mut self, look: &StyleAttr) {
+        let (r, g, b, _) = look.line_color.to_rgba();
+        let dash: Vec<String> = look.dash.iter().map(|x| num(*x)).collect();
+        self.content.push_str(&format!(
+            "{} {} {} RG\n{} w\n[{}] 0 d\n",
+            num(r as f64 / 255.),
+            num(g as f64 / 255.),
+            num(b as f64 / 255.),
+            num(look.penwidth),
+            dash.join(" ")
+        ));
+    }
+
+    // Set the fill color to \p color.
+    fn set_fill(&mut self, color: Color) {
+        let (r, g, b, _) = color.to_rgba();
+        self.content.push_str(&format!(
+            "{} {} {} rg\n",
+            num(r as f64 / 255.),
+            num(g as f64 / 255.),
+            num(b as f64 / 255.)
+        ));
+    }
+
+    // Emit the path of a box with rounded corners of radius \p r.
+    fn rect_path(&mut self, xy: Point, size: Point, r: f64) {
+        let r = r.min(size.x / 2.).min(size.y / 2.).max(0.);
+        if r == 0. {
+            self.content.push_str(&format!(
+                "{} {} {} {} re\n",
+                num(xy.x),
+                num(xy.y),
+                num(size.x),
+                num(size.y)
+            ));
+            return;
+        }
+        let (x0, y0) = (xy.x, xy.y);
+        let (x1, y1) = (xy.x + size.x, xy.y + size.y);
+        let k = r * (1. - KAPPA);
+        let p = |x: f64, y: f64| format!("{} {}", num(x), num(y));
+        let path = format!(
+            "{} m\n{} l\n{} {} {} c\n{} l\n{} {} {} c\n{} l\n{} {} {} c\n\
+            {} l\n{} {} {} c\nh\n",
+            p(x0 + r, y0),
+            p(x1 - r, y0),
+            p(x1 - k, y0),
+            p(x1, y0 + k),
+            p(x1, y0 + r),
+            p(x1, y1 - r),
+            p(x1, y1 - k),
+            p(x1 - k, y1),
+            p(x1 - r, y1),
+            p(x0 + r, y1),
+            p(x0 + k, y1),
+            p(x0, y1 - k),
+            p(x0, y1 - r),
+            p(x0, y0 + r),
+            p(x0, y0 + k),
+            p(x0 + k, y0),
+            p(x0 + r, y0),
+        );
+        self.content.push_str(&path);
+    }
+
+    // Emit the path of an ellipse with the center \p xy and the radii \p r.
+    fn ellipse_path(&mut self, xy: Point, r: Point) {
+        let (kx, ky) = (r.x * KAPPA, r.y * KAPPA);
+        let p = |x: f64, y: f64| format!("{} {}", num(xy.x + x), num(xy.y + y));
+        let path = format!(
+            "{} m\n{} {} {} c\n{} {} {} c\n{} {} {} c\n{} {} {} c\nh\n",
+            p(r.x, 0.),
+            p(r.x, ky),
+            p(kx, r.y),
+            p(0., r.y),
+            p(-kx, r.y),
+            p(-r.x, ky),
+            p(-r.x, 0.),
+            p(-r.x, -ky),
+            p(-kx, -r.y),
+            p(0., -r.y),
+            p(kx, -r.y),
+            p(r.x, -ky),
+            p(r.x, 0.),
+        );
+        self.content.push_str(&path);
+    }
+
+    // Fill and stroke the path that \p path emits, according to \p look.
+    fn paint_path<F: Fn(&mut Self)>(&mut self, look: &StyleAttr, path: F) {
+        if let Some(fill) = look.fill_color {
+            if fill.to_rgba().3 != 0 {
+                self.content.push_str("q\n");
+                self.set_alpha(fill);
+                self.set_fill(fill);
+                path(self);
+                self.content.push_str("f\nQ\n");
+            }
+        }
+        if look.penwidth > 0. {
+            self.content.push_str("q\n");
+            self.set_alpha(look.line_color);
+            self.set_stroke(look);
+            path(self);
+            self.content.push_str("S\nQ\n");
+        }
+    }
+
+    // Draw the lines of \p text centered at \p xy.
+    fn draw_centered_text(&mut self, xy: Point, text: &str, font_size: usize) {
+        let lines: Vec<&str> = text.lines().collect();
+        let height = (lines.len() * font_size) as f64;
+        self.content.push_str("q\n0 0 0 rg\nBT\n");
+        for (i, line) in lines.iter().enumerate() {
+            let width = text_width(line, font_size);
+            // Place the baseline so that the line is centered vertically. The
+            // text matrix flips the y axis back, so the glyphs are upright.
+            let top = xy.y - height / 2. + (i * font_size) as f64;
+            let baseline = top + font_size as f64 * 0.8;
+            let x = xy.x - width / 2.;
+            self.grow_window(Point::new(x, top), Point::new(width, 0.));
+            self.content.push_str(&format!(
+                "/F1 {} Tf\n1 0 0 -1 {} {} Tm\n({}) Tj\n",
+                font_size,
+                num(x),
+                num(baseline),
+                escape_string(line)
+            ));
+        }
+        self.content.push_str("ET\nQ\n");
+    }
+}
+
+impl RenderBackend for PDFWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.grow_window(xy, size);
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str("q\n");
+            self.rect_path(xy, size, rounded_px as f64);
+            self.content.push_str("W n\n");
+        }
+        let rounded = look.rounded as f64;
+        self.paint_path(look, |pdf| pdf.rect_path(xy, size, rounded));
+        if clip.is_some() {
+            self.content.push_str("Q\n");
+        }
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(start, Point::zero());
+        self.grow_window(stop, Point::zero());
+        let mut look = look.clone();
+        look.fill_color = None;
+        self.paint_path(&look, |pdf| {
+            pdf.content.push_str(&format!(
+                "{} {} m\n{} {} l\n",
+                num(start.x),
+                num(start.y),
+                num(stop.x),
+                num(stop.y)
+            ));
+        });
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(xy, size);
+        let radii = size.scale(0.5);
+        self.paint_path(look, |pdf| pdf.ellipse_path(xy, radii));
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        for point in points {
+            self.grow_window(*point, Point::zero());
+        }
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str("q\n");
+            self.rect_path(xy, size, rounded_px as f64);
+            self.content.push_str("W n\n");
+        }
+        self.paint_path(look, |pdf| {
+            let mut path = String::new();
+            for (i, p) in points.iter().enumerate() {
+                let op = if i == 0 { "m" } else { "l" };
+                path.push_str(&format!("{} {} {}\n", num(p.x), num(p.y), op));
+            }
+            path.push_str("h\n");
+            pdf.content.push_str(&path);
+        });
+        if clip.is_some() {
+            self.content.push_str("Q\n");
+        }
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        for (p0, p1, p2, p3) in segments.iter() {
+            for p in [p0, p1, p2, p3] {
+                self.grow_window(*p, Point::zero());
+            }
+        }
+        self.paint_path(look, |pdf| {
+            let mut path = String::new();
+            let mut last: Option<Point> = None;
+            for (p0, p1, p2, p3) in segments.iter() {
+                if last != Some(*p0) {
+                    path.push_str(&format!("{} {} m\n", num(p0.x), num(p0.y)));
+                }
+                path.push_str(&format!(
+                    "{} {} {} {} {} {} c\n",
+                    num(p1.x),
+                    num(p1.y),
+                    num(p2.x),
+                    num(p2.y),
+                    num(p3.x),
+                    num(p3.y)
+                ));
+                last = Some(*p3);
+            }
+            pdf.content.push_str(&path);
+        });
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.draw_centered_text(xy, text, look.font_size);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        _properties: Option<String>,
+        text: &str,
+    ) {
+        let segments = get_bezier_segments(path);
+        if segments.is_empty() {
+            return;
+        }
+        for point in path {
+            self.grow_window(point.0, Point::zero());
+            self.grow_window(point.1, Point::zero());
+        }
+
+        let mut look = look.clone();
+        look.fill_color = None;
+        if dashed && !look.is_dashed() {
+            look.set_dash(&[5., 5.]);
+        }
+        self.paint_path(&look, |pdf| {
+            let start = segments[0].0;
+            let mut path = format!("{} {} m\n", num(start.x), num(start.y));
+            for (_, c1, c2, end) in segments.iter() {
+                path.push_str(&format!(
+                    "{} {} {} {} {} {} c\n",
+                    num(c1.x),
+                    num(c1.y),
+                    num(c2.x),
+                    num(c2.y),
+                    num(end.x),
+                    num(end.y)
+                ));
+            }
+            pdf.content.push_str(&path);
+        });
+
+        draw_arrow_heads(self, path, head, &look);
+
+        if !text.is_empty() {
+            // Place the label above the middle of the path.
+            let (p0, p1, p2, p3) = segments[segments.len() / 2];
+            let t = if segments.len() % 2 == 1 { 0.5 } else { 0. };
+            let mid = cubic_bezier(p0, p1, p2, p3, t);
+            let offset = Point::new(0., look.font_size as f64 / 2.);
+            self.draw_centered_text(mid.sub(offset), text, look.font_size);
+        }
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.background = color;
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.grow_window(xy, Point::zero());
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        let handle = self.clips.len();
+        self.clips.push((xy, size, rounded_px));
+        handle
+    }
+}
+
+#[test]
+fn test_pdf_writer() {
+    assert_eq!(escape_string("a(b)\\é"), "a\\(b\\)\\\\\\351");
+    assert_eq!(text_width("ab", 10), 9.44);
+
+    let mut pdf = PDFWriter::new();
+    let mut look = StyleAttr::simple();
+    look.fill_color = Some(Color::new(0xff000080));
+    pdf.draw_rect(
+        Point::new(10., 10.),
+        Point::new(40., 20.),
+        &look,
+        None,
+        None,
+    );
+    pdf.draw_text(Point::new(30., 20.), "hello", &look);
+    assert_eq!(pdf.alphas, vec![0x80]);
+    assert!(pdf.content.contains("10 10 40 20 re\n"));
+    assert!(pdf.content.contains("(hello) Tj"));
+
+    let bytes = pdf.finalize();
+    let find = |pattern: &[u8]| {
+        bytes
+            .windows(pattern.len())
+            .rposition(|x| x == pattern)
+            .unwrap()
+    };
+    let tail = String::from_utf8_lossy(&bytes[find(b"trailer")..]);
+    assert!(tail.ends_with("%%EOF\n"));
+    find(b"/MediaBox [0 0 55 35]");
+    find(b"/GS128 << /ca 0.502 /CA 0.502 >>");
+
+    // Check that the cross reference table points to the objects.
+    let xref = find(b"xref\n0 6");
+    let table = String::from_utf8_lossy(&bytes[xref..]);
+    assert!(tail.contains(&format!("startxref\n{}\n", xref)));
+    for (i, line) in table.lines().skip(3).take(5).enumerate() {
+        let offset: usize = line[..10].parse().unwrap();
+        let header = format!("{} 0 obj", i + 1);
+        assert!(bytes[offset..].starts_with(header.as_bytes()));
+    }
+}
@@ -0,0 +1,657 @@
+//! PNG rendering backend that rasterizes the draw calls into an RGBA pixel
+//! buffer and encodes it as a PNG image.
+//!
+//! The writer follows the conventions of the SVG backend: the image grows to
+//! fit the shapes that are drawn, the background is white, and arrows are
+//! cubic splines with a triangle head. Shapes are anti-aliased, and text is
+//! drawn with a built-in bitmap font that is scaled to the font size.
+//!
+//! ```
+//! # use layout::backends::png::PNGWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut png = PNGWriter::new();
+//! let look = StyleAttr::simple();
+//! png.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! png.draw_text(Point::new(50., 50.), "a", &look);
+//! let bytes: Vec<u8> = png.finalize();
+//! assert_eq!(&bytes[1..4], b"PNG");
+//! ```
+
+use super::bitmap_font;
+use crate::core::color::Color;
+use crate::core::format::{draw_arrow_heads, ClipHandle, RenderBackend};
+use crate::core::geometry::{
+    cubic_bezier, flatten_bezier_segments, get_bezier_segments,
+    point_in_polygon, Point,
+};
+use crate::core::style::StyleAttr;
+
+/// The number of straight segments that approximate each spline segment.
+const SPLINE_STEPS: usize = 16;
+
+#[derive(Debug)]
+pub struct PNGWriter {
+    // RGBA pixels, row by row.
+    pixels: Vec<[u8; 4]>,
+    width: usize,
+    height: usize,
+    // A list of clip regions: (top-left, size, rounded_px).
+    clips: Vec<(Point, Point, usize)>,
+    // The clip that is applied to the shape that is being drawn.
+    active_clip: Option<ClipHandle>,
+    // The color of the pixels that nothing is drawn on.
+    background: [u8; 4],
+}
+
+impl PNGWriter {
+    pub fn new() -> PNGWriter {
+        PNGWriter {
+            pixels: Vec::new(),
+            width: 0,
+            height: 0,
+            clips: Vec::new(),
+            active_clip: None,
+            background: [255, 255, 255, 255],
+        }
+    }
+
+    /// \returns the size of the image in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// \returns the color of the pixel at \p x, \p y as RGBA.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<(u8, u8, u8, u8)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let [r, g, b, a] = self.pixels[y * self.width + x];
+        Some((r, g, b, a))
+    }
+
+    /// Encode the image as PNG. \returns the content of the PNG file.
+    pub fn finalize(&self) -> Vec<u8> {
+        let (width, height) = (self.width.max(1), self.height.max(1));
+        // Each row starts with the filter type (0 = none).
+        let mut raw = Vec::with_capacity(height * (width * 4 + 1));
+        for y in 0..height {
+            raw.push(0);
+            for x in 0..width {
+                match self.pixel(x, y) {
+                    Some((r, g, b, a)) => raw.extend_from_slice(&[r, g, b, a]),
+                    None => raw.extend_from_slice(&self.background),
+                }
+            }
+        }
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+        // Bit depth 8, color type RGBA, default compression, filter and no
+        // interlacing.
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        let idat = miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6);
+        write_chunk(&mut png, b"IDAT", &idat);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+impl Default for PNGWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append a PNG chunk with the type \p kind and the payload \p data to \p out.
+pub(crate) fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// \returns the CRC-32 checksum (as used by PNG) of \p data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffff_u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+/// \returns the signed distance from \p p to the edge of a box with the
+/// center \p center, half size \p half and rounded corners of radius \p r.
+fn rounded_box_distance(p: Point, center: Point, half: Point, r: f64) -> f64 {
+    let r = r.min(half.x).min(half.y).max(0.);
+    let qx = (p.x - center.x).abs() - half.x + r;
+    let qy = (p.y - center.y).abs() - half.y + r;
+    let outside = Point::new(qx.max(0.), qy.max(0.)).length();
+    outside + qx.max(qy).min(0.) - r
+}
+
+/// \returns the approximate signed distance from \p p to the edge of an
+/// ellipse with the center \p center and the radii \p radii.
+fn ellipse_distance(p: Point, center: Point, radii: Point) -> f64 {
+    let (dx, dy) = (p.x - center.x, p.y - center.y);
+    let k0 = ((dx / radii.x).powi(2) + (dy / radii.y).powi(2)).sqrt();
+    let k1 = ((dx / radii.x.powi(2)).powi(2) + (dy / radii.y.powi(2)).powi(2))
+        .sqrt();
+    if k1 == 0. {
+        return -radii.x.min(radii.y);
+    }
+    k0 * (k0 - 1.) / k1
+}
+
+/// \returns the distance from \p p to the segment \p a - \p b.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b.sub(a);
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    if len2 == 0. {
+        return p.distance_to(a);
+    }
+    let ap = p.sub(a);
+    let t = ((ap.x * ab.x + ap.y * ab.y) / len2).clamp(0., 1.);
+    p.distance_to(a.add(ab.scale(t)))
+}
+
+/// Split the polyline \p points into the segments that are drawn by the
+/// on/off dash lengths in \p dash.
+fn dash_polyline(points: &[Point], dash: &[f64]) -> Vec<(Point, Point)> {
+    let mut res = Vec::new();
+    let total: f64 = dash.iter().sum();
+    if dash.is_empty() || total <= 0. {
+        for seg in points.windows(2) {
+            res.push((seg[0], seg[1]));
+        }
+        return res;
+    }
+
+    // The index of the current dash entry, and the length that is left in it.
+    let mut idx = 0;
+    let mut left = dash[0];
+    for seg in points.windows(2) {
+        let len = seg[0].distance_to(seg[1]);
+        let mut pos = 0.;
+        while pos < len {
+            let step = left.min(len - pos);
+            if idx % 2 == 0 && step > 0. {
+                let from = seg[0].add(seg[1].sub(seg[0]).scale(pos / len));
+                let to =
+                    seg[0].add(seg[1].sub(seg[0]).scale((pos + step) / len));
+                res.push((from, to));
+            }
+            pos += step;
+            left -= step;
+            if left <= 0. {
+                idx = (idx + 1) % dash.len();
+                left = dash[idx];
+            }
+        }
+    }
+    res
+}
+
+impl PNGWriter {
+    // Grow the image to include the point \p point plus some offset \p size.
+    fn grow_window(&mut self, point: Point, size: Point) {
+        let width = (point.x + size.x + 5.).ceil().max(0.) as usize;
+        let height = (point.y + size.y + 5.).ceil().max(0.) as usize;
+        if width <= self.width && height <= self.height {
+            return;
+        }
+        let width = width.max(self.width);
+        let height = height.max(self.height);
+        let mut pixels = vec![self.background; width * height];
+        for y in 0..self.height {
+            let src = &self.pixels[y * self.width..(y + 1) * self.width];
+            pixels[y * width..y * width + self.width].copy_from_slice(src);
+        }
+        self.pixels = pixels;
+        self.width = width;
+        self.height = height;
+    }
+
+    fn is_in_clip(&self, p: Point) -> bool {
+        let (xy, size, rounded_px) = match self.active_clip {
+            Some(handle) => match self.clips.get(handle) {
+                Some(clip) => *clip,
+                None => return true,
+            },
+            None => return true,
+        };
+        let half = size.scale(0.5);
+        rounded_box_distance(p, xy.add(half), half, rounded_px as f64) <= 0.
+    }
+
+    /// Blend \p color into the pixel at \p x, \p y. The \p coverage is the
+    /// fraction of the pixel that the shape covers.
+    fn blend(&mut self, x: isize, y: isize, color: Color, coverage: f64) {
+        if x < 0 || y < 0 || coverage <= 0. {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let center = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+        if !self.is_in_clip(center) {
+            return;
+        }
+        let (r, g, b, a) = color.to_rgba();
+        let alpha = coverage.min(1.) * a as f64 / 255.;
+        if alpha <= 0. {
+            return;
+        }
+        // Composite the color over the pixel, with non-premultiplied alpha.
+        let pixel = &mut self.pixels[y * self.width + x];
+        let dest_alpha = pixel[3] as f64 / 255.;
+        let res_alpha = alpha + dest_alpha * (1. - alpha);
+        for (channel, value) in pixel.iter_mut().zip([r, g, b]) {
+            let mixed = value as f64 * alpha
+                + *channel as f64 * dest_alpha * (1. - alpha);
+            *channel = (mixed / res_alpha).round() as u8;
+        }
+        pixel[3] = (res_alpha * 255.).round() as u8;
+    }
+
+    /// Paint the pixels in the box \p from .. \p to with the coverage that
+    /// \p coverage computes for the center of each pixel.
+    fn paint<F: Fn(Point) -> f64>(
+        &mut self,
+        from: Point,
+        to: Point,
+        color: Color,
+        coverage: F,
+    ) {
+        let x0 = from.x.floor() as isize - 1;
+        let y0 = from.y.floor() as isize - 1;
+        let x1 = to.x.ceil() as isize + 1;
+        let y1 = to.y.ceil() as isize + 1;
+        for y in y0.max(0)..=y1 {
+            for x in x0.max(0)..=x1 {
+                let p = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+                self.blend(x, y, color, coverage(p));
+            }
+        }
+    }
+
+    /// Fill or stroke the shape that the signed distance function \p sdf
+    /// describes, within the bounding box \p from .. \p to.
+    fn draw_sdf<F: Fn(Point) -> f64>(
+        &mut self,
+        from: Point,
+        to: Point,
+        look: &StyleAttr,
+        sdf: F,
+    ) {
+        if let Some(fill) = look.fill_color {
+            self.paint(from, to, fill, |p| (0.5 - sdf(p)).clamp(0., 1.));
+        }
+        if look.is_dashed() {
+            // Dashed outlines are traced as polylines by the callers.
+            return;
+        }
+        let half_width = look.penwidth / 2.;
+        let pad = Point::splat(half_width);
+        self.paint(from.sub(pad), to.add(pad), look.line_color, |p| {
+            (half_width + 0.5 - sdf(p).abs()).clamp(0., 1.)
+        });
+    }
+
+    /// Stroke the polyline \p points with the line width and the dash
+    /// pattern of \p look.
+    fn stroke_polyline(&mut self, points: &[Point], look: &StyleAttr) {
+        let half_width = (look.penwidth / 2.).max(0.5);
+        for (a, b) in dash_polyline(points, &look.dash) {
+            let from = Point::new(a.x.min(b.x), a.y.min(b.y));
+            let to = Point::new(a.x.max(b.x), a.y.max(b.y));
+            let pad = Point::splat(half_width);
+            self.paint(from.sub(pad), to.add(pad), look.line_color, |p| {
+                (half_width + 0.5 - segment_distance(p, a, b)).clamp(0., 1.)
+            });
+        }
+    }
+
+    /// Fill the polygon \p points, using 4x4 samples per pixel.
+    fn fill_polygon(&mut self, points: &[Point], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let mut from = points[0];
+        let mut to = points[0];
+        for p in points {
+            from = Point::new(from.x.min(p.x), from.y.min(p.y));
+            to = Point::new(to.x.max(p.x), to.y.max(p.y));
+        }
+        self.paint(from, to, color, |p| {
+            let mut hits = 0;
+            for i in 0..4 {
+                for j in 0..4 {
+                    let dx = (i as f64 + 0.5) / 4. - 0.5;
+                    let dy = (j as f64 + 0.5) / 4. - 0.5;
+                    let sample = Point::new(p.x + dx, p.y + dy);
+                    if point_in_polygon(sample, points) {
+                        hits += 1;
+                    }
+                }
+            }
+            hits as f64 / 16.
+        });
+    }
+
+    /// Draw the text \p text with the top-left corner at \p xy. Each glyph is
+    /// scaled to \p font_size and sampled with 3x3 samples per pixel.
+    fn draw_string(&mut self, xy: Point, text: &str, font_size: usize) {
+        let scale = font_size as f64 / bitmap_font::GLYPH_HEIGHT as f64;
+        let advance = bitmap_font::GLYPH_WIDTH as f64 * scale;
+        let height = font_size as f64;
+        let color = Color::fast("black");
+        for (i, ch) in text.chars().enumerate() {
+            if ch == ' ' {
+                continue;
+            }
+            let left = xy.x + i as f64 * advance;
+            let from = Point::new(left, xy.y);
+            let to = Point::new(left + advance, xy.y + height);
+            self.paint(from, to, color, |p| {
+                let mut hits = 0;
+                for sx in 0..3 {
+                    for sy in 0..3 {
+                        let px = p.x - 0.5 + (sx as f64 + 0.5) / 3. - left;
+                        let py = p.y - 0.5 + (sy as f64 + 0.5) / 3. - xy.y;
+                        if px < 0. || py < 0. {
+                            continue;
+                        }
+                        let gx = (px / scale) as usize;
+                        let gy = (py / scale) as usize;
+                        if bitmap_font::is_set(ch, gx, gy) {
+                            hits += 1;
+                        }
+                    }
+                }
+                hits as f64 / 9.
+            });
+        }
+    }
+
+    /// Draw the lines of \p text centered at \p xy.
+    fn draw_centered_text(&mut self, xy: Point, text: &str, font_size: usize) {
+        let scale = font_size as f64 / bitmap_font::GLYPH_HEIGHT as f64;
+        let advance = bitmap_font::GLYPH_WIDTH as f64 * scale;
+        let lines: Vec<&str> = text.lines().collect();
+        let height = (lines.len() * font_size) as f64;
+        for (i, line) in lines.iter().enumerate() {
+            let width = line.chars().count() as f64 * advance;
+            let top = xy.y - height / 2. + (i * font_size) as f64;
+            let top_left = Point::new(xy.x - width / 2., top);
+            self.grow_window(top_left, Point::new(width, font_size as f64));
+            self.draw_string(top_left, line, font_size);
+        }
+    }
+}
+
+impl RenderBackend for PNGWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.grow_window(xy, size);
+        self.active_clip = clip;
+        let half = size.scale(0.5);
+        let center = xy.add(half);
+        let rounded = look.rounded as f64;
+        self.draw_sdf(xy, xy.add(size), look, |p| {
+            rounded_box_distance(p, center, half, rounded)
+        });
+        if look.is_dashed() {
+            let corners = [
+                xy,
+                Point::new(xy.x + size.x, xy.y),
+                xy.add(size),
+                Point::new(xy.x, xy.y + size.y),
+                xy,
+            ];
+            self.stroke_polyline(&corners, look);
+        }
+        self.active_clip = None;
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(start, Point::zero());
+        self.grow_window(stop, Point::zero());
+        self.stroke_polyline(&[start, stop], look);
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.grow_window(xy, size);
+        let radii = size.scale(0.5);
+        if radii.x <= 0. || radii.y <= 0. {
+            return;
+        }
+        self.draw_sdf(xy.sub(radii), xy.add(radii), look, |p| {
+            ellipse_distance(p, xy, radii)
+        });
+        if look.is_dashed() {
+            let steps = 64;
+            let outline: Vec<Point> = (0..=steps)
+                .map(|i| {
+                    let t = i as f64 / steps as f64 * std::f64::consts::TAU;
+                    Point::new(
+                        xy.x + radii.x * t.cos(),
+                        xy.y + radii.y * t.sin(),
+                    )
+                })
+                .collect();
+            self.stroke_polyline(&outline, look);
+        }
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        for point in points {
+            self.grow_window(*point, Point::zero());
+        }
+        self.active_clip = clip;
+        if let Some(fill) = look.fill_color {
+            self.fill_polygon(points, fill);
+        }
+        let mut outline = points.to_vec();
+        outline.push(points[0]);
+        self.stroke_polyline(&outline, look);
+        self.active_clip = None;
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        for (p0, p1, p2, p3) in segments.iter() {
+            for p in [p0, p1, p2, p3] {
+                self.grow_window(*p, Point::zero());
+            }
+        }
+        let polylines = flatten_bezier_segments(segments, SPLINE_STEPS);
+        if let Some(fill) = look.fill_color {
+            for polyline in polylines.iter() {
+                self.fill_polygon(polyline, fill);
+            }
+        }
+        for polyline in polylines.iter() {
+            self.stroke_polyline(polyline, look);
+        }
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.draw_centered_text(xy, text, look.font_size);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        _properties: Option<String>,
+        text: &str,
+    ) {
+        if path.len() < 2 {
+            return;
+        }
+        for point in path {
+            self.grow_window(point.0, Point::zero());
+            self.grow_window(point.1, Point::zero());
+        }
+
+        // Flatten the spline into short segments.
+        let mut points = vec![path[0].0];
+        let segments = get_bezier_segments(path);
+        for (p0, p1, p2, p3) in segments.iter() {
+            for i in 1..=SPLINE_STEPS {
+                let t = i as f64 / SPLINE_STEPS as f64;
+                points.push(cubic_bezier(*p0, *p1, *p2, *p3, t));
+            }
+        }
+
+        let mut look = look.clone();
+        if dashed && !look.is_dashed() {
+            look.set_dash(&[5., 5.]);
+        }
+        self.stroke_polyline(&points, &look);
+
+        draw_arrow_heads(self, path, head, &look);
+
+        if !text.is_empty() {
+            // Place the label above the middle of the path.
+            let mid = points[points.len() / 2];
+            let offset = Point::new(0., look.font_size as f64 / 2.);
+            self.draw_centered_text(mid.sub(offset), text, look.font_size);
+        }
+    }
+
+    fn set_background(&mut self, color: Color) {
+        let (r, g, b, a) = color.to_rgba();
+        let background = [r, g, b, a];
+        for pixel in self.pixels.iter_mut() {
+            if *pixel == self.background {
+                *pixel = background;
+            }
+        }
+        self.background = background;
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.grow_window(xy, Point::zero());
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        let handle = self.clips.len();
+        self.clips.push((xy, size, rounded_px));
+        handle
+    }
+}
+
+#[test]
+fn test_png_writer() {
+    let mut png = PNGWriter::new();
+    let look = StyleAttr::simple();
+    png.draw_rect(
+        Point::new(10., 10.),
+        Point::new(40., 20.),
+        &look,
+        None,
+        None,
+    );
+    png.draw_line(Point::new(0., 60.), Point::new(50., 60.), &look, None);
+    assert_eq!(png.size(), (55, 65));
+    // The outline, the fill and the background.
+    assert_eq!(png.pixel(10, 20), Some((0, 0, 0, 255)));
+    assert_eq!(png.pixel(30, 20), Some((255, 255, 255, 255)));
+    assert_eq!(png.pixel(30, 60), Some((0, 0, 0, 255)));
+    assert_eq!(png.pixel(30, 40), Some((255, 255, 255, 255)));
+
+    let bytes = png.finalize();
+    assert_eq!(
+        &bytes[..8],
+        &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]
+    );
+    assert_eq!(&bytes[12..16], b"IHDR");
+    assert_eq!(&bytes[bytes.len() - 8..bytes.len() - 4], b"IEND");
+    // The CRC of the empty IEND chunk is a well known constant.
+    assert_eq!(&bytes[bytes.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+}
+
+#[test]
+fn test_png_alpha_blending() {
+    let mut png = PNGWriter::new();
+    let mut look = StyleAttr::simple();
+    look.fill_color = Some(Color::new(0xff000080));
+    look.line_color = Color::transparent();
+    let (xy, size) = (Point::new(10., 10.), Point::new(20., 20.));
+    png.draw_rect(xy, size, &look, None, None);
+    // Half of the red is mixed into the white background.
+    assert_eq!(png.pixel(20, 20), Some((255, 127, 127, 255)));
+    // The transparent outline leaves the background as is.
+    assert_eq!(png.pixel(5, 5), Some((255, 255, 255, 255)));
+
+    // A second layer composites over the first one.
+    look.fill_color = Some(Color::new(0x0000ff80));
+    png.draw_rect(xy, size, &look, None, None);
+    assert_eq!(png.pixel(20, 20), Some((127, 63, 191, 255)));
+}
+
+#[test]
+fn test_png_background() {
+    let mut png = PNGWriter::new();
+    png.set_background(Color::fast("transparent"));
+    let mut look = StyleAttr::simple();
+    look.fill_color = Some(Color::new(0xff000080));
+    look.line_color = Color::transparent();
+    let (xy, size) = (Point::new(10., 10.), Point::new(20., 20.));
+    png.draw_rect(xy, size, &look, None, None);
+    // Translucent shapes over a transparent background stay translucent.
+    assert_eq!(png.pixel(5, 5), Some((0, 0, 0, 0)));
+    assert_eq!(png.pixel(20, 20), Some((255, 0, 0, 128)));
+}
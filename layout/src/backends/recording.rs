@@ -0,0 +1,459 @@
+//! A backend that records the draw calls into a display list.
+//!
+//! The display list can be replayed into any other backend without running
+//! the layout again, compared with the list of another render, or handed to
+//! a custom renderer. With the `serde` feature the commands can be serialized.
+//!
+//! ```
+//! # use layout::backends::recording::RecordingBackend;
+//! # use layout::backends::svg::SVGWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut rec = RecordingBackend::new();
+//! let look = StyleAttr::simple();
+//! rec.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! assert_eq!(rec.commands().len(), 1);
+//!
+//! let mut svg = SVGWriter::new();
+//! rec.replay(&mut svg);
+//! assert!(svg.finalize().contains("<ellipse"));
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::{ClipHandle, Link, RenderBackend, Stylesheet};
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+
+/// A single call to one of the methods of RenderBackend.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawCommand {
+    Rect {
+        xy: Point,
+        size: Point,
+        look: StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    },
+    Line {
+        start: Point,
+        stop: Point,
+        look: StyleAttr,
+        properties: Option<String>,
+    },
+    Circle {
+        xy: Point,
+        size: Point,
+        look: StyleAttr,
+        properties: Option<String>,
+    },
+    Polygon {
+        points: Vec<Point>,
+        look: StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    },
+    Path {
+        segments: Vec<(Point, Point, Point, Point)>,
+        look: StyleAttr,
+        properties: Option<String>,
+    },
+    Text {
+        xy: Point,
+        text: String,
+        look: StyleAttr,
+    },
+    Arrow {
+        path: Vec<(Point, Point)>,
+        dashed: bool,
+        head: (bool, bool),
+        look: StyleAttr,
+        properties: Option<String>,
+        text: String,
+    },
+    Image {
+        xy: Point,
+        size: Point,
+        href: String,
+        properties: Option<String>,
+    },
+    BeginLink {
+        link: Link,
+    },
+    EndLink,
+    BeginGroup {
+        properties: String,
+    },
+    EndGroup,
+    BeginLayer {
+        name: String,
+    },
+    EndLayer,
+    /// Creates the clip region with the handle that is the number of clip
+    /// commands that came before it.
+    Clip {
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    },
+    /// Paints the background of the canvas.
+    Background(Color),
+    /// Grows the canvas to contain the point.
+    GrowCanvas(Point),
+    /// Adds a stylesheet to the document.
+    AddStylesheet(Stylesheet),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordingBackend {
+    commands: Vec<DrawCommand>,
+    num_clips: usize,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a recording from the display list \p commands.
+    pub fn from_commands(commands: Vec<DrawCommand>) -> Self {
+        let num_clips = commands
+            .iter()
+            .filter(|cmd| matches!(cmd, DrawCommand::Clip { .. }))
+            .count();
+        Self {
+            commands,
+            num_clips,
+        }
+    }
+
+    /// \returns the recorded display list.
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    /// \returns the recorded display list, and consumes the recording.
+    pub fn into_commands(self) -> Vec<DrawCommand> {
+        self.commands
+    }
+
+    /// Replay the recorded draw calls into \p backend. Clip handles are
+    /// translated to the handles that \p backend returns.
+    pub fn replay(&self, backend: &mut dyn RenderBackend) {
+        let mut clips: Vec<ClipHandle> = Vec::new();
+        for cmd in &self.commands {
+            match cmd {
+                DrawCommand::Rect {
+                    xy,
+                    size,
+                    look,
+                    properties,
+                    clip,
+                } => {
+                    let clip =
+                        clip.and_then(|handle| clips.get(handle).cloned());
+                    backend.draw_rect(
+                        *xy,
+                        *size,
+                        look,
+                        properties.clone(),
+                        clip,
+                    )
+                }
+                DrawCommand::Line {
+                    start,
+                    stop,
+                    look,
+                    properties,
+                } => backend.draw_line(*start, *stop, look, properties.clone()),
+                DrawCommand::Circle {
+                    xy,
+                    size,
+                    look,
+                    properties,
+                } => backend.draw_circle(*xy, *size, look, properties.clone()),
+                DrawCommand::Polygon {
+                    points,
+                    look,
+                    properties,
+                    clip,
+                } => {
+                    let clip =
+                        clip.and_then(|handle| clips.get(handle).cloned());
+                    backend.draw_polygon(points, look, properties.clone(), clip)
+                }
+                DrawCommand::Path {
+                    segments,
+                    look,
+                    properties,
+                } => backend.draw_path(segments, look, properties.clone()),
+                DrawCommand::Text { xy, text, look } => {
+                    backend.draw_text(*xy, text, look)
+                }
+                DrawCommand::Arrow {
+                    path,
+                    dashed,
+                    head,
+                    look,
+                    properties,
+                    text,
+                } => backend.draw_arrow(
+                    path,
+                    *dashed,
+                    *head,
+                    look,
+                    properties.clone(),
+                    text,
+                ),
+                DrawCommand::Image {
+                    xy,
+                    size,
+                    href,
+                    properties,
+                } => backend.draw_image(*xy, *size, href, properties.clone()),
+                DrawCommand::BeginLink { link } => backend.begin_link(link),
+                DrawCommand::EndLink => backend.end_link(),
+                DrawCommand::BeginGroup { properties } => {
+                    backend.begin_group(properties)
+                }
+                DrawCommand::EndGroup => backend.end_group(),
+                DrawCommand::BeginLayer { name } => backend.begin_layer(name),
+                DrawCommand::EndLayer => backend.end_layer(),
+                DrawCommand::Clip {
+                    xy,
+                    size,
+                    rounded_px,
+                } => clips.push(backend.create_clip(*xy, *size, *rounded_px)),
+                DrawCommand::Background(color) => {
+                    backend.set_background(*color)
+                }
+                DrawCommand::GrowCanvas(xy) => backend.grow_canvas(*xy),
+                DrawCommand::AddStylesheet(sheet) => {
+                    backend.add_stylesheet(sheet)
+                }
+            }
+        }
+    }
+
+    /// Compare this recording with \p other. \returns the indices of the
+    /// commands that are different, or that only exist in one of the lists.
+    pub fn diff(&self, other: &RecordingBackend) -> Vec<usize> {
+        let len = self.commands.len().max(other.commands.len());
+        (0..len)
+            .filter(|i| self.commands.get(*i) != other.commands.get(*i))
+            .collect()
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.commands.push(DrawCommand::Rect {
+            xy,
+            size,
+            look: look.clone(),
+            properties,
+            clip,
+        });
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.commands.push(DrawCommand::Line {
+            start,
+            stop,
+            look: look.clone(),
+            properties,
+        });
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.commands.push(DrawCommand::Circle {
+            xy,
+            size,
+            look: look.clone(),
+            properties,
+        });
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.commands.push(DrawCommand::Polygon {
+            points: points.to_vec(),
+            look: look.clone(),
+            properties,
+            clip,
+        });
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.commands.push(DrawCommand::Path {
+            segments: segments.to_vec(),
+            look: look.clone(),
+            properties,
+        });
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.commands.push(DrawCommand::Text {
+            xy,
+            text: text.to_string(),
+            look: look.clone(),
+        });
+    }
+
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        self.commands.push(DrawCommand::Image {
+            xy,
+            size,
+            href: href.to_string(),
+            properties,
+        });
+    }
+
+    fn begin_link(&mut self, link: &Link) {
+        self.commands
+            .push(DrawCommand::BeginLink { link: link.clone() });
+    }
+
+    fn end_link(&mut self) {
+        self.commands.push(DrawCommand::EndLink);
+    }
+
+    fn begin_group(&mut self, properties: &str) {
+        self.commands.push(DrawCommand::BeginGroup {
+            properties: properties.into(),
+        });
+    }
+
+    fn end_group(&mut self) {
+        self.commands.push(DrawCommand::EndGroup);
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        self.commands
+            .push(DrawCommand::BeginLayer { name: name.into() });
+    }
+
+    fn end_layer(&mut self) {
+        self.commands.push(DrawCommand::EndLayer);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        properties: Option<String>,
+        text: &str,
+    ) {
+        self.commands.push(DrawCommand::Arrow {
+            path: path.to_vec(),
+            dashed,
+            head,
+            look: look.clone(),
+            properties,
+            text: text.to_string(),
+        });
+    }
+
+    fn add_stylesheet(&mut self, sheet: &Stylesheet) {
+        self.commands
+            .push(DrawCommand::AddStylesheet(sheet.clone()));
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.commands.push(DrawCommand::Background(color));
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.commands.push(DrawCommand::GrowCanvas(xy));
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        self.commands.push(DrawCommand::Clip {
+            xy,
+            size,
+            rounded_px,
+        });
+        self.num_clips += 1;
+        self.num_clips - 1
+    }
+}
+
+#[test]
+fn test_recording_backend() {
+    let look = StyleAttr::simple();
+    let mut rec = RecordingBackend::new();
+    let clip = rec.create_clip(Point::new(0., 0.), Point::new(10., 10.), 2);
+    rec.draw_rect(
+        Point::new(1., 1.),
+        Point::new(5., 5.),
+        &look,
+        None,
+        Some(clip),
+    );
+    rec.draw_text(Point::new(3., 3.), "a", &look);
+
+    // Replaying into another recording reproduces the display list.
+    let mut copy = RecordingBackend::new();
+    rec.replay(&mut copy);
+    assert_eq!(copy, rec);
+    assert!(rec.diff(&copy).is_empty());
+
+    let mut other = RecordingBackend::from_commands(rec.commands().to_vec());
+    other.draw_text(Point::new(3., 3.), "b", &look);
+    assert_eq!(rec.diff(&other), vec![3]);
+    assert_eq!(other.create_clip(Point::zero(), Point::zero(), 0), 1);
+
+    // The background is replayed too.
+    rec.set_background(Color::fast("navy"));
+    rec.grow_canvas(Point::new(400., 300.));
+    rec.add_stylesheet(&Stylesheet::Link("style.css".to_string()));
+    let mut svg = crate::backends::svg::SVGWriter::new();
+    rec.replay(&mut svg);
+    let content = svg.finalize();
+    assert!(content.contains("#000080"));
+    assert!(content.contains("viewBox=\"0 0 405 305\""));
+    assert!(content.contains("<?xml-stylesheet href=\"style.css\""));
+}
@@ -0,0 +1,381 @@
+//! A backend that scales the drawing by a constant factor, and forwards the
+//! draw calls to another backend. The coordinates, the sizes, the widths of
+//! the lines and the sizes of the fonts are scaled, so the drawing looks the
+//! same at a different size. The backend can also flip and move the drawing
+//! with a Transform, to match the coordinates of the application that shows
+//! it.
+//!
+//! ```
+//! # use layout::backends::scaled::ScaledBackend;
+//! # use layout::backends::svg::SVGWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut svg = SVGWriter::new();
+//! let mut scaled = ScaledBackend::new(&mut svg, 2.);
+//! let look = StyleAttr::simple();
+//! scaled.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! assert!(svg.finalize().contains("cx=\"100\""));
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::{ClipHandle, Link, RenderBackend, Stylesheet};
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+use std::fmt;
+
+/// The number of points in an inch. The layout is measured in points.
+pub const POINTS_PER_INCH: f64 = 72.;
+
+/// Maps the coordinates of the layout to the coordinates of the output. The
+/// drawing is scaled, then flipped, and then moved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub scale: f64,
+    /// The resolution of the output, in dots per inch. The drawing is scaled
+    /// by dpi / 72, so 96 DPI draws it in CSS pixels.
+    pub dpi: f64,
+    /// Turn the drawing upside down, so that the y axis points up. The
+    /// drawing is mirrored around the x axis, see flip_in_place.
+    pub flip_y: bool,
+    pub translate: Point,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            dpi: POINTS_PER_INCH,
+            flip_y: false,
+            translate: Point::zero(),
+        }
+    }
+}
+
+impl Transform {
+    /// \returns the transform that scales the drawing by \p scale.
+    pub fn scale(scale: f64) -> Self {
+        Self {
+            scale,
+            ..Self::default()
+        }
+    }
+
+    /// \returns the factor that the drawing is scaled by.
+    pub fn factor(&self) -> f64 {
+        self.scale * self.dpi / POINTS_PER_INCH
+    }
+
+    /// \returns the transform with the y axis flipped, and moved so that the
+    /// drawing stays in the box \p bbox (top-left, bottom-right), such as
+    /// the bounding box of the layout, after it's scaled.
+    pub fn flip_in_place(mut self, bbox: (Point, Point)) -> Self {
+        self.flip_y = !self.flip_y;
+        let y = (bbox.0.y + bbox.1.y) * self.factor();
+        self.translate.y += if self.flip_y { y } else { -y };
+        self
+    }
+
+    /// \returns the location of the point \p p of the layout in the output.
+    pub fn apply(&self, p: Point) -> Point {
+        let p = p.scale(self.factor());
+        let p = if self.flip_y {
+            Point::new(p.x, -p.y)
+        } else {
+            p
+        };
+        p.add(self.translate)
+    }
+}
+
+/// Draws on another backend, scaled by a constant factor, or transformed.
+pub struct ScaledBackend<'a> {
+    inner: &'a mut dyn RenderBackend,
+    transform: Transform,
+    factor: f64,
+}
+
+impl fmt::Debug for ScaledBackend<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScaledBackend")
+            .field("transform", &self.transform)
+            .finish()
+    }
+}
+
+impl<'a> ScaledBackend<'a> {
+    /// Create a backend that draws on \p inner, scaled by \p factor.
+    pub fn new(inner: &'a mut dyn RenderBackend, factor: f64) -> Self {
+        Self::with_transform(inner, Transform::scale(factor))
+    }
+
+    /// Create a backend that draws on \p inner, with the coordinates mapped
+    /// by \p transform.
+    pub fn with_transform(
+        inner: &'a mut dyn RenderBackend,
+        transform: Transform,
+    ) -> Self {
+        let factor = transform.factor();
+        Self {
+            inner,
+            transform,
+            factor,
+        }
+    }
+
+    fn point(&self, p: Point) -> Point {
+        self.transform.apply(p)
+    }
+
+    fn size(&self, size: Point) -> Point {
+        size.scale(self.factor)
+    }
+
+    /// \returns the top-left corner and the size of the box at \p xy with
+    /// the size \p size, in the output. The bottom of flipped boxes becomes
+    /// their top.
+    fn rect(&self, xy: Point, size: Point) -> (Point, Point) {
+        let corner = if self.transform.flip_y {
+            xy.add(Point::new(0., size.y))
+        } else {
+            xy
+        };
+        (self.point(corner), self.size(size))
+    }
+
+    fn length(&self, x: usize) -> usize {
+        (x as f64 * self.factor).round() as usize
+    }
+
+    /// \returns the style \p look with the widths of the lines, the dashes,
+    /// the rounded corners and the fonts scaled.
+    fn look(&self, look: &StyleAttr) -> StyleAttr {
+        let mut look = look.clone();
+        look.penwidth *= self.factor;
+        look.rounded = self.length(look.rounded);
+        look.font_size = self.length(look.font_size).max(1);
+        look.dash = look.dash.iter().map(|x| x * self.factor).collect();
+        look
+    }
+}
+
+impl RenderBackend for ScaledBackend<'_> {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        let look = self.look(look);
+        let (xy, size) = self.rect(xy, size);
+        self.inner.draw_rect(xy, size, &look, properties, clip);
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        let look = self.look(look);
+        let (start, stop) = (self.point(start), self.point(stop));
+        self.inner.draw_line(start, stop, &look, properties);
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        let look = self.look(look);
+        let (xy, size) = (self.point(xy), self.size(size));
+        self.inner.draw_circle(xy, size, &look, properties);
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        let look = self.look(look);
+        let points: Vec<Point> =
+            points.iter().map(|p| self.point(*p)).collect();
+        self.inner.draw_polygon(&points, &look, properties, clip);
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        let look = self.look(look);
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|s| {
+                let (a, b) = (self.point(s.0), self.point(s.1));
+                (a, b, self.point(s.2), self.point(s.3))
+            })
+            .collect();
+        self.inner.draw_path(&segments, &look, properties);
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        let look = self.look(look);
+        self.inner.draw_text(self.point(xy), text, &look);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        properties: Option<String>,
+        text: &str,
+    ) {
+        let look = self.look(look);
+        let path: Vec<(Point, Point)> = path
+            .iter()
+            .map(|x| (self.point(x.0), self.point(x.1)))
+            .collect();
+        self.inner
+            .draw_arrow(&path, dashed, head, &look, properties, text);
+    }
+
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        let (xy, size) = self.rect(xy, size);
+        self.inner.draw_image(xy, size, href, properties);
+    }
+
+    fn begin_link(&mut self, link: &Link) {
+        self.inner.begin_link(link);
+    }
+
+    fn end_link(&mut self) {
+        self.inner.end_link();
+    }
+
+    fn add_stylesheet(&mut self, sheet: &Stylesheet) {
+        self.inner.add_stylesheet(sheet);
+    }
+
+    fn begin_group(&mut self, properties: &str) {
+        self.inner.begin_group(properties);
+    }
+
+    fn end_group(&mut self) {
+        self.inner.end_group();
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        self.inner.begin_layer(name);
+    }
+
+    fn end_layer(&mut self) {
+        self.inner.end_layer();
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.inner.set_background(color);
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        // Flipped drawings grow towards the origin, that moves to the bottom.
+        self.inner.grow_canvas(self.point(xy));
+        if self.transform.flip_y {
+            self.inner.grow_canvas(self.point(Point::new(xy.x, 0.)));
+        }
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        let (xy, size) = self.rect(xy, size);
+        let rounded_px = self.length(rounded_px);
+        self.inner.create_clip(xy, size, rounded_px)
+    }
+}
+
+#[test]
+fn test_scaled_backend() {
+    use crate::backends::recording::{DrawCommand, RecordingBackend};
+    let mut rec = RecordingBackend::new();
+    let mut scaled = ScaledBackend::new(&mut rec, 0.5);
+    let mut look = StyleAttr::simple();
+    look.penwidth = 2.;
+    look.font_size = 14;
+    look.dash = vec![4., 2.];
+    scaled.draw_text(Point::new(10., 20.), "a", &look);
+    let handle = scaled.create_clip(Point::zero(), Point::new(8., 8.), 4);
+    assert_eq!(handle, 0);
+    match &rec.commands()[0] {
+        DrawCommand::Text { xy, look, .. } => {
+            assert_eq!(*xy, Point::new(5., 10.));
+            assert_eq!(look.font_size, 7);
+            assert_eq!(look.penwidth, 1.);
+            assert_eq!(look.dash, vec![2., 1.]);
+        }
+        _ => panic!("Expected a text command"),
+    }
+    assert_eq!(
+        rec.commands()[1],
+        DrawCommand::Clip {
+            xy: Point::zero(),
+            size: Point::new(4., 4.),
+            rounded_px: 2,
+        }
+    );
+}
+
+#[test]
+fn test_transform() {
+    use crate::backends::recording::{DrawCommand, RecordingBackend};
+    let t = Transform {
+        dpi: 96.,
+        translate: Point::new(1., 2.),
+        ..Transform::default()
+    };
+    assert_eq!(t.factor(), 96. / 72.);
+    assert_eq!(t.apply(Point::new(72., 36.)), Point::new(97., 50.));
+
+    // Flip the box from (0, 10) to (20, 30) in place.
+    let t = Transform::scale(2.)
+        .flip_in_place((Point::new(0., 10.), Point::new(20., 30.)));
+    assert_eq!(t.apply(Point::new(0., 10.)), Point::new(0., 60.));
+    assert_eq!(t.apply(Point::new(20., 30.)), Point::new(40., 20.));
+
+    let mut rec = RecordingBackend::new();
+    let mut scaled = ScaledBackend::with_transform(&mut rec, t);
+    let look = StyleAttr::simple();
+    scaled.draw_rect(
+        Point::new(0., 10.),
+        Point::new(20., 20.),
+        &look,
+        None,
+        None,
+    );
+    match &rec.commands()[0] {
+        DrawCommand::Rect { xy, size, .. } => {
+            assert_eq!(*xy, Point::new(0., 20.));
+            assert_eq!(*size, Point::new(40., 40.));
+        }
+        _ => panic!("Expected a rect command"),
+    }
+}
@@ -1,10 +1,16 @@
 //! SVG rendering backend that accepts draw calls and saves the output to a file.
 
+use crate::backends::tiles::TileConfig;
 use crate::core::color::Color;
-use crate::core::format::{ClipHandle, RenderBackend};
-use crate::core::geometry::Point;
-use crate::core::style::StyleAttr;
-use std::collections::HashMap;
+use crate::core::format::{ClipHandle, Link, RenderBackend, Stylesheet};
+use crate::core::geometry::{get_arrow_head_shape, HeadShape, Point};
+use crate::core::style::{
+    ArrowHeadKind, Gradient, GradientKind, StyleAttr, DEFAULT_FONT_NAME,
+};
+use crate::core::text::get_size_for_text;
+use crate::core::utils::escape_xml;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 
 static SVG_HEADER: &str =
     r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#;
@@ -23,29 +29,56 @@ refX="10" refY="3.5" orient="auto">
 
 static SVG_FOOTER: &str = "</svg>";
 
-fn escape_string(x: &str) -> String {
+// \returns the color attribute \p name, such as "fill" or "stroke", for the
+// color \p color. Translucent colors also get the opacity attribute
+// \p opacity_name, because not all SVG readers accept #rrggbbaa colors.
+fn color_attribute(name: &str, opacity_name: &str, color: Color) -> String {
+    let (r, g, b, a) = color.to_rgba();
+    let mut res = format!("{}=\"#{:02x}{:02x}{:02x}\"", name, r, g, b);
+    if a != 0xff {
+        let opacity = (a as f64 / 255. * 1000.).round() / 1000.;
+        res.push_str(&format!(" {}=\"{}\"", opacity_name, opacity));
+    }
+    res
+}
+
+// \returns the fill attributes for the color \p color.
+fn fill_attribute(color: Color) -> String {
+    color_attribute("fill", "fill-opacity", color)
+}
+
+// \returns the stroke attributes for the color \p color.
+fn stroke_attribute(color: Color) -> String {
+    color_attribute("stroke", "stroke-opacity", color)
+}
+
+// \returns the stroke-dasharray attribute for the dash pattern of \p look.
+fn dash_attribute(look: &StyleAttr) -> String {
+    if !look.is_dashed() {
+        return String::new();
+    }
+    let lengths: Vec<String> =
+        look.dash.iter().map(|x| x.to_string()).collect();
+    format!("stroke-dasharray=\"{}\"", lengths.join(","))
+}
+
+// \returns the attributes of text in the font of \p look. The font size and
+// the default font family are set by the class of the text.
+fn font_attributes(look: &StyleAttr) -> String {
     let mut res = String::new();
-    for c in x.chars() {
-        match c {
-            '&' => {
-                res.push_str("&amp;");
-            }
-            '<' => {
-                res.push_str("&lt;");
-            }
-            '>' => {
-                res.push_str("&gt;");
-            }
-            '"' => {
-                res.push_str("&quot;");
-            }
-            '\'' => {
-                res.push_str("&apos;");
-            }
-            _ => {
-                res.push(c);
-            }
-        }
+    if look.font_name != DEFAULT_FONT_NAME {
+        let family = escape_xml(&look.font_name);
+        res.push_str(&format!(" font-family=\"{}\"", family));
+    }
+    if look.font_bold {
+        res.push_str(" font-weight=\"bold\"");
+    }
+    if look.font_italic {
+        res.push_str(" font-style=\"italic\"");
+    }
+    if look.font_color != Color::fast("black") {
+        res.push(' ');
+        res.push_str(&fill_attribute(look.font_color));
     }
     res
 }
@@ -55,10 +88,27 @@ pub struct SVGWriter {
     content: String,
     view_size: Point,
     counter: usize,
-    // Maps font sizes to their class name and class impl.
-    font_style_map: HashMap<usize, (String, String)>,
+    // Maps font sizes to their class name and class impl. The map is sorted,
+    // so that the styles are emitted in the same order in every run.
+    font_style_map: BTreeMap<usize, (String, String)>,
     // A list of clip regions to generate.
     clip_regions: Vec<String>,
+    // A list of gradient definitions to generate.
+    gradients: Vec<String>,
+    // A list of (id, definition) of the arrow head markers to generate.
+    markers: Vec<(String, String)>,
+    // The color of the background, or None for white.
+    background: Option<Color>,
+    // The closing tags of the links that are open.
+    open_links: Vec<&'static str>,
+    // The URLs of the linked stylesheets, and the CSS rules that are added to
+    // the style block of the document.
+    stylesheets: Vec<String>,
+    styles: String,
+    // The name of the layer that is drawn, if any.
+    layer: Option<String>,
+    // The prefix of the ids of the gradients and the clip regions.
+    id_prefix: String,
 }
 
 impl SVGWriter {
@@ -67,10 +117,25 @@ impl SVGWriter {
             content: String::new(),
             view_size: Point::zero(),
             counter: 0,
-            font_style_map: HashMap::new(),
+            font_style_map: BTreeMap::new(),
             clip_regions: Vec::new(),
+            gradients: Vec::new(),
+            markers: Vec::new(),
+            background: None,
+            open_links: Vec::new(),
+            stylesheets: Vec::new(),
+            styles: String::new(),
+            layer: None,
+            id_prefix: String::new(),
         }
     }
+
+    /// Prefix the ids of the gradients and the clip regions of the document
+    /// with \p prefix. The ids are numbered by each writer, so pages that
+    /// inline several documents give each of them its own prefix.
+    pub fn set_id_prefix(&mut self, prefix: &str) {
+        self.id_prefix = prefix.to_string();
+    }
 }
 
 impl Default for SVGWriter {
@@ -115,33 +180,278 @@ impl SVGWriter {
             content.push_str(&p.1 .1);
             content.push('\n');
         }
+        content.push_str(&self.styles);
         content.push_str("</style>\n");
         for p in self.clip_regions.iter() {
             content.push_str(p);
             content.push('\n');
         }
+        for p in self.gradients.iter() {
+            content.push_str(p);
+            content.push('\n');
+        }
+        for p in self.markers.iter() {
+            content.push_str(&p.1);
+            content.push('\n');
+        }
         content
     }
 
+    // Returns the id of the marker that draws the arrow head \p kind at the
+    // start or the end of the line, scaled by \p size. Creates the
+    // definition of the marker if needed. Returns None if there is no head.
+    fn get_marker(
+        &mut self,
+        kind: ArrowHeadKind,
+        size: f64,
+        is_start: bool,
+    ) -> Option<String> {
+        let side = if is_start { "start" } else { "end" };
+        if kind == ArrowHeadKind::None {
+            return None;
+        }
+        if kind == ArrowHeadKind::Normal && size == 1. {
+            return Some(format!("{}arrow", side));
+        }
+        let id = format!("{}{}{}", side, kind.name(), (size * 100.) as usize);
+        if self.markers.iter().any(|x| x.0 == id) {
+            return Some(id);
+        }
+
+        // The head points to the right, and the tip is at the origin. Move
+        // the head into the marker box, and mirror the start marker.
+        let shapes = get_arrow_head_shape(kind);
+        let mut length: f64 = 0.;
+        let mut height: f64 = 0.;
+        for shape in shapes.iter() {
+            match shape {
+                HeadShape::Polygon(points, _) => {
+                    for p in points {
+                        length = length.max(-p.x);
+                        height = height.max(2. * p.y.abs());
+                    }
+                }
+                HeadShape::Circle(center, radius, _) => {
+                    length = length.max(radius - center.x);
+                    height = height.max(2. * radius);
+                }
+            }
+        }
+        let to_marker = |p: Point| {
+            let x = if is_start { -p.x } else { p.x + length };
+            Point::new(x, p.y + height / 2.).scale(size)
+        };
+
+        let mut content = String::new();
+        for shape in shapes.iter() {
+            let shape = shape.transform(to_marker, size);
+            let (fill, stroke) = if shape.is_filled() {
+                ("context-stroke", "")
+            } else {
+                ("white", "stroke=\"context-stroke\" stroke-width=\"1\"")
+            };
+            match shape {
+                HeadShape::Polygon(points, _) => {
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|p| format!("{} {}", p.x, p.y))
+                        .collect();
+                    content.push_str(&format!(
+                        "<polygon points=\"{}\" fill=\"{}\" {} />",
+                        points.join(", "),
+                        fill,
+                        stroke
+                    ));
+                }
+                HeadShape::Circle(center, radius, _) => {
+                    content.push_str(&format!(
+                        "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" \
+                        fill=\"{}\" {} />",
+                        center.x, center.y, radius, fill, stroke
+                    ));
+                }
+            }
+        }
+
+        let ref_x = if is_start { 0. } else { length * size };
+        let def = format!(
+            "<marker id=\"{}\" markerWidth=\"{}\" markerHeight=\"{}\" \
+            refX=\"{}\" refY=\"{}\" orient=\"auto\" overflow=\"visible\">\
+            {}</marker>",
+            id,
+            length * size,
+            height * size,
+            ref_x,
+            height * size / 2.,
+            content
+        );
+        self.markers.push((id.clone(), def));
+        Some(id)
+    }
+
+    // Creates the definition of the gradient \p gradient. Returns the id of
+    // the definition.
+    fn create_gradient(&mut self, gradient: &Gradient) -> String {
+        let id = format!("{}G{}", self.id_prefix, self.gradients.len());
+        let mut stops = String::new();
+        for (offset, color) in gradient.stops.iter() {
+            stops.push_str(&format!(
+                "<stop offset=\"{}\" {} />",
+                offset.clamp(0., 1.),
+                color_attribute("stop-color", "stop-opacity", *color)
+            ));
+        }
+        let def = match gradient.kind {
+            GradientKind::Linear(angle) => {
+                // The angle is counterclockwise, and the y axis points down.
+                let (sin, cos) = angle.to_radians().sin_cos();
+                format!(
+                    "<linearGradient id=\"{}\" x1=\"{}\" y1=\"{}\" \
+                    x2=\"{}\" y2=\"{}\">{}</linearGradient>",
+                    id,
+                    0.5 - cos / 2.,
+                    0.5 + sin / 2.,
+                    0.5 + cos / 2.,
+                    0.5 - sin / 2.,
+                    stops
+                )
+            }
+            GradientKind::Radial | GradientKind::Wedged => format!(
+                "<radialGradient id=\"{}\">{}</radialGradient>",
+                id, stops
+            ),
+        };
+        self.gradients.push(def);
+        id
+    }
+
+    // Returns the fill attributes for the style \p look.
+    fn get_fill(&mut self, look: &StyleAttr) -> String {
+        let fill_color = match look.fill_color {
+            Option::Some(color) => color,
+            None => return fill_attribute(Color::transparent()),
+        };
+        if let Option::Some(gradient) = &look.gradient {
+            // Only ellipses are cut into wedges. See get_wedges.
+            let is_wedged = gradient.kind == GradientKind::Wedged;
+            if !gradient.stops.is_empty() && !is_wedged {
+                let id = self.create_gradient(gradient);
+                return format!("fill=\"url(#{})\"", id);
+            }
+        }
+        fill_attribute(fill_color)
+    }
+
+    // Returns the pie slices that fill the ellipse with the center \p xy and
+    // the size \p size, if the style \p look has a wedged fill.
+    fn get_wedges(xy: Point, size: Point, look: &StyleAttr) -> Option<String> {
+        let gradient = look.gradient.as_ref()?;
+        if gradient.kind != GradientKind::Wedged || look.fill_color.is_none() {
+            return None;
+        }
+        let (rx, ry) = (size.x / 2., size.y / 2.);
+        // The angles are counterclockwise, and the y axis points down.
+        let at = |fraction: f64| {
+            let angle = fraction * 2. * std::f64::consts::PI;
+            Point::new(xy.x + rx * angle.cos(), xy.y - ry * angle.sin())
+        };
+        let mut res = String::new();
+        for band in gradient.stops.chunks(2) {
+            let (start, color) = band[0];
+            let end = band.last().map_or(start, |x| x.0);
+            let fill = fill_attribute(color);
+            if end - start >= 1. {
+                res.push_str(&format!(
+                    "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {} />\n",
+                    xy.x, xy.y, rx, ry, fill
+                ));
+            } else if end > start {
+                let (from, to) = (at(start), at(end));
+                let large = i32::from(end - start > 0.5);
+                res.push_str(&format!(
+                    "<path d=\"M {} {} L {} {} A {} {} 0 {} 0 {} {} Z\" \
+                    {} />\n",
+                    xy.x, xy.y, from.x, from.y, rx, ry, large, to.x, to.y, fill
+                ));
+            }
+        }
+        Some(res)
+    }
+
     pub fn finalize(&self) -> String {
+        let mut result = self.prologue();
+        result.push_str(&self.content);
+        result.push_str(SVG_FOOTER);
+        result
+    }
+
+    /// Build one SVG document for each page of the drawing, that starts at
+    /// the corners \p tiles and has the size and the crop marks of
+    /// \p config. \returns the content of the SVG files.
+    pub fn finalize_tiles(
+        &self,
+        config: &TileConfig,
+        tiles: &[Point],
+    ) -> Vec<String> {
+        let mut res = Vec::new();
+        for tile in tiles {
+            let mut result = self.prologue_for(*tile, config.page);
+            result.push_str(&self.content);
+            if config.crop_marks {
+                for (start, stop) in config.get_crop_marks(*tile) {
+                    result.push_str(&format!(
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" \
+                        stroke=\"black\" stroke-width=\"0.5\" />\n",
+                        start.x, start.y, stop.x, stop.y
+                    ));
+                }
+            }
+            result.push_str(SVG_FOOTER);
+            res.push(result);
+        }
+        res
+    }
+
+    // Returns the beginning of the document, that comes before the drawing:
+    // the size of the canvas, the background and the definitions.
+    fn prologue(&self) -> String {
+        self.prologue_for(Point::zero(), self.view_size)
+    }
+
+    // Returns the beginning of a document that shows the part of the drawing
+    // that starts at \p origin and has the size \p size.
+    fn prologue_for(&self, origin: Point, size: Point) -> String {
         let mut result = String::new();
         result.push_str(SVG_HEADER);
+        for href in &self.stylesheets {
+            result.push_str(&format!(
+                "\n<?xml-stylesheet href=\"{}\" type=\"text/css\"?>",
+                escape_xml(href)
+            ));
+        }
 
         let svg_line = format!(
-            "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\
+            "<svg width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\
             \" xmlns=\"http://www.w3.org/2000/svg\">\n",
-            self.view_size.x,
-            self.view_size.y,
-            self.view_size.x,
-            self.view_size.y
+            size.x, size.y, origin.x, origin.y, size.x, size.y
         );
         result.push_str(&svg_line);
-        result
-            .push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\" />");
+        let fill = match self.background {
+            Option::Some(color) => fill_attribute(color),
+            None => "fill=\"white\"".to_string(),
+        };
+        // The percents are relative to the viewport, and don't move with it.
+        let position = if origin == Point::zero() {
+            String::new()
+        } else {
+            format!("x=\"{}\" y=\"{}\" ", origin.x, origin.y)
+        };
+        result.push_str(&format!(
+            "<rect {}width=\"100%\" height=\"100%\" {} />",
+            position, fill
+        ));
         result.push_str(SVG_DEFS);
         result.push_str(&self.emit_svg_font_styles());
-        result.push_str(&self.content);
-        result.push_str(SVG_FOOTER);
         result
     }
 }
@@ -158,25 +468,27 @@ impl RenderBackend for SVGWriter {
 
         let mut clip_option = String::new();
         if let Option::Some(clip_id) = clip {
-            clip_option = format!("clip-path=\"url(#C{})\"", clip_id);
+            clip_option =
+                format!("clip-path=\"url(#{}C{})\"", self.id_prefix, clip_id);
         }
         let props = properties.unwrap_or_default();
-        let fill_color = look.fill_color.unwrap_or_else(Color::transparent);
-        let stroke_width = look.line_width;
+        let fill = self.get_fill(look);
+        let stroke_width = look.penwidth;
         let stroke_color = look.line_color;
         let rounded_px = look.rounded;
+        let dash = dash_attribute(look);
         let line1 = format!(
             "<g {props}>\n
-            <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" 
-            stroke-width=\"{}\" stroke=\"{}\" rx=\"{}\" {} />\n
+            <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {} 
+            stroke-width=\"{}\" {} rx=\"{}\" {} {dash} />\n
             </g>\n",
             xy.x,
             xy.y,
             size.x,
             size.y,
-            fill_color.to_web_color(),
+            fill,
             stroke_width,
-            stroke_color.to_web_color(),
+            stroke_attribute(stroke_color),
             rounded_px,
             clip_option
         );
@@ -191,29 +503,111 @@ impl RenderBackend for SVGWriter {
         properties: Option<String>,
     ) {
         self.grow_window(xy, size);
-        let fill_color = look.fill_color.unwrap_or_else(Color::transparent);
-        let stroke_width = look.line_width;
+        let wedges = Self::get_wedges(xy, size, look);
+        let fill = if wedges.is_some() {
+            fill_attribute(Color::transparent())
+        } else {
+            self.get_fill(look)
+        };
+        let wedges = wedges.unwrap_or_default();
+        let stroke_width = look.penwidth;
         let stroke_color = look.line_color;
         let props = properties.unwrap_or_default();
+        let dash = dash_attribute(look);
         let line1 = format!(
-            "<g {props}>\n
-            <ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" fill=\"{}\" 
-            stroke-width=\"{}\" stroke=\"{}\"/>\n
+            "<g {props}>\n{wedges}
+            <ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {} 
+            stroke-width=\"{}\" {} {dash}/>\n
             </g>\n",
             xy.x,
             xy.y,
             size.x / 2.,
             size.y / 2.,
-            fill_color.to_web_color(),
+            fill,
             stroke_width,
-            stroke_color.to_web_color()
+            stroke_attribute(stroke_color)
         );
         self.content.push_str(&line1);
     }
 
-    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
-        let len = text.len();
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        for point in points {
+            self.grow_window(*point, Point::zero());
+        }
+
+        let mut clip_option = String::new();
+        if let Option::Some(clip_id) = clip {
+            clip_option =
+                format!("clip-path=\"url(#{}C{})\"", self.id_prefix, clip_id);
+        }
+        let corners: Vec<String> =
+            points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+        let props = properties.unwrap_or_default();
+        let fill = self.get_fill(look);
+        let stroke_width = look.penwidth;
+        let stroke_color = look.line_color;
+        let dash = dash_attribute(look);
+        let line1 = format!(
+            "<g {props}>\n
+            <polygon points=\"{}\" {} 
+            stroke-width=\"{}\" {} {} {dash} />\n
+            </g>\n",
+            corners.join(" "),
+            fill,
+            stroke_width,
+            stroke_attribute(stroke_color),
+            clip_option
+        );
+        self.content.push_str(&line1);
+    }
 
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        let mut path = String::new();
+        let mut last: Option<Point> = None;
+        for (p0, p1, p2, p3) in segments.iter() {
+            for p in [p0, p1, p2, p3] {
+                self.grow_window(*p, Point::zero());
+            }
+            if last != Some(*p0) {
+                path.push_str(&format!("M {} {} ", p0.x, p0.y));
+            }
+            path.push_str(&format!(
+                "C {} {}, {} {}, {} {} ",
+                p1.x, p1.y, p2.x, p2.y, p3.x, p3.y
+            ));
+            last = Some(*p3);
+        }
+
+        let props = properties.unwrap_or_default();
+        let fill = self.get_fill(look);
+        let stroke_width = look.penwidth;
+        let stroke_color = look.line_color;
+        let dash = dash_attribute(look);
+        let line1 = format!(
+            "<g {props}>\n
+            <path d=\"{}\" {} 
+            stroke-width=\"{}\" {} {dash} />\n
+            </g>\n",
+            path.trim_end(),
+            fill,
+            stroke_width,
+            stroke_attribute(stroke_color)
+        );
+        self.content.push_str(&line1);
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
         let font_class = self.get_or_create_font_style(look.font_size);
 
         let mut content = String::new();
@@ -221,17 +615,19 @@ impl RenderBackend for SVGWriter {
         let size_y = (cnt * look.font_size) as f64;
         for line in text.lines() {
             content.push_str(&format!("<tspan x = \"{}\" dy=\"1.0em\">", xy.x));
-            content.push_str(&escape_string(line));
+            content.push_str(&escape_xml(line));
             content.push_str("</tspan>");
         }
 
-        self.grow_window(xy, Point::new(10., len as f64 * 10.));
+        let size = get_size_for_text(text, look);
+        self.grow_window(xy.sub(size.scale(0.5)), size);
         let line = format!(
             "<text dominant-baseline=\"middle\" text-anchor=\"middle\" 
-            x=\"{}\" y=\"{}\" class=\"{}\">{}</text>",
+            x=\"{}\" y=\"{}\" class=\"{}\"{}>{}</text>",
             xy.x,
             xy.y - size_y / 2.,
             font_class,
+            font_attributes(look),
             &content
         );
 
@@ -258,21 +654,31 @@ impl RenderBackend for SVGWriter {
             self.grow_window(point.1, Point::zero());
         }
 
-        let dash = if dashed {
-            &"stroke-dasharray=\"5,5\""
+        // An explicit dash pattern in the style overrides the default dashes.
+        let dash = if look.is_dashed() {
+            dash_attribute(look)
+        } else if dashed {
+            "stroke-dasharray=\"5,5\"".to_string()
         } else {
-            &""
-        };
-        let start = if head.0 {
-            "marker-start=\"url(#startarrow)\""
-        } else {
-            ""
-        };
-        let end = if head.1 {
-            "marker-end=\"url(#endarrow)\""
-        } else {
-            ""
+            String::new()
         };
+        let (start_kind, end_kind) = look.arrow_heads;
+        let mut start = String::new();
+        let mut end = String::new();
+        if head.0 {
+            if let Option::Some(id) =
+                self.get_marker(start_kind, look.arrow_size, true)
+            {
+                start = format!("marker-start=\"url(#{})\"", id);
+            }
+        }
+        if head.1 {
+            if let Option::Some(id) =
+                self.get_marker(end_kind, look.arrow_size, false)
+            {
+                end = format!("marker-end=\"url(#{})\"", id);
+            }
+        }
 
         let mut path_builder = String::new();
 
@@ -297,18 +703,18 @@ impl RenderBackend for SVGWriter {
             ));
         }
 
-        let stroke_width = look.line_width;
+        let stroke_width = look.penwidth;
         let stroke_color = look.line_color;
         let props = properties.unwrap_or_default();
         let line = format!(
             "<g {props}>\n
             <path id=\"arrow{}\" d=\"{}\" \
-            stroke=\"{}\" stroke-width=\"{}\" {} {} {} 
+            {} stroke-width=\"{}\" {} {} {} 
             fill=\"transparent\" />\n
             </g>\n",
             self.counter,
             path_builder.as_str(),
-            stroke_color.to_web_color(),
+            stroke_attribute(stroke_color),
             stroke_width,
             dash,
             start,
@@ -319,10 +725,11 @@ impl RenderBackend for SVGWriter {
         let font_class = self.get_or_create_font_style(look.font_size);
         let line = format!(
             "<text><textPath href=\"#arrow{}\" startOffset=\"50%\" \
-            text-anchor=\"middle\" class=\"{}\">{}</textPath></text>",
+            text-anchor=\"middle\" class=\"{}\"{}>{}</textPath></text>",
             self.counter,
             font_class,
-            escape_string(text)
+            font_attributes(look),
+            escape_xml(text)
         );
         self.content.push_str(&line);
         self.counter += 1;
@@ -335,24 +742,126 @@ impl RenderBackend for SVGWriter {
         look: &StyleAttr,
         properties: Option<String>,
     ) {
-        let stroke_width = look.line_width;
+        let stroke_width = look.penwidth;
         let stroke_color = look.line_color;
         let props = properties.unwrap_or_default();
+        let dash = dash_attribute(look);
         let line1 = format!(
             "<g {props}>\n
              <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\"
-             stroke=\"{}\" />\n
+             {} {dash} />\n
              </g>\n",
             start.x,
             start.y,
             stop.x,
             stop.y,
             stroke_width,
-            stroke_color.to_web_color()
+            stroke_attribute(stroke_color)
         );
         self.content.push_str(&line1);
     }
 
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        self.grow_window(xy, size);
+        let props = properties.unwrap_or_default();
+        let line = format!(
+            "<image {props} x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" \
+             href=\"{}\" preserveAspectRatio=\"none\" />\n",
+            xy.x,
+            xy.y,
+            size.x,
+            size.y,
+            escape_xml(href)
+        );
+        self.content.push_str(&line);
+    }
+
+    fn begin_link(&mut self, link: &Link) {
+        // Elements with a URL are wrapped in an anchor, and elements with
+        // only a tooltip in a group, so that the title applies to them.
+        let mut line = match &link.url {
+            Option::Some(url) => {
+                let mut attrs = format!("href=\"{}\"", escape_xml(url));
+                if let Option::Some(target) = &link.target {
+                    attrs.push_str(&format!(
+                        " target=\"{}\"",
+                        escape_xml(target)
+                    ));
+                }
+                self.open_links.push("</a>\n");
+                format!("<a {}>\n", attrs)
+            }
+            None => {
+                self.open_links.push("</g>\n");
+                String::from("<g>\n")
+            }
+        };
+        if let Option::Some(tooltip) = &link.tooltip {
+            line.push_str(&format!("<title>{}</title>\n", escape_xml(tooltip)));
+        }
+        self.content.push_str(&line);
+    }
+
+    fn end_link(&mut self) {
+        if let Option::Some(tag) = self.open_links.pop() {
+            self.content.push_str(tag);
+        }
+    }
+
+    fn begin_group(&mut self, properties: &str) {
+        // Elements are drawn once in every layer that they belong to, so the
+        // ids are prefixed with the name of the layer to keep them unique.
+        let line = match (&self.layer, properties.strip_prefix("id=\"")) {
+            (Option::Some(layer), Option::Some(rest)) => {
+                format!("<g id=\"{}_{}>\n", layer, rest)
+            }
+            _ => format!("<g {}>\n", properties),
+        };
+        self.content.push_str(&line);
+    }
+
+    fn end_group(&mut self) {
+        self.content.push_str("</g>\n");
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        let line = format!("<g id=\"layer_{}\" class=\"layer\">\n", name);
+        self.content.push_str(&line);
+        self.layer = Some(name.into());
+    }
+
+    fn end_layer(&mut self) {
+        self.content.push_str("</g>\n");
+        self.layer = None;
+    }
+
+    fn add_stylesheet(&mut self, sheet: &Stylesheet) {
+        // Embedded rules come after the default styles, and override them.
+        match sheet {
+            Stylesheet::Link(href) => self.stylesheets.push(href.clone()),
+            Stylesheet::Inline(css) => {
+                self.styles.push_str(css);
+                if !css.ends_with('\n') {
+                    self.styles.push('\n');
+                }
+            }
+        }
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.background = Some(color);
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.grow_window(xy, Point::zero());
+    }
+
     fn create_clip(
         &mut self,
         xy: Point,
@@ -362,10 +871,10 @@ impl RenderBackend for SVGWriter {
         let handle = self.clip_regions.len();
 
         let clip_code = format!(
-            "<clipPath id=\"C{}\"><rect x=\"{}\" y=\"{}\" \
+            "<clipPath id=\"{}C{}\"><rect x=\"{}\" y=\"{}\" \
             width=\"{}\" height=\"{}\" rx=\"{}\" /> \
             </clipPath>",
-            handle, xy.x, xy.y, size.x, size.y, rounded_px
+            self.id_prefix, handle, xy.x, xy.y, size.x, size.y, rounded_px
         );
 
         self.clip_regions.push(clip_code);
@@ -373,3 +882,221 @@ impl RenderBackend for SVGWriter {
         handle
     }
 }
+
+/// The number of bytes of the drawing that are buffered before they are
+/// written to the sink of a streaming SVG writer.
+const STREAM_CHUNK_SIZE: usize = 1 << 16;
+
+/// Render the SVG document of the drawing \p draw into the sink \p out, without
+/// keeping the whole document in memory. The drawing is rendered twice: the
+/// first time to measure the canvas and to collect the definitions that start
+/// the document, and the second time to write the elements to \p out in
+/// chunks, as they are drawn. The output is the same as the output of
+/// SVGWriter::finalize. For example:
+///
+/// ```
+/// use layout::backends::svg::write_svg;
+/// use layout::gv::{DotParser, GraphBuilder};
+///
+/// let graph = DotParser::new("digraph { a -> b; }").process().unwrap();
+/// let mut gb = GraphBuilder::new();
+/// gb.visit_graph(&graph);
+/// let mut vg = gb.get();
+/// vg.layout(false, false);
+///
+/// let mut out = Vec::new();
+/// write_svg(&mut out, |rb| vg.draw(false, rb)).unwrap();
+/// ```
+pub fn write_svg<W: Write>(
+    out: &mut W,
+    mut draw: impl FnMut(&mut dyn RenderBackend),
+) -> io::Result<()> {
+    let mut measure = SVGStream::new(None);
+    draw(&mut measure);
+    measure.flush();
+
+    out.write_all(measure.svg.prologue().as_bytes())?;
+    let mut stream = SVGStream::new(Some(out));
+    draw(&mut stream);
+    stream.flush();
+    if let Option::Some(err) = stream.error {
+        return Err(err);
+    }
+    out.write_all(SVG_FOOTER.as_bytes())
+}
+
+/// A backend that renders the drawing with an SVGWriter, and moves the
+/// elements out of the writer when they are drawn, to the sink \p out, or
+/// nowhere when there is no sink.
+struct SVGStream<'a> {
+    svg: SVGWriter,
+    out: Option<&'a mut dyn Write>,
+    // The first error of the sink. Nothing is written after an error.
+    error: Option<io::Error>,
+}
+
+impl<'a> SVGStream<'a> {
+    fn new(out: Option<&'a mut dyn Write>) -> Self {
+        Self {
+            svg: SVGWriter::new(),
+            out,
+            error: None,
+        }
+    }
+
+    // Write the buffered elements to the sink, once there are enough of them.
+    fn write_chunk(&mut self) {
+        if self.svg.content.len() >= STREAM_CHUNK_SIZE {
+            self.flush();
+        }
+    }
+
+    // Write all of the buffered elements to the sink.
+    fn flush(&mut self) {
+        if let (Option::Some(out), None) = (&mut self.out, &self.error) {
+            if let Result::Err(err) = out.write_all(self.svg.content.as_bytes())
+            {
+                self.error = Some(err);
+            }
+        }
+        self.svg.content.clear();
+    }
+}
+
+impl RenderBackend for SVGStream<'_> {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.svg.draw_rect(xy, size, look, properties, clip);
+        self.write_chunk();
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_line(start, stop, look, properties);
+        self.write_chunk();
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_circle(xy, size, look, properties);
+        self.write_chunk();
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.svg.draw_polygon(points, look, properties, clip);
+        self.write_chunk();
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_path(segments, look, properties);
+        self.write_chunk();
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.svg.draw_text(xy, text, look);
+        self.write_chunk();
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        properties: Option<String>,
+        text: &str,
+    ) {
+        self.svg
+            .draw_arrow(path, dashed, head, look, properties, text);
+        self.write_chunk();
+    }
+
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        self.svg.draw_image(xy, size, href, properties);
+        self.write_chunk();
+    }
+
+    fn begin_link(&mut self, link: &Link) {
+        self.svg.begin_link(link);
+        self.write_chunk();
+    }
+
+    fn end_link(&mut self) {
+        self.svg.end_link();
+        self.write_chunk();
+    }
+
+    fn add_stylesheet(&mut self, sheet: &Stylesheet) {
+        self.svg.add_stylesheet(sheet);
+    }
+
+    fn begin_group(&mut self, properties: &str) {
+        self.svg.begin_group(properties);
+        self.write_chunk();
+    }
+
+    fn end_group(&mut self) {
+        self.svg.end_group();
+        self.write_chunk();
+    }
+
+    fn begin_layer(&mut self, name: &str) {
+        self.svg.begin_layer(name);
+        self.write_chunk();
+    }
+
+    fn end_layer(&mut self) {
+        self.svg.end_layer();
+        self.write_chunk();
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.svg.set_background(color);
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.svg.grow_canvas(xy);
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        self.svg.create_clip(xy, size, rounded_px)
+    }
+}
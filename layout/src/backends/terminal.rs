@@ -0,0 +1,386 @@
+//! Terminal rendering backend that displays the graph as an inline image.
+//!
+//! The draw calls are rasterized with the PNG backend, and the image is
+//! encoded with the Kitty graphics protocol or as Sixel escape sequences.
+//! Terminals that support neither get the text rendering of ASCIIWriter.
+//!
+//! ```
+//! # use layout::backends::terminal::{GraphicsProtocol, TerminalWriter};
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut term = TerminalWriter::with_protocol(GraphicsProtocol::Sixel);
+//! let look = StyleAttr::simple();
+//! term.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! assert!(term.finalize().starts_with("\x1bPq"));
+//! ```
+
+use super::ascii_art::{is_stdout_terminal, ASCIIWriter};
+use super::png::PNGWriter;
+use crate::core::color::Color;
+use crate::core::format::{ClipHandle, RenderBackend};
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+use crate::core::text::TextMetrics;
+
+/// The largest payload of a single Kitty graphics escape sequence.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Selects how the image is sent to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphicsProtocol {
+    /// The Kitty graphics protocol, with PNG data.
+    Kitty,
+    /// Sixel graphics, with a 216 color palette.
+    Sixel,
+    /// No graphics support. The graph is rendered as text.
+    #[default]
+    Text,
+}
+
+impl GraphicsProtocol {
+    /// Guess the graphics protocol that the terminal supports from the
+    /// `KITTY_WINDOW_ID`, `TERM_PROGRAM` and `TERM` environment variables.
+    pub fn detect() -> Self {
+        if !is_stdout_terminal() {
+            return GraphicsProtocol::Text;
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if std::env::var("KITTY_WINDOW_ID").is_ok()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "WezTerm"
+            || program == "ghostty"
+        {
+            return GraphicsProtocol::Kitty;
+        }
+        if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || program == "iTerm.app"
+        {
+            return GraphicsProtocol::Sixel;
+        }
+        GraphicsProtocol::Text
+    }
+}
+
+#[derive(Debug)]
+enum Canvas {
+    Raster(PNGWriter),
+    Text(ASCIIWriter),
+}
+
+#[derive(Debug)]
+pub struct TerminalWriter {
+    protocol: GraphicsProtocol,
+    canvas: Canvas,
+}
+
+impl TerminalWriter {
+    /// Create a writer for the protocol that the terminal supports.
+    pub fn new() -> Self {
+        Self::with_protocol(GraphicsProtocol::detect())
+    }
+
+    /// Create a writer that renders with the protocol \p protocol.
+    pub fn with_protocol(protocol: GraphicsProtocol) -> Self {
+        let canvas = match protocol {
+            GraphicsProtocol::Text => {
+                Canvas::Text(ASCIIWriter::new_with_terminal_setting(true))
+            }
+            _ => Canvas::Raster(PNGWriter::new()),
+        };
+        TerminalWriter { protocol, canvas }
+    }
+
+    pub fn protocol(&self) -> GraphicsProtocol {
+        self.protocol
+    }
+
+    /// \returns the metrics that fit the labels of the graph to the canvas.
+    /// Terminals without graphics draw each character in a cell.
+    pub fn text_metrics(&self) -> TextMetrics {
+        match self.canvas {
+            Canvas::Raster(_) => TextMetrics::Font,
+            Canvas::Text(_) => TextMetrics::Grid,
+        }
+    }
+
+    /// \returns the escape sequences (or the text) that display the graph.
+    pub fn finalize(&self) -> String {
+        match (&self.canvas, self.protocol) {
+            (Canvas::Text(ascii), _) => ascii.finalize(),
+            (Canvas::Raster(png), GraphicsProtocol::Kitty) => encode_kitty(png),
+            (Canvas::Raster(png), _) => encode_sixel(png),
+        }
+    }
+
+    fn backend(&mut self) -> &mut dyn RenderBackend {
+        match &mut self.canvas {
+            Canvas::Raster(png) => png,
+            Canvas::Text(ascii) => ascii,
+        }
+    }
+}
+
+impl Default for TerminalWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encode \p data as base64, with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+/// Send the PNG image of \p png with the Kitty graphics protocol. The data is
+/// split into chunks, and every chunk but the last one is marked with m=1.
+fn encode_kitty(png: &PNGWriter) -> String {
+    let data = base64(&png.finalize());
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+    let mut res = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as usize;
+        if i == 0 {
+            res.push_str(&format!("\x1b_Ga=T,f=100,m={};", more));
+        } else {
+            res.push_str(&format!("\x1b_Gm={};", more));
+        }
+        res.push_str(std::str::from_utf8(chunk).unwrap());
+        res.push_str("\x1b\\");
+    }
+    res.push('\n');
+    res
+}
+
+/// \returns the index of the color \p rgba in the 6x6x6 color cube, after
+/// blending it over a white background.
+fn palette_index(rgba: (u8, u8, u8, u8)) -> usize {
+    let (r, g, b, a) = rgba;
+    let level = |c: u8| {
+        let c = (c as usize * a as usize + 255 * (255 - a as usize)) / 255;
+        (c * 5 + 127) / 255
+    };
+    level(r) * 36 + level(g) * 6 + level(b)
+}
+
+/// Encode the image of \p png as Sixel graphics. Each band of six rows is
+/// painted one color at a time, and runs of the same sixel are compressed.
+fn encode_sixel(png: &PNGWriter) -> String {
+    let (width, height) = png.size();
+    let (width, height) = (width.max(1), height.max(1));
+    let white = (255, 255, 255, 255);
+    let color =
+        |x: usize, y: usize| palette_index(png.pixel(x, y).unwrap_or(white));
+
+    let mut res = String::from("\x1bPq");
+    res.push_str(&format!("\"1;1;{};{}", width, height));
+    let mut used = [false; 216];
+    for y in 0..height {
+        for x in 0..width {
+            used[color(x, y)] = true;
+        }
+    }
+    for (idx, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let percent = |level: usize| level * 100 / 5;
+        res.push_str(&format!(
+            "#{};2;{};{};{}",
+            idx,
+            percent(idx / 36),
+            percent(idx / 6 % 6),
+            percent(idx % 6)
+        ));
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut colors = vec![0u8; width * rows];
+        let mut band_used = [false; 216];
+        for dy in 0..rows {
+            for x in 0..width {
+                let idx = color(x, band + dy);
+                colors[dy * width + x] = idx as u8;
+                band_used[idx] = true;
+            }
+        }
+        let mut first = true;
+        for idx in (0..216).filter(|idx| band_used[*idx]) {
+            if !first {
+                res.push('$');
+            }
+            first = false;
+            res.push_str(&format!("#{}", idx));
+            let sixel = |x: usize| {
+                let mut bits = 0;
+                for dy in 0..rows {
+                    if colors[dy * width + x] as usize == idx {
+                        bits |= 1 << dy;
+                    }
+                }
+                (63 + bits) as u8 as char
+            };
+            let mut x = 0;
+            while x < width {
+                let ch = sixel(x);
+                let mut run = 1;
+                while x + run < width && sixel(x + run) == ch {
+                    run += 1;
+                }
+                if run > 3 {
+                    res.push_str(&format!("!{}{}", run, ch));
+                } else {
+                    (0..run).for_each(|_| res.push(ch));
+                }
+                x += run;
+            }
+        }
+        res.push('-');
+    }
+    res.push_str("\x1b\\\n");
+    res
+}
+
+impl RenderBackend for TerminalWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.backend().draw_rect(xy, size, look, properties, clip);
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.backend().draw_line(start, stop, look, properties);
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.backend().draw_circle(xy, size, look, properties);
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        self.backend().draw_polygon(points, look, properties, clip);
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    ) {
+        self.backend().draw_path(segments, look, properties);
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.backend().draw_text(xy, text, look);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        properties: Option<String>,
+        text: &str,
+    ) {
+        self.backend()
+            .draw_arrow(path, dashed, head, look, properties, text);
+    }
+
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        self.backend().draw_image(xy, size, href, properties);
+    }
+
+    fn set_background(&mut self, color: Color) {
+        self.backend().set_background(color);
+    }
+
+    fn grow_canvas(&mut self, xy: Point) {
+        self.backend().grow_canvas(xy);
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        self.backend().create_clip(xy, size, rounded_px)
+    }
+}
+
+#[test]
+fn test_terminal_writer() {
+    assert_eq!(base64(b""), "");
+    assert_eq!(base64(b"f"), "Zg==");
+    assert_eq!(base64(b"fo"), "Zm8=");
+    assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    assert_eq!(palette_index((255, 0, 0, 255)), 180);
+    assert_eq!(palette_index((0, 0, 0, 0)), 215);
+
+    let look = StyleAttr::simple();
+    let mut term = TerminalWriter::with_protocol(GraphicsProtocol::Kitty);
+    term.draw_rect(Point::new(5., 5.), Point::new(20., 10.), &look, None, None);
+    let kitty = term.finalize();
+    assert!(kitty.starts_with("\x1b_Ga=T,f=100,m=0;iVBORw0KGgo"));
+    assert!(kitty.ends_with("\x1b\\\n"));
+
+    let mut term = TerminalWriter::with_protocol(GraphicsProtocol::Sixel);
+    term.draw_rect(Point::new(5., 5.), Point::new(20., 10.), &look, None, None);
+    let sixel = term.finalize();
+    assert!(sixel.starts_with("\x1bPq\"1;1;30;20#0;2;0;0;0"));
+    assert!(sixel.contains("#215;2;100;100;100"));
+    assert!(sixel.ends_with("-\x1b\\\n"));
+
+    let mut term = TerminalWriter::with_protocol(GraphicsProtocol::Text);
+    term.draw_rect(Point::new(5., 5.), Point::new(20., 10.), &look, None, None);
+    assert!(!term.finalize().contains('\x1b'));
+}
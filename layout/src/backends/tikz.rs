@@ -0,0 +1,448 @@
+//! TikZ rendering backend that emits a `tikzpicture` environment, for
+//! embedding graphs in LaTeX documents.
+//!
+//! The picture uses the coordinates of the SVG backend, in points, with the y
+//! axis pointing down. Colors are declared with `\definecolor` inside of the
+//! picture, so the output only needs `\usepackage{tikz}` in the preamble (or
+//! use `finalize_document` for a standalone document).
+//!
+//! ```
+//! # use layout::backends::tikz::TikZWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let mut tikz = TikZWriter::new();
+//! let look = StyleAttr::simple();
+//! tikz.draw_circle(Point::new(50., 50.), Point::new(60., 40.), &look, None);
+//! tikz.draw_text(Point::new(50., 50.), "a", &look);
+//! assert!(tikz.finalize().starts_with("\\begin{tikzpicture}"));
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::{draw_arrow_heads, ClipHandle, RenderBackend};
+use crate::core::geometry::{cubic_bezier, get_bezier_segments, Point};
+use crate::core::style::{ArrowHeadKind, StyleAttr};
+use crate::core::utils::format_number;
+
+/// Escape the characters that have a special meaning in LaTeX.
+fn escape_string(x: &str) -> String {
+    let mut res = String::new();
+    for c in x.chars() {
+        match c {
+            '\\' => res.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                res.push('\\');
+                res.push(c);
+            }
+            '~' => res.push_str("\\textasciitilde{}"),
+            '^' => res.push_str("\\textasciicircum{}"),
+            '<' => res.push_str("\\textless{}"),
+            '>' => res.push_str("\\textgreater{}"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+/// \returns the TikZ coordinate of \p p.
+fn coord(p: Point) -> String {
+    format!("({},{})", format_number(p.x), format_number(p.y))
+}
+
+#[derive(Debug)]
+pub struct TikZWriter {
+    content: String,
+    // The colors that are declared with \definecolor, in RGB.
+    colors: Vec<u32>,
+    // A list of clip regions: (top-left, size, rounded_px).
+    clips: Vec<(Point, Point, usize)>,
+    // The color of the background, or None for a transparent background.
+    background: Option<Color>,
+}
+
+impl TikZWriter {
+    pub fn new() -> TikZWriter {
+        TikZWriter {
+            content: String::new(),
+            colors: Vec::new(),
+            clips: Vec::new(),
+            background: None,
+        }
+    }
+
+    /// \returns the tikzpicture environment.
+    pub fn finalize(&self) -> String {
+        let mut result = String::new();
+        result.push_str("\\begin{tikzpicture}[x=1pt, y=-1pt]\n");
+        for (i, rgb) in self.colors.iter().enumerate() {
+            result.push_str(&format!(
+                "\\definecolor{{c{}}}{{HTML}}{{{:06X}}}\n",
+                i, rgb
+            ));
+        }
+        // The background fills the bounding box of the drawing, on a layer
+        // behind the shapes.
+        let mut content = self.content.clone();
+        if let Some(color) = self.background {
+            result.push_str("\\pgfdeclarelayer{background}\n");
+            result.push_str("\\pgfsetlayers{background,main}\n");
+            let (name, alpha) = self.find_color(color);
+            let mut options = vec![name];
+            if alpha < 1. {
+                options.push(format!("opacity={}", format_number(alpha)));
+            }
+            content.push_str(&format!(
+                "\\begin{{pgfonlayer}}{{background}}\n\\fill[{}] \
+                (current bounding box.south west) rectangle \
+                (current bounding box.north east);\n\\end{{pgfonlayer}}\n",
+                options.join(", ")
+            ));
+        }
+        result.push_str(&content);
+        result.push_str("\\end{tikzpicture}\n");
+        result
+    }
+
+    /// \returns a standalone LaTeX document that contains the picture.
+    pub fn finalize_document(&self) -> String {
+        let mut result = String::new();
+        result.push_str("\\documentclass[tikz]{standalone}\n");
+        result.push_str("\\begin{document}\n");
+        result.push_str(&self.finalize());
+        result.push_str("\\end{document}\n");
+        result
+    }
+}
+
+impl Default for TikZWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TikZWriter {
+    // Gets or declares the color \p color. Returns the name of the color and
+    // its opacity.
+    fn get_or_create_color(&mut self, color: Color) -> (String, f64) {
+        let (r, g, b, _) = color.to_rgba();
+        let rgb = ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
+        if !self.colors.contains(&rgb) {
+            self.colors.push(rgb);
+        }
+        self.find_color(color)
+    }
+
+    // Returns the name and the opacity of the declared color \p color.
+    fn find_color(&self, color: Color) -> (String, f64) {
+        let (r, g, b, a) = color.to_rgba();
+        let rgb = ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
+        let idx = self.colors.iter().position(|x| *x == rgb).unwrap();
+        (format!("c{}", idx), a as f64 / 255.)
+    }
+
+    // Returns the options that draw the outline of \p look.
+    fn stroke_options(&mut self, look: &StyleAttr) -> Vec<String> {
+        let (color, alpha) = self.get_or_create_color(look.line_color);
+        let mut options = vec![
+            format!("draw={}", color),
+            format!("line width={}pt", format_number(look.penwidth)),
+        ];
+        if alpha < 1. {
+            options.push(format!("draw opacity={}", format_number(alpha)));
+        }
+        if look.is_dashed() {
+            let mut pattern = Vec::new();
+            for (i, len) in look.dash.iter().enumerate() {
+                let kind = if i % 2 == 0 { "on" } else { "off" };
+                pattern.push(format!("{} {}pt", kind, format_number(*len)));
+            }
+            options.push(format!("dash pattern={}", pattern.join(" ")));
+        }
+        options
+    }
+
+    // Returns the options that draw the outline and fill of \p look.
+    fn shape_options(&mut self, look: &StyleAttr) -> Vec<String> {
+        let mut options = self.stroke_options(look);
+        if let Some(fill) = look.fill_color {
+            let (color, alpha) = self.get_or_create_color(fill);
+            if alpha > 0. {
+                options.push(format!("fill={}", color));
+            }
+            if alpha > 0. && alpha < 1. {
+                options.push(format!("fill opacity={}", format_number(alpha)));
+            }
+        }
+        options
+    }
+
+    // Draw the lines of \p text centered at \p xy.
+    fn draw_centered_text(&mut self, xy: Point, text: &str, font_size: usize) {
+        let lines: Vec<String> = text.lines().map(escape_string).collect();
+        if lines.is_empty() {
+            return;
+        }
+        self.content.push_str(&format!(
+            "\\node[font=\\fontsize{{{}}}{{{}}}\\selectfont, align=center, \
+            inner sep=0pt] at {} {{{}}};\n",
+            font_size,
+            font_size + 2,
+            coord(xy),
+            lines.join("\\\\")
+        ));
+    }
+}
+
+impl RenderBackend for TikZWriter {
+    fn set_background(&mut self, color: Color) {
+        self.get_or_create_color(color);
+        self.background = Some(color);
+    }
+
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str(&format!(
+                "\\begin{{scope}}\n\\clip[rounded corners={}pt] {} rectangle {};\n",
+                rounded_px,
+                coord(xy),
+                coord(xy.add(size))
+            ));
+        }
+        let mut options = self.shape_options(look);
+        if look.rounded > 0 {
+            options.push(format!("rounded corners={}pt", look.rounded));
+        }
+        self.content.push_str(&format!(
+            "\\path[{}] {} rectangle {};\n",
+            options.join(", "),
+            coord(xy),
+            coord(xy.add(size))
+        ));
+        if clip.is_some() {
+            self.content.push_str("\\end{scope}\n");
+        }
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        let options = self.stroke_options(look);
+        self.content.push_str(&format!(
+            "\\path[{}] {} -- {};\n",
+            options.join(", "),
+            coord(start),
+            coord(stop)
+        ));
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        let options = self.shape_options(look);
+        self.content.push_str(&format!(
+            "\\path[{}] {} ellipse ({}pt and {}pt);\n",
+            options.join(", "),
+            coord(xy),
+            format_number(size.x / 2.),
+            format_number(size.y / 2.)
+        ));
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        clip: Option<ClipHandle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let clip = clip.and_then(|handle| self.clips.get(handle).cloned());
+        if let Some((xy, size, rounded_px)) = clip {
+            self.content.push_str(&format!(
+                "\\begin{{scope}}\n\\clip[rounded corners={}pt] {} rectangle {};\n",
+                rounded_px,
+                coord(xy),
+                coord(xy.add(size))
+            ));
+        }
+        let options = self.shape_options(look);
+        let corners: Vec<String> = points.iter().map(|p| coord(*p)).collect();
+        self.content.push_str(&format!(
+            "\\path[{}] {} -- cycle;\n",
+            options.join(", "),
+            corners.join(" -- ")
+        ));
+        if clip.is_some() {
+            self.content.push_str("\\end{scope}\n");
+        }
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        if segments.is_empty() {
+            return;
+        }
+        let options = self.shape_options(look);
+        let mut curve = String::new();
+        let mut last: Option<Point> = None;
+        for (p0, p1, p2, p3) in segments.iter() {
+            if last != Some(*p0) {
+                curve.push_str(&format!(" {}", coord(*p0)));
+            }
+            curve.push_str(&format!(
+                " .. controls {} and {} .. {}",
+                coord(*p1),
+                coord(*p2),
+                coord(*p3)
+            ));
+            last = Some(*p3);
+        }
+        self.content.push_str(&format!(
+            "\\path[{}]{};\n",
+            options.join(", "),
+            curve
+        ));
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.draw_centered_text(xy, text, look.font_size);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        _properties: Option<String>,
+        text: &str,
+    ) {
+        let segments = get_bezier_segments(path);
+        if segments.is_empty() {
+            return;
+        }
+        let mut look = look.clone();
+        if dashed && !look.is_dashed() {
+            look.set_dash(&[5., 5.]);
+        }
+        // Use the arrow tips of TikZ for the normal heads, and draw the other
+        // heads as shapes.
+        let is_tip = |kind: ArrowHeadKind| {
+            kind == ArrowHeadKind::Normal && look.arrow_size == 1.
+        };
+        let tips = (
+            head.0 && is_tip(look.arrow_heads.0),
+            head.1 && is_tip(look.arrow_heads.1),
+        );
+        let shapes = (head.0 && !tips.0, head.1 && !tips.1);
+        let mut options = self.stroke_options(&look);
+        match tips {
+            (true, true) => options.push("<->".to_string()),
+            (true, false) => options.push("<-".to_string()),
+            (false, true) => options.push("->".to_string()),
+            (false, false) => {}
+        }
+
+        let mut curve = coord(segments[0].0);
+        for (_, c1, c2, end) in segments.iter() {
+            curve.push_str(&format!(
+                " .. controls {} and {} .. {}",
+                coord(*c1),
+                coord(*c2),
+                coord(*end)
+            ));
+        }
+        self.content.push_str(&format!(
+            "\\path[{}] {};\n",
+            options.join(", "),
+            curve
+        ));
+        draw_arrow_heads(self, path, shapes, &look);
+
+        if !text.is_empty() {
+            // Place the label above the middle of the path.
+            let (p0, p1, p2, p3) = segments[segments.len() / 2];
+            let t = if segments.len() % 2 == 1 { 0.5 } else { 0. };
+            let mid = cubic_bezier(p0, p1, p2, p3, t);
+            let offset = Point::new(0., look.font_size as f64 / 2.);
+            self.draw_centered_text(mid.sub(offset), text, look.font_size);
+        }
+    }
+
+    fn create_clip(
+        &mut self,
+        xy: Point,
+        size: Point,
+        rounded_px: usize,
+    ) -> ClipHandle {
+        let handle = self.clips.len();
+        self.clips.push((xy, size, rounded_px));
+        handle
+    }
+}
+
+#[test]
+fn test_tikz_writer() {
+    assert_eq!(escape_string("a_b{c}~"), "a\\_b\\{c\\}\\textasciitilde{}");
+
+    let mut tikz = TikZWriter::new();
+    let look = StyleAttr::simple();
+    tikz.draw_rect(
+        Point::new(10., 10.),
+        Point::new(40., 20.5),
+        &look,
+        None,
+        None,
+    );
+    tikz.draw_text(Point::new(30., 20.), "a\nb", &look);
+    let path = [
+        (Point::new(0., 0.), Point::new(10., 0.)),
+        (Point::new(20., 10.), Point::new(30., 10.)),
+    ];
+    tikz.draw_arrow(&path, true, (false, true), &look, None, "");
+
+    let output = tikz.finalize();
+    assert!(output.contains("\\definecolor{c0}{HTML}{000000}"));
+    assert!(output.contains("\\definecolor{c1}{HTML}{FFFFFF}"));
+    assert!(output.contains(
+        "\\path[draw=c0, line width=2pt, fill=c1] (10,10) rectangle (50,30.5);"
+    ));
+    assert!(output.contains("{a\\\\b};"));
+    assert!(output.contains(
+        "dash pattern=on 5pt off 5pt, ->] (0,0) .. controls (10,0) and \
+        (20,10) .. (30,10);"
+    ));
+    assert!(!output.contains("pgfonlayer"));
+
+    // The background is drawn behind the shapes.
+    tikz.set_background(Color::fast("navy"));
+    let output = tikz.finalize();
+    assert!(output.contains("\\definecolor{c2}{HTML}{000080}"));
+    assert!(output.contains("\\pgfsetlayers{background,main}"));
+    assert!(output.contains(
+        "\\begin{pgfonlayer}{background}\n\\fill[c2] (current bounding \
+        box.south west) rectangle (current bounding box.north east);"
+    ));
+}
@@ -0,0 +1,134 @@
+//! Splits drawings that are larger than a page into a grid of pages, so that
+//! posters can be printed on standard printers and glued together.
+//!
+//! The pages can overlap, so that the sheets can be trimmed and glued, and
+//! crop marks show where to cut. The SVG writer writes one document per page,
+//! and the PDF writer writes one document with a page per tile.
+//!
+//! ```
+//! # use layout::backends::tiles::TileConfig;
+//! # use layout::core::geometry::Point;
+//! let mut config = TileConfig::new(Point::new(100., 100.));
+//! config.overlap = 20.;
+//! // Pages of 100 points, that advance by 80 points.
+//! let tiles = config.get_tiles(Point::new(170., 90.));
+//! assert_eq!(tiles.len(), 2);
+//! ```
+
+use crate::core::geometry::Point;
+use crate::topo::placer::PageDir;
+
+/// The length of the crop marks, from the edge of the page.
+pub const CROP_MARK_LENGTH: f64 = 12.;
+
+/// Controls how a drawing is split into pages.
+#[derive(Debug, Clone, Copy)]
+pub struct TileConfig {
+    /// The size of the pages, in the coordinates of the backend.
+    pub page: Point,
+    /// The width of the strip that neighboring pages share.
+    pub overlap: f64,
+    /// The order of the pages.
+    pub order: PageDir,
+    /// Draw the lines to cut the pages along, in the middle of the overlap.
+    pub crop_marks: bool,
+}
+
+impl TileConfig {
+    pub fn new(page: Point) -> Self {
+        Self {
+            page,
+            overlap: 0.,
+            order: PageDir::default(),
+            crop_marks: false,
+        }
+    }
+
+    /// \returns the overlap along the x and the y axis, limited to half of
+    /// the page, so that the pages always advance.
+    fn get_overlap(&self) -> Point {
+        Point::new(
+            self.overlap.clamp(0., self.page.x / 2.),
+            self.overlap.clamp(0., self.page.y / 2.),
+        )
+    }
+
+    /// \returns the top-left corners of the pages that cover a canvas with
+    /// the size \p canvas, in the order of the pages.
+    pub fn get_tiles(&self, canvas: Point) -> Vec<Point> {
+        if self.page.x <= 0. || self.page.y <= 0. {
+            return Vec::new();
+        }
+        let overlap = self.get_overlap();
+        // Ignore the rounding errors of the scaled canvas, that would add
+        // empty pages.
+        let stride = self.page.sub(overlap);
+        let count = |len: f64, page: f64, stride: f64| {
+            let rest = (len - page).max(0.);
+            1 + (rest / stride - 1e-9).ceil().max(0.) as usize
+        };
+        let columns = count(canvas.x, self.page.x, stride.x);
+        let rows = count(canvas.y, self.page.y, stride.y);
+        self.order
+            .get_order(columns, rows)
+            .iter()
+            .map(|(x, y)| {
+                Point::new(*x as f64 * stride.x, *y as f64 * stride.y)
+            })
+            .collect()
+    }
+
+    /// \returns the crop marks of the page with the top-left corner \p tile,
+    /// as lines from one point to the other. The marks start at the edges of
+    /// the page, in the middle of the overlap.
+    pub fn get_crop_marks(&self, tile: Point) -> Vec<(Point, Point)> {
+        let inset = self.get_overlap().scale(0.5);
+        let end = tile.add(self.page);
+        let len = CROP_MARK_LENGTH;
+        let mut res = Vec::new();
+        for x in [tile.x + inset.x, end.x - inset.x] {
+            res.push((Point::new(x, tile.y), Point::new(x, tile.y + len)));
+            res.push((Point::new(x, end.y - len), Point::new(x, end.y)));
+        }
+        for y in [tile.y + inset.y, end.y - inset.y] {
+            res.push((Point::new(tile.x, y), Point::new(tile.x + len, y)));
+            res.push((Point::new(end.x - len, y), Point::new(end.x, y)));
+        }
+        res
+    }
+}
+
+#[test]
+fn test_tiles() {
+    let mut config = TileConfig::new(Point::new(100., 50.));
+    // The canvas fits on one page.
+    assert_eq!(config.get_tiles(Point::new(80., 50.)), vec![Point::zero()]);
+
+    // Two columns and two rows, that start at the bottom left.
+    let tiles = config.get_tiles(Point::new(150., 60.));
+    assert_eq!(
+        tiles,
+        vec![
+            Point::new(0., 50.),
+            Point::new(100., 50.),
+            Point::new(0., 0.),
+            Point::new(100., 0.),
+        ]
+    );
+
+    // The overlap needs a third column.
+    config.overlap = 30.;
+    config.order = PageDir::from_name("TL").unwrap();
+    let tiles = config.get_tiles(Point::new(180., 50.));
+    assert_eq!(
+        tiles,
+        vec![Point::zero(), Point::new(70., 0.), Point::new(140., 0.)]
+    );
+
+    // The overlap is limited to half of the page.
+    config.overlap = 80.;
+    let marks = config.get_crop_marks(Point::zero());
+    assert_eq!(marks.len(), 8);
+    assert_eq!(marks[0], (Point::new(25., 0.), Point::new(25., 12.)));
+    assert_eq!(marks[4], (Point::new(0., 12.5), Point::new(12., 12.5)));
+}
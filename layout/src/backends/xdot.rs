@@ -0,0 +1,476 @@
+//! A backend that converts the draw calls into the drawing operations of the
+//! xdot format, that Graphviz embeds in the `_draw_` and `_ldraw_` attributes
+//! of the graph, of the nodes and of the edges. Viewers such as xdot.py draw
+//! graphs from these attributes. See <https://graphviz.org/docs/outputs/canon/>.
+//!
+//! The operations of each element are collected separately. Draw an element,
+//! and then call take_attributes to get the attributes of the element. The
+//! coordinates are in points, and the y axis points up, like in Graphviz.
+//!
+//! ```
+//! # use layout::backends::xdot::XDotWriter;
+//! # use layout::core::format::RenderBackend;
+//! # use layout::core::geometry::Point;
+//! # use layout::core::style::StyleAttr;
+//! let bbox = (Point::zero(), Point::new(100., 100.));
+//! let mut xdot = XDotWriter::new(bbox);
+//! let look = StyleAttr::simple();
+//! xdot.draw_circle(Point::new(50., 20.), Point::new(60., 40.), &look, None);
+//! xdot.draw_text(Point::new(50., 20.), "a", &look);
+//! let attrs = xdot.take_attributes();
+//! assert_eq!(attrs[0].0, "_draw_");
+//! assert!(attrs[0].1.ends_with("E 50 80 30 20"));
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::{ClipHandle, RenderBackend};
+use crate::core::geometry::{
+    cubic_bezier, get_arrow_heads, get_bezier_segments, HeadShape, Point,
+};
+use crate::core::style::StyleAttr;
+use crate::core::text::get_size_for_text;
+use crate::core::utils::format_number;
+
+/// The version of the xdot format that the operations follow.
+pub const XDOT_VERSION: &str = "1.7";
+
+/// The ratio between the control points of a bezier quarter circle and its
+/// radius.
+const KAPPA: f64 = 0.552_284_8;
+
+/// The distance of the baseline of text below the center of the line, as a
+/// fraction of the font size.
+const BASELINE_OFFSET: f64 = 0.3;
+
+// The kinds of state that the operations set, such as the color of the pen.
+const PEN_COLOR: usize = 0;
+const FILL_COLOR: usize = 1;
+const DASH_STYLE: usize = 2;
+const LINE_WIDTH: usize = 3;
+const FONT: usize = 4;
+const FONT_FLAGS: usize = 5;
+
+/// The operations of one of the xdot attributes. The state that operations
+/// set, such as the color of the pen, stays until it's set again, so it's
+/// only set when it changes.
+#[derive(Debug, Default)]
+struct Operations {
+    ops: Vec<String>,
+    state: [String; 6],
+}
+
+impl Operations {
+    fn set(&mut self, kind: usize, op: String) {
+        if self.state[kind] != op {
+            self.ops.push(op.clone());
+            self.state[kind] = op;
+        }
+    }
+
+    fn push(&mut self, op: String) {
+        self.ops.push(op);
+    }
+
+    fn take(&mut self) -> String {
+        let res = self.ops.join(" ");
+        *self = Self::default();
+        res
+    }
+}
+
+/// The xdot attributes, in the order in which they are written.
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    /// The shapes of the element.
+    Draw,
+    /// The labels of the element.
+    Label,
+    /// The arrow head at the head of an edge.
+    Head,
+    /// The arrow head at the tail of an edge.
+    Tail,
+}
+
+const TARGET_NAMES: [&str; 4] = ["_draw_", "_ldraw_", "_hdraw_", "_tdraw_"];
+
+#[derive(Debug)]
+pub struct XDotWriter {
+    // The drawing is flipped around the middle of this range of y values.
+    flip: f64,
+    targets: [Operations; 4],
+}
+
+impl XDotWriter {
+    /// Create a writer for a drawing with the bounding box \p bbox, that is
+    /// flipped so that the y axis points up.
+    pub fn new(bbox: (Point, Point)) -> Self {
+        Self {
+            flip: bbox.0.y + bbox.1.y,
+            targets: Default::default(),
+        }
+    }
+
+    /// \returns the xdot attributes, such as '_draw_', with the operations
+    /// that were drawn since the last call, and starts a new element. Empty
+    /// attributes are omitted.
+    pub fn take_attributes(&mut self) -> Vec<(String, String)> {
+        let mut res = Vec::new();
+        for (i, target) in self.targets.iter_mut().enumerate() {
+            let ops = target.take();
+            if !ops.is_empty() {
+                res.push((TARGET_NAMES[i].to_string(), ops));
+            }
+        }
+        res
+    }
+
+    fn target(&mut self, target: Target) -> &mut Operations {
+        &mut self.targets[target as usize]
+    }
+
+    /// \returns the coordinates of \p p, with the y axis pointing up.
+    fn coord(&self, p: Point) -> String {
+        format!("{} {}", format_number(p.x), format_number(self.flip - p.y))
+    }
+
+    fn coords(&self, points: &[Point]) -> String {
+        let points: Vec<String> =
+            points.iter().map(|p| self.coord(*p)).collect();
+        format!("{} {}", points.len(), points.join(" "))
+    }
+
+    /// Set the color, the dashes and the width of the lines of \p look.
+    fn set_pen(&mut self, target: Target, look: &StyleAttr) {
+        let ops = self.target(target);
+        ops.set(PEN_COLOR, format!("c {}", text(&color(look.line_color))));
+        let dash = if !look.is_dashed() {
+            "solid"
+        } else if look.dash.first().is_some_and(|x| *x < 2.) {
+            "dotted"
+        } else {
+            "dashed"
+        };
+        ops.set(DASH_STYLE, format!("S {}", text(dash)));
+        let width = format!("setlinewidth({})", format_number(look.penwidth));
+        ops.set(LINE_WIDTH, format!("S {}", text(&width)));
+    }
+
+    /// Set the pen of \p look, and its fill color. \returns true if the shape
+    /// is filled.
+    fn set_pen_and_fill(&mut self, target: Target, look: &StyleAttr) -> bool {
+        self.set_pen(target, look);
+        let fill = look.fill_color.filter(|x| x.to_rgba().3 > 0);
+        if let Option::Some(fill) = fill {
+            let op = format!("C {}", text(&color(fill)));
+            self.target(target).set(FILL_COLOR, op);
+        }
+        fill.is_some()
+    }
+
+    /// Draw the cubic bezier \p segments, as one operation for each
+    /// connected part of the path.
+    fn push_segments(
+        &mut self,
+        target: Target,
+        segments: &[(Point, Point, Point, Point)],
+        filled: bool,
+    ) {
+        let op = if filled { "b" } else { "B" };
+        let mut points: Vec<Point> = Vec::new();
+        let mut parts = Vec::new();
+        for seg in segments {
+            if points.last() != Some(&seg.0) {
+                if !points.is_empty() {
+                    parts.push(std::mem::take(&mut points));
+                }
+                points.push(seg.0);
+            }
+            points.extend([seg.1, seg.2, seg.3]);
+        }
+        parts.push(points);
+        for part in parts.iter().filter(|x| x.len() > 1) {
+            let op = format!("{} {}", op, self.coords(part));
+            self.target(target).push(op);
+        }
+    }
+
+    /// Draw the lines of \p label centered at \p xy.
+    fn push_text(&mut self, xy: Point, label: &str, look: &StyleAttr) {
+        let lines: Vec<&str> = label.lines().collect();
+        if lines.is_empty() {
+            return;
+        }
+        let font_size = look.font_size as f64;
+        let font = format!("F {} {}", look.font_size, text(&look.font_name));
+        let flags = look.font_bold as usize + 2 * look.font_italic as usize;
+        let font_color = format!("c {}", text(&color(look.font_color)));
+        let top = xy.y - font_size * lines.len() as f64 / 2.;
+        for (i, line) in lines.iter().enumerate() {
+            let y = top + font_size * (i as f64 + 0.5 + BASELINE_OFFSET);
+            let width = get_size_for_text(line, look).x;
+            let op = format!(
+                "T {} 0 {} {}",
+                self.coord(Point::new(xy.x, y)),
+                format_number(width),
+                text(line)
+            );
+            let ops = self.target(Target::Label);
+            ops.set(FONT, font.clone());
+            ops.set(FONT_FLAGS, format!("t {}", flags));
+            ops.set(PEN_COLOR, font_color.clone());
+            ops.push(op);
+        }
+    }
+}
+
+/// \returns the string \p s in the xdot syntax, that is the number of bytes
+/// in the string, followed by a dash and the string.
+fn text(s: &str) -> String {
+    format!("{} -{}", s.len(), s)
+}
+
+/// \returns the color \p color as "#rrggbb", with the alpha channel only if
+/// the color is translucent.
+fn color(color: Color) -> String {
+    let web = color.to_web_color();
+    match web.strip_suffix("ff") {
+        Option::Some(rgb) if web.len() == 9 => rgb.to_string(),
+        _ => web,
+    }
+}
+
+/// \returns the bezier segments of the box at \p xy with the size \p size, and
+/// corners with the radius \p r.
+fn rounded_rect(
+    xy: Point,
+    size: Point,
+    r: f64,
+) -> Vec<(Point, Point, Point, Point)> {
+    let r = r.min(size.x / 2.).min(size.y / 2.);
+    let (x0, y0) = (xy.x, xy.y);
+    let (x1, y1) = (xy.x + size.x, xy.y + size.y);
+    let k = r * (1. - KAPPA);
+    let p = Point::new;
+    let line = |a: Point, b: Point| (a, a, b, b);
+    vec![
+        line(p(x0 + r, y0), p(x1 - r, y0)),
+        (p(x1 - r, y0), p(x1 - k, y0), p(x1, y0 + k), p(x1, y0 + r)),
+        line(p(x1, y0 + r), p(x1, y1 - r)),
+        (p(x1, y1 - r), p(x1, y1 - k), p(x1 - k, y1), p(x1 - r, y1)),
+        line(p(x1 - r, y1), p(x0 + r, y1)),
+        (p(x0 + r, y1), p(x0 + k, y1), p(x0, y1 - k), p(x0, y1 - r)),
+        line(p(x0, y1 - r), p(x0, y0 + r)),
+        (p(x0, y0 + r), p(x0, y0 + k), p(x0 + k, y0), p(x0 + r, y0)),
+    ]
+}
+
+impl RenderBackend for XDotWriter {
+    fn draw_rect(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+        _clip: Option<ClipHandle>,
+    ) {
+        let filled = self.set_pen_and_fill(Target::Draw, look);
+        if look.rounded > 0 {
+            let segments = rounded_rect(xy, size, look.rounded as f64);
+            self.push_segments(Target::Draw, &segments, filled);
+            return;
+        }
+        let points = [
+            xy,
+            Point::new(xy.x + size.x, xy.y),
+            xy.add(size),
+            Point::new(xy.x, xy.y + size.y),
+        ];
+        let op = if filled { "P" } else { "p" };
+        let op = format!("{} {}", op, self.coords(&points));
+        self.target(Target::Draw).push(op);
+    }
+
+    fn draw_line(
+        &mut self,
+        start: Point,
+        stop: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        self.set_pen(Target::Draw, look);
+        let op = format!("L {}", self.coords(&[start, stop]));
+        self.target(Target::Draw).push(op);
+    }
+
+    fn draw_circle(
+        &mut self,
+        xy: Point,
+        size: Point,
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        let filled = self.set_pen_and_fill(Target::Draw, look);
+        let op = format!(
+            "{} {} {} {}",
+            if filled { "E" } else { "e" },
+            self.coord(xy),
+            format_number(size.x / 2.),
+            format_number(size.y / 2.)
+        );
+        self.target(Target::Draw).push(op);
+    }
+
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        _properties: Option<String>,
+        _clip: Option<ClipHandle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let filled = self.set_pen_and_fill(Target::Draw, look);
+        let op = if filled { "P" } else { "p" };
+        let op = format!("{} {}", op, self.coords(points));
+        self.target(Target::Draw).push(op);
+    }
+
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        _properties: Option<String>,
+    ) {
+        if segments.is_empty() {
+            return;
+        }
+        let filled = self.set_pen_and_fill(Target::Draw, look);
+        self.push_segments(Target::Draw, segments, filled);
+    }
+
+    fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr) {
+        self.push_text(xy, text, look);
+    }
+
+    fn draw_arrow(
+        &mut self,
+        path: &[(Point, Point)],
+        dashed: bool,
+        head: (bool, bool),
+        look: &StyleAttr,
+        _properties: Option<String>,
+        text: &str,
+    ) {
+        let segments = get_bezier_segments(path);
+        if segments.is_empty() {
+            return;
+        }
+        let mut look = look.clone();
+        if dashed && !look.is_dashed() {
+            look.set_dash(&[5., 5.]);
+        }
+        self.set_pen(Target::Draw, &look);
+        self.push_segments(Target::Draw, &segments, false);
+
+        // The heads are solid, and filled with the color of the line, or
+        // with white.
+        for (target, head) in [
+            (Target::Tail, (head.0, false)),
+            (Target::Head, (false, head.1)),
+        ] {
+            for shape in get_arrow_heads(path, head, &look) {
+                let mut head_look = look.clone();
+                head_look.dash.clear();
+                head_look.penwidth = 1.;
+                let filled = match &shape {
+                    HeadShape::Polygon(_, filled) => *filled,
+                    HeadShape::Circle(_, _, filled) => *filled,
+                };
+                head_look.fill_color = Some(if filled {
+                    look.line_color
+                } else {
+                    Color::fast("white")
+                });
+                self.set_pen_and_fill(target, &head_look);
+                let op = match shape {
+                    HeadShape::Polygon(points, _) => {
+                        format!("P {}", self.coords(&points))
+                    }
+                    HeadShape::Circle(center, radius, _) => {
+                        let r = format_number(radius);
+                        format!("E {} {} {}", self.coord(center), r, r)
+                    }
+                };
+                self.target(target).push(op);
+            }
+        }
+
+        if !text.is_empty() {
+            // Place the label above the middle of the path.
+            let (p0, p1, p2, p3) = segments[segments.len() / 2];
+            let t = if segments.len() % 2 == 1 { 0.5 } else { 0. };
+            let mid = cubic_bezier(p0, p1, p2, p3, t);
+            let offset = Point::new(0., look.font_size as f64 / 2.);
+            self.push_text(mid.sub(offset), text, &look);
+        }
+    }
+
+    fn create_clip(
+        &mut self,
+        _xy: Point,
+        _size: Point,
+        _rounded_px: usize,
+    ) -> ClipHandle {
+        // The xdot format has no clip regions, and the shapes are drawn
+        // whole.
+        0
+    }
+}
+
+#[test]
+fn test_xdot_writer() {
+    assert_eq!(text("a b"), "3 -a b");
+    assert_eq!(color(Color::fast("red")), "#ff0000");
+
+    let bbox = (Point::zero(), Point::new(100., 50.));
+    let mut xdot = XDotWriter::new(bbox);
+    let mut look = StyleAttr::simple();
+    xdot.draw_rect(
+        Point::new(10., 10.),
+        Point::new(40., 20.),
+        &look,
+        None,
+        None,
+    );
+    look.set_dash(&[5., 5.]);
+    look.fill_color = None;
+    xdot.draw_line(Point::new(0., 0.), Point::new(10., 0.), &look, None);
+    xdot.draw_text(Point::new(30., 20.), "a\nbc", &look);
+    let attrs = xdot.take_attributes();
+    assert_eq!(attrs.len(), 2);
+    assert_eq!(
+        attrs[0].1,
+        "c 7 -#000000 S 5 -solid S 15 -setlinewidth(2) C 7 -#ffffff \
+        P 4 10 40 50 40 50 20 10 20 S 6 -dashed L 2 0 50 10 50"
+    );
+    assert_eq!(attrs[1].0, "_ldraw_");
+    assert_eq!(
+        attrs[1].1,
+        "F 15 5 -Times t 0 c 7 -#000000 T 30 33 0 6.66 1 -a \
+        T 30 18 0 14.16 2 -bc"
+    );
+    assert!(xdot.take_attributes().is_empty());
+
+    // The arrow heads are in their own attributes.
+    let path = [
+        (Point::new(0., 0.), Point::new(10., 0.)),
+        (Point::new(20., 10.), Point::new(30., 10.)),
+    ];
+    xdot.draw_arrow(&path, false, (false, true), &look, None, "x");
+    let attrs = xdot.take_attributes();
+    let names: Vec<&str> = attrs.iter().map(|x| x.0.as_str()).collect();
+    assert_eq!(names, ["_draw_", "_ldraw_", "_hdraw_"]);
+    assert!(attrs[0].1.ends_with("B 4 0 50 10 50 20 40 30 40"));
+    assert!(attrs[2].1.contains(" S 5 -solid S 15 -setlinewidth(1) "));
+}
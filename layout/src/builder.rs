@@ -0,0 +1,1462 @@
+//! A fluent API for constructing graphs in code, without writing DOT text or
+//! creating the elements and the arrows of the graph by hand. Nodes are
+//! identified by their names, and edges refer to the names of their ends.
+//! Nodes that are only mentioned by edges get the default style, like in DOT
+//! files.
+//!
+//! ```rust
+//! use layout::backends::svg::SVGWriter;
+//! use layout::builder::{GraphBuilder, Shape};
+//!
+//! let mut graph = GraphBuilder::new();
+//! graph.node("a").shape(Shape::Box).fill("#ddf");
+//! graph.edge("a", "b").label("x");
+//!
+//! let mut vg = graph.build();
+//! let mut svg = SVGWriter::new();
+//! vg.do_it(false, false, false, &mut svg);
+//! ```
+
+use crate::adt::dag::NodeHandle;
+use crate::core::base::{LayoutQuality, Orientation, SplineKind};
+use crate::core::color::Color;
+use crate::core::format::Link;
+use crate::core::geometry::Point;
+use crate::core::style::{ArrowHeadKind, LineStyleKind, StyleAttr};
+use crate::core::text::TextMetrics;
+use crate::core::utils::{escape_xml, format_number};
+use crate::er::{Cardinality, TableNode};
+use crate::gv::builder::get_edge_ids;
+use crate::gv::parser::ast;
+use crate::gv::record::record_builder;
+use crate::gv::writer::{
+    merge_attributes, write_dot, write_plain, LayoutAttributes,
+};
+use crate::std_shapes::render::get_shape_size;
+use crate::std_shapes::shapes::*;
+use crate::topo::layout::{LayoutResult, VisualGraph, WarmStart};
+use crate::topo::placer::{
+    OverlapConfig, PackConfig, PackMode, PageDir, Ratio, SizeConfig,
+};
+use crate::uml::{ClassNode, Relation};
+use std::collections::HashMap;
+
+/// The default size of the text of nodes, edges and graph titles.
+const DEFAULT_FONT_SIZE: usize = 14;
+
+/// The 'width' and 'height' attributes of nodes are in inches.
+const POINTS_PER_INCH: f64 = 72.;
+
+/// The fill color and the corner radius of the nodes of collapsed groups.
+const GROUP_FILL_COLOR: &str = "#eeeeee";
+const GROUP_ROUNDED_RADIUS: usize = 8;
+
+/// The shape of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+    #[default]
+    Ellipse,
+    Circle,
+    DoubleCircle,
+    Box,
+    Square,
+    // A small filled circle, without a label.
+    Point,
+    // The label describes the fields of the record, like "<f0> a|{b|c}".
+    Record,
+    Polygon(PolygonKind),
+}
+
+/// The description of a node, before it's added to the graph.
+#[derive(Debug, Clone)]
+struct NodeDesc {
+    name: String,
+    label: Option<String>,
+    shape: Shape,
+    look: StyleAttr,
+    // Set if the fill color was selected by the user.
+    has_fill: bool,
+    min_size: Option<Point>,
+    tooltip: Option<String>,
+    lane: Option<String>,
+    // The start and the end of the node on the axis of the timeline layout.
+    span: Option<(f64, f64)>,
+    // The compartments of a UML class, that replace the label.
+    class: Option<ClassNode>,
+    // The columns of a table of an ER diagram, that replace the shape.
+    table: Option<TableNode>,
+}
+
+impl NodeDesc {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            label: None,
+            shape: Shape::default(),
+            look: default_style(Some(Color::fast("white"))),
+            has_fill: false,
+            min_size: None,
+            tooltip: None,
+            lane: None,
+            span: None,
+            class: None,
+            table: None,
+        }
+    }
+
+    /// \returns the element that draws the node in a graph with the
+    /// orientation \p dir, with a label that is measured with \p metrics.
+    fn to_element(&self, dir: Orientation, metrics: TextMetrics) -> Element {
+        let text = self.get_label(dir);
+        let mut look = self.look.clone();
+        look.text_metrics = metrics;
+        let (shape, make_xy_same) = if let Option::Some(table) = &self.table {
+            (ShapeKind::Html(table.to_html()), false)
+        } else {
+            match self.shape {
+                Shape::Ellipse => (ShapeKind::Circle(text), false),
+                Shape::Circle => (ShapeKind::Circle(text), true),
+                Shape::DoubleCircle => (ShapeKind::DoubleCircle(text), true),
+                Shape::Box => (ShapeKind::Box(text), false),
+                Shape::Square => (ShapeKind::Box(text), true),
+                Shape::Point => (ShapeKind::Point, false),
+                Shape::Record => (record_builder(&text), false),
+                Shape::Polygon(kind) => (ShapeKind::Polygon(kind, text), false),
+            }
+        };
+
+        // Points are filled with the color of their outline.
+        if self.shape == Shape::Point && !self.has_fill {
+            look.fill_color = Some(look.line_color);
+        }
+
+        // Records grow to the left in graphs that grow top down.
+        let dir = dir.flip();
+        let mut size = get_shape_size(dir, &shape, &look, make_xy_same);
+        if let Option::Some(min_size) = self.min_size {
+            size = Point::new(size.x.max(min_size.x), size.y.max(min_size.y));
+        }
+
+        let mut attrs = vec![
+            ("id", self.name.as_str()),
+            ("class", "node"),
+            ("data-node", self.name.as_str()),
+        ];
+        if let Option::Some(tooltip) = &self.tooltip {
+            attrs.push(("data-tooltip", tooltip));
+        }
+        let mut elem = Element::create_with_properties(
+            shape,
+            look,
+            dir,
+            size,
+            get_properties(&attrs),
+        );
+        elem.link = self.tooltip.as_ref().map(|tooltip| Link {
+            tooltip: Some(tooltip.clone()),
+            ..Link::default()
+        });
+        elem
+    }
+
+    /// \returns the text of the node in a graph with the orientation \p dir.
+    fn get_label(&self, dir: Orientation) -> String {
+        if let Option::Some(class) = &self.class {
+            return class.to_record_label(dir);
+        }
+        if let Option::Some(table) = &self.table {
+            return table.title().to_string();
+        }
+        self.label.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// \returns the DOT attributes that describe the node in a graph with the
+    /// orientation \p dir, without the attributes that have the default
+    /// value.
+    fn to_attributes(&self, dir: Orientation) -> Vec<(String, String)> {
+        let mut res = Vec::new();
+        let mut add = |key: &str, val: String| res.push((key.to_string(), val));
+        let label = self.get_label(dir);
+        if self.label.is_some() || self.class.is_some() || label != self.name {
+            add("label", label);
+        }
+        let shape = match self.shape {
+            _ if self.table.is_some() => Some("table"),
+            Shape::Ellipse => None,
+            Shape::Circle => Some("circle"),
+            Shape::DoubleCircle => Some("doublecircle"),
+            Shape::Box => Some("box"),
+            Shape::Square => Some("square"),
+            Shape::Point => Some("point"),
+            Shape::Record => Some("record"),
+            Shape::Polygon(kind) => Some(kind.name()),
+        };
+        if let Option::Some(shape) = shape {
+            add("shape", shape.to_string());
+        }
+        if let Option::Some(table) = &self.table {
+            add("columns", table.to_columns_attr());
+        }
+        let look = &self.look;
+        let mut style = Vec::new();
+        if self.has_fill {
+            if let Option::Some(fill) = look.fill_color {
+                add("fillcolor", dot_color(fill));
+                style.push("filled");
+            }
+        }
+        if look.rounded > 0 {
+            style.push("rounded");
+        }
+        style.extend(line_style(look));
+        if !style.is_empty() {
+            add("style", style.join(","));
+        }
+        add_look_attributes(look, &mut add);
+        if let Option::Some(size) = self.min_size {
+            add("width", format_number(size.x / POINTS_PER_INCH));
+            add("height", format_number(size.y / POINTS_PER_INCH));
+        }
+        if let Option::Some(tooltip) = &self.tooltip {
+            add("tooltip", tooltip.clone());
+        }
+        if let Option::Some(lane) = &self.lane {
+            add("lane", lane.clone());
+        }
+        if let Option::Some((start, end)) = self.span {
+            add("start", format_number(start));
+            if end != start {
+                add("end", format_number(end));
+            }
+        }
+        res
+    }
+}
+
+/// The description of an edge, before it's added to the graph.
+#[derive(Debug, Clone)]
+struct EdgeDesc {
+    from: String,
+    to: String,
+    arrow: Arrow,
+}
+
+impl EdgeDesc {
+    /// \returns the DOT attributes that describe the edge, without the
+    /// attributes that have the default value.
+    fn to_attributes(&self) -> Vec<(String, String)> {
+        let mut res = Vec::new();
+        let mut add = |key: &str, val: String| res.push((key.to_string(), val));
+        let arrow = &self.arrow;
+        for (key, text) in [
+            ("label", &arrow.text),
+            ("headlabel", &arrow.head_label),
+            ("taillabel", &arrow.tail_label),
+        ] {
+            if !text.is_empty() {
+                add(key, text.clone());
+            }
+        }
+        let style = match arrow.line_style {
+            LineStyleKind::Normal => None,
+            LineStyleKind::Dashed => Some("dashed"),
+            LineStyleKind::Dotted => Some("dotted"),
+            LineStyleKind::None => Some("invis"),
+        };
+        if let Option::Some(style) = style {
+            add("style", style.to_string());
+        }
+        let dir = match (&arrow.start, &arrow.end) {
+            (LineEndKind::None, LineEndKind::Arrow) => None,
+            (LineEndKind::Arrow, LineEndKind::None) => Some("back"),
+            (LineEndKind::Arrow, LineEndKind::Arrow) => Some("both"),
+            (LineEndKind::None, LineEndKind::None) => Some("none"),
+        };
+        if let Option::Some(dir) = dir {
+            add("dir", dir.to_string());
+        }
+        let heads = [
+            ("arrowtail", &arrow.start, arrow.look.arrow_heads.0),
+            ("arrowhead", &arrow.end, arrow.look.arrow_heads.1),
+        ];
+        for (key, end, kind) in heads {
+            if matches!(end, LineEndKind::Arrow)
+                && kind != ArrowHeadKind::Normal
+            {
+                add(key, kind.name().to_string());
+            }
+        }
+        add_look_attributes(&arrow.look, &mut add);
+        if arrow.min_len != 1 {
+            add("minlen", arrow.min_len.to_string());
+        }
+        if arrow.weight != 1. {
+            add("weight", format_number(arrow.weight));
+        }
+        if !arrow.constraint {
+            add("constraint", "false".to_string());
+        }
+        res
+    }
+}
+
+/// The description of a group of nodes, that is drawn as a cluster around
+/// its nodes, or as a single node that stands for all of them when it's
+/// collapsed.
+#[derive(Debug, Clone)]
+struct GroupDesc {
+    name: String,
+    label: Option<String>,
+    // The names of the nodes of the group.
+    nodes: Vec<String>,
+    collapsed: bool,
+}
+
+impl GroupDesc {
+    /// \returns the text of the cluster of the expanded group.
+    fn get_label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// \returns the node that replaces the nodes of the collapsed group. The
+    /// node is named after the group, and its label tells how many nodes it
+    /// stands for.
+    fn to_node(&self) -> NodeDesc {
+        let mut node = NodeDesc::new(&self.name);
+        node.label =
+            Some(format!("{} ({})", self.get_label(), self.nodes.len()));
+        node.shape = Shape::Box;
+        node.look.fill_color = Some(Color::fast(GROUP_FILL_COLOR));
+        node.look.rounded = GROUP_ROUNDED_RADIUS;
+        node.has_fill = true;
+        node
+    }
+}
+
+/// The nodes and the edges that build() adds to the graph, after the
+/// collapsed groups are replaced by their nodes.
+struct GraphView<'a> {
+    nodes: Vec<NodeDesc>,
+    edges: Vec<EdgeDesc>,
+    // The expanded groups, and the indices of their nodes in the view.
+    clusters: Vec<(&'a GroupDesc, Vec<usize>)>,
+    // Maps the names of the nodes to their index in the view. The nodes of
+    // collapsed groups, and the groups, map to the node of the group.
+    index: HashMap<String, usize>,
+}
+
+/// \returns the DOT style of the outline that \p look draws.
+fn line_style(look: &StyleAttr) -> Option<&'static str> {
+    if !look.visible {
+        return Some("invis");
+    }
+    [LineStyleKind::Dashed, LineStyleKind::Dotted]
+        .iter()
+        .find(|x| !look.dash.is_empty() && look.dash == x.dash_pattern())
+        .map(|x| match x {
+            LineStyleKind::Dashed => "dashed",
+            _ => "dotted",
+        })
+}
+
+/// Pass the DOT attributes of the colors, the lines and the fonts of \p look
+/// that differ from the default style to \p add.
+fn add_look_attributes(look: &StyleAttr, add: &mut impl FnMut(&str, String)) {
+    let default = default_style(None);
+    if look.line_color != default.line_color {
+        add("color", dot_color(look.line_color));
+    }
+    if look.penwidth != default.penwidth {
+        add("penwidth", format_number(look.penwidth));
+    }
+    let mut font = look.font_name.clone();
+    match (look.font_bold, look.font_italic) {
+        (true, true) => font.push_str("-BoldItalic"),
+        (true, false) => font.push_str("-Bold"),
+        (false, true) => font.push_str("-Italic"),
+        (false, false) => {}
+    }
+    if font != default.font_name {
+        add("fontname", font);
+    }
+    if look.font_size != default.font_size {
+        add("fontsize", look.font_size.to_string());
+    }
+    if look.font_color != default.font_color {
+        add("fontcolor", dot_color(look.font_color));
+    }
+}
+
+/// \returns the color \p color as a DOT color, such as "#ff0000", with the
+/// alpha channel only if the color is translucent.
+fn dot_color(color: Color) -> String {
+    let web = color.to_web_color();
+    match web.strip_suffix("ff") {
+        Option::Some(rgb) if web.len() == 9 => rgb.to_string(),
+        _ => web,
+    }
+}
+
+/// \returns the style of the shapes and the lines that the builder creates,
+/// with the fill color \p fill.
+fn default_style(fill: Option<Color>) -> StyleAttr {
+    StyleAttr::new(Color::fast("black"), 1., fill, 0, DEFAULT_FONT_SIZE)
+}
+
+/// \returns the attribute list that identifies an element in the output, from
+/// the names and the values in \p attrs.
+fn get_properties(attrs: &[(&str, &str)]) -> String {
+    let attrs: Vec<String> = attrs
+        .iter()
+        .map(|(key, val)| format!("{}=\"{}\"", key, escape_xml(val)))
+        .collect();
+    attrs.join(" ")
+}
+
+/// Constructs a VisualGraph from named nodes and the edges between them.
+#[derive(Debug, Clone)]
+pub struct GraphBuilder {
+    orientation: Orientation,
+    splines: Option<SplineKind>,
+    concentrate: bool,
+    avoid_nodes: bool,
+    seed: Option<u64>,
+    quality: LayoutQuality,
+    pack: Option<PackConfig>,
+    overlap: Option<OverlapConfig>,
+    size: Option<SizeConfig>,
+    label: Option<String>,
+    // The title and the position of the legend, and the label of each entry
+    // with the name of the node, or the ends of the edge, that it shows.
+    legend: Option<(String, LegendPosition)>,
+    legend_entries: Vec<(String, String, Option<String>)>,
+    background: Option<Color>,
+    text_metrics: TextMetrics,
+    nodes: Vec<NodeDesc>,
+    // Maps the names of the nodes to their index in the node list.
+    node_index: HashMap<String, usize>,
+    edges: Vec<EdgeDesc>,
+    groups: Vec<GroupDesc>,
+    // The rank and the position along the rank of the nodes in a previous
+    // layout of the graph, by name.
+    previous: HashMap<String, (usize, f64)>,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self {
+            orientation: Orientation::TopToBottom,
+            splines: None,
+            concentrate: false,
+            avoid_nodes: false,
+            seed: None,
+            quality: LayoutQuality::default(),
+            pack: None,
+            overlap: None,
+            size: None,
+            label: None,
+            legend: None,
+            legend_entries: Vec::new(),
+            background: None,
+            text_metrics: TextMetrics::Font,
+            nodes: Vec::new(),
+            node_index: HashMap::new(),
+            edges: Vec::new(),
+            groups: Vec::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Grow the graph in the direction \p orientation.
+    pub fn orientation(&mut self, orientation: Orientation) -> &mut Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Route the edges of the graph with \p splines.
+    pub fn splines(&mut self, splines: SplineKind) -> &mut Self {
+        self.splines = Some(splines);
+        self
+    }
+
+    /// Merge the parallel parts of edges that leave or enter the same node.
+    pub fn concentrate(&mut self, concentrate: bool) -> &mut Self {
+        self.concentrate = concentrate;
+        self
+    }
+
+    /// Route the edges around the nodes that are in their way.
+    pub fn avoid_nodes(&mut self, avoid_nodes: bool) -> &mut Self {
+        self.avoid_nodes = avoid_nodes;
+        self
+    }
+
+    /// Break the ties of the layout heuristics with the seed \p seed.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Trade the speed of the layout for its quality with \p quality.
+    pub fn quality(&mut self, quality: LayoutQuality) -> &mut Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Lay out the connected components of the graph separately, and arrange
+    /// them with \p config.
+    pub fn pack(&mut self, config: PackConfig) -> &mut Self {
+        self.pack = Some(config);
+        self
+    }
+
+    /// Remove the overlaps between the nodes of the force-directed and the
+    /// stress layouts with \p config.
+    pub fn overlap(&mut self, config: OverlapConfig) -> &mut Self {
+        self.overlap = Some(config);
+        self
+    }
+
+    /// Fit the drawing in the size, the ratio and the pages of \p config.
+    pub fn size(&mut self, config: SizeConfig) -> &mut Self {
+        self.size = Some(config);
+        self
+    }
+
+    /// Draw the title \p label below the graph.
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Draw a legend with the title \p title beside the graph, in the corner
+    /// \p position. The entries of the legend are added with legend_node()
+    /// and legend_edge().
+    pub fn legend(
+        &mut self,
+        title: &str,
+        position: LegendPosition,
+    ) -> &mut Self {
+        self.legend = Some((title.to_string(), position));
+        self
+    }
+
+    /// Add an entry with the label \p label to the legend, that shows the
+    /// shape and the style of the node \p name.
+    pub fn legend_node(&mut self, label: &str, name: &str) -> &mut Self {
+        let entry = (label.to_string(), name.to_string(), None);
+        self.legend_entries.push(entry);
+        self
+    }
+
+    /// Add an entry with the label \p label to the legend, that shows the
+    /// style of the edge from \p from to \p to.
+    pub fn legend_edge(
+        &mut self,
+        label: &str,
+        from: &str,
+        to: &str,
+    ) -> &mut Self {
+        let entry = (label.to_string(), from.to_string(), Some(to.to_string()));
+        self.legend_entries.push(entry);
+        self
+    }
+
+    /// Paint the background of the drawing with the color \p color, such as
+    /// "lightyellow" or "#ffffe0".
+    pub fn background(&mut self, color: &str) -> &mut Self {
+        self.background = Some(Color::fast(color));
+        self
+    }
+
+    /// Measure the labels of the graph with \p metrics. Graphs that are
+    /// rendered by the text backends use TextMetrics::Grid, so that the
+    /// labels fit in the shapes.
+    pub fn text_metrics(&mut self, metrics: TextMetrics) -> &mut Self {
+        self.text_metrics = metrics;
+        self
+    }
+
+    /// \returns the index of the node \p name, and creates the node if it's
+    /// not in the graph.
+    fn get_or_create_node(&mut self, name: &str) -> usize {
+        if let Option::Some(idx) = self.node_index.get(name) {
+            return *idx;
+        }
+        self.nodes.push(NodeDesc::new(name));
+        self.node_index
+            .insert(name.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// \returns a builder that styles the node \p name. The node is created
+    /// if it's not in the graph.
+    pub fn node(&mut self, name: &str) -> NodeBuilder<'_> {
+        let idx = self.get_or_create_node(name);
+        NodeBuilder {
+            desc: &mut self.nodes[idx],
+        }
+    }
+
+    /// \returns a builder that styles a new edge from the node \p from to the
+    /// node \p to. The nodes are created if they are not in the graph.
+    pub fn edge(&mut self, from: &str, to: &str) -> EdgeBuilder<'_> {
+        self.get_or_create_node(from);
+        self.get_or_create_node(to);
+        let arrow = Arrow {
+            look: default_style(None),
+            ..Arrow::default()
+        };
+        self.edges.push(EdgeDesc {
+            from: from.to_string(),
+            to: to.to_string(),
+            arrow,
+        });
+        EdgeBuilder {
+            desc: self.edges.last_mut().unwrap(),
+        }
+    }
+
+    /// Remove the node \p name and the edges that go through it.
+    /// \returns false if the node is not in the graph.
+    pub fn remove_node(&mut self, name: &str) -> bool {
+        let idx = match self.node_index.remove(name) {
+            Option::Some(idx) => idx,
+            None => return false,
+        };
+        self.nodes.remove(idx);
+        self.edges.retain(|e| e.from != name && e.to != name);
+        for group in self.groups.iter_mut() {
+            group.nodes.retain(|x| x != name);
+        }
+        for (i, node) in self.nodes.iter().enumerate().skip(idx) {
+            self.node_index.insert(node.name.clone(), i);
+        }
+        true
+    }
+
+    /// Remove the edges from the node \p from to the node \p to. The nodes
+    /// stay in the graph. \returns false if there is no such edge.
+    pub fn remove_edge(&mut self, from: &str, to: &str) -> bool {
+        let len = self.edges.len();
+        self.edges.retain(|e| e.from != from || e.to != to);
+        self.edges.len() != len
+    }
+
+    /// \returns a builder that styles the group \p name, and adds the nodes
+    /// \p nodes to the group. The group and the nodes are created if they are
+    /// not in the graph, and nodes leave the group that they were in. The
+    /// name of the group must differ from the names of the nodes.
+    pub fn group(&mut self, name: &str, nodes: &[&str]) -> GroupBuilder<'_> {
+        for node in nodes {
+            self.get_or_create_node(node);
+            for group in self.groups.iter_mut() {
+                group.nodes.retain(|x| x != node);
+            }
+        }
+        let idx = match self.groups.iter().position(|x| x.name == name) {
+            Option::Some(idx) => idx,
+            None => {
+                self.groups.push(GroupDesc {
+                    name: name.to_string(),
+                    label: None,
+                    nodes: Vec::new(),
+                    collapsed: false,
+                });
+                self.groups.len() - 1
+            }
+        };
+        let desc = &mut self.groups[idx];
+        desc.nodes.extend(nodes.iter().map(|x| x.to_string()));
+        GroupBuilder { desc }
+    }
+
+    /// \returns true if the group \p name is collapsed, or None if there is
+    /// no such group.
+    pub fn is_collapsed(&self, name: &str) -> Option<bool> {
+        self.groups
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.collapsed)
+    }
+
+    /// Collapse the group \p name if it's expanded, and expand it if it's
+    /// collapsed. Build the graph again to lay out the new state. \returns
+    /// the new state, or None if there is no such group.
+    pub fn toggle_group(&mut self, name: &str) -> Option<bool> {
+        let group = self.groups.iter_mut().find(|x| x.name == name)?;
+        group.collapsed = !group.collapsed;
+        Some(group.collapsed)
+    }
+
+    /// Start the next layouts from the layout of \p vg, that was built by
+    /// this builder. Call this method before editing the builder, so that the
+    /// nodes of \p vg match the nodes of the builder. Nodes that are not
+    /// removed keep their rank and their order, and only the parts of the
+    /// graph that change are laid out again.
+    pub fn set_previous_layout(&mut self, vg: &VisualGraph) {
+        let warm_start = vg.warm_start();
+        let view = self.get_view();
+        let mut previous = HashMap::new();
+        for (node, place) in view.nodes.iter().zip(warm_start.nodes) {
+            if let Option::Some(place) = place {
+                previous.insert(node.name.clone(), place);
+            }
+        }
+        self.previous = previous;
+    }
+
+    /// \returns the number of nodes in the graph.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// \returns the handle of the node \p name in the graph that build()
+    /// builds, or None if there is no such node. The nodes of collapsed
+    /// groups, and the collapsed groups, have the handle of the node of the
+    /// group.
+    pub fn node_handle(&self, name: &str) -> Option<NodeHandle> {
+        self.get_view()
+            .index
+            .get(name)
+            .map(|idx| NodeHandle::new(*idx))
+    }
+
+    /// \returns the nodes and the edges that are drawn. The nodes of each
+    /// collapsed group are replaced by a node, at the place of the first of
+    /// them, the edges inside of the group are hidden, and the edges that
+    /// join the same nodes through the group are merged into one edge.
+    fn get_view(&self) -> GraphView<'_> {
+        let mut group_of: HashMap<&str, &GroupDesc> = HashMap::new();
+        for group in self.groups.iter() {
+            for node in group.nodes.iter() {
+                group_of.insert(node, group);
+            }
+        }
+        let is_collapsed =
+            |name: &str| group_of.get(name).is_some_and(|x| x.collapsed);
+
+        let mut nodes: Vec<NodeDesc> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for node in self.nodes.iter() {
+            if !is_collapsed(&node.name) {
+                index.insert(node.name.clone(), nodes.len());
+                nodes.push(node.clone());
+                continue;
+            }
+            let group = group_of[node.name.as_str()];
+            let idx = match index.get(&group.name) {
+                Option::Some(idx) => *idx,
+                None => {
+                    nodes.push(group.to_node());
+                    index.insert(group.name.clone(), nodes.len() - 1);
+                    nodes.len() - 1
+                }
+            };
+            index.insert(node.name.clone(), idx);
+        }
+
+        // The edges and the number of edges that each of them stands for.
+        let mut edges: Vec<(EdgeDesc, usize)> = Vec::new();
+        let mut merged: HashMap<(usize, usize), usize> = HashMap::new();
+        for edge in self.edges.iter() {
+            if !is_collapsed(&edge.from) && !is_collapsed(&edge.to) {
+                edges.push((edge.clone(), 1));
+                continue;
+            }
+            let (from, to) = (index[&edge.from], index[&edge.to]);
+            if from == to {
+                continue;
+            }
+            if let Option::Some(i) = merged.get(&(from, to)) {
+                edges[*i].1 += 1;
+                continue;
+            }
+            let mut edge = edge.clone();
+            edge.from = nodes[from].name.clone();
+            edge.to = nodes[to].name.clone();
+            merged.insert((from, to), edges.len());
+            edges.push((edge, 1));
+        }
+        let edges = edges
+            .into_iter()
+            .map(|(mut edge, count)| {
+                if count > 1 && edge.arrow.text.is_empty() {
+                    edge.arrow.text = format!("{} edges", count);
+                }
+                edge
+            })
+            .collect();
+
+        let mut clusters = Vec::new();
+        for group in self.groups.iter().filter(|x| !x.collapsed) {
+            let members: Vec<usize> =
+                group.nodes.iter().map(|x| index[x]).collect();
+            if !members.is_empty() {
+                clusters.push((group, members));
+            }
+        }
+        GraphView {
+            nodes,
+            edges,
+            clusters,
+            index,
+        }
+    }
+
+    /// \returns the legend of the graph, with the styles of the nodes and
+    /// the edges of its entries. Entries of nodes and edges that are not in
+    /// the graph are skipped.
+    fn get_legend(&self) -> Option<Legend> {
+        let (title, position) = self.legend.as_ref()?;
+        let mut look = default_style(None);
+        look.text_metrics = self.text_metrics;
+        let mut legend = Legend::new(title, &look);
+        legend.position = *position;
+        for (label, from, to) in self.legend_entries.iter() {
+            if let Option::Some(to) = to {
+                let edge =
+                    self.edges.iter().find(|x| x.from == *from && x.to == *to);
+                if let Option::Some(edge) = edge {
+                    let mut arrow = edge.arrow.clone();
+                    arrow.look.text_metrics = self.text_metrics;
+                    legend.add_edge(label, &arrow);
+                }
+            } else if let Option::Some(idx) = self.node_index.get(from) {
+                let node = &self.nodes[*idx];
+                let elem = node.to_element(self.orientation, self.text_metrics);
+                legend.add_node(label, &elem);
+            }
+        }
+        Some(legend)
+    }
+
+    /// \returns the graph that contains the nodes and the edges. The nodes
+    /// are added to the graph in the order of their creation. Expanded groups
+    /// are drawn as clusters, and collapsed groups as a single node.
+    pub fn build(&self) -> VisualGraph {
+        let mut vg = VisualGraph::new(self.orientation);
+        if let Option::Some(splines) = self.splines {
+            vg.set_splines(splines);
+        }
+        vg.set_concentrate(self.concentrate);
+        vg.set_avoid_nodes(self.avoid_nodes);
+        if let Option::Some(seed) = self.seed {
+            vg.set_seed(seed);
+        }
+        vg.set_quality(self.quality);
+        if let Option::Some(pack) = self.pack {
+            vg.set_pack_config(pack);
+        }
+        if let Option::Some(overlap) = self.overlap {
+            vg.set_overlap_config(overlap);
+        }
+        if let Option::Some(size) = self.size {
+            vg.set_size_config(size);
+        }
+        if let Option::Some(color) = self.background {
+            vg.set_background(color);
+        }
+        if let Option::Some(label) = &self.label {
+            vg.set_label(GraphLabel::new(label, &default_style(None)));
+        }
+        if let Option::Some(legend) = self.get_legend() {
+            vg.set_legend(legend);
+        }
+
+        let view = self.get_view();
+        let handles: Vec<_> = view
+            .nodes
+            .iter()
+            .map(|node| {
+                let elem = node.to_element(self.orientation, self.text_metrics);
+                vg.add_node(elem)
+            })
+            .collect();
+
+        let edge_ids =
+            get_edge_ids(view.edges.iter().map(|x| (&x.from, &x.to)));
+        for (edge, id) in view.edges.iter().zip(edge_ids.iter()) {
+            let mut arrow = edge.arrow.clone();
+            arrow.look.text_metrics = self.text_metrics;
+            let ident = [
+                ("id", id.as_str()),
+                ("class", "edge"),
+                ("data-from", edge.from.as_str()),
+                ("data-to", &edge.to),
+            ];
+            arrow.properties = Some(get_properties(&ident));
+            let from = handles[view.index[&edge.from]];
+            let to = handles[view.index[&edge.to]];
+            vg.add_edge(arrow, from, to);
+        }
+
+        for (group, members) in view.clusters.iter() {
+            let mut cluster =
+                Cluster::new(group.get_label(), &default_style(None), None);
+            let ident = [
+                ("id", group.name.as_str()),
+                ("class", "cluster"),
+                ("data-cluster", &group.name),
+            ];
+            cluster.properties = Some(get_properties(&ident));
+            let idx = vg.add_cluster(cluster);
+            for member in members {
+                vg.add_to_cluster(handles[*member], idx);
+            }
+        }
+
+        // The lanes are placed in the order in which they are first used.
+        let mut lanes: HashMap<&str, usize> = HashMap::new();
+        for (node, handle) in view.nodes.iter().zip(handles.iter()) {
+            let name = match &node.lane {
+                Option::Some(name) => name.as_str(),
+                None => continue,
+            };
+            let lane = *lanes.entry(name).or_insert_with(|| {
+                let mut lane = Lane::new(name, &default_style(None));
+                let ident = [("class", "lane"), ("data-lane", name)];
+                lane.properties = Some(get_properties(&ident));
+                vg.add_lane(lane)
+            });
+            vg.add_to_lane(*handle, lane);
+        }
+        for (node, handle) in view.nodes.iter().zip(handles.iter()) {
+            if let Option::Some((start, end)) = node.span {
+                vg.set_node_span(*handle, start, end);
+            }
+        }
+
+        if !self.previous.is_empty() {
+            let nodes = view
+                .nodes
+                .iter()
+                .map(|node| self.previous.get(&node.name).cloned())
+                .collect();
+            vg.set_warm_start(WarmStart { nodes });
+        }
+        vg
+    }
+
+    /// \returns the graph as DOT text, with the nodes and the edges in the
+    /// order of their creation and the attributes that differ from the
+    /// defaults. The text describes the graph that build() returns: expanded
+    /// groups are clusters, and collapsed groups are nodes. If \p layout is set, then the text also has the positions of
+    /// the nodes and the edges of the graph that build() returned, like the
+    /// output of `dot -Tdot`.
+    pub fn to_dot(&self, layout: Option<&LayoutResult>) -> String {
+        self.write_dot_with(layout.map(LayoutAttributes::new))
+    }
+
+    /// \returns the graph as DOT text, with the positions of the graph \p vg,
+    /// that build() returned and that was laid out, and with the xdot
+    /// operations that draw the graph, the nodes and the edges, like the
+    /// output of `dot -Txdot`.
+    pub fn to_xdot(&self, vg: &VisualGraph) -> String {
+        self.write_dot_with(Some(LayoutAttributes::with_drawing(vg)))
+    }
+
+    /// \returns the layout \p layout of the graph that build() returned, in
+    /// the 'plain' format of Graphviz. The edges of graphs that are built in
+    /// code have no ports, so the 'plain-ext' format, that is selected with
+    /// \p extended, is the same.
+    pub fn to_plain(&self, layout: &LayoutResult, extended: bool) -> String {
+        let view = self.get_view();
+        let nodes: Vec<_> = view
+            .nodes
+            .iter()
+            .map(|node| {
+                (node.name.clone(), node.to_attributes(self.orientation))
+            })
+            .collect();
+        let edges: Vec<_> = view
+            .edges
+            .iter()
+            .map(|e| {
+                let from = ast::NodeId::new(&e.from, &None);
+                let to = ast::NodeId::new(&e.to, &None);
+                (from, to, e.to_attributes())
+            })
+            .collect();
+        write_plain(layout, &nodes, &edges, extended)
+    }
+
+    fn write_dot_with(&self, layout: Option<LayoutAttributes>) -> String {
+        let mut graph = ast::Graph::new("");
+        let list = &mut graph.list.list;
+
+        let mut attrs = Vec::new();
+        let mut add = |key: &str, val: String| {
+            attrs.push((key.to_string(), val));
+        };
+        if !self.orientation.is_top_to_bottom() {
+            add("rankdir", "LR".to_string());
+        }
+        if let Option::Some(splines) = self.splines {
+            let name = match splines {
+                SplineKind::Spline => "spline",
+                SplineKind::Line => "line",
+                SplineKind::Polyline => "polyline",
+                SplineKind::Curved => "curved",
+                SplineKind::Ortho => "ortho",
+            };
+            add("splines", name.to_string());
+        }
+        if self.concentrate {
+            add("concentrate", "true".to_string());
+        }
+        if self.avoid_nodes {
+            add("avoidnodes", "true".to_string());
+        }
+        if let Option::Some(pack) = self.pack {
+            add("pack", format_number(pack.margin));
+            if let PackMode::Array(columns) = pack.mode {
+                let mode = match columns {
+                    Option::Some(columns) => format!("array_c{}", columns),
+                    None => "array".to_string(),
+                };
+                add("packmode", mode);
+            }
+        }
+        if let Option::Some(fit) = self.size {
+            // The sizes are in inches in the DOT language.
+            let inches = |p: Point| {
+                format!(
+                    "{},{}",
+                    format_number(p.x / POINTS_PER_INCH),
+                    format_number(p.y / POINTS_PER_INCH)
+                )
+            };
+            if let Option::Some(size) = fit.size {
+                let bang = if fit.fill { "!" } else { "" };
+                add("size", format!("{}{}", inches(size), bang));
+            }
+            if let Option::Some(ratio) = fit.ratio {
+                let name = match ratio {
+                    Ratio::Aspect(x) => format_number(x),
+                    Ratio::Fill => "fill".to_string(),
+                    Ratio::Compress => "compress".to_string(),
+                    Ratio::Expand => "expand".to_string(),
+                    Ratio::Auto => "auto".to_string(),
+                };
+                add("ratio", name);
+            }
+            if let Option::Some(page) = fit.page {
+                add("page", inches(page));
+            }
+            if fit.page_dir != PageDir::default() {
+                add("pagedir", fit.page_dir.to_name());
+            }
+        }
+        if let Option::Some(label) = &self.label {
+            add("label", label.clone());
+        }
+        if let Option::Some(color) = self.background {
+            add("bgcolor", dot_color(color));
+        }
+        let extra = layout.as_ref().map(|x| x.graph()).unwrap_or_default();
+        let attrs = merge_attributes(attrs, extra);
+        if !attrs.list.is_empty() {
+            let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+            list.push(ast::Stmt::Attribute(stmt));
+        }
+
+        let view = self.get_view();
+        for (i, node) in view.nodes.iter().enumerate() {
+            let extra = layout.as_ref().map(|x| x.node(i)).unwrap_or_default();
+            let attrs =
+                merge_attributes(node.to_attributes(self.orientation), extra);
+            let id = ast::NodeId::new(&node.name, &None);
+            list.push(ast::Stmt::Node(ast::NodeStmt::new_with_list(id, attrs)));
+        }
+        // The expanded groups are clusters that list their nodes.
+        for (group, members) in view.clusters.iter() {
+            let mut cluster =
+                ast::Graph::new(&format!("cluster_{}", group.name));
+            let mut attrs = ast::AttributeList::new();
+            attrs.add_attr("label", group.get_label());
+            let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+            cluster.list.list.push(ast::Stmt::Attribute(stmt));
+            for member in members {
+                let id = ast::NodeId::new(&view.nodes[*member].name, &None);
+                let stmt = ast::Stmt::Node(ast::NodeStmt::new(id));
+                cluster.list.list.push(stmt);
+            }
+            list.push(ast::Stmt::SubGraph(cluster));
+        }
+        for (i, edge) in view.edges.iter().enumerate() {
+            let mut stmt =
+                ast::EdgeStmt::new(ast::NodeId::new(&edge.from, &None));
+            stmt.insert(
+                ast::NodeId::new(&edge.to, &None),
+                ast::ArrowKind::Arrow,
+            );
+            let extra = layout.as_ref().map(|x| x.edge(i)).unwrap_or_default();
+            stmt.list = merge_attributes(edge.to_attributes(), extra);
+            list.push(ast::Stmt::Edge(stmt));
+        }
+        write_dot(&graph)
+    }
+}
+
+/// Sets the style of a node in a GraphBuilder.
+#[derive(Debug)]
+pub struct NodeBuilder<'a> {
+    desc: &'a mut NodeDesc,
+}
+
+impl<'a> NodeBuilder<'a> {
+    /// Draw the text \p label in the node, instead of its name.
+    pub fn label(self, label: &str) -> Self {
+        self.desc.label = Some(label.to_string());
+        self
+    }
+
+    pub fn shape(self, shape: Shape) -> Self {
+        self.desc.shape = shape;
+        self
+    }
+
+    /// Fill the node with the color \p color.
+    pub fn fill(self, color: &str) -> Self {
+        self.desc.look.fill_color = Some(Color::fast(color));
+        self.desc.has_fill = true;
+        self
+    }
+
+    /// Draw the node as the box of the UML class \p class, with compartments
+    /// for its name, its fields and its methods, instead of its label.
+    pub fn class(self, class: &ClassNode) -> Self {
+        self.desc.shape = Shape::Record;
+        self.desc.class = Some(class.clone());
+        self
+    }
+
+    /// Draw the node as the table \p table of an ER diagram, with a row for
+    /// each column, instead of its shape and its label.
+    pub fn table(self, table: &TableNode) -> Self {
+        self.desc.table = Some(table.clone());
+        self
+    }
+
+    /// Draw the outline of the node with the color \p color.
+    pub fn color(self, color: &str) -> Self {
+        self.desc.look.line_color = Color::fast(color);
+        self
+    }
+
+    pub fn pen_width(self, width: f64) -> Self {
+        self.desc.look.penwidth = width.max(0.);
+        self
+    }
+
+    /// Draw the outline of the node with the line style \p style.
+    pub fn style(self, style: LineStyleKind) -> Self {
+        self.desc.look.set_dash(&style.dash_pattern());
+        self.desc.look.visible = !matches!(style, LineStyleKind::None);
+        self
+    }
+
+    pub fn rounded(self, radius: usize) -> Self {
+        self.desc.look.rounded = radius;
+        self
+    }
+
+    /// Select the font \p name, such as "Helvetica" or "Times-Bold".
+    pub fn font(self, name: &str) -> Self {
+        self.desc.look.set_font_name(name);
+        self
+    }
+
+    pub fn font_size(self, size: usize) -> Self {
+        self.desc.look.font_size = size;
+        self
+    }
+
+    pub fn font_color(self, color: &str) -> Self {
+        self.desc.look.font_color = Color::fast(color);
+        self
+    }
+
+    /// Make the node at least as large as \p size, in pixels.
+    pub fn min_size(self, size: Point) -> Self {
+        self.desc.min_size = Some(size);
+        self
+    }
+
+    /// Show the text \p tooltip when the pointer is over the node, in the
+    /// backends that support it.
+    pub fn tooltip(self, tooltip: &str) -> Self {
+        self.desc.tooltip = Some(tooltip.to_string());
+        self
+    }
+
+    /// Place the node in the lane \p lane of the hierarchical layout. The
+    /// lanes are created when they are first used.
+    pub fn lane(self, lane: &str) -> Self {
+        self.desc.lane = Some(lane.to_string());
+        self
+    }
+
+    /// Place the node on the axis of the timeline layout, from \p start to
+    /// \p end. Nodes that start and end at the same time are milestones.
+    pub fn span(self, start: f64, end: f64) -> Self {
+        self.desc.span = Some((start.min(end), start.max(end)));
+        self
+    }
+}
+
+/// Sets the style of an edge in a GraphBuilder.
+#[derive(Debug)]
+pub struct EdgeBuilder<'a> {
+    desc: &'a mut EdgeDesc,
+}
+
+impl<'a> EdgeBuilder<'a> {
+    pub fn label(self, label: &str) -> Self {
+        self.desc.arrow.text = label.to_string();
+        self
+    }
+
+    /// Draw the text \p label next to the head of the edge.
+    pub fn head_label(self, label: &str) -> Self {
+        self.desc.arrow.head_label = label.to_string();
+        self
+    }
+
+    /// Draw the text \p label next to the tail of the edge.
+    pub fn tail_label(self, label: &str) -> Self {
+        self.desc.arrow.tail_label = label.to_string();
+        self
+    }
+
+    /// Draw the edge with the color \p color.
+    pub fn color(self, color: &str) -> Self {
+        self.desc.arrow.look.line_color = Color::fast(color);
+        self
+    }
+
+    pub fn pen_width(self, width: f64) -> Self {
+        self.desc.arrow.look.penwidth = width.max(0.);
+        self
+    }
+
+    /// Draw the edge with the line style \p style.
+    pub fn style(self, style: LineStyleKind) -> Self {
+        self.desc.arrow.line_style = style;
+        self.desc.arrow.look.set_dash(&style.dash_pattern());
+        self
+    }
+
+    /// Draw the edge as the UML relation \p relation between two classes,
+    /// with the head of the relation at the start of the edge.
+    pub fn relation(self, relation: Relation) -> Self {
+        let arrow = &mut self.desc.arrow;
+        arrow.look.arrow_heads = relation.arrow_heads();
+        arrow.start = LineEndKind::Arrow;
+        arrow.end = LineEndKind::None;
+        self.style(relation.line_style())
+    }
+
+    /// Draw the cardinality \p tail of the tail and \p head of the head of
+    /// the edge in the crow's foot notation of ER diagrams.
+    pub fn cardinality(self, tail: Cardinality, head: Cardinality) -> Self {
+        let arrow = &mut self.desc.arrow;
+        arrow.look.arrow_heads = (tail.arrow_head(), head.arrow_head());
+        arrow.start = LineEndKind::Arrow;
+        arrow.end = LineEndKind::Arrow;
+        self
+    }
+
+    /// Draw arrow heads at the tail of the edge if \p start is set, and at
+    /// its head if \p end is set.
+    pub fn heads(self, start: bool, end: bool) -> Self {
+        let kind = |x: bool| {
+            if x {
+                LineEndKind::Arrow
+            } else {
+                LineEndKind::None
+            }
+        };
+        self.desc.arrow.start = kind(start);
+        self.desc.arrow.end = kind(end);
+        self
+    }
+
+    pub fn font_size(self, size: usize) -> Self {
+        self.desc.arrow.look.font_size = size;
+        self
+    }
+
+    /// Keep the ends of the edge at least \p min_len ranks apart.
+    pub fn min_len(self, min_len: usize) -> Self {
+        self.desc.arrow.min_len = min_len;
+        self
+    }
+
+    /// Keep the edge shorter and straighter than edges with a lower
+    /// \p weight.
+    pub fn weight(self, weight: f64) -> Self {
+        self.desc.arrow.weight = weight.max(0.);
+        self
+    }
+
+    /// Let the edge affect the ranks of the nodes if \p constraint is set.
+    pub fn constraint(self, constraint: bool) -> Self {
+        self.desc.arrow.constraint = constraint;
+        self
+    }
+}
+
+/// Sets the label and the state of a group of nodes in a GraphBuilder.
+#[derive(Debug)]
+pub struct GroupBuilder<'a> {
+    desc: &'a mut GroupDesc,
+}
+
+impl<'a> GroupBuilder<'a> {
+    /// Draw the text \p label in the cluster or the node of the group,
+    /// instead of its name.
+    pub fn label(self, label: &str) -> Self {
+        self.desc.label = Some(label.to_string());
+        self
+    }
+
+    /// Draw the group as a single node if \p collapsed is set, and as a
+    /// cluster around its nodes otherwise.
+    pub fn collapsed(self, collapsed: bool) -> Self {
+        self.desc.collapsed = collapsed;
+        self
+    }
+}
+
+#[test]
+fn test_graph_builder() {
+    let mut graph = GraphBuilder::new();
+    graph
+        .node("a")
+        .shape(Shape::Box)
+        .fill("#ddf")
+        .label("first");
+    graph.edge("a", "b").label("x");
+    graph.edge("b", "c").weight(2.).heads(true, false);
+    graph.node("a").color("red");
+    assert_eq!(graph.num_nodes(), 3);
+
+    let vg = graph.build();
+    assert_eq!(vg.num_nodes(), 3);
+    let a = vg.element(NodeHandle::new(0));
+    assert!(matches!(&a.shape, ShapeKind::Box(text) if text == "first"));
+    assert_eq!(a.look.fill_color, Some(Color::fast("#ddf")));
+    assert_eq!(a.look.line_color, Color::fast("red"));
+    assert_eq!(
+        a.properties.as_deref(),
+        Some("id=\"a\" class=\"node\" data-node=\"a\"")
+    );
+}
+
+#[test]
+fn test_remove_from_graph_builder() {
+    let mut graph = GraphBuilder::new();
+    graph.edge("a", "b");
+    graph.edge("b", "c");
+    graph.edge("a", "c");
+    assert!(graph.remove_edge("a", "c"));
+    assert!(!graph.remove_edge("a", "c"));
+    assert!(graph.remove_node("b"));
+    assert!(!graph.remove_node("b"));
+    assert_eq!(graph.num_nodes(), 2);
+
+    // The edges of the removed node are gone, and the names of the other
+    // nodes still refer to them.
+    graph.edge("c", "a");
+    let vg = graph.build();
+    assert_eq!(vg.num_nodes(), 2);
+    let c = vg.element(NodeHandle::new(1));
+    assert_eq!(
+        c.properties.as_deref(),
+        Some("id=\"c\" class=\"node\" data-node=\"c\"")
+    );
+}
+
+#[test]
+fn test_graph_builder_to_dot() {
+    let mut graph = GraphBuilder::new();
+    graph.orientation(Orientation::LeftToRight).label("title");
+    graph
+        .node("a")
+        .shape(Shape::Polygon(PolygonKind::House))
+        .fill("#ddddff")
+        .style(LineStyleKind::Dashed)
+        .font("Helvetica-Bold");
+    graph.node("b").min_size(Point::new(144., 36.));
+    graph.edge("a", "b").label("x").heads(true, true).min_len(2);
+    graph.edge("b", "c").color("red").constraint(false);
+
+    let text = graph.to_dot(None);
+    assert!(text.starts_with("digraph {\n  graph [rankdir=LR, label=title];\n"));
+    assert!(text.contains(
+        "  a [shape=house, fillcolor=\"#ddddff\", style=\"filled,dashed\", \
+         fontname=\"Helvetica-Bold\"];\n"
+    ));
+    assert!(text.contains("  b [width=2, height=0.5];\n  c;\n"));
+    assert!(text.contains("  a -> b [label=x, dir=both, minlen=2];\n"));
+    assert!(text.contains("  b -> c [color=\"#ff0000\", constraint=false];\n"));
+
+    // The positions of the nodes and the edges are added after layout.
+    let mut vg = graph.build();
+    vg.layout(false, false);
+    let text = graph.to_dot(Some(&vg.layout_result()));
+    assert!(text.contains("  graph [rankdir=LR, label=title, bb=\""));
+    assert!(text.contains("  c [pos=\""));
+    assert!(text.contains("constraint=false, pos=\""));
+}
+
+#[test]
+fn test_collapsible_groups() {
+    let mut graph = GraphBuilder::new();
+    graph.edge("a", "b");
+    graph.edge("b", "c");
+    graph.edge("a", "c");
+    graph.edge("c", "d");
+    graph.edge("b", "d");
+    graph.group("g", &["b", "c"]).label("inner");
+    assert_eq!(graph.is_collapsed("g"), Some(false));
+    assert_eq!(graph.is_collapsed("h"), None);
+
+    // The expanded group is a cluster around its nodes.
+    let vg = graph.build();
+    assert_eq!(vg.num_nodes(), 4);
+    let text = graph.to_dot(None);
+    assert!(text.contains(
+        "  subgraph cluster_g {\n    graph [label=inner];\n    b;\n    c;\n  }\n"
+    ));
+
+    // The collapsed group replaces its nodes, at the place of the first one.
+    // The edges inside of the group are hidden, and the parallel edges are
+    // merged.
+    assert_eq!(graph.toggle_group("g"), Some(true));
+    assert_eq!(graph.toggle_group("h"), None);
+    let vg = graph.build();
+    assert_eq!(vg.num_nodes(), 3);
+    assert_eq!(graph.node_handle("c"), graph.node_handle("g"));
+    assert_eq!(graph.node_handle("d"), Some(NodeHandle::new(2)));
+    let text = graph.to_dot(None);
+    assert!(text.contains("  g [label=\"inner (2)\", shape=box"));
+    assert!(text.contains("  a -> g [label=\"2 edges\"];\n"));
+    assert!(text.contains("  g -> d [label=\"2 edges\"];\n"));
+    assert!(!text.contains("subgraph"));
+
+    // Nodes move between groups, and removed nodes leave their group.
+    graph.group("h", &["c"]).collapsed(true);
+    assert!(graph.remove_node("d"));
+    let text = graph.to_dot(None);
+    assert!(text.contains("  g [label=\"inner (1)\""));
+    assert!(text.contains("  g -> h;\n"));
+}
@@ -50,3 +50,96 @@ impl Orientation {
         Orientation::TopToBottom
     }
 }
+
+/// The way that the edges are routed between the nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplineKind {
+    /// Bezier curves that go through the channels between the nodes.
+    Spline,
+    /// Straight lines between the ends of the edge.
+    Line,
+    /// Straight lines that go through the channels between the nodes.
+    Polyline,
+    /// Arcs between the ends of the edge.
+    Curved,
+    /// Horizontal and vertical lines that go through the channels between the
+    /// nodes.
+    Ortho,
+}
+
+/// The algorithm that places the nodes of the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutEngine {
+    /// Place the nodes in ranks, along the direction of the edges.
+    Hierarchical,
+    /// Place the nodes with a spring-electrical simulation.
+    ForceDirected,
+    /// Place the nodes of the biconnected components on circles.
+    Circular,
+    /// Place the nodes on rings around a root, by their distance from it.
+    Radial,
+    /// Place the nodes as a top-down tidy tree.
+    Tree,
+    /// Place the nodes at the distances of the shortest paths between them.
+    Stress,
+    /// Place the nodes in the cells of a grid, in row-major order.
+    Grid,
+    /// Place the nodes on a time axis, from their start to their end.
+    Timeline,
+}
+
+impl LayoutEngine {
+    /// \returns the engine for the Graphviz layout program \p name, such as
+    /// "dot" or "neato".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" => Some(LayoutEngine::Hierarchical),
+            "fdp" | "sfdp" => Some(LayoutEngine::ForceDirected),
+            "neato" => Some(LayoutEngine::Stress),
+            "grid" => Some(LayoutEngine::Grid),
+            "circo" => Some(LayoutEngine::Circular),
+            "twopi" => Some(LayoutEngine::Radial),
+            "tree" => Some(LayoutEngine::Tree),
+            "timeline" => Some(LayoutEngine::Timeline),
+            _ => None,
+        }
+    }
+}
+
+/// The order in which the nodes and the edges are drawn. Elements with a
+/// higher z-order are drawn last, regardless of the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputOrder {
+    /// Draw the edges over the nodes.
+    #[default]
+    NodesFirst,
+    /// Draw the nodes over the edges.
+    EdgesFirst,
+}
+
+impl OutputOrder {
+    /// \returns the order of the Graphviz 'outputorder' value \p name, such
+    /// as "edgesfirst".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "breadthfirst" | "nodesfirst" => Some(OutputOrder::NodesFirst),
+            "edgesfirst" => Some(OutputOrder::EdgesFirst),
+            _ => None,
+        }
+    }
+}
+
+/// Trades the speed of the hierarchical layout for the quality of the drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutQuality {
+    /// Rank the nodes by the longest path, and spend fewer iterations on the
+    /// order of the nodes in the ranks.
+    Fast,
+    /// Rank the nodes by the longest path, and then move them to shorten the
+    /// edges.
+    #[default]
+    Normal,
+    /// Rank the nodes with network simplex, that minimizes the total length
+    /// of the edges, and spend more iterations on the order of the nodes.
+    High,
+}
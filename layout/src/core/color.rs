@@ -1,16 +1,23 @@
 //! This module handles the parsing and saving of colors in different formats.
 
-static KNOWN_COLORS: [(&str, u32); 559] = [
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use alloc::format;
+/// The colors of the X11 color scheme.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+static X11_COLORS: [(&str, u32); 657] = [
     ("aliceblue", 0xf0f8ff),
     ("antiquewhite", 0xfaebd7),
     ("antiquewhite1", 0xffefdb),
     ("antiquewhite2", 0xeedfcc),
     ("antiquewhite3", 0xcdc0b0),
     ("antiquewhite4", 0x8b8378),
-    ("aqua", 0x00ffff),
     ("aquamarine", 0x7fffd4),
     ("aquamarine1", 0x7fffd4),
     ("aquamarine2", 0x76eec6),
+    ("aquamarine3", 0x66cdaa),
     ("aquamarine4", 0x458b74),
     ("azure", 0xf0ffff),
     ("azure1", 0xf0ffff),
@@ -28,6 +35,7 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("blue", 0x0000ff),
     ("blue1", 0x0000ff),
     ("blue2", 0x0000ee),
+    ("blue3", 0x0000cd),
     ("blue4", 0x00008b),
     ("blueviolet", 0x8a2be2),
     ("brown", 0xa52a2a),
@@ -54,6 +62,7 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("chocolate1", 0xff7f24),
     ("chocolate2", 0xee7621),
     ("chocolate3", 0xcd661d),
+    ("chocolate4", 0x8b4513),
     ("coral", 0xff7f50),
     ("coral1", 0xff7256),
     ("coral2", 0xee6a50),
@@ -65,7 +74,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("cornsilk2", 0xeee8cd),
     ("cornsilk3", 0xcdc8b1),
     ("cornsilk4", 0x8b8878),
-    ("crimson", 0xdc143c),
     ("cyan", 0x00ffff),
     ("cyan1", 0x00ffff),
     ("cyan2", 0x00eeee),
@@ -138,7 +146,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("firebrick4", 0x8b1a1a),
     ("floralwhite", 0xfffaf0),
     ("forestgreen", 0x228b22),
-    ("fuchsia", 0xff00ff),
     ("gainsboro", 0xdcdcdc),
     ("ghostwhite", 0xf8f8ff),
     ("gold", 0xffd700),
@@ -151,7 +158,8 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("goldenrod2", 0xeeb422),
     ("goldenrod3", 0xcd9b1d),
     ("goldenrod4", 0x8b6914),
-    ("gray", 0x808080),
+    ("gray", 0xbebebe),
+    ("gray0", 0x000000),
     ("gray1", 0x030303),
     ("gray2", 0x050505),
     ("gray3", 0x080808),
@@ -247,16 +255,119 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("gray93", 0xededed),
     ("gray94", 0xf0f0f0),
     ("gray95", 0xf2f2f2),
+    ("gray96", 0xf5f5f5),
     ("gray97", 0xf7f7f7),
     ("gray98", 0xfafafa),
     ("gray99", 0xfcfcfc),
-    ("green", 0x008000),
+    ("gray100", 0xffffff),
+    ("green", 0x00ff00),
     ("green1", 0x00ff00),
     ("green2", 0x00ee00),
     ("green3", 0x00cd00),
     ("green4", 0x008b00),
     ("greenyellow", 0xadff2f),
-    ("grey", 0x808080),
+    ("grey", 0xbebebe),
+    ("grey0", 0x000000),
+    ("grey1", 0x030303),
+    ("grey2", 0x050505),
+    ("grey3", 0x080808),
+    ("grey4", 0x0a0a0a),
+    ("grey5", 0x0d0d0d),
+    ("grey6", 0x0f0f0f),
+    ("grey7", 0x121212),
+    ("grey8", 0x141414),
+    ("grey9", 0x171717),
+    ("grey10", 0x1a1a1a),
+    ("grey11", 0x1c1c1c),
+    ("grey12", 0x1f1f1f),
+    ("grey13", 0x212121),
+    ("grey14", 0x242424),
+    ("grey15", 0x262626),
+    ("grey16", 0x292929),
+    ("grey17", 0x2b2b2b),
+    ("grey18", 0x2e2e2e),
+    ("grey19", 0x303030),
+    ("grey20", 0x333333),
+    ("grey21", 0x363636),
+    ("grey22", 0x383838),
+    ("grey23", 0x3b3b3b),
+    ("grey24", 0x3d3d3d),
+    ("grey25", 0x404040),
+    ("grey26", 0x424242),
+    ("grey27", 0x454545),
+    ("grey28", 0x474747),
+    ("grey29", 0x4a4a4a),
+    ("grey30", 0x4d4d4d),
+    ("grey31", 0x4f4f4f),
+    ("grey32", 0x525252),
+    ("grey33", 0x545454),
+    ("grey34", 0x575757),
+    ("grey35", 0x595959),
+    ("grey36", 0x5c5c5c),
+    ("grey37", 0x5e5e5e),
+    ("grey38", 0x616161),
+    ("grey39", 0x636363),
+    ("grey40", 0x666666),
+    ("grey41", 0x696969),
+    ("grey42", 0x6b6b6b),
+    ("grey43", 0x6e6e6e),
+    ("grey44", 0x707070),
+    ("grey45", 0x737373),
+    ("grey46", 0x757575),
+    ("grey47", 0x787878),
+    ("grey48", 0x7a7a7a),
+    ("grey49", 0x7d7d7d),
+    ("grey50", 0x7f7f7f),
+    ("grey51", 0x828282),
+    ("grey52", 0x858585),
+    ("grey53", 0x878787),
+    ("grey54", 0x8a8a8a),
+    ("grey55", 0x8c8c8c),
+    ("grey56", 0x8f8f8f),
+    ("grey57", 0x919191),
+    ("grey58", 0x949494),
+    ("grey59", 0x969696),
+    ("grey60", 0x999999),
+    ("grey61", 0x9c9c9c),
+    ("grey62", 0x9e9e9e),
+    ("grey63", 0xa1a1a1),
+    ("grey64", 0xa3a3a3),
+    ("grey65", 0xa6a6a6),
+    ("grey66", 0xa8a8a8),
+    ("grey67", 0xababab),
+    ("grey68", 0xadadad),
+    ("grey69", 0xb0b0b0),
+    ("grey70", 0xb3b3b3),
+    ("grey71", 0xb5b5b5),
+    ("grey72", 0xb8b8b8),
+    ("grey73", 0xbababa),
+    ("grey74", 0xbdbdbd),
+    ("grey75", 0xbfbfbf),
+    ("grey76", 0xc2c2c2),
+    ("grey77", 0xc4c4c4),
+    ("grey78", 0xc7c7c7),
+    ("grey79", 0xc9c9c9),
+    ("grey80", 0xcccccc),
+    ("grey81", 0xcfcfcf),
+    ("grey82", 0xd1d1d1),
+    ("grey83", 0xd4d4d4),
+    ("grey84", 0xd6d6d6),
+    ("grey85", 0xd9d9d9),
+    ("grey86", 0xdbdbdb),
+    ("grey87", 0xdedede),
+    ("grey88", 0xe0e0e0),
+    ("grey89", 0xe3e3e3),
+    ("grey90", 0xe5e5e5),
+    ("grey91", 0xe8e8e8),
+    ("grey92", 0xebebeb),
+    ("grey93", 0xededed),
+    ("grey94", 0xf0f0f0),
+    ("grey95", 0xf2f2f2),
+    ("grey96", 0xf5f5f5),
+    ("grey97", 0xf7f7f7),
+    ("grey98", 0xfafafa),
+    ("grey99", 0xfcfcfc),
+    ("grey100", 0xffffff),
     ("honeydew", 0xf0fff0),
     ("honeydew1", 0xf0fff0),
     ("honeydew2", 0xe0eee0),
@@ -272,7 +383,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("indianred2", 0xee6363),
     ("indianred3", 0xcd5555),
     ("indianred4", 0x8b3a3a),
-    ("indigo", 0x4b0082),
     ("ivory", 0xfffff0),
     ("ivory1", 0xfffff0),
     ("ivory2", 0xeeeee0),
@@ -332,19 +442,18 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("lightskyblue3", 0x8db6cd),
     ("lightskyblue4", 0x607b8b),
     ("lightslateblue", 0x8470ff),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
     ("lightsteelblue1", 0xcae1ff),
     ("lightsteelblue2", 0xbcd2ee),
     ("lightsteelblue3", 0xa2b5cd),
     ("lightsteelblue4", 0x6e7b8b),
-    ("lightslategray", 0x778899),
-    ("lightslategrey", 0x778899),
-    ("lightsteelblue", 0xb0c4de),
     ("lightyellow", 0xffffe0),
     ("lightyellow1", 0xffffe0),
     ("lightyellow2", 0xeeeed1),
     ("lightyellow3", 0xcdcdb4),
     ("lightyellow4", 0x8b8b7a),
-    ("lime", 0x00ff00),
     ("limegreen", 0x32cd32),
     ("linen", 0xfaf0e6),
     ("magenta", 0xff00ff),
@@ -352,7 +461,7 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("magenta2", 0xee00ee),
     ("magenta3", 0xcd00cd),
     ("magenta4", 0x8b008b),
-    ("maroon", 0x800000),
+    ("maroon", 0xb03060),
     ("maroon1", 0xff34b3),
     ("maroon2", 0xee30a7),
     ("maroon3", 0xcd2990),
@@ -360,7 +469,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("mediumaquamarine", 0x66cdaa),
     ("mediumblue", 0x0000cd),
     ("mediumorchid", 0xba55d3),
-    ("mediumorchid", 0xba55d3),
     ("mediumorchid1", 0xe066ff),
     ("mediumorchid2", 0xd15fee),
     ("mediumorchid3", 0xb452cd),
@@ -391,10 +499,10 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("navy", 0x000080),
     ("navyblue", 0x000080),
     ("oldlace", 0xfdf5e6),
-    ("olive", 0x808000),
     ("olivedrab", 0x6b8e23),
     ("olivedrab1", 0xc0ff3e),
     ("olivedrab2", 0xb3ee3a),
+    ("olivedrab3", 0x9acd32),
     ("olivedrab4", 0x698b22),
     ("orange", 0xffa500),
     ("orange1", 0xffa500),
@@ -445,12 +553,11 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("plum3", 0xcd96cd),
     ("plum4", 0x8b668b),
     ("powderblue", 0xb0e0e6),
-    ("purple", 0x800080),
+    ("purple", 0xa020f0),
     ("purple1", 0x9b30ff),
     ("purple2", 0x912cee),
     ("purple3", 0x7d26cd),
     ("purple4", 0x551a8b),
-    ("rebeccapurple", 0x663399),
     ("red", 0xff0000),
     ("red1", 0xff0000),
     ("red2", 0xee0000),
@@ -488,7 +595,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("sienna2", 0xee7942),
     ("sienna3", 0xcd6839),
     ("sienna4", 0x8b4726),
-    ("silver", 0xc0c0c0),
     ("skyblue", 0x87ceeb),
     ("skyblue1", 0x87ceff),
     ("skyblue2", 0x7ec0ee),
@@ -525,7 +631,6 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("tan2", 0xee9a49),
     ("tan3", 0xcd853f),
     ("tan4", 0x8b5a2b),
-    ("teal", 0x008080),
     ("thistle", 0xd8bfd8),
     ("thistle1", 0xffe1ff),
     ("thistle2", 0xeed2ee),
@@ -562,7 +667,447 @@ static KNOWN_COLORS: [(&str, u32); 559] = [
     ("yellowgreen", 0x9acd32),
 ];
 
-#[derive(Debug, Clone, Copy)]
+/// The colors of the SVG color scheme.
+static SVG_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("grey", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// The ColorBrewer color schemes, with the largest number of colors. The
+/// colors of the qualitative schemes (true) are also used in the schemes with
+/// fewer colors, such as "set13". The other schemes are only available with
+/// all of their colors, such as "blues9".
+static BREWER_SCHEMES: [(&str, bool, &[u32]); 35] = [
+    (
+        "blues",
+        false,
+        &[
+            0xf7fbff, 0xdeebf7, 0xc6dbef, 0x9ecae1, 0x6baed6, 0x4292c6,
+            0x2171b5, 0x08519c, 0x08306b,
+        ],
+    ),
+    (
+        "bugn",
+        false,
+        &[
+            0xf7fcfd, 0xe5f5f9, 0xccece6, 0x99d8c9, 0x66c2a4, 0x41ae76,
+            0x238b45, 0x006d2c, 0x00441b,
+        ],
+    ),
+    (
+        "bupu",
+        false,
+        &[
+            0xf7fcfd, 0xe0ecf4, 0xbfd3e6, 0x9ebcda, 0x8c96c6, 0x8c6bb1,
+            0x88419d, 0x810f7c, 0x4d004b,
+        ],
+    ),
+    (
+        "gnbu",
+        false,
+        &[
+            0xf7fcf0, 0xe0f3db, 0xccebc5, 0xa8ddb5, 0x7bccc4, 0x4eb3d3,
+            0x2b8cbe, 0x0868ac, 0x084081,
+        ],
+    ),
+    (
+        "greens",
+        false,
+        &[
+            0xf7fcf5, 0xe5f5e0, 0xc7e9c0, 0xa1d99b, 0x74c476, 0x41ab5d,
+            0x238b45, 0x006d2c, 0x00441b,
+        ],
+    ),
+    (
+        "greys",
+        false,
+        &[
+            0xffffff, 0xf0f0f0, 0xd9d9d9, 0xbdbdbd, 0x969696, 0x737373,
+            0x525252, 0x252525, 0x000000,
+        ],
+    ),
+    (
+        "oranges",
+        false,
+        &[
+            0xfff5eb, 0xfee6ce, 0xfdd0a2, 0xfdae6b, 0xfd8d3c, 0xf16913,
+            0xd94801, 0xa63603, 0x7f2704,
+        ],
+    ),
+    (
+        "orrd",
+        false,
+        &[
+            0xfff7ec, 0xfee8c8, 0xfdd49e, 0xfdbb84, 0xfc8d59, 0xef6548,
+            0xd7301f, 0xb30000, 0x7f0000,
+        ],
+    ),
+    (
+        "pubu",
+        false,
+        &[
+            0xfff7fb, 0xece7f2, 0xd0d1e6, 0xa6bddb, 0x74a9cf, 0x3690c0,
+            0x0570b0, 0x045a8d, 0x023858,
+        ],
+    ),
+    (
+        "pubugn",
+        false,
+        &[
+            0xfff7fb, 0xece2f0, 0xd0d1e6, 0xa6bddb, 0x67a9cf, 0x3690c0,
+            0x02818a, 0x016c59, 0x014636,
+        ],
+    ),
+    (
+        "purd",
+        false,
+        &[
+            0xf7f4f9, 0xe7e1ef, 0xd4b9da, 0xc994c7, 0xdf65b0, 0xe7298a,
+            0xce1256, 0x980043, 0x67001f,
+        ],
+    ),
+    (
+        "purples",
+        false,
+        &[
+            0xfcfbfd, 0xefedf5, 0xdadaeb, 0xbcbddc, 0x9e9ac8, 0x807dba,
+            0x6a51a3, 0x54278f, 0x3f007d,
+        ],
+    ),
+    (
+        "rdpu",
+        false,
+        &[
+            0xfff7f3, 0xfde0dd, 0xfcc5c0, 0xfa9fb5, 0xf768a1, 0xdd3497,
+            0xae017e, 0x7a0177, 0x49006a,
+        ],
+    ),
+    (
+        "reds",
+        false,
+        &[
+            0xfff5f0, 0xfee0d2, 0xfcbba1, 0xfc9272, 0xfb6a4a, 0xef3b2c,
+            0xcb181d, 0xa50f15, 0x67000d,
+        ],
+    ),
+    (
+        "ylgn",
+        false,
+        &[
+            0xffffe5, 0xf7fcb9, 0xd9f0a3, 0xaddd8e, 0x78c679, 0x41ab5d,
+            0x238443, 0x006837, 0x004529,
+        ],
+    ),
+    (
+        "ylgnbu",
+        false,
+        &[
+            0xffffd9, 0xedf8b1, 0xc7e9b4, 0x7fcdbb, 0x41b6c4, 0x1d91c0,
+            0x225ea8, 0x253494, 0x081d58,
+        ],
+    ),
+    (
+        "ylorbr",
+        false,
+        &[
+            0xffffe5, 0xfff7bc, 0xfee391, 0xfec44f, 0xfe9929, 0xec7014,
+            0xcc4c02, 0x993404, 0x662506,
+        ],
+    ),
+    (
+        "ylorrd",
+        false,
+        &[
+            0xffffcc, 0xffeda0, 0xfed976, 0xfeb24c, 0xfd8d3c, 0xfc4e2a,
+            0xe31a1c, 0xbd0026, 0x800026,
+        ],
+    ),
+    (
+        "brbg",
+        false,
+        &[
+            0x543005, 0x8c510a, 0xbf812d, 0xdfc27d, 0xf6e8c3, 0xf5f5f5,
+            0xc7eae5, 0x80cdc1, 0x35978f, 0x01665e, 0x003c30,
+        ],
+    ),
+    (
+        "piyg",
+        false,
+        &[
+            0x8e0152, 0xc51b7d, 0xde77ae, 0xf1b6da, 0xfde0ef, 0xf7f7f7,
+            0xe6f5d0, 0xb8e186, 0x7fbc41, 0x4d9221, 0x276419,
+        ],
+    ),
+    (
+        "prgn",
+        false,
+        &[
+            0x40004b, 0x762a83, 0x9970ab, 0xc2a5cf, 0xe7d4e8, 0xf7f7f7,
+            0xd9f0d3, 0xa6dba0, 0x5aae61, 0x1b7837, 0x00441b,
+        ],
+    ),
+    (
+        "puor",
+        false,
+        &[
+            0x7f3b08, 0xb35806, 0xe08214, 0xfdb863, 0xfee0b6, 0xf7f7f7,
+            0xd8daeb, 0xb2abd2, 0x8073ac, 0x542788, 0x2d004b,
+        ],
+    ),
+    (
+        "rdbu",
+        false,
+        &[
+            0x67001f, 0xb2182b, 0xd6604d, 0xf4a582, 0xfddbc7, 0xf7f7f7,
+            0xd1e5f0, 0x92c5de, 0x4393c3, 0x2166ac, 0x053061,
+        ],
+    ),
+    (
+        "rdgy",
+        false,
+        &[
+            0x67001f, 0xb2182b, 0xd6604d, 0xf4a582, 0xfddbc7, 0xffffff,
+            0xe0e0e0, 0xbababa, 0x878787, 0x4d4d4d, 0x1a1a1a,
+        ],
+    ),
+    (
+        "rdylbu",
+        false,
+        &[
+            0xa50026, 0xd73027, 0xf46d43, 0xfdae61, 0xfee090, 0xffffbf,
+            0xe0f3f8, 0xabd9e9, 0x74add1, 0x4575b4, 0x313695,
+        ],
+    ),
+    (
+        "rdylgn",
+        false,
+        &[
+            0xa50026, 0xd73027, 0xf46d43, 0xfdae61, 0xfee08b, 0xffffbf,
+            0xd9ef8b, 0xa6d96a, 0x66bd63, 0x1a9850, 0x006837,
+        ],
+    ),
+    (
+        "spectral",
+        false,
+        &[
+            0x9e0142, 0xd53e4f, 0xf46d43, 0xfdae61, 0xfee08b, 0xffffbf,
+            0xe6f598, 0xabdda4, 0x66c2a5, 0x3288bd, 0x5e4fa2,
+        ],
+    ),
+    (
+        "accent",
+        true,
+        &[
+            0x7fc97f, 0xbeaed4, 0xfdc086, 0xffff99, 0x386cb0, 0xf0027f,
+            0xbf5b17, 0x666666,
+        ],
+    ),
+    (
+        "dark2",
+        true,
+        &[
+            0x1b9e77, 0xd95f02, 0x7570b3, 0xe7298a, 0x66a61e, 0xe6ab02,
+            0xa6761d, 0x666666,
+        ],
+    ),
+    (
+        "paired",
+        true,
+        &[
+            0xa6cee3, 0x1f78b4, 0xb2df8a, 0x33a02c, 0xfb9a99, 0xe31a1c,
+            0xfdbf6f, 0xff7f00, 0xcab2d6, 0x6a3d9a, 0xffff99, 0xb15928,
+        ],
+    ),
+    (
+        "pastel1",
+        true,
+        &[
+            0xfbb4ae, 0xb3cde3, 0xccebc5, 0xdecbe4, 0xfed9a6, 0xffffcc,
+            0xe5d8bd, 0xfddaec, 0xf2f2f2,
+        ],
+    ),
+    (
+        "pastel2",
+        true,
+        &[
+            0xb3e2cd, 0xfdcdac, 0xcbd5e8, 0xf4cae4, 0xe6f5c9, 0xfff2ae,
+            0xf1e2cc, 0xcccccc,
+        ],
+    ),
+    (
+        "set1",
+        true,
+        &[
+            0xe41a1c, 0x377eb8, 0x4daf4a, 0x984ea3, 0xff7f00, 0xffff33,
+            0xa65628, 0xf781bf, 0x999999,
+        ],
+    ),
+    (
+        "set2",
+        true,
+        &[
+            0x66c2a5, 0xfc8d62, 0x8da0cb, 0xe78ac3, 0xa6d854, 0xffd92f,
+            0xe5c494, 0xb3b3b3,
+        ],
+    ),
+    (
+        "set3",
+        true,
+        &[
+            0x8dd3c7, 0xffffb3, 0xbebada, 0xfb8072, 0x80b1d3, 0xfdb462,
+            0xb3de69, 0xfccde5, 0xd9d9d9, 0xbc80bd, 0xccebc5, 0xffed6f,
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     // Color in the format RGBA
     color: u32,
@@ -587,11 +1132,19 @@ impl Color {
         }
     }
 
+    /// \returns the color \p name, such as "coral", "transparent", "#edebe9",
+    /// the HSV triplet "0.482 0.714 0.878", or a color in a color scheme, such
+    /// as "/x11/gray" or "/blues9/3". Names are matched in the SVG scheme
+    /// first, and then in the X11 scheme, and are not case sensitive.
     pub fn from_name(name: &str) -> Option<Color> {
-        for pair in KNOWN_COLORS {
-            if name == pair.0 {
-                return Some(Color::new((pair.1 << 8) + 0xff));
-            }
+        let name = name.trim().to_lowercase();
+        if name == "transparent" {
+            return Some(Color::transparent());
+        }
+        // Colors in a color scheme. Example: /blues9/3.
+        if let Option::Some(rest) = name.strip_prefix('/') {
+            let (scheme, color) = rest.split_once('/')?;
+            return Self::from_scheme(scheme, color);
         }
         // Parse the web format. Example: #edebe9.
         if name.starts_with('#') {
@@ -603,13 +1156,118 @@ impl Color {
                     return Some(Color::new(color));
                 }
             }
+            return None;
+        }
+        if let Option::Some(color) = Self::from_hsv_string(&name) {
+            return Some(color);
+        }
+        Self::from_table(&SVG_COLORS, &name)
+            .or_else(|| Self::from_table(&X11_COLORS, &name))
+    }
+
+    /// \returns the color \p name in the color \p table.
+    fn from_table(table: &[(&str, u32)], name: &str) -> Option<Color> {
+        let pair = table.iter().find(|pair| pair.0 == name)?;
+        Some(Color::new((pair.1 << 8) + 0xff))
+    }
+
+    /// \returns the color \p name in the color scheme \p scheme. The scheme
+    /// is "x11", "svg", or a ColorBrewer scheme such as "blues9", where the
+    /// colors are numbered from 1. The empty scheme is the default scheme.
+    fn from_scheme(scheme: &str, name: &str) -> Option<Color> {
+        match scheme {
+            "" => Self::from_name(name),
+            "x11" => Self::from_table(&X11_COLORS, name),
+            "svg" => Self::from_table(&SVG_COLORS, name),
+            _ => {
+                let colors = get_brewer_scheme(scheme)?;
+                let index = name.parse::<usize>().ok()?.checked_sub(1)?;
+                let color = colors.get(index)?;
+                Some(Color::new((color << 8) + 0xff))
+            }
+        }
+    }
+
+    /// \returns the color of the HSV triplet \p name, such as
+    /// "0.482 0.714 0.878" or "0.482,0.714,0.878".
+    pub fn from_hsv_string(name: &str) -> Option<Color> {
+        let is_separator = |c: char| c == ',' || c.is_whitespace();
+        let parts: Vec<f64> = name
+            .split(is_separator)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<f64>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+        if parts.len() != 3 {
+            return None;
         }
-        None
+        Some(Self::from_hsv(parts[0], parts[1], parts[2]))
+    }
+
+    /// \returns the color with the hue \p h, saturation \p s and value \p v,
+    /// that are in the range [0..1].
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Color {
+        let h = h.clamp(0., 1.) * 6.;
+        let s = s.clamp(0., 1.);
+        let v = v.clamp(0., 1.);
+        let f = h - h.floor();
+        let p = v * (1. - s);
+        let q = v * (1. - s * f);
+        let t = v * (1. - s * (1. - f));
+        let (r, g, b) = match h.floor() as usize % 6 {
+            0 => (v, t, p),
+            1 => (q, v, p),
+            2 => (p, v, t),
+            3 => (p, q, v),
+            4 => (t, p, v),
+            _ => (v, p, q),
+        };
+        let to_byte = |x: f64| (x * 255.).round() as u32;
+        Color::new(to_byte(r) << 24 | to_byte(g) << 16 | to_byte(b) << 8 | 0xff)
     }
 
     pub fn to_web_color(&self) -> String {
         format!("#{:08x}", self.color)
     }
+
+    /// \returns the red, green, blue and alpha channels of the color.
+    pub fn to_rgba(&self) -> (u8, u8, u8, u8) {
+        let [r, g, b, a] = self.color.to_be_bytes();
+        (r, g, b, a)
+    }
+}
+
+/// \returns the colors of the ColorBrewer scheme \p name, such as "blues9" or
+/// "set13", or None if the scheme is unknown.
+fn get_brewer_scheme(name: &str) -> Option<&'static [u32]> {
+    for (base, is_qualitative, colors) in BREWER_SCHEMES.iter() {
+        let count = name.strip_prefix(base).and_then(|x| x.parse().ok());
+        if let Option::Some(count) = count {
+            let max = colors.len();
+            if count == max || (*is_qualitative && (3..max).contains(&count)) {
+                return Some(&colors[..count]);
+            }
+        }
+    }
+    None
+}
+
+/// Parse the Graphviz color list \p list, such as "red:blue" or
+/// "red;0.3:blue", into its colors and their optional weights. The weight is
+/// the fraction of the area that the color fills.
+pub fn parse_color_list(list: &str) -> Vec<(Color, Option<f64>)> {
+    let mut res = Vec::new();
+    for item in list.split(':').map(|x| x.trim()) {
+        let (name, weight) = match item.split_once(';') {
+            Option::Some((name, weight)) => (name, weight.parse::<f64>().ok()),
+            Option::None => (item, None),
+        };
+        if name.is_empty() {
+            continue;
+        }
+        res.push((Color::fast(name), weight.map(|x| x.clamp(0., 1.))));
+    }
+    res
 }
 
 #[test]
@@ -624,4 +1282,49 @@ fn test_color() {
     assert_eq!(color.unwrap().to_web_color(), "#112233ff");
     let color = Color::from_name("#112233FA");
     assert_eq!(color.unwrap().to_web_color(), "#112233fa");
+    assert_eq!(color.unwrap().to_rgba(), (0x11, 0x22, 0x33, 0xfa));
+}
+
+#[test]
+fn test_color_list() {
+    let list = parse_color_list("red:blue");
+    assert_eq!(
+        list,
+        vec![(Color::fast("red"), None), (Color::fast("blue"), None)]
+    );
+
+    let list = parse_color_list("red;0.3:#0000ff:transparent;2");
+    assert_eq!(list[0], (Color::fast("red"), Some(0.3)));
+    assert_eq!(list[1], (Color::fast("blue"), None));
+    assert_eq!(list[2], (Color::transparent(), Some(1.)));
+    assert!(parse_color_list("").is_empty());
+}
+
+#[test]
+fn test_color_schemes() {
+    let web = |name: &str| Color::from_name(name).unwrap().to_web_color();
+    // SVG names take precedence over X11 names.
+    assert_eq!(web("gray"), "#808080ff");
+    assert_eq!(web("/x11/gray"), "#bebebeff");
+    assert_eq!(web("/svg/gray"), "#808080ff");
+    assert_eq!(web("//green"), "#008000ff");
+    assert_eq!(web("Gray50"), "#7f7f7fff");
+    assert_eq!(web("navajowhite3"), "#cdb38bff");
+    assert_eq!(web("rebeccapurple"), "#663399ff");
+
+    // ColorBrewer schemes.
+    assert_eq!(web("/blues9/1"), "#f7fbffff");
+    assert_eq!(web("/set13/2"), "#377eb8ff");
+    assert_eq!(web("/set312/12"), "#ffed6fff");
+    assert_eq!(web("/pubugn9/9"), "#014636ff");
+    assert!(Color::from_name("/set13/4").is_none());
+    assert!(Color::from_name("/blues9/0").is_none());
+    assert!(Color::from_name("/blues3/1").is_none());
+    assert!(Color::from_name("/nosuchscheme/1").is_none());
+
+    // HSV triplets.
+    assert_eq!(web("0.482 0.714 0.878"), "#40e0cfff");
+    assert_eq!(web("0,1,1"), "#ff0000ff");
+    assert_eq!(web("0.5, 0, 0.5"), "#808080ff");
+    assert!(Color::from_name("0.5 0.5").is_none());
 }
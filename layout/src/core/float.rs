@@ -0,0 +1,52 @@
+//! The math functions of f64 that are implemented by std, for the builds
+//! without std, where they are computed by the libm crate.
+
+/// The methods of f64 that are missing from `core`. Import this trait only
+/// without std, because the inherent methods of f64 shadow it.
+pub(crate) trait FloatExt {
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn sin_cos(self) -> (Self, Self)
+    where
+        Self: Sized;
+    fn atan2(self, other: Self) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn fract(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+}
+
+impl FloatExt for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+    fn sin_cos(self) -> (f64, f64) {
+        (libm::sin(self), libm::cos(self))
+    }
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+    fn fract(self) -> f64 {
+        self - libm::trunc(self)
+    }
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+}
@@ -1,9 +1,32 @@
 //! Defines the interfaces for accessing and querying shapes.
 
 use super::{
-    geometry::{Point, Position},
+    color::Color,
+    geometry::{get_arrow_heads, HeadShape, Point, Position},
     style::StyleAttr,
 };
+use alloc::string::String;
+
+/// The hyperlink and the tooltip of a node, an edge or a cluster, from the
+/// 'URL' (or 'href'), 'target' and 'tooltip' attributes.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Link {
+    pub url: Option<String>,
+    /// The window or the frame that the link opens in, such as "_blank".
+    pub target: Option<String>,
+    pub tooltip: Option<String>,
+}
+
+/// A CSS stylesheet of the drawing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stylesheet {
+    /// A link to the stylesheet at a URL.
+    Link(String),
+    /// CSS rules that are embedded in the document.
+    Inline(String),
+}
 
 /// This is the trait that all elements that can be arranged need to implement.
 pub trait Visible {
@@ -88,6 +111,27 @@ pub trait RenderBackend {
         properties: Option<String>,
     );
 
+    /// Draw a closed polygon through the corners in \p points. The parameter
+    /// \p clip is an optional clip region (see: create_clip).
+    fn draw_polygon(
+        &mut self,
+        points: &[Point],
+        look: &StyleAttr,
+        properties: Option<String>,
+        clip: Option<ClipHandle>,
+    );
+
+    /// Draw a path made of the cubic bezier \p segments, where each segment is
+    /// (start, control 1, control 2, end). A segment that does not start at
+    /// the end of the previous segment begins a new sub-path. The path is
+    /// filled if \p look has a fill color.
+    fn draw_path(
+        &mut self,
+        segments: &[(Point, Point, Point, Point)],
+        look: &StyleAttr,
+        properties: Option<String>,
+    );
+
     /// Draw a labe.
     fn draw_text(&mut self, xy: Point, text: &str, look: &StyleAttr);
 
@@ -102,6 +146,61 @@ pub trait RenderBackend {
         text: &str,
     );
 
+    /// Draw the image \p href, with the top-left corner \p xy and the size
+    /// \p size. Backends that can't draw images draw a box with the name of
+    /// the image file at the top, above the label of the node, instead.
+    fn draw_image(
+        &mut self,
+        xy: Point,
+        size: Point,
+        href: &str,
+        properties: Option<String>,
+    ) {
+        let look = StyleAttr::simple();
+        self.draw_rect(xy, size, &look, properties, Option::None);
+        let name = if href.starts_with("data:") {
+            "image"
+        } else {
+            href.rsplit(['/', '\\']).next().unwrap_or(href)
+        };
+        let top = Point::new(size.x / 2., look.font_size as f64 / 2.);
+        self.draw_text(xy.add(top), name, &look);
+    }
+
+    /// Begin the group of the shapes of a node, an edge or a cluster, that is
+    /// identified by the attributes \p properties. The shapes that are drawn
+    /// until the matching call to end_group() belong to the element.
+    fn begin_group(&mut self, _properties: &str) {}
+
+    /// End the group of the last call to begin_group().
+    fn end_group(&mut self) {}
+
+    /// Begin the layer \p name of the drawing. The elements that are in
+    /// several layers are drawn once in each of them.
+    fn begin_layer(&mut self, _name: &str) {}
+
+    /// End the layer of the last call to begin_layer().
+    fn end_layer(&mut self) {}
+
+    /// Begin the shapes of an element that has the hyperlink or the tooltip
+    /// \p link. The shapes that are drawn until the matching call to
+    /// end_link() belong to the element. Links nest, like the clusters.
+    fn begin_link(&mut self, _link: &Link) {}
+
+    /// End the shapes of the element of the last call to begin_link().
+    fn end_link(&mut self) {}
+
+    /// Style the drawing with the CSS stylesheet \p sheet. Backends that
+    /// don't use CSS ignore it.
+    fn add_stylesheet(&mut self, _sheet: &Stylesheet) {}
+
+    /// Paint the background of the canvas with \p color, instead of white.
+    fn set_background(&mut self, _color: Color) {}
+
+    /// Grow the canvas to contain the point \p xy. Backends that fit the
+    /// canvas to the drawing use this to leave a margin around it.
+    fn grow_canvas(&mut self, _xy: Point) {}
+
     /// Generate a clip region that shapes can use to create complex shapes.
     fn create_clip(
         &mut self,
@@ -110,3 +209,33 @@ pub trait RenderBackend {
         rounded_px: usize,
     ) -> ClipHandle;
 }
+
+/// Draw the heads of the arrow \p path on \p canvas, as polygons and circles.
+/// This is used by the backends that don't have markers. See
+/// RenderBackend::draw_arrow for the meaning of \p head and \p look.
+pub fn draw_arrow_heads(
+    canvas: &mut dyn RenderBackend,
+    path: &[(Point, Point)],
+    head: (bool, bool),
+    look: &StyleAttr,
+) {
+    let get_look = |filled: bool| {
+        let fill = if filled {
+            look.line_color
+        } else {
+            Color::fast("white")
+        };
+        StyleAttr::new(look.line_color, 1., Some(fill), 0, look.font_size)
+    };
+    for shape in get_arrow_heads(path, head, look) {
+        match shape {
+            HeadShape::Polygon(points, filled) => {
+                canvas.draw_polygon(&points, &get_look(filled), None, None);
+            }
+            HeadShape::Circle(center, radius, filled) => {
+                let size = Point::splat(2. * radius);
+                canvas.draw_circle(center, size, &get_look(filled), None);
+            }
+        }
+    }
+}
@@ -2,8 +2,15 @@
 //! interaction. This includes things like intersection of shapes and length
 //! of vectors.
 
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::style::{ArrowHeadKind, StyleAttr};
+use alloc::vec;
+use alloc::vec::Vec;
+
 // Stores a 2D coordinate, or a vector.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -66,8 +73,8 @@ impl Point {
     }
 }
 
-impl std::fmt::Display for Point {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for Point {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "(x: {:.3}, y: {:.3})", self.x, self.y)
     }
 }
@@ -92,7 +99,6 @@ pub fn get_connection_point_for_circle(
     from: Point,
     force: f64,
 ) -> (Point, Point) {
-    let loc = loc;
     let dx = from.x - loc.x;
     let dy = from.y - loc.y;
 
@@ -100,6 +106,11 @@ pub fn get_connection_point_for_circle(
     let b = size.y / 2.;
     let m = dy / dx;
 
+    // Edges connect to the center of shapes without an area.
+    if a <= 0. || b <= 0. {
+        return create_vector_of_length(loc, from, force);
+    }
+
     if dx == 0. {
         let b = b * dy.signum();
         let loc1 = Point::new(loc.x, loc.y + b);
@@ -124,10 +135,220 @@ pub fn interpolate(v0: Point, v1: Point, w: f64) -> Point {
     v0.scale(w).add(v1.scale(1. - w))
 }
 
-/// Return the normalized vector \p v multiplied by the scalar \p s.
+/// \returns the point at \p t on the cubic bezier curve \p p0 .. \p p3.
+pub fn cubic_bezier(
+    p0: Point,
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    t: f64,
+) -> Point {
+    let s = 1. - t;
+    let a = p0.scale(s * s * s);
+    let b = p1.scale(3. * s * s * t);
+    let c = p2.scale(3. * s * t * t);
+    let d = p3.scale(t * t * t);
+    a.add(b).add(c).add(d)
+}
+
+/// Convert the arrow path \p path, that is passed to draw_arrow, into a list
+/// of cubic bezier segments (start, control 1, control 2, end). The path has
+/// the structure of an SVG path: [(M,C) S S S ...], where the first control
+/// point of each 'S' segment is the reflection of the previous control point.
+pub fn get_bezier_segments(
+    path: &[(Point, Point)],
+) -> Vec<(Point, Point, Point, Point)> {
+    if path.len() < 2 {
+        return Vec::new();
+    }
+    let mut res = vec![(path[0].0, path[0].1, path[1].0, path[1].1)];
+    for point in path.iter().skip(2) {
+        let prev = res[res.len() - 1];
+        let c1 = prev.3.add(prev.3.sub(prev.2));
+        res.push((prev.3, c1, point.0, point.1));
+    }
+    res
+}
+
+/// Approximate the bezier \p segments (see RenderBackend::draw_path) with
+/// \p steps straight lines per segment. \returns a polyline for each of the
+/// connected sub-paths.
+pub fn flatten_bezier_segments(
+    segments: &[(Point, Point, Point, Point)],
+    steps: usize,
+) -> Vec<Vec<Point>> {
+    let mut res: Vec<Vec<Point>> = Vec::new();
+    let mut last: Option<Point> = None;
+    for (p0, p1, p2, p3) in segments.iter() {
+        if last != Some(*p0) {
+            res.push(vec![*p0]);
+        }
+        let polyline = res.last_mut().unwrap();
+        for i in 1..=steps.max(1) {
+            let t = i as f64 / steps.max(1) as f64;
+            polyline.push(cubic_bezier(*p0, *p1, *p2, *p3, t));
+        }
+        last = Some(*p3);
+    }
+    res
+}
+
+/// A part of an arrow head.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadShape {
+    /// A polygon, that is filled with the line color when the flag is set,
+    /// and with white otherwise.
+    Polygon(Vec<Point>, bool),
+    /// A circle with a center and a radius, that is filled like the polygon.
+    Circle(Point, f64, bool),
+}
+
+impl HeadShape {
+    pub fn is_filled(&self) -> bool {
+        match self {
+            HeadShape::Polygon(_, filled) => *filled,
+            HeadShape::Circle(_, _, filled) => *filled,
+        }
+    }
+
+    /// \returns the shape with its points moved by \p f, and with its radius
+    /// scaled by \p scale.
+    pub fn transform(&self, f: impl Fn(Point) -> Point, scale: f64) -> Self {
+        match self {
+            HeadShape::Polygon(points, filled) => HeadShape::Polygon(
+                points.iter().map(|p| f(*p)).collect(),
+                *filled,
+            ),
+            HeadShape::Circle(center, radius, filled) => {
+                HeadShape::Circle(f(*center), radius * scale, *filled)
+            }
+        }
+    }
+}
+
+/// \returns the parts of the arrow head \p kind, in units of the line width.
+/// The tip of the head is at the origin, and the head points in the direction
+/// of the positive x axis, so the rest of the head is at negative x values.
+pub fn get_arrow_head_shape(kind: ArrowHeadKind) -> Vec<HeadShape> {
+    let poly = |points: &[(f64, f64)], filled: bool| {
+        let points = points.iter().map(|(x, y)| Point::new(*x, *y));
+        HeadShape::Polygon(points.collect(), filled)
+    };
+    // A bar across the line, and a hollow circle, that start at x.
+    let tee =
+        |x: f64| poly(&[(x, -5.), (x, 5.), (x - 2., 5.), (x - 2., -5.)], true);
+    let ring = |x: f64| HeadShape::Circle(Point::new(x - 4., 0.), 4., false);
+    let crow = || poly(&[(0., -4.), (-10., 0.), (0., 4.), (-3., 0.)], true);
+    match kind {
+        ArrowHeadKind::Normal | ArrowHeadKind::ONormal => {
+            let filled = kind == ArrowHeadKind::Normal;
+            vec![poly(&[(0., 0.), (-10., 3.5), (-10., -3.5)], filled)]
+        }
+        ArrowHeadKind::Vee => vec![poly(
+            &[(0., 0.), (-10., 3.5), (-7., 0.), (-10., -3.5)],
+            true,
+        )],
+        ArrowHeadKind::Dot | ArrowHeadKind::ODot => {
+            let filled = kind == ArrowHeadKind::Dot;
+            vec![HeadShape::Circle(Point::new(-4., 0.), 4., filled)]
+        }
+        ArrowHeadKind::Diamond | ArrowHeadKind::ODiamond => {
+            let filled = kind == ArrowHeadKind::Diamond;
+            vec![poly(
+                &[(0., 0.), (-6., 3.5), (-12., 0.), (-6., -3.5)],
+                filled,
+            )]
+        }
+        ArrowHeadKind::Box => vec![poly(
+            &[(0., -3.5), (0., 3.5), (-7., 3.5), (-7., -3.5)],
+            true,
+        )],
+        ArrowHeadKind::Tee => vec![tee(-2.)],
+        ArrowHeadKind::Crow => vec![crow()],
+        // The second shape is behind the first one, away from the node.
+        ArrowHeadKind::TeeTee => vec![tee(-2.), tee(-6.)],
+        ArrowHeadKind::TeeODot => vec![tee(-2.), ring(-6.)],
+        ArrowHeadKind::CrowTee => vec![crow(), tee(-11.)],
+        ArrowHeadKind::CrowODot => vec![crow(), ring(-11.)],
+        ArrowHeadKind::None => Vec::new(),
+    }
+}
+
+/// \returns the parts of the arrow heads that the backends without markers
+/// draw for the arrow \p path (see get_bezier_segments). The flags in \p head
+/// select the start and the end of the arrow. The shapes of the heads are
+/// selected by \p look, and the heads are scaled by the line width and the
+/// arrow size of \p look.
+pub fn get_arrow_heads(
+    path: &[(Point, Point)],
+    head: (bool, bool),
+    look: &StyleAttr,
+) -> Vec<HeadShape> {
+    let segments = get_bezier_segments(path);
+    let mut res = Vec::new();
+    if segments.is_empty() {
+        return res;
+    }
+    let scale = look.penwidth.max(1.) * look.arrow_size;
+    let mut add_head = |kind: ArrowHeadKind, tip: Point, dir: Point| {
+        let len = dir.length();
+        if len == 0. {
+            return;
+        }
+        let unit = dir.scale(1. / len);
+        let side = Point::new(-unit.y, unit.x);
+        let to_world = |p: Point| {
+            tip.add(unit.scale(p.x * scale))
+                .add(side.scale(p.y * scale))
+        };
+        for shape in get_arrow_head_shape(kind) {
+            res.push(shape.transform(to_world, scale));
+        }
+    };
+    if head.0 {
+        add_head(look.arrow_heads.0, path[0].0, path[0].0.sub(path[0].1));
+    }
+    if head.1 {
+        let (p0, p1, p2, p3) = segments[segments.len() - 1];
+        // Use the curve itself if the last control point is the end.
+        let dir = if p3.distance_to(p2) > 0. {
+            p3.sub(p2)
+        } else {
+            p3.sub(cubic_bezier(p0, p1, p2, p3, 0.9))
+        };
+        add_head(look.arrow_heads.1, p3, dir);
+    }
+    res
+}
+
+/// \returns True if \p p is inside of the polygon \p points, using the
+/// even-odd rule.
+pub fn point_in_polygon(p: Point, points: &[Point]) -> bool {
+    let mut res = false;
+    if points.is_empty() {
+        return res;
+    }
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[j]);
+        if (a.y > p.y) != (b.y > p.y)
+            && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x
+        {
+            res = !res;
+        }
+        j = i;
+    }
+    res
+}
+
+/// Return the normalized vector \p v multiplied by the scalar \p s. The zero
+/// vector has no direction, and becomes a vector along the x axis, like in
+/// create_vector_of_length.
 pub fn normalize_scale_vector(v: Point, s: f64) -> Point {
     let len = Point::zero().distance_to(v);
-    assert!(len > 0., "Can't normalize the unit vector");
+    if len == 0. || len.is_nan() {
+        return Point::new(s, 0.);
+    }
     v.scale(s / len)
 }
 // Returns a vector in a direction of \to target, of length \p s.
@@ -145,6 +366,60 @@ pub fn create_vector_of_length(
     (from, t.add(from))
 }
 
+/// \returns true if \p name is one of the compass points that DOT ports use,
+/// such as "n", "se" or "c".
+pub fn is_compass_point(name: &str) -> bool {
+    matches!(
+        name,
+        "n" | "ne" | "e" | "se" | "s" | "sw" | "w" | "nw" | "c" | "_"
+    )
+}
+
+/// \returns the direction of the compass point \p name, such as (1, -1) for
+/// "ne". The center ("c" or "_") has no direction.
+pub fn get_compass_direction(name: &str) -> Option<Point> {
+    let (x, y) = match name {
+        "n" => (0., -1.),
+        "ne" => (1., -1.),
+        "e" => (1., 0.),
+        "se" => (1., 1.),
+        "s" => (0., 1.),
+        "sw" => (-1., 1.),
+        "w" => (-1., 0.),
+        "nw" => (-1., -1.),
+        _ => return None,
+    };
+    Some(Point::new(x, y))
+}
+
+/// This is the implementation of get_connector_location for ports with a
+/// compass point. The edge connects to the side or the corner \p dir of the
+/// box at \p loc with the size \p size, and leaves in the direction \p dir.
+pub fn get_compass_point_for_box(
+    loc: Point,
+    size: Point,
+    dir: Point,
+    force: f64,
+) -> (Point, Point) {
+    let con =
+        Point::new(loc.x + dir.x * size.x / 2., loc.y + dir.y * size.y / 2.);
+    (con, con.add(normalize_scale_vector(dir, force)))
+}
+
+/// This is the implementation of get_connector_location for ports with a
+/// compass point, for circle-like shapes. See get_compass_point_for_box.
+pub fn get_compass_point_for_circle(
+    loc: Point,
+    size: Point,
+    dir: Point,
+    force: f64,
+) -> (Point, Point) {
+    let unit = normalize_scale_vector(dir, 1.);
+    let con =
+        Point::new(loc.x + unit.x * size.x / 2., loc.y + unit.y * size.y / 2.);
+    (con, con.add(unit.scale(force)))
+}
+
 /// This is the implementation of get_connector_location for box-like shapes.
 /// 'See get_connector_location' for details.
 pub fn get_connection_point_for_box(
@@ -218,6 +493,43 @@ pub fn get_connection_point_for_box(
     create_vector_of_length(con, from, force)
 }
 
+/// This is the implementation of get_connector_location for shapes with the
+/// outline \p points, that surrounds the center \p loc. The edge connects where
+/// the line from the center to \p from crosses the outline.
+/// 'See get_connector_location' for details.
+pub fn get_connection_point_for_polygon(
+    loc: Point,
+    points: &[Point],
+    from: Point,
+    force: f64,
+) -> (Point, Point) {
+    let cross = |a: Point, b: Point| a.x * b.y - a.y * b.x;
+    let dir = from.sub(loc);
+
+    // Find the nearest side that the ray from the center crosses. The ray is
+    // loc + t * dir, and the side is a + u * (b - a).
+    let mut nearest = f64::INFINITY;
+    for (i, a) in points.iter().enumerate() {
+        let side = points[(i + 1) % points.len()].sub(*a);
+        let denom = cross(dir, side);
+        if denom.abs() < 1e-9 {
+            continue;
+        }
+        let w = a.sub(loc);
+        let t = cross(w, side) / denom;
+        let u = cross(w, dir) / denom;
+        if t >= 0. && (0. ..=1.).contains(&u) {
+            nearest = nearest.min(t);
+        }
+    }
+    let con = if nearest.is_finite() {
+        loc.add(dir.scale(nearest))
+    } else {
+        loc
+    };
+    create_vector_of_length(con, from, force)
+}
+
 pub fn get_passthrough_path_invisible(
     _size: Point,
     center: Point,
@@ -240,8 +552,22 @@ pub fn get_passthrough_path_invisible(
     let ar = center.sub(from);
     let rb = to.sub(center);
 
-    let a_outgoing_edge = normalize_scale_vector(ar.neg(), force);
-    let b_outgoing_edge = normalize_scale_vector(rb.neg(), force);
+    // Connectors that sit on one of their neighbors, which can happen in large
+    // graphs, follow the direction to the other neighbor.
+    let outgoing = |v: Point| {
+        if v.length() > 0. {
+            Some(normalize_scale_vector(v.neg(), force))
+        } else {
+            None
+        }
+    };
+    let (a_outgoing_edge, b_outgoing_edge) = match (outgoing(ar), outgoing(rb))
+    {
+        (Some(a), Some(b)) => (a, b),
+        (Some(a), None) => (a, a),
+        (None, Some(b)) => (b, b),
+        (None, None) => return (center, center.add(Point::new(0., force))),
+    };
 
     // If this is a self-edge then handle it in a special way. First check if
     // the source and destination are identical. If they are then prevent the
@@ -280,7 +606,9 @@ pub fn pad_shape_scalar(size: Point, s: f64) -> Point {
     Point::new(size.x + s, size.y + s)
 }
 
-/// Estimate the bounding box of some rendered text.
+/// Estimate the bounding box of some rendered text, where each character
+/// takes a square of the font size. See text::get_size_for_text for the size
+/// of text in its font.
 pub fn get_size_for_str(label: &str, font_size: usize) -> Point {
     // Find the longest line.
     let max_line_len = if !label.is_empty() {
@@ -448,6 +776,14 @@ impl Position {
         self.size = size;
     }
 
+    pub fn halo(&self) -> Point {
+        self.halo
+    }
+
+    pub fn set_halo(&mut self, halo: Point) {
+        self.halo = halo;
+    }
+
     /// Update the center point for the shape. This is expressed as the delta
     /// from the center of mass (middle-point).
     pub fn set_new_center_point(&mut self, center: Point) {
@@ -606,3 +942,54 @@ fn segment_rect_intersection_test() {
     assert!(!segment_rect_intersection((v1.0, v1.1), (v1.2, v1.3)));
     assert!(!segment_rect_intersection((v2.0, v2.1), (v2.2, v2.3)));
 }
+
+#[test]
+fn zero_length_vectors_test() {
+    let zero = Point::zero();
+    assert_eq!(normalize_scale_vector(zero, 3.), Point::new(3., 0.));
+    // Shapes without an area connect at their center.
+    let loc = Point::new(10., 10.);
+    let from = Point::new(10., 40.);
+    let (con, dir) = get_connection_point_for_circle(loc, zero, from, 5.);
+    assert_eq!(con, loc);
+    assert_eq!(dir, Point::new(10., 15.));
+}
+
+#[test]
+fn flatten_bezier_segments_test() {
+    let a = Point::new(0., 0.);
+    let b = Point::new(30., 0.);
+    let c = Point::new(60., 60.);
+    // A straight curve, followed by a connected segment and a sub-path that
+    // starts somewhere else.
+    let segments = [(a, a, b, b), (b, b, b, b), (c, c, c, c)];
+    let res = flatten_bezier_segments(&segments, 2);
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0], vec![a, Point::new(15., 0.), b, b, b]);
+    assert_eq!(res[1], vec![c, c, c]);
+}
+
+#[test]
+fn test_connection_point_for_polygon() {
+    // A diamond around the origin.
+    let diamond = [
+        Point::new(0., -10.),
+        Point::new(20., 0.),
+        Point::new(0., 10.),
+        Point::new(-20., 0.),
+    ];
+    let loc = Point::zero();
+    let con = get_connection_point_for_polygon(loc, &diamond, loc, 0.);
+    assert_eq!(con.0, loc);
+
+    // The corners and the middle of the sides.
+    let con = get_connection_point_for_polygon(loc, &diamond, diamond[1], 0.);
+    assert_eq!(con.0, diamond[1]);
+    let from = Point::new(0., -100.);
+    let con = get_connection_point_for_polygon(loc, &diamond, from, 5.);
+    assert_eq!(con.0, diamond[0]);
+    assert_eq!(con.1, Point::new(0., -15.));
+    let from = Point::new(-100., 50.);
+    let con = get_connection_point_for_polygon(loc, &diamond, from, 0.);
+    assert_eq!(con.0, Point::new(-10., 5.));
+}
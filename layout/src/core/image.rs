@@ -0,0 +1,247 @@
+//! Images that are drawn inside of nodes, like the `image` attribute of
+//! Graphviz. The image is measured when it's loaded, so that the node can be
+//! made large enough to contain it, and the backends draw it by reference, or
+//! embed its data in the output.
+
+use super::geometry::Point;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::{format, vec::Vec};
+
+/// The size of a pixel, in points. Images are assumed to have 96 pixels per
+/// inch, like in Graphviz.
+pub const POINTS_PER_PIXEL: f64 = 0.75;
+
+/// Selects how the image fills the node, when the node is larger than the
+/// image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageScale {
+    /// Draw the image in its natural size.
+    #[default]
+    None,
+    /// Scale the image to fit the node, and keep its aspect ratio.
+    Fit,
+    /// Stretch the image to the width of the node.
+    Width,
+    /// Stretch the image to the height of the node.
+    Height,
+    /// Stretch the image to the size of the node.
+    Both,
+}
+
+impl ImageScale {
+    /// \returns the scaling of the `imagescale` attribute value \p name, such
+    /// as "true" or "width".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "false" | "" => Some(ImageScale::None),
+            "true" => Some(ImageScale::Fit),
+            "width" => Some(ImageScale::Width),
+            "height" => Some(ImageScale::Height),
+            "both" => Some(ImageScale::Both),
+            _ => None,
+        }
+    }
+}
+
+/// An image that is drawn in a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// The reference to the image in the output, such as the path of the file
+    /// or a data URI.
+    pub href: String,
+    /// The natural size of the image, in points.
+    pub size: Point,
+    pub scale: ImageScale,
+}
+
+impl Image {
+    pub fn new(href: &str, size: Point) -> Self {
+        Self {
+            href: href.into(),
+            size,
+            scale: ImageScale::None,
+        }
+    }
+
+    /// \returns the top-left corner and the size of the image, when it's
+    /// drawn in a node with the center \p center and the size \p size.
+    pub fn placement(&self, center: Point, size: Point) -> (Point, Point) {
+        let natural = self.size;
+        let drawn = match self.scale {
+            ImageScale::None => natural,
+            ImageScale::Fit if natural.x > 0. && natural.y > 0. => {
+                natural.scale((size.x / natural.x).min(size.y / natural.y))
+            }
+            ImageScale::Fit => natural,
+            ImageScale::Width => Point::new(size.x, natural.y),
+            ImageScale::Height => Point::new(natural.x, size.y),
+            ImageScale::Both => size,
+        };
+        (center.sub(drawn.scale(0.5)), drawn)
+    }
+
+    /// Load and measure the image file \p path, that is a PNG, JPEG, GIF or
+    /// SVG image. The image refers to the file, or contains the data of the
+    /// file as a data URI if \p embed is set. \returns None if the file can't
+    /// be read or measured.
+    #[cfg(feature = "std")]
+    pub fn load(path: &str, embed: bool) -> Option<Self> {
+        let bytes = std::fs::read(path).ok()?;
+        let (mime, width, height) = get_image_info(&bytes)?;
+        let size = Point::new(width, height).scale(POINTS_PER_PIXEL);
+        let href = if embed {
+            format!("data:{};base64,{}", mime, encode_base64(&bytes))
+        } else {
+            path.into()
+        };
+        Some(Self::new(&href, size))
+    }
+}
+
+/// \returns the MIME type and the size in pixels of the PNG, JPEG, GIF or
+/// SVG image \p bytes.
+#[cfg(feature = "std")]
+pub fn get_image_info(bytes: &[u8]) -> Option<(&'static str, f64, f64)> {
+    let be16 = |i: usize| {
+        Some(u16::from_be_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]))
+    };
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.len() >= 24 {
+        let be32 = |i: usize| {
+            u32::from_be_bytes([
+                bytes[i],
+                bytes[i + 1],
+                bytes[i + 2],
+                bytes[i + 3],
+            ])
+        };
+        return Some(("image/png", be32(16) as f64, be32(20) as f64));
+    }
+    if bytes.starts_with(b"GIF8") && bytes.len() >= 10 {
+        let le16 = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        return Some(("image/gif", le16(6) as f64, le16(8) as f64));
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments of the JPEG file, until the start of the frame,
+        // that has the size of the image.
+        let mut i = 2;
+        while *bytes.get(i)? == 0xff {
+            let marker = *bytes.get(i + 1)?;
+            let is_frame = (0xc0..=0xcf).contains(&marker)
+                && ![0xc4, 0xc8, 0xcc].contains(&marker);
+            if is_frame {
+                let height = be16(i + 5)? as f64;
+                let width = be16(i + 7)? as f64;
+                return Some(("image/jpeg", width, height));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+        return None;
+    }
+    let text = std::str::from_utf8(bytes).ok()?;
+    let start = text.find("<svg")?;
+    let tag = &text[start..start + text[start..].find('>')?];
+    let (width, height) =
+        match (get_svg_length(tag, "width"), get_svg_length(tag, "height")) {
+            (Option::Some(width), Option::Some(height)) => (width, height),
+            _ => {
+                // Use the size of the view box.
+                let view_box = get_svg_attribute(tag, "viewBox")?;
+                let parts: Vec<f64> = view_box
+                    .split([' ', ','])
+                    .filter_map(|x| x.parse().ok())
+                    .collect();
+                (*parts.get(2)?, *parts.get(3)?)
+            }
+        };
+    Some(("image/svg+xml", width, height))
+}
+
+/// \returns the value of the attribute \p name in the SVG tag \p tag.
+#[cfg(feature = "std")]
+fn get_svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let quote = tag[start..].chars().next()?;
+    let rest = &tag[start + 1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// \returns the length in pixels of the attribute \p name in the SVG tag
+/// \p tag, such as "200" or "150px". Lengths in points are converted to
+/// pixels, and percentages are ignored.
+#[cfg(feature = "std")]
+fn get_svg_length(tag: &str, name: &str) -> Option<f64> {
+    let val = get_svg_attribute(tag, name)?.trim();
+    if let Option::Some(pt) = val.strip_suffix("pt") {
+        return pt.parse::<f64>().ok().map(|x| x / POINTS_PER_PIXEL);
+    }
+    val.trim_end_matches("px").parse().ok()
+}
+
+/// \returns the base64 encoding of \p bytes.
+#[cfg(feature = "std")]
+fn encode_base64(bytes: &[u8]) -> String {
+    const CHARS: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut res = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(CHARS[(n >> (18 - 6 * i)) & 63] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_image_info() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    png.extend_from_slice(&[0, 0, 0, 200, 0, 0, 0, 100]);
+    assert_eq!(get_image_info(&png), Some(("image/png", 200., 100.)));
+
+    let gif = b"GIF89a\x20\x00\x10\x00";
+    assert_eq!(get_image_info(gif), Some(("image/gif", 32., 16.)));
+
+    let jpeg = [
+        0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xc0, 0, 11, 8, 0, 30, 0, 40,
+    ];
+    assert_eq!(get_image_info(&jpeg), Some(("image/jpeg", 40., 30.)));
+
+    let svg = b"<?xml?><svg width=\"60pt\" height='20'>";
+    assert_eq!(get_image_info(svg), Some(("image/svg+xml", 80., 20.)));
+    let svg = b"<svg viewBox=\"0 0 64 48\"></svg>";
+    assert_eq!(get_image_info(svg), Some(("image/svg+xml", 64., 48.)));
+    assert_eq!(get_image_info(b"text"), None);
+
+    assert_eq!(encode_base64(b"Man"), "TWFu");
+    assert_eq!(encode_base64(b"Ma"), "TWE=");
+    assert_eq!(encode_base64(b"M"), "TQ==");
+}
+
+#[test]
+fn test_image_placement() {
+    let mut image = Image::new("a.png", Point::new(40., 20.));
+    let center = Point::new(100., 100.);
+    let size = Point::new(80., 80.);
+    let place = |image: &Image| image.placement(center, size);
+    assert_eq!(place(&image), (Point::new(80., 90.), Point::new(40., 20.)));
+    image.scale = ImageScale::Fit;
+    assert_eq!(place(&image), (Point::new(60., 80.), Point::new(80., 40.)));
+    image.scale = ImageScale::Height;
+    assert_eq!(place(&image).1, Point::new(40., 80.));
+    image.scale = ImageScale::Both;
+    assert_eq!(place(&image).1, size);
+    assert_eq!(ImageScale::from_name("width"), Some(ImageScale::Width));
+    assert_eq!(ImageScale::from_name("maybe"), None);
+}
@@ -0,0 +1,384 @@
+//! A small JSON parser and printer, for the file formats that are based on
+//! JSON. The members of objects keep their order, so that the output of the
+//! printer is the same in every run.
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use alloc::format;
+/// A JSON value.
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parse the JSON document \p input. \returns the value, or a message
+    /// that describes the first error and its location.
+    pub fn parse(input: &str) -> Result<JsonValue, String> {
+        let mut parser = JsonParser {
+            input: input.as_bytes(),
+            pos: 0,
+        };
+        let res = parser.parse_value().and_then(|value| match parser.peek() {
+            None => Result::Ok(value),
+            Option::Some(_) => Err("Unexpected text after the value"),
+        });
+        res.map_err(|msg| {
+            let before = &input.as_bytes()[..parser.pos.min(input.len())];
+            let line = 1 + before.iter().filter(|x| **x == b'\n').count();
+            let col =
+                1 + before.iter().rev().take_while(|x| **x != b'\n').count();
+            format!("{} at line {}, column {}", msg, line, col)
+        })
+    }
+
+    /// \returns the member \p key of an object, or None if this is not an
+    /// object or it has no such member.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => {
+                members.iter().find(|x| x.0 == key).map(|x| &x.1)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// \returns the value as text, indented by two spaces for each level of
+    /// nesting.
+    pub fn to_pretty_string(&self) -> String {
+        let mut res = String::new();
+        self.print(&mut res, 0);
+        res.push('\n');
+        res
+    }
+
+    fn print(&self, out: &mut String, depth: usize) {
+        let indent = |out: &mut String, depth: usize| {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+        };
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => {
+                out.push_str(if *b { "true" } else { "false" })
+            }
+            JsonValue::Number(x) => out.push_str(&format_json_number(*x)),
+            JsonValue::String(s) => print_json_string(out, s),
+            JsonValue::Array(items) => {
+                if items.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+                // Lists of numbers, such as points, are printed in one line.
+                let is_flat = items.iter().all(|x| {
+                    !matches!(x, JsonValue::Array(_) | JsonValue::Object(_))
+                });
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    if is_flat {
+                        if i > 0 {
+                            out.push(' ');
+                        }
+                    } else {
+                        indent(out, depth + 1);
+                    }
+                    item.print(out, depth + 1);
+                }
+                if !is_flat {
+                    indent(out, depth);
+                }
+                out.push(']');
+            }
+            JsonValue::Object(members) => {
+                if members.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    indent(out, depth + 1);
+                    print_json_string(out, key);
+                    out.push_str(": ");
+                    value.print(out, depth + 1);
+                }
+                indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// \returns the number \p x in JSON syntax. Whole numbers are printed without
+/// a fraction, and numbers that JSON can't represent are printed as null.
+pub fn format_json_number(x: f64) -> String {
+    if !x.is_finite() {
+        return "null".to_string();
+    }
+    if x.fract() == 0. && x.abs() < 1e15 {
+        return format!("{}", x as i64);
+    }
+    format!("{}", x)
+}
+
+fn print_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    /// \returns the next character that is not whitespace.
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos < self.input.len()
+            && self.input[self.pos].is_ascii_whitespace()
+        {
+            self.pos += 1;
+        }
+        self.input.get(self.pos).cloned()
+    }
+
+    /// Consume the character \p c, after the whitespace.
+    fn expect(&mut self, c: u8, msg: &'static str) -> Result<(), &'static str> {
+        if self.peek() != Some(c) {
+            return Err(msg);
+        }
+        self.pos += 1;
+        Result::Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, &'static str> {
+        match self.peek() {
+            Option::Some(b'{') => self.parse_object(),
+            Option::Some(b'[') => self.parse_array(),
+            Option::Some(b'"') => {
+                Result::Ok(JsonValue::String(self.parse_string()?))
+            }
+            Option::Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Option::Some(_) => {
+                for (word, value) in [
+                    ("true", JsonValue::Bool(true)),
+                    ("false", JsonValue::Bool(false)),
+                    ("null", JsonValue::Null),
+                ] {
+                    if self.input[self.pos..].starts_with(word.as_bytes()) {
+                        self.pos += word.len();
+                        return Result::Ok(value);
+                    }
+                }
+                Err("Expected a value")
+            }
+            None => Err("Unexpected end of the input"),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, &'static str> {
+        self.expect(b'{', "Expected '{'")?;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Result::Ok(JsonValue::Object(members));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err("Expected the name of a member");
+            }
+            let key = self.parse_string()?;
+            self.expect(b':', "Expected ':'")?;
+            members.push((key, self.parse_value()?));
+            match self.peek() {
+                Option::Some(b',') => self.pos += 1,
+                Option::Some(b'}') => {
+                    self.pos += 1;
+                    return Result::Ok(JsonValue::Object(members));
+                }
+                _ => return Err("Expected ',' or '}'"),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, &'static str> {
+        self.expect(b'[', "Expected '['")?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Result::Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            match self.peek() {
+                Option::Some(b',') => self.pos += 1,
+                Option::Some(b']') => {
+                    self.pos += 1;
+                    return Result::Ok(JsonValue::Array(items));
+                }
+                _ => return Err("Expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, &'static str> {
+        let start = self.pos;
+        let is_number_char =
+            |c: u8| c.is_ascii_digit() || b"+-.eE".contains(&c);
+        while self.pos < self.input.len()
+            && is_number_char(self.input[self.pos])
+        {
+            self.pos += 1;
+        }
+        let text = core::str::from_utf8(&self.input[start..self.pos]).unwrap();
+        match text.parse::<f64>() {
+            Result::Ok(x) => Result::Ok(JsonValue::Number(x)),
+            Result::Err(_) => {
+                self.pos = start;
+                Err("Invalid number")
+            }
+        }
+    }
+
+    /// Parse a string, at the opening quote.
+    fn parse_string(&mut self) -> Result<String, &'static str> {
+        self.expect(b'"', "Expected '\"'")?;
+        let mut bytes = Vec::new();
+        loop {
+            let c = match self.input.get(self.pos) {
+                Option::Some(c) => *c,
+                None => return Err("Unterminated string"),
+            };
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let c = self.input.get(self.pos).cloned();
+                    self.pos += 1;
+                    let escaped = match c {
+                        Option::Some(b'"') => '"',
+                        Option::Some(b'\\') => '\\',
+                        Option::Some(b'/') => '/',
+                        Option::Some(b'b') => '\u{8}',
+                        Option::Some(b'f') => '\u{c}',
+                        Option::Some(b'n') => '\n',
+                        Option::Some(b'r') => '\r',
+                        Option::Some(b't') => '\t',
+                        Option::Some(b'u') => self.parse_unicode_escape()?,
+                        _ => return Err("Invalid escape"),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => bytes.push(c),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| "Invalid UTF-8 in a string")
+    }
+
+    /// Parse the digits of a \\u escape, and the second half of surrogate
+    /// pairs.
+    fn parse_unicode_escape(&mut self) -> Result<char, &'static str> {
+        let mut code = self.parse_hex_digits()?;
+        if (0xd800..0xdc00).contains(&code) {
+            if !self.input[self.pos..].starts_with(b"\\u") {
+                return Err("Invalid surrogate pair");
+            }
+            self.pos += 2;
+            let low = self.parse_hex_digits()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err("Invalid surrogate pair");
+            }
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+        char::from_u32(code).ok_or("Invalid unicode escape")
+    }
+
+    /// Parse the four hex digits of a \\u escape.
+    fn parse_hex_digits(&mut self) -> Result<u32, &'static str> {
+        let digits = self.input.get(self.pos..self.pos + 4);
+        let digits = digits.and_then(|x| core::str::from_utf8(x).ok());
+        let code = digits.and_then(|x| u32::from_str_radix(x, 16).ok());
+        self.pos += 4;
+        code.ok_or("Invalid unicode escape")
+    }
+}
+
+#[test]
+fn test_json() {
+    let text =
+        r#" {"a": [1, -2.5e1, true, null], "b\n\u00e9\ud83d\ude00": {}} "#;
+    let value = JsonValue::parse(text).unwrap();
+    let items = value.get("a").unwrap().as_array().unwrap();
+    assert_eq!(items[1], JsonValue::Number(-25.));
+    assert_eq!(items[2].as_bool(), Some(true));
+    let key = &value.as_object().unwrap()[1].0;
+    assert_eq!(key, "b\n\u{e9}\u{1f600}");
+
+    // The printer and the parser agree.
+    let printed = value.to_pretty_string();
+    assert_eq!(JsonValue::parse(&printed).unwrap(), value);
+    assert!(printed.contains("\"b\\n\u{e9}\u{1f600}\": {}"));
+    assert!(printed.contains("[1, -25, true, null]"));
+
+    // Errors point to their location.
+    let err = JsonValue::parse("{\"a\": 1,\n \"b\" 2}").unwrap_err();
+    assert_eq!(err, "Expected ':' at line 2, column 6");
+    assert!(JsonValue::parse("[1, 2").is_err());
+    assert!(JsonValue::parse("[1] 2").is_err());
+    assert!(JsonValue::parse("\"\\ud800\"").is_err());
+}
@@ -3,7 +3,13 @@
 
 pub mod base;
 pub mod color;
+#[cfg(not(feature = "std"))]
+pub(crate) mod float;
 pub mod format;
 pub mod geometry;
+pub mod image;
+pub mod json;
 pub mod style;
+pub mod text;
+pub mod theme;
 pub mod utils;
@@ -1,6 +1,14 @@
 //! This module represents general shape style information.
 
 use crate::core::color::Color;
+use crate::core::geometry::Point;
+use crate::core::text::TextMetrics;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The font family of text that does not set the font name.
+pub const DEFAULT_FONT_NAME: &str = "Times";
 
 #[derive(Debug, Copy, Clone)]
 pub enum LineStyleKind {
@@ -10,36 +18,274 @@ pub enum LineStyleKind {
     None,
 }
 
-#[derive(Clone, Debug)]
+impl LineStyleKind {
+    /// \returns the on/off dash lengths (in pixels) that draw this line style.
+    /// Solid lines return an empty pattern.
+    pub fn dash_pattern(&self) -> Vec<f64> {
+        match self {
+            LineStyleKind::Dashed => vec![5., 5.],
+            LineStyleKind::Dotted => vec![1., 3.],
+            LineStyleKind::Normal | LineStyleKind::None => Vec::new(),
+        }
+    }
+}
+
+/// The shape of the head at the end of an arrow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArrowHeadKind {
+    Normal,
+    // A hollow triangle, like the heads of inheritance in UML diagrams.
+    ONormal,
+    Vee,
+    Dot,
+    ODot,
+    Diamond,
+    ODiamond,
+    Box,
+    Tee,
+    Crow,
+    // The crow's foot notation of the cardinality of relations, with the
+    // shape at the end of the edge first: exactly one, zero or one, one or
+    // many, and zero or many.
+    TeeTee,
+    TeeODot,
+    CrowTee,
+    CrowODot,
+    None,
+}
+
+impl ArrowHeadKind {
+    /// \returns the arrow head with the name \p name, such as "odiamond".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "normal" => Some(ArrowHeadKind::Normal),
+            "onormal" | "empty" => Some(ArrowHeadKind::ONormal),
+            "vee" => Some(ArrowHeadKind::Vee),
+            "dot" => Some(ArrowHeadKind::Dot),
+            "odot" => Some(ArrowHeadKind::ODot),
+            "diamond" => Some(ArrowHeadKind::Diamond),
+            "odiamond" => Some(ArrowHeadKind::ODiamond),
+            "box" => Some(ArrowHeadKind::Box),
+            "tee" => Some(ArrowHeadKind::Tee),
+            "crow" => Some(ArrowHeadKind::Crow),
+            "teetee" => Some(ArrowHeadKind::TeeTee),
+            "teeodot" => Some(ArrowHeadKind::TeeODot),
+            "crowtee" => Some(ArrowHeadKind::CrowTee),
+            "crowodot" => Some(ArrowHeadKind::CrowODot),
+            "none" => Some(ArrowHeadKind::None),
+            _ => None,
+        }
+    }
+
+    /// \returns the name of the arrow head.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrowHeadKind::Normal => "normal",
+            ArrowHeadKind::ONormal => "onormal",
+            ArrowHeadKind::Vee => "vee",
+            ArrowHeadKind::Dot => "dot",
+            ArrowHeadKind::ODot => "odot",
+            ArrowHeadKind::Diamond => "diamond",
+            ArrowHeadKind::ODiamond => "odiamond",
+            ArrowHeadKind::Box => "box",
+            ArrowHeadKind::Tee => "tee",
+            ArrowHeadKind::Crow => "crow",
+            ArrowHeadKind::TeeTee => "teetee",
+            ArrowHeadKind::TeeODot => "teeodot",
+            ArrowHeadKind::CrowTee => "crowtee",
+            ArrowHeadKind::CrowODot => "crowodot",
+            ArrowHeadKind::None => "none",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GradientKind {
+    /// A linear gradient along the angle (in degrees, counterclockwise from
+    /// the positive x axis).
+    Linear(f64),
+    /// A radial gradient that starts at the center of the shape.
+    Radial,
+    /// Pie slices of the colors, counterclockwise from the positive x axis.
+    /// The slices are made of pairs of stops, at the start and at the end of
+    /// each slice.
+    Wedged,
+}
+
+/// A gradient fill made of color stops.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gradient {
+    pub kind: GradientKind,
+    // Pairs of (offset, color), where the offset is in the range [0..1].
+    pub stops: Vec<(f64, Color)>,
+}
+
+impl Gradient {
+    pub fn new(kind: GradientKind, stops: Vec<(f64, Color)>) -> Self {
+        Self { kind, stops }
+    }
+
+    /// Create a gradient of \p kind with the \p colors spaced evenly.
+    pub fn with_colors(kind: GradientKind, colors: &[Color]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        let stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, color)| (i as f64 / last, *color))
+            .collect();
+        Self::new(kind, stops)
+    }
+
+    /// Create a gradient of \p kind where each color fills its own band, and
+    /// doesn't fade into the next color. The optional weight of each color in
+    /// \p colors is the fraction of the area that it fills, and colors without
+    /// a weight split the rest of the area evenly. Each band is made of two
+    /// stops, at the start and at the end of the band.
+    pub fn with_weights(
+        kind: GradientKind,
+        colors: &[(Color, Option<f64>)],
+    ) -> Self {
+        let known: f64 = colors.iter().filter_map(|x| x.1).sum();
+        let num_missing = colors.iter().filter(|x| x.1.is_none()).count();
+        let rest = (1. - known).max(0.) / num_missing.max(1) as f64;
+        let mut stops = Vec::new();
+        let mut start: f64 = 0.;
+        for (color, weight) in colors {
+            let end = (start + weight.unwrap_or(rest)).min(1.);
+            stops.push((start, *color));
+            stops.push((end, *color));
+            start = end;
+        }
+        Self::new(kind, stops)
+    }
+
+    /// \returns the color of the first stop, for backends that can't draw
+    /// gradients.
+    pub fn first_color(&self) -> Option<Color> {
+        self.stops.first().map(|x| x.1)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StyleAttr {
     pub line_color: Color,
-    pub line_width: usize,
+    pub penwidth: f64,
     pub fill_color: Option<Color>,
     pub rounded: usize,
     pub font_size: usize,
+    // Alternating on/off lengths of the outline, in pixels. Empty means solid.
+    pub dash: Vec<f64>,
+    // Replaces the fill color in backends that support gradients. Only used
+    // when the shape has a fill color.
+    pub gradient: Option<Gradient>,
+    // The shapes of the heads at the start and at the end of arrows.
+    pub arrow_heads: (ArrowHeadKind, ArrowHeadKind),
+    // Scales the heads of arrows.
+    pub arrow_size: f64,
+    // Invisible shapes take space in the layout, but are not drawn.
+    pub visible: bool,
+    // The font family of text, such as "Times" or "Helvetica".
+    pub font_name: String,
+    // The color of text.
+    pub font_color: Color,
+    // Draw text in bold or italic type.
+    pub font_bold: bool,
+    pub font_italic: bool,
+    // Selects how the size of text is measured.
+    pub text_metrics: TextMetrics,
+    // The space between the label and the outline of the shape, on each
+    // side. None selects the default padding of the shape.
+    pub margin: Option<Point>,
 }
 
 impl StyleAttr {
     pub fn new(
         line_color: Color,
-        line_width: usize,
+        penwidth: f64,
         fill_color: Option<Color>,
         rounded: usize,
         font_size: usize,
     ) -> Self {
         Self {
             line_color,
-            line_width,
+            penwidth,
             fill_color,
             rounded,
             font_size,
+            dash: Vec::new(),
+            gradient: None,
+            arrow_heads: (ArrowHeadKind::Normal, ArrowHeadKind::Normal),
+            arrow_size: 1.,
+            visible: true,
+            font_name: DEFAULT_FONT_NAME.to_string(),
+            font_color: Color::fast("black"),
+            font_bold: false,
+            font_italic: false,
+            text_metrics: TextMetrics::Font,
+            margin: None,
+        }
+    }
+
+    /// Set the font of text to the Graphviz font name \p name, such as
+    /// "Helvetica", "Times-BoldItalic" or "Arial Bold". The style words at
+    /// the end of the name select bold and italic type.
+    pub fn set_font_name(&mut self, name: &str) {
+        let mut family = Vec::new();
+        self.font_bold = false;
+        self.font_italic = false;
+        for word in name.split(['-', ' ', ':']).filter(|x| !x.is_empty()) {
+            let lower = word.to_lowercase();
+            let bold = lower.contains("bold");
+            let italic = lower.contains("italic") || lower.contains("oblique");
+            let rest = ["bold", "italic", "oblique", "roman", "regular"]
+                .iter()
+                .fold(lower, |acc, x| acc.replace(x, ""));
+            // The style words follow the family name.
+            if rest.is_empty() && !family.is_empty() {
+                self.font_bold |= bold;
+                self.font_italic |= italic;
+            } else if !self.font_bold && !self.font_italic {
+                family.push(word);
+            }
         }
+        self.font_name = if family.is_empty() {
+            DEFAULT_FONT_NAME.to_string()
+        } else {
+            family.join(" ")
+        };
+    }
+
+    /// Set the on/off dash lengths of lines and outlines to \p pattern.
+    /// Patterns that contain no positive length are drawn solid.
+    pub fn set_dash(&mut self, pattern: &[f64]) {
+        if pattern.iter().any(|x| *x > 0.) {
+            self.dash = pattern.iter().map(|x| x.max(0.)).collect();
+        } else {
+            self.dash.clear();
+        }
+    }
+
+    /// Fill the shape with \p gradient. The fill color is set to the first
+    /// stop, which is used by backends that only draw solid fills.
+    pub fn set_gradient(&mut self, gradient: Gradient) {
+        if let Option::Some(color) = gradient.first_color() {
+            self.fill_color = Some(color);
+        }
+        self.gradient = Some(gradient);
+    }
+
+    pub fn is_dashed(&self) -> bool {
+        !self.dash.is_empty()
     }
 
     pub fn simple() -> Self {
         StyleAttr::new(
             Color::fast("black"),
-            2,
+            2.,
             Option::Some(Color::fast("white")),
             0,
             15,
@@ -49,7 +295,7 @@ impl StyleAttr {
     pub fn debug0() -> Self {
         StyleAttr::new(
             Color::fast("black"),
-            1,
+            1.,
             Option::Some(Color::fast("pink")),
             0,
             15,
@@ -58,7 +304,7 @@ impl StyleAttr {
     pub fn debug1() -> Self {
         StyleAttr::new(
             Color::fast("black"),
-            1,
+            1.,
             Option::Some(Color::fast("aliceblue")),
             0,
             15,
@@ -67,10 +313,78 @@ impl StyleAttr {
     pub fn debug2() -> Self {
         StyleAttr::new(
             Color::fast("black"),
-            1,
+            1.,
             Option::Some(Color::fast("white")),
             0,
             15,
         )
     }
 }
+
+/// The color and the width of the lines of the nodes and the edges that are
+/// highlighted, that replace their own color and width when they are drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HighlightStyle {
+    pub color: Color,
+    pub width: f64,
+}
+
+impl Default for HighlightStyle {
+    fn default() -> Self {
+        Self {
+            color: Color::fast("red"),
+            width: 3.,
+        }
+    }
+}
+
+impl HighlightStyle {
+    pub fn new(color: Color, width: f64) -> Self {
+        Self { color, width }
+    }
+
+    /// Highlight the element with the style \p look.
+    pub fn apply(&self, look: &mut StyleAttr) {
+        look.line_color = self.color;
+        look.penwidth = self.width;
+    }
+}
+
+#[test]
+fn test_weighted_gradient() {
+    let red = Color::fast("red");
+    let blue = Color::fast("blue");
+    let green = Color::fast("green");
+    let colors = [(red, Some(0.5)), (blue, None), (green, None)];
+    let gradient = Gradient::with_weights(GradientKind::Wedged, &colors);
+    assert_eq!(
+        gradient.stops,
+        vec![
+            (0., red),
+            (0.5, red),
+            (0.5, blue),
+            (0.75, blue),
+            (0.75, green),
+            (1., green)
+        ]
+    );
+
+    // The weights are cut at the end of the area.
+    let colors = [(red, Some(0.8)), (blue, Some(0.8))];
+    let gradient = Gradient::with_weights(GradientKind::Linear(0.), &colors);
+    assert_eq!(gradient.stops[3], (1., blue));
+}
+
+#[test]
+fn test_font_name() {
+    let mut look = StyleAttr::simple();
+    look.set_font_name("Helvetica-BoldOblique");
+    assert_eq!(look.font_name, "Helvetica");
+    assert!(look.font_bold && look.font_italic);
+    look.set_font_name("DejaVu Sans Mono Bold");
+    assert_eq!(look.font_name, "DejaVu Sans Mono");
+    assert!(look.font_bold && !look.font_italic);
+    look.set_font_name("Times-Roman");
+    assert_eq!(look.font_name, "Times");
+    assert!(!look.font_bold && !look.font_italic);
+}
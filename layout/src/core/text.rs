@@ -0,0 +1,277 @@
+//! Measures the size of text with the metrics of the fonts that render it.
+//! The module embeds the advance widths of the standard PostScript fonts, and
+//! maps the font names of the styles to the closest one of them.
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Selects how the width of text is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextMetrics {
+    /// Use the advance widths of the glyphs in the font of the text.
+    #[default]
+    Font,
+    /// Every character takes a square cell of the size of the font, like in
+    /// the text backends.
+    Grid,
+}
+
+/// The advance widths of the printable ASCII characters in the Times-Roman
+/// font, in 1/1000 of the font size.
+pub(crate) static TIMES_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 333, 333, 333, 500, 564, 250, 333, 250,
+    278, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564,
+    564, 444, 921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611,
+    889, 722, 722, 556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333,
+    278, 333, 469, 500, 333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278,
+    500, 278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500,
+    444, 480, 200, 480, 541,
+];
+
+/// The advance widths of the Times-Bold font.
+static TIMES_BOLD_WIDTHS: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 333, 333, 333, 500, 570, 250, 333, 250,
+    278, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570,
+    570, 500, 930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667,
+    944, 722, 778, 611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333,
+    278, 333, 581, 500, 333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333,
+    556, 278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500,
+    444, 394, 220, 394, 520,
+];
+
+/// The advance widths of the Helvetica font.
+static HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 222, 333, 333, 389, 584, 278, 333, 278,
+    278, 556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584,
+    584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556,
+    833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278,
+    278, 278, 469, 556, 222, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222,
+    500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+    500, 334, 260, 334, 584,
+];
+
+/// The advance widths of the Helvetica-Bold font.
+static HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 278, 333, 333, 389, 584, 278, 333, 278,
+    278, 556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584,
+    584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611,
+    833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333,
+    278, 333, 584, 556, 278, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278,
+    556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556,
+    500, 389, 280, 389, 584,
+];
+
+/// The advance width of all of the characters in the Courier font.
+const COURIER_WIDTH: u16 = 600;
+
+/// The advance width of characters that take a full cell, such as CJK
+/// ideographs and emoji.
+const WIDE_CHAR_WIDTH: u16 = 1000;
+
+/// \returns true if the character \p ch takes the full width of a cell in
+/// East Asian scripts and in emoji.
+fn is_wide_char(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1FAFF
+        | 0x20000..=0x3FFFD)
+}
+
+/// \returns the table of advance widths of the font of \p look, or None for
+/// monospaced fonts. The italic fonts use the widths of the upright fonts.
+fn get_font_widths(look: &StyleAttr) -> Option<&'static [u16; 95]> {
+    let name = look.font_name.to_lowercase();
+    if ["courier", "mono", "consolas"]
+        .iter()
+        .any(|x| name.contains(x))
+    {
+        return None;
+    }
+    let sans = ["helvetica", "arial", "sans", "verdana", "tahoma"];
+    let is_sans = sans.iter().any(|x| name.contains(x));
+    Some(match (is_sans, look.font_bold) {
+        (true, true) => &HELVETICA_BOLD_WIDTHS,
+        (true, false) => &HELVETICA_WIDTHS,
+        (false, true) => &TIMES_BOLD_WIDTHS,
+        (false, false) => &TIMES_WIDTHS,
+    })
+}
+
+/// \returns the advance width of \p ch in the font table \p widths, in 1/1000
+/// of the font size.
+fn get_char_width(ch: char, widths: Option<&[u16; 95]>) -> u16 {
+    if is_wide_char(ch) {
+        return WIDE_CHAR_WIDTH;
+    }
+    let widths = match widths {
+        Option::Some(widths) => widths,
+        None => return COURIER_WIDTH,
+    };
+    match ch {
+        ' '..='~' => widths[ch as usize - 32],
+        // Other letters get the width of a lowercase letter.
+        _ => widths['n' as usize - 32],
+    }
+}
+
+/// \returns the width of the line of text \p line in the style \p look.
+pub fn get_line_width(line: &str, look: &StyleAttr) -> f64 {
+    let font_size = look.font_size as f64;
+    if look.text_metrics == TextMetrics::Grid {
+        return line.chars().count() as f64 * font_size;
+    }
+    let widths = get_font_widths(look);
+    let width: u32 = line
+        .chars()
+        .map(|ch| get_char_width(ch, widths) as u32)
+        .sum();
+    width as f64 * font_size / 1000.
+}
+
+/// The justification of a line of a label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Justification {
+    Left,
+    Center,
+    Right,
+}
+
+/// Split the label \p label into lines, and \returns the text and the
+/// justification of each line. Lines that end with the escapes `\l` and `\r`
+/// are justified to the left and to the right, and lines that end with a
+/// newline are centered. The escape `\\` stands for a backslash.
+pub fn get_label_lines(label: &str) -> Vec<(String, Justification)> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = label.chars().peekable();
+    while let Option::Some(ch) = chars.next() {
+        let end = match (ch, chars.peek()) {
+            ('\n', _) => Justification::Center,
+            ('\\', Option::Some('l')) => Justification::Left,
+            ('\\', Option::Some('r')) => Justification::Right,
+            ('\\', Option::Some('\\')) => {
+                chars.next();
+                line.push('\\');
+                continue;
+            }
+            _ => {
+                line.push(ch);
+                continue;
+            }
+        };
+        if ch == '\\' {
+            chars.next();
+        }
+        lines.push((core::mem::take(&mut line), end));
+    }
+    if !line.is_empty() {
+        lines.push((line, Justification::Center));
+    }
+    lines
+}
+
+/// \returns the text of the label \p label without the escapes, with a
+/// newline at the end of each line but the last.
+pub fn get_label_text(label: &str) -> String {
+    let lines: Vec<String> =
+        get_label_lines(label).into_iter().map(|x| x.0).collect();
+    lines.join("\n")
+}
+
+/// Clip the lines \p lines of a label to the area \p size, and \returns the
+/// lines that fit. Lines that are too wide are cut, and end with an ellipsis.
+pub fn clip_label_lines(
+    lines: Vec<(String, Justification)>,
+    size: Point,
+    look: &StyleAttr,
+) -> Vec<(String, Justification)> {
+    let max_lines = (size.y / look.font_size as f64).floor().max(0.) as usize;
+    let mut res = Vec::new();
+    for (line, justification) in lines.into_iter().take(max_lines) {
+        if get_line_width(&line, look) <= size.x {
+            res.push((line, justification));
+            continue;
+        }
+        let mut clipped = String::new();
+        for ch in line.chars() {
+            clipped.push(ch);
+            clipped.push('…');
+            let fits = get_line_width(&clipped, look) <= size.x;
+            clipped.pop();
+            if !fits {
+                clipped.pop();
+                break;
+            }
+        }
+        clipped.push('…');
+        res.push((clipped, justification));
+    }
+    res
+}
+
+/// \returns the size of the text \p label in the style \p look. Each line of
+/// the text is as high as the font size.
+pub fn get_size_for_text(label: &str, look: &StyleAttr) -> Point {
+    let lines = get_label_lines(label);
+    let width = lines
+        .iter()
+        .map(|line| get_line_width(&line.0, look))
+        .fold(0., f64::max);
+    // Empty labels take the space of one character.
+    let width = if label.is_empty() {
+        look.font_size as f64
+    } else {
+        width
+    };
+    let lines = lines.len().max(1);
+    Point::new(width, (lines * look.font_size) as f64)
+}
+
+#[test]
+fn test_text_metrics() {
+    let mut look = StyleAttr::simple();
+    look.font_size = 10;
+    assert_eq!(get_line_width("Hi", &look), 10.);
+    assert_eq!(get_line_width("日本", &look), 20.);
+    assert_eq!(get_size_for_text("a\nbb", &look), Point::new(10., 20.));
+
+    // Proportional fonts have narrow and wide letters.
+    assert!(get_line_width("iii", &look) < get_line_width("mmm", &look));
+    look.font_bold = true;
+    assert_eq!(get_line_width("a", &look), 5.);
+    look.font_name = "Helvetica".to_string();
+    assert_eq!(get_line_width("a", &look), 5.56);
+    look.font_name = "Courier New".to_string();
+    assert_eq!(get_line_width("iii", &look), get_line_width("mmm", &look));
+
+    // Escapes justify the lines of labels.
+    let lines = get_label_lines("a\\lb\nc\\rd\\\\e");
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], ("a".to_string(), Justification::Left));
+    assert_eq!(lines[1], ("b".to_string(), Justification::Center));
+    assert_eq!(lines[2], ("c".to_string(), Justification::Right));
+    assert_eq!(lines[3], ("d\\e".to_string(), Justification::Center));
+    assert_eq!(get_label_text("a\\lb\\l"), "a\nb");
+
+    // Labels are clipped to the size of their shapes.
+    let lines = get_label_lines("abcdef\nb\nc");
+    let lines = clip_label_lines(lines, Point::new(30., 25.), &look);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].0, "abcd…");
+    assert_eq!(lines[1].0, "b");
+
+    // The grid metrics fit the text backends.
+    look.text_metrics = TextMetrics::Grid;
+    assert_eq!(get_size_for_text("abc\nd", &look), Point::new(30., 20.));
+}
@@ -0,0 +1,153 @@
+//! Themes select the default colors of the drawing, such as a dark
+//! background with light lines. The graph builder starts every element from
+//! the theme, and the attributes of the element override it.
+
+use super::color::Color;
+
+/// The names of the preset themes, that Theme::from_name accepts.
+pub const THEME_NAMES: &[&str] =
+    &["light", "dark", "monochrome", "high-contrast", "solarized"];
+
+/// The default colors of the nodes, the edges and the clusters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Theme {
+    /// The color of the background, or None to keep the background of the
+    /// backend.
+    pub background: Option<Color>,
+    pub node_line: Color,
+    pub node_fill: Color,
+    pub node_font: Color,
+    /// The fill of the nodes and the clusters with the 'filled' style and
+    /// without a fill color.
+    pub filled: Color,
+    pub edge_line: Color,
+    pub edge_font: Color,
+    pub cluster_line: Color,
+    pub cluster_font: Color,
+    /// The color of the title of the graph.
+    pub graph_font: Color,
+    /// The width of the lines and the outlines.
+    pub penwidth: f64,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Theme {
+    /// Black lines and text on white, the default look of the drawing.
+    pub fn light() -> Self {
+        let black = Color::fast("black");
+        Self {
+            background: None,
+            node_line: black,
+            node_fill: Color::fast("white"),
+            node_font: black,
+            filled: Color::fast("lightgray"),
+            edge_line: black,
+            edge_font: black,
+            cluster_line: black,
+            cluster_font: black,
+            graph_font: black,
+            penwidth: 1.,
+        }
+    }
+
+    /// Light gray lines and text on a dark gray background.
+    pub fn dark() -> Self {
+        let text = Color::fast("#d4d4d4");
+        Self {
+            background: Some(Color::fast("#1e1e1e")),
+            node_line: text,
+            node_fill: Color::fast("#2d2d2d"),
+            node_font: text,
+            filled: Color::fast("#3c3c3c"),
+            edge_line: Color::fast("#a0a0a0"),
+            edge_font: text,
+            cluster_line: Color::fast("#808080"),
+            cluster_font: text,
+            graph_font: text,
+            penwidth: 1.,
+        }
+    }
+
+    /// Black and shades of gray on white, for printing.
+    pub fn monochrome() -> Self {
+        let black = Color::fast("black");
+        Self {
+            background: Some(Color::fast("white")),
+            node_line: black,
+            node_fill: Color::fast("white"),
+            node_font: black,
+            filled: Color::fast("#cccccc"),
+            edge_line: black,
+            edge_font: black,
+            cluster_line: Color::fast("#666666"),
+            cluster_font: black,
+            graph_font: black,
+            penwidth: 1.,
+        }
+    }
+
+    /// Bright lines and text on black, with wide lines.
+    pub fn high_contrast() -> Self {
+        let white = Color::fast("white");
+        Self {
+            background: Some(Color::fast("black")),
+            node_line: white,
+            node_fill: Color::fast("black"),
+            node_font: white,
+            filled: Color::fast("#000080"),
+            edge_line: Color::fast("yellow"),
+            edge_font: white,
+            cluster_line: Color::fast("cyan"),
+            cluster_font: white,
+            graph_font: white,
+            penwidth: 2.,
+        }
+    }
+
+    /// The light variant of the Solarized palette.
+    pub fn solarized() -> Self {
+        let text = Color::fast("#586e75");
+        Self {
+            background: Some(Color::fast("#fdf6e3")),
+            node_line: Color::fast("#657b83"),
+            node_fill: Color::fast("#eee8d5"),
+            node_font: text,
+            filled: Color::fast("#e4dcc4"),
+            edge_line: Color::fast("#268bd2"),
+            edge_font: text,
+            cluster_line: Color::fast("#2aa198"),
+            cluster_font: text,
+            graph_font: text,
+            penwidth: 1.,
+        }
+    }
+
+    /// \returns the preset theme \p name, such as "dark" or "high-contrast".
+    /// See THEME_NAMES.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::light()),
+            "dark" => Some(Self::dark()),
+            "monochrome" => Some(Self::monochrome()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "solarized" => Some(Self::solarized()),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_theme_names() {
+    for name in THEME_NAMES {
+        assert!(Theme::from_name(name).is_some(), "{}", name);
+    }
+    assert_eq!(Theme::from_name("sepia"), None);
+    assert_eq!(Theme::default(), Theme::light());
+    assert!(Theme::dark().background.is_some());
+}
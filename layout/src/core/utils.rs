@@ -1,10 +1,15 @@
 //! This is a collection of useful utilities.
 
-#[cfg(feature = "log")]
+use alloc::format;
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "std", feature = "log"))]
 use log;
+#[cfg(feature = "std")]
 use std::fs::File;
+#[cfg(feature = "std")]
 use std::io::{Error, Write};
 
+#[cfg(feature = "std")]
 pub fn save_to_file(filename: &str, content: &str) -> Result<(), Error> {
     let f = File::create(filename)?;
     let _ = write!(&f, "{}", content);
@@ -12,3 +17,100 @@ pub fn save_to_file(filename: &str, content: &str) -> Result<(), Error> {
     log::info!("Wrote {}", filename);
     Result::Ok(())
 }
+
+#[cfg(feature = "std")]
+pub fn save_bytes_to_file(filename: &str, content: &[u8]) -> Result<(), Error> {
+    let mut f = File::create(filename)?;
+    f.write_all(content)?;
+    #[cfg(feature = "log")]
+    log::info!("Wrote {}", filename);
+    Result::Ok(())
+}
+
+/// Escape the characters that have a special meaning in XML and HTML text and
+/// attribute values.
+pub fn escape_xml(x: &str) -> String {
+    let mut res = String::new();
+    for c in x.chars() {
+        match c {
+            '&' => {
+                res.push_str("&amp;");
+            }
+            '<' => {
+                res.push_str("&lt;");
+            }
+            '>' => {
+                res.push_str("&gt;");
+            }
+            '"' => {
+                res.push_str("&quot;");
+            }
+            '\'' => {
+                res.push_str("&apos;");
+            }
+            _ => {
+                res.push(c);
+            }
+        }
+    }
+    res
+}
+
+/// Format \p x with up to three decimal digits, and without trailing zeros.
+/// This is used by the backends that print coordinates as text.
+pub fn format_number(x: f64) -> String {
+    let res = format!("{:.3}", x);
+    let res = res.trim_end_matches('0').trim_end_matches('.');
+    if res == "-0" {
+        return "0".to_string();
+    }
+    res.to_string()
+}
+
+/// A small pseudo-random number generator (SplitMix64), for the heuristics
+/// of the layout. The same seed produces the same numbers on all platforms.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// \returns a number in the range [0, n).
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Empty range");
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[test]
+fn test_rng() {
+    let mut a = Rng::new(7);
+    let mut b = Rng::new(7);
+    let mut c = Rng::new(8);
+    let a: Vec<usize> = (0..10).map(|_| a.below(100)).collect();
+    let b: Vec<usize> = (0..10).map(|_| b.below(100)).collect();
+    let c: Vec<usize> = (0..10).map(|_| c.below(100)).collect();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert!(a.iter().all(|x| *x < 100));
+}
+
+#[test]
+fn test_format_number() {
+    assert_eq!(format_number(1.5), "1.5");
+    assert_eq!(format_number(2.0), "2");
+    assert_eq!(format_number(-0.0001), "0");
+    assert_eq!(format_number(100.), "100");
+}
@@ -0,0 +1,280 @@
+//! Draws entity-relationship diagrams. Tables are nodes with a header and a
+//! row for each column, that lists the key markers, the name and the type of
+//! the column. The rows are ports that are named after their columns, so the
+//! edges of the relations can join the columns. The cardinality of the ends
+//! of a relation is drawn in the crow's foot notation.
+//!
+//! In DOT files, the nodes with `shape=table` list their columns in the
+//! `columns` attribute, and the edges select the cardinality of their ends
+//! with the `cardinality` attribute:
+//!
+//! ```txt
+//! users [shape=table, columns="id int PK; email text UK"];
+//! posts [shape=table, columns="id int PK; user_id int FK"];
+//! users:id -> posts:user_id [cardinality="1:0..*"];
+//! ```
+
+use crate::core::color::Color;
+use crate::core::style::ArrowHeadKind;
+use crate::std_shapes::shapes::{HtmlCell, HtmlLabel, HtmlTable, HtmlText};
+
+/// The background color of the header of the tables.
+const HEADER_COLOR: &str = "#eeeeee";
+
+/// The space around the text of the cells of the tables.
+const CELL_PADDING: usize = 4;
+
+/// The keys that a column is part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Primary,
+    Foreign,
+    Unique,
+}
+
+impl Key {
+    /// \returns the key with the marker \p name, such as "PK".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PK" => Some(Key::Primary),
+            "FK" => Some(Key::Foreign),
+            "UK" => Some(Key::Unique),
+            _ => None,
+        }
+    }
+
+    /// \returns the marker of the key in the row of the column.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Key::Primary => "PK",
+            Key::Foreign => "FK",
+            Key::Unique => "UK",
+        }
+    }
+}
+
+/// A column of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// The type of the column, such as "int". Empty if it's not shown.
+    pub type_name: String,
+    pub keys: Vec<Key>,
+}
+
+/// The title and the columns of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableNode {
+    title: String,
+    columns: Vec<Column>,
+}
+
+impl TableNode {
+    /// Create the table \p title, without columns.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Create the table \p title with the columns that the text \p columns
+    /// describes, in the format of the `columns` attribute. The columns are
+    /// separated by semicolons, and each column is made of its name, its
+    /// type, and the markers of its keys, such as "id int PK; name text".
+    pub fn from_columns(title: &str, columns: &str) -> Self {
+        let mut res = Self::new(title);
+        for column in columns.split(';') {
+            let mut words: Vec<&str> = column.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+            let mut keys = Vec::new();
+            while words.len() > 1 {
+                match Key::from_name(words[words.len() - 1]) {
+                    Option::Some(key) => keys.insert(0, key),
+                    None => break,
+                }
+                words.pop();
+            }
+            res.columns.push(Column {
+                name: words[0].to_string(),
+                type_name: words[1..].join(" "),
+                keys,
+            });
+        }
+        res
+    }
+
+    /// Add the column \p name of the type \p type_name.
+    pub fn column(&mut self, name: &str, type_name: &str) -> &mut Self {
+        self.columns.push(Column {
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            keys: Vec::new(),
+        });
+        self
+    }
+
+    /// Mark the column \p name as a part of the key \p key.
+    pub fn key(&mut self, name: &str, key: Key) -> &mut Self {
+        for column in self.columns.iter_mut().filter(|x| x.name == name) {
+            if !column.keys.contains(&key) {
+                column.keys.push(key);
+            }
+        }
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// \returns the description of the columns in the format of the
+    /// `columns` attribute (see from_columns).
+    pub fn to_columns_attr(&self) -> String {
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut words = vec![column.name.as_str()];
+                if !column.type_name.is_empty() {
+                    words.push(&column.type_name);
+                }
+                words.extend(column.keys.iter().map(|x| x.name()));
+                words.join(" ")
+            })
+            .collect();
+        columns.join("; ")
+    }
+
+    /// \returns the HTML-like table that draws the table, with the title in
+    /// the first row. The cells of each column share the port that is named
+    /// after the column. The markers of the keys are in the first cell of
+    /// the rows, if any column is part of a key.
+    pub fn to_html(&self) -> HtmlLabel {
+        let has_keys = self.columns.iter().any(|x| !x.keys.is_empty());
+        let cell = |text: &str, port: Option<&String>| HtmlCell {
+            content: HtmlLabel::Text(vec![vec![HtmlText {
+                text: text.to_string(),
+                font_size: None,
+            }]]),
+            port: port.cloned(),
+            bgcolor: None,
+            border: None,
+            colspan: 1,
+            rowspan: 1,
+        };
+
+        let mut table = HtmlTable::new();
+        table.cell_border = Some(0);
+        table.cell_spacing = 0;
+        table.cell_padding = CELL_PADDING;
+        let mut header = cell(&self.title, None);
+        header.bgcolor = Some(Color::fast(HEADER_COLOR));
+        header.colspan = if has_keys { 3 } else { 2 };
+        table.rows.push(vec![header]);
+        for column in self.columns.iter() {
+            let port = Some(&column.name);
+            let mut row = Vec::new();
+            if has_keys {
+                let keys: Vec<&str> =
+                    column.keys.iter().map(|x| x.name()).collect();
+                row.push(cell(&keys.join(","), port));
+            }
+            row.push(cell(&column.name, port));
+            row.push(cell(&column.type_name, port));
+            table.rows.push(row);
+        }
+        HtmlLabel::Table(table)
+    }
+}
+
+/// The number of rows of a table that a relation joins to a row of the
+/// table at the other end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    One,
+    ZeroOrOne,
+    OneOrMany,
+    ZeroOrMany,
+}
+
+impl Cardinality {
+    /// \returns the cardinality with the name \p name: "1", "0..1", "1..*",
+    /// or "0..*", that is also written as "*".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim() {
+            "1" => Some(Cardinality::One),
+            "0..1" => Some(Cardinality::ZeroOrOne),
+            "1..*" => Some(Cardinality::OneOrMany),
+            "0..*" | "*" => Some(Cardinality::ZeroOrMany),
+            _ => None,
+        }
+    }
+
+    /// \returns the arrow head that draws the cardinality at the end of an
+    /// edge.
+    pub fn arrow_head(&self) -> ArrowHeadKind {
+        match self {
+            Cardinality::One => ArrowHeadKind::TeeTee,
+            Cardinality::ZeroOrOne => ArrowHeadKind::TeeODot,
+            Cardinality::OneOrMany => ArrowHeadKind::CrowTee,
+            Cardinality::ZeroOrMany => ArrowHeadKind::CrowODot,
+        }
+    }
+}
+
+/// \returns the cardinality of the tail and of the head of an edge from the
+/// value \p val of the `cardinality` attribute, such as "1:0..*".
+pub fn parse_cardinality(val: &str) -> Option<(Cardinality, Cardinality)> {
+    let (tail, head) = val.split_once(':')?;
+    Some((Cardinality::from_name(tail)?, Cardinality::from_name(head)?))
+}
+
+#[test]
+fn test_tables() {
+    let table = TableNode::from_columns(
+        "users",
+        "id int PK; email varchar 255 UK;; team_id int FK PK; note",
+    );
+    assert_eq!(table.columns().len(), 4);
+    assert_eq!(table.columns()[1].type_name, "varchar 255");
+    assert_eq!(table.columns()[2].keys, vec![Key::Foreign, Key::Primary]);
+    assert_eq!(table.columns()[3].type_name, "");
+    assert_eq!(
+        table.to_columns_attr(),
+        "id int PK; email varchar 255 UK; team_id int FK PK; note"
+    );
+
+    let mut built = TableNode::new("users");
+    built.column("id", "int").column("email", "varchar 255");
+    built.column("team_id", "int").column("note", "");
+    built.key("id", Key::Primary).key("email", Key::Unique);
+    built
+        .key("team_id", Key::Foreign)
+        .key("team_id", Key::Primary);
+    assert_eq!(built, table);
+
+    // The header spans the key, the name and the type of the columns.
+    let HtmlLabel::Table(html) = table.to_html() else {
+        panic!("expected a table");
+    };
+    assert_eq!(html.rows.len(), 5);
+    assert_eq!(html.rows[0][0].colspan, 3);
+    assert_eq!(html.rows[3][0].content.to_plain_text(), "FK,PK");
+    assert!(html.rows[3]
+        .iter()
+        .all(|x| x.port.as_deref() == Some("team_id")));
+
+    assert_eq!(
+        parse_cardinality("1:0..*"),
+        Some((Cardinality::One, Cardinality::ZeroOrMany))
+    );
+    assert_eq!(parse_cardinality("1"), None);
+    assert_eq!(parse_cardinality("2:1"), None);
+}
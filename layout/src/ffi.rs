@@ -0,0 +1,173 @@
+//! A small C interface to the parser and the SVG renderer, for applications
+//! that are not written in Rust, such as C++ programs or Python scripts that
+//! use ctypes. This module is enabled by the `ffi` feature. The declarations
+//! of the functions are in `include/layout.h`, and the shared library is
+//! built with:
+//!
+//! ```txt
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! The strings that the functions return are owned by the library, and are
+//! released with layout_free_string().
+
+use crate::backends::svg::SVGWriter;
+use crate::gv::parser::ast;
+use crate::gv::{DotParser, GraphBuilder};
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// A parsed DOT graph, that is opaque to C.
+#[derive(Debug)]
+pub struct LayoutGraph {
+    graph: ast::Graph,
+}
+
+/// \returns a copy of \p str that C owns until it's passed to
+/// layout_free_string(), or null if \p str contains a nul byte.
+fn to_c_string(str: &str) -> *mut c_char {
+    match CString::new(str) {
+        Result::Ok(s) => s.into_raw(),
+        Result::Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Parse the nul-terminated DOT text \p text.
+/// \returns the graph, that is released with layout_free(), or null if the
+/// text can't be parsed or the parser fails. If \p error is not null then
+/// it's set to the error message on failure, and to null on success.
+///
+/// # Safety
+///
+/// \p text must be a valid nul-terminated string, and \p error must be null or
+/// point to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn layout_parse_dot(
+    text: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut LayoutGraph {
+    let set_error = |msg: Option<&str>| {
+        if !error.is_null() {
+            *error = msg.map_or(ptr::null_mut(), to_c_string);
+        }
+    };
+    if text.is_null() {
+        set_error(Option::Some("The text is null"));
+        return ptr::null_mut();
+    }
+    let text = match CStr::from_ptr(text).to_str() {
+        Result::Ok(text) => text,
+        Result::Err(_) => {
+            set_error(Option::Some("The text is not valid UTF-8"));
+            return ptr::null_mut();
+        }
+    };
+    // Panics must not unwind into the C caller.
+    let tree =
+        catch_unwind(AssertUnwindSafe(|| DotParser::new(text).process()));
+    match tree {
+        Result::Ok(Result::Ok(graph)) => {
+            set_error(None);
+            Box::into_raw(Box::new(LayoutGraph { graph }))
+        }
+        Result::Ok(Result::Err(err)) => {
+            set_error(Option::Some(&err.to_string()));
+            ptr::null_mut()
+        }
+        Result::Err(_) => {
+            set_error(Option::Some("The parser failed with an internal error"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Lay out the graph \p graph and draw it.
+/// \returns the SVG document, that is released with layout_free_string(), or
+/// null if the graph is null or can't be laid out.
+///
+/// # Safety
+///
+/// \p graph must be null or a graph that layout_parse_dot() returned and that
+/// was not released.
+#[no_mangle]
+pub unsafe extern "C" fn layout_render_svg(
+    graph: *const LayoutGraph,
+) -> *mut c_char {
+    if graph.is_null() {
+        return ptr::null_mut();
+    }
+    let graph = &(*graph).graph;
+    // Panics must not unwind into the C caller.
+    let svg = catch_unwind(AssertUnwindSafe(|| {
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        svg.finalize()
+    }));
+    match svg {
+        Result::Ok(svg) => to_c_string(&svg),
+        Result::Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Release the graph \p graph. Null is ignored.
+///
+/// # Safety
+///
+/// \p graph must be null or a graph that layout_parse_dot() returned and that
+/// was not released.
+#[no_mangle]
+pub unsafe extern "C" fn layout_free(graph: *mut LayoutGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// Release the string \p str. Null is ignored.
+///
+/// # Safety
+///
+/// \p str must be null or a string that this library returned and that was
+/// not released.
+#[no_mangle]
+pub unsafe extern "C" fn layout_free_string(str: *mut c_char) {
+    if !str.is_null() {
+        drop(CString::from_raw(str));
+    }
+}
+
+#[test]
+fn test_ffi() {
+    let text = CString::new("digraph { a -> b; }").unwrap();
+    let mut error = ptr::null_mut();
+    unsafe {
+        let graph = layout_parse_dot(text.as_ptr(), &mut error);
+        assert!(!graph.is_null());
+        assert!(error.is_null());
+
+        let svg = layout_render_svg(graph);
+        assert!(!svg.is_null());
+        let str = CStr::from_ptr(svg).to_str().unwrap();
+        assert!(str.contains("<svg"));
+        assert!(str.contains(">a<"));
+        layout_free_string(svg);
+        layout_free(graph);
+
+        // Errors are reported with a message.
+        let text = CString::new("digraph { a -> }").unwrap();
+        let graph = layout_parse_dot(text.as_ptr(), &mut error);
+        assert!(graph.is_null());
+        assert!(!error.is_null());
+        assert!(!CStr::from_ptr(error).to_bytes().is_empty());
+        layout_free_string(error);
+
+        assert!(layout_parse_dot(ptr::null(), ptr::null_mut()).is_null());
+        assert!(layout_render_svg(ptr::null()).is_null());
+        layout_free(ptr::null_mut());
+        layout_free_string(ptr::null_mut());
+    }
+}
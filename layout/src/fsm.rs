@@ -0,0 +1,232 @@
+//! Draws finite automata. The states are circles, and the accepting states
+//! are double circles. The initial state has an arrow that comes from
+//! nowhere, and the symbols of the transitions between the same states are
+//! merged into the label of one edge, so that each state has at most one
+//! loop.
+//!
+//! ```rust
+//! use layout::backends::svg::SVGWriter;
+//! use layout::fsm::StateMachine;
+//!
+//! let mut fsm = StateMachine::new();
+//! fsm.initial("q0").accepting("q1");
+//! fsm.transition("q0", "q1", "a");
+//! fsm.transition("q0", "q1", "b");
+//! fsm.transition("q1", "q1", "a");
+//!
+//! let mut vg = fsm.build();
+//! let mut svg = SVGWriter::new();
+//! vg.do_it(false, false, false, &mut svg);
+//! ```
+
+use crate::builder::{GraphBuilder, Shape};
+use crate::core::base::Orientation;
+use crate::core::style::LineStyleKind;
+use crate::topo::layout::VisualGraph;
+
+/// The name of the invisible node that the arrow of the initial state comes
+/// from. States can't have this name.
+pub const INITIAL_NODE: &str = "__initial";
+
+/// The separator of the symbols in the labels of the transitions.
+const SYMBOL_SEPARATOR: &str = ", ";
+
+/// A state of the automaton.
+#[derive(Debug, Clone)]
+struct StateDesc {
+    name: String,
+    label: Option<String>,
+    accepting: bool,
+}
+
+/// The transitions from one state to another, with their symbols.
+#[derive(Debug, Clone)]
+struct TransitionDesc {
+    from: String,
+    to: String,
+    symbols: Vec<String>,
+}
+
+/// Constructs the drawing of a finite automaton from its states and its
+/// transitions. States are identified by their names, and are created when
+/// they are first mentioned.
+#[derive(Debug, Clone)]
+pub struct StateMachine {
+    orientation: Orientation,
+    states: Vec<StateDesc>,
+    initial: Option<String>,
+    transitions: Vec<TransitionDesc>,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    /// Create an empty automaton, that grows from left to right.
+    pub fn new() -> Self {
+        Self {
+            orientation: Orientation::LeftToRight,
+            states: Vec::new(),
+            initial: None,
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Grow the drawing in the direction \p orientation.
+    pub fn orientation(&mut self, orientation: Orientation) -> &mut Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// \returns the index of the state \p name, and creates the state if
+    /// it's not in the automaton.
+    fn get_or_create_state(&mut self, name: &str) -> usize {
+        assert!(name != INITIAL_NODE, "Reserved state name");
+        if let Option::Some(idx) =
+            self.states.iter().position(|x| x.name == name)
+        {
+            return idx;
+        }
+        self.states.push(StateDesc {
+            name: name.to_string(),
+            label: None,
+            accepting: false,
+        });
+        self.states.len() - 1
+    }
+
+    /// Add the state \p name, to place it before the states that are
+    /// mentioned later.
+    pub fn state(&mut self, name: &str) -> &mut Self {
+        self.get_or_create_state(name);
+        self
+    }
+
+    /// Draw the text \p label in the state \p name, instead of its name.
+    pub fn label(&mut self, name: &str, label: &str) -> &mut Self {
+        let idx = self.get_or_create_state(name);
+        self.states[idx].label = Some(label.to_string());
+        self
+    }
+
+    /// Select the state \p name as the initial state, that the arrow from
+    /// nowhere points to.
+    pub fn initial(&mut self, name: &str) -> &mut Self {
+        self.get_or_create_state(name);
+        self.initial = Some(name.to_string());
+        self
+    }
+
+    /// Mark the state \p name as an accepting state, that is drawn as a
+    /// double circle.
+    pub fn accepting(&mut self, name: &str) -> &mut Self {
+        let idx = self.get_or_create_state(name);
+        self.states[idx].accepting = true;
+        self
+    }
+
+    /// Add the transition from the state \p from to the state \p to on the
+    /// symbol \p symbol. The transitions between the same states share one
+    /// edge, that lists their symbols.
+    pub fn transition(
+        &mut self,
+        from: &str,
+        to: &str,
+        symbol: &str,
+    ) -> &mut Self {
+        self.get_or_create_state(from);
+        self.get_or_create_state(to);
+        let pos = self
+            .transitions
+            .iter()
+            .position(|x| x.from == from && x.to == to);
+        let desc = match pos {
+            Option::Some(idx) => &mut self.transitions[idx],
+            None => {
+                self.transitions.push(TransitionDesc {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    symbols: Vec::new(),
+                });
+                self.transitions.last_mut().unwrap()
+            }
+        };
+        if !desc.symbols.iter().any(|x| x == symbol) {
+            desc.symbols.push(symbol.to_string());
+        }
+        self
+    }
+
+    /// \returns the number of states of the automaton.
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// \returns a graph builder that holds the drawing of the automaton, for
+    /// callers that style the nodes and the edges further, or write the
+    /// graph as DOT text.
+    pub fn to_builder(&self) -> GraphBuilder {
+        let mut graph = GraphBuilder::new();
+        graph.orientation(self.orientation);
+
+        // The arrow of the initial state comes from an invisible point, that
+        // is placed before the states.
+        if let Option::Some(initial) = &self.initial {
+            graph
+                .node(INITIAL_NODE)
+                .shape(Shape::Point)
+                .style(LineStyleKind::None);
+            graph.edge(INITIAL_NODE, initial);
+        }
+        for state in self.states.iter() {
+            let shape = if state.accepting {
+                Shape::DoubleCircle
+            } else {
+                Shape::Circle
+            };
+            let node = graph.node(&state.name).shape(shape);
+            if let Option::Some(label) = &state.label {
+                node.label(label);
+            }
+        }
+        for transition in self.transitions.iter() {
+            let label = transition.symbols.join(SYMBOL_SEPARATOR);
+            graph.edge(&transition.from, &transition.to).label(&label);
+        }
+        graph
+    }
+
+    /// \returns the graph that draws the automaton.
+    pub fn build(&self) -> VisualGraph {
+        self.to_builder().build()
+    }
+}
+
+#[test]
+fn test_state_machine() {
+    let mut fsm = StateMachine::new();
+    fsm.state("q0").initial("q0").accepting("q2");
+    fsm.transition("q0", "q1", "a");
+    fsm.transition("q1", "q1", "a");
+    fsm.transition("q1", "q1", "b");
+    fsm.transition("q1", "q1", "a");
+    fsm.transition("q1", "q2", "b");
+    assert_eq!(fsm.num_states(), 3);
+
+    let text = fsm.to_builder().to_dot(None);
+    assert!(text.contains("  __initial [shape=point, style=invis];\n"));
+    assert!(text.contains("  q0 [shape=circle];\n"));
+    assert!(text.contains("  q2 [shape=doublecircle];\n"));
+    assert!(text.contains("  __initial -> q0;\n"));
+    // The symbols of the loop are merged into one edge.
+    assert!(text.contains("  q1 -> q1 [label=\"a, b\"];\n"));
+    assert_eq!(text.matches("q1 -> q1").count(), 1);
+
+    // The invisible node is not drawn.
+    let vg = fsm.build();
+    assert_eq!(vg.num_nodes(), 4);
+    assert!(!vg.element(crate::adt::dag::NodeHandle::new(0)).look.visible);
+}
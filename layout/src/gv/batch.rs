@@ -0,0 +1,221 @@
+//! Lays out and renders many DOT documents in one call, for programs that
+//! render all of the diagrams of a project. Each document may contain several
+//! graphs, one after the other, like the output of `cat *.dot`. The graphs
+//! are independent, and with the 'rayon' feature they are processed in
+//! parallel.
+//!
+//! ```
+//! use layout::gv::batch::{BatchFormat, BatchRenderer};
+//!
+//! let docs = vec![
+//!     ("a.dot".to_string(), "digraph { a -> b; }".to_string()),
+//!     ("b.dot".to_string(), "graph { c -- d; } graph { e; }".to_string()),
+//! ];
+//! let outputs = BatchRenderer::new(BatchFormat::Svg).render(&docs);
+//! assert_eq!(outputs.len(), 3);
+//! assert!(outputs.iter().all(|x| x.output.is_ok()));
+//! ```
+
+use super::jgf::write_jgf;
+use super::parser::ast;
+use super::{DotParser, GraphBuilder};
+use crate::backends::svg::SVGWriter;
+use crate::core::theme::Theme;
+
+/// The formats that a batch is rendered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchFormat {
+    Svg,
+    Dot,
+    Xdot,
+    Json,
+    Plain,
+}
+
+impl BatchFormat {
+    /// \returns the format with the name \p name, as in the `-T` flag of the
+    /// command line tool, such as "svg".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "svg" => Some(BatchFormat::Svg),
+            "dot" => Some(BatchFormat::Dot),
+            "xdot" => Some(BatchFormat::Xdot),
+            "json" => Some(BatchFormat::Json),
+            "plain" => Some(BatchFormat::Plain),
+            _ => None,
+        }
+    }
+
+    /// \returns the extension of the files in this format, such as "svg".
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BatchFormat::Svg => "svg",
+            BatchFormat::Dot => "dot",
+            BatchFormat::Xdot => "xdot",
+            BatchFormat::Json => "json",
+            BatchFormat::Plain => "plain",
+        }
+    }
+}
+
+/// The rendering of one graph of a batch.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    /// The name of the document that contains the graph.
+    pub name: String,
+    /// The index of the graph in the document. Documents that can't be
+    /// parsed have a single output, with the index 0.
+    pub index: usize,
+    /// The number of graphs in the document.
+    pub count: usize,
+    /// The rendered graph, or the error message of the parser.
+    pub output: Result<String, String>,
+}
+
+/// Renders lists of DOT documents.
+#[derive(Debug, Clone)]
+pub struct BatchRenderer {
+    format: BatchFormat,
+    lenient: bool,
+    parallel: bool,
+    theme: Theme,
+}
+
+impl BatchRenderer {
+    pub fn new(format: BatchFormat) -> Self {
+        Self {
+            format,
+            lenient: false,
+            parallel: true,
+            theme: Theme::light(),
+        }
+    }
+
+    /// Parse the documents in the lenient mode of DotParser if \p lenient is
+    /// set.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Process the graphs one by one, on the calling thread, if \p parallel
+    /// is not set. Batches are only processed in parallel with the 'rayon'
+    /// feature.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Draw the graphs with the default colors of \p theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// \returns the graphs of the document \p text, or the error message of
+    /// the parser.
+    fn parse(&self, text: &str) -> Result<Vec<ast::Graph>, String> {
+        let mut parser = DotParser::new(text);
+        parser.set_lenient(self.lenient);
+        parser.process_all().map_err(|err| err.to_string())
+    }
+
+    /// \returns the graph \p graph, laid out and rendered in the format of
+    /// the batch.
+    fn render_graph(&self, graph: &ast::Graph) -> String {
+        let mut gb = GraphBuilder::new();
+        gb.set_theme(self.theme);
+        gb.visit_graph(graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        match self.format {
+            BatchFormat::Svg => {
+                let mut svg = SVGWriter::new();
+                vg.draw(false, &mut svg);
+                svg.finalize()
+            }
+            BatchFormat::Dot => gb.to_dot(Some(&vg.layout_result())),
+            BatchFormat::Xdot => gb.to_xdot(&vg),
+            BatchFormat::Json => write_jgf(&gb, Some(&vg.layout_result())),
+            BatchFormat::Plain => gb.to_plain(&vg.layout_result(), false),
+        }
+    }
+
+    /// Lay out and render the graphs of the documents \p docs, that are pairs
+    /// of a name, such as the path of the file, and the DOT text.
+    /// \returns the outputs of the graphs, in the order of the documents and
+    /// of the graphs in each document.
+    pub fn render(&self, docs: &[(String, String)]) -> Vec<BatchOutput> {
+        // Parse the documents, and then render the graphs, so that documents
+        // with many graphs are spread over the threads.
+        let parsed: Vec<_> = self.map(docs, |doc| self.parse(&doc.1));
+        let mut jobs: Vec<(usize, usize, usize)> = Vec::new();
+        for (i, graphs) in parsed.iter().enumerate() {
+            let count = graphs.as_ref().map_or(1, |x| x.len());
+            jobs.extend((0..count).map(|j| (i, j, count)));
+        }
+        self.map(&jobs, |(i, j, count)| {
+            let output = match &parsed[*i] {
+                Result::Ok(graphs) => {
+                    Result::Ok(self.render_graph(&graphs[*j]))
+                }
+                Result::Err(err) => Result::Err(err.clone()),
+            };
+            BatchOutput {
+                name: docs[*i].0.clone(),
+                index: *j,
+                count: *count,
+                output,
+            }
+        })
+    }
+
+    /// \returns the results of \p f on the items of \p items, in order.
+    fn map<T: Sync, R: Send>(
+        &self,
+        items: &[T],
+        f: impl Fn(&T) -> R + Sync + Send,
+    ) -> Vec<R> {
+        #[cfg(feature = "rayon")]
+        if self.parallel {
+            use rayon::prelude::*;
+            return items.par_iter().map(f).collect();
+        }
+        items.iter().map(f).collect()
+    }
+}
+
+#[test]
+fn test_batch_render() {
+    let docs = vec![
+        ("a.dot".to_string(), "digraph A { a -> b; }".to_string()),
+        ("b.dot".to_string(), "digraph { a -> }".to_string()),
+        (
+            "c.dot".to_string(),
+            "graph { c; }\ngraph { d; }".to_string(),
+        ),
+    ];
+    for parallel in [false, true] {
+        let mut batch = BatchRenderer::new(BatchFormat::Plain);
+        batch.set_parallel(parallel);
+        let outputs = batch.render(&docs);
+        let names: Vec<_> = outputs
+            .iter()
+            .map(|x| (x.name.as_str(), x.index, x.count))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a.dot", 0, 1),
+                ("b.dot", 0, 1),
+                ("c.dot", 0, 2),
+                ("c.dot", 1, 2)
+            ]
+        );
+        assert!(outputs[0].output.as_ref().unwrap().contains("edge a b"));
+        assert!(outputs[1].output.as_ref().unwrap_err().starts_with("1:16:"));
+        assert!(outputs[2].output.as_ref().unwrap().contains("node c"));
+        assert!(outputs[3].output.as_ref().unwrap().contains("node d"));
+    }
+
+    assert_eq!(BatchFormat::from_name("json"), Some(BatchFormat::Json));
+    assert_eq!(BatchFormat::Json.extension(), "json");
+    assert_eq!(BatchFormat::from_name("png"), None);
+}
@@ -1,38 +1,103 @@
 //! A graph builder that converts parsed AST trees to graphs.
 
-use super::record::record_builder;
-use crate::adt::dag::NodeHandle;
+use super::html::{html_builder, is_html_label, parse_html_label};
+use super::record::{record_builder, unescape_record_chars};
+use crate::adt::dag::{NodeHandle, RankConstraint};
 use crate::adt::map::ScopedMap;
-use crate::core::base::Orientation;
-use crate::core::color::Color;
+use crate::core::base::{LayoutEngine, Orientation, OutputOrder, SplineKind};
+use crate::core::color::{parse_color_list, Color};
+use crate::core::format::{Link, Stylesheet};
+use crate::core::geometry::Point;
+use crate::core::image::{Image, ImageScale};
 use crate::core::style::*;
+use crate::core::text::{Justification, TextMetrics};
+use crate::core::theme::Theme;
+use crate::core::utils::escape_xml;
+use crate::er::{parse_cardinality, TableNode};
 use crate::gv::parser::ast;
+use crate::gv::writer::{
+    merge_attributes, sorted, write_dot, write_plain, LayoutAttributes,
+};
 use crate::std_shapes::render::get_shape_size;
 use crate::std_shapes::shapes::ShapeKind;
 use crate::std_shapes::shapes::*;
-use crate::topo::layout::VisualGraph;
+use crate::topo::layout::{LayoutResult, VisualGraph};
+use crate::topo::placer::{OverlapMode, PackConfig, PackMode, PageDir, Ratio};
 use std::collections::HashMap;
 
 type PropertyList = HashMap<String, String>;
 
+/// The radius of the corners of shapes with style=rounded.
+const ROUNDED_RADIUS: usize = 15;
+
+/// The size of the text of the elements that don't set 'fontsize'.
+const DEFAULT_FONT_SIZE: usize = 14;
+
+/// The width of the lines of shapes and edges with style=bold.
+const BOLD_LINE_WIDTH: f64 = 2.;
+
+/// The 'width' and 'height' attributes of nodes are in inches.
+const POINTS_PER_INCH: f64 = 72.;
+
+/// The smallest width and height of a node, in inches, as in Graphviz.
+const MIN_NODE_INCHES: f64 = 0.01;
+
 // The methods in this file are responsible for converting the parsed Graphviz
 // AST into the VisualGraph data-structure that we use for layout and rendering
 // of the graph.
 
 #[derive(Debug)]
-struct EdgeDesc {
-    from: String,
-    to: String,
+struct ClusterDesc {
+    name: String,
     props: PropertyList,
-    is_directed: bool,
-    from_port: Option<String>,
-    to_port: Option<String>,
+    parent: Option<usize>,
+}
+
+#[derive(Debug)]
+pub(crate) struct EdgeDesc {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) props: PropertyList,
+    pub(crate) is_directed: bool,
+    pub(crate) from_port: Option<String>,
+    pub(crate) to_port: Option<String>,
+}
+
+/// \returns the CSS classes of an element of the kind \p kind, such as
+/// "node", followed by the classes of the 'class' attribute in \p lst.
+fn get_class(kind: &str, lst: &PropertyList) -> String {
+    match lst.get("class").filter(|x| !x.trim().is_empty()) {
+        Option::Some(class) => format!("{} {}", kind, class.trim()),
+        None => kind.to_string(),
+    }
+}
+
+/// \returns the ids of the edges between the pairs of nodes \p edges, such
+/// as "a->b". The edges that repeat a pair get the number of the earlier
+/// edges of the pair as a suffix, as in "a->b:1", so that the ids are
+/// unique.
+pub(crate) fn get_edge_ids<'a>(
+    edges: impl Iterator<Item = (&'a String, &'a String)>,
+) -> Vec<String> {
+    let mut seen: HashMap<(&str, &str), usize> = HashMap::new();
+    edges
+        .map(|(from, to)| {
+            let count = seen.entry((from, to)).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                format!("{}->{}", from, to)
+            } else {
+                format!("{}->{}:{}", from, to, *count - 1)
+            }
+        })
+        .collect()
 }
 
 /// This class constructs a visual graph from the parsed AST.
 #[derive(Debug)]
 pub struct GraphBuilder {
-    // This records the state of the top-level graph.
+    // This records the name and the state of the top-level graph.
+    graph_name: String,
     global_state: PropertyList,
     // This keeps track of the construction order of the nodes, because
     // hashmap does not maintain a persistent iteration order.
@@ -41,11 +106,26 @@ pub struct GraphBuilder {
     nodes: HashMap<String, PropertyList>,
     // A list of edge properties.
     edges: Vec<EdgeDesc>,
+    // The 'cluster' subgraphs, and the clusters that we are currently in.
+    clusters: Vec<ClusterDesc>,
+    cluster_stack: Vec<usize>,
+    // Maps node names to the innermost cluster that contains them.
+    node_clusters: HashMap<String, usize>,
+    // The nodes in each of the subgraphs that we are currently in, and the
+    // nodes of the subgraphs that constrain their rank.
+    subgraph_nodes: Vec<Vec<String>>,
+    rank_groups: Vec<(RankConstraint, Vec<String>)>,
     /// Scopes that maintain the property list that changes as we enter and
     /// leave different regions of the graph.
     global_attr: ScopedMap<String, String>,
     node_attr: ScopedMap<String, String>,
     edge_attr: ScopedMap<String, String>,
+    // Selects how the size of the labels is measured.
+    text_metrics: TextMetrics,
+    // Embed the data of the node images in the output.
+    embed_images: bool,
+    // The default colors of the elements.
+    theme: Theme,
 }
 impl Default for GraphBuilder {
     fn default() -> Self {
@@ -56,16 +136,63 @@ impl Default for GraphBuilder {
 impl GraphBuilder {
     pub fn new() -> Self {
         Self {
+            graph_name: String::new(),
             global_state: PropertyList::new(),
             node_order: Vec::new(),
             nodes: HashMap::new(),
             edges: Vec::new(),
+            clusters: Vec::new(),
+            cluster_stack: Vec::new(),
+            node_clusters: HashMap::new(),
+            subgraph_nodes: Vec::new(),
+            rank_groups: Vec::new(),
             global_attr: ScopedMap::new(),
             node_attr: ScopedMap::new(),
             edge_attr: ScopedMap::new(),
+            text_metrics: TextMetrics::Font,
+            embed_images: false,
+            theme: Theme::light(),
         }
     }
+
+    /// Measure the labels of the graph with \p metrics. Graphs that are
+    /// rendered by the text backends use TextMetrics::Grid, so that the
+    /// labels fit in the shapes.
+    pub fn set_text_metrics(&mut self, metrics: TextMetrics) {
+        self.text_metrics = metrics;
+    }
+
+    /// Embed the images of the nodes in the output as data URIs if \p embed
+    /// is set, instead of referring to the image files.
+    pub fn set_embed_images(&mut self, embed: bool) {
+        self.embed_images = embed;
+    }
+
+    /// Draw the elements with the default colors of \p theme. The attributes
+    /// of the elements override the theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
     pub fn visit_graph(&mut self, graph: &ast::Graph) {
+        // Subgraphs whose name starts with 'cluster' are drawn in a box.
+        let is_cluster =
+            !self.global_attr.is_empty() && graph.name.starts_with("cluster");
+        if is_cluster {
+            self.clusters.push(ClusterDesc {
+                name: graph.name.clone(),
+                props: PropertyList::new(),
+                parent: self.cluster_stack.last().cloned(),
+            });
+            self.cluster_stack.push(self.clusters.len() - 1);
+        }
+        let is_subgraph = !self.global_attr.is_empty();
+        if is_subgraph {
+            self.subgraph_nodes.push(Vec::new());
+        } else {
+            self.graph_name = graph.name.clone();
+        }
+
         self.global_attr.push();
         self.node_attr.push();
         self.edge_attr.push();
@@ -73,6 +200,31 @@ impl GraphBuilder {
             self.visit_stmt(stmt);
         }
 
+        if is_cluster {
+            let idx = self.cluster_stack.pop().unwrap();
+            self.clusters[idx].props = self.global_attr.flatten();
+        }
+
+        // Subgraphs with the 'rank' attribute constrain the rank of their
+        // nodes.
+        if is_subgraph {
+            let nodes = self.subgraph_nodes.pop().unwrap();
+            let rank = self.global_attr.get(&"rank".to_string());
+            let kind = match rank.as_deref() {
+                Option::Some("same") => Some(RankConstraint::Same),
+                Option::Some("min") => Some(RankConstraint::Min),
+                Option::Some("max") => Some(RankConstraint::Max),
+                Option::Some("source") => Some(RankConstraint::Source),
+                Option::Some("sink") => Some(RankConstraint::Sink),
+                _ => None,
+            };
+            if let Option::Some(kind) = kind {
+                if !nodes.is_empty() {
+                    self.rank_groups.push((kind, nodes));
+                }
+            }
+        }
+
         // TODO: we dump the property list when we close the scope. This is not
         // correct for sub graphs.
         self.global_state = self.global_attr.flatten();
@@ -132,6 +284,12 @@ impl GraphBuilder {
     // If \p overwrite is set then we are declaring a node. This means that
     // we need to update the properties that already exist.
     fn init_node_with_name(&mut self, name: &str, overwrite: bool) {
+        self.add_node_to_cluster(name);
+        for nodes in self.subgraph_nodes.iter_mut() {
+            if !nodes.iter().any(|x| x == name) {
+                nodes.push(name.to_string());
+            }
+        }
         let node_attr = self.node_attr.flatten();
 
         if let Option::Some(prop_list) = self.nodes.get_mut(name) {
@@ -147,6 +305,26 @@ impl GraphBuilder {
         }
     }
 
+    /// Place the node \p name in the cluster that we are in. Nodes that are
+    /// mentioned in several clusters stay in the first one, unless they are
+    /// mentioned in a cluster that is nested in it.
+    fn add_node_to_cluster(&mut self, name: &str) {
+        let curr = match self.cluster_stack.last() {
+            Option::Some(curr) => *curr,
+            Option::None => return,
+        };
+        if let Option::Some(prev) = self.node_clusters.get(name) {
+            let mut parent = self.clusters[curr].parent;
+            while parent.is_some() && parent != Some(*prev) {
+                parent = self.clusters[parent.unwrap()].parent;
+            }
+            if parent.is_none() {
+                return;
+            }
+        }
+        self.node_clusters.insert(name.to_string(), curr);
+    }
+
     fn visit_node(&mut self, n: &ast::NodeStmt) {
         self.node_attr.push();
 
@@ -178,6 +356,187 @@ impl GraphBuilder {
         }
     }
 
+    /// \returns the name of the top-level graph.
+    pub(crate) fn graph_name(&self) -> &str {
+        &self.graph_name
+    }
+
+    /// \returns the attributes of the top-level graph.
+    pub(crate) fn graph_attributes(&self) -> &HashMap<String, String> {
+        &self.global_state
+    }
+
+    /// \returns the handle of the node \p name in the graph that get() builds,
+    /// or None if there is no such node.
+    pub fn node_handle(&self, name: &str) -> Option<NodeHandle> {
+        let idx = self.node_order.iter().position(|x| x == name)?;
+        Some(NodeHandle::new(idx))
+    }
+
+    /// \returns the names and the attributes of the nodes, in the order of the
+    /// handles of the nodes in the graph that get() builds.
+    pub(crate) fn node_list(
+        &self,
+    ) -> impl Iterator<Item = (&str, &HashMap<String, String>)> + '_ {
+        self.node_order
+            .iter()
+            .map(move |name| (name.as_str(), &self.nodes[name]))
+    }
+
+    /// \returns the edges, in the order in which they are added to the graph
+    /// that get() builds.
+    pub(crate) fn edge_list(&self) -> &[EdgeDesc] {
+        &self.edges
+    }
+
+    /// \returns the graph that this builder visited as canonical DOT text,
+    /// with the resolved attributes of each node and edge. If \p layout is
+    /// set, then the text also has the positions of the nodes and the edges in
+    /// the 'pos' attributes, like the output of `dot -Tdot`.
+    pub fn to_dot(&self, layout: Option<&LayoutResult>) -> String {
+        self.write_dot_with(layout.map(LayoutAttributes::new))
+    }
+
+    /// \returns the graph that this builder visited as DOT text, with the
+    /// positions of the graph \p vg, that get() returned and that was laid
+    /// out, and with the xdot operations that draw the graph, the nodes and
+    /// the edges, like the output of `dot -Txdot`.
+    pub fn to_xdot(&self, vg: &VisualGraph) -> String {
+        self.write_dot_with(Some(LayoutAttributes::with_drawing(vg)))
+    }
+
+    /// \returns the layout \p layout of the graph that get() returned, in the
+    /// 'plain' format of Graphviz, or in the 'plain-ext' format, that has the
+    /// ports of the edges, if \p extended is set.
+    pub fn to_plain(&self, layout: &LayoutResult, extended: bool) -> String {
+        let nodes: Vec<_> = self
+            .node_order
+            .iter()
+            .map(|name| (name.clone(), sorted(&self.nodes[name])))
+            .collect();
+        let edges: Vec<_> = self
+            .edges
+            .iter()
+            .map(|e| {
+                let from = ast::NodeId::new(&e.from, &e.from_port);
+                let to = ast::NodeId::new(&e.to, &e.to_port);
+                (from, to, sorted(&e.props))
+            })
+            .collect();
+        write_plain(layout, &nodes, &edges, extended)
+    }
+
+    fn write_dot_with(&self, layout: Option<LayoutAttributes>) -> String {
+        write_dot(&self.to_ast(layout))
+    }
+
+    /// \returns the graph that this builder visited as a canonical AST, with
+    /// the graph attributes first, a statement with the resolved attributes
+    /// of each node, the clusters and the rank groups, and a statement for
+    /// each edge. The attributes of \p layout are added to the elements.
+    pub(crate) fn to_ast(
+        &self,
+        layout: Option<LayoutAttributes>,
+    ) -> ast::Graph {
+        let mut graph = ast::Graph::new(&self.graph_name);
+        let list = &mut graph.list.list;
+
+        let extra = layout.as_ref().map(|x| x.graph()).unwrap_or_default();
+        let attrs = merge_attributes(sorted(&self.global_state), extra);
+        if !attrs.list.is_empty() {
+            let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+            list.push(ast::Stmt::Attribute(stmt));
+        }
+
+        // Declare the nodes first, to keep their order, and then list them
+        // again in their clusters and rank groups.
+        for (i, name) in self.node_order.iter().enumerate() {
+            let extra = layout.as_ref().map(|x| x.node(i)).unwrap_or_default();
+            let attrs = merge_attributes(sorted(&self.nodes[name]), extra);
+            let id = ast::NodeId::new(name, &None);
+            list.push(ast::Stmt::Node(ast::NodeStmt::new_with_list(id, attrs)));
+        }
+        for (i, desc) in self.clusters.iter().enumerate() {
+            if desc.parent.is_none() {
+                list.push(ast::Stmt::SubGraph(self.cluster_to_ast(i)));
+            }
+        }
+        for (kind, names) in self.rank_groups.iter() {
+            let rank = match kind {
+                RankConstraint::Same => "same",
+                RankConstraint::Min => "min",
+                RankConstraint::Source => "source",
+                RankConstraint::Max => "max",
+                RankConstraint::Sink => "sink",
+            };
+            let mut group = ast::Graph::new("");
+            let mut attrs = ast::AttributeList::new();
+            attrs.add_attr("rank", rank);
+            let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+            group.list.list.push(ast::Stmt::Attribute(stmt));
+            for name in names {
+                let stmt = ast::NodeStmt::new(ast::NodeId::new(name, &None));
+                group.list.list.push(ast::Stmt::Node(stmt));
+            }
+            list.push(ast::Stmt::SubGraph(group));
+        }
+
+        for (i, desc) in self.edges.iter().enumerate() {
+            let from = ast::NodeId::new(&desc.from, &desc.from_port);
+            let to = ast::NodeId::new(&desc.to, &desc.to_port);
+            let kind = if desc.is_directed {
+                ast::ArrowKind::Arrow
+            } else {
+                ast::ArrowKind::Line
+            };
+            let mut stmt = ast::EdgeStmt::new(from);
+            stmt.insert(to, kind);
+            let extra = layout.as_ref().map(|x| x.edge(i)).unwrap_or_default();
+            stmt.list = merge_attributes(sorted(&desc.props), extra);
+            list.push(ast::Stmt::Edge(stmt));
+        }
+        graph
+    }
+
+    /// \returns the cluster \p idx and the clusters in it as subgraphs. The
+    /// attributes of clusters are inherited from the enclosing scopes, so
+    /// only the attributes that differ from the enclosing cluster, or from the
+    /// graph, are written.
+    fn cluster_to_ast(&self, idx: usize) -> ast::Graph {
+        let desc = &self.clusters[idx];
+        let outer = match desc.parent {
+            Option::Some(parent) => &self.clusters[parent].props,
+            None => &self.global_state,
+        };
+        let mut graph = ast::Graph::new(&desc.name);
+        let mut attrs = ast::AttributeList::new();
+        for (key, val) in sorted(&desc.props) {
+            // The rank constraints are written as rank groups.
+            if key != "rank" && outer.get(&key) != Some(&val) {
+                attrs.add_attr(&key, &val);
+            }
+        }
+        if !attrs.list.is_empty() {
+            let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+            graph.list.list.push(ast::Stmt::Attribute(stmt));
+        }
+        for name in self.node_order.iter() {
+            if self.node_clusters.get(name) == Some(&idx) {
+                let stmt = ast::NodeStmt::new(ast::NodeId::new(name, &None));
+                graph.list.list.push(ast::Stmt::Node(stmt));
+            }
+        }
+        for (i, child) in self.clusters.iter().enumerate() {
+            if child.parent == Some(idx) {
+                graph
+                    .list
+                    .list
+                    .push(ast::Stmt::SubGraph(self.cluster_to_ast(i)));
+            }
+        }
+        graph
+    }
+
     pub fn get(&self) -> VisualGraph {
         let mut dir = Orientation::TopToBottom;
 
@@ -190,6 +549,226 @@ impl GraphBuilder {
 
         let mut vg = VisualGraph::new(dir);
 
+        // Select the layout engine based on the 'layout' property.
+        if let Option::Some(engine) = self.global_state.get("layout") {
+            if let Option::Some(engine) = LayoutEngine::from_name(engine) {
+                vg.set_layout_engine(engine);
+            }
+        }
+
+        // Control the stress placer with the 'maxiter' and 'epsilon'
+        // properties.
+        let mut stress = vg.stress_config();
+        if let Option::Some(iter) = self.global_state.get("maxiter") {
+            if let Result::Ok(iter) = iter.parse::<usize>() {
+                stress.max_iterations = iter;
+            }
+        }
+        if let Option::Some(eps) = self.global_state.get("epsilon") {
+            if let Result::Ok(eps) = eps.parse::<f64>() {
+                stress.epsilon = eps;
+            }
+        }
+        vg.set_stress_config(stress);
+
+        // Remove the overlaps between the nodes of the force-directed and the
+        // stress layouts with the 'overlap' property.
+        if let Option::Some(val) = self.global_state.get("overlap") {
+            if let Option::Some(mode) = OverlapMode::from_name(val) {
+                let mut overlap = vg.overlap_config();
+                overlap.mode = mode;
+                vg.set_overlap_config(overlap);
+            }
+        }
+
+        // Control the grid placer with the 'columns' and 'cellpadding'
+        // properties.
+        let mut grid = vg.grid_config();
+        if let Option::Some(columns) = self.global_state.get("columns") {
+            if let Result::Ok(columns) = columns.parse::<usize>() {
+                grid.columns = Some(columns);
+            }
+        }
+        if let Option::Some(pad) = self.global_state.get("cellpadding") {
+            if let Result::Ok(pad) = pad.parse::<f64>() {
+                grid.padding = pad;
+            }
+        }
+        vg.set_grid_config(grid);
+
+        // Scale the effort of the reduction of edge crossings with the
+        // 'mclimit' property.
+        if let Option::Some(factor) = self.global_state.get("mclimit") {
+            if let Result::Ok(factor) = factor.parse::<f64>() {
+                let ordering = vg.ordering_config().scaled(factor);
+                vg.set_ordering_config(ordering);
+            }
+        }
+
+        // Pack the components of the graph with the 'pack' and 'packmode'
+        // properties. The value of 'pack' is a boolean or the margin between
+        // the components, in points, and 'packmode' is 'graph' or 'array', that may end
+        // with the number of columns, such as 'array_c3'.
+        let mut pack: Option<PackConfig> = None;
+        if let Option::Some(val) = self.global_state.get("pack") {
+            if let Result::Ok(margin) = val.parse::<f64>() {
+                // Negative margins turn off the packing.
+                if margin >= 0. {
+                    pack = Some(PackConfig {
+                        margin,
+                        ..PackConfig::default()
+                    });
+                }
+            } else if val == "true" {
+                pack = Some(PackConfig::default());
+            }
+        }
+        if let Option::Some(mode) = self.global_state.get("packmode") {
+            let mode = if let Option::Some(rest) = mode.strip_prefix("array") {
+                let columns = rest.trim_start_matches(['_', 'c']);
+                Some(PackMode::Array(columns.parse::<usize>().ok()))
+            } else if ["graph", "node", "clust"].contains(&mode.as_str()) {
+                Some(PackMode::Graph)
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Unknown pack mode \"{}\"", mode);
+                None
+            };
+            if let Option::Some(mode) = mode {
+                let config = pack.get_or_insert_with(PackConfig::default);
+                config.mode = mode;
+            }
+        }
+        if let Option::Some(pack) = pack {
+            vg.set_pack_config(pack);
+        }
+
+        // Control the space between the nodes and the ranks with the 'nodesep'
+        // and 'ranksep' properties. The rank separation is a list of gaps,
+        // such as "0.5:1", that may end with the word 'equally'.
+        let mut spacing = vg.spacing_config();
+        if let Option::Some(sep) = self.global_state.get("nodesep") {
+            if let Result::Ok(sep) = sep.parse::<f64>() {
+                spacing.node_gap = Some(sep.max(0.) * POINTS_PER_INCH);
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", sep);
+            }
+        }
+        if let Option::Some(sep) = self.global_state.get("ranksep") {
+            for word in sep.split_whitespace() {
+                if word == "equally" {
+                    spacing.equal_ranks = true;
+                    continue;
+                }
+                for gap in word.split(':') {
+                    if let Result::Ok(gap) = gap.parse::<f64>() {
+                        spacing.rank_gaps.push(gap.max(0.) * POINTS_PER_INCH);
+                    } else {
+                        #[cfg(feature = "log")]
+                        log::info!("Can't parse number \"{}\"", gap);
+                    }
+                }
+            }
+        }
+        vg.set_spacing_config(spacing);
+
+        // The 'margin' property sets the space around the drawing.
+        if let Option::Some(margin) = self.global_state.get("margin") {
+            if let Option::Some(margin) = Self::parse_margin(margin) {
+                vg.set_margin(margin);
+            }
+        }
+
+        // Fit the drawing in the 'size' and the 'page' properties, that are
+        // in inches, and stretch the layout with the 'ratio' property. Sizes
+        // that end with '!' scale small drawings up.
+        let mut fit = vg.size_config();
+        if let Option::Some(size) = self.global_state.get("size") {
+            let (size, fill) = match size.strip_suffix('!') {
+                Option::Some(size) => (size, true),
+                None => (size.as_str(), false),
+            };
+            fit.size =
+                Self::parse_margin(size).filter(|x| x.x > 0. && x.y > 0.);
+            fit.fill = fill;
+        }
+        if let Option::Some(page) = self.global_state.get("page") {
+            fit.page =
+                Self::parse_margin(page).filter(|x| x.x > 0. && x.y > 0.);
+        }
+        if let Option::Some(dir) = self.global_state.get("pagedir") {
+            if let Option::Some(dir) = PageDir::from_name(dir) {
+                fit.page_dir = dir;
+            }
+        }
+        if let Option::Some(ratio) = self.global_state.get("ratio") {
+            fit.ratio = Ratio::from_name(ratio);
+        }
+        vg.set_size_config(fit);
+
+        // The 'bgcolor' property paints the background of the drawing.
+        if let Option::Some(color) = self.global_state.get("bgcolor") {
+            let color = Self::normalize_color(color.clone());
+            vg.set_background(Color::fast(&color));
+        } else if let Option::Some(color) = self.theme.background {
+            vg.set_background(color);
+        }
+
+        // The 'stylesheet' property links the SVG output to a stylesheet.
+        if let Option::Some(href) = self.global_state.get("stylesheet") {
+            vg.add_stylesheet(Stylesheet::Link(href.clone()));
+        }
+
+        // The 'label' property is the title of the graph.
+        if let Option::Some(label) = self.get_graph_label() {
+            vg.set_label(label);
+        }
+
+        // The 'concentrate' property merges parallel edges into trunks.
+        if self
+            .global_state
+            .get("concentrate")
+            .is_some_and(|x| x == "true")
+        {
+            vg.set_concentrate(true);
+        }
+
+        // The 'avoidnodes' property routes the edges around the nodes that
+        // are in their way.
+        if self
+            .global_state
+            .get("avoidnodes")
+            .is_some_and(|x| x == "true")
+        {
+            vg.set_avoid_nodes(true);
+        }
+
+        // Set the edge routing based on the 'splines' property.
+        if let Option::Some(splines) = self.global_state.get("splines") {
+            let splines = match splines.as_str() {
+                "line" | "false" => Some(SplineKind::Line),
+                "polyline" => Some(SplineKind::Polyline),
+                "curved" => Some(SplineKind::Curved),
+                "ortho" => Some(SplineKind::Ortho),
+                "spline" | "true" => Some(SplineKind::Spline),
+                _ => None,
+            };
+            if let Option::Some(splines) = splines {
+                vg.set_splines(splines);
+            }
+        }
+
+        // The 'layers' property splits the drawing into layers, and the
+        // 'outputorder' property selects if the edges are drawn first.
+        let layers = self.get_layer_names();
+        vg.set_layers(layers.clone());
+        if let Option::Some(order) = self.global_state.get("outputorder") {
+            if let Option::Some(order) = OutputOrder::from_name(order) {
+                vg.set_output_order(order);
+            }
+        }
+
         // Keeps track of the newly created nodes and indexes them by name.
         let mut node_map: HashMap<String, NodeHandle> = HashMap::new();
 
@@ -199,53 +778,465 @@ impl GraphBuilder {
         for node_name in self.node_order.iter() {
             let node_prop = self.nodes.get(node_name).unwrap();
 
-            let shape =
-                Self::get_shape_from_attributes(dir, node_prop, node_name);
+            let mut shape =
+                self.get_shape_from_attributes(dir, node_prop, node_name);
+            let id = node_prop.get("id").unwrap_or(node_name);
+            let class = get_class("node", node_prop);
+            let ident = [
+                ("id", id.as_str()),
+                ("class", &class),
+                ("data-node", node_name.as_str()),
+            ];
+            shape.properties = Some(Self::get_properties(&ident, node_prop));
+            shape.link = Self::get_link(node_prop);
+            shape.layers = Self::get_layers(&layers, node_prop);
+            shape.z_order = Self::get_z_order(node_prop);
             let handle = vg.add_node(shape);
             node_map.insert(node_name.to_string(), handle);
+
+            // A node can select itself as the root with 'root=true'.
+            if node_prop.get("root").is_some_and(|x| x == "true") {
+                vg.set_root(handle);
+            }
+        }
+
+        // Select the root of the radial and tree layouts by name.
+        if let Option::Some(root) = self.global_state.get("root") {
+            if let Option::Some(handle) = node_map.get(root) {
+                vg.set_root(*handle);
+            }
         }
 
         // Create and register all of the edges.
-        for edge_prop in &self.edges {
-            let shape = Self::get_arrow_from_attributes(
-                &edge_prop.props,
-                edge_prop.is_directed,
-                edge_prop.from_port.clone(),
-                edge_prop.to_port.clone(),
-            );
+        let edge_ids =
+            get_edge_ids(self.edges.iter().map(|x| (&x.from, &x.to)));
+        for (edge_prop, edge_id) in self.edges.iter().zip(edge_ids.iter()) {
+            let mut shape = self.get_arrow_from_attributes(edge_prop);
+            let id = edge_prop.props.get("id").unwrap_or(edge_id);
+            let class = get_class("edge", &edge_prop.props);
+            let ident = [
+                ("id", id.as_str()),
+                ("class", &class),
+                ("data-from", edge_prop.from.as_str()),
+                ("data-to", edge_prop.to.as_str()),
+            ];
+            shape.properties =
+                Some(Self::get_properties(&ident, &edge_prop.props));
+            shape.link = Self::get_link(&edge_prop.props);
+            shape.layers = self.get_edge_layers(&layers, edge_prop);
+            shape.z_order = Self::get_z_order(&edge_prop.props);
             let from = node_map.get(&edge_prop.from).unwrap();
             let to = node_map.get(&edge_prop.to).unwrap();
             vg.add_edge(shape, *from, *to);
         }
 
+        // Create the clusters, and place the nodes in them.
+        for desc in &self.clusters {
+            let mut cluster = self.get_cluster_from_attributes(desc);
+            cluster.parent = desc.parent;
+            let id = desc.props.get("id").unwrap_or(&desc.name);
+            let class = get_class("cluster", &desc.props);
+            let ident = [
+                ("id", id.as_str()),
+                ("class", &class),
+                ("data-cluster", desc.name.as_str()),
+            ];
+            cluster.properties =
+                Some(Self::get_properties(&ident, &desc.props));
+            cluster.link = Self::get_link(&desc.props);
+            cluster.layers = Self::get_layers(&layers, &desc.props);
+            cluster.z_order = Self::get_z_order(&desc.props);
+            vg.add_cluster(cluster);
+        }
+        for (name, cluster) in &self.node_clusters {
+            vg.add_to_cluster(node_map[name], *cluster);
+        }
+
+        for (kind, names) in &self.rank_groups {
+            let nodes = names.iter().map(|name| node_map[name]).collect();
+            vg.add_rank_constraint(*kind, nodes);
+        }
+
+        // The 'lane' property places the nodes in the lanes of the graph, in
+        // the order in which the lanes are first used.
+        let mut lanes: HashMap<&str, usize> = HashMap::new();
+        for node_name in self.node_order.iter() {
+            let name = match self.nodes[node_name].get("lane") {
+                Option::Some(name) => name.as_str(),
+                None => continue,
+            };
+            let lane = *lanes.entry(name).or_insert_with(|| {
+                let mut look = StyleAttr::new(
+                    self.theme.cluster_line,
+                    1.,
+                    None,
+                    0,
+                    DEFAULT_FONT_SIZE,
+                );
+                look.font_color = self.theme.cluster_font;
+                let mut lane = Lane::new(name, &look);
+                let ident = [("class", "lane"), ("data-lane", name)];
+                lane.properties =
+                    Some(Self::get_properties(&ident, &PropertyList::new()));
+                vg.add_lane(lane)
+            });
+            vg.add_to_lane(node_map[node_name], lane);
+        }
+
+        // The 'start' and 'end' properties place the nodes on the axis of the
+        // timeline layout. Nodes without an end are milestones.
+        for node_name in self.node_order.iter() {
+            let attrs = &self.nodes[node_name];
+            let get =
+                |name| attrs.get(name).and_then(|x| x.parse::<f64>().ok());
+            if let Option::Some(start) = get("start") {
+                let end = get("end").unwrap_or(start);
+                let handle = node_map[node_name];
+                vg.set_node_span(handle, start.min(end), start.max(end));
+            }
+        }
+
         vg
     }
 
-    fn get_arrow_from_attributes(
-        lst: &PropertyList,
-        has_arrow: bool,
-        from_port: Option<String>,
-        to_port: Option<String>,
-    ) -> Arrow {
-        let mut line_width = 1;
+    /// Expand the escapes in the label \p label that stand for names, such as
+    /// "\\N" for the name of the node. \p names maps the letters of the
+    /// escapes to the names.
+    fn expand_label_names(label: &str, names: &[(char, &str)]) -> String {
+        let mut res = String::new();
+        let mut chars = label.chars().peekable();
+        while let Option::Some(ch) = chars.next() {
+            if ch == '\\' {
+                if let Option::Some(&next) = chars.peek() {
+                    if let Option::Some(name) =
+                        names.iter().find(|x| x.0 == next)
+                    {
+                        res.push_str(name.1);
+                        chars.next();
+                        continue;
+                    }
+                    // Don't expand the escapes after escaped backslashes.
+                    if next == '\\' {
+                        res.push(ch);
+                        res.push(next);
+                        chars.next();
+                        continue;
+                    }
+                }
+            }
+            res.push(ch);
+        }
+        res
+    }
+
+    /// \returns the title of the graph, from the 'label', 'labelloc' and
+    /// 'labeljust' properties, or None if the graph has no label.
+    fn get_graph_label(&self) -> Option<GraphLabel> {
+        let lst = &self.global_state;
+        let val = lst.get("label")?;
+        let text = match parse_html_label(val) {
+            Result::Ok(html) if is_html_label(val) => html.to_plain_text(),
+            _ => {
+                let names = [('G', self.graph_name.as_str())];
+                unescape_record_chars(&Self::expand_label_names(val, &names))
+            }
+        };
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut font_size: usize = 14;
+        if let Option::Some(fx) = lst.get("fontsize") {
+            if let Result::Ok(x) = fx.parse::<usize>() {
+                font_size = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse integer \"{}\"", fx);
+            }
+        }
+        let mut look =
+            StyleAttr::new(Color::fast("black"), 1., None, 0, font_size);
+        look.font_color = self.theme.graph_font;
+        self.set_font_from_attributes(lst, &mut look);
+
+        let mut label = GraphLabel::new(&text, &look);
+        label.top = lst.get("labelloc").is_some_and(|x| x.starts_with('t'));
+        label.justification = match lst.get("labeljust").map(|x| x.as_str()) {
+            Option::Some("l") => Justification::Left,
+            Option::Some("r") => Justification::Right,
+            _ => Justification::Center,
+        };
+        Some(label)
+    }
+
+    fn get_cluster_from_attributes(&self, desc: &ClusterDesc) -> Cluster {
+        let lst = &desc.props;
+        let mut line_color = self.theme.cluster_line.to_web_color();
+        let mut fill_color: Option<String> = None;
         let mut font_size: usize = 14;
-        let start = LineEndKind::None;
-        let end = if has_arrow {
+        let mut penwidth: f64 = self.theme.penwidth;
+        let mut rounded = 0;
+        let mut bold = false;
+        let mut line_style = LineStyleKind::Normal;
+
+        let label = match lst.get("label") {
+            Option::Some(val) => {
+                let names = [('G', desc.name.as_str())];
+                unescape_record_chars(&Self::expand_label_names(val, &names))
+            }
+            Option::None => String::new(),
+        };
+
+        if let Option::Some(x) =
+            lst.get("pencolor").or_else(|| lst.get("color"))
+        {
+            line_color = Self::normalize_color(x.clone());
+        }
+
+        if let Option::Some(x) = lst.get("bgcolor") {
+            fill_color = Some(Self::normalize_color(x.clone()));
+        }
+
+        if let Option::Some(style) = lst.get("style") {
+            for style in style.split(',').map(|x| x.trim()) {
+                match style {
+                    "filled" => {
+                        let color = lst
+                            .get("fillcolor")
+                            .or_else(|| lst.get("color"))
+                            .or_else(|| lst.get("bgcolor"))
+                            .cloned()
+                            .unwrap_or_else(|| {
+                                self.theme.filled.to_web_color()
+                            });
+                        fill_color = Some(Self::normalize_color(color));
+                    }
+                    "rounded" => rounded = ROUNDED_RADIUS,
+                    "dashed" => line_style = LineStyleKind::Dashed,
+                    "dotted" => line_style = LineStyleKind::Dotted,
+                    "bold" => bold = true,
+                    "invis" => line_style = LineStyleKind::None,
+                    _ => {}
+                }
+            }
+        }
+
+        if let Option::Some(fx) = lst.get("fontsize") {
+            if let Result::Ok(x) = fx.parse::<usize>() {
+                font_size = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse integer \"{}\"", fx);
+            }
+        }
+
+        if let Option::Some(pw) = lst.get("penwidth") {
+            if let Result::Ok(x) = pw.parse::<f64>() {
+                penwidth = x.max(0.);
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", pw);
+            }
+        }
+
+        if bold {
+            penwidth = penwidth.max(BOLD_LINE_WIDTH);
+        }
+
+        let mut line_color = Color::fast(&line_color);
+        if let LineStyleKind::None = line_style {
+            line_color = Color::transparent();
+        }
+        let fill_color = fill_color.map(|x| Color::fast(&x));
+        let mut look = StyleAttr::new(
+            line_color, penwidth, fill_color, rounded, font_size,
+        );
+        look.set_dash(&line_style.dash_pattern());
+        look.visible = !matches!(line_style, LineStyleKind::None);
+        look.font_color = self.theme.cluster_font;
+        self.set_font_from_attributes(lst, &mut look);
+        Cluster::new(&label, &look, None)
+    }
+
+    /// Create the attributes that the SVG based backends attach to the group
+    /// of a node or an edge. They contain the names in \p ident, that tell
+    /// which node or edge the shape belongs to, and the tooltip and the URL
+    /// from the property list \p lst.
+    fn get_properties(ident: &[(&str, &str)], lst: &PropertyList) -> String {
+        let mut attrs: Vec<(&str, &str)> = ident.to_vec();
+        if let Option::Some(tooltip) = lst.get("tooltip") {
+            attrs.push(("data-tooltip", tooltip));
+        }
+        if let Option::Some(url) = lst.get("URL").or_else(|| lst.get("href")) {
+            attrs.push(("data-url", url));
+        }
+        let attrs: Vec<String> = attrs
+            .iter()
+            .map(|(key, val)| format!("{}=\"{}\"", key, escape_xml(val)))
+            .collect();
+        attrs.join(" ")
+    }
+
+    /// \returns the hyperlink and the tooltip of the 'URL' (or 'href'),
+    /// 'target' and 'tooltip' attributes in \p lst, or None if the element
+    /// has neither a URL nor a tooltip.
+    fn get_link(lst: &PropertyList) -> Option<Link> {
+        let url = lst.get("URL").or_else(|| lst.get("href")).cloned();
+        let tooltip = lst.get("tooltip").cloned();
+        if url.is_none() && tooltip.is_none() {
+            return Option::None;
+        }
+        let target = lst.get("target").cloned();
+        Option::Some(Link {
+            url,
+            target,
+            tooltip,
+        })
+    }
+
+    /// \returns the names of the layers in the 'layers' property, such as
+    /// "back:front", that are separated by the characters in 'layersep'.
+    fn get_layer_names(&self) -> Vec<String> {
+        let Option::Some(layers) = self.global_state.get("layers") else {
+            return Vec::new();
+        };
+        let sep = self.global_state.get("layersep").map_or(":\t ", |x| x);
+        layers
+            .split(|c| sep.contains(c))
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// \returns the indices in \p names of the layers that the 'layer'
+    /// property in \p lst selects, or None if the element is drawn in all of
+    /// the layers. The property is a comma separated list of layers, that are
+    /// names or numbers that start at 1, or ranges such as "2:4". The word
+    /// "all" stands for all of the layers, or for the end of a range.
+    fn get_layers(names: &[String], lst: &PropertyList) -> Option<Vec<usize>> {
+        let spec = lst.get("layer")?;
+        if names.is_empty() || spec == "all" {
+            return Option::None;
+        }
+        let last = names.len() - 1;
+        let index = |name: &str, all: usize| {
+            if name == "all" {
+                return Some(all);
+            }
+            if let Option::Some(idx) = names.iter().position(|x| x == name) {
+                return Some(idx);
+            }
+            let num = name.parse::<usize>().ok()?;
+            (1..=names.len()).contains(&num).then(|| num - 1)
+        };
+        let mut layers = Vec::new();
+        for part in spec.split(',').map(|x| x.trim()) {
+            let range = match part.split_once(':') {
+                Option::Some((lo, hi)) => (index(lo, 0), index(hi, last)),
+                None if part == "all" => (Some(0), Some(last)),
+                None => (index(part, 0), index(part, 0)),
+            };
+            if let (Option::Some(lo), Option::Some(hi)) = range {
+                layers.extend(lo..=hi);
+            }
+        }
+        layers.sort();
+        layers.dedup();
+        Some(layers)
+    }
+
+    /// \returns the layers of the edge \p edge, like get_layers. Edges
+    /// without a 'layer' property are drawn in the layers of their ends.
+    fn get_edge_layers(
+        &self,
+        names: &[String],
+        edge: &EdgeDesc,
+    ) -> Option<Vec<usize>> {
+        if edge.props.contains_key("layer") {
+            return Self::get_layers(names, &edge.props);
+        }
+        let from = Self::get_layers(names, &self.nodes[&edge.from])?;
+        let to = Self::get_layers(names, &self.nodes[&edge.to])?;
+        let mut layers = [from, to].concat();
+        layers.sort();
+        layers.dedup();
+        Some(layers)
+    }
+
+    /// \returns the z-order of the 'zorder' property in \p lst. Elements with
+    /// a higher z-order are drawn over the elements with a lower one.
+    fn get_z_order(lst: &PropertyList) -> i32 {
+        lst.get("zorder").and_then(|x| x.parse().ok()).unwrap_or(0)
+    }
+
+    fn get_arrow_from_attributes(&self, edge: &EdgeDesc) -> Arrow {
+        let lst = &edge.props;
+        let has_arrow = edge.is_directed;
+        let mut penwidth: f64 = self.theme.penwidth;
+        let mut font_size: usize = 14;
+        let mut start = LineEndKind::None;
+        let mut end = if has_arrow {
             LineEndKind::Arrow
         } else {
             LineEndKind::None
         };
+
+        // The 'dir' attribute selects the ends of the edge that have heads.
+        if let Option::Some(dir) = lst.get(&"dir".to_string()) {
+            let heads = match dir.as_str() {
+                "forward" => Some((false, true)),
+                "back" => Some((true, false)),
+                "both" => Some((true, true)),
+                "none" => Some((false, false)),
+                _ => None,
+            };
+            if let Option::Some((has_start, has_end)) = heads {
+                let kind = |x: bool| {
+                    if x {
+                        LineEndKind::Arrow
+                    } else {
+                        LineEndKind::None
+                    }
+                };
+                start = kind(has_start);
+                end = kind(has_end);
+            }
+        }
         let mut label = String::from("");
-        let mut color = String::from("black");
+        let mut color = self.theme.edge_line.to_web_color();
         let mut line_style = LineStyleKind::Normal;
 
+        // The escapes in the labels of the edge stand for the name of the
+        // edge, its head and tail nodes, and the graph.
+        let edge_op = if has_arrow { "->" } else { "--" };
+        let edge_name = format!("{}{}{}", edge.from, edge_op, edge.to);
+        let names = [
+            ('E', edge_name.as_str()),
+            ('H', edge.to.as_str()),
+            ('T', edge.from.as_str()),
+            ('G', self.graph_name.as_str()),
+        ];
+        let get_label = |val: &String| {
+            unescape_record_chars(&Self::expand_label_names(val, &names))
+        };
+
         if let Option::Some(val) = lst.get(&"label".to_string()) {
-            label = val.clone();
+            label = match parse_html_label(val) {
+                Result::Ok(html) if is_html_label(val) => html.to_plain_text(),
+                _ => get_label(val),
+            };
         }
 
-        if let Option::Some(stl) = lst.get(&"style".to_string()) {
-            if stl == "dashed" {
-                line_style = LineStyleKind::Dashed;
+        let mut bold = false;
+        if let Option::Some(style) = lst.get(&"style".to_string()) {
+            for style in style.split(',').map(|x| x.trim()) {
+                match style {
+                    "dashed" => line_style = LineStyleKind::Dashed,
+                    "dotted" => line_style = LineStyleKind::Dotted,
+                    "bold" => bold = true,
+                    "invis" => line_style = LineStyleKind::None,
+                    _ => {}
+                }
             }
         }
 
@@ -254,12 +1245,12 @@ impl GraphBuilder {
             color = Self::normalize_color(color);
         }
 
-        if let Option::Some(pw) = lst.get(&"penwidth".to_string()) {
-            if let Result::Ok(x) = pw.parse::<usize>() {
-                line_width = x;
+        if let Option::Some(pw) = lst.get("penwidth") {
+            if let Result::Ok(x) = pw.parse::<f64>() {
+                penwidth = x.max(0.);
             } else {
                 #[cfg(feature = "log")]
-                log::info!("Can't parse integer \"{}\"", pw);
+                log::info!("Can't parse number \"{}\"", pw);
             }
         }
 
@@ -272,72 +1263,379 @@ impl GraphBuilder {
             }
         }
 
+        if bold {
+            penwidth = penwidth.max(BOLD_LINE_WIDTH);
+        }
+
         let color = Color::fast(&color);
-        let look = StyleAttr::new(color, line_width, None, 0, font_size);
-        Arrow::new(start, end, line_style, &label, &look, &from_port, &to_port)
+        let mut look = StyleAttr::new(color, penwidth, None, 0, font_size);
+        look.set_dash(&line_style.dash_pattern());
+        look.font_color = self.theme.edge_font;
+        self.set_font_from_attributes(lst, &mut look);
+
+        // The cardinality of the ends of the relations of ER diagrams, in the
+        // crow's foot notation. Both ends have heads, unless 'dir' says
+        // otherwise.
+        if let Option::Some(val) = lst.get("cardinality") {
+            if let Option::Some((tail, head)) = parse_cardinality(val) {
+                look.arrow_heads = (tail.arrow_head(), head.arrow_head());
+                if !lst.contains_key("dir") {
+                    start = LineEndKind::Arrow;
+                    end = LineEndKind::Arrow;
+                }
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse the cardinality \"{}\"", val);
+            }
+        }
+
+        // The shapes and the size of the arrow heads.
+        if let Option::Some(val) = lst.get(&"arrowtail".to_string()) {
+            if let Option::Some(kind) = ArrowHeadKind::from_name(val) {
+                look.arrow_heads.0 = kind;
+            }
+        }
+        if let Option::Some(val) = lst.get(&"arrowhead".to_string()) {
+            if let Option::Some(kind) = ArrowHeadKind::from_name(val) {
+                look.arrow_heads.1 = kind;
+            }
+        }
+        if let Option::Some(val) = lst.get(&"arrowsize".to_string()) {
+            if let Result::Ok(x) = val.parse::<f64>() {
+                look.arrow_size = x.max(0.);
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", val);
+            }
+        }
+        let mut arrow = Arrow::new(
+            start,
+            end,
+            line_style,
+            &label,
+            &look,
+            &edge.from_port,
+            &edge.to_port,
+        );
+
+        // The labels next to the ends of the edge.
+        if let Option::Some(val) = lst.get(&"headlabel".to_string()) {
+            arrow.head_label = get_label(val);
+        }
+        if let Option::Some(val) = lst.get(&"taillabel".to_string()) {
+            arrow.tail_label = get_label(val);
+        }
+        if let Option::Some(ld) = lst.get(&"labeldistance".to_string()) {
+            if let Result::Ok(x) = ld.parse::<f64>() {
+                arrow.label_distance = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", ld);
+            }
+        }
+        if let Option::Some(la) = lst.get(&"labelangle".to_string()) {
+            if let Result::Ok(x) = la.parse::<f64>() {
+                arrow.label_angle = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", la);
+            }
+        }
+
+        // The ranking constraints of the edge.
+        if let Option::Some(ml) = lst.get(&"minlen".to_string()) {
+            if let Result::Ok(x) = ml.parse::<usize>() {
+                arrow.min_len = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", ml);
+            }
+        }
+        if let Option::Some(w) = lst.get(&"weight".to_string()) {
+            match w.parse::<f64>() {
+                Result::Ok(x) if x >= 0. => arrow.weight = x,
+                _ => {
+                    #[cfg(feature = "log")]
+                    log::info!("Can't parse number \"{}\"", w);
+                }
+            }
+        }
+
+        // Edges with 'constraint=false' don't affect the ranks of the nodes.
+        if let Option::Some(c) = lst.get(&"constraint".to_string()) {
+            arrow.constraint = !matches!(c.as_str(), "false" | "no" | "0");
+        }
+        arrow
+    }
+
+    /// Set the font of the text in \p look from the `fontname` and `fontcolor`
+    /// attributes, and the way that the size of the text is measured.
+    fn set_font_from_attributes(
+        &self,
+        lst: &PropertyList,
+        look: &mut StyleAttr,
+    ) {
+        look.text_metrics = self.text_metrics;
+        if let Option::Some(name) = lst.get("fontname") {
+            look.set_font_name(name);
+        }
+        if let Option::Some(color) = lst.get("fontcolor") {
+            let color = Self::normalize_color(color.clone());
+            look.font_color = Color::fast(&color);
+        }
     }
 
     /// Convert the color to some color that we can handle.
     fn normalize_color(color: String) -> String {
         let mut color = color;
-        if let Option::Some(idx) = color.find(':') {
+        if let Option::Some(idx) = color.find([':', ';']) {
             color = color[0..idx].to_string();
         }
-        if color == "transparent" {
-            color = "white".to_string();
-        }
         color
     }
 
+    /// Create the gradient fill that Graphviz draws for color lists, like
+    /// `fillcolor="red:blue"` or `fillcolor="red;0.3:blue"`, or for the
+    /// `radial`, `striped` and `wedged` styles.
+    fn get_gradient_from_attributes(lst: &PropertyList) -> Option<Gradient> {
+        let has_style = |name: &str| {
+            lst.get("style")
+                .is_some_and(|x| x.split(',').any(|x| x.trim() == name))
+        };
+
+        // Stripes and wedges use the outline colors if there is no fill.
+        if has_style("striped") || has_style("wedged") {
+            let list = lst.get("fillcolor").or_else(|| lst.get("color"))?;
+            let list = parse_color_list(list);
+            if list.is_empty() {
+                return None;
+            }
+            let kind = if has_style("wedged") {
+                GradientKind::Wedged
+            } else {
+                GradientKind::Linear(0.)
+            };
+            return Some(Gradient::with_weights(kind, &list));
+        }
+
+        let list = lst
+            .get("fillcolor")
+            .map(|x| parse_color_list(x))
+            .unwrap_or_default();
+        let colors: Vec<Color> = list.iter().map(|x| x.0).collect();
+        let radial = has_style("radial");
+        if colors.len() < 2 && !radial {
+            return None;
+        }
+        if radial {
+            // A single color fades from white in the center.
+            let colors = match colors.len() {
+                0 => vec![Color::fast("white"), Color::fast("lightgray")],
+                1 => vec![Color::fast("white"), colors[0]],
+                _ => colors,
+            };
+            return Some(Gradient::with_colors(GradientKind::Radial, &colors));
+        }
+
+        let mut angle = 0.;
+        if let Option::Some(ga) = lst.get(&"gradientangle".to_string()) {
+            if let Result::Ok(x) = ga.parse::<f64>() {
+                angle = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", ga);
+            }
+        }
+        // Weighted colors fill bands, and don't fade into each other.
+        let kind = GradientKind::Linear(angle);
+        if list.iter().any(|x| x.1.is_some()) {
+            return Some(Gradient::with_weights(kind, &list));
+        }
+        Some(Gradient::with_colors(kind, &colors))
+    }
+
+    /// Parse the margin \p val, such as "0.2" or "0.3,0.1", in inches, and
+    /// \returns the horizontal and the vertical margin in points.
+    fn parse_margin(val: &str) -> Option<Point> {
+        let parts: Result<Vec<f64>, _> =
+            val.split(',').map(|x| x.trim().parse()).collect();
+        let margin = match parts.as_deref() {
+            Result::Ok([x]) => Point::splat(*x),
+            Result::Ok([x, y]) => Point::new(*x, *y),
+            _ => {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse the margin \"{}\"", val);
+                return None;
+            }
+        };
+        let margin = Point::new(margin.x.max(0.), margin.y.max(0.));
+        Some(margin.scale(POINTS_PER_INCH))
+    }
+
+    /// \returns the size of a node, that the label needs the size \p sz, with
+    /// the 'width', 'height' and 'fixedsize' attributes in \p lst. The width
+    /// and the height are the minimal size of the node, unless the size is
+    /// fixed. Shapes that are \p make_xy_same stay square.
+    fn get_node_size(
+        lst: &PropertyList,
+        sz: Point,
+        make_xy_same: bool,
+    ) -> Point {
+        let get_inches = |name: &str| {
+            let val = lst.get(name)?;
+            if let Result::Ok(x) = val.parse::<f64>() {
+                Some(x.max(MIN_NODE_INCHES) * POINTS_PER_INCH)
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse number \"{}\"", val);
+                None
+            }
+        };
+        let mut width = get_inches("width");
+        let mut height = get_inches("height");
+        if make_xy_same {
+            let side = match (width, height) {
+                (Option::Some(w), Option::Some(h)) => Some(w.max(h)),
+                (w, h) => w.or(h),
+            };
+            width = side;
+            height = side;
+        }
+        let fixed = lst
+            .get("fixedsize")
+            .is_some_and(|x| x == "true" || x == "shape");
+        let get_side = |label: f64, user: Option<f64>| match user {
+            Option::Some(user) if fixed => user,
+            Option::Some(user) => label.max(user),
+            Option::None => label,
+        };
+        Point::new(get_side(sz.x, width), get_side(sz.y, height))
+    }
+
+    /// \returns the image of the 'image' attribute in \p lst, loaded from
+    /// the directories of the 'imagepath' graph attribute, or None if the
+    /// node has no image or if it can't be loaded.
+    fn get_image_from_attributes(&self, lst: &PropertyList) -> Option<Image> {
+        let path = lst.get("image").filter(|x| !x.is_empty())?;
+        let mut candidates = vec![path.clone()];
+        if let Option::Some(dirs) = self.global_state.get("imagepath") {
+            for dir in dirs.split([':', ';']).filter(|x| !x.is_empty()) {
+                let full = std::path::Path::new(dir).join(path);
+                candidates.push(full.to_string_lossy().to_string());
+            }
+        }
+        let found = candidates
+            .iter()
+            .find_map(|x| Image::load(x, self.embed_images));
+        let mut image = match found {
+            Option::Some(image) => image,
+            Option::None => {
+                #[cfg(feature = "log")]
+                log::info!("Can't load the image \"{}\"", path);
+                return Option::None;
+            }
+        };
+        if let Option::Some(scale) = lst.get("imagescale") {
+            image.scale = ImageScale::from_name(scale).unwrap_or_default();
+        }
+        Option::Some(image)
+    }
+
     fn get_shape_from_attributes(
+        &self,
         dir: Orientation,
         lst: &PropertyList,
         default_name: &str,
     ) -> Element {
-        let mut label = default_name.to_string();
-        let mut edge_color = String::from("black");
-        let mut fill_color = String::from("white");
+        let mut label = String::from("\\N");
+        let mut edge_color = self.theme.node_line.to_web_color();
+        let mut fill_color = self.theme.node_fill.to_web_color();
         let mut font_size: usize = 14;
-        let mut line_width: usize = 1;
+        let mut penwidth: f64 = self.theme.penwidth;
         let mut make_xy_same = false;
         let mut rounded_corder_value = 0;
+        let mut line_style = LineStyleKind::Normal;
 
         if let Option::Some(val) = lst.get(&"label".to_string()) {
             label = val.clone();
         }
 
-        let mut shape = ShapeKind::Circle(label.clone());
+        // The escapes in the label stand for the name of the node and of the
+        // graph. Records parse the escapes in the label, and other shapes
+        // display the escaped characters.
+        let names = [('N', default_name), ('G', self.graph_name.as_str())];
+        let label = Self::expand_label_names(&label, &names);
+        let text = unescape_record_chars(&label);
+        let mut shape = ShapeKind::Circle(text.clone());
 
         // Set the shape.
         if let Option::Some(val) = lst.get(&"shape".to_string()) {
             match &val[..] {
                 "box" => {
-                    shape = ShapeKind::Box(label);
+                    shape = ShapeKind::Box(text);
                     make_xy_same = false;
                 }
                 "doublecircle" => {
-                    shape = ShapeKind::DoubleCircle(label);
+                    shape = ShapeKind::DoubleCircle(text);
+                    make_xy_same = true;
+                }
+                "rect" | "rectangle" => {
+                    shape = ShapeKind::Box(text);
+                }
+                "square" => {
+                    shape = ShapeKind::Box(text);
                     make_xy_same = true;
                 }
+                "point" => {
+                    shape = ShapeKind::Point;
+                }
                 "record" => {
                     shape = record_builder(&label);
                 }
                 "Mrecord" => {
-                    rounded_corder_value = 15;
+                    rounded_corder_value = ROUNDED_RADIUS;
                     shape = record_builder(&label);
                 }
-                _ => shape = ShapeKind::Circle(label),
+                "table" => {
+                    let columns = lst.get("columns").cloned();
+                    let columns = columns.unwrap_or_default();
+                    let table = TableNode::from_columns(&text, &columns);
+                    shape = ShapeKind::Html(table.to_html());
+                }
+                name => {
+                    shape = match PolygonKind::from_name(name) {
+                        Option::Some(kind) => ShapeKind::Polygon(kind, text),
+                        Option::None => ShapeKind::Circle(text),
+                    };
+                }
             }
         }
 
+        // HTML-like labels replace the shape of the node. Records keep their
+        // labels, that may start with a port such as "<f0>".
+        if is_html_label(&label) && !matches!(shape, ShapeKind::Record(_)) {
+            shape = html_builder(&label);
+        }
+
         if let Option::Some(x) = lst.get(&"color".to_string()) {
             edge_color = x.clone();
             edge_color = Self::normalize_color(edge_color);
         }
 
+        let mut bold = false;
         if let Option::Some(style) = lst.get(&"style".to_string()) {
-            if style == "filled" && !lst.contains_key("fillcolor") {
-                fill_color = "lightgray".to_string();
+            for style in style.split(',').map(|x| x.trim()) {
+                match style {
+                    "filled" if !lst.contains_key("fillcolor") => {
+                        fill_color = self.theme.filled.to_web_color();
+                    }
+                    "rounded" => rounded_corder_value = ROUNDED_RADIUS,
+                    "dashed" => line_style = LineStyleKind::Dashed,
+                    "dotted" => line_style = LineStyleKind::Dotted,
+                    "bold" => bold = true,
+                    "invis" => line_style = LineStyleKind::None,
+                    _ => {}
+                }
             }
         }
 
@@ -346,6 +1644,11 @@ impl GraphBuilder {
             fill_color = Self::normalize_color(fill_color);
         }
 
+        // Points are filled with the color of their outline.
+        if matches!(shape, ShapeKind::Point) && !lst.contains_key("fillcolor") {
+            fill_color = edge_color.clone();
+        }
+
         if let Option::Some(fx) = lst.get(&"fontsize".to_string()) {
             if let Result::Ok(x) = fx.parse::<usize>() {
                 font_size = x;
@@ -355,27 +1658,60 @@ impl GraphBuilder {
             }
         }
 
-        if let Option::Some(pw) = lst.get(&"width".to_string()) {
-            if let Result::Ok(x) = pw.parse::<usize>() {
-                line_width = x;
+        if let Option::Some(pw) = lst.get("penwidth") {
+            if let Result::Ok(x) = pw.parse::<f64>() {
+                penwidth = x.max(0.);
             } else {
                 #[cfg(feature = "log")]
-                log::info!("Can't parse integer \"{}\"", pw);
+                log::info!("Can't parse number \"{}\"", pw);
             }
         }
 
+        if bold {
+            penwidth = penwidth.max(BOLD_LINE_WIDTH);
+        }
+
         // We flip the orientation before we create the shape. In graphs that
         // grow top down the records grow to the left.
         let dir = dir.flip();
 
-        let sz = get_shape_size(dir, &shape, font_size, make_xy_same);
-        let look = StyleAttr::new(
+        let mut look = StyleAttr::new(
             Color::fast(&edge_color),
-            line_width,
+            penwidth,
             Option::Some(Color::fast(&fill_color)),
             rounded_corder_value,
             font_size,
         );
-        Element::create(shape, look, dir, sz)
+        look.set_dash(&line_style.dash_pattern());
+        look.visible = !matches!(line_style, LineStyleKind::None);
+        if let Option::Some(gradient) = Self::get_gradient_from_attributes(lst)
+        {
+            look.set_gradient(gradient);
+        }
+        look.font_color = self.theme.node_font;
+        self.set_font_from_attributes(lst, &mut look);
+        // The 'margin' attribute sets the space around the label.
+        if let Option::Some(margin) = lst.get("margin") {
+            look.margin = Self::parse_margin(margin);
+        }
+        let mut sz = get_shape_size(dir, &shape, &look, make_xy_same);
+        // The node is large enough to contain its image.
+        let image = self.get_image_from_attributes(lst);
+        if let Option::Some(image) = &image {
+            sz = Point::new(sz.x.max(image.size.x), sz.y.max(image.size.y));
+        }
+        let sz = Self::get_node_size(lst, sz, make_xy_same);
+        let mut elem = Element::create(shape, look, dir, sz);
+        elem.image = image;
+        elem.clip_label = lst.get("labeloverflow").is_some_and(|x| x == "clip");
+        if let Option::Some(p) = lst.get(&"peripheries".to_string()) {
+            if let Result::Ok(x) = p.parse::<usize>() {
+                elem.peripheries = x;
+            } else {
+                #[cfg(feature = "log")]
+                log::info!("Can't parse integer \"{}\"", p);
+            }
+        }
+        elem
     }
 }
@@ -0,0 +1,402 @@
+//! Compares two versions of a graph, and draws them in one graph where the
+//! differences are highlighted. The nodes are matched by their names, and the
+//! edges by their ids, such as "a->b", and the elements are compared by their
+//! resolved attributes, after the default attributes of the scopes are
+//! applied. This is useful to review the changes to a diagram.
+//!
+//! ```rust
+//! use layout::gv::diff::{DiffKind, GraphDiff};
+//! use layout::gv::{DotParser, GraphBuilder};
+//!
+//! let old = DotParser::new("digraph { a -> b; b -> c; }").process();
+//! let mut new = DotParser::new("digraph { a -> b [color=red]; a -> d; }");
+//! let diff = GraphDiff::new(&old.unwrap(), &new.process().unwrap());
+//! assert_eq!(diff.nodes[0].name, "d");
+//! assert_eq!(diff.nodes[0].kind, DiffKind::Added);
+//!
+//! // The added elements are green, the removed elements are red, and the
+//! // changed elements are orange.
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(diff.graph());
+//! let mut vg = gb.get();
+//! vg.layout(false, false);
+//! ```
+
+use crate::gv::builder::{get_edge_ids, GraphBuilder};
+use crate::gv::parser::ast;
+use crate::gv::writer::merge_attributes;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The color of the elements that only the new graph has.
+const ADDED_COLOR: &str = "#2ca02c";
+
+/// The color of the elements that only the old graph has.
+const REMOVED_COLOR: &str = "#d62728";
+
+/// The color of the elements whose attributes changed.
+const CHANGED_COLOR: &str = "#ff7f0e";
+
+/// The way in which an element differs between the two graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl DiffKind {
+    /// \returns the color that highlights the elements of this kind.
+    pub fn color(&self) -> &'static str {
+        match self {
+            DiffKind::Added => ADDED_COLOR,
+            DiffKind::Removed => REMOVED_COLOR,
+            DiffKind::Changed => CHANGED_COLOR,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            DiffKind::Added => '+',
+            DiffKind::Removed => '-',
+            DiffKind::Changed => '~',
+        }
+    }
+}
+
+/// An attribute of a changed element, with its old and its new value. The
+/// value is None if the element doesn't have the attribute in that graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for AttributeChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (Option::Some(old), Option::Some(new)) => {
+                write!(f, "{}=\"{}\" -> \"{}\"", self.name, old, new)
+            }
+            (Option::Some(old), None) => {
+                write!(f, "-{}=\"{}\"", self.name, old)
+            }
+            (None, Option::Some(new)) => {
+                write!(f, "+{}=\"{}\"", self.name, new)
+            }
+            (None, None) => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A node or an edge that differs between the two graphs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementDiff {
+    /// The name of the node, or the id of the edge, such as "a->b".
+    pub name: String,
+    pub kind: DiffKind,
+    /// The attributes that changed, sorted by name. This is empty for the
+    /// elements that were added or removed.
+    pub changes: Vec<AttributeChange>,
+}
+
+/// The differences between two graphs, and a graph that shows them.
+#[derive(Debug, Clone)]
+pub struct GraphDiff {
+    /// The nodes that differ, in the order of the new graph, followed by the
+    /// nodes that were removed, in the order of the old graph.
+    pub nodes: Vec<ElementDiff>,
+    /// The edges that differ, in the same order as the nodes.
+    pub edges: Vec<ElementDiff>,
+    // The new graph, with the removed elements of the old graph.
+    graph: ast::Graph,
+}
+
+impl GraphDiff {
+    /// Compare the graph \p old to the graph \p new.
+    pub fn new(old: &ast::Graph, new: &ast::Graph) -> Self {
+        let mut old_builder = GraphBuilder::new();
+        old_builder.visit_graph(old);
+        let mut new_builder = GraphBuilder::new();
+        new_builder.visit_graph(new);
+
+        let old_nodes: Vec<(String, &HashMap<String, String>)> = old_builder
+            .node_list()
+            .map(|(name, attrs)| (name.to_string(), attrs))
+            .collect();
+        let new_nodes: Vec<(String, &HashMap<String, String>)> = new_builder
+            .node_list()
+            .map(|(name, attrs)| (name.to_string(), attrs))
+            .collect();
+        let old_edges = get_edges(&old_builder);
+        let new_edges = get_edges(&new_builder);
+
+        let nodes = compare(&old_nodes, &new_nodes);
+        let edges = compare(&old_edges, &new_edges);
+        let graph = highlight(&old_builder, &new_builder, &nodes, &edges);
+        Self {
+            nodes,
+            edges,
+            graph,
+        }
+    }
+
+    /// \returns true if the two graphs have the same nodes and edges, with
+    /// the same attributes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    /// \returns the new graph, with the removed nodes and edges of the old
+    /// graph, where the elements that differ are highlighted with the color
+    /// of their DiffKind. The removed elements are dashed.
+    pub fn graph(&self) -> &ast::Graph {
+        &self.graph
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    /// Writes a line for each element that differs, such as
+    /// `~ node "a": color="red" -> "blue"`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let all = self.nodes.iter().map(|x| ("node", x));
+        let all = all.chain(self.edges.iter().map(|x| ("edge", x)));
+        for (kind, elem) in all {
+            write!(f, "{} {} \"{}\"", elem.kind.symbol(), kind, elem.name)?;
+            for (i, change) in elem.changes.iter().enumerate() {
+                let sep = if i == 0 { ": " } else { ", " };
+                write!(f, "{}{}", sep, change)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// \returns the ids and the attributes of the edges of \p builder.
+fn get_edges(
+    builder: &GraphBuilder,
+) -> Vec<(String, &HashMap<String, String>)> {
+    let edges = builder.edge_list();
+    let ids = get_edge_ids(edges.iter().map(|x| (&x.from, &x.to)));
+    ids.into_iter()
+        .zip(edges.iter().map(|x| &x.props))
+        .collect()
+}
+
+/// \returns the elements of \p old and \p new that differ. The elements are
+/// matched by their names.
+fn compare(
+    old: &[(String, &HashMap<String, String>)],
+    new: &[(String, &HashMap<String, String>)],
+) -> Vec<ElementDiff> {
+    let old_map: HashMap<&str, &HashMap<String, String>> = old
+        .iter()
+        .map(|(name, attrs)| (name.as_str(), *attrs))
+        .collect();
+    let new_map: HashMap<&str, &HashMap<String, String>> = new
+        .iter()
+        .map(|(name, attrs)| (name.as_str(), *attrs))
+        .collect();
+
+    let mut res = Vec::new();
+    for (name, attrs) in new.iter() {
+        let (kind, changes) = match old_map.get(name.as_str()) {
+            Option::Some(prev) => {
+                let changes = compare_attributes(prev, attrs);
+                if changes.is_empty() {
+                    continue;
+                }
+                (DiffKind::Changed, changes)
+            }
+            None => (DiffKind::Added, Vec::new()),
+        };
+        res.push(ElementDiff {
+            name: name.clone(),
+            kind,
+            changes,
+        });
+    }
+    for (name, _) in old.iter() {
+        if !new_map.contains_key(name.as_str()) {
+            res.push(ElementDiff {
+                name: name.clone(),
+                kind: DiffKind::Removed,
+                changes: Vec::new(),
+            });
+        }
+    }
+    res
+}
+
+/// \returns the attributes that differ between \p old and \p new, sorted by
+/// name.
+fn compare_attributes(
+    old: &HashMap<String, String>,
+    new: &HashMap<String, String>,
+) -> Vec<AttributeChange> {
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .map(|name| AttributeChange {
+            name: name.clone(),
+            old: old.get(name).cloned(),
+            new: new.get(name).cloned(),
+        })
+        .collect()
+}
+
+/// \returns the attributes \p list, with the colors of \p kind.
+fn add_highlight(
+    list: &ast::AttributeList,
+    kind: DiffKind,
+) -> ast::AttributeList {
+    let mut extra = vec![
+        ("color".to_string(), kind.color().to_string()),
+        ("fontcolor".to_string(), kind.color().to_string()),
+        ("penwidth".to_string(), "2".to_string()),
+    ];
+    if kind == DiffKind::Removed {
+        extra.push(("style".to_string(), "dashed".to_string()));
+    }
+    merge_attributes(list.list.clone(), extra)
+}
+
+/// \returns the canonical graph of \p new_builder, with the removed nodes
+/// and edges of \p old_builder, where the elements of \p nodes and \p edges
+/// are highlighted.
+fn highlight(
+    old_builder: &GraphBuilder,
+    new_builder: &GraphBuilder,
+    nodes: &[ElementDiff],
+    edges: &[ElementDiff],
+) -> ast::Graph {
+    let kinds = |list: &[ElementDiff]| -> HashMap<String, DiffKind> {
+        list.iter().map(|x| (x.name.clone(), x.kind)).collect()
+    };
+    let node_kinds = kinds(nodes);
+    let edge_kinds = kinds(edges);
+
+    // Highlight the nodes and the edges that were added or changed, in the
+    // order of the statements of the canonical graph.
+    let mut graph = new_builder.to_ast(None);
+    let new_ids = get_edges(new_builder);
+    let mut new_ids = new_ids.iter().map(|x| &x.0);
+    for stmt in graph.list.list.iter_mut() {
+        match stmt {
+            ast::Stmt::Node(node) => {
+                if let Option::Some(kind) = node_kinds.get(&node.id.name) {
+                    node.list = add_highlight(&node.list, *kind);
+                }
+            }
+            ast::Stmt::Edge(edge) => {
+                let id = new_ids.next().unwrap();
+                if let Option::Some(kind) = edge_kinds.get(id) {
+                    edge.list = add_highlight(&edge.list, *kind);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Add the nodes and the edges that were removed. The removed nodes are
+    // declared after the other nodes, and before the clusters.
+    let old_graph = old_builder.to_ast(None);
+    let old_ids = get_edges(old_builder);
+    let mut old_ids = old_ids.iter().map(|x| &x.0);
+    let mut pos = graph
+        .list
+        .list
+        .iter()
+        .rposition(|x| matches!(x, ast::Stmt::Node(_)))
+        .map_or(0, |x| x + 1);
+    for stmt in old_graph.list.list.iter() {
+        match stmt {
+            ast::Stmt::Node(node) => {
+                let kind = node_kinds.get(&node.id.name);
+                if kind == Some(&DiffKind::Removed) {
+                    let mut node = node.clone();
+                    node.list = add_highlight(&node.list, DiffKind::Removed);
+                    graph.list.list.insert(pos, ast::Stmt::Node(node));
+                    pos += 1;
+                }
+            }
+            ast::Stmt::Edge(edge) => {
+                let id = old_ids.next().unwrap();
+                if edge_kinds.get(id) == Some(&DiffKind::Removed) {
+                    let mut edge = edge.clone();
+                    edge.list = add_highlight(&edge.list, DiffKind::Removed);
+                    graph.list.list.push(ast::Stmt::Edge(edge));
+                }
+            }
+            _ => {}
+        }
+    }
+    graph
+}
+
+#[test]
+fn test_graph_diff() {
+    use crate::gv::DotParser;
+    let old = "digraph { node [shape=box]; a -> b; b -> c; a -> b; c; }";
+    let new = "digraph { node [shape=box]; a -> b; b -> d; a -> b [color=blue];
+        c [shape=circle]; }";
+    let old = DotParser::new(old).process().unwrap();
+    let new = DotParser::new(new).process().unwrap();
+    let diff = GraphDiff::new(&old, &new);
+    assert!(!diff.is_empty());
+
+    let nodes: Vec<(&str, DiffKind)> = diff
+        .nodes
+        .iter()
+        .map(|x| (x.name.as_str(), x.kind))
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![("d", DiffKind::Added), ("c", DiffKind::Changed)]
+    );
+    let change = &diff.nodes[1].changes[0];
+    assert_eq!(change.old.as_deref(), Some("box"));
+    assert_eq!(change.new.as_deref(), Some("circle"));
+
+    // The second edge between a and b is a different edge.
+    let edges: Vec<(&str, DiffKind)> = diff
+        .edges
+        .iter()
+        .map(|x| (x.name.as_str(), x.kind))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            ("b->d", DiffKind::Added),
+            ("a->b:1", DiffKind::Changed),
+            ("b->c", DiffKind::Removed),
+        ]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "+ node \"d\"\n\
+         ~ node \"c\": shape=\"box\" -> \"circle\"\n\
+         + edge \"b->d\"\n\
+         ~ edge \"a->b:1\": +color=\"blue\"\n\
+         - edge \"b->c\"\n"
+    );
+
+    // The combined graph has the elements of both graphs.
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(diff.graph());
+    assert_eq!(gb.node_list().count(), 4);
+    let edges = gb.edge_list();
+    assert_eq!(edges.len(), 4);
+    assert_eq!(edges[3].props["color"], REMOVED_COLOR);
+    assert_eq!(edges[3].props["style"], "dashed");
+    assert_eq!(edges[2].props["color"], CHANGED_COLOR);
+    assert!(!edges[0].props.contains_key("color"));
+
+    // Identical graphs have no differences.
+    assert!(GraphDiff::new(&old, &old).is_empty());
+}
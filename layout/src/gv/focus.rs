@@ -0,0 +1,421 @@
+//! Extracts the neighborhood of some nodes from a graph, to explore large
+//! graphs one part at a time. The nodes within a number of hops from the
+//! focused nodes are kept, and the rest of the graph is hidden, faded, or
+//! collapsed into a node for each group of connected nodes.
+//!
+//! ```rust
+//! use layout::gv::focus::{focus_graph, ContextMode, FocusConfig};
+//! use layout::gv::{DotParser, GraphBuilder};
+//!
+//! let program = "digraph { a -> b -> c -> d -> e; }";
+//! let graph = DotParser::new(program).process().unwrap();
+//! let mut config = FocusConfig::new(1);
+//! config.context = ContextMode::Collapse;
+//! let focus = focus_graph(&graph, &["c".to_string()], &config);
+//!
+//! // The nodes b, c and d, and a node for a and for e.
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(&focus);
+//! let mut vg = gb.get();
+//! assert_eq!(vg.num_nodes(), 5);
+//! vg.layout(false, false);
+//! ```
+
+use crate::gv::builder::GraphBuilder;
+use crate::gv::parser::ast;
+use crate::gv::writer::merge_attributes;
+use std::collections::{HashMap, HashSet};
+
+/// The color of the nodes and edges that are outside of the focus.
+const FADED_COLOR: &str = "#c8c8c8";
+
+/// The fill color of the filled nodes that are outside of the focus.
+const FADED_FILL_COLOR: &str = "#f2f2f2";
+
+/// The prefix of the names of the nodes that stand for the collapsed parts of
+/// the graph.
+const COLLAPSED_PREFIX: &str = "collapsed";
+
+/// Selects the edges that the neighborhood follows, in directed graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    /// Follow the edges to the successors of the nodes.
+    Out,
+    /// Follow the edges to the predecessors of the nodes.
+    In,
+    /// Follow the edges in both directions.
+    Both,
+}
+
+/// Selects how the nodes outside of the focus are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextMode {
+    /// Only draw the nodes in the focus, and the edges between them.
+    Hide,
+    /// Draw the whole graph, with the nodes outside of the focus in light
+    /// colors.
+    Fade,
+    /// Replace each connected group of nodes outside of the focus with one
+    /// node, that is connected to the nodes in the focus that it touches.
+    Collapse,
+}
+
+impl ContextMode {
+    /// \returns the mode with the name \p name, such as "fade".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hide" => Some(ContextMode::Hide),
+            "fade" => Some(ContextMode::Fade),
+            "collapse" => Some(ContextMode::Collapse),
+            _ => None,
+        }
+    }
+}
+
+/// Controls the neighborhood that is extracted, and how the rest of the
+/// graph is drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct FocusConfig {
+    /// The largest number of edges between a node of the neighborhood and the
+    /// closest focused node.
+    pub hops: usize,
+    pub direction: FocusDirection,
+    pub context: ContextMode,
+}
+
+impl FocusConfig {
+    pub fn new(hops: usize) -> Self {
+        Self {
+            hops,
+            direction: FocusDirection::Both,
+            context: ContextMode::Hide,
+        }
+    }
+}
+
+/// \returns the names of the nodes of \p graph that \p filter accepts, in the
+/// order of the graph. The filter is called with the name and the resolved
+/// attributes of each node.
+pub fn find_nodes(
+    graph: &ast::Graph,
+    filter: impl Fn(&str, &HashMap<String, String>) -> bool,
+) -> Vec<String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    gb.node_list()
+        .filter(|(name, attrs)| filter(name, attrs))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// \returns the names of the nodes of the builder \p gb that are at most
+/// \p hops edges in the direction \p direction away from the nodes \p roots,
+/// in the order of the graph.
+fn get_neighborhood_in(
+    gb: &GraphBuilder,
+    roots: &[String],
+    hops: usize,
+    direction: FocusDirection,
+) -> Vec<String> {
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in gb.edge_list() {
+        let (from, to) = (edge.from.as_str(), edge.to.as_str());
+        // Undirected edges are followed in both directions.
+        let both = direction == FocusDirection::Both || !edge.is_directed;
+        if both || direction == FocusDirection::Out {
+            adjacent.entry(from).or_default().push(to);
+        }
+        if both || direction == FocusDirection::In {
+            adjacent.entry(to).or_default().push(from);
+        }
+    }
+
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut frontier: Vec<&str> = Vec::new();
+    for (name, _) in gb.node_list() {
+        if roots.iter().any(|x| x == name) {
+            seen.insert(name);
+            frontier.push(name);
+        }
+    }
+    for _ in 0..hops {
+        let mut next = Vec::new();
+        for name in frontier {
+            for to in adjacent.get(name).into_iter().flatten() {
+                if seen.insert(to) {
+                    next.push(*to);
+                }
+            }
+        }
+        frontier = next;
+    }
+    gb.node_list()
+        .map(|(name, _)| name)
+        .filter(|name| seen.contains(name))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// \returns the names of the nodes of \p graph that are at most \p hops
+/// edges in the direction \p direction away from the nodes \p roots, in the
+/// order of the graph. The roots that are not in the graph are ignored.
+pub fn get_neighborhood(
+    graph: &ast::Graph,
+    roots: &[String],
+    hops: usize,
+    direction: FocusDirection,
+) -> Vec<String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    get_neighborhood_in(&gb, roots, hops, direction)
+}
+
+/// \returns the attributes \p list, with the light colors of the nodes and
+/// edges outside of the focus.
+fn fade(list: &ast::AttributeList) -> ast::AttributeList {
+    let mut extra = vec![
+        ("color".to_string(), FADED_COLOR.to_string()),
+        ("fontcolor".to_string(), FADED_COLOR.to_string()),
+    ];
+    if list.iter().any(|x| x.0 == "fillcolor") {
+        extra.push(("fillcolor".to_string(), FADED_FILL_COLOR.to_string()));
+    }
+    merge_attributes(list.list.clone(), extra)
+}
+
+/// Hide or fade the nodes of the statements \p list that are not in \p keep,
+/// and the edges that leave \p keep, with \p mode. Hidden subgraphs that are
+/// left without nodes are removed.
+fn filter_statements(
+    list: &mut Vec<ast::Stmt>,
+    keep: &HashSet<String>,
+    mode: ContextMode,
+) {
+    let fade_all = mode == ContextMode::Fade;
+    list.retain_mut(|stmt| match stmt {
+        ast::Stmt::Node(node) => {
+            if keep.contains(&node.id.name) {
+                return true;
+            }
+            if fade_all {
+                node.list = fade(&node.list);
+            }
+            fade_all
+        }
+        ast::Stmt::Edge(edge) => {
+            let mut names = edge.to.iter().map(|x| &x.0.name);
+            if keep.contains(&edge.from.name) && names.all(|x| keep.contains(x))
+            {
+                return true;
+            }
+            if fade_all {
+                edge.list = fade(&edge.list);
+            }
+            fade_all
+        }
+        ast::Stmt::SubGraph(graph) => {
+            filter_statements(&mut graph.list.list, keep, mode);
+            fade_all
+                || graph.list.list.iter().any(|x| {
+                    matches!(x, ast::Stmt::Node(_) | ast::Stmt::SubGraph(_))
+                })
+        }
+        ast::Stmt::Attribute(_) => true,
+    });
+}
+
+/// \returns the graph \p graph, where the nodes that are at most
+/// config.hops edges away from the nodes \p roots are in focus, and the
+/// rest of the graph is drawn with config.context. The graph is written
+/// with the resolved attributes of each element, like GraphBuilder::to_dot.
+pub fn focus_graph(
+    graph: &ast::Graph,
+    roots: &[String],
+    config: &FocusConfig,
+) -> ast::Graph {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    let names = get_neighborhood_in(&gb, roots, config.hops, config.direction);
+    let keep: HashSet<String> = names.into_iter().collect();
+
+    let mut res = gb.to_ast(None);
+    filter_statements(&mut res.list.list, &keep, config.context);
+    if config.context == ContextMode::Collapse {
+        add_collapsed_nodes(&gb, &keep, &mut res);
+    }
+    res
+}
+
+/// Add a node to \p graph for each connected group of the nodes of \p gb
+/// that are not in \p keep, with a label that counts its nodes, and connect
+/// it to the nodes in \p keep that the group is connected to.
+fn add_collapsed_nodes(
+    gb: &GraphBuilder,
+    keep: &HashSet<String>,
+    graph: &mut ast::Graph,
+) {
+    // Group the hidden nodes by their connected component.
+    let hidden: Vec<&str> = gb
+        .node_list()
+        .map(|(name, _)| name)
+        .filter(|name| !keep.contains(*name))
+        .collect();
+    let index: HashMap<&str, usize> =
+        hidden.iter().enumerate().map(|(i, x)| (*x, i)).collect();
+    let mut parent: Vec<usize> = (0..hidden.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for edge in gb.edge_list() {
+        let from = index.get(edge.from.as_str());
+        let to = index.get(edge.to.as_str());
+        if let (Option::Some(from), Option::Some(to)) = (from, to) {
+            let (a, b) = (find(&mut parent, *from), find(&mut parent, *to));
+            parent[a] = b;
+        }
+    }
+
+    // Number the groups in the order of their first node, and give them
+    // names that are not used by the graph.
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    let mut group_of = Vec::with_capacity(hidden.len());
+    for i in 0..hidden.len() {
+        let root = find(&mut parent, i);
+        let idx = match groups.iter().position(|x| x.0 == root) {
+            Option::Some(idx) => idx,
+            None => {
+                groups.push((root, 0));
+                groups.len() - 1
+            }
+        };
+        groups[idx].1 += 1;
+        group_of.push(idx);
+    }
+    let mut names = Vec::new();
+    let mut next = 0;
+    for _ in groups.iter() {
+        let mut name = format!("{}_{}", COLLAPSED_PREFIX, next);
+        while gb.node_list().any(|(x, _)| x == name) {
+            next += 1;
+            name = format!("{}_{}", COLLAPSED_PREFIX, next);
+        }
+        next += 1;
+        names.push(name);
+    }
+
+    let list = &mut graph.list.list;
+    let pos = list
+        .iter()
+        .rposition(|x| matches!(x, ast::Stmt::Node(_)))
+        .map_or(0, |x| x + 1);
+    for (i, (_, count)) in groups.iter().enumerate().rev() {
+        let label = match count {
+            1 => "1 more node".to_string(),
+            _ => format!("{} more nodes", count),
+        };
+        let mut attrs = ast::AttributeList::new();
+        attrs.add_attr("label", &label);
+        attrs.add_attr("shape", "box");
+        attrs.add_attr("style", "dashed");
+        attrs.add_attr("color", FADED_COLOR);
+        attrs.add_attr("fontcolor", "gray");
+        let id = ast::NodeId::new(&names[i], &None);
+        let stmt = ast::NodeStmt::new_with_list(id, attrs);
+        list.insert(pos, ast::Stmt::Node(stmt));
+    }
+
+    // Connect the groups to the nodes in focus, once for each pair of nodes
+    // and direction.
+    let mut added: HashSet<(String, String)> = HashSet::new();
+    for edge in gb.edge_list() {
+        let from = index.get(edge.from.as_str()).map(|x| &names[group_of[*x]]);
+        let to = index.get(edge.to.as_str()).map(|x| &names[group_of[*x]]);
+        let (from, to) = match (from, to) {
+            (Option::Some(from), None) => (from.clone(), edge.to.clone()),
+            (None, Option::Some(to)) => (edge.from.clone(), to.clone()),
+            _ => continue,
+        };
+        if !added.insert((from.clone(), to.clone())) {
+            continue;
+        }
+        let kind = if edge.is_directed {
+            ast::ArrowKind::Arrow
+        } else {
+            ast::ArrowKind::Line
+        };
+        let mut stmt = ast::EdgeStmt::new(ast::NodeId::new(&from, &None));
+        stmt.insert(ast::NodeId::new(&to, &None), kind);
+        stmt.list.add_attr("style", "dashed");
+        stmt.list.add_attr("color", FADED_COLOR);
+        list.push(ast::Stmt::Edge(stmt));
+    }
+}
+
+#[test]
+fn test_focus_graph() {
+    use crate::gv::DotParser;
+    let program = "digraph { a -> b -> c -> d -> e; x -> c;
+        subgraph cluster_y { y -> z; } c -> y; }";
+    let graph = DotParser::new(program).process().unwrap();
+    let roots = vec!["c".to_string()];
+
+    let all = FocusDirection::Both;
+    let names = get_neighborhood(&graph, &roots, 1, all);
+    assert_eq!(names, vec!["b", "c", "d", "x", "y"]);
+    let names = get_neighborhood(&graph, &roots, 2, FocusDirection::Out);
+    assert_eq!(names, vec!["c", "d", "e", "y", "z"]);
+    let names = get_neighborhood(&graph, &roots, 0, FocusDirection::In);
+    assert_eq!(names, vec!["c"]);
+    let names = find_nodes(&graph, |name, _| name > "w");
+    assert_eq!(names, vec!["x", "y", "z"]);
+
+    // The hidden nodes are removed, with their edges.
+    let mut config = FocusConfig::new(1);
+    config.direction = FocusDirection::In;
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(&focus_graph(&graph, &roots, &config));
+    let nodes: Vec<&str> = gb.node_list().map(|x| x.0).collect();
+    assert_eq!(nodes, vec!["b", "c", "x"]);
+    assert_eq!(gb.edge_list().len(), 2);
+
+    // The faded nodes keep their place.
+    config.context = ContextMode::Fade;
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(&focus_graph(&graph, &roots, &config));
+    let colors: Vec<Option<&String>> =
+        gb.node_list().map(|x| x.1.get("color")).collect();
+    assert_eq!(colors.len(), 8);
+    assert_eq!(colors[0].map(|x| x.as_str()), Some(FADED_COLOR));
+    assert_eq!(colors[2], None);
+
+    // The nodes a, the nodes d and e, and the nodes y and z are groups,
+    // that are connected to b and to c.
+    config.context = ContextMode::Collapse;
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(&focus_graph(&graph, &roots, &config));
+    let nodes: Vec<(&str, Option<&String>)> =
+        gb.node_list().map(|x| (x.0, x.1.get("label"))).collect();
+    let more = "2 more nodes".to_string();
+    assert_eq!(nodes.len(), 6);
+    assert_eq!(nodes[3], ("collapsed_0", Some(&"1 more node".to_string())));
+    assert_eq!(nodes[5], ("collapsed_2", Some(&more)));
+    let edges: Vec<(&str, &str)> = gb
+        .edge_list()
+        .iter()
+        .map(|x| (x.from.as_str(), x.to.as_str()))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            ("b", "c"),
+            ("x", "c"),
+            ("collapsed_0", "b"),
+            ("c", "collapsed_1"),
+            ("c", "collapsed_2"),
+        ]
+    );
+}
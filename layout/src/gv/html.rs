@@ -0,0 +1,426 @@
+//! A parser for Graphviz HTML-like labels, such as:
+//! <<TABLE BORDER="0"><TR><TD PORT="f0" BGCOLOR="yellow">a</TD></TR></TABLE>>
+//!
+//! The parser supports tables with rows and cells, text with line breaks and
+//! the FONT element. Other formatting elements, such as B or I, keep their
+//! text and drop the formatting.
+
+use crate::core::color::Color;
+use crate::std_shapes::shapes::*;
+
+/// A tag, such as <TD PORT="a">, </TD> or <BR/>.
+#[derive(Debug)]
+struct Tag {
+    name: String,
+    attrs: Vec<(String, String)>,
+    is_close: bool,
+    is_self_closing: bool,
+}
+
+impl Tag {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn get_usize(&self, name: &str) -> Option<usize> {
+        self.get(name).and_then(|value| value.trim().parse().ok())
+    }
+
+    fn get_color(&self, name: &str) -> Option<Color> {
+        self.get(name).and_then(Color::from_name)
+    }
+}
+
+#[derive(Debug)]
+enum Item {
+    Text(String),
+    Tag(Tag),
+}
+
+struct HtmlParser {
+    input: Vec<char>,
+    pos: usize,
+}
+
+impl HtmlParser {
+    fn new(input: &str) -> Self {
+        Self {
+            input: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input.get(self.pos).cloned()
+    }
+
+    /// Decode the entity that starts at the current '&', such as "&amp;".
+    fn read_entity(&mut self) -> char {
+        let rest: String = self.input[self.pos..].iter().take(8).collect();
+        if let Option::Some(end) = rest.find(';') {
+            let ch = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                name => name
+                    .strip_prefix('#')
+                    .and_then(|num| num.parse::<u32>().ok())
+                    .and_then(char::from_u32),
+            };
+            if let Option::Some(ch) = ch {
+                self.pos += rest[..=end].chars().count();
+                return ch;
+            }
+        }
+        self.pos += 1;
+        '&'
+    }
+
+    fn read_tag(&mut self) -> Result<Tag, String> {
+        // Consume the '<'.
+        self.pos += 1;
+        let mut tag = Tag {
+            name: String::new(),
+            attrs: Vec::new(),
+            is_close: false,
+            is_self_closing: false,
+        };
+        if self.peek_char() == Some('/') {
+            tag.is_close = true;
+            self.pos += 1;
+        }
+        tag.name = self.read_name();
+        loop {
+            self.skip_whitespace();
+            match self.peek_char() {
+                Option::Some('>') => {
+                    self.pos += 1;
+                    return Ok(tag);
+                }
+                Option::Some('/') => {
+                    tag.is_self_closing = true;
+                    self.pos += 1;
+                }
+                Option::Some(_) => {
+                    let key = self.read_name();
+                    if key.is_empty() {
+                        return Err(format!(
+                            "Unexpected char in <{}>",
+                            tag.name
+                        ));
+                    }
+                    self.skip_whitespace();
+                    let mut value = String::new();
+                    if self.peek_char() == Some('=') {
+                        self.pos += 1;
+                        self.skip_whitespace();
+                        value = self.read_value();
+                    }
+                    tag.attrs.push((key, value));
+                }
+                Option::None => {
+                    return Err(format!("Unterminated tag <{}", tag.name));
+                }
+            }
+        }
+    }
+
+    /// Read a tag or attribute name. Names are case insensitive, and are
+    /// converted to lower case.
+    fn read_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Option::Some(ch) = self.peek_char() {
+            if !ch.is_ascii_alphanumeric() && ch != '-' && ch != '_' {
+                break;
+            }
+            name.push(ch.to_ascii_lowercase());
+            self.pos += 1;
+        }
+        name
+    }
+
+    fn read_value(&mut self) -> String {
+        let mut value = String::new();
+        let quote = match self.peek_char() {
+            Option::Some(ch) if ch == '"' || ch == '\'' => {
+                self.pos += 1;
+                Some(ch)
+            }
+            _ => None,
+        };
+        while let Option::Some(ch) = self.peek_char() {
+            match quote {
+                Option::Some(q) if ch == q => {
+                    self.pos += 1;
+                    break;
+                }
+                Option::None
+                    if ch.is_whitespace() || ch == '>' || ch == '/' =>
+                {
+                    break;
+                }
+                _ => {}
+            }
+            if ch == '&' {
+                value.push(self.read_entity());
+            } else {
+                value.push(ch);
+                self.pos += 1;
+            }
+        }
+        value
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek_char().is_some_and(|ch| ch.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// \returns the next text run or tag, or None at the end of the input.
+    fn next_item(&mut self) -> Result<Option<Item>, String> {
+        match self.peek_char() {
+            Option::None => Ok(None),
+            Option::Some('<') => Ok(Some(Item::Tag(self.read_tag()?))),
+            Option::Some(_) => {
+                let mut text = String::new();
+                while let Option::Some(ch) = self.peek_char() {
+                    match ch {
+                        '<' => break,
+                        '&' => text.push(self.read_entity()),
+                        _ => {
+                            text.push(ch);
+                            self.pos += 1;
+                        }
+                    }
+                }
+                Ok(Some(Item::Text(text)))
+            }
+        }
+    }
+
+    /// Parse the label until the closing tag \p end, or the end of the input
+    /// if \p end is None. The text uses the font size \p font_size.
+    fn parse_label(
+        &mut self,
+        end: Option<&str>,
+        font_size: Option<usize>,
+    ) -> Result<HtmlLabel, String> {
+        let mut lines: Vec<Vec<HtmlText>> = vec![Vec::new()];
+        let mut table: Option<HtmlTable> = None;
+        // The font sizes of the open FONT elements.
+        let mut fonts: Vec<Option<usize>> = vec![font_size];
+        // The names of the open formatting elements.
+        let mut open: Vec<String> = Vec::new();
+
+        loop {
+            let item = match self.next_item()? {
+                Option::Some(item) => item,
+                Option::None => {
+                    if let Option::Some(end) = end {
+                        return Err(format!("Missing </{}>", end));
+                    }
+                    break;
+                }
+            };
+            match item {
+                Item::Text(text) => {
+                    // Whitespace around tables and between lines is not
+                    // part of the label.
+                    let text = text.replace(['\n', '\r', '\t'], " ");
+                    if text.trim().is_empty()
+                        && (table.is_some() || lines.last().unwrap().is_empty())
+                    {
+                        continue;
+                    }
+                    if table.is_some() {
+                        return Err("Text next to a table".to_string());
+                    }
+                    let font_size = *fonts.last().unwrap();
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push(HtmlText { text, font_size });
+                }
+                Item::Tag(tag) if tag.is_close => {
+                    if Some(tag.name.as_str()) == end && open.is_empty() {
+                        break;
+                    }
+                    if open.pop().as_deref() != Some(tag.name.as_str()) {
+                        return Err(format!("Unexpected </{}>", tag.name));
+                    }
+                    if tag.name == "font" {
+                        fonts.pop();
+                    }
+                }
+                Item::Tag(tag) => match tag.name.as_str() {
+                    "br" => lines.push(Vec::new()),
+                    "table" => {
+                        if table.is_some()
+                            || lines.iter().any(|l| !l.is_empty())
+                        {
+                            return Err("Text next to a table".to_string());
+                        }
+                        let font_size = *fonts.last().unwrap();
+                        table = Some(self.parse_table(&tag, font_size)?);
+                    }
+                    _ if tag.is_self_closing => {}
+                    "font" => {
+                        let size = tag.get_usize("point-size");
+                        fonts.push(size.or(*fonts.last().unwrap()));
+                        open.push(tag.name);
+                    }
+                    "b" | "i" | "u" | "o" | "s" | "sub" | "sup" => {
+                        open.push(tag.name)
+                    }
+                    _ => return Err(format!("Unexpected <{}>", tag.name)),
+                },
+            }
+        }
+
+        if let Option::Some(table) = table {
+            return Ok(HtmlLabel::Table(table));
+        }
+        // Drop the empty line that follows a trailing <BR/>.
+        if lines.len() > 1 && lines.last().unwrap().is_empty() {
+            lines.pop();
+        }
+        Ok(HtmlLabel::Text(lines))
+    }
+
+    fn parse_table(
+        &mut self,
+        tag: &Tag,
+        font_size: Option<usize>,
+    ) -> Result<HtmlTable, String> {
+        let mut table = HtmlTable::new();
+        if let Option::Some(border) = tag.get_usize("border") {
+            table.border = border;
+        }
+        table.cell_border = tag.get_usize("cellborder");
+        if let Option::Some(spacing) = tag.get_usize("cellspacing") {
+            table.cell_spacing = spacing;
+        }
+        if let Option::Some(padding) = tag.get_usize("cellpadding") {
+            table.cell_padding = padding;
+        }
+        table.bgcolor = tag.get_color("bgcolor");
+        table.color = tag.get_color("color");
+
+        loop {
+            self.skip_whitespace();
+            let tag = match self.next_item()? {
+                Option::Some(Item::Tag(tag)) => tag,
+                Option::Some(Item::Text(_)) => {
+                    return Err("Text outside of a cell".to_string());
+                }
+                Option::None => return Err("Missing </table>".to_string()),
+            };
+            match (tag.name.as_str(), tag.is_close) {
+                ("table", true) => return Ok(table),
+                ("tr", false) => table.rows.push(Vec::new()),
+                ("tr", true) | ("hr", _) | ("vr", _) => {}
+                ("td", false) => {
+                    let row = match table.rows.last_mut() {
+                        Option::Some(row) => row,
+                        Option::None => {
+                            return Err("<td> outside of <tr>".into())
+                        }
+                    };
+                    let content = if tag.is_self_closing {
+                        HtmlLabel::Text(vec![Vec::new()])
+                    } else {
+                        self.parse_label(Some("td"), font_size)?
+                    };
+                    row.push(HtmlCell {
+                        content,
+                        port: tag.get("port").map(|port| port.to_string()),
+                        bgcolor: tag.get_color("bgcolor"),
+                        border: tag.get_usize("border"),
+                        colspan: tag.get_usize("colspan").unwrap_or(1).max(1),
+                        rowspan: tag.get_usize("rowspan").unwrap_or(1).max(1),
+                    });
+                }
+                (name, _) => {
+                    return Err(format!("Unexpected <{}> in a table", name));
+                }
+            }
+        }
+    }
+}
+
+/// \returns true if the label \p label is an HTML-like label. The lexer keeps
+/// the angle brackets around these labels, like in <<B>text</B>>, so quoted
+/// strings that start with '<' and end with '>' look the same.
+pub fn is_html_label(label: &str) -> bool {
+    label.len() >= 2 && label.starts_with('<') && label.ends_with('>')
+}
+
+/// Parse the HTML-like label \p label, with or without the outer angle
+/// brackets. \returns an error message if the label is malformed.
+pub fn parse_html_label(label: &str) -> Result<HtmlLabel, String> {
+    let label = if is_html_label(label) {
+        &label[1..label.len() - 1]
+    } else {
+        label
+    };
+    HtmlParser::new(label).parse_label(None, None)
+}
+
+/// Construct a shape from an HTML-like label. Malformed labels are displayed
+/// as text.
+pub fn html_builder(label: &str) -> ShapeKind {
+    match parse_html_label(label) {
+        Result::Ok(html) => ShapeKind::Html(html),
+        Result::Err(_err) => {
+            #[cfg(feature = "log")]
+            log::info!("Can't parse the HTML label: {}", _err);
+            ShapeKind::Box(label.to_string())
+        }
+    }
+}
+
+#[test]
+fn parse_html_labels() {
+    let text = |s: &str, font_size: Option<usize>| HtmlText {
+        text: s.to_string(),
+        font_size,
+    };
+
+    let res = parse_html_label("<a<BR/><font point-size=\"20\">b</font> c>");
+    let expected = HtmlLabel::Text(vec![
+        vec![text("a", None)],
+        vec![text("b", Some(20)), text(" c", None)],
+    ]);
+    assert_eq!(res, Ok(expected));
+
+    let res = parse_html_label("<x &amp; &lt;y&gt;>").unwrap();
+    assert_eq!(res.to_plain_text(), "x & <y>");
+
+    let res = parse_html_label(
+        "<<table border='0' cellborder=\"1\"><tr><td port=\"p\" \
+         bgcolor=\"red\">a</td><td colspan=\"2\"><b>b</b></td></tr>\
+         <tr><td/></tr></table>>",
+    );
+    if let Result::Ok(HtmlLabel::Table(table)) = res {
+        assert_eq!(table.border, 0);
+        assert_eq!(table.cell_border, Some(1));
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][0].port, Some("p".to_string()));
+        assert_eq!(table.rows[0][0].bgcolor, Color::from_name("red"));
+        assert_eq!(table.rows[0][1].colspan, 2);
+        assert_eq!(table.rows[0][1].content.to_plain_text(), "b");
+    } else {
+        panic!("Expected a table, got {:?}", res);
+    }
+
+    assert!(parse_html_label("<<table><tr><td>a</tr></table>>").is_err());
+    assert!(parse_html_label("<a <table></table>>").is_err());
+    assert!(parse_html_label("<<b>a</i>>").is_err());
+}
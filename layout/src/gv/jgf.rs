@@ -0,0 +1,284 @@
+//! Reads and writes graphs in the JSON Graph Format (JGF), that describes the
+//! nodes and the edges of a graph with their metadata. See
+//! <https://jsongraphformat.info>. The graphs are read into the same AST that
+//! the DOT parser builds, so the metadata of the nodes and the edges are
+//! treated as DOT attributes, such as "shape" or "color". Clusters and rank
+//! constraints have no JGF representation, and are not written.
+//!
+//! ```rust
+//! use layout::gv::jgf::{parse_jgf, write_jgf};
+//! use layout::gv::GraphBuilder;
+//!
+//! let text = r#"{"graph": {"nodes": {"a": {}, "b": {"label": "B"}},
+//!     "edges": [{"source": "a", "target": "b"}]}}"#;
+//! let graph = parse_jgf(text).unwrap();
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(&graph);
+//! let mut vg = gb.get();
+//! vg.layout(false, false);
+//! let json = write_jgf(&gb, Some(&vg.layout_result()));
+//! ```
+
+use crate::core::geometry::Point;
+use crate::core::json::{format_json_number, JsonValue};
+use crate::gv::builder::GraphBuilder;
+use crate::gv::parser::ast;
+use crate::topo::layout::LayoutResult;
+use std::collections::HashMap;
+
+/// Parse the JGF document \p input. Documents with a list of graphs select
+/// the first graph. \returns the AST of the graph, or an error message.
+pub fn parse_jgf(input: &str) -> Result<ast::Graph, String> {
+    let doc = JsonValue::parse(input)?;
+    let graph = match (doc.get("graph"), doc.get("graphs")) {
+        (Option::Some(graph), _) => graph,
+        (None, Option::Some(JsonValue::Array(graphs))) => {
+            graphs.first().ok_or("The list of graphs is empty")?
+        }
+        _ => return Err("Expected a \"graph\" or \"graphs\" member".into()),
+    };
+    if graph.as_object().is_none() {
+        return Err("Expected the graph to be an object".into());
+    }
+
+    let name = graph.get("id").and_then(|x| x.as_str()).unwrap_or("");
+    let mut res = ast::Graph::new(name);
+    let directed = graph.get("directed").and_then(|x| x.as_bool());
+    let directed = directed.unwrap_or(true);
+
+    let mut attrs = get_attributes(graph)?;
+    if let Option::Some(label) = graph.get("label").and_then(|x| x.as_str()) {
+        attrs.add_attr("label", label);
+    }
+    if !attrs.list.is_empty() {
+        let stmt = ast::AttrStmt::new(ast::AttrStmtTarget::Graph, attrs);
+        res.list.list.push(ast::Stmt::Attribute(stmt));
+    }
+
+    // The nodes are a map from their ids, or a list of nodes with an 'id'
+    // member in the first version of the format.
+    let mut nodes: Vec<(String, &JsonValue)> = Vec::new();
+    match graph.get("nodes") {
+        Option::Some(JsonValue::Object(members)) => {
+            for (id, node) in members {
+                nodes.push((id.clone(), node));
+            }
+        }
+        Option::Some(JsonValue::Array(items)) => {
+            for node in items {
+                let id = node.get("id").and_then(get_scalar);
+                let id = id.ok_or("Expected the node to have an id")?;
+                nodes.push((id, node));
+            }
+        }
+        None => {}
+        _ => return Err("Expected the nodes to be an object".into()),
+    }
+    for (id, node) in nodes {
+        let mut attrs = get_attributes(node)?;
+        if let Option::Some(label) = node.get("label").and_then(get_scalar) {
+            attrs.add_attr("label", &label);
+        }
+        let id = ast::NodeId::new(&id, &None);
+        let stmt = ast::NodeStmt::new_with_list(id, attrs);
+        res.list.list.push(ast::Stmt::Node(stmt));
+    }
+
+    let edges = match graph.get("edges") {
+        Option::Some(edges) => {
+            edges.as_array().ok_or("Expected a list of edges")?
+        }
+        None => &[],
+    };
+    for edge in edges {
+        let end = |key: &str| {
+            let msg = format!("Expected the edge to have a {}", key);
+            edge.get(key).and_then(get_scalar).ok_or(msg)
+        };
+        let (source, target) = (end("source")?, end("target")?);
+        let mut attrs = get_attributes(edge)?;
+        if let Option::Some(label) = edge.get("label").and_then(get_scalar) {
+            attrs.add_attr("label", &label);
+        }
+
+        // The ports of the ends are written as 'a:p' in DOT.
+        let mut port = |key: &str| {
+            let idx = attrs.list.iter().position(|x| x.0 == key)?;
+            Some(attrs.list.remove(idx).1)
+        };
+        let from = ast::NodeId::new(&source, &port("tailport"));
+        let to = ast::NodeId::new(&target, &port("headport"));
+
+        let is_directed = edge.get("directed").and_then(|x| x.as_bool());
+        let kind = if is_directed.unwrap_or(directed) {
+            ast::ArrowKind::Arrow
+        } else {
+            ast::ArrowKind::Line
+        };
+        let mut stmt = ast::EdgeStmt::new(from);
+        stmt.insert(to, kind);
+        stmt.list = attrs;
+        res.list.list.push(ast::Stmt::Edge(stmt));
+    }
+    Result::Ok(res)
+}
+
+/// \returns the text of the scalar value \p value, or None for lists, objects
+/// and nulls.
+fn get_scalar(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Number(x) => Some(format_json_number(*x)),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// \returns the metadata of the JGF element \p elem as a DOT attribute list.
+/// Lists and objects in the metadata, such as the layout of the element, are
+/// not attributes and are skipped.
+fn get_attributes(elem: &JsonValue) -> Result<ast::AttributeList, String> {
+    let mut res = ast::AttributeList::new();
+    let metadata = match elem.get("metadata") {
+        Option::Some(metadata) => metadata,
+        None => return Result::Ok(res),
+    };
+    let members = metadata.as_object().ok_or("Expected an object")?;
+    for (key, value) in members {
+        if let Option::Some(value) = get_scalar(value) {
+            res.add_attr(key, &value);
+        }
+    }
+    Result::Ok(res)
+}
+
+/// \returns the JGF document of the graph that \p builder visited. The
+/// attributes of the graph, the nodes and the edges are written as metadata.
+/// If \p layout is set, then the metadata also has a 'layout' member with the
+/// center and the size of the nodes, the points of the bezier curves of the
+/// edges and the bounding box of the drawing, in points.
+pub fn write_jgf(
+    builder: &GraphBuilder,
+    layout: Option<&LayoutResult>,
+) -> String {
+    let edges = builder.edge_list();
+    let directed = edges.iter().all(|x| x.is_directed);
+    let mut graph = Vec::new();
+    if !builder.graph_name().is_empty() {
+        graph.push(("id".to_string(), string(builder.graph_name())));
+    }
+    graph.push(("directed".to_string(), JsonValue::Bool(directed)));
+    let attrs = builder.graph_attributes();
+    if let Option::Some(label) = attrs.get("label") {
+        graph.push(("label".to_string(), string(label)));
+    }
+    let mut metadata = get_metadata(attrs);
+    if let Option::Some(layout) = layout {
+        let (p0, p1) = layout.bbox;
+        let bbox = [p0.x, p0.y, p1.x, p1.y].map(number);
+        let layout = vec![("bbox".to_string(), JsonValue::Array(bbox.into()))];
+        metadata.push(("layout".to_string(), JsonValue::Object(layout)));
+    }
+    if !metadata.is_empty() {
+        graph.push(("metadata".to_string(), JsonValue::Object(metadata)));
+    }
+
+    let mut nodes = Vec::new();
+    for (i, (name, attrs)) in builder.node_list().enumerate() {
+        let mut node = Vec::new();
+        if let Option::Some(label) = attrs.get("label") {
+            node.push(("label".to_string(), string(label)));
+        }
+        let mut metadata = get_metadata(attrs);
+        if let Option::Some(layout) = layout {
+            let (p0, p1) = layout.nodes[i];
+            let center = p0.add(p1).scale(0.5);
+            let size = p1.sub(p0);
+            let layout = vec![
+                ("x".to_string(), number(center.x)),
+                ("y".to_string(), number(center.y)),
+                ("width".to_string(), number(size.x)),
+                ("height".to_string(), number(size.y)),
+            ];
+            metadata.push(("layout".to_string(), JsonValue::Object(layout)));
+        }
+        if !metadata.is_empty() {
+            node.push(("metadata".to_string(), JsonValue::Object(metadata)));
+        }
+        nodes.push((name.to_string(), JsonValue::Object(node)));
+    }
+    graph.push(("nodes".to_string(), JsonValue::Object(nodes)));
+
+    let mut res = Vec::new();
+    for (i, desc) in edges.iter().enumerate() {
+        let mut edge = vec![
+            ("source".to_string(), string(&desc.from)),
+            ("target".to_string(), string(&desc.to)),
+        ];
+        if desc.is_directed != directed {
+            edge.push((
+                "directed".to_string(),
+                JsonValue::Bool(desc.is_directed),
+            ));
+        }
+        if let Option::Some(label) = desc.props.get("label") {
+            edge.push(("label".to_string(), string(label)));
+        }
+        let mut metadata = get_metadata(&desc.props);
+        for (key, port) in
+            [("tailport", &desc.from_port), ("headport", &desc.to_port)]
+        {
+            if let Option::Some(port) = port {
+                metadata.push((key.to_string(), string(port)));
+            }
+        }
+        if let Option::Some(layout) = layout {
+            let edge = &layout.edges[i];
+            let mut points = Vec::new();
+            for (j, seg) in edge.path.iter().enumerate() {
+                if j == 0 {
+                    points.push(point(seg.0));
+                }
+                points.extend([point(seg.1), point(seg.2), point(seg.3)]);
+            }
+            let mut layout =
+                vec![("path".to_string(), JsonValue::Array(points))];
+            if let Option::Some(label) = edge.label {
+                layout.push(("label".to_string(), point(label)));
+            }
+            metadata.push(("layout".to_string(), JsonValue::Object(layout)));
+        }
+        if !metadata.is_empty() {
+            edge.push(("metadata".to_string(), JsonValue::Object(metadata)));
+        }
+        res.push(JsonValue::Object(edge));
+    }
+    graph.push(("edges".to_string(), JsonValue::Array(res)));
+
+    let doc = vec![("graph".to_string(), JsonValue::Object(graph))];
+    JsonValue::Object(doc).to_pretty_string()
+}
+
+/// \returns the attributes \p attrs, except for the label, sorted by name.
+fn get_metadata(attrs: &HashMap<String, String>) -> Vec<(String, JsonValue)> {
+    let mut res: Vec<(String, JsonValue)> = attrs
+        .iter()
+        .filter(|x| x.0 != "label")
+        .map(|(key, value)| (key.clone(), string(value)))
+        .collect();
+    res.sort_by(|a, b| a.0.cmp(&b.0));
+    res
+}
+
+fn string(s: &str) -> JsonValue {
+    JsonValue::String(s.to_string())
+}
+
+/// \returns the coordinate \p x, rounded to three decimal digits.
+fn number(x: f64) -> JsonValue {
+    JsonValue::Number((x * 1000.).round() / 1000.)
+}
+
+fn point(p: Point) -> JsonValue {
+    JsonValue::Array(vec![number(p.x), number(p.y)])
+}
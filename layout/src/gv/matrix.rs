@@ -0,0 +1,275 @@
+//! Draws the adjacency matrix of a graph as a heatmap, which stays readable
+//! when the graph is too dense for a node-link drawing. The matrix has a row
+//! and a column for each node, in the order of the nodes in the graph, and
+//! the cell in row i and column j is shaded by the total weight of the edges
+//! from node i to node j. Undirected edges shade the cells of both
+//! directions. The rows are labeled on the left, and the columns are labeled
+//! above the matrix, with one character in each line.
+//!
+//! ```rust
+//! use layout::backends::svg::SVGWriter;
+//! use layout::core::style::StyleAttr;
+//! use layout::gv::matrix::AdjacencyMatrix;
+//! use layout::gv::DotParser;
+//!
+//! let mut parser = DotParser::new("digraph { a -> b; b -> c; c -> a; }");
+//! let matrix = AdjacencyMatrix::from_graph(&parser.process().unwrap());
+//! println!("{}", matrix.to_text(true));
+//!
+//! let mut svg = SVGWriter::new();
+//! matrix.render(&StyleAttr::simple(), &mut svg);
+//! ```
+
+use crate::core::color::Color;
+use crate::core::format::RenderBackend;
+use crate::core::geometry::Point;
+use crate::core::style::StyleAttr;
+use crate::core::text::{get_label_text, get_size_for_text};
+use crate::gv::builder::GraphBuilder;
+use crate::gv::parser::ast;
+use std::collections::HashMap;
+
+/// The fraction of the ordered pairs of nodes that are connected, above
+/// which node-link drawings are hard to read.
+pub const DENSE_GRAPH: f64 = 0.25;
+
+/// The number of nodes that a graph needs to be dense. Small graphs are
+/// readable at any density.
+const MIN_DENSE_NODES: usize = 8;
+
+/// The space between the text of the labels and the matrix, and between the
+/// text of the cells and their borders.
+const MATRIX_PADDING: f64 = 4.;
+
+/// The color of the borders of the cells.
+const GRID_COLOR: &str = "#dddddd";
+
+/// The characters that shade the cells in the text drawing, from the empty
+/// cells to the cells with the heaviest edges.
+const ASCII_SHADES: [char; 5] = ['.', ':', '+', '*', '#'];
+const UNICODE_SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// The adjacency matrix of a graph, with the labels of its nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjacencyMatrix {
+    labels: Vec<String>,
+    // The total weight of the edges between each pair of nodes, by rows.
+    weights: Vec<Vec<f64>>,
+}
+
+impl AdjacencyMatrix {
+    /// Create the matrix of the graph that \p builder visited. Edges count
+    /// with their 'weight' attribute, or with 1.
+    pub fn new(builder: &GraphBuilder) -> Self {
+        let names: Vec<&str> = builder.node_list().map(|x| x.0).collect();
+        let labels = builder
+            .node_list()
+            .map(|(name, attrs)| match attrs.get("label") {
+                Option::Some(label) if !label.starts_with('<') => {
+                    get_label_text(&label.replace("\\N", name))
+                        .replace('\n', " ")
+                }
+                _ => name.to_string(),
+            })
+            .collect();
+        let n = names.len();
+        let mut weights = vec![vec![0.; n]; n];
+        let index: HashMap<&str, usize> =
+            names.iter().enumerate().map(|(i, x)| (*x, i)).collect();
+        for edge in builder.edge_list() {
+            let weight = match edge.props.get("weight") {
+                Option::Some(weight) => weight.parse::<f64>().unwrap_or(1.),
+                None => 1.,
+            };
+            if let (Option::Some(from), Option::Some(to)) =
+                (index.get(edge.from.as_str()), index.get(edge.to.as_str()))
+            {
+                weights[*from][*to] += weight;
+                if !edge.is_directed && from != to {
+                    weights[*to][*from] += weight;
+                }
+            }
+        }
+        Self { labels, weights }
+    }
+
+    /// Create the matrix of the graph \p graph.
+    pub fn from_graph(graph: &ast::Graph) -> Self {
+        let mut builder = GraphBuilder::new();
+        builder.visit_graph(graph);
+        Self::new(&builder)
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// \returns the label of the row and of the column of the node \p idx.
+    pub fn label(&self, idx: usize) -> &str {
+        &self.labels[idx]
+    }
+
+    /// \returns the total weight of the edges from the node \p from to the
+    /// node \p to.
+    pub fn weight(&self, from: usize, to: usize) -> f64 {
+        self.weights[from][to]
+    }
+
+    /// \returns the fraction of the ordered pairs of different nodes that
+    /// are connected, or zero for graphs with less than two nodes.
+    pub fn density(&self) -> f64 {
+        let n = self.num_nodes();
+        if n < 2 {
+            return 0.;
+        }
+        let mut connected = 0;
+        for (i, row) in self.weights.iter().enumerate() {
+            connected += row
+                .iter()
+                .enumerate()
+                .filter(|(j, w)| *j != i && **w != 0.)
+                .count();
+        }
+        connected as f64 / (n * (n - 1)) as f64
+    }
+
+    /// \returns true if the graph is too dense for a node-link drawing, and
+    /// should be drawn as a matrix.
+    pub fn is_dense(&self) -> bool {
+        self.num_nodes() >= MIN_DENSE_NODES && self.density() >= DENSE_GRAPH
+    }
+
+    /// \returns the largest weight of the cells, that gets the darkest shade.
+    fn get_max_weight(&self) -> f64 {
+        let cells = self.weights.iter().flatten();
+        cells.fold(0., |acc: f64, x| acc.max(x.abs()))
+    }
+
+    /// Draw the matrix on \p canvas, with the labels and the borders of the
+    /// cells in the style \p look. The cells are filled with the shades of
+    /// the "blues9" color scheme.
+    pub fn render(&self, look: &StyleAttr, canvas: &mut dyn RenderBackend) {
+        let n = self.num_nodes();
+        let cell = look.font_size as f64 + 2. * MATRIX_PADDING;
+        let columns: Vec<String> = self
+            .labels
+            .iter()
+            .map(|x| x.chars().map(String::from).collect::<Vec<_>>().join("\n"))
+            .collect();
+        let row_sizes: Vec<Point> = self
+            .labels
+            .iter()
+            .map(|x| get_size_for_text(x, look))
+            .collect();
+        let column_sizes: Vec<Point> =
+            columns.iter().map(|x| get_size_for_text(x, look)).collect();
+        let left = row_sizes.iter().fold(0., |acc, x| x.x.max(acc));
+        let top = column_sizes.iter().fold(0., |acc, x| x.y.max(acc));
+        let corner = Point::new(left, top).add(Point::splat(MATRIX_PADDING));
+
+        for i in 0..n {
+            let y = corner.y + cell * i as f64 + cell / 2.;
+            let x = corner.x - MATRIX_PADDING - row_sizes[i].x / 2.;
+            canvas.draw_text(Point::new(x, y), &self.labels[i], look);
+            let x = corner.x + cell * i as f64 + cell / 2.;
+            let y = corner.y - MATRIX_PADDING - column_sizes[i].y / 2.;
+            canvas.draw_text(Point::new(x, y), &columns[i], look);
+        }
+
+        let max = self.get_max_weight();
+        let mut cell_look = look.clone();
+        cell_look.line_color = Color::fast(GRID_COLOR);
+        cell_look.penwidth = 1.;
+        for i in 0..n {
+            for j in 0..n {
+                let level = get_level(self.weights[i][j], max, 8);
+                cell_look.fill_color = if level == 0 {
+                    None
+                } else {
+                    Color::from_name(&format!("/blues9/{}", level + 1))
+                };
+                let xy = corner.add(Point::new(j as f64, i as f64).scale(cell));
+                let size = Point::splat(cell);
+                canvas.draw_rect(xy, size, &cell_look, None, None);
+            }
+        }
+    }
+
+    /// \returns the drawing of the matrix as text, with the cells shaded with
+    /// block characters if \p unicode is set, or with ASCII characters. Each
+    /// cell takes two characters, to look about square in terminals.
+    pub fn to_text(&self, unicode: bool) -> String {
+        let shades = if unicode {
+            &UNICODE_SHADES
+        } else {
+            &ASCII_SHADES
+        };
+        let n = self.num_nodes();
+        let labels: Vec<Vec<char>> =
+            self.labels.iter().map(|x| x.chars().collect()).collect();
+        let width = labels.iter().map(|x| x.len()).max().unwrap_or(0);
+        let height = width;
+
+        let max = self.get_max_weight();
+        let mut res = String::new();
+        for line in 0..height {
+            let mut text = " ".repeat(width + 1);
+            for label in labels.iter() {
+                // The labels of the columns end above the matrix.
+                let idx = (line + label.len()).checked_sub(height);
+                text.push(idx.map_or(' ', |x| label[x]));
+                text.push(' ');
+            }
+            res.push_str(text.trim_end());
+            res.push('\n');
+        }
+        for (i, label) in labels.iter().enumerate() {
+            let label: String = label.iter().collect();
+            res.push_str(&format!("{:>width$} ", label, width = width));
+            for j in 0..n {
+                let level =
+                    get_level(self.weights[i][j], max, shades.len() - 1);
+                let ch = shades[level];
+                res.push(ch);
+                res.push(ch);
+            }
+            res.push('\n');
+        }
+        res
+    }
+}
+
+/// \returns the index of the shade of a cell of the weight \p weight, from
+/// zero for empty cells to \p levels for the cells of the weight \p max.
+fn get_level(weight: f64, max: f64, levels: usize) -> usize {
+    if weight == 0. || max == 0. {
+        return 0;
+    }
+    let shade = weight.abs() / max;
+    ((shade * levels as f64).ceil() as usize).clamp(1, levels)
+}
+
+#[test]
+fn test_adjacency_matrix() {
+    use crate::gv::DotParser;
+
+    let program = "graph { a -- b [weight=3]; b -- c; c -- c; \
+        d [label=\"node \\N\"]; }";
+    let graph = DotParser::new(program).process().unwrap();
+    let matrix = AdjacencyMatrix::from_graph(&graph);
+    assert_eq!(matrix.num_nodes(), 4);
+    assert_eq!(matrix.label(3), "node d");
+    // Undirected edges fill both cells, and loops fill one cell.
+    assert_eq!(matrix.weight(0, 1), 3.);
+    assert_eq!(matrix.weight(1, 0), 3.);
+    assert_eq!(matrix.weight(2, 2), 1.);
+    assert_eq!(matrix.density(), 4. / 12.);
+    assert!(!matrix.is_dense());
+
+    let text = matrix.to_text(false);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 6 + 4);
+    assert_eq!(lines[5], "       a b c d");
+    assert_eq!(lines[6], "     a ..##....");
+    assert_eq!(lines[8], "     c ..++++..");
+}
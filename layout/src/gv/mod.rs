@@ -1,12 +1,22 @@
 //! A module that contains everything that has to do with handling the GraphViz
 //! file format (parsing, building a compatible graph, etc.)
 
+pub mod batch;
 pub mod builder;
+pub mod diff;
+pub mod focus;
+pub mod html;
+pub mod jgf;
+pub mod matrix;
 pub mod parser;
 pub mod record;
+pub mod validate;
+pub mod writer;
 
 pub use builder::GraphBuilder;
 pub use parser::lexer::Lexer;
 pub use parser::lexer::Token;
 pub use parser::printer::dump_ast;
 pub use parser::DotParser;
+pub use parser::ParseError;
+pub use validate::validate_attributes;
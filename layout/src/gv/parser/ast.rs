@@ -29,7 +29,7 @@ impl AttributeList {
         self.list.push((from.to_string(), to.to_string()));
     }
 
-    pub fn iter(&self) -> std::slice::Iter<(String, String)> {
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, String)> {
         self.list.iter()
     }
 }
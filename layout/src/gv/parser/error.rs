@@ -0,0 +1,98 @@
+//! The errors of the DOT parser, with the location of the error in the source
+//! and a snippet of the line that contains it.
+
+use std::fmt;
+
+/// An error that the parser found in a DOT file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// A readable description of the error.
+    pub message: String,
+    /// The byte offset of the start of the token where the error was found.
+    pub offset: usize,
+    /// The line and the column of the token, counted in characters and
+    /// starting at 1.
+    pub line: usize,
+    pub column: usize,
+    /// The tokens that the parser accepts at this location, such as "'{'" or
+    /// "an identifier". This is empty if the error is not about a missing
+    /// token.
+    pub expected: Vec<String>,
+    /// The line of the source that contains the error, and a line with a '^'
+    /// marker under the token.
+    pub snippet: String,
+}
+
+impl ParseError {
+    /// \returns an error with the message \p message, at the character
+    /// \p index of \p input, that accepts the tokens in \p expected.
+    pub fn new(
+        input: &[char],
+        index: usize,
+        message: &str,
+        expected: &[&str],
+    ) -> Self {
+        let index = index.min(input.len());
+        let line_start = input[..index]
+            .iter()
+            .rposition(|ch| *ch == '\n')
+            .map_or(0, |x| x + 1);
+        let line_end = input[index..]
+            .iter()
+            .position(|ch| *ch == '\n')
+            .map_or(input.len(), |x| index + x);
+
+        // Keep the tabs of the line in the padding, so that the marker is
+        // aligned with the token.
+        let mut snippet: String = input[line_start..line_end]
+            .iter()
+            .filter(|ch| **ch != '\r')
+            .collect();
+        snippet.push('\n');
+        for ch in &input[line_start..index] {
+            snippet.push(if *ch == '\t' { '\t' } else { ' ' });
+        }
+        snippet.push('^');
+
+        Self {
+            message: message.to_string(),
+            offset: input[..index].iter().map(|ch| ch.len_utf8()).sum(),
+            line: input[..index].iter().filter(|ch| **ch == '\n').count() + 1,
+            column: index - line_start + 1,
+            expected: expected.iter().map(|x| x.to_string()).collect(),
+            snippet,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}\n{}",
+            self.line, self.column, self.message, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[test]
+fn test_parse_error() {
+    let input: Vec<char> = "digraph {\n\ta -> é b;\n}".chars().collect();
+    let err = ParseError::new(&input, 18, "Expected ';'", &["';'"]);
+    assert_eq!(err.line, 2);
+    assert_eq!(err.column, 9);
+    assert_eq!(err.offset, 19);
+    assert_eq!(err.expected, vec!["';'".to_string()]);
+    assert_eq!(err.snippet, "\ta -> é b;\n\t       ^");
+    assert_eq!(
+        err.to_string(),
+        "2:9: Expected ';'\n\ta -> é b;\n\t       ^"
+    );
+
+    // Errors at the end of the file point after the last character.
+    let err = ParseError::new(&input, 100, "Expected '}'", &[]);
+    assert_eq!((err.line, err.column), (3, 2));
+    assert_eq!(err.snippet, "}\n ^");
+}
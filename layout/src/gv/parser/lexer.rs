@@ -1,5 +1,7 @@
 //! The Lexer implementation for the GraphViz file format.
 
+use super::error::ParseError;
+
 #[derive(Debug, Clone)]
 pub enum Token {
     EOF,
@@ -23,11 +25,63 @@ pub enum Token {
     Error(usize),
 }
 
+impl Token {
+    /// \returns the text of the token if it's a keyword, such as "node".
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Token::GraphKW => Option::Some("graph"),
+            Token::NodeKW => Option::Some("node"),
+            Token::EdgeKW => Option::Some("edge"),
+            Token::DigraphKW => Option::Some("digraph"),
+            Token::StrictKW => Option::Some("strict"),
+            Token::SubgraphKW => Option::Some("subgraph"),
+            _ => None,
+        }
+    }
+
+    /// \returns a description of the token for error messages, such as "'{'"
+    /// or "the end of the file".
+    pub fn describe(&self) -> String {
+        if let Option::Some(keyword) = self.keyword() {
+            return format!("'{}'", keyword);
+        }
+        let text = match self {
+            Token::EOF => return "the end of the file".to_string(),
+            Token::Error(_) => return "an invalid token".to_string(),
+            Token::Identifier(name) => {
+                // Long strings are shortened.
+                let mut text: String = name.chars().take(20).collect();
+                if text.len() < name.len() {
+                    text.push_str("...");
+                }
+                return format!("'{}'", text);
+            }
+            Token::Equal => "=",
+            Token::Colon => ":",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::ArrowRight => "->",
+            Token::ArrowLine => "--",
+            Token::OpenBracket => "[",
+            Token::CloseBracket => "]",
+            Token::OpenBrace => "{",
+            Token::CloseBrace => "}",
+            _ => unreachable!("keywords are handled above"),
+        };
+        format!("'{}'", text)
+    }
+}
+
 #[derive(Debug)]
 pub struct Lexer {
     input: Vec<char>,
     pub pos: usize,
     pub ch: char,
+    // The index of the current character, or the length of the input at the
+    // end of the file.
+    index: usize,
+    /// The index of the first character of the last token.
+    pub start: usize,
 }
 
 impl Lexer {
@@ -41,6 +95,8 @@ impl Lexer {
             input,
             pos: 0,
             ch: '\0',
+            index: 0,
+            start: 0,
         };
         l.read_char();
         l
@@ -84,12 +140,20 @@ impl Lexer {
     pub fn read_char(&mut self) {
         if !self.has_next() {
             self.ch = '\0';
+            self.index = self.input.len();
         } else {
             self.ch = self.input[self.pos];
+            self.index = self.pos;
             self.pos += 1;
         }
     }
 
+    /// \returns an error with the message \p message at the start of the last
+    /// token, where the tokens in \p expected are accepted.
+    pub fn error(&self, message: &str, expected: &[&str]) -> ParseError {
+        ParseError::new(&self.input, self.start, message, expected)
+    }
+
     pub fn skip_whitespace(&mut self) -> bool {
         let mut changed = false;
         while self.ch.is_ascii_whitespace() {
@@ -171,7 +235,15 @@ impl Lexer {
                 self.read_char();
                 self.ch = match self.ch {
                     'n' => '\n',
-                    'l' => '\n',
+                    // Keep the escape of the characters that have a meaning
+                    // in record labels, and of the escapes that justify the
+                    // lines of labels or stand for the names of the elements.
+                    // The graph builder handles them.
+                    '{' | '}' | '|' | '<' | '>' | '\\' | 'l' | 'r' | 'N'
+                    | 'G' | 'E' | 'H' | 'T' => {
+                        result.push('\\');
+                        self.ch
+                    }
                     _ => self.ch,
                 }
             } else if self.ch == '\0' {
@@ -184,9 +256,31 @@ impl Lexer {
         Token::Identifier(result)
     }
 
+    /// Read an HTML string, such as <<B>text</B>>. The angle brackets must be
+    /// balanced, and the outer brackets are kept in the identifier.
+    pub fn read_html_string(&mut self) -> Token {
+        let mut result = String::new();
+        let mut depth = 0;
+        loop {
+            match self.ch {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                '\0' => return Token::Error(self.pos),
+                _ => {}
+            }
+            result.push(self.ch);
+            if depth == 0 {
+                break;
+            }
+            self.read_char();
+        }
+        Token::Identifier(result)
+    }
+
     pub fn next_token(&mut self) -> Token {
         let tok: Token;
         while self.skip_comment() || self.skip_whitespace() {}
+        self.start = self.index;
         match self.ch {
             '=' => {
                 tok = Token::Equal;
@@ -215,6 +309,9 @@ impl Lexer {
             '"' => {
                 tok = self.read_string();
             }
+            '<' => {
+                tok = self.read_html_string();
+            }
             '-' => {
                 self.read_char();
                 match self.ch {
@@ -230,7 +327,8 @@ impl Lexer {
                             let res = self.read_number();
                             num.push('-');
                             num.push_str(&res[..]);
-                            tok = Token::Identifier(num);
+                            // The number is followed by the next token.
+                            return Token::Identifier(num);
                         } else {
                             tok = Token::Error(self.pos);
                         }
@@ -241,7 +339,7 @@ impl Lexer {
                 tok = Token::EOF;
             }
             _ => {
-                if self.ch.is_ascii_alphabetic() {
+                if self.ch.is_ascii_alphabetic() || self.ch == '_' {
                     let name = self.read_identifier();
                     match name.as_str() {
                         "graph" => {
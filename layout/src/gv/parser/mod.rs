@@ -1,10 +1,13 @@
 //! GraphViz file format parser.
 
 pub mod ast;
+pub mod error;
 pub mod lexer;
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub mod printer;
 
+pub use error::ParseError;
 pub use lexer::Lexer;
 pub use lexer::Token;
 pub use parser::DotParser;
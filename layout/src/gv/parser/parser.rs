@@ -1,16 +1,40 @@
 use super::ast;
+use super::error::ParseError;
 use super::lexer::Lexer;
 use super::lexer::Token;
 
+/// The tokens that start a statement, or close the list of statements.
+const STMT_START: [&str; 7] = [
+    "an identifier",
+    "'subgraph'",
+    "'graph'",
+    "'node'",
+    "'edge'",
+    "'{'",
+    "'}'",
+];
+
 #[derive(Debug)]
 pub struct DotParser {
     lexer: Lexer,
     tok: Token,
+    // The errors that were found, in the order of the source. The parser
+    // skips the statements that have errors, and reports all of them.
+    errors: Vec<ParseError>,
+    // Accepts the deviations from the DOT grammar that other tools write,
+    // and reports them as warnings.
+    lenient: bool,
+    warnings: Vec<ParseError>,
 }
 
-/// Creates an error from the string \p str.
-fn to_error<T>(str: &str) -> Result<T, String> {
-    Result::Err(str.to_string())
+/// \returns the alternatives in \p list as text, such as "'a', 'b' or 'c'".
+fn join_alternatives(list: &[&str]) -> String {
+    match list.split_last() {
+        Option::Some((last, rest)) if !rest.is_empty() => {
+            format!("{} or {}", rest.join(", "), last)
+        }
+        _ => list.join(""),
+    }
 }
 
 impl DotParser {
@@ -19,11 +43,129 @@ impl DotParser {
         Self {
             lexer: Lexer::new(chars),
             tok: Token::Colon,
+            errors: Vec::new(),
+            lenient: false,
+            warnings: Vec::new(),
         }
     }
 
+    /// Accept the common deviations from the DOT grammar if \p lenient is
+    /// set: keywords that are used as identifiers, stray commas, attributes
+    /// without a value and scopes that are not closed. The statements that
+    /// have errors are skipped, and process() returns the rest of the graph.
+    /// The deviations and the errors are reported by warnings().
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// \returns the deviations from the grammar that process() accepted in
+    /// the lenient mode, in the order of the source.
+    pub fn warnings(&self) -> &[ParseError] {
+        &self.warnings
+    }
+
+    /// Print the errors that process() found, with the lines that contain
+    /// them, to the standard error.
     pub fn print_error(&self) {
-        self.lexer.print_error();
+        for err in &self.errors {
+            eprintln!("{}", err);
+        }
+    }
+
+    /// \returns the errors that process() found, in the order of the source.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// \returns an error at the current token, that says that one of the
+    /// tokens in \p expected was expected.
+    fn expected<T>(&self, expected: &[&str]) -> Result<T, ParseError> {
+        let message = format!(
+            "Expected {}, found {}",
+            join_alternatives(expected),
+            self.tok.describe()
+        );
+        Result::Err(self.lexer.error(&message, expected))
+    }
+
+    /// Record the error \p err, unless it was already recorded by a nested
+    /// scope. Errors are warnings in the lenient mode.
+    fn add_error(&mut self, err: ParseError) {
+        let list = if self.lenient {
+            &mut self.warnings
+        } else {
+            &mut self.errors
+        };
+        if list.last() != Option::Some(&err) {
+            list.push(err);
+        }
+    }
+
+    /// Record a warning with the message \p message at the current token.
+    fn warn(&mut self, message: &str) {
+        let warning = self.lexer.error(message, &[]);
+        self.warnings.push(warning);
+    }
+
+    /// Consume the identifier at the current token. In the lenient mode,
+    /// keywords are accepted as identifiers. \returns None if the token is not
+    /// an identifier.
+    fn parse_id(&mut self) -> Option<String> {
+        let id = match self.tok.clone() {
+            Token::Identifier(id) => id,
+            tok => match tok.keyword() {
+                Option::Some(keyword) if self.lenient => {
+                    let message = format!(
+                        "The keyword '{}' is used as an identifier",
+                        keyword
+                    );
+                    self.warn(&message);
+                    keyword.to_string()
+                }
+                _ => return None,
+            },
+        };
+        self.lex();
+        Option::Some(id)
+    }
+
+    /// Skip the commas and semicolons at the current token, that are not
+    /// allowed there, in the lenient mode.
+    fn skip_stray_separators(&mut self) {
+        while self.lenient
+            && matches!(self.tok, Token::Comma | Token::Semicolon)
+        {
+            let message = format!("Unexpected {}", self.tok.describe());
+            self.warn(&message);
+            self.lex();
+        }
+    }
+
+    /// Skip the tokens of a statement that has an error, up to the ';' that
+    /// ends it, the '}' that ends its scope, or the end of the file. Nested
+    /// scopes are skipped with the statement.
+    fn skip_statement(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.tok {
+                Token::EOF => return,
+                Token::Semicolon if depth == 0 => {
+                    self.lex();
+                    return;
+                }
+                Token::CloseBrace if depth == 0 => return,
+                Token::OpenBrace => depth += 1,
+                Token::CloseBrace => depth -= 1,
+                Token::Error(_) => {
+                    // Skip the character that the lexer rejected.
+                    self.lexer.read_char();
+                    self.tok = self.lexer.next_token();
+                    continue;
+                }
+                _ => {}
+            }
+            self.lex();
+        }
     }
 
     pub fn lex(&mut self) {
@@ -46,7 +188,7 @@ impl DotParser {
     pub fn parse_graph(
         &mut self,
         is_subgraph: bool,
-    ) -> Result<ast::Graph, String> {
+    ) -> Result<ast::Graph, ParseError> {
         let mut graph = ast::Graph::new("");
 
         // Handle the subgraph structure.
@@ -55,7 +197,7 @@ impl DotParser {
             if let Token::SubgraphKW = self.tok.clone() {
                 self.lex();
             } else {
-                return to_error("Expected 'subgraph'");
+                return self.expected(&["'subgraph'"]);
             }
 
             // Consume the optional graph name.
@@ -67,7 +209,7 @@ impl DotParser {
             if let Token::OpenBrace = self.tok.clone() {
                 self.lex();
             } else {
-                return to_error("Expected '{'");
+                return self.expected(&["'{'"]);
             }
             graph.list = self.parse_stmt_list()?;
             return Result::Ok(graph);
@@ -89,7 +231,7 @@ impl DotParser {
                 self.lex();
             }
             _ => {
-                return to_error("Expected (graph|digraph)");
+                return self.expected(&["'graph'", "'digraph'"]);
             }
         }
 
@@ -102,13 +244,13 @@ impl DotParser {
         if let Token::OpenBrace = self.tok.clone() {
             self.lex();
         } else {
-            return to_error("Expected '{'");
+            return self.expected(&["'{'"]);
         }
         graph.list = self.parse_stmt_list()?;
         Result::Ok(graph)
     }
     // stmt_list : [ stmt [ ';' ] stmt_list ]
-    pub fn parse_stmt_list(&mut self) -> Result<ast::StmtList, String> {
+    pub fn parse_stmt_list(&mut self) -> Result<ast::StmtList, ParseError> {
         let mut lst = ast::StmtList::new();
 
         loop {
@@ -116,18 +258,35 @@ impl DotParser {
                 // Consume the semicolon.
                 self.lex();
             }
+            self.skip_stray_separators();
 
             if let Token::CloseBrace = self.tok.clone() {
                 // Consume the '}' and exit.
                 self.lex();
                 return Result::Ok(lst);
             }
-            let stmt = self.parse_stmt()?;
-            lst.list.push(stmt);
+            if self.lenient && matches!(self.tok, Token::EOF) {
+                self.warn("Expected '}' at the end of the file");
+                return Result::Ok(lst);
+            }
+            match self.parse_stmt() {
+                Result::Ok(stmt) => lst.list.push(stmt),
+                Result::Err(err) => {
+                    self.add_error(err.clone());
+                    // The scope can't be closed after the end of the file.
+                    if let Token::EOF = self.tok {
+                        if self.lenient {
+                            return Result::Ok(lst);
+                        }
+                        return Result::Err(err);
+                    }
+                    self.skip_statement();
+                }
+            }
         }
     }
     // stmt : node_stmt | edge_stmt | attr_stmt | ID '=' ID | subgraph
-    pub fn parse_stmt(&mut self) -> Result<ast::Stmt, String> {
+    pub fn parse_stmt(&mut self) -> Result<ast::Stmt, ParseError> {
         match self.tok {
             Token::Identifier(_) => {
                 let id0 = self.parse_node_id()?;
@@ -166,7 +325,15 @@ impl DotParser {
                         let ns = ast::Stmt::Node(ns);
                         Result::Ok(ns)
                     }
-                    _ => to_error("Unsupported token"),
+                    _ => self.expected(&[
+                        "'->'",
+                        "'--'",
+                        "'='",
+                        "'['",
+                        "';'",
+                        "'}'",
+                        "an identifier",
+                    ]),
                 }
             }
             Token::SubgraphKW => {
@@ -202,43 +369,49 @@ impl DotParser {
                 Result::Ok(ast::Stmt::SubGraph(graph))
             }
 
-            _ => to_error("Unknown token"),
+            _ => self.expected(&STMT_START),
         }
     }
     //attr_list : '[' [ a_list ] ']' [ attr_list ]
-    pub fn parse_attr_list(&mut self) -> Result<ast::AttributeList, String> {
+    pub fn parse_attr_list(
+        &mut self,
+    ) -> Result<ast::AttributeList, ParseError> {
         let mut lst = ast::AttributeList::new();
 
         if let Token::OpenBracket = self.tok.clone() {
             self.lex();
         } else {
-            return to_error("Expected '['");
+            return self.expected(&["'['"]);
         }
 
+        self.skip_stray_separators();
         while !matches!(self.tok, Token::CloseBracket) {
-            let prop: String;
-
-            if let Token::Identifier(id) = self.tok.clone() {
-                prop = id;
-                // Consume the property name.
-                self.lex();
-            } else {
-                return to_error("Expected property name");
-            }
+            // Consume the property name.
+            let prop = match self.parse_id() {
+                Option::Some(prop) => prop,
+                None => return self.expected(&["an identifier", "']'"]),
+            };
 
             if let Token::Equal = self.tok.clone() {
                 // Consume the '='.
                 self.lex();
+            } else if self.lenient {
+                let message = format!(
+                    "The attribute '{}' has no value, and is true",
+                    prop
+                );
+                self.warn(&message);
+                lst.add_attr(&prop, "true");
+                self.skip_stray_separators();
+                continue;
             } else {
-                return to_error("Expected '='");
+                return self.expected(&["'='"]);
             }
 
-            if let Token::Identifier(value) = self.tok.clone() {
-                lst.add_attr(&prop, &value);
-                // Consume the value name.
-                self.lex();
-            } else {
-                return to_error("Expected value after assignment");
+            // Consume the value name.
+            match self.parse_id() {
+                Option::Some(value) => lst.add_attr(&prop, &value),
+                None => return self.expected(&["an identifier"]),
             }
 
             // Skip semicolon.
@@ -249,11 +422,12 @@ impl DotParser {
             if let Token::Comma = self.tok.clone() {
                 self.lex()
             }
+            self.skip_stray_separators();
         }
         if let Token::CloseBracket = self.tok.clone() {
             self.lex();
         } else {
-            return to_error("Expected ']'");
+            return self.expected(&["']'"]);
         }
         Result::Ok(lst)
     }
@@ -266,24 +440,24 @@ impl DotParser {
     pub fn parse_attribute_stmt(
         &mut self,
         id: ast::NodeId,
-    ) -> Result<ast::AttrStmt, String> {
+    ) -> Result<ast::AttrStmt, ParseError> {
         let mut lst = ast::AttributeList::new();
 
         if id.port.is_some() {
-            return to_error("Can't assign into a port");
+            return Result::Err(
+                self.lexer.error("Can't assign into a port", &[]),
+            );
         }
 
         if let Token::Equal = self.tok.clone() {
             self.lex();
         } else {
-            return to_error("Expected '='");
+            return self.expected(&["'='"]);
         }
 
-        if let Token::Identifier(val) = self.tok.clone() {
-            lst.add_attr(&id.name, &val);
-            self.lex();
-        } else {
-            return to_error("Expected identifier.");
+        match self.parse_id() {
+            Option::Some(val) => lst.add_attr(&id.name, &val),
+            None => return self.expected(&["an identifier"]),
         }
 
         Result::Ok(ast::AttrStmt::new(ast::AttrStmtTarget::Graph, lst))
@@ -293,7 +467,7 @@ impl DotParser {
     pub fn parse_edge_stmt(
         &mut self,
         id: ast::NodeId,
-    ) -> Result<ast::EdgeStmt, String> {
+    ) -> Result<ast::EdgeStmt, ParseError> {
         let mut es = ast::EdgeStmt::new(id);
 
         while self.is_edge_token() {
@@ -301,7 +475,7 @@ impl DotParser {
                 Token::ArrowLine => ast::ArrowKind::Line,
                 Token::ArrowRight => ast::ArrowKind::Arrow,
                 _ => {
-                    return to_error("Expected '->' or '--' ");
+                    return self.expected(&["'->'", "'--'"]);
                 }
             };
             // Consume the arrow.
@@ -318,38 +492,95 @@ impl DotParser {
     }
 
     //node_id : ID [ port ]
-    pub fn parse_node_id(&mut self) -> Result<ast::NodeId, String> {
-        let node_name: String;
-        if let Token::Identifier(name) = self.tok.clone() {
-            node_name = name;
-            // Consume the value name.
-            self.lex();
-        } else {
-            return to_error("port");
-        }
+    //port : ':' ID [ ':' compass_pt ] | ':' compass_pt
+    // The compass point is parsed but ignored, because edges are attached to
+    // the closest point on the port.
+    pub fn parse_node_id(&mut self) -> Result<ast::NodeId, ParseError> {
+        // Consume the value name.
+        let node_name = match self.parse_id() {
+            Option::Some(name) => name,
+            None => return self.expected(&["an identifier"]),
+        };
 
         if let Token::Colon = self.tok.clone() {
             // Consume the colon.
             self.lex();
-            if let Token::Identifier(port) = self.tok.clone() {
-                // Consume the port name.
-                self.lex();
+            // Consume the port name.
+            if let Option::Some(mut port) = self.parse_id() {
+                if let Token::Colon = self.tok.clone() {
+                    // Consume the colon and the compass point. The compass
+                    // point is kept in the port, as in "port:n".
+                    self.lex();
+                    if let Option::Some(compass) = self.parse_id() {
+                        port.push(':');
+                        port.push_str(&compass);
+                    } else {
+                        return self.expected(&["a compass point"]);
+                    }
+                }
                 return Result::Ok(ast::NodeId::new(&node_name, &Some(port)));
             } else {
-                return to_error("Expected a port name");
+                return self.expected(&["a port name"]);
             }
         }
         Result::Ok(ast::NodeId::new(&node_name, &None))
     }
 
+    /// Parse all of the graphs in the input, such as the concatenation of
+    /// several DOT files. \returns the graphs in the order of the input, or
+    /// the first error, like process().
+    pub fn process_all(&mut self) -> Result<Vec<ast::Graph>, ParseError> {
+        self.errors.clear();
+        self.warnings.clear();
+        self.lex();
+        let mut graphs = Vec::new();
+        while !matches!(self.tok, Token::EOF) {
+            match self.parse_graph(false) {
+                Result::Ok(graph) => graphs.push(graph),
+                Result::Err(err) => {
+                    if self.errors.last() != Option::Some(&err) {
+                        self.errors.push(err);
+                    }
+                    return Result::Err(self.errors[0].clone());
+                }
+            }
+        }
+        match self.errors.first() {
+            Option::Some(err) => Result::Err(err.clone()),
+            None => Result::Ok(graphs),
+        }
+    }
+
     /// Parses dot files, as specified here:
     /// <https://graphviz.org/doc/info/lang.html>
-    pub fn process(&mut self) -> Result<ast::Graph, String> {
+    /// The statements that have errors are skipped, and the parser continues
+    /// after them. \returns the first error, if there are errors, and all of
+    /// them are returned by errors().
+    pub fn process(&mut self) -> Result<ast::Graph, ParseError> {
+        self.errors.clear();
+        self.warnings.clear();
         self.lex();
-        let result = self.parse_graph(false)?;
-        if let Token::EOF = self.tok {
-            return Result::Ok(result);
+        match self.parse_graph(false) {
+            Result::Ok(graph) => {
+                if !matches!(self.tok, Token::EOF) {
+                    let err = self.lexer.error(
+                        "Unexpected content at the end of the file",
+                        &["the end of the file"],
+                    );
+                    self.add_error(err);
+                }
+                match self.errors.first() {
+                    Option::Some(err) => Result::Err(err.clone()),
+                    None => Result::Ok(graph),
+                }
+            }
+            Result::Err(err) => {
+                // The graph can't be recovered, even in the lenient mode.
+                if self.errors.last() != Option::Some(&err) {
+                    self.errors.push(err);
+                }
+                Result::Err(self.errors[0].clone())
+            }
         }
-        to_error("Unexpected content at the end of the file.")
     }
 }
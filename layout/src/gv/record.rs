@@ -34,6 +34,8 @@ struct RecordParser {
 struct RecordParserFrame {
     label: String,
     arr: Vec<RecordDef>,
+    // True if the last field was a nested record, that has no label.
+    after_record: bool,
 }
 
 impl RecordParserFrame {
@@ -41,6 +43,7 @@ impl RecordParserFrame {
         Self {
             label: String::new(),
             arr: Vec::new(),
+            after_record: false,
         }
     }
 
@@ -52,12 +55,12 @@ impl RecordParserFrame {
             if let Option::Some(idx) = str.find('>') {
                 let port = &str[1..idx];
                 return (
-                    str[idx + 1..].trim().to_string(),
+                    unescape_record_chars(str[idx + 1..].trim()),
                     Option::Some(port.to_string()),
                 );
             }
         }
-        (str.to_string(), Option::None)
+        (unescape_record_chars(str), Option::None)
     }
 
     pub fn finalize_label(&mut self) {
@@ -69,12 +72,29 @@ impl RecordParserFrame {
         }
     }
 
-    pub fn finalize_record(&mut self) -> RecordDef {
-        self.finalize_label();
-        match self.arr.len() {
-            0 => RecordDef::Text(String::from(""), Option::None),
-            _ => RecordDef::Array(self.arr.clone()),
+    /// Finalize the field that ends at a '|' or a '}'. Fields that contain a
+    /// nested record don't need a label, but other fields are kept even if
+    /// they are empty, like the middle field of "a||b".
+    pub fn finalize_field(&mut self) {
+        if self.after_record {
+            self.finalize_label();
+        } else {
+            let ret = Self::split_label_to_text_and_port(&self.label);
+            self.arr.push(RecordDef::Text(ret.0, ret.1));
         }
+        self.label.clear();
+        self.after_record = false;
+    }
+
+    pub fn push_record(&mut self, rec: RecordDef) {
+        self.finalize_label();
+        self.arr.push(rec);
+        self.after_record = true;
+    }
+
+    pub fn finalize_record(&mut self) -> RecordDef {
+        self.finalize_field();
+        RecordDef::Array(self.arr.clone())
     }
 }
 impl RecordParser {
@@ -88,30 +108,42 @@ impl RecordParser {
     pub fn parse(&mut self) -> RecordDef {
         let mut frame = RecordParserFrame::new();
         loop {
+            // Are we at the end of the buffer?
+            if self.pos >= self.input.len() {
+                return frame.finalize_record();
+            }
+
             // Read one char.
             let ch = self.input[self.pos];
 
             match ch {
                 '{' => {
                     self.pos += 1;
-                    // Finalize the label.
-                    frame.finalize_label();
                     // Parse the sub row:
                     let ret = self.parse();
-                    frame.arr.push(ret);
+                    frame.push_record(ret);
                 }
                 '|' => {
                     // New record in the row.
                     self.pos += 1;
-                    frame.finalize_label();
+                    frame.finalize_field();
                 }
                 '}' => {
                     // Finish the row.
                     self.pos += 1;
-                    // Finalize the row.
-                    frame.finalize_label();
                     return frame.finalize_record();
                 }
+                '\\' if self.pos + 1 < self.input.len() => {
+                    // Escaped characters are part of the label. Keep the
+                    // escape of '<' and '>', that the port parser handles,
+                    // and the escapes that justify the lines of the label.
+                    let next = self.input[self.pos + 1];
+                    if matches!(next, '<' | '>' | '\\' | 'l' | 'r') {
+                        frame.label.push(ch);
+                    }
+                    frame.label.push(next);
+                    self.pos += 2;
+                }
                 _ => {
                     self.pos += 1;
 
@@ -119,12 +151,32 @@ impl RecordParser {
                     frame.label.push(ch);
                 }
             }
-            // Are we at the end of the buffer?
-            if self.pos == self.input.len() {
-                return frame.finalize_record();
+        }
+    }
+}
+
+/// Remove the backslash from the escaped characters that have a special
+/// meaning in record labels, such as "\\{" or "\\|". The lexer keeps these
+/// escapes, so labels that are not records need to remove them. The other
+/// escapes of the label are kept.
+pub fn unescape_record_chars(label: &str) -> String {
+    let mut res = String::new();
+    let mut chars = label.chars().peekable();
+    while let Option::Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.peek() {
+                Option::Some('{' | '}' | '|' | '<' | '>') => continue,
+                Option::Some('\\') => {
+                    res.push(ch);
+                    res.push(chars.next().unwrap());
+                    continue;
+                }
+                _ => {}
             }
         }
+        res.push(ch);
     }
+    res
 }
 
 pub fn parse_record_string(label: &str) -> RecordDef {
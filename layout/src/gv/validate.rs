@@ -0,0 +1,616 @@
+//! Validation of the attributes of a DOT graph against the catalogue of the
+//! attributes that the graph builder supports. The builder silently ignores
+//! the attributes that it doesn't know, the attributes that don't apply to
+//! the element that they are attached to, and the values that it can't use.
+//! This module reports each of them with the reason.
+
+use super::parser::ast;
+use crate::core::color::Color;
+use crate::core::style::ArrowHeadKind;
+use crate::er::parse_cardinality;
+use crate::std_shapes::shapes::PolygonKind;
+use crate::topo::placer::{OverlapMode, PageDir, Ratio};
+use std::fmt;
+
+/// The kind of element that an attribute is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttrTarget {
+    Graph,
+    Subgraph,
+    Node,
+    Edge,
+}
+
+impl AttrTarget {
+    /// \returns the name of the target in DOT files, such as "node".
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttrTarget::Graph => "graph",
+            AttrTarget::Subgraph => "subgraph",
+            AttrTarget::Node => "node",
+            AttrTarget::Edge => "edge",
+        }
+    }
+}
+
+/// The reason why an attribute is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IgnoreReason {
+    /// Graphviz doesn't define the attribute. This is often a typo.
+    Unknown,
+    /// Graphviz defines the attribute, but the builder doesn't implement it.
+    Unsupported,
+    /// The builder supports the attribute, but not on this kind of element,
+    /// such as 'rankdir' in a subgraph.
+    WrongTarget,
+    /// The builder can't use the value. The string describes the values that
+    /// are accepted.
+    InvalidValue(&'static str),
+}
+
+/// An attribute of the graph that the builder ignores.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredAttribute {
+    pub target: AttrTarget,
+    /// The name of the graph, the subgraph or the node, or the edge, as in
+    /// "a -> b". This is None for the default attributes, as in 'node [...]'.
+    pub element: Option<String>,
+    pub name: String,
+    pub value: String,
+    pub reason: IgnoreReason,
+}
+
+impl fmt::Display for IgnoredAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.element {
+            Option::Some(element) => {
+                write!(f, "{} \"{}\": ", self.target.name(), element)?
+            }
+            None => write!(f, "{} [...]: ", self.target.name())?,
+        }
+        match self.reason {
+            IgnoreReason::Unknown => {
+                write!(f, "\"{}\" is not a Graphviz attribute", self.name)
+            }
+            IgnoreReason::Unsupported => {
+                write!(f, "\"{}\" is not supported", self.name)
+            }
+            IgnoreReason::WrongTarget => write!(
+                f,
+                "\"{}\" does not apply to a {}",
+                self.name,
+                self.target.name()
+            ),
+            IgnoreReason::InvalidValue(expected) => write!(
+                f,
+                "\"{}={}\" is not supported, expected {}",
+                self.name, self.value, expected
+            ),
+        }
+    }
+}
+
+/// The values that an attribute accepts.
+#[derive(Debug, Clone, Copy)]
+enum ValueKind {
+    Text,
+    Number,
+    Integer,
+    SignedInteger,
+    Bool,
+    /// A color, or a list of colors such as "red;0.3:blue".
+    Color,
+    /// One or two numbers, such as "0.3,0.1".
+    Point,
+    /// A point that may end with '!', such as "7.5,10!".
+    Size,
+    /// A number, or one of 'fill', 'compress', 'expand' and 'auto'.
+    Ratio,
+    /// Two sides, such as "BL" or "RT".
+    PageDir,
+    /// A comma separated list of styles, such as "filled,dashed".
+    Style,
+    Shape,
+    Arrow,
+    /// The cardinality of the tail and of the head, such as "1:0..*".
+    Cardinality,
+    /// A list of rank gaps, such as "0.5:1", that may end with 'equally'.
+    RankSep,
+    /// A boolean or a margin.
+    Pack,
+    PackMode,
+    /// A boolean, 'scale', or the name of a removal algorithm.
+    Overlap,
+    OneOf(&'static [&'static str], &'static str),
+}
+
+const G: &[AttrTarget] = &[AttrTarget::Graph];
+const S: &[AttrTarget] = &[AttrTarget::Subgraph];
+const N: &[AttrTarget] = &[AttrTarget::Node];
+const E: &[AttrTarget] = &[AttrTarget::Edge];
+const GN: &[AttrTarget] = &[AttrTarget::Graph, AttrTarget::Node];
+const GS: &[AttrTarget] = &[AttrTarget::Graph, AttrTarget::Subgraph];
+const SN: &[AttrTarget] = &[AttrTarget::Subgraph, AttrTarget::Node];
+const SNE: &[AttrTarget] =
+    &[AttrTarget::Subgraph, AttrTarget::Node, AttrTarget::Edge];
+const ALL: &[AttrTarget] = &[
+    AttrTarget::Graph,
+    AttrTarget::Subgraph,
+    AttrTarget::Node,
+    AttrTarget::Edge,
+];
+
+/// The attributes that the graph builder reads, the elements that they apply
+/// to, and their values. An attribute may have one entry per kind of value.
+const SUPPORTED: &[(&str, &[AttrTarget], ValueKind)] = &[
+    // The graph.
+    ("avoidnodes", G, ValueKind::Bool),
+    ("bgcolor", GS, ValueKind::Color),
+    ("cellpadding", G, ValueKind::Number),
+    ("columns", G, ValueKind::Integer),
+    ("concentrate", G, ValueKind::Bool),
+    ("epsilon", G, ValueKind::Number),
+    ("imagepath", G, ValueKind::Text),
+    (
+        "labeljust",
+        G,
+        ValueKind::OneOf(&["l", "r", "c"], "l, r or c"),
+    ),
+    (
+        "labelloc",
+        G,
+        ValueKind::OneOf(&["t", "b", "c"], "t, b or c"),
+    ),
+    (
+        "layout",
+        G,
+        ValueKind::OneOf(
+            &[
+                "dot", "fdp", "sfdp", "neato", "grid", "circo", "twopi",
+                "tree", "timeline",
+            ],
+            "dot, fdp, sfdp, neato, grid, circo, twopi, tree or timeline",
+        ),
+    ),
+    ("layers", G, ValueKind::Text),
+    ("layersep", G, ValueKind::Text),
+    ("margin", GN, ValueKind::Point),
+    ("maxiter", G, ValueKind::Integer),
+    ("mclimit", G, ValueKind::Number),
+    ("nodesep", G, ValueKind::Number),
+    (
+        "outputorder",
+        G,
+        ValueKind::OneOf(
+            &["breadthfirst", "nodesfirst", "edgesfirst"],
+            "breadthfirst, nodesfirst or edgesfirst",
+        ),
+    ),
+    ("overlap", G, ValueKind::Overlap),
+    ("pack", G, ValueKind::Pack),
+    ("packmode", G, ValueKind::PackMode),
+    ("page", G, ValueKind::Point),
+    ("pagedir", G, ValueKind::PageDir),
+    ("rankdir", G, ValueKind::OneOf(&["TB", "LR"], "TB or LR")),
+    ("ranksep", G, ValueKind::RankSep),
+    ("ratio", G, ValueKind::Ratio),
+    ("root", G, ValueKind::Text),
+    ("size", G, ValueKind::Size),
+    ("stylesheet", G, ValueKind::Text),
+    ("root", N, ValueKind::Bool),
+    (
+        "splines",
+        G,
+        ValueKind::OneOf(
+            &[
+                "line", "false", "polyline", "curved", "ortho", "spline",
+                "true",
+            ],
+            "line, polyline, curved, ortho or spline",
+        ),
+    ),
+    // The subgraphs.
+    (
+        "rank",
+        S,
+        ValueKind::OneOf(
+            &["same", "min", "max", "source", "sink"],
+            "same, min, max, source or sink",
+        ),
+    ),
+    ("pencolor", S, ValueKind::Color),
+    // The elements that have a label.
+    ("label", ALL, ValueKind::Text),
+    ("fontcolor", ALL, ValueKind::Color),
+    ("fontname", ALL, ValueKind::Text),
+    ("fontsize", ALL, ValueKind::Integer),
+    ("color", SNE, ValueKind::Color),
+    ("fillcolor", SN, ValueKind::Color),
+    ("penwidth", SNE, ValueKind::Number),
+    ("style", SNE, ValueKind::Style),
+    // The nodes.
+    (
+        "fixedsize",
+        N,
+        ValueKind::OneOf(&["true", "false", "shape"], "a bool"),
+    ),
+    ("gradientangle", N, ValueKind::Number),
+    ("height", N, ValueKind::Number),
+    ("image", N, ValueKind::Text),
+    (
+        "imagescale",
+        N,
+        ValueKind::OneOf(
+            &["true", "false", "width", "height", "both"],
+            "a bool, width, height or both",
+        ),
+    ),
+    ("columns", N, ValueKind::Text),
+    ("end", N, ValueKind::Number),
+    ("labeloverflow", N, ValueKind::OneOf(&["clip"], "clip")),
+    ("peripheries", N, ValueKind::Integer),
+    ("shape", N, ValueKind::Shape),
+    ("start", N, ValueKind::Number),
+    ("width", N, ValueKind::Number),
+    ("class", SNE, ValueKind::Text),
+    ("href", SNE, ValueKind::Text),
+    ("id", SNE, ValueKind::Text),
+    ("layer", SNE, ValueKind::Text),
+    ("target", SNE, ValueKind::Text),
+    ("tooltip", SNE, ValueKind::Text),
+    ("URL", SNE, ValueKind::Text),
+    ("zorder", SNE, ValueKind::SignedInteger),
+    // The edges.
+    ("arrowhead", E, ValueKind::Arrow),
+    ("arrowsize", E, ValueKind::Number),
+    ("arrowtail", E, ValueKind::Arrow),
+    ("cardinality", E, ValueKind::Cardinality),
+    (
+        "constraint",
+        E,
+        ValueKind::OneOf(&["true", "false", "yes", "no", "1", "0"], "a bool"),
+    ),
+    (
+        "dir",
+        E,
+        ValueKind::OneOf(
+            &["forward", "back", "both", "none"],
+            "forward, back, both or none",
+        ),
+    ),
+    ("headlabel", E, ValueKind::Text),
+    ("labelangle", E, ValueKind::Number),
+    ("labeldistance", E, ValueKind::Number),
+    ("minlen", E, ValueKind::Integer),
+    ("taillabel", E, ValueKind::Text),
+    ("weight", E, ValueKind::Number),
+];
+
+/// The Graphviz attributes that the graph builder doesn't implement.
+const UNSUPPORTED: &[&str] = &[
+    "Damping",
+    "K",
+    "center",
+    "charset",
+    "clusterrank",
+    "colorscheme",
+    "comment",
+    "compound",
+    "decorate",
+    "defaultdist",
+    "dim",
+    "dimen",
+    "distortion",
+    "dpi",
+    "edgeURL",
+    "edgehref",
+    "edgetarget",
+    "edgetooltip",
+    "esep",
+    "forcelabels",
+    "group",
+    "headURL",
+    "headclip",
+    "headhref",
+    "headport",
+    "headtarget",
+    "headtooltip",
+    "imagepos",
+    "inputscale",
+    "labelURL",
+    "labelfloat",
+    "labelfontcolor",
+    "labelfontname",
+    "labelfontsize",
+    "labelhref",
+    "labeltarget",
+    "labeltooltip",
+    "landscape",
+    "layerselect",
+    "len",
+    "levels",
+    "levelsgap",
+    "lhead",
+    "lheight",
+    "lp",
+    "ltail",
+    "lwidth",
+    "mode",
+    "model",
+    "newrank",
+    "nojustify",
+    "normalize",
+    "notranslate",
+    "nslimit",
+    "nslimit1",
+    "ordering",
+    "orientation",
+    "overlap_scaling",
+    "overlap_shrink",
+    "pad",
+    "pos",
+    "quadtree",
+    "quantum",
+    "regular",
+    "remincross",
+    "repulsiveforce",
+    "resolution",
+    "rotate",
+    "rotation",
+    "samehead",
+    "sametail",
+    "samplepoints",
+    "scale",
+    "searchsize",
+    "sep",
+    "showboxes",
+    "sides",
+    "skew",
+    "smoothing",
+    "sortv",
+    "tailURL",
+    "tailclip",
+    "tailhref",
+    "tailport",
+    "tailtarget",
+    "tailtooltip",
+    "truecolor",
+    "vertices",
+    "viewport",
+    "voro_margin",
+    "xlabel",
+    "xlp",
+    "z",
+];
+
+/// The styles of the nodes, the edges and the clusters.
+const STYLES: &[&str] = &[
+    "solid", "filled", "rounded", "dashed", "dotted", "bold", "invis",
+    "radial", "striped", "wedged",
+];
+
+/// \returns true if \p val parses as a number.
+fn is_number(val: &str) -> bool {
+    val.trim().parse::<f64>().is_ok()
+}
+
+/// \returns the description of the values of \p kind, if \p val is not one
+/// of them.
+fn check_value(kind: ValueKind, val: &str) -> Option<&'static str> {
+    let valid = match kind {
+        ValueKind::Text => true,
+        ValueKind::Number => is_number(val),
+        ValueKind::Integer => val.parse::<usize>().is_ok(),
+        ValueKind::SignedInteger => val.parse::<i32>().is_ok(),
+        ValueKind::Bool => val == "true" || val == "false",
+        ValueKind::Color => val
+            .split(':')
+            .map(|x| x.split(';').next().unwrap_or(x))
+            .all(|x| Color::from_name(x).is_some()),
+        ValueKind::Point => {
+            let parts: Vec<&str> = val.split(',').collect();
+            parts.len() <= 2 && parts.iter().all(|x| is_number(x))
+        }
+        ValueKind::Size => {
+            let val = val.strip_suffix('!').unwrap_or(val);
+            check_value(ValueKind::Point, val).is_none()
+        }
+        ValueKind::Ratio => Ratio::from_name(val).is_some(),
+        ValueKind::PageDir => PageDir::from_name(val).is_some(),
+        ValueKind::Style => val.split(',').all(|x| STYLES.contains(&x.trim())),
+        ValueKind::Shape => {
+            let builtin = [
+                "box",
+                "circle",
+                "doublecircle",
+                "ellipse",
+                "oval",
+                "rect",
+                "rectangle",
+                "square",
+                "point",
+                "record",
+                "Mrecord",
+                "table",
+            ];
+            builtin.contains(&val) || PolygonKind::from_name(val).is_some()
+        }
+        ValueKind::Arrow => ArrowHeadKind::from_name(val).is_some(),
+        ValueKind::Cardinality => parse_cardinality(val).is_some(),
+        ValueKind::RankSep => val
+            .split_whitespace()
+            .all(|word| word == "equally" || word.split(':').all(is_number)),
+        ValueKind::Pack => val == "true" || val == "false" || is_number(val),
+        ValueKind::PackMode => {
+            val.starts_with("array")
+                || ["graph", "node", "clust"].contains(&val)
+        }
+        ValueKind::Overlap => OverlapMode::from_name(val).is_some(),
+        ValueKind::OneOf(values, _) => values.contains(&val),
+    };
+    if valid {
+        return None;
+    }
+    Some(match kind {
+        ValueKind::Text => "a string",
+        ValueKind::Number => "a number",
+        ValueKind::Integer => "a positive integer",
+        ValueKind::SignedInteger => "an integer",
+        ValueKind::Bool => "true or false",
+        ValueKind::Color => "a color or a list of colors",
+        ValueKind::Point => "one or two numbers",
+        ValueKind::Size => "one or two numbers, and an optional '!'",
+        ValueKind::Ratio => "a number, fill, compress, expand or auto",
+        ValueKind::PageDir => "two sides, such as BL or RT",
+        ValueKind::Style => "a list of supported styles",
+        ValueKind::Shape => "a supported shape",
+        ValueKind::Arrow => "a supported arrow head",
+        ValueKind::Cardinality => "two of 1, 0..1, 1..* or 0..*, as in 1:0..*",
+        ValueKind::RankSep => "a list of numbers",
+        ValueKind::Pack => "true, false or a number",
+        ValueKind::PackMode => "graph, node, clust or array",
+        ValueKind::Overlap => "a bool, scale or prism",
+        ValueKind::OneOf(_, desc) => desc,
+    })
+}
+
+/// \returns the reason why the builder ignores the attribute \p name with
+/// the value \p val on a \p target, or None if it's used.
+fn check_attribute(
+    target: AttrTarget,
+    name: &str,
+    val: &str,
+) -> Option<IgnoreReason> {
+    let mut known = false;
+    for (attr, targets, kind) in SUPPORTED {
+        if *attr != name {
+            continue;
+        }
+        known = true;
+        if targets.contains(&target) {
+            return check_value(*kind, val).map(IgnoreReason::InvalidValue);
+        }
+    }
+    if known {
+        Some(IgnoreReason::WrongTarget)
+    } else if UNSUPPORTED.contains(&name) {
+        Some(IgnoreReason::Unsupported)
+    } else {
+        Some(IgnoreReason::Unknown)
+    }
+}
+
+/// Visits the graph and collects the attributes that are ignored.
+struct Validator {
+    ignored: Vec<IgnoredAttribute>,
+}
+
+impl Validator {
+    fn check_list(
+        &mut self,
+        target: AttrTarget,
+        element: Option<&str>,
+        list: &ast::AttributeList,
+    ) {
+        for (name, value) in list.iter() {
+            if let Option::Some(reason) = check_attribute(target, name, value) {
+                self.ignored.push(IgnoredAttribute {
+                    target,
+                    element: element.map(|x| x.to_string()),
+                    name: name.clone(),
+                    value: value.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    fn visit_graph(&mut self, graph: &ast::Graph, target: AttrTarget) {
+        for stmt in &graph.list.list {
+            match stmt {
+                ast::Stmt::Node(n) => {
+                    self.check_list(AttrTarget::Node, Some(&n.id.name), &n.list)
+                }
+                ast::Stmt::Edge(e) => {
+                    let mut name = e.from.name.clone();
+                    for (to, arrow) in &e.to {
+                        name.push_str(match arrow {
+                            ast::ArrowKind::Arrow => " -> ",
+                            ast::ArrowKind::Line => " -- ",
+                        });
+                        name.push_str(&to.name);
+                    }
+                    self.check_list(AttrTarget::Edge, Some(&name), &e.list);
+                }
+                ast::Stmt::Attribute(a) => {
+                    let (target, element) = match a.target {
+                        // Statements like 'a=b' have a single attribute.
+                        ast::AttrStmtTarget::Graph => {
+                            (target, Some(graph.name.as_str()))
+                        }
+                        ast::AttrStmtTarget::Node => (AttrTarget::Node, None),
+                        ast::AttrStmtTarget::Edge => (AttrTarget::Edge, None),
+                    };
+                    self.check_list(target, element, &a.list);
+                }
+                ast::Stmt::SubGraph(g) => {
+                    self.visit_graph(g, AttrTarget::Subgraph)
+                }
+            }
+        }
+    }
+}
+
+/// \returns the attributes of \p graph that the graph builder ignores, in the
+/// order of the source, with the reason why they are ignored.
+pub fn validate_attributes(graph: &ast::Graph) -> Vec<IgnoredAttribute> {
+    let mut validator = Validator {
+        ignored: Vec::new(),
+    };
+    validator.visit_graph(graph, AttrTarget::Graph);
+    validator.ignored
+}
+
+#[test]
+fn test_validate_attributes() {
+    use super::DotParser;
+    let program = "digraph G {
+        rankdir=BT; splines=ortho; sizee=3;
+        node [shape=box, colour=red];
+        a [shape=blob, pos=\"1,2\", fillcolor=\"red;0.3:blue\"];
+        a -> b [rankdir=LR, arrowhead=vee, weight=x];
+        subgraph cluster_x { rank=same; layout=dot; c; }
+    }";
+    let graph = DotParser::new(program).process().unwrap();
+    let ignored = validate_attributes(&graph);
+    let found: Vec<(&str, IgnoreReason)> = ignored
+        .iter()
+        .map(|x| (x.name.as_str(), x.reason.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("rankdir", IgnoreReason::InvalidValue("TB or LR")),
+            ("sizee", IgnoreReason::Unknown),
+            ("colour", IgnoreReason::Unknown),
+            ("shape", IgnoreReason::InvalidValue("a supported shape")),
+            ("pos", IgnoreReason::Unsupported),
+            ("rankdir", IgnoreReason::WrongTarget),
+            ("weight", IgnoreReason::InvalidValue("a number")),
+            ("layout", IgnoreReason::WrongTarget),
+        ]
+    );
+    assert_eq!(
+        ignored[0].to_string(),
+        "graph \"G\": \"rankdir=BT\" is not supported, expected TB or LR"
+    );
+    assert_eq!(
+        ignored[2].to_string(),
+        "node [...]: \"colour\" is not a Graphviz attribute"
+    );
+    assert_eq!(ignored[5].element.as_deref(), Some("a -> b"));
+    assert_eq!(
+        ignored[7].to_string(),
+        "subgraph \"cluster_x\": \"layout\" does not apply to a subgraph"
+    );
+}
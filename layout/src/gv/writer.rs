@@ -0,0 +1,491 @@
+//! Writes graphs in the DOT format. The AST of a graph is printed as canonical
+//! DOT text, that the parser reads back into the same AST, and the positions
+//! of a layout are added as attributes, like in the output of `dot -Tdot`.
+
+use crate::adt::dag::NodeHandle;
+use crate::backends::xdot::{XDotWriter, XDOT_VERSION};
+use crate::core::geometry::Point;
+use crate::core::utils::format_number;
+use crate::gv::parser::ast;
+use crate::topo::layout::{LayoutResult, VisualGraph};
+use std::collections::HashMap;
+
+/// The 'width' and 'height' attributes of nodes are in inches.
+const POINTS_PER_INCH: f64 = 72.;
+
+/// \returns the graph \p graph as DOT text. The graph is a 'graph' if all of
+/// its edges are undirected, and a 'digraph' otherwise.
+pub fn write_dot(graph: &ast::Graph) -> String {
+    let mut num_edges = (0, 0);
+    count_edges(graph, &mut num_edges);
+    let (directed, undirected) = num_edges;
+    let keyword = if undirected > 0 && directed == 0 {
+        "graph"
+    } else {
+        "digraph"
+    };
+
+    let mut res = String::from(keyword);
+    if !graph.name.is_empty() {
+        res.push(' ');
+        res.push_str(&quote_id(&graph.name));
+    }
+    res.push_str(" {\n");
+    write_stmt_list(&graph.list, 1, &mut res);
+    res.push_str("}\n");
+    res
+}
+
+/// Count the directed and the undirected edges in \p graph into \p counts.
+fn count_edges(graph: &ast::Graph, counts: &mut (usize, usize)) {
+    for stmt in graph.list.list.iter() {
+        match stmt {
+            ast::Stmt::Edge(e) => {
+                for (_, kind) in e.to.iter() {
+                    match kind {
+                        ast::ArrowKind::Arrow => counts.0 += 1,
+                        ast::ArrowKind::Line => counts.1 += 1,
+                    }
+                }
+            }
+            ast::Stmt::SubGraph(g) => count_edges(g, counts),
+            _ => {}
+        }
+    }
+}
+
+fn write_stmt_list(list: &ast::StmtList, depth: usize, res: &mut String) {
+    let indent = "  ".repeat(depth);
+    for stmt in list.list.iter() {
+        res.push_str(&indent);
+        match stmt {
+            ast::Stmt::Edge(e) => {
+                res.push_str(&write_node_id(&e.from));
+                for (id, kind) in e.to.iter() {
+                    res.push_str(match kind {
+                        ast::ArrowKind::Arrow => " -> ",
+                        ast::ArrowKind::Line => " -- ",
+                    });
+                    res.push_str(&write_node_id(id));
+                }
+                res.push_str(&write_attributes(&e.list));
+            }
+            ast::Stmt::Node(n) => {
+                res.push_str(&write_node_id(&n.id));
+                res.push_str(&write_attributes(&n.list));
+            }
+            ast::Stmt::Attribute(a) => {
+                res.push_str(match a.target {
+                    ast::AttrStmtTarget::Graph => "graph",
+                    ast::AttrStmtTarget::Node => "node",
+                    ast::AttrStmtTarget::Edge => "edge",
+                });
+                res.push_str(&write_attributes(&a.list));
+            }
+            ast::Stmt::SubGraph(g) => {
+                res.push_str("subgraph ");
+                if !g.name.is_empty() {
+                    res.push_str(&quote_id(&g.name));
+                    res.push(' ');
+                }
+                res.push_str("{\n");
+                write_stmt_list(&g.list, depth + 1, res);
+                res.push_str(&indent);
+                res.push('}');
+                res.push('\n');
+                continue;
+            }
+        }
+        res.push_str(";\n");
+    }
+}
+
+fn write_node_id(id: &ast::NodeId) -> String {
+    match &id.port {
+        Option::Some(port) => {
+            format!("{}:{}", quote_id(&id.name), quote_id(port))
+        }
+        None => quote_id(&id.name),
+    }
+}
+
+/// \returns the attribute list \p list in brackets, or nothing if the list is
+/// empty.
+fn write_attributes(list: &ast::AttributeList) -> String {
+    if list.list.is_empty() {
+        return String::new();
+    }
+    let attrs: Vec<String> = list
+        .iter()
+        .map(|(key, val)| format!("{}={}", quote_id(key), quote_id(val)))
+        .collect();
+    format!(" [{}]", attrs.join(", "))
+}
+
+/// \returns the identifier \p id as DOT text. Names and numbers are written as
+/// they are, HTML labels are written in angle brackets, and everything else
+/// is quoted.
+pub fn quote_id(id: &str) -> String {
+    let is_name = id
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let keywords = ["graph", "digraph", "subgraph", "node", "edge", "strict"];
+    let is_keyword = keywords.iter().any(|k| k.eq_ignore_ascii_case(id));
+    let is_number = !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && id.matches('.').count() <= 1;
+    let is_html = is_html_id(id);
+    if (is_name && !is_keyword) || is_number || is_html {
+        return id.to_string();
+    }
+
+    // The lexer keeps the escapes of the labels, such as "\l", and only
+    // unescapes quotes and new lines.
+    let mut res = String::from("\"");
+    for c in id.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            _ => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// \returns true if \p id is a single group of balanced angle brackets, like
+/// the HTML labels that the lexer reads. Quoted record labels, such as
+/// "<f0> a | <f1> b", also start and end with angle brackets.
+fn is_html_id(id: &str) -> bool {
+    if !id.starts_with('<') {
+        return false;
+    }
+    let mut depth = 0;
+    for (i, c) in id.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return i + 1 == id.len();
+        }
+    }
+    false
+}
+
+/// Converts the positions of a layout to the attributes of `dot -Tdot`. The
+/// coordinates are in points, and the y axis points up, like in Graphviz.
+pub(crate) struct LayoutAttributes {
+    layout: LayoutResult,
+    drawing: Option<Drawing>,
+}
+
+/// The xdot attributes of the graph, of the nodes and of the edges.
+struct Drawing {
+    graph: Vec<(String, String)>,
+    nodes: Vec<Vec<(String, String)>>,
+    edges: Vec<Vec<(String, String)>>,
+}
+
+impl LayoutAttributes {
+    pub(crate) fn new(layout: &LayoutResult) -> Self {
+        Self {
+            layout: layout.clone(),
+            drawing: None,
+        }
+    }
+
+    /// Create the attributes of the graph \p vg, after layout, with the
+    /// operations that draw each element in the xdot format.
+    pub(crate) fn with_drawing(vg: &VisualGraph) -> Self {
+        let layout = vg.layout_result();
+        let mut xdot = XDotWriter::new(layout.bbox);
+        vg.render_lanes(&mut xdot);
+        vg.render_timeline(&mut xdot);
+        vg.render_clusters(&mut xdot);
+        vg.render_graph_label(&mut xdot);
+        let mut graph = xdot.take_attributes();
+        graph.push(("xdotversion".to_string(), XDOT_VERSION.to_string()));
+        let mut nodes = Vec::new();
+        for i in 0..layout.nodes.len() {
+            vg.render_node(NodeHandle::from(i), &mut xdot);
+            nodes.push(xdot.take_attributes());
+        }
+        let mut edges = Vec::new();
+        for i in 0..layout.edges.len() {
+            vg.render_edge(i, &mut xdot);
+            edges.push(xdot.take_attributes());
+        }
+        let drawing = Drawing {
+            graph,
+            nodes,
+            edges,
+        };
+        Self {
+            layout,
+            drawing: Some(drawing),
+        }
+    }
+
+    fn point(&self, x: f64, y: f64) -> String {
+        let (top, bottom) = (self.layout.bbox.0.y, self.layout.bbox.1.y);
+        format!("{},{}", format_number(x), format_number(top + bottom - y))
+    }
+
+    /// \returns the bounding box of the drawing, as the 'bb' attribute.
+    pub(crate) fn graph(&self) -> Vec<(String, String)> {
+        let (p0, p1) = self.layout.bbox;
+        let bb =
+            format!("{},{}", self.point(p0.x, p1.y), self.point(p1.x, p0.y));
+        let mut res = vec![("bb".to_string(), bb)];
+        if let Option::Some(drawing) = &self.drawing {
+            res.extend(drawing.graph.iter().cloned());
+        }
+        res
+    }
+
+    /// \returns the center and the size of the node \p idx, in the order of
+    /// the handles of the nodes.
+    pub(crate) fn node(&self, idx: usize) -> Vec<(String, String)> {
+        let (p0, p1) = self.layout.nodes[idx];
+        let center = p0.add(p1).scale(0.5);
+        let size = p1.sub(p0).scale(1. / POINTS_PER_INCH);
+        let mut res = vec![
+            ("pos".to_string(), self.point(center.x, center.y)),
+            ("width".to_string(), format_number(size.x)),
+            ("height".to_string(), format_number(size.y)),
+        ];
+        if let Option::Some(drawing) = &self.drawing {
+            res.extend(drawing.nodes[idx].iter().cloned());
+        }
+        res
+    }
+
+    /// \returns the control points of the bezier curve of the edge \p idx,
+    /// in the order in which the edges were added, and the position of its
+    /// label.
+    pub(crate) fn edge(&self, idx: usize) -> Vec<(String, String)> {
+        let edge = &self.layout.edges[idx];
+        let mut res = Vec::new();
+        let mut points = Vec::new();
+        for (i, seg) in edge.path.iter().enumerate() {
+            if i == 0 {
+                points.push(self.point(seg.0.x, seg.0.y));
+            }
+            for p in [seg.1, seg.2, seg.3] {
+                points.push(self.point(p.x, p.y));
+            }
+        }
+        if !points.is_empty() {
+            res.push(("pos".to_string(), points.join(" ")));
+        }
+        if let Option::Some(label) = edge.label {
+            res.push(("lp".to_string(), self.point(label.x, label.y)));
+        }
+        if let Option::Some(drawing) = &self.drawing {
+            res.extend(drawing.edges[idx].iter().cloned());
+        }
+        res
+    }
+}
+
+/// \returns the attributes \p attrs sorted by name, so that the output is
+/// the same in every run.
+pub(crate) fn sorted(attrs: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut res: Vec<(String, String)> =
+        attrs.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    res.sort();
+    res
+}
+
+/// \returns the attribute list with the attributes in \p attrs, and the
+/// attributes in \p extra, that replace the attributes with the same name.
+pub(crate) fn merge_attributes(
+    attrs: Vec<(String, String)>,
+    extra: Vec<(String, String)>,
+) -> ast::AttributeList {
+    let mut res = ast::AttributeList::new();
+    for (key, val) in attrs.iter() {
+        if !extra.iter().any(|x| &x.0 == key) {
+            res.add_attr(key, val);
+        }
+    }
+    for (key, val) in extra.iter() {
+        res.add_attr(key, val);
+    }
+    res
+}
+
+/// The tail, the head and the attributes of an edge in the 'plain' format.
+pub(crate) type PlainEdge = (ast::NodeId, ast::NodeId, Vec<(String, String)>);
+
+/// \returns the layout \p layout in the 'plain' format of Graphviz, that
+/// lists the size of the graph, the nodes and the edges on separate lines, in
+/// inches and with the y axis pointing up. The names and the attributes of
+/// the nodes are in \p nodes, in the order of their handles, and the ends
+/// and the attributes of the edges are in \p edges, in the order in which the
+/// edges were added. If \p extended is set, then the ends of the edges have
+/// their ports, like in the 'plain-ext' format.
+pub(crate) fn write_plain(
+    layout: &LayoutResult,
+    nodes: &[(String, Vec<(String, String)>)],
+    edges: &[PlainEdge],
+    extended: bool,
+) -> String {
+    let (p0, p1) = layout.bbox;
+    let inches = |x: f64| format_number(x / POINTS_PER_INCH);
+    let point =
+        |p: Point| format!("{} {}", inches(p.x - p0.x), inches(p1.y - p.y));
+    let get = |attrs: &[(String, String)], key: &str, default: &str| {
+        let val = attrs.iter().find(|x| x.0 == key).map(|x| x.1.as_str());
+        quote_id(val.unwrap_or(default))
+    };
+
+    let size = p1.sub(p0);
+    let mut res = format!("graph 1 {} {}\n", inches(size.x), inches(size.y));
+    for (i, (name, attrs)) in nodes.iter().enumerate() {
+        let (n0, n1) = layout.nodes[i];
+        let size = n1.sub(n0);
+        let label = attrs.iter().find(|x| x.0 == "label");
+        let label = label.map_or(name.clone(), |x| x.1.replace("\\N", name));
+        res.push_str(&format!(
+            "node {} {} {} {} {} {} {} {} {}\n",
+            quote_id(name),
+            point(n0.add(n1).scale(0.5)),
+            inches(size.x),
+            inches(size.y),
+            quote_id(&label),
+            get(attrs, "style", "solid"),
+            get(attrs, "shape", "ellipse"),
+            get(attrs, "color", "black"),
+            get(attrs, "fillcolor", "lightgrey"),
+        ));
+    }
+    for (i, (from, to, attrs)) in edges.iter().enumerate() {
+        let edge = &layout.edges[i];
+        let end = |id: &ast::NodeId| match &id.port {
+            Option::Some(port) if extended => {
+                format!("{}:{}", quote_id(&id.name), quote_id(port))
+            }
+            _ => quote_id(&id.name),
+        };
+        let mut points = Vec::new();
+        for (j, seg) in edge.path.iter().enumerate() {
+            if j == 0 {
+                points.push(point(seg.0));
+            }
+            points.extend([point(seg.1), point(seg.2), point(seg.3)]);
+        }
+        let mut line =
+            format!("edge {} {} {}", end(from), end(to), points.len());
+        for p in points {
+            line.push(' ');
+            line.push_str(&p);
+        }
+        let label = attrs.iter().find(|x| x.0 == "label");
+        if let (Option::Some(label), Option::Some(pos)) = (label, edge.label) {
+            line.push_str(&format!(" {} {}", quote_id(&label.1), point(pos)));
+        }
+        line.push_str(&format!(
+            " {} {}\n",
+            get(attrs, "style", "solid"),
+            get(attrs, "color", "black")
+        ));
+        res.push_str(&line);
+    }
+    res.push_str("stop\n");
+    res
+}
+
+#[test]
+fn test_write_dot() {
+    use crate::gv::DotParser;
+
+    let program = "digraph G { graph [label=\"a \\\"b\\\"\\l\"]; \
+        x [shape=box, label=<<b>x</b>>]; \"node 1\" -> x:p [weight=2.5]; \
+        subgraph cluster_a { y; } z -- x; }";
+    let graph = DotParser::new(program).process().unwrap();
+    let text = write_dot(&graph);
+    assert!(text.starts_with("digraph G {\n"));
+    assert!(text.contains("  graph [label=\"a \\\"b\\\"\\l\"];\n"));
+    assert!(text.contains("  x [shape=box, label=<<b>x</b>>];\n"));
+    assert!(text.contains("  \"node 1\" -> x:p [weight=2.5];\n"));
+    assert!(text.contains("  subgraph cluster_a {\n    y;\n  }\n"));
+    assert!(text.contains("  z -- x;\n"));
+
+    // The text is parsed back into the same text.
+    let graph = DotParser::new(&text).process().unwrap();
+    assert_eq!(write_dot(&graph), text);
+
+    assert_eq!(quote_id("graph"), "\"graph\"");
+    assert_eq!(quote_id("1.5"), "1.5");
+    assert_eq!(quote_id("-1"), "\"-1\"");
+    assert_eq!(quote_id(""), "\"\"");
+    assert_eq!(quote_id("<f0> a|<f1> b"), "\"<f0> a|<f1> b\"");
+}
+
+#[test]
+fn test_write_plain() {
+    use crate::topo::layout::EdgeLayout;
+
+    let a = NodeHandle::from(0);
+    let b = NodeHandle::from(1);
+    let layout = LayoutResult {
+        nodes: vec![
+            (Point::new(10., 10.), Point::new(82., 46.)),
+            (Point::new(10., 118.), Point::new(82., 154.)),
+        ],
+        edges: vec![EdgeLayout {
+            from: a,
+            to: b,
+            path: vec![(
+                Point::new(46., 46.),
+                Point::new(46., 70.),
+                Point::new(46., 94.),
+                Point::new(46., 118.),
+            )],
+            label: Option::Some(Point::new(64., 82.)),
+        }],
+        clusters: vec![],
+        bbox: (Point::new(0., 0.), Point::new(92., 164.)),
+    };
+    let nodes = vec![
+        ("a".to_string(), vec![]),
+        (
+            "b b".to_string(),
+            vec![("shape".to_string(), "box".to_string())],
+        ),
+    ];
+    let label = vec![("label".to_string(), "x".to_string())];
+    let edges = vec![(
+        ast::NodeId::new("a", &Option::Some("p".to_string())),
+        ast::NodeId::new("b b", &None),
+        label,
+    )];
+
+    let text = write_plain(&layout, &nodes, &edges, false);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "graph 1 1.278 2.278");
+    assert_eq!(
+        lines[1],
+        "node a 0.639 1.889 1 0.5 a solid ellipse black lightgrey"
+    );
+    assert_eq!(
+        lines[2],
+        "node \"b b\" 0.639 0.389 1 0.5 \"b b\" solid box black lightgrey"
+    );
+    assert_eq!(
+        lines[3],
+        "edge a \"b b\" 4 0.639 1.639 0.639 1.306 0.639 0.972 0.639 0.639 \
+         x 0.889 1.139 solid black"
+    );
+    assert_eq!(lines[4], "stop");
+
+    // The extended format has the ports of the edges.
+    let text = write_plain(&layout, &nodes, &edges, true);
+    assert!(text.contains("edge a:p \"b b\" 4 "));
+}
@@ -44,13 +44,9 @@ The example above would print the program AST, or a readable error message,
 such as:
 
 ```txt
-digraph {
-
-node [fillcolor="purple"] A B;
-node [fillcolor="orange"] Z;
+5:33: Expected an identifier, found ';'
 node [fillcolor="green"] G; a = ;
                                 ^
-Error: Expected an identifier.
 ```
 
 
@@ -101,13 +97,70 @@ fn simple_graph() {
 }
 ```
 
+# Fluent builder example: create a graph by name
+
+The `builder` module constructs the same graph without creating the elements
+and the arrows by hand. Nodes are referred to by their names:
+
+```rust
+use layout::backends::svg::SVGWriter;
+use layout::builder::{GraphBuilder, Shape};
+
+let mut graph = GraphBuilder::new();
+graph.node("one").shape(Shape::Box);
+graph.edge("one", "two").label("123");
+
+let mut vg = graph.build();
+let mut svg = SVGWriter::new();
+vg.do_it(false, false, false, &mut svg);
+```
+
+# Layout without std
+
+The layout engine is usable in `no_std` programs that have an allocator.
+Disable the default `std` feature and enable the `libm` feature, that provides
+the math functions:
+
+```toml
+layout-rs = { version = "0.1.1", default-features = false, features = ["libm"] }
+```
+
+Without std, the crate has the `adt`, `core`, `std_shapes` and `topo` modules,
+and the graphs are built with `VisualGraph` and drawn with a custom
+`RenderBackend`, or read with `VisualGraph::layout_result`. The parser, the
+graph builders, the backends and the file IO need std.
+
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_debug_implementations)]
 
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("layout-rs requires either the `std` or the `libm` feature");
+
+extern crate alloc;
+
 pub mod adt;
+#[cfg(feature = "std")]
 pub mod backends;
+#[cfg(feature = "std")]
+pub mod builder;
 pub mod core;
+#[cfg(feature = "std")]
+pub mod er;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fsm;
+#[cfg(feature = "std")]
 pub mod gv;
+#[cfg(feature = "petgraph")]
+pub mod petgraph;
 pub mod std_shapes;
 pub mod topo;
+#[cfg(feature = "std")]
+pub mod tree;
+#[cfg(feature = "std")]
+pub mod uml;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,176 @@
+//! Lays out the graphs of the petgraph crate, such as `Graph` and
+//! `StableGraph`, without converting them to DOT by hand. This module is
+//! enabled by the `petgraph` feature. The caller passes two closures that
+//! style each node and each edge from its weight, with the builders of the
+//! `builder` module.
+//!
+//! ```rust
+//! use layout::backends::svg::SVGWriter;
+//! use layout::builder::Shape;
+//! use petgraph::graph::Graph;
+//!
+//! let mut g = Graph::<&str, u32>::new();
+//! let a = g.add_node("a");
+//! let b = g.add_node("b");
+//! g.add_edge(a, b, 7);
+//!
+//! let node_style = |w: &&str, node: layout::builder::NodeBuilder| {
+//!     node.label(w).shape(Shape::Box);
+//! };
+//! let edge_style = |w: &u32, edge: layout::builder::EdgeBuilder| {
+//!     edge.label(&w.to_string());
+//! };
+//!
+//! // The positions of the nodes, indexed by the indices of petgraph.
+//! let result = layout::petgraph::layout(&g, node_style, edge_style);
+//! assert_eq!(result.nodes.len(), 2);
+//!
+//! // The rendered graph.
+//! let mut svg = SVGWriter::new();
+//! layout::petgraph::render(&g, node_style, edge_style, &mut svg);
+//! ```
+
+use crate::adt::dag::NodeHandle;
+use crate::builder::{EdgeBuilder, GraphBuilder, NodeBuilder};
+use crate::core::format::RenderBackend;
+use crate::core::geometry::Point;
+use crate::topo::layout::LayoutResult;
+use ::petgraph::visit::{
+    EdgeRef, GraphProp, IntoEdgeReferences, IntoNodeReferences, NodeIndexable,
+    NodeRef,
+};
+
+/// \returns a builder with the nodes and the edges of \p graph. The nodes are
+/// named by their index in \p graph, and are styled by \p node_style, that
+/// is called with the weight of each node. The edges are styled by
+/// \p edge_style, that is called with the weight of each edge. The edges of
+/// undirected graphs have no arrow heads.
+pub fn to_builder<G, FN, FE>(
+    graph: G,
+    mut node_style: FN,
+    mut edge_style: FE,
+) -> GraphBuilder
+where
+    G: IntoNodeReferences + IntoEdgeReferences + NodeIndexable + GraphProp,
+    FN: FnMut(&G::NodeWeight, NodeBuilder<'_>),
+    FE: FnMut(&G::EdgeWeight, EdgeBuilder<'_>),
+{
+    let name = |id: G::NodeId| graph.to_index(id).to_string();
+    let mut gb = GraphBuilder::new();
+    for node in graph.node_references() {
+        node_style(node.weight(), gb.node(&name(node.id())));
+    }
+    for edge in graph.edge_references() {
+        let mut builder = gb.edge(&name(edge.source()), &name(edge.target()));
+        if !graph.is_directed() {
+            builder = builder.heads(false, false);
+        }
+        edge_style(edge.weight(), builder);
+    }
+    gb
+}
+
+/// \returns the layout of \p graph, with the nodes and the edges styled by
+/// \p node_style and \p edge_style, like in to_builder(). The nodes of the
+/// result are indexed by their index in \p graph, and the indices of removed
+/// nodes of stable graphs have an empty box at the origin. The edges are in
+/// the order of the edge references of \p graph.
+pub fn layout<G, FN, FE>(
+    graph: G,
+    node_style: FN,
+    edge_style: FE,
+) -> LayoutResult
+where
+    G: IntoNodeReferences + IntoEdgeReferences + NodeIndexable + GraphProp,
+    FN: FnMut(&G::NodeWeight, NodeBuilder<'_>),
+    FE: FnMut(&G::EdgeWeight, EdgeBuilder<'_>),
+{
+    let mut vg = to_builder(graph, node_style, edge_style).build();
+    vg.layout(false, false);
+    let mut result = vg.layout_result();
+
+    // Map the handles of the builder, that are in the order of the node
+    // references, to the indices of the graph.
+    let indices: Vec<usize> = graph
+        .node_references()
+        .map(|node| graph.to_index(node.id()))
+        .collect();
+    let mut nodes = vec![(Point::zero(), Point::zero()); graph.node_bound()];
+    for (i, bbox) in result.nodes.iter().enumerate() {
+        nodes[indices[i]] = *bbox;
+    }
+    result.nodes = nodes;
+    for edge in result.edges.iter_mut() {
+        edge.from = NodeHandle::from(indices[edge.from.get_index()]);
+        edge.to = NodeHandle::from(indices[edge.to.get_index()]);
+    }
+    result
+}
+
+/// Lay out \p graph, with the nodes and the edges styled by \p node_style and
+/// \p edge_style, like in to_builder(), and draw it to the backend \p rb.
+pub fn render<G, FN, FE>(
+    graph: G,
+    node_style: FN,
+    edge_style: FE,
+    rb: &mut dyn RenderBackend,
+) where
+    G: IntoNodeReferences + IntoEdgeReferences + NodeIndexable + GraphProp,
+    FN: FnMut(&G::NodeWeight, NodeBuilder<'_>),
+    FE: FnMut(&G::EdgeWeight, EdgeBuilder<'_>),
+{
+    let mut vg = to_builder(graph, node_style, edge_style).build();
+    vg.do_it(false, false, false, rb);
+}
+
+#[test]
+fn test_petgraph_layout() {
+    use ::petgraph::graph::{Graph, UnGraph};
+    use ::petgraph::stable_graph::StableGraph;
+
+    let mut g = Graph::<&str, &str>::new();
+    let a = g.add_node("a");
+    let b = g.add_node("b");
+    let c = g.add_node("c");
+    g.add_edge(a, b, "x");
+    g.add_edge(b, c, "y");
+    let gb = to_builder(
+        &g,
+        |w, node| {
+            node.label(w);
+        },
+        |w, edge| {
+            edge.label(w);
+        },
+    );
+    assert_eq!(gb.num_nodes(), 3);
+    let dot = gb.to_dot(None);
+    assert!(dot.contains("  0 [label=a];\n"));
+    assert!(dot.contains("  1 -> 2 [label=y];\n"));
+
+    // The nodes of the layout follow the indices of the graph, and the edges
+    // point down.
+    let result = layout(&g, |_, _| {}, |_, _| {});
+    assert_eq!(result.nodes.len(), 3);
+    assert!(result.nodes[0].1.y < result.nodes[1].0.y);
+    assert!(result.nodes[1].1.y < result.nodes[2].0.y);
+
+    // Removed nodes of stable graphs keep their index.
+    let mut sg = StableGraph::<u32, ()>::new();
+    let a = sg.add_node(0);
+    let b = sg.add_node(1);
+    let c = sg.add_node(2);
+    sg.add_edge(a, c, ());
+    sg.remove_node(b);
+    let result = layout(&sg, |_, _| {}, |_, _| {});
+    assert_eq!(result.nodes.len(), 3);
+    assert_eq!(result.nodes[1], (Point::zero(), Point::zero()));
+    assert_eq!(result.edges[0].from, NodeHandle::from(0));
+    assert_eq!(result.edges[0].to, NodeHandle::from(2));
+    assert!(result.nodes[0].1.y < result.nodes[2].0.y);
+
+    // The edges of undirected graphs have no arrow heads.
+    let g = UnGraph::<(), ()>::from_edges([(0, 1)]);
+    let dot = to_builder(&g, |_, _| {}, |_, _| {}).to_dot(None);
+    assert!(dot.contains("0 -> 1 [dir=none]"));
+}
@@ -1,28 +1,38 @@
 //! Implements the drawing of elements and arrows on the backing canvas.
 
-use crate::core::base::Orientation;
+use crate::core::base::{Orientation, SplineKind};
+use crate::core::color::Color;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
 use crate::core::format::{ClipHandle, RenderBackend, Renderable, Visible};
 use crate::core::geometry::*;
 use crate::core::style::{LineStyleKind, StyleAttr};
+use crate::core::text::Justification;
+use crate::core::text::{clip_label_lines, get_label_lines, get_label_text};
+use crate::core::text::{get_line_width, get_size_for_text, TextMetrics};
 use crate::std_shapes::shapes::*;
+use crate::topo::placer::timeline::TICK_SIZE;
+use crate::topo::placer::TimeAxis;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
 
 /// Return the height and width of the record, depending on the geometry and
 /// internal text.
 fn get_record_size(
     rec: &RecordDef,
     dir: Orientation,
-    font_size: usize,
+    look: &StyleAttr,
 ) -> Point {
     match rec {
-        RecordDef::Text(label, _) => pad_shape_scalar(
-            get_size_for_str(label, font_size),
-            BOX_SHAPE_PADDING,
-        ),
+        RecordDef::Text(label, _) => {
+            pad_label(get_size_for_text(label, look), look, BOX_SHAPE_PADDING)
+        }
         RecordDef::Array(arr) => {
             let mut x: f64 = 0.;
             let mut y: f64 = 0.;
             for elem in arr {
-                let ret = get_record_size(elem, dir.flip(), font_size);
+                let ret = get_record_size(elem, dir.flip(), look);
                 if dir.is_left_right() {
                     x += ret.x;
                     y = y.max(ret.y);
@@ -36,36 +46,531 @@ fn get_record_size(
     }
 }
 
+/// \returns the size of the lines of text \p lines, and the size of each
+/// line. Runs without a font size use the font of the style \p look.
+fn get_html_text_size(
+    lines: &[Vec<HtmlText>],
+    look: &StyleAttr,
+) -> (Point, Vec<Point>) {
+    let mut sizes = Vec::new();
+    let mut total = Point::zero();
+    for line in lines {
+        let mut size = Point::zero();
+        for run in line {
+            let look = get_html_run_look(run, look);
+            size.x += get_line_width(&run.text, &look);
+            size.y = size.y.max(look.font_size as f64);
+        }
+        if line.is_empty() {
+            size.y = look.font_size as f64;
+        }
+        total = Point::new(total.x.max(size.x), total.y + size.y);
+        sizes.push(size);
+    }
+    (total, sizes)
+}
+
+/// \returns the style of the text run \p run in a label with the style \p look.
+fn get_html_run_look(run: &HtmlText, look: &StyleAttr) -> StyleAttr {
+    let mut look = look.clone();
+    look.font_size = run.font_size.unwrap_or(look.font_size);
+    look
+}
+
+fn get_html_cell_border(table: &HtmlTable, cell: &HtmlCell) -> usize {
+    cell.border.or(table.cell_border).unwrap_or(table.border)
+}
+
+/// Place the cells of \p table on a grid. \returns the size of the table,
+/// and the location (of the top left corner, relative to the top left corner
+/// of the table) and size of each cell, in row order.
+fn layout_html_table(
+    table: &HtmlTable,
+    look: &StyleAttr,
+) -> (Point, Vec<(Point, Point)>) {
+    // Assign a row and column to each cell. Cells skip the grid slots that
+    // are taken by the cells above them that span multiple rows.
+    let mut taken: Vec<Vec<bool>> = Vec::new();
+    let mut places: Vec<(usize, usize)> = Vec::new();
+    for (r, row) in table.rows.iter().enumerate() {
+        let mut c = 0;
+        for cell in row {
+            while taken.get(r).is_some_and(|t| t.get(c) == Some(&true)) {
+                c += 1;
+            }
+            for rr in r..r + cell.rowspan {
+                if taken.len() <= rr {
+                    taken.resize(rr + 1, Vec::new());
+                }
+                if taken[rr].len() < c + cell.colspan {
+                    taken[rr].resize(c + cell.colspan, false);
+                }
+                for slot in &mut taken[rr][c..c + cell.colspan] {
+                    *slot = true;
+                }
+            }
+            places.push((r, c));
+            c += cell.colspan;
+        }
+    }
+    let num_rows = taken.len();
+    let num_cols = taken.iter().map(|t| t.len()).max().unwrap_or(0);
+
+    let cells: Vec<&HtmlCell> = table.rows.iter().flatten().collect();
+    let sizes: Vec<Point> = cells
+        .iter()
+        .map(|cell| {
+            let pad = table.cell_padding + get_html_cell_border(table, cell);
+            let size = get_html_size(&cell.content, look);
+            pad_shape_scalar(size, 2. * pad as f64)
+        })
+        .collect();
+
+    // Find the width of the columns and the height of the rows. Start with
+    // the cells that span a single slot, and grow the slots evenly to make
+    // room for the other cells.
+    let spacing = table.cell_spacing as f64;
+    let mut widths = vec![0.; num_cols];
+    let mut heights = vec![0.; num_rows];
+    for single in [true, false] {
+        for (i, cell) in cells.iter().enumerate() {
+            let (r, c) = places[i];
+            if (cell.colspan == 1) == single {
+                let span = &mut widths[c..c + cell.colspan];
+                let extra = spacing * (cell.colspan - 1) as f64;
+                let missing = sizes[i].x - span.iter().sum::<f64>() - extra;
+                if missing > 0. {
+                    let len = span.len() as f64;
+                    span.iter_mut().for_each(|w| *w += missing / len);
+                }
+            }
+            if (cell.rowspan == 1) == single {
+                let span = &mut heights[r..r + cell.rowspan];
+                let extra = spacing * (cell.rowspan - 1) as f64;
+                let missing = sizes[i].y - span.iter().sum::<f64>() - extra;
+                if missing > 0. {
+                    let len = span.len() as f64;
+                    span.iter_mut().for_each(|h| *h += missing / len);
+                }
+            }
+        }
+    }
+
+    // Find the offset of each column and row.
+    let border = table.border as f64;
+    let offsets = |lengths: &[f64]| {
+        let mut res = Vec::new();
+        let mut pos = border + spacing;
+        for len in lengths {
+            res.push(pos);
+            pos += len + spacing;
+        }
+        (res, pos + border)
+    };
+    let (xs, width) = offsets(&widths);
+    let (ys, height) = offsets(&heights);
+
+    let rects = cells
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let (r, c) = places[i];
+            let w: f64 = widths[c..c + cell.colspan].iter().sum::<f64>()
+                + spacing * (cell.colspan - 1) as f64;
+            let h: f64 = heights[r..r + cell.rowspan].iter().sum::<f64>()
+                + spacing * (cell.rowspan - 1) as f64;
+            (Point::new(xs[c], ys[r]), Point::new(w, h))
+        })
+        .collect();
+    (Point::new(width, height), rects)
+}
+
+/// Return the size of the HTML-like label \p label, with the default font
+/// of the style \p look.
+fn get_html_size(label: &HtmlLabel, look: &StyleAttr) -> Point {
+    match label {
+        HtmlLabel::Text(lines) => get_html_text_size(lines, look).0,
+        HtmlLabel::Table(table) => layout_html_table(table, look).0,
+    }
+}
+
+/// Render the HTML-like label \p label centered at \p loc.
+fn render_html(
+    label: &HtmlLabel,
+    loc: Point,
+    look: &StyleAttr,
+    canvas: &mut dyn RenderBackend,
+) {
+    match label {
+        HtmlLabel::Text(lines) => {
+            let (total, sizes) = get_html_text_size(lines, look);
+            let mut y = loc.y - total.y / 2.;
+            for (line, size) in lines.iter().zip(sizes) {
+                let mut x = loc.x - size.x / 2.;
+                for run in line {
+                    let look = get_html_run_look(run, look);
+                    let width = get_line_width(&run.text, &look);
+                    if !run.text.trim().is_empty() {
+                        let xy = Point::new(x + width / 2., y + size.y / 2.);
+                        canvas.draw_text(xy, &run.text, &look);
+                    }
+                    x += width;
+                }
+                y += size.y;
+            }
+        }
+        HtmlLabel::Table(table) => {
+            let (size, rects) = layout_html_table(table, look);
+            let top_left = loc.sub(size.scale(0.5));
+            let color = table.color.unwrap_or(look.line_color);
+            let style = |width: usize, bgcolor: Option<Color>| {
+                let mut style = look.clone();
+                style.penwidth = width as f64;
+                style.line_color = color;
+                if width == 0 {
+                    style.line_color = Color::transparent();
+                }
+                style.fill_color = bgcolor;
+                style.gradient = None;
+                style.rounded = 0;
+                style
+            };
+            canvas.draw_rect(
+                top_left,
+                size,
+                &style(table.border, table.bgcolor),
+                Option::None,
+                Option::None,
+            );
+            let cells = table.rows.iter().flatten();
+            for (cell, (xy, size)) in cells.zip(rects) {
+                let xy = top_left.add(xy);
+                let cell_border = get_html_cell_border(table, cell);
+                if cell_border > 0 || cell.bgcolor.is_some() {
+                    canvas.draw_rect(
+                        xy,
+                        size,
+                        &style(cell_border, cell.bgcolor),
+                        Option::None,
+                        Option::None,
+                    );
+                }
+                let center = xy.add(size.scale(0.5));
+                render_html(&cell.content, center, look, canvas);
+            }
+        }
+    }
+}
+
+/// \returns the location and size of the table cell with the port
+/// \p port_name, in the label \p label that is centered at \p loc. The cells
+/// of a table that share a port, such as the cells of a row, make one region
+/// that covers all of them.
+fn get_html_port_location(
+    label: &HtmlLabel,
+    loc: Point,
+    look: &StyleAttr,
+    port_name: &str,
+) -> Option<(Point, Point)> {
+    if let HtmlLabel::Table(table) = label {
+        let (size, rects) = layout_html_table(table, look);
+        let top_left = loc.sub(size.scale(0.5));
+        let cells: Vec<_> = table.rows.iter().flatten().zip(rects).collect();
+        let mut region: Option<(Point, Point)> = None;
+        for (cell, (xy, size)) in cells.iter() {
+            if cell.port.as_deref() != Some(port_name) {
+                continue;
+            }
+            let (lo, hi) = (top_left.add(*xy), top_left.add(*xy).add(*size));
+            region = Some(match region {
+                Option::Some((a, b)) => (
+                    Point::new(a.x.min(lo.x), a.y.min(lo.y)),
+                    Point::new(b.x.max(hi.x), b.y.max(hi.y)),
+                ),
+                None => (lo, hi),
+            });
+        }
+        if let Option::Some((lo, hi)) = region {
+            return Some((lo.add(hi).scale(0.5), hi.sub(lo)));
+        }
+        for (cell, (xy, size)) in cells.iter() {
+            let center = top_left.add(*xy).add(size.scale(0.5));
+            let inner =
+                get_html_port_location(&cell.content, center, look, port_name);
+            if inner.is_some() {
+                return inner;
+            }
+        }
+    }
+    None
+}
+
 const BOX_SHAPE_PADDING: f64 = 10.;
 const CIRCLE_SHAPE_PADDING: f64 = 20.;
+const POINT_SHAPE_SIZE: f64 = 10.;
+
+/// The largest size of the folded corner of notes and of the tab of folders.
+const FOLD_SIZE: f64 = 10.;
+
+/// \returns how much larger than the label the shape \p kind is, so that the
+/// label fits inside of the outline.
+fn get_polygon_scale(kind: PolygonKind) -> Point {
+    match kind {
+        PolygonKind::Diamond => Point::new(2., 2.),
+        PolygonKind::Triangle | PolygonKind::InvTriangle => Point::new(2.5, 2.),
+        PolygonKind::Trapezium
+        | PolygonKind::Parallelogram
+        | PolygonKind::Hexagon => Point::new(1.5, 1.),
+        PolygonKind::Octagon => Point::new(1.5, 1.5),
+        PolygonKind::House
+        | PolygonKind::Cylinder
+        | PolygonKind::Note
+        | PolygonKind::Folder => Point::new(1., 1.5),
+        PolygonKind::Component | PolygonKind::Plaintext => Point::new(1., 1.),
+    }
+}
+
+/// \returns the size of the corner of notes and of the tab of folders, in
+/// shapes of the size \p size.
+fn get_fold_size(size: Point) -> f64 {
+    (size.x.min(size.y) / 4.).min(FOLD_SIZE)
+}
+
+/// \returns the corners of the outline of the shape \p kind, with the center
+/// \p loc and the size \p size. Edges connect to this outline. Shapes that
+/// are not polygons, such as cylinders, are approximated with their box.
+pub fn get_polygon_outline(
+    kind: PolygonKind,
+    loc: Point,
+    size: Point,
+) -> Vec<Point> {
+    // The corners are given as fractions of the size, relative to the center.
+    let third = 1. / 3.;
+    let sixth = 1. / 6.;
+    let corners: Vec<(f64, f64)> = match kind {
+        PolygonKind::Diamond => {
+            vec![(0., -0.5), (0.5, 0.), (0., 0.5), (-0.5, 0.)]
+        }
+        PolygonKind::Triangle => vec![(0., -0.5), (0.5, 0.5), (-0.5, 0.5)],
+        PolygonKind::InvTriangle => {
+            vec![(-0.5, -0.5), (0.5, -0.5), (0., 0.5)]
+        }
+        PolygonKind::Trapezium => {
+            vec![(-third, -0.5), (third, -0.5), (0.5, 0.5), (-0.5, 0.5)]
+        }
+        PolygonKind::Parallelogram => {
+            vec![(-third, -0.5), (0.5, -0.5), (third, 0.5), (-0.5, 0.5)]
+        }
+        PolygonKind::Hexagon => vec![
+            (-third, -0.5),
+            (third, -0.5),
+            (0.5, 0.),
+            (third, 0.5),
+            (-third, 0.5),
+            (-0.5, 0.),
+        ],
+        PolygonKind::Octagon => vec![
+            (-third, -0.5),
+            (third, -0.5),
+            (0.5, -third),
+            (0.5, third),
+            (third, 0.5),
+            (-third, 0.5),
+            (-0.5, third),
+            (-0.5, -third),
+        ],
+        PolygonKind::House => vec![
+            (0., -0.5),
+            (0.5, -sixth),
+            (0.5, 0.5),
+            (-0.5, 0.5),
+            (-0.5, -sixth),
+        ],
+        PolygonKind::Note | PolygonKind::Folder => {
+            // The fold is measured in pixels, and not as a part of the size.
+            let fold = get_fold_size(size);
+            let (left, top) = (loc.x - size.x / 2., loc.y - size.y / 2.);
+            let (right, bottom) = (loc.x + size.x / 2., loc.y + size.y / 2.);
+            return if kind == PolygonKind::Note {
+                vec![
+                    Point::new(left, top),
+                    Point::new(right - fold, top),
+                    Point::new(right, top + fold),
+                    Point::new(right, bottom),
+                    Point::new(left, bottom),
+                ]
+            } else {
+                let tab = left + size.x / 3.;
+                vec![
+                    Point::new(left, top),
+                    Point::new(tab, top),
+                    Point::new(tab + fold, top + fold),
+                    Point::new(right, top + fold),
+                    Point::new(right, bottom),
+                    Point::new(left, bottom),
+                ]
+            };
+        }
+        PolygonKind::Cylinder
+        | PolygonKind::Component
+        | PolygonKind::Plaintext => {
+            vec![(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)]
+        }
+    };
+    corners
+        .iter()
+        .map(|(x, y)| Point::new(loc.x + x * size.x, loc.y + y * size.y))
+        .collect()
+}
+
+/// \returns the center of the label of the shape \p kind, with the center
+/// \p loc and the size \p size. Labels are moved to the wide part of shapes
+/// such as triangles.
+fn get_polygon_label_location(
+    kind: PolygonKind,
+    loc: Point,
+    size: Point,
+) -> Point {
+    match kind {
+        PolygonKind::Triangle | PolygonKind::House => {
+            Point::new(loc.x, loc.y + size.y / 6.)
+        }
+        PolygonKind::InvTriangle => Point::new(loc.x, loc.y - size.y / 6.),
+        PolygonKind::Folder => {
+            Point::new(loc.x, loc.y + get_fold_size(size) / 2.)
+        }
+        _ => loc,
+    }
+}
+
+/// Draw the outline of the shape \p kind, with the center \p loc and the
+/// size \p size.
+fn render_polygon(
+    kind: PolygonKind,
+    loc: Point,
+    size: Point,
+    look: &StyleAttr,
+    properties: Option<String>,
+    canvas: &mut dyn RenderBackend,
+) {
+    let outline = get_polygon_outline(kind, loc, size);
+    let (left, top) = (loc.x - size.x / 2., loc.y - size.y / 2.);
+    let (right, bottom) = (loc.x + size.x / 2., loc.y + size.y / 2.);
+    let unfilled = {
+        let mut x = look.clone();
+        x.fill_color = None;
+        x.gradient = None;
+        x
+    };
+    match kind {
+        PolygonKind::Cylinder => {
+            // The body is made of the sides, the front of the bottom ellipse
+            // and the back of the top ellipse. The front of the top ellipse
+            // is drawn over it. The control points of the quarters of the
+            // ellipses are 'k' of the radius away from the ends.
+            let k = 0.5523;
+            let (rx, ry) = (size.x / 2., size.y / 8.);
+            let (top, bottom) = (top + ry, bottom - ry);
+            let line = |a: Point, b: Point| (a, a, b, b);
+            let arc = |from: Point, to: Point, dy: f64| {
+                let mid = Point::new(loc.x, from.y + dy);
+                let dx = (to.x - from.x).signum() * k * rx;
+                [
+                    (
+                        from,
+                        Point::new(from.x, from.y + k * dy),
+                        Point::new(mid.x - dx, mid.y),
+                        mid,
+                    ),
+                    (
+                        mid,
+                        Point::new(mid.x + dx, mid.y),
+                        Point::new(to.x, to.y + k * dy),
+                        to,
+                    ),
+                ]
+            };
+            let (tl, tr) = (Point::new(left, top), Point::new(right, top));
+            let (bl, br) =
+                (Point::new(left, bottom), Point::new(right, bottom));
+            let mut body = vec![line(tl, bl)];
+            body.extend(arc(bl, br, ry));
+            body.push(line(br, tr));
+            body.extend(arc(tr, tl, -ry));
+            canvas.draw_path(&body, look, properties);
+            canvas.draw_path(&arc(tl, tr, ry), &unfilled, None);
+        }
+        _ => {
+            canvas.draw_polygon(&outline, look, properties, Option::None);
+        }
+    }
+
+    // Draw the decorations inside of the outline.
+    match kind {
+        PolygonKind::Note => {
+            let fold = get_fold_size(size);
+            let corner = Point::new(right - fold, top + fold);
+            canvas.draw_line(outline[1], corner, &unfilled, None);
+            canvas.draw_line(corner, outline[2], &unfilled, None);
+        }
+        PolygonKind::Component => {
+            let rect_size = Point::new(FOLD_SIZE, FOLD_SIZE / 2.);
+            for y in [loc.y - size.y / 4., loc.y + size.y / 4.] {
+                let xy = Point::new(left, y).sub(rect_size.scale(0.5));
+                canvas.draw_rect(xy, rect_size, look, None, Option::None);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Pad the size \p size of a label with the margin of the style \p look on
+/// each side, or with the default padding \p pad of the shape.
+fn pad_label(size: Point, look: &StyleAttr, pad: f64) -> Point {
+    match look.margin {
+        Option::Some(margin) => size.add(margin.scale(2.)),
+        None => pad_shape_scalar(size, pad),
+    }
+}
 
 /// Return the size of the shape. If \p make_xy_same is set then make the
 /// X and the Y of the shape the same. This will turn ellipses into circles and
 /// rectangles into boxes. The parameter \p dir specifies the direction of the
 /// graph. This tells us if we need to draw records left to right or top down.
+/// The text of the shape uses the font of the style \p look.
 pub fn get_shape_size(
     dir: Orientation,
     s: &ShapeKind,
-    font: usize,
+    look: &StyleAttr,
     make_xy_same: bool,
 ) -> Point {
     let mut res = match s {
         ShapeKind::Box(text) => {
-            pad_shape_scalar(get_size_for_str(text, font), BOX_SHAPE_PADDING)
+            pad_label(get_size_for_text(text, look), look, BOX_SHAPE_PADDING)
         }
-        ShapeKind::Circle(text) => {
-            pad_shape_scalar(get_size_for_str(text, font), CIRCLE_SHAPE_PADDING)
+        ShapeKind::Circle(text) | ShapeKind::DoubleCircle(text) => {
+            pad_label(get_size_for_text(text, look), look, CIRCLE_SHAPE_PADDING)
         }
-        ShapeKind::DoubleCircle(text) => {
-            pad_shape_scalar(get_size_for_str(text, font), CIRCLE_SHAPE_PADDING)
+        ShapeKind::Polygon(kind, text) => {
+            let size = get_size_for_text(text, look);
+            let scale = get_polygon_scale(*kind);
+            let size = Point::new(size.x * scale.x, size.y * scale.y);
+            pad_label(size, look, BOX_SHAPE_PADDING)
         }
+        ShapeKind::Point => Point::splat(POINT_SHAPE_SIZE),
         ShapeKind::Record(sr) => {
-            pad_shape_scalar(get_record_size(sr, dir, font), BOX_SHAPE_PADDING)
+            pad_shape_scalar(get_record_size(sr, dir, look), BOX_SHAPE_PADDING)
+        }
+        ShapeKind::Html(label @ HtmlLabel::Table(_)) => {
+            get_html_size(label, look)
+        }
+        ShapeKind::Html(label) => {
+            pad_shape_scalar(get_html_size(label, look), BOX_SHAPE_PADDING)
         }
         ShapeKind::Connector(text) => {
             if let Option::Some(text) = text {
                 pad_shape_scalar(
-                    get_size_for_str(text, font),
+                    get_size_for_text(text, look),
                     BOX_SHAPE_PADDING,
                 )
             } else {
@@ -159,11 +664,12 @@ fn render_record(
         fn handle_text(
             &mut self,
             loc: Point,
-            _size: Point,
+            size: Point,
             label: &str,
             _port: &Option<String>,
         ) {
-            self.canvas.draw_text(loc, label, &self.look);
+            let width = size.x - BOX_SHAPE_PADDING;
+            draw_label(self.canvas, loc, width, label, &self.look);
         }
     }
 
@@ -218,7 +724,7 @@ fn visit_record(
             // Figure out the recursive size of each element, and the largest
             // element.
             for elem in arr {
-                let sz = get_record_size(elem, dir, look.font_size);
+                let sz = get_record_size(elem, dir, look);
                 sizes.push(sz);
                 sum = Point::new(sum.x + sz.x, sum.y + sz.y);
                 mx = Point::new(mx.x.max(sz.x), mx.y.max(sz.y));
@@ -274,6 +780,13 @@ fn visit_record(
 
 impl Renderable for Element {
     fn render(&self, debug: bool, canvas: &mut dyn RenderBackend) {
+        if !self.look.visible {
+            return;
+        }
+        if let Option::Some(properties) = &self.properties {
+            canvas.begin_group(properties);
+        }
+
         if debug {
             // Draw the pink bounding box.
             let debug_look = StyleAttr::debug0();
@@ -282,11 +795,14 @@ impl Renderable for Element {
                 bb.0,
                 self.pos.size(true),
                 &debug_look,
-                self.properties.clone(),
+                Option::None,
                 Option::None,
             );
         }
 
+        if let Option::Some(link) = &self.link {
+            canvas.begin_link(link);
+        }
         match &self.shape {
             ShapeKind::None => {}
             ShapeKind::Record(rec) => {
@@ -299,44 +815,36 @@ impl Renderable for Element {
                     canvas,
                 );
             }
-            ShapeKind::Box(text) => {
-                canvas.draw_rect(
-                    self.pos.bbox(false).0,
-                    self.pos.size(false),
-                    &self.look,
-                    self.properties.clone(),
-                    Option::None,
-                );
-                canvas.draw_text(self.pos.center(), text.as_str(), &self.look);
+            ShapeKind::Html(label) => {
+                // Tables draw their own outline, and text is placed in a box.
+                if let HtmlLabel::Text(_) = label {
+                    canvas.draw_rect(
+                        self.pos.bbox(false).0,
+                        self.pos.size(false),
+                        &self.look,
+                        Option::None,
+                        Option::None,
+                    );
+                }
+                render_html(label, self.pos.center(), &self.look, canvas);
             }
-            ShapeKind::Circle(text) => {
-                canvas.draw_circle(
-                    self.pos.center(),
-                    self.pos.size(false),
-                    &self.look,
-                    self.properties.clone(),
-                );
-                canvas.draw_text(self.pos.center(), text.as_str(), &self.look);
+            ShapeKind::Box(text)
+            | ShapeKind::Circle(text)
+            | ShapeKind::DoubleCircle(text) => {
+                render_borders(self, canvas);
+                render_image(self, canvas);
+                render_element_label(self, self.pos.center(), text, canvas);
             }
-            ShapeKind::DoubleCircle(text) => {
-                canvas.draw_circle(
-                    self.pos.center(),
-                    self.pos.size(false),
-                    &self.look,
-                    self.properties.clone(),
-                );
-                let outer_circle_style = {
-                    let mut x = self.look.clone();
-                    x.fill_color = None;
-                    x
-                };
-                canvas.draw_circle(
-                    self.pos.center(),
-                    self.pos.size(false).add(Point::splat(8.)),
-                    &outer_circle_style,
-                    None,
-                );
-                canvas.draw_text(self.pos.center(), text.as_str(), &self.look);
+            ShapeKind::Polygon(kind, text) => {
+                render_borders(self, canvas);
+                render_image(self, canvas);
+                let loc = self.pos.center();
+                let size = self.pos.size(false);
+                let xy = get_polygon_label_location(*kind, loc, size);
+                render_element_label(self, xy, text, canvas);
+            }
+            ShapeKind::Point => {
+                render_borders(self, canvas);
             }
             ShapeKind::Connector(label) => {
                 if debug {
@@ -357,10 +865,14 @@ impl Renderable for Element {
                     );
                 }
                 if let Option::Some(label) = label {
-                    canvas.draw_text(self.pos.middle(), label, &self.look);
+                    let loc = self.pos.middle();
+                    draw_label(canvas, loc, 0., label, &self.look);
                 }
             }
         }
+        if self.link.is_some() {
+            canvas.end_link();
+        }
         if debug {
             canvas.draw_circle(
                 self.pos.center(),
@@ -369,6 +881,9 @@ impl Renderable for Element {
                 Option::None,
             );
         }
+        if self.properties.is_some() {
+            canvas.end_group();
+        }
     }
 
     fn get_connector_location(
@@ -377,40 +892,66 @@ impl Renderable for Element {
         force: f64,
         port: &Option<String>,
     ) -> (Point, Point) {
+        let (field, compass) = split_port(port);
+        let dir = compass.and_then(get_compass_direction);
+        let mut loc = self.pos.center();
+        let mut size = self.outline_size();
         match &self.shape {
             ShapeKind::None => (Point::zero(), Point::zero()),
-            ShapeKind::Record(rec) => {
-                let mut loc = self.pos.center();
-                let mut size = self.pos.size(false);
-                // Find the region that represents the inner box in the record.
-                if let Option::Some(port_name) = port {
-                    let r = get_record_port_location(
-                        rec,
-                        self.orientation,
-                        loc,
-                        size,
-                        &self.look,
-                        port_name,
-                    );
-                    loc = r.0;
-                    size = r.1;
+            ShapeKind::Record(_) | ShapeKind::Html(_) | ShapeKind::Box(_) => {
+                // Find the region that represents the field in the record or
+                // the cell in the table.
+                if let Option::Some(field) = field {
+                    let r = match &self.shape {
+                        ShapeKind::Record(rec) => {
+                            Some(get_record_port_location(
+                                rec,
+                                self.orientation,
+                                loc,
+                                size,
+                                &self.look,
+                                field,
+                            ))
+                        }
+                        ShapeKind::Html(label) => get_html_port_location(
+                            label, loc, &self.look, field,
+                        ),
+                        _ => None,
+                    };
+                    if let Option::Some(r) = r {
+                        loc = r.0;
+                        size = r.1;
+                    }
+                }
+                if let Option::Some(dir) = dir {
+                    return get_compass_point_for_box(loc, size, dir, force);
                 }
-
-                get_connection_point_for_box(loc, size, from, force)
-            }
-            ShapeKind::Box(_) => {
-                let loc = self.pos.center();
-                let size = self.pos.size(false);
                 get_connection_point_for_box(loc, size, from, force)
             }
-            ShapeKind::Circle(_) => {
-                let loc = self.pos.center();
-                let size = self.pos.size(false);
-                get_connection_point_for_circle(loc, size, from, force)
+            ShapeKind::Polygon(kind, _) => {
+                let outline = get_polygon_outline(*kind, loc, size);
+                // Compass points connect where the outline crosses the line
+                // from the center in their direction.
+                let from = if let Option::Some(dir) = dir {
+                    loc.add(dir.scale(size.x + size.y))
+                } else {
+                    from
+                };
+                let con = get_connection_point_for_polygon(
+                    loc, &outline, from, force,
+                );
+                if let Option::Some(dir) = dir {
+                    let dir = normalize_scale_vector(dir, force);
+                    return (con.0, con.0.add(dir));
+                }
+                con
             }
-            ShapeKind::DoubleCircle(_) => {
-                let loc = self.pos.center();
-                let size = self.pos.size(false);
+            ShapeKind::Circle(_)
+            | ShapeKind::DoubleCircle(_)
+            | ShapeKind::Point => {
+                if let Option::Some(dir) = dir {
+                    return get_compass_point_for_circle(loc, size, dir, force);
+                }
                 get_connection_point_for_circle(loc, size, from, force)
             }
             _ => {
@@ -435,6 +976,359 @@ impl Renderable for Element {
     }
 }
 
+/// Draw the image of \p elem, if it has one, in the middle of the shape.
+fn render_image(elem: &Element, canvas: &mut dyn RenderBackend) {
+    if let Option::Some(image) = &elem.image {
+        let size = elem.pos.size(false);
+        let (xy, size) = image.placement(elem.pos.center(), size);
+        canvas.draw_image(xy, size, &image.href, Option::None);
+    }
+}
+
+/// Draw the borders of the element \p elem, from the inside out. The inner
+/// border is filled, and the outer borders are drawn PERIPHERY_GAP apart. See
+/// Element::peripheries.
+fn render_borders(elem: &Element, canvas: &mut dyn RenderBackend) {
+    let loc = elem.pos.center();
+    let unfilled = {
+        let mut x = elem.look.clone();
+        x.fill_color = None;
+        x.gradient = None;
+        x
+    };
+    for i in 0..elem.peripheries {
+        let gap = 2. * PERIPHERY_GAP * i as f64;
+        let size = elem.pos.size(false).add(Point::splat(gap));
+        let look = if i == 0 { &elem.look } else { &unfilled };
+        let properties = Option::None;
+        match &elem.shape {
+            ShapeKind::Box(_) => {
+                let xy = loc.sub(size.scale(0.5));
+                canvas.draw_rect(xy, size, look, properties, Option::None);
+            }
+            ShapeKind::Circle(_)
+            | ShapeKind::DoubleCircle(_)
+            | ShapeKind::Point => {
+                canvas.draw_circle(loc, size, look, properties);
+            }
+            // The outer borders of polygons don't repeat the decorations.
+            ShapeKind::Polygon(kind, _)
+                if i == 0 || *kind == PolygonKind::Cylinder =>
+            {
+                render_polygon(*kind, loc, size, look, properties, canvas);
+            }
+            ShapeKind::Polygon(kind, _) => {
+                let outline = get_polygon_outline(*kind, loc, size);
+                canvas.draw_polygon(&outline, look, properties, Option::None);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Draw the outline and the label of the cluster \p cluster, that occupies
+/// the box \p bbox.
+/// Draw the label \p label of the element \p elem centered at \p loc. The
+/// label is clipped to the shape if the element asks for it.
+fn render_element_label(
+    elem: &Element,
+    loc: Point,
+    label: &str,
+    canvas: &mut dyn RenderBackend,
+) {
+    let mut lines = get_label_lines(label);
+    if elem.clip_label {
+        lines = clip_label_lines(lines, elem.pos.size(false), &elem.look);
+    }
+    draw_label_lines(canvas, loc, 0., lines, &elem.look);
+}
+
+pub fn render_cluster(
+    cluster: &Cluster,
+    bbox: (Point, Point),
+    canvas: &mut dyn RenderBackend,
+) {
+    if !cluster.look.visible {
+        return;
+    }
+    if let Option::Some(properties) = &cluster.properties {
+        canvas.begin_group(properties);
+    }
+    if let Option::Some(link) = &cluster.link {
+        canvas.begin_link(link);
+    }
+    canvas.draw_rect(
+        bbox.0,
+        bbox.1.sub(bbox.0),
+        &cluster.look,
+        Option::None,
+        Option::None,
+    );
+    if !cluster.label.is_empty() {
+        let size = get_size_for_text(&cluster.label, &cluster.look);
+        let xy = Point::new(
+            (bbox.0.x + bbox.1.x) / 2.,
+            bbox.0.y + BOX_SHAPE_PADDING + size.y / 2.,
+        );
+        draw_label(canvas, xy, 0., &cluster.label, &cluster.look);
+    }
+    if cluster.link.is_some() {
+        canvas.end_link();
+    }
+    if cluster.properties.is_some() {
+        canvas.end_group();
+    }
+}
+
+/// Draw the lane \p lane in the box \p bbox, with its label in the box
+/// \p header. Neighboring lanes share their borders, that divide them.
+pub fn render_lane(
+    lane: &Lane,
+    bbox: (Point, Point),
+    header: (Point, Point),
+    canvas: &mut dyn RenderBackend,
+) {
+    if !lane.look.visible {
+        return;
+    }
+    if let Option::Some(properties) = &lane.properties {
+        canvas.begin_group(properties);
+    }
+    for (a, b) in [bbox, header] {
+        canvas.draw_rect(a, b.sub(a), &lane.look, Option::None, Option::None);
+    }
+    let xy = header.0.add(header.1).scale(0.5);
+    draw_label(canvas, xy, 0., &lane.label, &lane.look);
+    if lane.properties.is_some() {
+        canvas.end_group();
+    }
+}
+
+/// Draw the time axis \p axis of a timeline with the style \p look. The
+/// labels of the ticks are above the axis, and the dashed gridlines go down
+/// from the ticks, behind the nodes.
+pub fn render_time_axis(
+    axis: &TimeAxis,
+    look: &StyleAttr,
+    canvas: &mut dyn RenderBackend,
+) {
+    let mut dashed = look.clone();
+    dashed.dash = vec![2., 2.];
+    let (left, right) = (
+        Point::new(axis.left, axis.y),
+        Point::new(axis.right, axis.y),
+    );
+    canvas.draw_line(left, right, look, Option::None);
+    for (x, label) in axis.ticks.iter() {
+        let (top, bottom) = (axis.y - TICK_SIZE, axis.bottom);
+        let y = axis.y;
+        canvas.draw_line(Point::new(*x, top), Point::new(*x, y), look, None);
+        canvas.draw_line(
+            Point::new(*x, y),
+            Point::new(*x, bottom),
+            &dashed,
+            None,
+        );
+        canvas.draw_text(Point::new(*x, axis.label_y), label, look);
+    }
+}
+
+/// Draw the label \p label centered at \p loc. The lines of the label that
+/// are justified to the left or to the right are aligned with the edges of a
+/// box of width \p width, or with the edges of the text if it is wider.
+fn draw_label(
+    canvas: &mut dyn RenderBackend,
+    loc: Point,
+    width: f64,
+    label: &str,
+    look: &StyleAttr,
+) {
+    draw_label_lines(canvas, loc, width, get_label_lines(label), look);
+}
+
+/// Draw the lines \p lines of a label centered at \p loc. See draw_label.
+fn draw_label_lines(
+    canvas: &mut dyn RenderBackend,
+    loc: Point,
+    width: f64,
+    lines: Vec<(String, Justification)>,
+    look: &StyleAttr,
+) {
+    if lines.iter().all(|x| x.1 == Justification::Center) {
+        let lines: Vec<String> = lines.into_iter().map(|x| x.0).collect();
+        canvas.draw_text(loc, &lines.join("\n"), look);
+        return;
+    }
+    let font_size = look.font_size as f64;
+    let text_width = lines
+        .iter()
+        .map(|x| get_line_width(&x.0, look))
+        .fold(0., f64::max);
+    let width = width.max(text_width);
+    let mut y = loc.y - (lines.len() as f64 * font_size) / 2. + font_size / 2.;
+    for (line, justification) in lines {
+        let line_width = get_line_width(&line, look);
+        let x = match justification {
+            Justification::Left => loc.x - width / 2. + line_width / 2.,
+            Justification::Center => loc.x,
+            Justification::Right => loc.x + width / 2. - line_width / 2.,
+        };
+        if !line.is_empty() {
+            canvas.draw_text(Point::new(x, y), &line, look);
+        }
+        y += font_size;
+    }
+}
+
+/// Draw the title of the graph \p label centered at \p loc.
+pub fn render_graph_label(
+    label: &GraphLabel,
+    loc: Point,
+    canvas: &mut dyn RenderBackend,
+) {
+    draw_label(canvas, loc, 0., &label.text, &label.look);
+}
+
+/// \returns the size of the swatches of the legend \p legend, and the space
+/// around them and around the labels, that scale with the font. The text
+/// backends draw each line in a cell of the grid, so the space takes whole
+/// cells, and the swatches are tall enough for the borders of the nodes.
+fn get_legend_metrics(legend: &Legend) -> (Point, f64) {
+    let font_size = legend.look.font_size as f64;
+    if legend.look.text_metrics == TextMetrics::Grid {
+        return (Point::new(4. * font_size, 3. * font_size), font_size);
+    }
+    (Point::new(2. * font_size, font_size), font_size / 2.)
+}
+
+/// \returns the size of the legend \p legend, with its title above the
+/// entries, and the swatch of each entry to the left of its label.
+pub fn get_legend_size(legend: &Legend) -> Point {
+    let (swatch, pad) = get_legend_metrics(legend);
+    let mut size = Point::new(0., pad);
+    if !legend.title.is_empty() {
+        let title = get_size_for_text(&legend.title, &legend.look);
+        size = Point::new(title.x + 2. * pad, size.y + title.y + pad);
+    }
+    for entry in legend.entries.iter() {
+        let text = get_size_for_text(&entry.label, &legend.look);
+        let width = swatch.x + text.x + 3. * pad;
+        size =
+            Point::new(size.x.max(width), size.y + text.y.max(swatch.y) + pad);
+    }
+    size
+}
+
+/// Draw the legend \p legend with the top-left corner \p xy.
+pub fn render_legend(
+    legend: &Legend,
+    xy: Point,
+    canvas: &mut dyn RenderBackend,
+) {
+    if !legend.look.visible {
+        return;
+    }
+    let (swatch, pad) = get_legend_metrics(legend);
+    let size = get_legend_size(legend);
+    canvas.draw_rect(xy, size, &legend.look, Option::None, Option::None);
+    let mut y = xy.y + pad;
+    if !legend.title.is_empty() {
+        let title = get_size_for_text(&legend.title, &legend.look);
+        let loc = Point::new(xy.x + size.x / 2., y + title.y / 2.);
+        draw_label(canvas, loc, 0., &legend.title, &legend.look);
+        y += title.y + pad;
+    }
+    for entry in legend.entries.iter() {
+        let text = get_size_for_text(&entry.label, &legend.look);
+        let height = text.y.max(swatch.y);
+        let center = Point::new(xy.x + pad + swatch.x / 2., y + height / 2.);
+        match &entry.swatch {
+            LegendSwatch::Node(elem) => {
+                let mut elem = elem.clone();
+                elem.pos =
+                    Position::new(center, swatch, Point::zero(), Point::zero());
+                elem.render(false, canvas);
+            }
+            LegendSwatch::Edge(arrow) => {
+                let half = Point::new(swatch.x / 2., 0.);
+                let (a, b) = (center.sub(half), center.add(half));
+                render_arrow_path(canvas, false, &[(a, a), (b, b)], arrow);
+            }
+        }
+        let x = xy.x + swatch.x + 2. * pad + text.x / 2.;
+        let loc = Point::new(x, y + height / 2.);
+        draw_label(canvas, loc, 0., &entry.label, &legend.look);
+        y += height + pad;
+    }
+}
+
+/// The space between the text of an annotation and its box.
+const ANNOTATION_PADDING: f64 = 4.;
+
+/// The fraction of the width of the drawing that watermarks span.
+const WATERMARK_WIDTH: f64 = 0.8;
+
+/// Draw the box of the annotation text \p text centered at \p loc, and an
+/// arrow from the border of the box to \p target for callouts.
+pub fn render_annotation_box(
+    text: &str,
+    loc: Point,
+    target: Option<Point>,
+    look: &StyleAttr,
+    canvas: &mut dyn RenderBackend,
+) {
+    let size = get_size_for_text(text, look);
+    let size = size.add(Point::splat(2. * ANNOTATION_PADDING));
+    let xy = loc.sub(size.scale(0.5));
+    canvas.draw_rect(xy, size, look, Option::None, Option::None);
+    draw_label(canvas, loc, 0., text, look);
+    if let Option::Some(target) = target {
+        let start = get_connection_point_for_box(loc, size, target, 0.).0;
+        if start.distance_to(target) > 0. {
+            let path = [(start, start), (target, target)];
+            canvas.draw_arrow(&path, false, (false, true), look, None, "");
+        }
+    }
+}
+
+/// Draw the watermark \p text across the middle of the box \p bbox. The
+/// font of \p look is scaled to make the text span most of the width of
+/// the box, without growing taller than the box. The text backends draw
+/// the characters in the cells of the grid, and keep the font.
+pub fn render_watermark(
+    text: &str,
+    bbox: (Point, Point),
+    look: &StyleAttr,
+    canvas: &mut dyn RenderBackend,
+) {
+    let size = get_size_for_text(text, look);
+    let extent = bbox.1.sub(bbox.0);
+    let scale = (WATERMARK_WIDTH * extent.x / size.x).min(extent.y / size.y);
+    let mut look = look.clone();
+    if look.text_metrics != TextMetrics::Grid {
+        look.font_size = ((look.font_size as f64 * scale) as usize).max(1);
+    }
+    let center = bbox.0.add(bbox.1).scale(0.5);
+    canvas.draw_text(center, text, &look);
+}
+
+/// Split the port \p port of an edge, such as "f0:n", "f0" or "n", into the
+/// name of the field and the compass point.
+fn split_port(port: &Option<String>) -> (Option<&str>, Option<&str>) {
+    match port.as_deref() {
+        Option::None => (None, None),
+        Option::Some(port) => {
+            if let Option::Some((field, compass)) = port.rsplit_once(':') {
+                (Some(field), Some(compass))
+            } else if is_compass_point(port) {
+                (None, Some(port))
+            } else {
+                (Some(port), None)
+            }
+        }
+    }
+}
+
 pub fn generate_curve_for_elements(
     elements: &[Element],
     arrow: &Arrow,
@@ -475,16 +1369,230 @@ pub fn generate_curve_for_elements(
     path
 }
 
+/// \returns the path of a self edge of the node \p node, that loops around
+/// the connector \p conn. The connector is next to the node, and holds the
+/// label of the edge, so the loop leaves the label outside of it. The loops
+/// of a node are stacked, because each of them goes around its own connector.
+pub fn generate_curve_for_self_loop(
+    node: &Element,
+    conn: &Element,
+    arrow: &Arrow,
+) -> Vec<(Point, Point)> {
+    let center = node.position().center();
+    let size = node.position().size(false);
+    let bbox = conn.position().bbox(false);
+    let middle = conn.position().middle();
+
+    // Find the side of the node that faces the connector, the direction
+    // \p dir that points to the connector, and the distance to it.
+    let (dir, half_size, half_width, reach) =
+        if (middle.x - center.x).abs() >= (middle.y - center.y).abs() {
+            let sign = (middle.x - center.x).signum();
+            let edge = if sign > 0. { bbox.0.x } else { bbox.1.x };
+            let reach = (edge - center.x).abs();
+            (Point::new(sign, 0.), size.x / 2., size.y / 2., reach)
+        } else {
+            let sign = (middle.y - center.y).signum();
+            let edge = if sign > 0. { bbox.0.y } else { bbox.1.y };
+            let reach = (edge - center.y).abs();
+            (Point::new(0., sign), size.y / 2., size.x / 2., reach)
+        };
+    let side = Point::new(dir.y.abs(), dir.x.abs());
+    let width = (reach - half_size).max(10.);
+    let offset = half_width / 2.;
+
+    // The loop leaves and enters the node on the same side, at equal
+    // distances from the middle of the side.
+    let base = center.add(dir.scale(half_size));
+    let from = base.sub(side.scale(offset));
+    let to = base.add(side.scale(offset));
+    let start = node.get_connector_location(from, 0., &arrow.src_port).0;
+    let end = node.get_connector_location(to, 0., &arrow.dst_port).0;
+
+    // The tip of a cubic bezier curve is at 3/4 of the control points.
+    let force = dir.scale(width * 4. / 3.);
+    let spread = side.scale(width / 2.);
+    let start_ctrl = start.add(force).sub(spread);
+    let end_ctrl = end.add(force).add(spread);
+    vec![(start, start_ctrl), (end_ctrl, end)]
+}
+
+/// \returns a path of straight lines that goes through \p points.
+pub(crate) fn get_polyline_path(points: &[Point]) -> Vec<(Point, Point)> {
+    let mut points = points.to_vec();
+    points.dedup();
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    // The control points of the inner points are the points themselves, so
+    // the reflected control points make corners. The control points of the
+    // ends are on the lines, to give the arrow heads their direction.
+    let last = points.len() - 1;
+    let mut path =
+        vec![(points[0], interpolate(points[0], points[1], 2. / 3.))];
+    for point in &points[1..last] {
+        path.push((*point, *point));
+    }
+    let ctrl = interpolate(points[last], points[last - 1], 2. / 3.);
+    path.push((ctrl, points[last]));
+    path
+}
+
+/// \returns the points of the edge \p arrow that goes through \p elements,
+/// where the segments between the points are horizontal or vertical.
+fn get_ortho_points(elements: &[Element], arrow: &Arrow) -> Vec<Point> {
+    let first = &elements[0];
+    let last = &elements[elements.len() - 1];
+    // The shapes are oriented across the ranks, so shapes that are laid out
+    // left to right belong to top to bottom graphs.
+    let is_tb = first.orientation.is_left_right();
+
+    // Leave and enter the nodes straight along the rank direction.
+    let align = |elem: &Element, to: Point| {
+        let center = elem.position().center();
+        let res = if is_tb {
+            Point::new(center.x, to.y)
+        } else {
+            Point::new(to.x, center.y)
+        };
+        // Edges between nodes on the same rank leave from the side.
+        if res == center {
+            to
+        } else {
+            res
+        }
+    };
+    let next = elements[1].position().center();
+    let prev = elements[elements.len() - 2].position().center();
+    let from = align(first, next);
+    let to = align(last, prev);
+    let start = first.get_connector_location(from, 0., &arrow.src_port).0;
+    let end = last.get_connector_location(to, 0., &arrow.dst_port).0;
+
+    let mut points = vec![start];
+    for elem in &elements[1..elements.len() - 1] {
+        points.push(elem.position().center());
+    }
+    points.push(end);
+
+    // Connect the points with a step in the middle of each segment.
+    let mut res = vec![start];
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if is_tb {
+            let y = (a.y + b.y) / 2.;
+            res.push(Point::new(a.x, y));
+            res.push(Point::new(b.x, y));
+        } else {
+            let x = (a.x + b.x) / 2.;
+            res.push(Point::new(x, a.y));
+            res.push(Point::new(x, b.y));
+        }
+        res.push(b);
+    }
+    res
+}
+
+/// \returns the path of the edge \p arrow that goes through \p elements,
+/// with the edge routing \p splines.
+pub fn generate_curve_for_splines(
+    elements: &[Element],
+    arrow: &Arrow,
+    splines: SplineKind,
+) -> Vec<(Point, Point)> {
+    let first = &elements[0];
+    let last = &elements[elements.len() - 1];
+    let src = &arrow.src_port;
+    let dst = &arrow.dst_port;
+    match splines {
+        SplineKind::Spline => generate_curve_for_elements(elements, arrow, 30.),
+        SplineKind::Line | SplineKind::Curved => {
+            let from = first.position().center();
+            let to = last.position().center();
+            let start = first.get_connector_location(to, 0., src).0;
+            let end = last.get_connector_location(from, 0., dst).0;
+            if let SplineKind::Line = splines {
+                return get_polyline_path(&[start, end]);
+            }
+
+            // Bend the line into an arc, to the left of the direction of the
+            // edge.
+            let dir = end.sub(start);
+            let bend = Point::new(dir.y, -dir.x).scale(0.2);
+            let c1 = interpolate(start, end, 2. / 3.).add(bend);
+            let c2 = interpolate(start, end, 1. / 3.).add(bend);
+            vec![(start, c1), (c2, end)]
+        }
+        SplineKind::Polyline => {
+            let mut points: Vec<Point> =
+                elements.iter().map(|x| x.position().center()).collect();
+            let len = points.len();
+            points[0] = first.get_connector_location(points[1], 0., src).0;
+            points[len - 1] =
+                last.get_connector_location(points[len - 2], 0., dst).0;
+            get_polyline_path(&points)
+        }
+        SplineKind::Ortho => {
+            get_polyline_path(&get_ortho_points(elements, arrow))
+        }
+    }
+}
+
 pub fn render_arrow(
     canvas: &mut dyn RenderBackend,
     debug: bool,
     elements: &[Element],
     arrow: &Arrow,
 ) {
-    let path = generate_curve_for_elements(elements, arrow, 30.);
+    render_arrow_with_splines(
+        canvas,
+        debug,
+        elements,
+        arrow,
+        SplineKind::Spline,
+    );
+}
+
+/// Draw the edge \p arrow that goes through \p elements, with the edge
+/// routing \p splines.
+pub fn render_arrow_with_splines(
+    canvas: &mut dyn RenderBackend,
+    debug: bool,
+    elements: &[Element],
+    arrow: &Arrow,
+    splines: SplineKind,
+) {
+    let path = generate_curve_for_splines(elements, arrow, splines);
+    render_arrow_path(canvas, debug, &path, arrow);
+}
+
+/// Draw the self edge \p arrow of the node \p node, that loops around the
+/// connector \p conn.
+pub fn render_self_loop(
+    canvas: &mut dyn RenderBackend,
+    debug: bool,
+    node: &Element,
+    conn: &Element,
+    arrow: &Arrow,
+) {
+    let path = generate_curve_for_self_loop(node, conn, arrow);
+    render_arrow_path(canvas, debug, &path, arrow);
+}
 
+/// Draw the edge \p arrow along the path \p path, in the form that is
+/// passed to RenderBackend::draw_arrow.
+pub fn render_arrow_path(
+    canvas: &mut dyn RenderBackend,
+    debug: bool,
+    path: &[(Point, Point)],
+    arrow: &Arrow,
+) {
+    if path.is_empty() {
+        return;
+    }
     if debug {
-        for seg in &path {
+        for seg in path {
             canvas.draw_line(seg.0, seg.1, &StyleAttr::debug2(), Option::None);
             canvas.draw_circle(
                 seg.0,
@@ -514,11 +1622,57 @@ pub fn render_arrow(
     let end = matches!(arrow.end, LineEndKind::Arrow);
 
     canvas.draw_arrow(
-        &path,
+        path,
         dash,
         (start, end),
         &arrow.look,
-        arrow.properties.clone(),
-        &arrow.text,
+        Option::None,
+        &get_label_text(&arrow.text),
     );
+
+    let first = path[0];
+    let last = path[path.len() - 1];
+    let labels = [
+        (&arrow.tail_label, first.0, first.1, last.1),
+        (&arrow.head_label, last.1, last.0, first.0),
+    ];
+    for (label, point, ctrl, other_end) in labels {
+        if label.is_empty() {
+            continue;
+        }
+        let loc = get_end_label_location(point, ctrl, other_end, arrow);
+        draw_label(canvas, loc, 0., label, &arrow.look);
+    }
+}
+
+/// The distance between the end of an edge and the center of its head or
+/// tail label, before scaling with 'labeldistance'.
+const END_LABEL_DISTANCE: f64 = 20.;
+
+/// \returns the location of the head or tail label of \p arrow, at the end
+/// \p point of the edge, where \p ctrl is the control point next to it. The
+/// label is placed along the edge, rotated by the label angle of the arrow. If
+/// the control point is at the end of the edge then the direction to
+/// \p other_end is used.
+pub fn get_end_label_location(
+    point: Point,
+    ctrl: Point,
+    other_end: Point,
+    arrow: &Arrow,
+) -> Point {
+    let mut dir = ctrl.sub(point);
+    if dir.length() < 0.01 {
+        dir = other_end.sub(point);
+    }
+    if dir.length() < 0.01 {
+        dir = Point::new(1., 0.);
+    }
+    let dir = dir.scale(1. / dir.length());
+
+    // Positive angles are counterclockwise. The y axis points down.
+    let angle = arrow.label_angle.to_radians();
+    let (sin, cos) = angle.sin_cos();
+    let rotated =
+        Point::new(dir.x * cos + dir.y * sin, dir.y * cos - dir.x * sin);
+    point.add(rotated.scale(END_LABEL_DISTANCE * arrow.label_distance))
 }
@@ -3,15 +3,24 @@
 //! Shapes need to contain all of the information that they need to be rendered.
 //! This includes things like font size, and color.
 
+use crate::adt::dag::NodeHandle;
 use crate::core::base::Orientation;
-use crate::core::format::Visible;
+use crate::core::color::Color;
+use crate::core::format::{Link, Visible};
 use crate::core::geometry::{Point, Position};
+use crate::core::image::Image;
 use crate::core::style::{LineStyleKind, StyleAttr};
+use crate::core::text::Justification;
 use crate::std_shapes::render::get_shape_size;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 const PADDING: f64 = 60.;
 const CONN_PADDING: f64 = 10.;
 
+/// The space between the borders of shapes with multiple borders.
+pub const PERIPHERY_GAP: f64 = 4.;
+
 #[derive(Debug, Copy, Clone)]
 pub enum LineEndKind {
     None,
@@ -35,13 +44,163 @@ impl RecordDef {
     }
 }
 
+/// A run of text in an HTML-like label. The font size is None if the text
+/// uses the font size of the node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlText {
+    pub text: String,
+    pub font_size: Option<usize>,
+}
+
+/// A cell in an HTML-like table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlCell {
+    pub content: HtmlLabel,
+    pub port: Option<String>,
+    pub bgcolor: Option<Color>,
+    // The width of the cell border. None if the table decides.
+    pub border: Option<usize>,
+    pub colspan: usize,
+    pub rowspan: usize,
+}
+
+/// A table in an HTML-like label, such as <TABLE><TR><TD>a</TD></TR></TABLE>.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlTable {
+    pub rows: Vec<Vec<HtmlCell>>,
+    pub border: usize,
+    // The default width of the cell borders. None means 'same as border'.
+    pub cell_border: Option<usize>,
+    pub cell_spacing: usize,
+    pub cell_padding: usize,
+    pub bgcolor: Option<Color>,
+    pub color: Option<Color>,
+}
+
+impl HtmlTable {
+    /// \returns a table with the Graphviz default borders and spacing.
+    pub fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            border: 1,
+            cell_border: None,
+            cell_spacing: 2,
+            cell_padding: 2,
+            bgcolor: None,
+            color: None,
+        }
+    }
+}
+
+impl Default for HtmlTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The content of a Graphviz HTML-like label.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlLabel {
+    /// Lines of text. Each line is made of runs with their own font size.
+    Text(Vec<Vec<HtmlText>>),
+    Table(HtmlTable),
+}
+
+impl HtmlLabel {
+    /// \returns the text of the label, without the formatting.
+    pub fn to_plain_text(&self) -> String {
+        match self {
+            HtmlLabel::Text(lines) => lines
+                .iter()
+                .map(|line| line.iter().map(|run| run.text.as_str()).collect())
+                .collect::<Vec<String>>()
+                .join("\n"),
+            HtmlLabel::Table(table) => table
+                .rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| cell.content.to_plain_text())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// The node shapes that are drawn as an outline around the label, such as
+/// 'diamond' or 'house'.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PolygonKind {
+    Diamond,
+    Triangle,
+    InvTriangle,
+    Trapezium,
+    Parallelogram,
+    Hexagon,
+    Octagon,
+    House,
+    Cylinder,
+    Note,
+    Folder,
+    Component,
+    Plaintext,
+}
+
+impl PolygonKind {
+    /// \returns the shape with the name \p name, such as "hexagon".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "diamond" => Some(PolygonKind::Diamond),
+            "triangle" => Some(PolygonKind::Triangle),
+            "invtriangle" => Some(PolygonKind::InvTriangle),
+            "trapezium" => Some(PolygonKind::Trapezium),
+            "parallelogram" => Some(PolygonKind::Parallelogram),
+            "hexagon" => Some(PolygonKind::Hexagon),
+            "octagon" => Some(PolygonKind::Octagon),
+            "house" => Some(PolygonKind::House),
+            "cylinder" => Some(PolygonKind::Cylinder),
+            "note" => Some(PolygonKind::Note),
+            "folder" => Some(PolygonKind::Folder),
+            "component" => Some(PolygonKind::Component),
+            "plaintext" | "plain" | "none" => Some(PolygonKind::Plaintext),
+            _ => None,
+        }
+    }
+
+    /// \returns the name of the shape in DOT files, such as "hexagon".
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolygonKind::Diamond => "diamond",
+            PolygonKind::Triangle => "triangle",
+            PolygonKind::InvTriangle => "invtriangle",
+            PolygonKind::Trapezium => "trapezium",
+            PolygonKind::Parallelogram => "parallelogram",
+            PolygonKind::Hexagon => "hexagon",
+            PolygonKind::Octagon => "octagon",
+            PolygonKind::House => "house",
+            PolygonKind::Cylinder => "cylinder",
+            PolygonKind::Note => "note",
+            PolygonKind::Folder => "folder",
+            PolygonKind::Component => "component",
+            PolygonKind::Plaintext => "plaintext",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ShapeKind {
     None,
     Box(String),
     Circle(String),
     DoubleCircle(String),
+    Polygon(PolygonKind, String),
+    // A small filled circle, without a label.
+    Point,
     Record(RecordDef),
+    Html(HtmlLabel),
     Connector(Option<String>),
 }
 
@@ -55,6 +214,9 @@ impl ShapeKind {
     pub fn new_double_circle(s: &str) -> Self {
         ShapeKind::DoubleCircle(s.to_string())
     }
+    pub fn new_polygon(kind: PolygonKind, s: &str) -> Self {
+        ShapeKind::Polygon(kind, s.to_string())
+    }
     pub fn new_record(r: &RecordDef) -> Self {
         ShapeKind::Record(r.clone())
     }
@@ -64,6 +226,17 @@ impl ShapeKind {
         }
         ShapeKind::Connector(Some(s.to_string()))
     }
+
+    /// \returns the number of borders that the shape is drawn with, unless
+    /// the user asks for a different number.
+    pub fn default_peripheries(&self) -> usize {
+        match self {
+            ShapeKind::DoubleCircle(_) => 2,
+            ShapeKind::Polygon(PolygonKind::Plaintext, _) => 0,
+            ShapeKind::None | ShapeKind::Connector(_) => 0,
+            _ => 1,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -73,6 +246,20 @@ pub struct Element {
     pub look: StyleAttr,
     pub orientation: Orientation,
     pub properties: Option<String>,
+    // The number of borders around the shape. The outer borders are drawn
+    // PERIPHERY_GAP apart, outside of the shape.
+    pub peripheries: usize,
+    // Clip the label to the shape, instead of letting it overflow.
+    pub clip_label: bool,
+    // An image that is drawn inside of the shape, behind the label.
+    pub image: Option<Image>,
+    // The hyperlink and the tooltip of the shape.
+    pub link: Option<Link>,
+    // The layers of the graph that the shape is drawn in, or None for all
+    // of the layers.
+    pub layers: Option<Vec<usize>>,
+    // Shapes with a higher z-order are drawn over the other shapes.
+    pub z_order: i32,
 }
 
 impl Element {
@@ -83,6 +270,7 @@ impl Element {
         size: Point,
     ) -> Element {
         Element {
+            peripheries: shape.default_peripheries(),
             shape,
             look,
             orientation,
@@ -93,6 +281,11 @@ impl Element {
                 Point::splat(PADDING),
             ),
             properties: Option::None,
+            clip_label: false,
+            image: Option::None,
+            link: Option::None,
+            layers: Option::None,
+            z_order: 0,
         }
     }
 
@@ -123,6 +316,12 @@ impl Element {
                 Point::splat(CONN_PADDING),
             ),
             properties: Option::None,
+            peripheries: 0,
+            clip_label: false,
+            image: Option::None,
+            link: Option::None,
+            layers: Option::None,
+            z_order: 0,
         }
     }
 
@@ -130,12 +329,254 @@ impl Element {
         Self::create_connector("", &StyleAttr::simple(), dir)
     }
 
+    /// \returns the size of the outermost border of the shape.
+    pub fn outline_size(&self) -> Point {
+        let extra = self.peripheries.max(1) - 1;
+        let gap = 2. * PERIPHERY_GAP * extra as f64;
+        self.pos.size(false).add(Point::splat(gap))
+    }
+
     // Make the center of the shape point to \p to.
     pub fn move_to(&mut self, to: Point) {
         self.pos.move_to(to)
     }
 }
 
+/// A group of nodes that is laid out together, and drawn inside a labeled
+/// rectangle. Clusters can be nested in the cluster \p parent.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub label: String,
+    pub look: StyleAttr,
+    pub parent: Option<usize>,
+    pub properties: Option<String>,
+    pub link: Option<Link>,
+    // The layers of the graph that the cluster is drawn in, or None for all
+    // of the layers.
+    pub layers: Option<Vec<usize>>,
+    // Shapes with a higher z-order are drawn over the other clusters.
+    pub z_order: i32,
+}
+
+impl Cluster {
+    pub fn new(label: &str, look: &StyleAttr, parent: Option<usize>) -> Self {
+        Self {
+            label: label.to_string(),
+            look: look.clone(),
+            parent,
+            properties: Option::None,
+            link: Option::None,
+            layers: Option::None,
+            z_order: 0,
+        }
+    }
+}
+
+/// A band across the ranks of a hierarchical layout, that holds the nodes
+/// that are assigned to it. The lanes are columns in graphs that grow top
+/// down, and rows in graphs that grow to the right. Each lane is drawn with
+/// a header that shows its label.
+#[derive(Debug, Clone)]
+pub struct Lane {
+    pub label: String,
+    pub look: StyleAttr,
+    pub properties: Option<String>,
+}
+
+impl Lane {
+    pub fn new(label: &str, look: &StyleAttr) -> Self {
+        Self {
+            label: label.to_string(),
+            look: look.clone(),
+            properties: Option::None,
+        }
+    }
+}
+
+/// The title of the graph, that is drawn above the drawing if \p top is set,
+/// or below it. The title is aligned with the sides of the drawing by
+/// \p justification.
+#[derive(Debug, Clone)]
+pub struct GraphLabel {
+    pub text: String,
+    pub look: StyleAttr,
+    pub top: bool,
+    pub justification: Justification,
+}
+
+impl GraphLabel {
+    pub fn new(text: &str, look: &StyleAttr) -> Self {
+        Self {
+            text: text.to_string(),
+            look: look.clone(),
+            top: false,
+            justification: Justification::Center,
+        }
+    }
+}
+
+/// The corner of the drawing that the legend is placed next to. The legend is
+/// placed beside the drawing, on its left or on its right side, and is
+/// aligned with its top or with its bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegendPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The sample of a style that an entry of the legend is drawn with.
+#[derive(Debug, Clone)]
+pub enum LegendSwatch {
+    /// A small node with the shape and the style of the element.
+    Node(Element),
+    /// A short edge with the line and the heads of the arrow.
+    Edge(Arrow),
+}
+
+#[derive(Debug, Clone)]
+pub struct LegendEntry {
+    pub label: String,
+    pub swatch: LegendSwatch,
+}
+
+/// A box beside the drawing that explains the styles of the nodes and the
+/// edges of the graph, with a swatch and a label for each style, below the
+/// title \p title. The frame and the text of the legend use \p look.
+#[derive(Debug, Clone)]
+pub struct Legend {
+    pub title: String,
+    pub look: StyleAttr,
+    pub position: LegendPosition,
+    pub entries: Vec<LegendEntry>,
+}
+
+impl Legend {
+    pub fn new(title: &str, look: &StyleAttr) -> Self {
+        Self {
+            title: title.to_string(),
+            look: look.clone(),
+            position: LegendPosition::TopRight,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add an entry with the label \p label, that shows the shape and the
+    /// style of the node \p elem. The label, the image and the link of the
+    /// node are not drawn in the swatch.
+    pub fn add_node(&mut self, label: &str, elem: &Element) {
+        let mut elem = elem.clone();
+        elem.shape = get_swatch_shape(&elem.shape);
+        elem.properties = Option::None;
+        elem.image = Option::None;
+        elem.link = Option::None;
+        elem.layers = Option::None;
+        let swatch = LegendSwatch::Node(elem);
+        self.entries.push(LegendEntry {
+            label: label.to_string(),
+            swatch,
+        });
+    }
+
+    /// Add an entry with the label \p label, that shows the line and the
+    /// heads of the edge \p arrow. The labels of the edge are not drawn in
+    /// the swatch.
+    pub fn add_edge(&mut self, label: &str, arrow: &Arrow) {
+        let mut arrow = arrow.clone();
+        arrow.text = String::new();
+        arrow.head_label = String::new();
+        arrow.tail_label = String::new();
+        arrow.properties = Option::None;
+        arrow.link = Option::None;
+        arrow.layers = Option::None;
+        let swatch = LegendSwatch::Edge(arrow);
+        self.entries.push(LegendEntry {
+            label: label.to_string(),
+            swatch,
+        });
+    }
+}
+
+/// The location of an annotation: a point of the drawing, or the offset of a
+/// point from the center of a node, that follows the node when it moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationAnchor {
+    Point(Point),
+    Node(NodeHandle, Point),
+}
+
+#[derive(Debug, Clone)]
+pub enum AnnotationKind {
+    /// A box with the text, centered at the anchor.
+    Text(String),
+    /// A box with the text, centered at the anchor, with an arrow from the
+    /// box to the target. Arrows to the center of a node end at its border.
+    Callout(String, AnnotationAnchor),
+    /// Large text across the middle of the drawing, such as "DRAFT", that is
+    /// scaled to the size of the drawing. The anchor is not used.
+    Watermark(String),
+}
+
+/// Text that is added to the graph after layout, and is drawn over the
+/// diagram without moving its elements. The box, the arrow and the text of
+/// the annotation are drawn with \p look.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    pub anchor: AnnotationAnchor,
+    pub look: StyleAttr,
+}
+
+impl Annotation {
+    pub fn text(
+        text: &str,
+        anchor: AnnotationAnchor,
+        look: &StyleAttr,
+    ) -> Self {
+        Self {
+            kind: AnnotationKind::Text(text.to_string()),
+            anchor,
+            look: look.clone(),
+        }
+    }
+
+    pub fn callout(
+        text: &str,
+        anchor: AnnotationAnchor,
+        target: AnnotationAnchor,
+        look: &StyleAttr,
+    ) -> Self {
+        Self {
+            kind: AnnotationKind::Callout(text.to_string(), target),
+            anchor,
+            look: look.clone(),
+        }
+    }
+
+    pub fn watermark(text: &str, look: &StyleAttr) -> Self {
+        Self {
+            kind: AnnotationKind::Watermark(text.to_string()),
+            anchor: AnnotationAnchor::Point(Point::zero()),
+            look: look.clone(),
+        }
+    }
+}
+
+/// \returns the shape \p shape without its label. Records and HTML labels
+/// are drawn as plain boxes.
+fn get_swatch_shape(shape: &ShapeKind) -> ShapeKind {
+    match shape {
+        ShapeKind::Box(_) | ShapeKind::Record(_) | ShapeKind::Html(_) => {
+            ShapeKind::new_box("")
+        }
+        ShapeKind::Circle(_) => ShapeKind::new_circle(""),
+        ShapeKind::DoubleCircle(_) => ShapeKind::new_double_circle(""),
+        ShapeKind::Polygon(kind, _) => ShapeKind::new_polygon(*kind, ""),
+        _ => shape.clone(),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Arrow {
     pub start: LineEndKind,
@@ -146,6 +587,27 @@ pub struct Arrow {
     pub properties: Option<String>,
     pub src_port: Option<String>,
     pub dst_port: Option<String>,
+    // The labels that are drawn next to the head and the tail of the edge.
+    pub head_label: String,
+    pub tail_label: String,
+    // The distance of the head and tail labels from the ends of the edge, as
+    // a multiple of the default distance.
+    pub label_distance: f64,
+    // The angle, in degrees, between the edge and the head and tail labels.
+    pub label_angle: f64,
+    // The minimal number of ranks between the ends of the edge.
+    pub min_len: usize,
+    // How important it is to keep the edge short and straight.
+    pub weight: f64,
+    // Set if the edge takes part in the ranking of the nodes.
+    pub constraint: bool,
+    // The hyperlink and the tooltip of the edge.
+    pub link: Option<Link>,
+    // The layers of the graph that the edge is drawn in, or None for all
+    // of the layers.
+    pub layers: Option<Vec<usize>>,
+    // Shapes with a higher z-order are drawn over the other edges.
+    pub z_order: i32,
 }
 
 impl Default for Arrow {
@@ -159,21 +621,43 @@ impl Default for Arrow {
             properties: Option::None,
             src_port: Option::None,
             dst_port: Option::None,
+            head_label: String::new(),
+            tail_label: String::new(),
+            label_distance: 1.,
+            label_angle: -25.,
+            min_len: 1,
+            weight: 1.,
+            constraint: true,
+            link: Option::None,
+            layers: Option::None,
+            z_order: 0,
         }
     }
 }
 
 impl Arrow {
     pub fn reverse(&self) -> Arrow {
+        let mut look = self.look.clone();
+        look.arrow_heads = (look.arrow_heads.1, look.arrow_heads.0);
         Arrow {
             start: self.end,
             end: self.start,
             line_style: self.line_style,
             text: self.text.clone(),
-            look: self.look.clone(),
+            look,
             properties: self.properties.clone(),
             src_port: self.dst_port.clone(),
             dst_port: self.src_port.clone(),
+            head_label: self.tail_label.clone(),
+            tail_label: self.head_label.clone(),
+            label_distance: self.label_distance,
+            label_angle: self.label_angle,
+            min_len: self.min_len,
+            weight: self.weight,
+            constraint: self.constraint,
+            link: self.link.clone(),
+            layers: self.layers.clone(),
+            z_order: self.z_order,
         }
     }
 
@@ -195,9 +679,11 @@ impl Arrow {
             properties: Option::None,
             src_port: src_port.clone(),
             dst_port: dst_port.clone(),
+            ..Arrow::default()
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn with_properties(
         start: LineEndKind,
         end: LineEndKind,
@@ -217,6 +703,7 @@ impl Arrow {
             properties: Option::Some(properties.into()),
             src_port: src_port.clone(),
             dst_port: dst_port.clone(),
+            ..Arrow::default()
         }
     }
 
@@ -276,7 +763,7 @@ impl Visible for Element {
             let size = get_shape_size(
                 self.orientation,
                 &self.shape,
-                self.look.font_size,
+                &self.look,
                 false,
             );
             self.pos.set_size(size);
@@ -0,0 +1,251 @@
+//! Computes structural properties of the nodes of a graph, such as their
+//! degree, their depth and their strongly connected component, and maps them
+//! to the colors and the sizes of the nodes, so that the structure of a graph
+//! can be seen at a glance.
+//!
+//! ```
+//! # use layout::gv::{DotParser, GraphBuilder};
+//! use layout::topo::analysis::NodeMetric;
+//!
+//! let mut parser = DotParser::new("digraph { a -> b -> c -> b; c -> d; }");
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(&parser.process().unwrap());
+//! let mut vg = gb.get();
+//! assert_eq!(vg.node_metric(NodeMetric::Depth), vec![0., 1., 1., 2.]);
+//! vg.color_by(NodeMetric::SccId);
+//! vg.size_by(NodeMetric::InDegree);
+//! ```
+
+use crate::adt::dag::NodeHandle;
+use crate::core::color::Color;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The number of colors of the sequential scheme that shades the nodes by
+/// the value of a metric, and of the qualitative scheme that colors the
+/// strongly connected components.
+const SEQUENTIAL_COLORS: usize = 9;
+const QUALITATIVE_COLORS: usize = 12;
+
+/// The shades of the sequential scheme that are dark enough to need white
+/// text.
+const DARK_SHADES: usize = 7;
+
+/// The factor that scales the nodes with the largest value of a metric. The
+/// nodes with the value zero keep their size.
+pub const MAX_SIZE_SCALE: f64 = 2.;
+
+/// A property of the nodes of a graph, that is computed from its edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeMetric {
+    /// The number of edges that enter the node.
+    InDegree,
+    /// The number of edges that leave the node.
+    OutDegree,
+    /// The number of edges that enter or leave the node.
+    Degree,
+    /// The number of edges on the longest path from a node without
+    /// predecessors to the node. The nodes of a cycle share their depth.
+    Depth,
+    /// The index of the strongly connected component of the node. The
+    /// components are numbered in the order of their first node.
+    SccId,
+}
+
+impl NodeMetric {
+    /// \returns true if the values of the metric name groups of nodes, and
+    /// don't measure them.
+    pub fn is_categorical(&self) -> bool {
+        matches!(self, NodeMetric::SccId)
+    }
+}
+
+/// \returns the value of the metric \p metric for each of the \p n nodes of
+/// the graph with the edges \p edges, indexed by the handles of the nodes.
+pub fn compute_metric(
+    n: usize,
+    edges: &[(NodeHandle, NodeHandle)],
+    metric: NodeMetric,
+) -> Vec<f64> {
+    let values = match metric {
+        NodeMetric::InDegree => get_degrees(n, edges, false, true),
+        NodeMetric::OutDegree => get_degrees(n, edges, true, false),
+        NodeMetric::Degree => get_degrees(n, edges, true, true),
+        NodeMetric::Depth => get_depths(n, edges),
+        NodeMetric::SccId => get_scc_ids(n, edges),
+    };
+    values.into_iter().map(|x| x as f64).collect()
+}
+
+/// \returns the number of edges of each node, that count if they leave the
+/// node and \p out is set, or if they enter the node and \p inc is set.
+fn get_degrees(
+    n: usize,
+    edges: &[(NodeHandle, NodeHandle)],
+    out: bool,
+    inc: bool,
+) -> Vec<usize> {
+    let mut res = vec![0; n];
+    for (from, to) in edges.iter() {
+        if out {
+            res[from.get_index()] += 1;
+        }
+        if inc {
+            res[to.get_index()] += 1;
+        }
+    }
+    res
+}
+
+/// \returns the index of the strongly connected component of each node,
+/// with Tarjan's algorithm. The components are numbered in the order of
+/// their first node.
+pub fn get_scc_ids(n: usize, edges: &[(NodeHandle, NodeHandle)]) -> Vec<usize> {
+    let mut succ: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (from, to) in edges.iter() {
+        succ[from.get_index()].push(to.get_index());
+    }
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut component = vec![usize::MAX; n];
+    let mut num_components = 0;
+    let mut counter = 0;
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // The nodes on the path of the search, with the next successor to
+        // visit, instead of recursion.
+        let mut path = vec![(root, 0)];
+        index[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Option::Some((node, next)) = path.pop() {
+            if next < succ[node].len() {
+                path.push((node, next + 1));
+                let child = succ[node][next];
+                if index[child] == usize::MAX {
+                    index[child] = counter;
+                    low[child] = counter;
+                    counter += 1;
+                    stack.push(child);
+                    on_stack[child] = true;
+                    path.push((child, 0));
+                } else if on_stack[child] {
+                    low[node] = low[node].min(index[child]);
+                }
+                continue;
+            }
+            if let Option::Some((parent, _)) = path.last() {
+                low[*parent] = low[*parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                while let Option::Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component[member] = num_components;
+                    if member == node {
+                        break;
+                    }
+                }
+                num_components += 1;
+            }
+        }
+    }
+    // Number the components in the order of their first node.
+    let mut ids = vec![usize::MAX; num_components];
+    let mut next_id = 0;
+    for c in component.iter() {
+        if ids[*c] == usize::MAX {
+            ids[*c] = next_id;
+            next_id += 1;
+        }
+    }
+    component.iter().map(|c| ids[*c]).collect()
+}
+
+/// \returns the number of edges on the longest path from a node without
+/// predecessors to each node, in the graph where the strongly connected
+/// components are merged into one node.
+fn get_depths(n: usize, edges: &[(NodeHandle, NodeHandle)]) -> Vec<usize> {
+    let scc = get_scc_ids(n, edges);
+    let m = scc.iter().map(|x| x + 1).max().unwrap_or(0);
+    let mut succ: Vec<Vec<usize>> = vec![Vec::new(); m];
+    let mut num_preds = vec![0; m];
+    for (from, to) in edges.iter() {
+        let (a, b) = (scc[from.get_index()], scc[to.get_index()]);
+        if a != b {
+            succ[a].push(b);
+            num_preds[b] += 1;
+        }
+    }
+    let mut depth = vec![0; m];
+    let mut worklist: VecDeque<usize> =
+        (0..m).filter(|x| num_preds[*x] == 0).collect();
+    while let Option::Some(c) = worklist.pop_front() {
+        for next in succ[c].iter() {
+            depth[*next] = depth[*next].max(depth[c] + 1);
+            num_preds[*next] -= 1;
+            if num_preds[*next] == 0 {
+                worklist.push_back(*next);
+            }
+        }
+    }
+    scc.iter().map(|c| depth[*c]).collect()
+}
+
+/// \returns the fill color and the font color of a node with the value
+/// \p value of a metric, where \p max is the largest value. The values of
+/// categorical metrics pick the colors of the "set312" scheme, and the other
+/// values pick the shades of the "blues9" scheme.
+pub fn get_metric_colors(
+    value: f64,
+    max: f64,
+    categorical: bool,
+) -> (Color, Color) {
+    let black = Color::fast("black");
+    if categorical {
+        let idx = value as usize % QUALITATIVE_COLORS + 1;
+        return (Color::fast(&format!("/set312/{}", idx)), black);
+    }
+    let shade = if max > 0. { value / max } else { 0. };
+    let level = (shade * (SEQUENTIAL_COLORS - 1) as f64).round() as usize + 1;
+    let level = level.clamp(1, SEQUENTIAL_COLORS);
+    let font = if level >= DARK_SHADES {
+        Color::fast("white")
+    } else {
+        black
+    };
+    (Color::fast(&format!("/blues9/{}", level)), font)
+}
+
+#[test]
+fn test_node_metrics() {
+    let h = NodeHandle::new;
+    // A cycle of b, c and d, between a and e, and f on its own.
+    let edges = [(h(0), h(1)), (h(1), h(2)), (h(2), h(3)), (h(3), h(1))];
+    let edges = [&edges[..], &[(h(3), h(4)), (h(0), h(4))]].concat();
+    let metric = |m| compute_metric(6, &edges, m);
+    assert_eq!(metric(NodeMetric::InDegree), [0., 2., 1., 1., 2., 0.]);
+    assert_eq!(metric(NodeMetric::OutDegree), [2., 1., 1., 2., 0., 0.]);
+    assert_eq!(metric(NodeMetric::Degree), [2., 3., 2., 3., 2., 0.]);
+    assert_eq!(metric(NodeMetric::SccId), [0., 1., 1., 1., 2., 3.]);
+    assert_eq!(metric(NodeMetric::Depth), [0., 1., 1., 1., 2., 0.]);
+
+    let (fill, font) = get_metric_colors(4., 4., false);
+    assert_eq!(fill, Color::fast("/blues9/9"));
+    assert_eq!(font, Color::fast("white"));
+    assert_eq!(get_metric_colors(0., 0., false).0, Color::fast("/blues9/1"));
+    let (a, b) = (
+        get_metric_colors(1., 3., true),
+        get_metric_colors(13., 3., true),
+    );
+    assert_eq!(a, b);
+}
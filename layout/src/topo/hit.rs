@@ -0,0 +1,313 @@
+//! Hit-testing on the layout of a graph. The HitIndex finds the nodes, the
+//! edges and the clusters under a point, or in a rectangle, in the
+//! coordinates of the layout, so that applications that embed the drawing
+//! can implement hovering and selection.
+
+use crate::adt::dag::NodeHandle;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::{flatten_bezier_segments, Point};
+use crate::topo::layout::{ElementRef, LayoutResult};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The default distance from an edge, in points, that still hits the edge.
+pub const EDGE_TOLERANCE: f64 = 4.;
+
+/// The number of straight lines that approximate each bezier segment of an
+/// edge.
+const EDGE_STEPS: usize = 8;
+
+/// An element of the index, with its bounding box.
+#[derive(Debug, Clone)]
+struct HitItem {
+    element: ElementRef,
+    bbox: (Point, Point),
+    /// The polylines of edges. Nodes and clusters are hit in their box.
+    lines: Vec<Vec<Point>>,
+}
+
+/// A spatial index over the nodes, the edges and the clusters of a layout.
+/// The elements are stored in the cells of a uniform grid that covers the
+/// drawing, and the queries only look at the cells that they touch.
+#[derive(Debug, Clone)]
+pub struct HitIndex {
+    items: Vec<HitItem>,
+    // The top-left corner of the grid, the size of the cells, and the number
+    // of columns and rows.
+    origin: Point,
+    cell: f64,
+    cols: usize,
+    rows: usize,
+    // The indices of the items whose bounding box overlaps each cell.
+    cells: Vec<Vec<usize>>,
+    // The distance from an edge that still hits the edge.
+    tolerance: f64,
+}
+
+/// \returns true if the boxes \p a and \p b overlap.
+fn overlaps(a: (Point, Point), b: (Point, Point)) -> bool {
+    a.0.x <= b.1.x && b.0.x <= a.1.x && a.0.y <= b.1.y && b.0.y <= a.1.y
+}
+
+/// \returns true if the box \p inner is inside of the box \p outer.
+fn contains(outer: (Point, Point), inner: (Point, Point)) -> bool {
+    outer.0.x <= inner.0.x
+        && outer.0.y <= inner.0.y
+        && inner.1.x <= outer.1.x
+        && inner.1.y <= outer.1.y
+}
+
+/// \returns the distance from the point \p p to the line segment \p a-\p b.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b.sub(a);
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    if len2 == 0. {
+        return p.distance_to(a);
+    }
+    let ap = p.sub(a);
+    let t = ((ap.x * ab.x + ap.y * ab.y) / len2).clamp(0., 1.);
+    p.distance_to(a.add(ab.scale(t)))
+}
+
+/// \returns true if the line segment \p a-\p b crosses the box \p rect. The
+/// segment is clipped to the box, one axis at a time.
+pub(crate) fn segment_crosses(
+    a: Point,
+    b: Point,
+    rect: (Point, Point),
+) -> bool {
+    let (mut t0, mut t1) = (0_f64, 1_f64);
+    let d = b.sub(a);
+    let edges = [
+        (-d.x, a.x - rect.0.x),
+        (d.x, rect.1.x - a.x),
+        (-d.y, a.y - rect.0.y),
+        (d.y, rect.1.y - a.y),
+    ];
+    for (p, q) in edges {
+        if p == 0. {
+            if q < 0. {
+                return false;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0. {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return false;
+        }
+    }
+    true
+}
+
+/// \returns the bounding box of the points \p points.
+fn get_bbox(points: impl Iterator<Item = Point>) -> (Point, Point) {
+    let inf = f64::INFINITY;
+    let mut bbox = (Point::splat(inf), Point::splat(-inf));
+    for p in points {
+        bbox.0 = Point::new(bbox.0.x.min(p.x), bbox.0.y.min(p.y));
+        bbox.1 = Point::new(bbox.1.x.max(p.x), bbox.1.y.max(p.y));
+    }
+    bbox
+}
+
+impl HitIndex {
+    /// Index the nodes, the edges and the clusters of \p layout.
+    pub fn new(layout: &LayoutResult) -> Self {
+        let mut items = Vec::new();
+        for (i, bbox) in layout.nodes.iter().enumerate() {
+            items.push(HitItem {
+                element: ElementRef::Node(NodeHandle::new(i)),
+                bbox: *bbox,
+                lines: Vec::new(),
+            });
+        }
+        for (i, edge) in layout.edges.iter().enumerate() {
+            let lines = flatten_bezier_segments(&edge.path, EDGE_STEPS);
+            if lines.is_empty() {
+                continue;
+            }
+            items.push(HitItem {
+                element: ElementRef::Edge(i),
+                bbox: get_bbox(lines.iter().flatten().copied()),
+                lines,
+            });
+        }
+        for (i, bbox) in layout.clusters.iter().enumerate() {
+            if let Option::Some(bbox) = bbox {
+                items.push(HitItem {
+                    element: ElementRef::Cluster(i),
+                    bbox: *bbox,
+                    lines: Vec::new(),
+                });
+            }
+        }
+
+        // Make a grid with about one cell per element.
+        let bbox = get_bbox(items.iter().flat_map(|x| [x.bbox.0, x.bbox.1]));
+        let (origin, size) = if items.is_empty() {
+            (Point::zero(), Point::splat(1.))
+        } else {
+            (bbox.0, bbox.1.sub(bbox.0))
+        };
+        let area = (size.x * size.y).max(1.);
+        let cell = (area / items.len().max(1) as f64).sqrt().max(1.);
+        let cols = (size.x / cell).floor() as usize + 1;
+        let rows = (size.y / cell).floor() as usize + 1;
+        let mut index = Self {
+            items,
+            origin,
+            cell,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+            tolerance: EDGE_TOLERANCE,
+        };
+        for i in 0..index.items.len() {
+            let (c0, r0, c1, r1) = index.get_cells(index.items[i].bbox);
+            for r in r0..=r1 {
+                for c in c0..=c1 {
+                    index.cells[r * cols + c].push(i);
+                }
+            }
+        }
+        index
+    }
+
+    /// Set the distance from an edge that still hits the edge to
+    /// \p tolerance.
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        self.tolerance = tolerance.max(0.);
+    }
+
+    /// \returns the range of columns and rows (first column, first row, last
+    /// column, last row) of the cells that the box \p bbox overlaps.
+    fn get_cells(&self, bbox: (Point, Point)) -> (usize, usize, usize, usize) {
+        let col = |x: f64| {
+            let c = ((x - self.origin.x) / self.cell).floor().max(0.);
+            (c as usize).min(self.cols - 1)
+        };
+        let row = |y: f64| {
+            let r = ((y - self.origin.y) / self.cell).floor().max(0.);
+            (r as usize).min(self.rows - 1)
+        };
+        (col(bbox.0.x), row(bbox.0.y), col(bbox.1.x), row(bbox.1.y))
+    }
+
+    /// \returns the indices of the items in the cells that the box \p bbox
+    /// overlaps, in the order of the items, without duplicates.
+    fn get_candidates(&self, bbox: (Point, Point)) -> Vec<usize> {
+        let (c0, r0, c1, r1) = self.get_cells(bbox);
+        let mut res = Vec::new();
+        for r in r0..=r1 {
+            for c in c0..=c1 {
+                res.extend_from_slice(&self.cells[r * self.cols + c]);
+            }
+        }
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    /// \returns the element at the point \p p. Nodes are preferred over the
+    /// edges, and edges over the clusters. Overlapping nodes return the one
+    /// that is drawn last, and nested clusters return the inner cluster.
+    pub fn element_at(&self, p: Point) -> Option<ElementRef> {
+        let tol = Point::splat(self.tolerance);
+        let mut best: Option<(usize, f64, ElementRef)> = None;
+        for i in self.get_candidates((p.sub(tol), p.add(tol))) {
+            let item = &self.items[i];
+            // Lower ranks win, and then smaller distances and areas.
+            let (rank, score) = match item.element {
+                ElementRef::Node(_) => (0, -(i as f64)),
+                ElementRef::Edge(_) => {
+                    let dist = item
+                        .lines
+                        .iter()
+                        .flat_map(|x| x.windows(2))
+                        .map(|x| segment_distance(p, x[0], x[1]))
+                        .fold(f64::INFINITY, f64::min);
+                    if dist > self.tolerance {
+                        continue;
+                    }
+                    (1, dist)
+                }
+                ElementRef::Cluster(_) => {
+                    let size = item.bbox.1.sub(item.bbox.0);
+                    (2, size.x * size.y)
+                }
+            };
+            let is_edge = matches!(item.element, ElementRef::Edge(_));
+            if !is_edge && !contains(item.bbox, (p, p)) {
+                continue;
+            }
+            let is_better = match best {
+                Option::Some((r, s, _)) => rank < r || (rank == r && score < s),
+                None => true,
+            };
+            if is_better {
+                best = Some((rank, score, item.element));
+            }
+        }
+        best.map(|x| x.2)
+    }
+
+    /// \returns the elements in the rectangle from \p top_left to
+    /// \p bottom_right, in the order nodes, edges and clusters. Only the
+    /// elements that are entirely inside of the rectangle are returned if
+    /// \p inside is set, and the elements that touch it otherwise.
+    pub fn elements_in_rect(
+        &self,
+        top_left: Point,
+        bottom_right: Point,
+        inside: bool,
+    ) -> Vec<ElementRef> {
+        let rect = (top_left, bottom_right);
+        let mut res = Vec::new();
+        for i in self.get_candidates(rect) {
+            let item = &self.items[i];
+            let hit = if inside {
+                contains(rect, item.bbox)
+            } else if item.lines.is_empty() {
+                overlaps(rect, item.bbox)
+            } else {
+                item.lines
+                    .iter()
+                    .flat_map(|x| x.windows(2))
+                    .any(|x| segment_crosses(x[0], x[1], rect))
+            };
+            if hit {
+                res.push(item.element);
+            }
+        }
+        res
+    }
+}
+
+#[test]
+fn test_segment_geometry() {
+    let a = Point::new(0., 0.);
+    let b = Point::new(10., 0.);
+    assert_eq!(segment_distance(Point::new(5., 3.), a, b), 3.);
+    assert_eq!(segment_distance(Point::new(-4., 3.), a, b), 5.);
+    assert_eq!(segment_distance(Point::new(1., 1.), a, a), 2_f64.sqrt());
+
+    let rect = (Point::new(2., -1.), Point::new(4., 1.));
+    assert!(segment_crosses(a, b, rect));
+    assert!(!segment_crosses(a, Point::new(1., 0.), rect));
+    assert!(!segment_crosses(
+        Point::new(0., 5.),
+        Point::new(10., 5.),
+        rect
+    ));
+    assert!(segment_crosses(
+        Point::new(3., 5.),
+        Point::new(3., -5.),
+        rect
+    ));
+}
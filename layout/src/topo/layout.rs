@@ -8,30 +8,134 @@
 extern crate log;
 
 use crate::adt::dag::*;
-use crate::core::base::Orientation;
+use crate::adt::HashMap;
+#[cfg(feature = "std")]
+use crate::backends::scaled::ScaledBackend;
+use crate::core::base::{
+    LayoutEngine, LayoutQuality, Orientation, OutputOrder, SplineKind,
+};
+use crate::core::color::Color;
 use crate::core::format::RenderBackend;
 use crate::core::format::Renderable;
+use crate::core::format::Stylesheet;
 use crate::core::format::Visible;
-use crate::core::geometry::Position;
+use crate::core::geometry::{
+    cubic_bezier, get_bezier_segments, Point, Position,
+};
+use crate::core::style::{HighlightStyle, StyleAttr};
+use crate::core::text::{get_size_for_text, Justification};
 use crate::std_shapes::render::*;
 use crate::std_shapes::shapes::*;
-use crate::topo::optimizer::EdgeCrossOptimizer;
 use crate::topo::optimizer::RankOptimizer;
-use std::mem::swap;
-use std::vec;
+use crate::topo::optimizer::{EdgeCrossOptimizer, OrderingConfig};
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::swap;
 
-use super::placer::Placer;
+use super::analysis::{
+    compute_metric, get_metric_colors, NodeMetric, MAX_SIZE_SCALE,
+};
+use super::hit::HitIndex;
+use super::metrics::LayoutMetrics;
+use super::placer::fit::fit_layout;
+use super::placer::pack::pack_boxes;
+use super::placer::timeline::get_ticks;
+use super::placer::EPSILON;
+use super::placer::{
+    CircularPlacer, ForcePlacer, GridConfig, GridPlacer, OverlapConfig,
+    PackConfig, Placer, RadialPlacer, SizeConfig, SpacingConfig, StressConfig,
+    StressPlacer, TimeAxis, TimelineConfig, TimelinePlacer, TreePlacer,
+};
+use super::router::route_edge;
+
+/// The space between the nodes of a cluster and its outline.
+const CLUSTER_PADDING: f64 = 15.;
+
+/// The space between the nodes of a lane and the borders of the lane, and
+/// around the label in the header of the lane.
+const LANE_PADDING: f64 = 15.;
+
+/// The box of a lane, and the box of its header.
+type LaneBoxes = ((Point, Point), (Point, Point));
+
+/// The space between the drawing and the label of the graph.
+const GRAPH_LABEL_GAP: f64 = 10.;
+
+/// The distance between the centers of the nodes of a rank, that rounding
+/// errors may leave.
+const RANK_EPSILON: f64 = 0.5;
+
+/// The space between the nodes in the frames of the ordering of the ranks.
+const ORDERING_FRAME_GAP: f64 = 20.;
+
+/// The location of an edge after layout.
+#[derive(Debug, Clone)]
+pub struct EdgeLayout {
+    /// The tail and the head of the edge, as they were added to the graph.
+    pub from: NodeHandle,
+    pub to: NodeHandle,
+    /// The cubic bezier segments (start, control 1, control 2, end) of the
+    /// edge, from the tail to the head.
+    pub path: Vec<(Point, Point, Point, Point)>,
+    /// The center of the label of the edge, if it has one.
+    pub label: Option<Point>,
+}
+
+/// The geometry of a graph after layout, for users that draw the graph with
+/// their own renderer instead of a RenderBackend.
+#[derive(Debug, Clone)]
+pub struct LayoutResult {
+    /// The bounding box (top-left, bottom-right) of each node, indexed by the
+    /// handles that the nodes were added with.
+    pub nodes: Vec<(Point, Point)>,
+    /// The edges, in the order in which they were added.
+    pub edges: Vec<EdgeLayout>,
+    /// The bounding box of each cluster, in the order in which they were
+    /// added, or None for the clusters without nodes.
+    pub clusters: Vec<Option<(Point, Point)>>,
+    /// The bounding box of the drawing, including the margin.
+    pub bbox: (Point, Point),
+}
+
+/// A snapshot of the locations of the nodes while the layout runs. The frames
+/// of a layout show how the placers move the nodes, for debugging the quality
+/// of the layout and for demos.
+#[derive(Debug, Clone)]
+pub struct LayoutFrame {
+    /// The step of the layout that moved the nodes, such as "ordering",
+    /// "force", "stress" or "final".
+    pub stage: &'static str,
+    /// The centers of the nodes that were added by the user, by handle.
+    pub centers: Vec<Point>,
+}
+
+/// The ranks and the order of the nodes of a previous layout, that a new
+/// layout of an edited graph starts from, so that the unchanged parts of the
+/// graph keep their place.
+#[derive(Debug, Clone, Default)]
+pub struct WarmStart {
+    /// The rank of each node and its position along the rank, indexed by the
+    /// handles of the nodes. New nodes are None.
+    pub nodes: Vec<Option<(usize, f64)>>,
+}
 
 #[derive(Debug)]
 pub struct VisualGraph {
     // Holds all of the elements in the graph.
     nodes: Vec<Element>,
-    // The arrows and the list of elements that they visits.
+    // The arrows and the list of elements that they visits. The edges keep
+    // the order in which they were added.
     edges: Vec<(Arrow, Vec<NodeHandle>)>,
-    // Contains a list of self-edges. We use this as a temporary storage during
-    // lowering. This list should be removes by the time we start the layout
-    // process.
-    self_edges: Vec<(Arrow, NodeHandle)>,
+    // The tail and the head of each edge, as they were added. Lowering may
+    // reverse the edges in the list above.
+    edge_ends: Vec<(NodeHandle, NodeHandle)>,
+    // The number of nodes that were added before lowering added connectors.
+    num_user_nodes: usize,
+    // The bottom-right corner of the canvas, if the drawing has a margin.
+    canvas_corner: Option<Point>,
     // Representing the connections between the nodes. Used to keep the graph
     // a dag by detecting reverse edges. Used to create 'levels', and decide
     // which node moves/controls which node. After lowering, the graph should
@@ -39,121 +143,2003 @@ pub struct VisualGraph {
     pub dag: DAG,
     // Sets the graph orientation (L-to-R, or T-to-B).
     orientation: Orientation,
+    // Sets the way that the edges are routed.
+    splines: SplineKind,
+    // Sets the algorithm that places the nodes.
+    engine: LayoutEngine,
+    // Controls the stress placer.
+    stress: StressConfig,
+    // Controls the grid placer.
+    grid: GridConfig,
+    // Controls the timeline placer, and the width of one unit of time in
+    // the timeline layout.
+    timeline: TimelineConfig,
+    time_scale: Option<f64>,
+    // Controls the removal of the overlaps between the nodes, after the
+    // force-directed and the stress placers.
+    overlap: OverlapConfig,
+    // Controls the space between the nodes of the hierarchical placer.
+    spacing: SpacingConfig,
+    // The space around the drawing, or None to keep the halo of the nodes.
+    margin: Option<Point>,
+    // Stretches the layout, and scales the drawing to the requested size.
+    fit: SizeConfig,
+    // The title of the graph, and the location of its center after layout.
+    label: Option<GraphLabel>,
+    label_pos: Option<Point>,
+    // The legend of the graph, and the location of its top-left corner after
+    // layout.
+    legend: Option<Legend>,
+    legend_pos: Option<Point>,
+    // The annotations that are drawn over the diagram after layout.
+    annotations: Vec<Annotation>,
+    // The nodes and the edges that are highlighted when they are drawn.
+    highlights: HashMap<ElementRef, HighlightStyle>,
+    // The color of the background, or None for the default of the backend.
+    background: Option<Color>,
+    // The CSS stylesheets of the drawing.
+    stylesheets: Vec<Stylesheet>,
+    // The names of the layers of the drawing, or nothing for a drawing
+    // without layers. See Element::layers.
+    layers: Vec<String>,
+    // Selects whether the edges are drawn over the nodes, or under them.
+    output_order: OutputOrder,
+    // Merges the long edges that leave or enter the same node into trunks.
+    concentrate: bool,
+    // Routes the edges around the nodes that are in their way.
+    avoid_nodes: bool,
+    // The node at the center of the radial layout, or at the top of the tree.
+    root: Option<NodeHandle>,
+    // The clusters in the graph. Parents come before their sub-clusters.
+    clusters: Vec<Cluster>,
+    // Maps each node to the innermost cluster that contains it.
+    node_clusters: Vec<Option<usize>>,
+    // The lanes of the graph, in the order in which they are placed, and the
+    // lane of each node.
+    lanes: Vec<Lane>,
+    node_lanes: Vec<Option<usize>>,
+    // The start and the end of each node on the axis of the timeline layout.
+    node_spans: Vec<Option<(f64, f64)>>,
+    // Groups of nodes that must be placed on the same rank, or on the first
+    // or last rank.
+    rank_constraints: Vec<(RankConstraint, Vec<NodeHandle>)>,
+    // The ranks and the order of the nodes in a previous layout.
+    warm_start: Option<WarmStart>,
+    // Seeds the heuristics that shuffle the nodes, or None for the fixed
+    // shuffle.
+    seed: Option<u64>,
+    // Lays out the connected components separately and packs them, or None
+    // to lay out the whole graph at once.
+    pack: Option<PackConfig>,
+    // Controls the optimization of the order of the nodes in the ranks, and
+    // records the number of crossings that are left after layout.
+    ordering: OrderingConfig,
+    crossings: Option<usize>,
+    // Trades the speed of the hierarchical layout for its quality.
+    quality: LayoutQuality,
+    // The snapshots of the layout while it runs, or None to not record them.
+    frames: Option<Vec<LayoutFrame>>,
+}
+
+impl VisualGraph {
+    pub fn new(orientation: Orientation) -> Self {
+        VisualGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            edge_ends: Vec::new(),
+            num_user_nodes: 0,
+            canvas_corner: None,
+            dag: DAG::new(),
+            orientation,
+            splines: SplineKind::Spline,
+            engine: LayoutEngine::Hierarchical,
+            stress: StressConfig::default(),
+            grid: GridConfig::default(),
+            timeline: TimelineConfig::default(),
+            time_scale: None,
+            overlap: OverlapConfig::default(),
+            spacing: SpacingConfig::default(),
+            margin: None,
+            fit: SizeConfig::default(),
+            label: None,
+            label_pos: None,
+            legend: None,
+            legend_pos: None,
+            annotations: Vec::new(),
+            highlights: HashMap::new(),
+            background: None,
+            stylesheets: Vec::new(),
+            layers: Vec::new(),
+            output_order: OutputOrder::NodesFirst,
+            concentrate: false,
+            avoid_nodes: false,
+            root: None,
+            clusters: Vec::new(),
+            node_clusters: Vec::new(),
+            lanes: Vec::new(),
+            node_lanes: Vec::new(),
+            node_spans: Vec::new(),
+            rank_constraints: Vec::new(),
+            warm_start: None,
+            seed: None,
+            pack: None,
+            ordering: OrderingConfig::default(),
+            crossings: None,
+            quality: LayoutQuality::default(),
+            frames: None,
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn splines(&self) -> SplineKind {
+        self.splines
+    }
+
+    /// Route the edges of the graph with \p splines.
+    pub fn set_splines(&mut self, splines: SplineKind) {
+        self.splines = splines;
+    }
+
+    pub fn layout_engine(&self) -> LayoutEngine {
+        self.engine
+    }
+
+    /// Place the nodes of the graph with the algorithm \p engine.
+    pub fn set_layout_engine(&mut self, engine: LayoutEngine) {
+        self.engine = engine;
+    }
+
+    pub fn stress_config(&self) -> StressConfig {
+        self.stress
+    }
+
+    /// Control the stress placer with \p config.
+    pub fn set_stress_config(&mut self, config: StressConfig) {
+        self.stress = config;
+    }
+
+    pub fn grid_config(&self) -> GridConfig {
+        self.grid
+    }
+
+    /// Control the grid placer with \p config.
+    pub fn set_grid_config(&mut self, config: GridConfig) {
+        self.grid = config;
+    }
+
+    pub fn timeline_config(&self) -> TimelineConfig {
+        self.timeline
+    }
+
+    /// Control the timeline placer with \p config.
+    pub fn set_timeline_config(&mut self, config: TimelineConfig) {
+        self.timeline = config;
+    }
+
+    pub fn overlap_config(&self) -> OverlapConfig {
+        self.overlap
+    }
+
+    /// Remove the overlaps between the nodes of the force-directed and the
+    /// stress layouts with \p config.
+    pub fn set_overlap_config(&mut self, config: OverlapConfig) {
+        self.overlap = config;
+    }
+
+    pub fn size_config(&self) -> SizeConfig {
+        self.fit
+    }
+
+    /// Stretch the layout with the ratio of \p config, and scale the drawing
+    /// to its size and its pages.
+    pub fn set_size_config(&mut self, config: SizeConfig) {
+        self.fit = config;
+    }
+
+    pub fn ordering_config(&self) -> OrderingConfig {
+        self.ordering
+    }
+
+    /// Spend the number of iterations in \p config on the reduction of the
+    /// edge crossings.
+    pub fn set_ordering_config(&mut self, config: OrderingConfig) {
+        self.ordering = config;
+    }
+
+    pub fn quality(&self) -> LayoutQuality {
+        self.quality
+    }
+
+    /// Select the ranking algorithm, and scale the number of iterations that
+    /// reduce the edge crossings, of the hierarchical layout with \p quality.
+    pub fn set_quality(&mut self, quality: LayoutQuality) {
+        self.quality = quality;
+    }
+
+    /// \returns the number of edge crossings between the ranks after the
+    /// hierarchical layout, or None if the graph was not laid out in ranks.
+    pub fn num_crossings(&self) -> Option<usize> {
+        self.crossings
+    }
+
+    /// Record the snapshots of the layout while it runs if \p record is set.
+    /// The force-directed and the stress placers record their iterations,
+    /// and the hierarchical placer records the passes that order the ranks.
+    pub fn set_record_frames(&mut self, record: bool) {
+        self.frames = record.then(Vec::new);
+    }
+
+    /// \returns the snapshots of the last layout, that end with the final
+    /// locations of the nodes, or nothing if they were not recorded.
+    pub fn frames(&self) -> &[LayoutFrame] {
+        self.frames.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn is_recording_frames(&self) -> bool {
+        self.frames.is_some()
+    }
+
+    /// Record a snapshot of the layout at the step \p stage, with the nodes
+    /// \p nodes at the locations \p locs and the other nodes where they are.
+    pub(crate) fn add_frame(
+        &mut self,
+        stage: &'static str,
+        nodes: &[NodeHandle],
+        locs: &[Point],
+    ) {
+        if self.frames.is_none() {
+            return;
+        }
+        let mut centers: Vec<Point> = (0..self.num_user_nodes)
+            .map(|i| self.pos(NodeHandle::new(i)).center())
+            .collect();
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            if let Option::Some(center) = centers.get_mut(node.get_index()) {
+                *center = *loc;
+            }
+        }
+        if let Option::Some(frames) = &mut self.frames {
+            frames.push(LayoutFrame { stage, centers });
+        }
+    }
+
+    /// Record the orders of the nodes in the ranks \p snapshots, that the
+    /// passes of the ordering produced, as frames that place the nodes on a
+    /// grid of ranks.
+    fn add_ordering_frames(&mut self, snapshots: &[RankType]) {
+        let cell = (0..self.num_user_nodes)
+            .map(|i| self.pos(NodeHandle::new(i)).size(false))
+            .fold(Point::zero(), |a, b| Point::new(a.x.max(b.x), a.y.max(b.y)))
+            .add(Point::splat(ORDERING_FRAME_GAP));
+        let transpose = !self.orientation.is_top_to_bottom();
+        for ranks in snapshots {
+            let mut nodes = Vec::new();
+            let mut locs = Vec::new();
+            for (level, row) in ranks.iter().enumerate() {
+                for (i, node) in row.iter().enumerate() {
+                    let loc =
+                        Point::new(i as f64 * cell.x, level as f64 * cell.y);
+                    nodes.push(*node);
+                    locs.push(if transpose { loc.transpose() } else { loc });
+                }
+            }
+            self.add_frame("ordering", &nodes, &locs);
+        }
+    }
+
+    /// Draw the snapshot \p frame of the layout on \p rb. The nodes are
+    /// drawn at their location in the frame, and the edges are straight
+    /// lines between them, without their labels.
+    pub fn draw_frame(&self, frame: &LayoutFrame, rb: &mut dyn RenderBackend) {
+        let moved = |h: NodeHandle| {
+            let mut elem = self.nodes[h.get_index()].clone();
+            if let Option::Some(center) = frame.centers.get(h.get_index()) {
+                elem.move_to(*center);
+            }
+            elem
+        };
+        for (arrow, lst) in self.edges.iter() {
+            let (from, to) = (lst[0], lst[lst.len() - 1]);
+            if from == to {
+                continue;
+            }
+            let elements = [moved(from), moved(to)];
+            let path =
+                generate_curve_for_splines(&elements, arrow, SplineKind::Line);
+            render_arrow_path(rb, false, &path, arrow);
+        }
+        for i in 0..self.num_user_nodes.min(frame.centers.len()) {
+            let node = NodeHandle::new(i);
+            if !self.is_connector(node) {
+                moved(node).render(false, rb);
+            }
+        }
+    }
+
+    pub fn pack_config(&self) -> Option<PackConfig> {
+        self.pack
+    }
+
+    /// Lay out each connected component of the graph separately, and arrange
+    /// the components with \p config.
+    pub fn set_pack_config(&mut self, config: PackConfig) {
+        self.pack = Option::Some(config);
+    }
+
+    pub fn spacing_config(&self) -> SpacingConfig {
+        self.spacing.clone()
+    }
+
+    /// Control the space between the nodes of the hierarchical placer with
+    /// \p config.
+    pub fn set_spacing_config(&mut self, config: SpacingConfig) {
+        self.spacing = config;
+    }
+
+    pub fn margin(&self) -> Option<Point> {
+        self.margin
+    }
+
+    /// Leave the space \p margin around the drawing, on each side.
+    pub fn set_margin(&mut self, margin: Point) {
+        self.margin = Some(margin);
+    }
+
+    pub fn label(&self) -> Option<&GraphLabel> {
+        self.label.as_ref()
+    }
+
+    /// Draw the title \p label above or below the drawing.
+    pub fn set_label(&mut self, label: GraphLabel) {
+        self.label = Some(label);
+    }
+
+    pub fn legend(&self) -> Option<&Legend> {
+        self.legend.as_ref()
+    }
+
+    /// Draw the legend \p legend beside the drawing, in the corner of its
+    /// position.
+    pub fn set_legend(&mut self, legend: Legend) {
+        self.legend = Some(legend);
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Draw the annotation \p annotation over the diagram. Annotations are
+    /// usually added after layout, and don't move the elements of the graph.
+    /// \returns the index of the annotation.
+    pub fn add_annotation(&mut self, annotation: Annotation) -> usize {
+        self.annotations.push(annotation);
+        self.annotations.len() - 1
+    }
+
+    /// Remove the annotations of the graph.
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    pub fn background(&self) -> Option<Color> {
+        self.background
+    }
+
+    /// Paint the background of the drawing with \p color.
+    pub fn set_background(&mut self, color: Color) {
+        self.background = Some(color);
+    }
+
+    pub fn layers(&self) -> &[String] {
+        &self.layers
+    }
+
+    /// Draw the elements in the layers \p names, in order. The elements
+    /// select their layers by the index of the layer in \p names.
+    pub fn set_layers(&mut self, names: Vec<String>) {
+        self.layers = names;
+    }
+
+    pub fn output_order(&self) -> OutputOrder {
+        self.output_order
+    }
+
+    /// Draw the nodes and the edges in the order \p order.
+    pub fn set_output_order(&mut self, order: OutputOrder) {
+        self.output_order = order;
+    }
+
+    pub fn stylesheets(&self) -> &[Stylesheet] {
+        &self.stylesheets
+    }
+
+    /// Style the drawing with the CSS stylesheet \p sheet, in the backends
+    /// that support it. The stylesheets are applied in the order in which
+    /// they were added.
+    pub fn add_stylesheet(&mut self, sheet: Stylesheet) {
+        self.stylesheets.push(sheet);
+    }
+
+    pub fn concentrate(&self) -> bool {
+        self.concentrate
+    }
+
+    /// Merge the parallel parts of edges that leave or enter the same node,
+    /// if \p concentrate is set.
+    pub fn set_concentrate(&mut self, concentrate: bool) {
+        self.concentrate = concentrate;
+    }
+
+    pub fn avoid_nodes(&self) -> bool {
+        self.avoid_nodes
+    }
+
+    /// Route the edges that go through the boxes of other nodes around them,
+    /// if \p avoid_nodes is set. Orthogonal edges keep their routing.
+    pub fn set_avoid_nodes(&mut self, avoid_nodes: bool) {
+        self.avoid_nodes = avoid_nodes;
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Break the ties of the node ordering heuristics with random numbers
+    /// from the seed \p seed. The same seed always produces the same layout.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Option::Some(seed);
+    }
+
+    /// Start the hierarchical layout from the ranks and the order of the nodes
+    /// in \p warm_start, and only reorder the nodes to remove crossings
+    /// around the nodes and the edges that changed.
+    pub fn set_warm_start(&mut self, warm_start: WarmStart) {
+        self.warm_start = Option::Some(warm_start);
+    }
+
+    /// \returns the ranks and the order of the nodes after layout, to start
+    /// the layout of an edited copy of the graph from. The result is empty
+    /// for graphs that were not laid out in ranks.
+    pub fn warm_start(&self) -> WarmStart {
+        if self.engine != LayoutEngine::Hierarchical
+            || self.dag.len() < self.num_user_nodes
+        {
+            return WarmStart::default();
+        }
+        let nodes = (0..self.num_user_nodes)
+            .map(|i| {
+                let node = NodeHandle::new(i);
+                let center = self.pos(node).center();
+                let pos = if self.orientation.is_left_right() {
+                    center.y
+                } else {
+                    center.x
+                };
+                Option::Some((self.dag.level(node), pos))
+            })
+            .collect();
+        WarmStart { nodes }
+    }
+
+    pub fn root(&self) -> Option<NodeHandle> {
+        self.root
+    }
+
+    /// Use \p node as the root of the radial and tree layouts.
+    pub fn set_root(&mut self, node: NodeHandle) {
+        assert!(node.get_index() < self.nodes.len(), "Invalid handle");
+        self.root = Some(node);
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.dag.len()
+    }
+
+    pub fn iter_nodes(&self) -> NodeIterator {
+        self.dag.iter()
+    }
+
+    pub fn succ(&self, node: NodeHandle) -> &Vec<NodeHandle> {
+        self.dag.successors(node)
+    }
+
+    pub fn preds(&self, node: NodeHandle) -> &Vec<NodeHandle> {
+        self.dag.predecessors(node)
+    }
+
+    pub fn pos(&self, n: NodeHandle) -> Position {
+        self.element(n).position()
+    }
+
+    pub fn pos_mut(&mut self, n: NodeHandle) -> &mut Position {
+        self.element_mut(n).position_mut()
+    }
+
+    pub fn is_connector(&self, n: NodeHandle) -> bool {
+        self.element(n).is_connector()
+    }
+
+    pub fn transpose(&mut self) {
+        for node in self.dag.iter() {
+            self.element_mut(node).transpose();
+        }
+    }
+
+    pub fn element(&self, node: NodeHandle) -> &Element {
+        &self.nodes[node.get_index()]
+    }
+
+    pub fn element_mut(&mut self, node: NodeHandle) -> &mut Element {
+        &mut self.nodes[node.get_index()]
+    }
+
+    /// Add a node to the graph.
+    /// \returns a handle to the node.
+    pub fn add_node(&mut self, elem: Element) -> NodeHandle {
+        let res = self.dag.new_node();
+        assert!(res.get_index() == self.nodes.len());
+        self.nodes.push(elem);
+        self.node_clusters.push(None);
+        self.node_lanes.push(None);
+        self.node_spans.push(None);
+        res
+    }
+
+    /// Add the cluster \p cluster to the graph. The parent of the cluster
+    /// must already be in the graph.
+    /// \returns the index of the cluster.
+    pub fn add_cluster(&mut self, cluster: Cluster) -> usize {
+        if let Option::Some(parent) = cluster.parent {
+            assert!(parent < self.clusters.len(), "Invalid parent cluster");
+        }
+        self.clusters.push(cluster);
+        self.clusters.len() - 1
+    }
+
+    /// Place the node \p node in the cluster \p cluster.
+    pub fn add_to_cluster(&mut self, node: NodeHandle, cluster: usize) {
+        assert!(cluster < self.clusters.len(), "Invalid cluster");
+        self.node_clusters[node.get_index()] = Some(cluster);
+    }
+
+    pub fn num_clusters(&self) -> usize {
+        self.clusters.len()
+    }
+
+    pub fn cluster(&self, cluster: usize) -> &Cluster {
+        &self.clusters[cluster]
+    }
+
+    /// \returns the innermost cluster that contains the node \p node.
+    pub fn cluster_of(&self, node: NodeHandle) -> Option<usize> {
+        self.node_clusters[node.get_index()]
+    }
+
+    /// \returns the clusters that contain the node \p node, from the
+    /// outermost to the innermost.
+    pub fn cluster_path(&self, node: NodeHandle) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut curr = self.cluster_of(node);
+        while let Option::Some(cluster) = curr {
+            path.push(cluster);
+            curr = self.clusters[cluster].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// \returns true if the cluster \p inner is \p outer or is nested in it.
+    pub fn is_in_cluster(&self, inner: usize, outer: usize) -> bool {
+        let mut curr = Some(inner);
+        while let Option::Some(cluster) = curr {
+            if cluster == outer {
+                return true;
+            }
+            curr = self.clusters[cluster].parent;
+        }
+        false
+    }
+
+    /// \returns the first and last node of each edge, and the connectors
+    /// that the edge passes through.
+    pub fn edge_connectors(
+        &self,
+    ) -> Vec<(NodeHandle, NodeHandle, Vec<NodeHandle>)> {
+        self.edges
+            .iter()
+            .map(|(_, lst)| {
+                let inner = lst[1..lst.len() - 1].to_vec();
+                (lst[0], lst[lst.len() - 1], inner)
+            })
+            .collect()
+    }
+
+    /// \returns the bounding box of the cluster \p cluster, or None if the
+    /// cluster is empty. The box surrounds the nodes and the sub-clusters,
+    /// and leaves room for the label above them. Set \p transposed if the
+    /// graph is transposed, to keep the room for the label on the side that
+    /// becomes the top.
+    pub fn cluster_bbox(
+        &self,
+        cluster: usize,
+        transposed: bool,
+    ) -> Option<(Point, Point)> {
+        let tr = |p: Point| if transposed { p.transpose() } else { p };
+        let mut bbox: Option<(Point, Point)> = None;
+        let mut extend = |b: (Point, Point)| {
+            let (b0, b1) = (tr(b.0), tr(b.1));
+            bbox = Some(match bbox {
+                Option::Some((a0, a1)) => (
+                    Point::new(a0.x.min(b0.x), a0.y.min(b0.y)),
+                    Point::new(a1.x.max(b1.x), a1.y.max(b1.y)),
+                ),
+                Option::None => (b0, b1),
+            });
+        };
+        for node in self.dag.iter() {
+            if self.cluster_of(node) == Some(cluster) {
+                extend(self.pos(node).bbox(false));
+            }
+        }
+        for (i, sub) in self.clusters.iter().enumerate() {
+            if sub.parent == Some(cluster) {
+                if let Option::Some(b) = self.cluster_bbox(i, transposed) {
+                    extend(b);
+                }
+            }
+        }
+
+        let (mut b0, mut b1) = bbox?;
+        let look = &self.clusters[cluster].look;
+        let label = &self.clusters[cluster].label;
+        b0 = b0.sub(Point::splat(CLUSTER_PADDING));
+        b1 = b1.add(Point::splat(CLUSTER_PADDING));
+        if !label.is_empty() {
+            let label_size = get_size_for_text(label, look);
+            b0.y -= label_size.y;
+            let missing = label_size.x + 2. * CLUSTER_PADDING - (b1.x - b0.x);
+            if missing > 0. {
+                b0.x -= missing / 2.;
+                b1.x += missing / 2.;
+            }
+        }
+        Some((tr(b0), tr(b1)))
+    }
+
+    /// Add the lane \p lane to the graph. The lanes are placed in the order
+    /// in which they are added, and only the hierarchical engine places the
+    /// nodes in lanes. \returns the index of the lane.
+    pub fn add_lane(&mut self, lane: Lane) -> usize {
+        self.lanes.push(lane);
+        self.lanes.len() - 1
+    }
+
+    /// Place the node \p node in the lane \p lane. Nodes that are not in a
+    /// lane are placed in the first lane.
+    pub fn add_to_lane(&mut self, node: NodeHandle, lane: usize) {
+        assert!(lane < self.lanes.len(), "Invalid lane");
+        self.node_lanes[node.get_index()] = Some(lane);
+    }
+
+    pub fn num_lanes(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn lane(&self, lane: usize) -> &Lane {
+        &self.lanes[lane]
+    }
+
+    /// \returns the lane that the node \p node was placed in.
+    pub fn lane_of(&self, node: NodeHandle) -> Option<usize> {
+        self.node_lanes[node.get_index()]
+    }
+
+    /// Place the node \p node on the axis of the timeline layout, from
+    /// \p start to \p end. Nodes that start and end at the same time are
+    /// milestones, that keep their size.
+    pub fn set_node_span(&mut self, node: NodeHandle, start: f64, end: f64) {
+        assert!(start <= end, "Invalid span");
+        self.node_spans[node.get_index()] = Some((start, end));
+    }
+
+    /// \returns the start and the end of the node \p node on the axis of the
+    /// timeline layout.
+    pub fn node_span(&self, node: NodeHandle) -> Option<(f64, f64)> {
+        self.node_spans[node.get_index()]
+    }
+
+    /// Record the width of one unit of time, after the timeline layout.
+    pub(crate) fn set_time_scale(&mut self, scale: Option<f64>) {
+        self.time_scale = scale;
+    }
+
+    /// \returns the style of the time axis, that uses the font of the first
+    /// node on the axis, or None if the graph doesn't have a time axis.
+    fn get_time_axis_look(&self) -> Option<StyleAttr> {
+        self.time_scale?;
+        if self.engine != LayoutEngine::Timeline {
+            return None;
+        }
+        let node = self.iter_nodes().find(|x| self.node_span(*x).is_some())?;
+        let mut look = self.element(node).look.clone();
+        look.line_color = look.font_color;
+        look.fill_color = None;
+        look.gradient = None;
+        look.penwidth = 1.;
+        look.dash = Vec::new();
+        Some(look)
+    }
+
+    /// \returns the time axis of the timeline layout, above the nodes, or
+    /// None if the graph doesn't have one. The axis is derived from the
+    /// locations of the nodes, so it follows them when they are moved after
+    /// layout.
+    pub fn time_axis(&self) -> Option<TimeAxis> {
+        let scale = self.time_scale?;
+        let look = self.get_time_axis_look()?;
+        let nodes: Vec<(NodeHandle, (f64, f64))> = self
+            .iter_nodes()
+            .filter_map(|x| self.node_span(x).map(|span| (x, span)))
+            .collect();
+        // The x coordinate of time zero, from the first node on the axis.
+        let (node, (start, end)) = nodes[0];
+        let pos = self.pos(node);
+        let zero = if end - start > EPSILON {
+            pos.left(false) - start * scale
+        } else {
+            pos.center().x - start * scale
+        };
+        let first = nodes.iter().fold(f64::MAX, |acc, x| acc.min(x.1 .0));
+        let last = nodes.iter().fold(f64::MIN, |acc, x| acc.max(x.1 .1));
+        let top = nodes
+            .iter()
+            .fold(f64::MAX, |acc, x| acc.min(self.pos(x.0).top(false)));
+        let bottom = nodes
+            .iter()
+            .fold(f64::MIN, |acc, x| acc.max(self.pos(x.0).bottom(false)));
+        let height = get_size_for_text("0", &look).y;
+        let ticks = get_ticks(first, last, scale)
+            .into_iter()
+            .map(|(t, label)| (zero + t * scale, label))
+            .collect();
+        Some(TimeAxis {
+            left: zero + first * scale,
+            right: zero + last * scale,
+            y: top - height,
+            label_y: top - 2. * height,
+            bottom,
+            ticks,
+        })
+    }
+
+    /// \returns the bounding box of the time axis and of the labels of its
+    /// ticks, or None if the graph doesn't have a time axis.
+    pub(crate) fn get_time_axis_bbox(&self) -> Option<(Point, Point)> {
+        let axis = self.time_axis()?;
+        let look = self.get_time_axis_look()?;
+        let mut top_left = Point::new(axis.left, axis.y);
+        let mut bottom_right = Point::new(axis.right, axis.bottom);
+        for (x, label) in axis.ticks.iter() {
+            let half = get_size_for_text(label, &look).scale(0.5);
+            let loc = Point::new(*x, axis.label_y);
+            let (a, b) = (loc.sub(half), loc.add(half));
+            top_left = Point::new(top_left.x.min(a.x), top_left.y.min(a.y));
+            bottom_right =
+                Point::new(bottom_right.x.max(b.x), bottom_right.y.max(b.y));
+        }
+        Some((top_left, bottom_right))
+    }
+
+    /// \returns the lane that holds the node \p node in the layout, or None
+    /// for connectors and for graphs without lanes.
+    pub(crate) fn get_node_lane(&self, node: NodeHandle) -> Option<usize> {
+        if self.lanes.is_empty() || self.is_connector(node) {
+            return None;
+        }
+        Some(self.lane_of(node).unwrap_or(0))
+    }
+
+    /// \returns the size of the header of the lane \p lane along the ranks,
+    /// and across the ranks.
+    fn get_lane_header_size(&self, lane: usize) -> (f64, f64) {
+        let lane = &self.lanes[lane];
+        let size = get_size_for_text(&lane.label, &lane.look)
+            .add(Point::splat(2. * LANE_PADDING));
+        if self.orientation.is_top_to_bottom() {
+            (size.y, size.x)
+        } else {
+            (size.x, size.y)
+        }
+    }
+
+    /// \returns the extent of each lane across the ranks, along the x axis,
+    /// or None for the empty lanes. The lanes surround their nodes, and are
+    /// wide enough for their headers. Set \p swap to swap the axes of the
+    /// graph, so that the ranks of graphs that grow to the right are rows.
+    pub(crate) fn get_lane_extents(
+        &self,
+        swap: bool,
+    ) -> Vec<Option<(f64, f64)>> {
+        let mut res = vec![None; self.lanes.len()];
+        if self.engine != LayoutEngine::Hierarchical {
+            return res;
+        }
+        for node in self.iter_nodes() {
+            let lane = match self.get_node_lane(node) {
+                Option::Some(lane) => lane,
+                None => continue,
+            };
+            let (mut a, mut b) = self.pos(node).bbox(false);
+            if swap {
+                (a, b) = (a.transpose(), b.transpose());
+            }
+            res[lane] = Some(match res[lane] {
+                Option::Some((lo, hi)) => (a.x.min(lo), b.x.max(hi)),
+                None => (a.x, b.x),
+            });
+        }
+        for (i, extent) in res.iter_mut().enumerate() {
+            if let Option::Some((lo, hi)) = extent {
+                let missing = self.get_lane_header_size(i).1 - (*hi - *lo);
+                let grow = missing.max(2. * LANE_PADDING) / 2.;
+                *lo -= grow;
+                *hi += grow;
+            }
+        }
+        res
+    }
+
+    /// \returns the box of each lane and the box of its header, or None for
+    /// the empty lanes. The lanes span the ranks of the graph, and the lines
+    /// between neighboring lanes are in the middle of the space between them.
+    /// Set \p swap to swap the axes of the graph, as in get_lane_extents.
+    pub(crate) fn get_lane_boxes(&self, swap: bool) -> Vec<Option<LaneBoxes>> {
+        let extents = self.get_lane_extents(swap);
+        let mut res = vec![None; extents.len()];
+        let used: Vec<(usize, (f64, f64))> = extents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| x.map(|x| (i, x)))
+            .collect();
+        if used.is_empty() {
+            return res;
+        }
+
+        // The lanes span the nodes and the edges, and the headers go above
+        // them.
+        let (mut top, mut bottom) = (f64::INFINITY, f64::NEG_INFINITY);
+        for node in self.iter_nodes() {
+            let (mut a, mut b) = self.pos(node).bbox(false);
+            if swap {
+                (a, b) = (a.transpose(), b.transpose());
+            }
+            top = top.min(a.y);
+            bottom = bottom.max(b.y);
+        }
+        let header = used
+            .iter()
+            .map(|(i, _)| self.get_lane_header_size(*i).0)
+            .fold(0., f64::max);
+        top -= LANE_PADDING + header;
+        bottom += LANE_PADDING;
+
+        let tr = |p: Point| if swap { p.transpose() } else { p };
+        for (j, (i, (lo, hi))) in used.iter().enumerate() {
+            let left = match j.checked_sub(1) {
+                Option::Some(prev) => (used[prev].1 .1 + lo) / 2.,
+                None => *lo,
+            };
+            let right = match used.get(j + 1) {
+                Option::Some((_, next)) => (hi + next.0) / 2.,
+                None => *hi,
+            };
+            let band = (Point::new(left, top), Point::new(right, bottom));
+            let head = (band.0, Point::new(right, top + header));
+            res[*i] =
+                Some(((tr(band.0), tr(band.1)), (tr(head.0), tr(head.1))));
+        }
+        res
+    }
+
+    /// \returns the bounding box of the lane \p lane and of its header, after
+    /// layout, or None if the lane is empty.
+    pub fn lane_bbox(&self, lane: usize) -> Option<(Point, Point)> {
+        let swap = !self.orientation.is_top_to_bottom();
+        self.get_lane_boxes(swap)[lane].map(|x| x.0)
+    }
+
+    /// Constrain the rank of the nodes \p nodes with the constraint \p kind.
+    /// Constraints that contradict the edges of the graph, or the constraints
+    /// that were added before them, are ignored.
+    pub fn add_rank_constraint(
+        &mut self,
+        kind: RankConstraint,
+        nodes: Vec<NodeHandle>,
+    ) {
+        for node in &nodes {
+            assert!(node.get_index() < self.nodes.len(), "Invalid handle");
+        }
+        self.rank_constraints.push((kind, nodes));
+    }
+
+    /// \returns True if the rank constraints place \p a and \p b on the same
+    /// rank.
+    fn is_same_rank(&self, a: NodeHandle, b: NodeHandle) -> bool {
+        // All of the nodes on the first rank share a group, and so do all of
+        // the nodes on the last rank.
+        let groups = |node: NodeHandle| {
+            let lst = self.rank_constraints.iter().enumerate();
+            lst.filter(move |(_, (_, nodes))| nodes.contains(&node))
+                .map(|(i, (kind, _))| match kind {
+                    RankConstraint::Same => i + 2,
+                    RankConstraint::Min | RankConstraint::Source => 0,
+                    RankConstraint::Max | RankConstraint::Sink => 1,
+                })
+        };
+        groups(a).any(|x| groups(b).any(|y| x == y))
+    }
+
+    /// Add an edge to the graph.
+    pub fn add_edge(&mut self, arrow: Arrow, from: NodeHandle, to: NodeHandle) {
+        assert!(from.get_index() < self.nodes.len(), "Invalid handle");
+        assert!(to.get_index() < self.nodes.len(), "Invalid handle");
+        let lst = vec![from, to];
+        self.edges.push((arrow, lst));
+        self.edge_ends.push((from, to));
+    }
+
+    /// Highlight the nodes and the edges of \p path with \p style when the
+    /// graph is drawn, without changing their own style. The edges that join
+    /// two nodes that follow each other in the path are highlighted too, so
+    /// the path can be a list of nodes. Clusters are not highlighted.
+    pub fn highlight_path(
+        &mut self,
+        path: &[ElementRef],
+        style: HighlightStyle,
+    ) {
+        for elem in path.iter() {
+            if !matches!(elem, ElementRef::Cluster(_)) {
+                self.highlights.insert(*elem, style);
+            }
+        }
+        for pair in path.windows(2) {
+            if let [ElementRef::Node(a), ElementRef::Node(b)] = *pair {
+                for (i, ends) in self.edge_ends.iter().enumerate() {
+                    if *ends == (a, b) || *ends == (b, a) {
+                        self.highlights.insert(ElementRef::Edge(i), style);
+                    }
+                }
+            }
+        }
+    }
+
+    /// \returns the style that highlights the element \p elem, or None if
+    /// the element is not highlighted.
+    pub fn highlight(&self, elem: ElementRef) -> Option<HighlightStyle> {
+        self.highlights.get(&elem).copied()
+    }
+
+    /// Remove the highlights of the nodes and the edges.
+    pub fn clear_highlights(&mut self) {
+        self.highlights.clear();
+    }
+
+    /// \returns the path with the fewest edges from the node \p from to the
+    /// node \p to, as the list of the nodes and the edges along the path, or
+    /// None if \p to can't be reached. Edges are followed from their tail to
+    /// their head if \p directed is set, or in both directions.
+    pub fn shortest_path(
+        &self,
+        from: NodeHandle,
+        to: NodeHandle,
+        directed: bool,
+    ) -> Option<Vec<ElementRef>> {
+        // The edge that reaches each node on the way from the first node.
+        let mut prev: Vec<Option<(usize, NodeHandle)>> =
+            vec![None; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        visited[from.get_index()] = true;
+        let mut worklist = VecDeque::from([from]);
+        while let Option::Some(node) = worklist.pop_front() {
+            if node == to {
+                break;
+            }
+            for (i, (a, b)) in self.edge_ends.iter().enumerate() {
+                let next = if *a == node {
+                    *b
+                } else if *b == node && !directed {
+                    *a
+                } else {
+                    continue;
+                };
+                if !visited[next.get_index()] {
+                    visited[next.get_index()] = true;
+                    prev[next.get_index()] = Some((i, node));
+                    worklist.push_back(next);
+                }
+            }
+        }
+        if !visited[to.get_index()] {
+            return None;
+        }
+        let mut path = vec![ElementRef::Node(to)];
+        let mut node = to;
+        while let Option::Some((edge, before)) = prev[node.get_index()] {
+            path.push(ElementRef::Edge(edge));
+            path.push(ElementRef::Node(before));
+            node = before;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// \returns the value of the metric \p metric for each node, indexed by
+    /// the handles of the nodes. Only the edges that were added to the graph
+    /// are counted.
+    pub fn node_metric(&self, metric: NodeMetric) -> Vec<f64> {
+        compute_metric(self.nodes.len(), &self.edge_ends, metric)
+    }
+
+    /// Fill the nodes with the colors of their values of the metric
+    /// \p metric. Measures are shaded from light to dark, and the strongly
+    /// connected components get distinct colors.
+    pub fn color_by(&mut self, metric: NodeMetric) {
+        let values = self.node_metric(metric);
+        let max = values.iter().fold(0., |acc: f64, x| acc.max(*x));
+        for (i, value) in values.iter().enumerate() {
+            let node = NodeHandle::new(i);
+            if self.is_connector(node) {
+                continue;
+            }
+            let categorical = metric.is_categorical();
+            let (fill, font) = get_metric_colors(*value, max, categorical);
+            let look = &mut self.element_mut(node).look;
+            look.fill_color = Some(fill);
+            look.gradient = None;
+            look.font_color = font;
+        }
+    }
+
+    /// Grow the nodes with their values of the metric \p metric, up to
+    /// MAX_SIZE_SCALE times their size for the largest value. The sizes of
+    /// the nodes are used by the layout, so this is called before layout.
+    pub fn size_by(&mut self, metric: NodeMetric) {
+        let values = self.node_metric(metric);
+        let max = values.iter().fold(0., |acc: f64, x| acc.max(*x));
+        if max <= 0. {
+            return;
+        }
+        for (i, value) in values.iter().enumerate() {
+            let node = NodeHandle::new(i);
+            if self.is_connector(node) {
+                continue;
+            }
+            let scale = 1. + (MAX_SIZE_SCALE - 1.) * value / max;
+            let pos = &mut self.element_mut(node).pos;
+            let size = pos.size(false).scale(scale);
+            pos.set_size(size);
+        }
+    }
+}
+
+/// Refers to a node, an edge or a cluster of a VisualGraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ElementRef {
+    Node(NodeHandle),
+    /// An edge, by the order in which the edges were added to the graph.
+    Edge(usize),
+    /// A cluster, by the order in which the clusters were added.
+    Cluster(usize),
 }
 
-impl VisualGraph {
-    pub fn new(orientation: Orientation) -> Self {
-        VisualGraph {
-            nodes: Vec::new(),
-            edges: Vec::new(),
-            self_edges: Vec::new(),
-            dag: DAG::new(),
-            orientation,
+/// Changes the style of an element just before it's drawn. See
+/// VisualGraph::draw_with_style_hook.
+pub type StyleHook<'a> = dyn FnMut(ElementRef, &mut StyleAttr) + 'a;
+
+/// An element of the drawing, that is drawn in one group.
+#[derive(Debug, Clone, Copy)]
+enum RenderItem {
+    Cluster(usize),
+    Node(usize),
+    Edge(usize),
+}
+
+// Render.
+impl VisualGraph {
+    fn render(
+        &self,
+        debug: bool,
+        rb: &mut dyn RenderBackend,
+        mut hook: Option<&mut StyleHook>,
+    ) {
+        self.render_lanes(rb);
+        self.render_timeline(rb);
+        let owners = self.get_connector_owners();
+        let items = self.get_render_order(&owners);
+        if self.layers.is_empty() {
+            for item in &items {
+                let hook = hook.as_deref_mut();
+                self.render_item(*item, &owners, debug, rb, hook);
+            }
+        } else {
+            // Draw the elements of each layer, and the elements that are in
+            // all of the layers, in every layer.
+            for (i, name) in self.layers.iter().enumerate() {
+                rb.begin_layer(name);
+                for item in &items {
+                    let layers = match *item {
+                        RenderItem::Cluster(c) => &self.clusters[c].layers,
+                        RenderItem::Node(n) => &self.nodes[n].layers,
+                        RenderItem::Edge(e) => &self.edges[e].0.layers,
+                    };
+                    if layers.as_ref().is_none_or(|x| x.contains(&i)) {
+                        let hook = hook.as_deref_mut();
+                        self.render_item(*item, &owners, debug, rb, hook);
+                    }
+                }
+                rb.end_layer();
+            }
+        }
+
+        self.render_graph_label(rb);
+        self.render_legend(rb);
+        self.render_annotations(rb);
+    }
+
+    /// \returns the index of the edge that draws each node, for the
+    /// connectors that the edges go through, or None for the nodes that are
+    /// drawn by themselves. Connectors that several edges share are drawn by
+    /// the first of them.
+    fn get_connector_owners(&self) -> Vec<Option<usize>> {
+        let mut owners = vec![None; self.nodes.len()];
+        for (i, arrow) in self.edges.iter().enumerate() {
+            let lst = &arrow.1;
+            for h in lst.iter().take(lst.len() - 1).skip(1) {
+                owners[h.get_index()].get_or_insert(i);
+            }
+        }
+        owners
+    }
+
+    /// \returns the elements of the graph in the order in which they are
+    /// drawn: the clusters, and then the nodes and the edges in the output
+    /// order of the graph. Elements with a higher z-order come last.
+    fn get_render_order(&self, owners: &[Option<usize>]) -> Vec<RenderItem> {
+        let (node_rank, edge_rank) = match self.output_order {
+            OutputOrder::NodesFirst => (1, 2),
+            OutputOrder::EdgesFirst => (2, 1),
+        };
+        let mut items = Vec::new();
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            items.push((cluster.z_order, 0, RenderItem::Cluster(i)));
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            if owners[i].is_none() {
+                items.push((node.z_order, node_rank, RenderItem::Node(i)));
+            }
+        }
+        for (i, arrow) in self.edges.iter().enumerate() {
+            items.push((arrow.0.z_order, edge_rank, RenderItem::Edge(i)));
+        }
+        // The sort is stable, and keeps the order of the elements of a kind.
+        items.sort_by_key(|x| (x.0, x.1));
+        items.into_iter().map(|x| x.2).collect()
+    }
+
+    /// Draw the element \p item. The elements are copied, and their style is
+    /// changed by \p hook, only if there is a hook.
+    fn render_item(
+        &self,
+        item: RenderItem,
+        owners: &[Option<usize>],
+        debug: bool,
+        rb: &mut dyn RenderBackend,
+        hook: Option<&mut StyleHook>,
+    ) {
+        match item {
+            RenderItem::Cluster(i) => {
+                let mut cluster = Cow::Borrowed(&self.clusters[i]);
+                if let Option::Some(hook) = hook {
+                    hook(ElementRef::Cluster(i), &mut cluster.to_mut().look);
+                }
+                if let Option::Some(bbox) = self.cluster_bbox(i, false) {
+                    render_cluster(&cluster, bbox, rb);
+                }
+            }
+            RenderItem::Node(i) => {
+                let mut node = Cow::Borrowed(&self.nodes[i]);
+                let handle = NodeHandle::new(i);
+                if let Option::Some(style) =
+                    self.highlight(ElementRef::Node(handle))
+                {
+                    style.apply(&mut node.to_mut().look);
+                }
+                if let Option::Some(hook) = hook {
+                    hook(ElementRef::Node(handle), &mut node.to_mut().look);
+                }
+                node.render(debug, rb);
+            }
+            RenderItem::Edge(i) => {
+                let mut arrow = Cow::Borrowed(&self.edges[i]);
+                let mut label_color = Option::None;
+                if let Option::Some(style) = self.highlight(ElementRef::Edge(i))
+                {
+                    style.apply(&mut arrow.to_mut().0.look);
+                }
+                if let Option::Some(hook) = hook {
+                    let look = &mut arrow.to_mut().0.look;
+                    hook(ElementRef::Edge(i), look);
+                    label_color = Some(look.font_color);
+                }
+                // The edge is drawn in one group with the connectors that
+                // hold its labels. The labels take the font color of the
+                // edge.
+                if let Option::Some(properties) = &arrow.0.properties {
+                    rb.begin_group(properties);
+                }
+                if let Option::Some(link) = &arrow.0.link {
+                    rb.begin_link(link);
+                }
+                for h in &arrow.1 {
+                    let idx = h.get_index();
+                    if owners[idx] != Some(i) {
+                        continue;
+                    }
+                    let mut conn = Cow::Borrowed(&self.nodes[idx]);
+                    if let Option::Some(color) = label_color {
+                        conn.to_mut().look.font_color = color;
+                    }
+                    conn.render(debug, rb);
+                }
+                self.render_arrow(&arrow, debug, rb);
+                if arrow.0.link.is_some() {
+                    rb.end_link();
+                }
+                if arrow.0.properties.is_some() {
+                    rb.end_group();
+                }
+            }
+        }
+    }
+
+    /// Draw the lanes and their headers, behind the clusters.
+    pub(crate) fn render_lanes(&self, rb: &mut dyn RenderBackend) {
+        let swap = !self.orientation.is_top_to_bottom();
+        for (i, boxes) in self.get_lane_boxes(swap).iter().enumerate() {
+            if let Option::Some((bbox, header)) = boxes {
+                render_lane(&self.lanes[i], *bbox, *header, rb);
+            }
         }
     }
 
-    pub fn orientation(&self) -> Orientation {
-        self.orientation
+    /// Draw the time axis of the timeline layout, and its gridlines behind
+    /// the nodes.
+    pub(crate) fn render_timeline(&self, rb: &mut dyn RenderBackend) {
+        if let (Option::Some(axis), Option::Some(look)) =
+            (self.time_axis(), self.get_time_axis_look())
+        {
+            render_time_axis(&axis, &look, rb);
+        }
     }
 
-    pub fn num_nodes(&self) -> usize {
-        self.dag.len()
+    /// Draw the clusters behind the nodes, and the outer clusters first.
+    #[cfg(feature = "std")]
+    pub(crate) fn render_clusters(&self, rb: &mut dyn RenderBackend) {
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            if let Option::Some(bbox) = self.cluster_bbox(i, false) {
+                render_cluster(cluster, bbox, rb);
+            }
+        }
     }
 
-    pub fn iter_nodes(&self) -> NodeIterator {
-        self.dag.iter()
+    /// Draw the title of the graph.
+    pub(crate) fn render_graph_label(&self, rb: &mut dyn RenderBackend) {
+        if let (Option::Some(label), Option::Some(loc)) =
+            (&self.label, self.label_pos)
+        {
+            render_graph_label(label, loc, rb);
+        }
     }
 
-    pub fn succ(&self, node: NodeHandle) -> &Vec<NodeHandle> {
-        self.dag.successors(node)
+    /// Draw the legend of the graph.
+    pub(crate) fn render_legend(&self, rb: &mut dyn RenderBackend) {
+        if let (Option::Some(legend), Option::Some(xy)) =
+            (&self.legend, self.legend_pos)
+        {
+            render_legend(legend, xy, rb);
+        }
     }
 
-    pub fn preds(&self, node: NodeHandle) -> &Vec<NodeHandle> {
-        self.dag.predecessors(node)
+    /// \returns the location of the anchor \p anchor of an annotation.
+    fn get_anchor_location(&self, anchor: AnnotationAnchor) -> Point {
+        match anchor {
+            AnnotationAnchor::Point(p) => p,
+            AnnotationAnchor::Node(node, offset) => {
+                self.pos(node).center().add(offset)
+            }
+        }
     }
 
-    pub fn pos(&self, n: NodeHandle) -> Position {
-        self.element(n).position()
+    /// Draw the annotations over the diagram, in the order in which they
+    /// were added.
+    pub(crate) fn render_annotations(&self, rb: &mut dyn RenderBackend) {
+        for annotation in self.annotations.iter() {
+            let loc = self.get_anchor_location(annotation.anchor);
+            let look = &annotation.look;
+            match &annotation.kind {
+                AnnotationKind::Text(text) => {
+                    render_annotation_box(text, loc, None, look, rb);
+                }
+                AnnotationKind::Callout(text, target) => {
+                    let point = match *target {
+                        AnnotationAnchor::Node(node, offset)
+                            if offset == Point::zero() =>
+                        {
+                            let elem = self.element(node);
+                            elem.get_connector_location(loc, 0., &None).0
+                        }
+                        _ => self.get_anchor_location(*target),
+                    };
+                    render_annotation_box(text, loc, Some(point), look, rb);
+                }
+                AnnotationKind::Watermark(text) => {
+                    if let Option::Some(bbox) = self.get_drawing_bbox() {
+                        render_watermark(text, bbox, look, rb);
+                    }
+                }
+            }
+        }
     }
 
-    pub fn pos_mut(&mut self, n: NodeHandle) -> &mut Position {
-        self.element_mut(n).position_mut()
+    fn render_arrow(
+        &self,
+        arrow: &(Arrow, Vec<NodeHandle>),
+        debug: bool,
+        rb: &mut dyn RenderBackend,
+    ) {
+        let path = self.get_edge_path(arrow);
+        render_arrow_path(rb, debug, &path, &arrow.0);
     }
 
-    pub fn is_connector(&self, n: NodeHandle) -> bool {
-        return self.element(n).is_connector();
+    /// Draw the node \p node, that was added to the graph by the user, after
+    /// layout.
+    #[cfg(feature = "std")]
+    pub(crate) fn render_node(
+        &self,
+        node: NodeHandle,
+        rb: &mut dyn RenderBackend,
+    ) {
+        self.nodes[node.get_index()].render(false, rb);
     }
 
-    pub fn transpose(&mut self) {
-        for node in self.dag.iter() {
-            self.element_mut(node).transpose();
+    /// Draw the edge \p idx, in the order in which the edges were added,
+    /// after layout. The connectors of the edge, that hold its label, are
+    /// drawn with it.
+    #[cfg(feature = "std")]
+    pub(crate) fn render_edge(&self, idx: usize, rb: &mut dyn RenderBackend) {
+        let arrow = &self.edges[idx];
+        for h in arrow.1.iter() {
+            if h.get_index() >= self.num_user_nodes {
+                self.nodes[h.get_index()].render(false, rb);
+            }
         }
+        self.render_arrow(arrow, false, rb);
     }
+}
 
-    pub fn element(&self, node: NodeHandle) -> &Element {
-        &self.nodes[node.get_index()]
+impl VisualGraph {
+    pub fn do_it(
+        &mut self,
+        debug_mode: bool,
+        disable_opt: bool,
+        disable_layout: bool,
+        rb: &mut dyn RenderBackend,
+    ) {
+        self.layout(disable_opt, disable_layout);
+        self.draw(debug_mode, rb);
     }
 
-    pub fn element_mut(&mut self, node: NodeHandle) -> &mut Element {
-        &mut self.nodes[node.get_index()]
+    /// Draw the graph, after it was laid out with layout(), on \p rb. If
+    /// \p debug_mode is set then extra markers are drawn.
+    pub fn draw(&self, debug_mode: bool, rb: &mut dyn RenderBackend) {
+        self.draw_impl(debug_mode, rb, Option::None);
     }
 
-    /// Add a node to the graph.
-    /// \returns a handle to the node.
-    pub fn add_node(&mut self, elem: Element) -> NodeHandle {
-        let res = self.dag.new_node();
-        assert!(res.get_index() == self.nodes.len());
-        self.nodes.push(elem);
-        res
+    /// Draw the graph like draw(), and call \p hook just before each node,
+    /// edge and cluster is drawn, with the element and a copy of its style.
+    /// Changes to the style only affect the drawing, and not the layout, so
+    /// the hook should not make the labels larger. The labels of an edge take
+    /// the font color of the edge.
+    pub fn draw_with_style_hook(
+        &self,
+        debug_mode: bool,
+        rb: &mut dyn RenderBackend,
+        hook: &mut StyleHook,
+    ) {
+        self.draw_impl(debug_mode, rb, Option::Some(hook));
     }
 
-    /// Add an edge to the graph.
-    pub fn add_edge(&mut self, arrow: Arrow, from: NodeHandle, to: NodeHandle) {
-        assert!(from.get_index() < self.nodes.len(), "Invalid handle");
-        assert!(to.get_index() < self.nodes.len(), "Invalid handle");
-        let lst = vec![from, to];
-        self.edges.push((arrow, lst));
+    fn draw_impl(
+        &self,
+        debug_mode: bool,
+        rb: &mut dyn RenderBackend,
+        hook: Option<&mut StyleHook>,
+    ) {
+        // Scale the drawing to the requested size.
+        #[cfg(feature = "std")]
+        {
+            let scale = self.output_scale();
+            if scale != 1. {
+                let mut scaled = ScaledBackend::new(rb, scale);
+                self.draw_unscaled(debug_mode, &mut scaled, hook);
+                return;
+            }
+        }
+        self.draw_unscaled(debug_mode, rb, hook);
     }
-}
 
-// Render.
-impl VisualGraph {
-    fn render(&self, debug: bool, rb: &mut dyn RenderBackend) {
-        // Draw the nodes.
-        for node in &self.nodes {
-            node.render(debug, rb);
+    fn draw_unscaled(
+        &self,
+        debug_mode: bool,
+        rb: &mut dyn RenderBackend,
+        hook: Option<&mut StyleHook>,
+    ) {
+        if let Option::Some(color) = self.background {
+            rb.set_background(color);
+        }
+        for sheet in &self.stylesheets {
+            rb.add_stylesheet(sheet);
+        }
+        self.render(debug_mode, rb, hook);
+        if let Option::Some(corner) = self.canvas_corner {
+            rb.grow_canvas(corner);
+        }
+        if let Option::Some(extent) = self.get_canvas_extent() {
+            let scale = self.output_scale();
+            if let Option::Some(corner) =
+                self.fit.get_page_corner(extent, scale)
+            {
+                rb.grow_canvas(corner);
+            }
         }
+    }
+
+    /// \returns the bottom-right corner of the canvas, that starts at the
+    /// origin and holds the nodes and the edges, or None if the graph is
+    /// empty.
+    fn get_canvas_extent(&self) -> Option<Point> {
+        if self.canvas_corner.is_some() {
+            return self.canvas_corner;
+        }
+        self.get_drawing_bbox()?;
+        Some(self.layout_result().bbox.1)
+    }
+
+    /// \returns the factor that scales the drawing to fit in the size of the
+    /// size config, or 1 if the drawing is not scaled. The drawing is scaled
+    /// when it's drawn, and the layout keeps its coordinates. Without the
+    /// 'std' feature the drawing is not scaled, and the factor is for the
+    /// caller to apply.
+    pub fn output_scale(&self) -> f64 {
+        match self.get_canvas_extent() {
+            Option::Some(extent) => self.fit.get_scale(extent),
+            None => 1.,
+        }
+    }
+
+    /// \returns the size of the canvas that the drawing is drawn on, after
+    /// it's scaled to the size of the size config and grown to a whole
+    /// number of pages, or None if the graph is empty.
+    pub fn output_size(&self) -> Option<Point> {
+        let extent = self.get_canvas_extent()?;
+        let scale = self.output_scale();
+        let corner = self.fit.get_page_corner(extent, scale).unwrap_or(extent);
+        Some(corner.scale(scale))
+    }
+
+    /// Place the nodes and route the edges of the graph, without drawing it.
+    /// The result is available with layout_result().
+    pub fn layout(&mut self, disable_opt: bool, disable_layout: bool) {
+        self.num_user_nodes = self.nodes.len();
+        if let Option::Some(frames) = &mut self.frames {
+            frames.clear();
+        }
+        let components = self.get_components();
+        // The lanes span the whole graph.
+        if self.pack.is_some()
+            && !matches!(
+                self.engine,
+                LayoutEngine::Grid | LayoutEngine::Timeline
+            )
+            && self.lanes.is_empty()
+            && components.len() > 1
+        {
+            self.layout_components(&components, disable_opt, disable_layout);
+        } else {
+            self.lower(disable_opt);
+            self.place(disable_layout);
+        }
+        self.label_pos = None;
+        self.legend_pos = None;
+        fit_layout(self);
+        self.legend_pos = self.place_legend();
+        self.label_pos = self.place_graph_label();
+        self.canvas_corner = self.apply_margin();
+        self.add_frame("final", &[], &[]);
+    }
 
-        // Draw the arrows:
-        for arrow in &self.edges {
-            let mut elements = Vec::new();
-            for h in &arrow.1 {
-                elements.push(self.nodes[h.get_index()].clone());
+    /// Assign coordinates to the nodes of the lowered graph.
+    fn place(&mut self, disable_layout: bool) {
+        match self.engine {
+            LayoutEngine::Hierarchical => {
+                Placer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::ForceDirected => {
+                ForcePlacer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::Circular => {
+                CircularPlacer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::Radial => {
+                RadialPlacer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::Tree => {
+                TreePlacer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::Stress => {
+                StressPlacer::new(self).layout(disable_layout);
+            }
+            LayoutEngine::Grid => {
+                GridPlacer::new(self).layout();
+            }
+            LayoutEngine::Timeline => {
+                TimelinePlacer::new(self).layout();
             }
-            render_arrow(rb, debug, &elements[..], &arrow.0);
         }
     }
-}
 
-impl VisualGraph {
-    pub fn do_it(
+    /// \returns the connected components of the graph, as sorted lists of
+    /// nodes. Nodes in the same top-level cluster, or in the same rank
+    /// constraint, are in the same component.
+    fn get_components(&self) -> Vec<Vec<NodeHandle>> {
+        fn find(group: &mut [usize], mut x: usize) -> usize {
+            while group[x] != x {
+                group[x] = group[group[x]];
+                x = group[x];
+            }
+            x
+        }
+        let n = self.nodes.len();
+        let mut group: Vec<usize> = (0..n).collect();
+        let mut join = |a: NodeHandle, b: NodeHandle| {
+            let a = find(&mut group, a.get_index());
+            let b = find(&mut group, b.get_index());
+            group[a.max(b)] = a.min(b);
+        };
+
+        for (from, to) in self.edge_ends.iter() {
+            join(*from, *to);
+        }
+        for (_, nodes) in self.rank_constraints.iter() {
+            for node in nodes.iter().skip(1) {
+                join(nodes[0], *node);
+            }
+        }
+        let mut cluster_nodes: Vec<Option<NodeHandle>> =
+            vec![None; self.clusters.len()];
+        for i in 0..n {
+            let mut cluster = match self.node_clusters[i] {
+                Option::Some(cluster) => cluster,
+                None => continue,
+            };
+            while let Option::Some(parent) = self.clusters[cluster].parent {
+                cluster = parent;
+            }
+            match cluster_nodes[cluster] {
+                Option::Some(first) => join(first, NodeHandle::new(i)),
+                None => cluster_nodes[cluster] = Some(NodeHandle::new(i)),
+            }
+        }
+
+        let mut components: Vec<Vec<NodeHandle>> = Vec::new();
+        let mut index = vec![usize::MAX; n];
+        for i in 0..n {
+            let root = find(&mut group, i);
+            if index[root] == usize::MAX {
+                index[root] = components.len();
+                components.push(Vec::new());
+            }
+            components[index[root]].push(NodeHandle::new(i));
+        }
+        components
+    }
+
+    /// \returns a graph with the settings of this graph, the nodes \p nodes,
+    /// and the edges between them, and the indices of these edges in this
+    /// graph.
+    fn get_subgraph(&self, nodes: &[NodeHandle]) -> (VisualGraph, Vec<usize>) {
+        let mut map = vec![None; self.nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            map[node.get_index()] = Some(NodeHandle::new(i));
+        }
+
+        let mut sub = VisualGraph::new(self.orientation);
+        sub.splines = self.splines;
+        sub.engine = self.engine;
+        sub.stress = self.stress;
+        sub.grid = self.grid;
+        sub.spacing = self.spacing.clone();
+        sub.concentrate = self.concentrate;
+        sub.seed = self.seed;
+        sub.ordering = self.ordering;
+        sub.quality = self.quality;
+        sub.root = self.root.and_then(|x| map[x.get_index()]);
+        sub.clusters = self.clusters.clone();
+        for node in nodes.iter() {
+            let handle = sub.add_node(self.nodes[node.get_index()].clone());
+            sub.node_clusters[handle.get_index()] =
+                self.node_clusters[node.get_index()];
+        }
+        for (kind, group) in self.rank_constraints.iter() {
+            if map[group[0].get_index()].is_some() {
+                let group = group.iter().filter_map(|x| map[x.get_index()]);
+                sub.rank_constraints.push((*kind, group.collect()));
+            }
+        }
+        if let Option::Some(warm_start) = &self.warm_start {
+            let nodes = nodes
+                .iter()
+                .map(|x| warm_start.nodes.get(x.get_index()).cloned().flatten())
+                .collect();
+            sub.warm_start = Some(WarmStart { nodes });
+        }
+
+        let mut edge_indices = Vec::new();
+        for (i, (from, to)) in self.edge_ends.iter().enumerate() {
+            if let (Option::Some(from), Option::Some(to)) =
+                (map[from.get_index()], map[to.get_index()])
+            {
+                sub.add_edge(self.edges[i].0.clone(), from, to);
+                edge_indices.push(i);
+            }
+        }
+        (sub, edge_indices)
+    }
+
+    /// Lay out each of the connected components \p components of the graph
+    /// separately, and pack the components next to each other. The components
+    /// are laid out in parallel with the 'rayon' feature.
+    fn layout_components(
         &mut self,
-        debug_mode: bool,
+        components: &[Vec<NodeHandle>],
         disable_opt: bool,
         disable_layout: bool,
-        rb: &mut dyn RenderBackend,
     ) {
-        self.lower(disable_opt);
-        Placer::new(self).layout(disable_layout);
-        self.render(debug_mode, rb);
+        #[cfg(feature = "log")]
+        log::info!("Laying out {} components.", components.len());
+        let mut parts: Vec<(VisualGraph, Vec<usize>)> =
+            components.iter().map(|x| self.get_subgraph(x)).collect();
+        let layout_part = |part: &mut (VisualGraph, Vec<usize>)| {
+            let sub = &mut part.0;
+            sub.num_user_nodes = sub.nodes.len();
+            sub.lower(disable_opt);
+            sub.place(disable_layout);
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            parts.par_iter_mut().for_each(layout_part);
+        }
+        #[cfg(not(feature = "rayon"))]
+        parts.iter_mut().for_each(layout_part);
+
+        // Find the place of each component.
+        let zero = (Point::zero(), Point::zero());
+        let boxes: Vec<(Point, Point)> = parts
+            .iter()
+            .map(|x| x.0.get_drawing_bbox().unwrap_or(zero))
+            .collect();
+        let sizes: Vec<Point> = boxes.iter().map(|b| b.1.sub(b.0)).collect();
+        let corners = pack_boxes(&sizes, &self.pack.unwrap_or_default());
+
+        // Move the nodes, the connectors and the edges of the components back
+        // to this graph. Connectors are added after the nodes of the user.
+        let mut ranks: RankType = Vec::new();
+        let mut dag_edges = Vec::new();
+        let crossings = parts.iter().map(|x| x.0.crossings).sum();
+        for (i, (sub, edge_indices)) in parts.into_iter().enumerate() {
+            let delta = corners[i].sub(boxes[i].0);
+            let mut map = components[i].clone();
+            for j in sub.num_user_nodes..sub.nodes.len() {
+                map.push(NodeHandle::new(self.nodes.len()));
+                self.nodes.push(sub.nodes[j].clone());
+                self.node_clusters.push(sub.node_clusters[j]);
+            }
+            for (j, node) in sub.nodes.into_iter().enumerate() {
+                self.nodes[map[j].get_index()] = node;
+                self.nodes[map[j].get_index()]
+                    .position_mut()
+                    .translate(delta);
+            }
+            for (j, edge) in sub.edges.into_iter().enumerate() {
+                let lst = edge.1.iter().map(|x| map[x.get_index()]).collect();
+                self.edges[edge_indices[j]] = (edge.0, lst);
+            }
+            for node in sub.dag.iter() {
+                for succ in sub.dag.successors(node) {
+                    let edge = (map[node.get_index()], map[succ.get_index()]);
+                    dag_edges.push(edge);
+                }
+            }
+            for (level, row) in sub.dag.ranks().iter().enumerate() {
+                if ranks.len() <= level {
+                    ranks.push(Vec::new());
+                }
+                ranks[level].extend(row.iter().map(|x| map[x.get_index()]));
+            }
+        }
+
+        self.dag.clear();
+        self.dag.new_nodes(self.nodes.len());
+        for (from, to) in dag_edges {
+            self.dag.add_edge(from, to);
+        }
+        self.dag.set_ranks(ranks);
+        self.crossings = crossings;
+    }
+
+    /// \returns the path of the edge \p edge, in the form that is passed to
+    /// RenderBackend::draw_arrow.
+    fn get_edge_path(
+        &self,
+        edge: &(Arrow, Vec<NodeHandle>),
+    ) -> Vec<(Point, Point)> {
+        let elements: Vec<Element> =
+            edge.1.iter().map(|h| self.element(*h).clone()).collect();
+        if edge.1.len() == 3 && edge.1[0] == edge.1[2] {
+            return generate_curve_for_self_loop(
+                &elements[0],
+                &elements[1],
+                &edge.0,
+            );
+        }
+        let path = generate_curve_for_splines(&elements, &edge.0, self.splines);
+        if !self.avoid_nodes || self.splines == SplineKind::Ortho {
+            return path;
+        }
+        self.route_around_nodes(edge, &path)
+    }
+
+    /// \returns the path \p path of the edge \p edge, moved around the nodes
+    /// that it goes through. The edge still goes through its connectors, that
+    /// hold its label.
+    fn route_around_nodes(
+        &self,
+        edge: &(Arrow, Vec<NodeHandle>),
+        path: &[(Point, Point)],
+    ) -> Vec<(Point, Point)> {
+        let (Option::Some(first), Option::Some(last)) =
+            (path.first(), path.last())
+        else {
+            return path.to_vec();
+        };
+        let ends = [edge.1[0], edge.1[edge.1.len() - 1]];
+        let boxes: Vec<(Point, Point)> = (0..self.num_user_nodes)
+            .map(NodeHandle::new)
+            .filter(|h| !ends.contains(h) && !self.is_connector(*h))
+            .map(|h| self.pos(h).bbox(false))
+            .collect();
+        let mut waypoints = vec![first.0];
+        for h in &edge.1[1..edge.1.len() - 1] {
+            waypoints.push(self.pos(*h).center());
+        }
+        waypoints.push(last.1);
+        let smooth =
+            !matches!(self.splines, SplineKind::Line | SplineKind::Polyline);
+        route_edge(path, &waypoints, &boxes, smooth)
+    }
+
+    /// \returns a spatial index over the nodes, the edges and the clusters,
+    /// that finds the elements at a point of the layout.
+    pub fn hit_index(&self) -> HitIndex {
+        HitIndex::new(&self.layout_result())
+    }
+
+    /// \returns the statistics of the graph and the quality metrics of its
+    /// layout, such as the number of edge crossings.
+    pub fn metrics(&self) -> LayoutMetrics {
+        let mut metrics = LayoutMetrics::from_result(&self.layout_result());
+        // The nodes of a rank are centered on the same line.
+        if self.engine == LayoutEngine::Hierarchical {
+            let mut ranks: Vec<f64> = (0..self.num_user_nodes)
+                .map(|i| self.pos(NodeHandle::from(i)).center())
+                .map(|x| match self.orientation {
+                    Orientation::TopToBottom => x.y,
+                    Orientation::LeftToRight => x.x,
+                })
+                .collect();
+            ranks.sort_by(|a, b| a.total_cmp(b));
+            ranks.dedup_by(|a, b| (*a - *b).abs() < RANK_EPSILON);
+            metrics.num_layers = Some(ranks.len());
+        }
+        metrics
+    }
+
+    /// \returns the location of the nodes and of the edges after layout.
+    pub fn layout_result(&self) -> LayoutResult {
+        let nodes: Vec<(Point, Point)> = (0..self.num_user_nodes)
+            .map(|i| self.pos(NodeHandle::from(i)).bbox(false))
+            .collect();
+
+        let mut edges = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            let (from, to) = self.edge_ends[i];
+            let mut path = get_bezier_segments(&self.get_edge_path(edge));
+            // Lowering reverses the edges that point up.
+            if edge.1[0] != from {
+                path.reverse();
+                for seg in path.iter_mut() {
+                    *seg = (seg.3, seg.2, seg.1, seg.0);
+                }
+            }
+            // Labels are in connectors, or in the middle of edges between
+            // nodes on the same rank.
+            let mut label =
+                edge.1.iter().find_map(|h| match &self.element(*h).shape {
+                    ShapeKind::Connector(Option::Some(_)) => {
+                        Some(self.pos(*h).center())
+                    }
+                    _ => None,
+                });
+            if label.is_none() && !edge.0.text.is_empty() && !path.is_empty() {
+                let (p0, p1, p2, p3) = path[path.len() / 2];
+                label = Some(cubic_bezier(p0, p1, p2, p3, 0.5));
+            }
+            edges.push(EdgeLayout {
+                from,
+                to,
+                path,
+                label,
+            });
+        }
+
+        // The edges may leave the boxes of the nodes.
+        let zero = (Point::zero(), Point::zero());
+        let mut bbox = self.get_drawing_bbox().unwrap_or(zero);
+        for seg in edges.iter().flat_map(|x| x.path.iter()) {
+            for p in [seg.0, seg.1, seg.2, seg.3] {
+                bbox.0 = Point::new(bbox.0.x.min(p.x), bbox.0.y.min(p.y));
+                bbox.1 = Point::new(bbox.1.x.max(p.x), bbox.1.y.max(p.y));
+            }
+        }
+        if let Option::Some(margin) = self.margin {
+            bbox = (bbox.0.sub(margin), bbox.1.add(margin));
+        }
+        let clusters = (0..self.num_clusters())
+            .map(|i| self.cluster_bbox(i, false))
+            .collect();
+        LayoutResult {
+            nodes,
+            edges,
+            clusters,
+            bbox,
+        }
+    }
+
+    /// Move the drawing to leave the margin on the top and on the left, and
+    /// \returns the bottom-right corner of the canvas, that leaves the margin
+    /// on the bottom and on the right. Returns None if there is no margin.
+    fn apply_margin(&mut self) -> Option<Point> {
+        let margin = self.margin?;
+        let (top_left, bottom_right) = self.get_drawing_bbox()?;
+        let delta = margin.sub(top_left);
+        for node in self.iter_nodes() {
+            self.pos_mut(node).translate(delta);
+        }
+        self.label_pos = self.label_pos.map(|x| x.add(delta));
+        self.legend_pos = self.legend_pos.map(|x| x.add(delta));
+        Some(bottom_right.add(delta).add(margin))
+    }
+
+    /// \returns the bounding box of the nodes, the clusters, the lanes, the
+    /// label and the legend of the graph, or None if the graph is empty.
+    pub(crate) fn get_drawing_bbox(&self) -> Option<(Point, Point)> {
+        let mut boxes = Vec::new();
+        for node in self.iter_nodes() {
+            boxes.push(self.pos(node).bbox(false));
+        }
+        for cluster in 0..self.num_clusters() {
+            boxes.extend(self.cluster_bbox(cluster, false));
+        }
+        let swap = !self.orientation.is_top_to_bottom();
+        boxes.extend(self.get_lane_boxes(swap).iter().flatten().map(|x| x.0));
+        boxes.extend(self.get_time_axis_bbox());
+        if let (Option::Some(label), Option::Some(loc)) =
+            (&self.label, self.label_pos)
+        {
+            let half = get_size_for_text(&label.text, &label.look).scale(0.5);
+            boxes.push((loc.sub(half), loc.add(half)));
+        }
+        if let (Option::Some(legend), Option::Some(xy)) =
+            (&self.legend, self.legend_pos)
+        {
+            boxes.push((xy, xy.add(get_legend_size(legend))));
+        }
+        let first = *boxes.first()?;
+        Some(boxes.iter().fold(first, |acc, b| {
+            let tl = Point::new(acc.0.x.min(b.0.x), acc.0.y.min(b.0.y));
+            let br = Point::new(acc.1.x.max(b.1.x), acc.1.y.max(b.1.y));
+            (tl, br)
+        }))
+    }
+
+    /// Make room for the label of the graph above or below the drawing, and
+    /// \returns the location of the center of the label.
+    fn place_graph_label(&mut self) -> Option<Point> {
+        let label = self.label.as_ref()?;
+        let size = get_size_for_text(&label.text, &label.look);
+        let (top_left, bottom_right) = self.get_drawing_bbox()?;
+        let x = match label.justification {
+            Justification::Left => top_left.x + size.x / 2.,
+            Justification::Center => (top_left.x + bottom_right.x) / 2.,
+            Justification::Right => bottom_right.x - size.x / 2.,
+        };
+        // Labels that are wider than the drawing push it to the right.
+        let overflow = (top_left.x - (x - size.x / 2.)).max(0.);
+        let (y, shift) = if label.top {
+            (top_left.y + size.y / 2., size.y + GRAPH_LABEL_GAP)
+        } else {
+            (bottom_right.y + GRAPH_LABEL_GAP + size.y / 2., 0.)
+        };
+        let delta = Point::new(overflow, shift);
+        for node in self.iter_nodes() {
+            self.pos_mut(node).translate(delta);
+        }
+        self.legend_pos = self.legend_pos.map(|x| x.add(delta));
+        Some(Point::new(x + overflow, y))
+    }
+
+    /// Make room for the legend of the graph beside the drawing, and
+    /// \returns the location of the top-left corner of the legend. Legends
+    /// on the left push the drawing to the right.
+    fn place_legend(&mut self) -> Option<Point> {
+        let legend = self.legend.as_ref()?;
+        let size = get_legend_size(legend);
+        let (top_left, bottom_right) = self.get_drawing_bbox()?;
+        let left = matches!(
+            legend.position,
+            LegendPosition::TopLeft | LegendPosition::BottomLeft
+        );
+        let y = match legend.position {
+            LegendPosition::TopLeft | LegendPosition::TopRight => top_left.y,
+            // Legends that are taller than the drawing hang below it.
+            _ => top_left.y.max(bottom_right.y - size.y),
+        };
+        if !left {
+            return Some(Point::new(bottom_right.x + GRAPH_LABEL_GAP, y));
+        }
+        let delta = Point::new(size.x + GRAPH_LABEL_GAP, 0.);
+        for node in self.iter_nodes() {
+            self.pos_mut(node).translate(delta);
+        }
+        Some(Point::new(top_left.x, y))
     }
 
     fn lower(&mut self, disable_optimizations: bool) {
         #[cfg(feature = "log")]
         log::info!("Lowering a graph with {} nodes.", self.num_nodes());
         self.to_valid_dag();
-        self.split_text_edges();
-        self.split_long_edges(disable_optimizations);
+        // Only the hierarchical placer needs ranks. The edges of the other
+        // placers are not split, and the labels stay on the edges.
+        if self.engine != LayoutEngine::Hierarchical {
+            self.expand_self_edges();
+        } else {
+            self.split_text_edges();
+            self.split_long_edges(disable_optimizations);
+        }
 
         for elem in self.dag.iter() {
             self.element_mut(elem).resize();
@@ -169,6 +2155,7 @@ impl VisualGraph {
         // At this point the DAG should have all of the nodes, but none of the
         // edges. In here we construct the edges.
         assert_eq!(self.nodes.len(), self.dag.len(), "bad number of nodes");
+        let mut order = TopologicalOrder::new(&self.dag);
 
         // For each edge.
         for edge in edges {
@@ -178,22 +2165,38 @@ impl VisualGraph {
             let mut from = lst[0];
             let mut to = lst[1];
 
-            if from == to {
-                self.self_edges.push((arrow, from));
+            // Self edges are expanded after the nodes are ranked, and edges
+            // that don't constrain the ranks join the dag after the nodes are
+            // ranked.
+            let unconstrained =
+                !arrow.constraint && self.engine == LayoutEngine::Hierarchical;
+            if from == to || unconstrained {
+                self.edges.push((arrow, vec![from, to]));
                 continue;
             }
 
             // Reverse back edges.
-            if self.dag.is_reachable(to, from) {
+            if !order.add_edge(&self.dag, from, to) {
                 swap(&mut from, &mut to);
                 arrow = arrow.reverse();
+                order.add_edge(&self.dag, from, to);
             }
 
+            // Parallel edges keep the longest length and the highest weight.
+            let mut attr = EdgeAttr {
+                min_len: arrow.min_len,
+                weight: arrow.weight,
+            };
+            if self.dag.successors(from).contains(&to) {
+                let other = self.dag.edge_attr(from, to);
+                attr.min_len = attr.min_len.max(other.min_len);
+                attr.weight = attr.weight.max(other.weight);
+            }
             self.dag.add_edge(from, to);
-            self.add_edge(arrow, from, to);
-
-            self.dag.verify();
+            self.dag.set_edge_attr(from, to, attr);
+            self.edges.push((arrow, vec![from, to]));
         }
+        self.dag.verify();
     }
 
     /// Convert all of the edges that contain text labels to edges that go
@@ -210,8 +2213,14 @@ impl VisualGraph {
             let from = lst[0];
             let to = lst[1];
 
-            // If the edge is empty then there is nothing to do.
-            if edge.0.text.is_empty() {
+            // If the edge is empty then there is nothing to do. Edges between
+            // nodes on the same rank, and edges that don't constrain the
+            // ranks, keep their label.
+            if edge.0.text.is_empty()
+                || from == to
+                || !edge.0.constraint
+                || self.is_same_rank(from, to)
+            {
                 continue;
             }
 
@@ -226,28 +2235,153 @@ impl VisualGraph {
             edge.1 = vec![from, conn, to];
             edge.0.text = String::new();
 
-            // Add the edge to dag.
+            // Add the edge to dag. The label takes one of the ranks of the
+            // edge.
+            let attr = self.dag.edge_attr(from, to);
             let res = self.dag.remove_edge(from, to);
             assert!(res, "Expected the edge to be in the graph!");
             self.dag.add_edge(from, conn);
             self.dag.add_edge(conn, to);
+            let first = EdgeAttr {
+                min_len: 1,
+                weight: attr.weight,
+            };
+            let second = EdgeAttr {
+                min_len: attr.min_len.saturating_sub(1).max(1),
+                weight: attr.weight,
+            };
+            self.dag.set_edge_attr(from, conn, first);
+            self.dag.set_edge_attr(conn, to, second);
         }
 
         self.edges = edges;
     }
 
+    /// Add the edges that don't constrain the ranks to the dag, after the
+    /// nodes are ranked. The edges are flipped to point to the lower rank, and
+    /// edges between nodes on the same rank are drawn directly between the
+    /// nodes.
+    fn add_unconstrained_edges(&mut self) {
+        for edge in self.edges.iter_mut().filter(|x| !x.0.constraint) {
+            let from_level = self.dag.level(edge.1[0]);
+            let to_level = self.dag.level(edge.1[1]);
+            if from_level > to_level {
+                edge.0 = edge.0.reverse();
+                edge.1.reverse();
+            }
+            if from_level != to_level {
+                self.dag.add_edge(edge.1[0], edge.1[1]);
+            }
+        }
+    }
+
+    /// \returns the end that each edge in \p edges shares with other edges
+    /// when the edges are concentrated: (true, tail) for long edges that leave
+    /// the same node, (false, head) for long edges that enter the same node,
+    /// or None for edges that are drawn on their own. Edges with labels are
+    /// not merged.
+    fn get_concentration_keys(
+        &self,
+        edges: &[(Arrow, Vec<NodeHandle>)],
+    ) -> Vec<Option<(bool, NodeHandle)>> {
+        let is_long = |lst: &Vec<NodeHandle>| {
+            lst.len() == 2
+                && self.dag.level(lst[1]) > self.dag.level(lst[0]) + 1
+        };
+        let mut tails: HashMap<NodeHandle, usize> = HashMap::new();
+        let mut heads: HashMap<NodeHandle, usize> = HashMap::new();
+        if self.concentrate {
+            for (_, lst) in edges.iter().filter(|x| is_long(&x.1)) {
+                *tails.entry(lst[0]).or_default() += 1;
+                *heads.entry(lst[1]).or_default() += 1;
+            }
+        }
+        edges
+            .iter()
+            .map(|(_, lst)| {
+                if !self.concentrate || !is_long(lst) {
+                    None
+                } else if tails[&lst[0]] > 1 {
+                    Some((true, lst[0]))
+                } else if heads[&lst[1]] > 1 {
+                    Some((false, lst[1]))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     pub fn split_long_edges(&mut self, disable_optimizations: bool) {
+        // Only the nodes of the user have a place in the previous layout.
+        let warm_start = self.warm_start.clone().map(|mut w| {
+            w.nodes.resize(self.num_user_nodes, None);
+            w
+        });
+
         // Assign optimal rank to nodes in the graph.
-        self.dag.recompute_node_ranks();
+        if let (true, Option::Some(warm_start)) =
+            (self.rank_constraints.is_empty(), &warm_start)
+        {
+            let hint: Vec<Option<usize>> =
+                warm_start.nodes.iter().map(|x| x.map(|x| x.0)).collect();
+            self.dag.recompute_node_ranks_with_hint(&hint);
+        } else if self.rank_constraints.is_empty() {
+            if self.quality == LayoutQuality::High {
+                self.dag.recompute_node_ranks_with_network_simplex();
+            } else {
+                self.dag.recompute_node_ranks();
+            }
+        } else {
+            let constraints = &self.rank_constraints;
+            let dropped =
+                self.dag.recompute_node_ranks_with_constraints(constraints);
+            for i in dropped.iter().rev() {
+                #[cfg(feature = "log")]
+                log::info!("Ignoring the rank constraint #{}.", i);
+                self.rank_constraints.remove(*i);
+            }
+        }
         self.dag.verify();
-        if !disable_optimizations {
-            RankOptimizer::new(&mut self.dag).optimize();
+        // The network simplex ranks are already optimal, and the fast layout
+        // keeps the longest path ranks.
+        let optimal_ranks = self.quality == LayoutQuality::High
+            && self.rank_constraints.is_empty()
+            && warm_start.is_none();
+        if !disable_optimizations
+            && !optimal_ranks
+            && self.quality != LayoutQuality::Fast
+        {
+            // Nodes keep their rank from the previous layout.
+            let hinted = warm_start.iter().flat_map(|w| {
+                let nodes = w.nodes.iter().enumerate();
+                nodes
+                    .filter(|x| x.1.is_some())
+                    .map(|x| NodeHandle::new(x.0))
+            });
+            let fixed: Vec<NodeHandle> = self
+                .rank_constraints
+                .iter()
+                .flat_map(|(_, nodes)| nodes.iter().cloned())
+                .chain(hinted)
+                .collect();
+            RankOptimizer::new(&mut self.dag)
+                .with_fixed_nodes(&fixed)
+                .optimize();
         }
 
+        self.add_unconstrained_edges();
+
         let mut edges = self.edges.clone();
         self.edges.clear();
 
-        for edge in edges.iter_mut() {
+        // Maps the shared end of concentrated edges and a level to the
+        // connector that the edges go through at that level.
+        let keys = self.get_concentration_keys(&edges);
+        let mut shared: HashMap<(bool, NodeHandle, usize), NodeHandle> =
+            HashMap::new();
+
+        for (idx, edge) in edges.iter_mut().enumerate() {
             let mut lst = edge.1.clone();
 
             // Points the 'to' edge in each pair in the graph. We start with
@@ -260,6 +2394,14 @@ impl VisualGraph {
                 let prev_level = self.dag.level(prev);
                 let curr_level = self.dag.level(curr);
 
+                // Edges between nodes on the same rank are drawn directly
+                // between the nodes, and are not a part of the dag.
+                if prev_level == curr_level {
+                    self.dag.remove_edge(prev, curr);
+                    i += 1;
+                    continue;
+                }
+
                 // If the edges point to a lower rank then move on.
                 assert!(prev_level < curr_level, "Invalid edge");
                 if prev_level + 1 == curr_level {
@@ -267,16 +2409,45 @@ impl VisualGraph {
                     continue;
                 }
 
+                // The parts of the edge keep its weight.
+                let attr = EdgeAttr {
+                    min_len: 1,
+                    weight: self.dag.edge_attr(prev, curr).weight,
+                };
+
+                // Concentrated edges go through the connector of the edges
+                // that they share an end with.
+                let key =
+                    keys[idx].map(|(out, end)| (out, end, prev_level + 1));
+                if let Option::Some(conn) = key.and_then(|k| shared.get(&k)) {
+                    let conn = *conn;
+                    lst.insert(i, conn);
+                    self.dag.remove_edge(prev, curr);
+                    if !self.dag.successors(prev).contains(&conn) {
+                        self.dag.add_edge(prev, conn);
+                    }
+                    if !self.dag.successors(conn).contains(&curr) {
+                        self.dag.add_edge(conn, curr);
+                        self.dag.set_edge_attr(conn, curr, attr);
+                    }
+                    continue;
+                }
+
                 // We need to add a new connector node.
                 let dir = self.element(prev).orientation;
                 let conn = Element::empty_connector(dir);
                 let conn = self.add_node(conn);
                 lst.insert(i, conn);
+                if let Option::Some(key) = key {
+                    shared.insert(key, conn);
+                }
 
                 // Update the dag connections.
                 self.dag.remove_edge(prev, curr);
                 self.dag.add_edge(prev, conn);
                 self.dag.add_edge(conn, curr);
+                self.dag.set_edge_attr(prev, conn, attr);
+                self.dag.set_edge_attr(conn, curr, attr);
 
                 // Place the new connection node at the right level.
                 self.dag.update_node_rank_level(conn, prev_level + 1, None);
@@ -286,28 +2457,123 @@ impl VisualGraph {
         }
         self.edges = edges;
 
-        if !disable_optimizations {
-            EdgeCrossOptimizer::new(&mut self.dag).optimize();
+        if let Option::Some(warm_start) = &warm_start {
+            self.order_ranks_by_warm_start(warm_start);
+            if !disable_optimizations {
+                EdgeCrossOptimizer::new(&mut self.dag).optimize_locally();
+            }
+        } else if !disable_optimizations {
+            let ordering = match self.quality {
+                LayoutQuality::Fast => self.ordering.scaled(0.25),
+                LayoutQuality::Normal => self.ordering,
+                LayoutQuality::High => self.ordering.scaled(2.),
+            };
+            let mut opt =
+                EdgeCrossOptimizer::new(&mut self.dag).with_config(ordering);
+            if let Option::Some(seed) = self.seed {
+                opt = opt.with_seed(seed);
+            }
+            if self.frames.is_some() {
+                opt = opt.with_snapshots();
+            }
+            opt.optimize();
+            let snapshots = opt.take_snapshots();
+            self.add_ordering_frames(&snapshots);
         }
+        let crossings = EdgeCrossOptimizer::new(&mut self.dag).num_crossings();
+        #[cfg(feature = "log")]
+        log::info!("The layout has {} edge crossings.", crossings);
+        self.crossings = Some(crossings);
         self.expand_self_edges()
     }
 
-    /// Convert all of the saved self edges into proper edges in the graph.
+    /// Sort the nodes in each rank by their position in the previous layout
+    /// \p warm_start. New nodes and connectors are placed at the average
+    /// position of their neighbors, or at the end of the rank.
+    fn order_ranks_by_warm_start(&mut self, warm_start: &WarmStart) {
+        let mut keys: Vec<Option<f64>> = (0..self.dag.len())
+            .map(|i| warm_start.nodes.get(i).cloned().flatten().map(|x| x.1))
+            .collect();
+
+        // Find the keys of the other nodes from the rows above them, and then
+        // from the rows below them.
+        let num_levels = self.dag.num_levels();
+        let levels: Vec<usize> = (0..num_levels).collect();
+        for (down, level) in levels
+            .iter()
+            .map(|x| (true, *x))
+            .chain(levels.iter().rev().map(|x| (false, *x)))
+        {
+            for node in self.dag.row(level).iter() {
+                if keys[node.get_index()].is_some() {
+                    continue;
+                }
+                let neighbors = if down {
+                    self.dag.predecessors(*node)
+                } else {
+                    self.dag.successors(*node)
+                };
+                let known: Vec<f64> = neighbors
+                    .iter()
+                    .filter_map(|x| keys[x.get_index()])
+                    .collect();
+                if !known.is_empty() {
+                    let avg = known.iter().sum::<f64>() / known.len() as f64;
+                    keys[node.get_index()] = Option::Some(avg);
+                }
+            }
+        }
+
+        for level in 0..num_levels {
+            let row = self.dag.row_mut(level);
+            row.sort_by(|a, b| {
+                let a = keys[a.get_index()].unwrap_or(f64::INFINITY);
+                let b = keys[b.get_index()].unwrap_or(f64::INFINITY);
+                a.total_cmp(&b)
+            });
+        }
+    }
+
+    /// Convert all of the self edges into proper edges in the graph.
+    /// Each self edge goes through a connector that is placed after the node,
+    /// and after the connectors of the previous self edges of the node, so
+    /// that the loops can be stacked around their labels. The loops of nodes
+    /// that are first in a longer row go before the node, on the free side,
+    /// so they don't run into the edges of the next node.
     pub fn expand_self_edges(&mut self) {
-        for se in self.self_edges.clone().iter() {
-            let mut arrow = se.0.clone();
-            let node = se.1;
-            let level = self.dag.level(node);
-            let text = arrow.text.to_string();
-            arrow.text = String::new();
+        let mut num_loops = vec![0; self.num_nodes()];
+        let mut before = vec![false; self.num_nodes()];
+        for row in self.dag.ranks() {
+            if row.len() > 1 {
+                before[row[0].get_index()] = true;
+            }
+        }
+        for i in 0..self.edges.len() {
+            let lst = &self.edges[i].1;
+            if lst.len() != 2 || lst[0] != lst[1] {
+                continue;
+            }
+            let node = lst[0];
+            let arrow = &mut self.edges[i].0;
+            let text = core::mem::take(&mut arrow.text);
             let dir = self.element(node).orientation;
-            let conn = Element::create_connector(&text, &arrow.look, dir);
+            let look = self.edges[i].0.look.clone();
+            let conn = Element::create_connector(&text, &look, dir);
             let conn = self.add_node(conn);
-            self.dag.update_node_rank_level(conn, level, Some(node));
-            self.edges.push((arrow, vec![node, conn, node]));
-        }
+            let level = self.dag.level(node);
+            self.dag.update_node_rank_level(conn, level, None);
 
-        // Wipe out the self edges.
-        self.self_edges.clear();
+            // Move the connector to its place in the row.
+            let row = self.dag.row_mut(level);
+            row.pop();
+            let idx = row.iter().position(|x| *x == node).unwrap();
+            if before[node.get_index()] {
+                row.insert(idx - num_loops[node.get_index()], conn);
+            } else {
+                row.insert(idx + 1 + num_loops[node.get_index()], conn);
+            }
+            num_loops[node.get_index()] += 1;
+            self.edges[i].1 = vec![node, conn, node];
+        }
     }
 }
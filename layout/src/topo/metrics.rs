@@ -0,0 +1,230 @@
+//! Measures the quality of a layout, such as the number of edge crossings
+//! and the total length of the edges, so that the layouts of different
+//! engines and settings can be compared, and regressions in the quality of
+//! the layout can be caught by tests.
+//!
+//! ```
+//! # use layout::gv::{DotParser, GraphBuilder};
+//! let graph = DotParser::new("digraph { a -> b -> c; a -> c; }").process();
+//! let mut gb = GraphBuilder::new();
+//! gb.visit_graph(&graph.unwrap());
+//! let mut vg = gb.get();
+//! vg.layout(false, false);
+//! let metrics = vg.metrics();
+//! assert_eq!(metrics.num_nodes, 3);
+//! assert_eq!(metrics.num_layers, Some(3));
+//! assert_eq!(metrics.edge_crossings, 0);
+//! ```
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::{flatten_bezier_segments, Point};
+use crate::topo::layout::LayoutResult;
+use alloc::vec::Vec;
+
+/// The number of straight lines that approximate each bezier segment of an
+/// edge.
+const EDGE_STEPS: usize = 8;
+
+/// The smallest angle, in radians, between two segments of an edge that
+/// counts as a bend. Smaller angles are the rounding errors of curves.
+const MIN_BEND_ANGLE: f64 = 0.05;
+
+/// The statistics of a graph and the quality of its layout.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutMetrics {
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    /// The number of ranks that hold the nodes of a hierarchical layout, or
+    /// None for the engines that don't place the nodes in ranks.
+    pub num_layers: Option<usize>,
+    /// The number of times that two edges cross each other. Edges that share
+    /// a node are not counted.
+    pub edge_crossings: usize,
+    /// The sum of the lengths of the drawn edges.
+    pub total_edge_length: f64,
+    /// The width of the drawing divided by its height, without the margin.
+    pub aspect_ratio: f64,
+    /// The number of corners along the edges. Smooth curves have none, and
+    /// each turn of an orthogonal edge is a bend.
+    pub bends: usize,
+}
+
+impl LayoutMetrics {
+    /// \returns the metrics of the layout \p result. The layout result
+    /// doesn't have the ranks, so the number of layers is None.
+    pub fn from_result(result: &LayoutResult) -> Self {
+        let lines: Vec<Vec<Vec<Point>>> = result
+            .edges
+            .iter()
+            .map(|x| flatten_bezier_segments(&x.path, EDGE_STEPS))
+            .collect();
+
+        let mut total_edge_length = 0.;
+        for polyline in lines.iter().flatten() {
+            for w in polyline.windows(2) {
+                total_edge_length += w[0].distance_to(w[1]);
+            }
+        }
+
+        let mut edge_crossings = 0;
+        for i in 0..lines.len() {
+            for j in i + 1..lines.len() {
+                let (a, b) = (&result.edges[i], &result.edges[j]);
+                let ends = [b.from, b.to];
+                if ends.contains(&a.from) || ends.contains(&a.to) {
+                    continue;
+                }
+                edge_crossings += count_crossings(&lines[i], &lines[j]);
+            }
+        }
+
+        let bends = result.edges.iter().map(|x| count_bends(&x.path)).sum();
+
+        // The extent of the nodes and of the edges.
+        let points = result.nodes.iter().flat_map(|x| [x.0, x.1]);
+        let points = points.chain(lines.iter().flatten().flatten().cloned());
+        let mut bbox: Option<(Point, Point)> = None;
+        for p in points {
+            bbox = Some(match bbox {
+                Option::Some((lo, hi)) => (
+                    Point::new(lo.x.min(p.x), lo.y.min(p.y)),
+                    Point::new(hi.x.max(p.x), hi.y.max(p.y)),
+                ),
+                None => (p, p),
+            });
+        }
+        let aspect_ratio = match bbox {
+            Option::Some((lo, hi)) if hi.y > lo.y => {
+                (hi.x - lo.x) / (hi.y - lo.y)
+            }
+            _ => 1.,
+        };
+
+        Self {
+            num_nodes: result.nodes.len(),
+            num_edges: result.edges.len(),
+            num_layers: None,
+            edge_crossings,
+            total_edge_length,
+            aspect_ratio,
+            bends,
+        }
+    }
+}
+
+/// \returns the side of the line \p a-\p b that the point \p p is on: a
+/// positive number on one side, a negative number on the other side, and
+/// zero on the line.
+fn orientation(a: Point, b: Point, p: Point) -> f64 {
+    let ab = b.sub(a);
+    let ap = p.sub(a);
+    ab.x * ap.y - ab.y * ap.x
+}
+
+/// \returns true if the line segments \p a and \p b cross. The points on
+/// the line of the other segment count as being on its positive side, so
+/// that polylines that cross at a shared point of their segments cross once,
+/// and segments on the same line don't cross.
+fn segments_cross(a: (Point, Point), b: (Point, Point)) -> bool {
+    let side = |x: f64| x >= 0.;
+    let d1 = side(orientation(b.0, b.1, a.0));
+    let d2 = side(orientation(b.0, b.1, a.1));
+    let d3 = side(orientation(a.0, a.1, b.0));
+    let d4 = side(orientation(a.0, a.1, b.1));
+    d1 != d2 && d3 != d4
+}
+
+/// \returns the number of times that the polylines \p a cross the polylines
+/// \p b.
+fn count_crossings(a: &[Vec<Point>], b: &[Vec<Point>]) -> usize {
+    let mut res = 0;
+    for line_a in a.iter() {
+        for line_b in b.iter() {
+            for sa in line_a.windows(2) {
+                for sb in line_b.windows(2) {
+                    if segments_cross((sa[0], sa[1]), (sb[0], sb[1])) {
+                        res += 1;
+                    }
+                }
+            }
+        }
+    }
+    res
+}
+
+/// \returns the number of corners between the bezier segments \p path, where
+/// the direction of the curve changes.
+fn count_bends(path: &[(Point, Point, Point, Point)]) -> usize {
+    // The direction at the end of a segment, and at the start of a segment.
+    // The control points of straight lines may be at the ends.
+    let end_dir = |s: &(Point, Point, Point, Point)| {
+        let d = s.3.sub(s.2);
+        if d.length() > 0. {
+            d
+        } else {
+            s.3.sub(s.0)
+        }
+    };
+    let start_dir = |s: &(Point, Point, Point, Point)| {
+        let d = s.1.sub(s.0);
+        if d.length() > 0. {
+            d
+        } else {
+            s.3.sub(s.0)
+        }
+    };
+    path.windows(2)
+        .filter(|w| w[0].3 == w[1].0)
+        .filter(|w| {
+            let (a, b) = (end_dir(&w[0]), start_dir(&w[1]));
+            let angle = (a.x * b.y - a.y * b.x).atan2(a.x * b.x + a.y * b.y);
+            angle.abs() > MIN_BEND_ANGLE
+        })
+        .count()
+}
+
+#[test]
+fn test_layout_metrics() {
+    use crate::adt::dag::NodeHandle;
+    use crate::topo::layout::EdgeLayout;
+
+    let line = |a: Point, b: Point| (a, a, b, b);
+    let edge = |from: usize, to: usize, path| EdgeLayout {
+        from: NodeHandle::new(from),
+        to: NodeHandle::new(to),
+        path,
+        label: None,
+    };
+    let p = Point::new;
+    let result = LayoutResult {
+        nodes: vec![(p(0., 0.), p(10., 10.)); 4],
+        edges: vec![
+            // Two edges that cross, and an edge with a corner.
+            edge(0, 1, vec![line(p(0., 0.), p(100., 50.))]),
+            edge(2, 3, vec![line(p(0., 50.), p(100., 0.))]),
+            edge(
+                0,
+                2,
+                vec![
+                    line(p(0., 0.), p(0., 50.)),
+                    line(p(0., 50.), p(100., 50.)),
+                ],
+            ),
+        ],
+        clusters: Vec::new(),
+        bbox: (p(0., 0.), p(100., 50.)),
+    };
+    let metrics = LayoutMetrics::from_result(&result);
+    assert_eq!(metrics.num_nodes, 4);
+    assert_eq!(metrics.num_edges, 3);
+    assert_eq!(metrics.num_layers, None);
+    // The third edge shares the nodes of the other edges.
+    assert_eq!(metrics.edge_crossings, 1);
+    assert_eq!(metrics.bends, 1);
+    assert_eq!(metrics.aspect_ratio, 2.);
+    let diagonal = p(100., 50.).length();
+    let total = 2. * diagonal + 150.;
+    assert!((metrics.total_edge_length - total).abs() < 1e-6);
+}
@@ -1,5 +1,9 @@
 //! A module that implements the topological-based layout.
 
+pub mod analysis;
+pub mod hit;
 pub mod layout;
+pub mod metrics;
 pub mod optimizer;
 pub mod placer;
+pub mod router;
@@ -4,8 +4,52 @@
 //! optimizations that move nodes within a row to reduce edge crossing.
 
 use crate::adt::dag::NodeHandle;
-use crate::adt::dag::DAG;
+use crate::adt::dag::{RankType, DAG};
 use crate::core::base::Direction;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::utils::Rng;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Controls the effort of the optimization of the order of the nodes in the
+/// ranks.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderingConfig {
+    /// The number of passes that sort the ranks by the median position of the
+    /// neighbors of the nodes, and then swap adjacent nodes.
+    pub passes: usize,
+    /// The number of times that the ranks are perturbed, to escape from
+    /// orders that the passes can't improve.
+    pub restarts: usize,
+}
+
+/// Graphs with more nodes than this, including the connectors of the long
+/// edges, get proportionally fewer iterations of the ordering heuristics, to
+/// lay out in a reasonable time.
+const LARGE_GRAPH: usize = 10_000;
+
+impl Default for OrderingConfig {
+    fn default() -> Self {
+        Self {
+            passes: 24,
+            restarts: 50,
+        }
+    }
+}
+
+impl OrderingConfig {
+    /// \returns the config with the number of iterations scaled by \p factor,
+    /// like the 'mclimit' attribute of Graphviz.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let scale = |x: usize| (x as f64 * factor.max(0.)).round() as usize;
+        Self {
+            passes: scale(self.passes),
+            restarts: scale(self.restarts),
+        }
+    }
+}
 
 /// This optimizations changes the order of nodes within a rank (ordering along
 /// the x-axis). The transformation tries to reduce the number of edges that
@@ -13,46 +57,83 @@ use crate::core::base::Direction;
 #[derive(Debug)]
 pub struct EdgeCrossOptimizer<'a> {
     dag: &'a mut DAG,
+    // Shuffles the ranks, or None to use a fixed permutation.
+    rng: Option<Rng>,
+    config: OrderingConfig,
+    // The order of the ranks after each pass, or None to not record them.
+    snapshots: Option<Vec<RankType>>,
 }
 impl<'a> EdgeCrossOptimizer<'a> {
     pub fn new(dag: &'a mut DAG) -> Self {
-        Self { dag }
+        Self {
+            dag,
+            rng: None,
+            config: OrderingConfig::default(),
+            snapshots: None,
+        }
+    }
+
+    /// Spend the number of iterations in \p config on the optimization.
+    pub fn with_config(mut self, config: OrderingConfig) -> Self {
+        self.config = config;
+        self
     }
 
-    /// Given two nodes that may have connections in \p row, check how many of
-    /// these edges intersect. Check both successors and predecessors.
-    ///               A   B
-    ///             /   \/ \
-    ///            /    /\  \
-    ///  Row: [][][][][][][][][][]
-    fn num_crossing(
+    /// Shuffle the ranks with random numbers from the seed \p seed, instead
+    /// of the fixed permutation. Each seed gives a different, reproducible,
+    /// order of the nodes.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Option::Some(Rng::new(seed));
+        self
+    }
+
+    /// Record the order of the ranks after each pass of the optimization.
+    pub fn with_snapshots(mut self) -> Self {
+        self.snapshots = Some(Vec::new());
+        self
+    }
+
+    /// \returns the recorded orders of the ranks, in the order of the passes.
+    pub fn take_snapshots(&mut self) -> Vec<RankType> {
+        self.snapshots.take().unwrap_or_default()
+    }
+
+    fn add_snapshot(&mut self) {
+        if let Option::Some(snapshots) = &mut self.snapshots {
+            snapshots.push(self.dag.ranks().clone());
+        }
+    }
+
+    /// \returns the sorted positions of the nodes in the rank \p level that
+    /// the node \p node is connected to, by its successors or predecessors.
+    /// The array \p pos maps the nodes to their position in their rank.
+    fn neighbor_positions(
         &self,
-        a: NodeHandle,
-        b: NodeHandle,
-        row: &[NodeHandle],
-    ) -> usize {
-        let mut sum = 0;
-        // Record the number of edges that previously connected with node B.
-        let mut num_b = 0;
-
-        let a_edges1 = self.dag.successors(a);
-        let a_edges2 = self.dag.predecessors(a);
-        let b_edges1 = self.dag.successors(b);
-        let b_edges2 = self.dag.predecessors(b);
-
-        for node in row {
-            let is_a1 = a_edges1.iter().any(|x| x == node);
-            let is_a2 = a_edges2.iter().any(|x| x == node);
-            let is_b1 = b_edges1.iter().any(|x| x == node);
-            let is_b2 = b_edges2.iter().any(|x| x == node);
-            if is_a1 || is_a2 {
-                sum += num_b;
-            }
-            if is_b1 || is_b2 {
-                num_b += 1;
+        node: NodeHandle,
+        level: usize,
+        pos: &[usize],
+    ) -> Vec<usize> {
+        let succ = self.dag.successors(node).iter();
+        let pred = self.dag.predecessors(node).iter();
+        let mut res: Vec<usize> = succ
+            .chain(pred)
+            .filter(|x| self.dag.level(**x) == level)
+            .map(|x| pos[x.get_index()])
+            .collect();
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    /// \returns the position of each node in its rank.
+    fn positions(&self) -> Vec<usize> {
+        let mut pos = vec![0; self.dag.len()];
+        for level in 0..self.dag.num_levels() {
+            for (i, node) in self.dag.row(level).iter().enumerate() {
+                pos[node.get_index()] = i;
             }
         }
-        sum
+        pos
     }
 
     // Shuffle the nodes in all of the ranks.
@@ -61,7 +142,11 @@ impl<'a> EdgeCrossOptimizer<'a> {
             let row = self.dag.row_mut(i);
             let len = row.len();
             for j in 0..len {
-                row.swap((j * 17) % len, j);
+                let other = match &mut self.rng {
+                    Option::Some(rng) => rng.below(len),
+                    None => (j * 17) % len,
+                };
+                row.swap(other, j);
             }
         }
     }
@@ -74,7 +159,9 @@ impl<'a> EdgeCrossOptimizer<'a> {
         }
     }
 
-    pub fn optimize(&mut self) {
+    /// Order the nodes in the ranks to reduce the number of crossings, and
+    /// \returns the number of crossings in the best order.
+    pub fn optimize(&mut self) -> usize {
         self.dag.verify();
         #[cfg(feature = "log")]
         log::info!("Optimizing edge crossing.");
@@ -83,13 +170,21 @@ impl<'a> EdgeCrossOptimizer<'a> {
         let mut best_cnt = self.count_crossed_edges();
         #[cfg(feature = "log")]
         log::info!("Starting with {} crossings.", best_cnt);
-        for i in 0..50 {
+
+        let size = self.dag.len().max(LARGE_GRAPH);
+        let config = self.config.scaled(LARGE_GRAPH as f64 / size as f64);
+
+        for i in 0..config.restarts {
+            if best_cnt == 0 {
+                break;
+            }
             let dir = match i % 4 {
                 0 => Direction::Both,
                 1 => Direction::Up,
                 _ => Direction::Down,
             };
             self.swap_crossed_edges(dir);
+            self.add_snapshot();
             let new_cnt = self.count_crossed_edges();
             if new_cnt < best_cnt {
                 #[cfg(feature = "log")]
@@ -102,35 +197,141 @@ impl<'a> EdgeCrossOptimizer<'a> {
                 self.perturb_rank();
             }
         }
+        *self.dag.ranks_mut() = best_rank.clone();
+        // Refine the best order by sorting the ranks by the medians of the
+        // neighbors, alternating between the passes down and up the graph,
+        // and by swapping adjacent nodes.
+        for i in 0..config.passes {
+            if best_cnt == 0 {
+                break;
+            }
+            self.sort_by_median(i % 2 == 0);
+            self.swap_crossed_edges(Direction::Both);
+            self.add_snapshot();
+            let new_cnt = self.count_crossed_edges();
+            if new_cnt < best_cnt {
+                #[cfg(feature = "log")]
+                log::info!("Found a rank with {} crossings.", new_cnt);
+                best_rank = self.dag.ranks().clone();
+                best_cnt = new_cnt;
+            }
+        }
         *self.dag.ranks_mut() = best_rank;
+        self.add_snapshot();
+        best_cnt
+    }
+
+    /// \returns the number of edges that cross each other in the ranks.
+    pub fn num_crossings(&self) -> usize {
+        self.count_crossed_edges()
+    }
+
+    /// Sort the nodes in each rank by the median position of their neighbors
+    /// in the previous rank, going down the graph if \p down is set, or up
+    /// the graph otherwise. Nodes without neighbors keep their place.
+    fn sort_by_median(&mut self, down: bool) {
+        let num_levels = self.dag.num_levels();
+        let mut pos = vec![0; self.dag.len()];
+        for level in 0..num_levels {
+            for (i, node) in self.dag.row(level).iter().enumerate() {
+                pos[node.get_index()] = i;
+            }
+        }
+
+        let levels: Vec<usize> = if down {
+            (1..num_levels).collect()
+        } else {
+            (0..num_levels.saturating_sub(1)).rev().collect()
+        };
+        for level in levels {
+            let row = self.dag.row(level).clone();
+            let medians: Vec<Option<f64>> = row
+                .iter()
+                .map(|node| {
+                    let neighbors = if down {
+                        self.dag.predecessors(*node)
+                    } else {
+                        self.dag.successors(*node)
+                    };
+                    let mut p: Vec<usize> =
+                        neighbors.iter().map(|x| pos[x.get_index()]).collect();
+                    p.sort();
+                    get_weighted_median(&p)
+                })
+                .collect();
+
+            // Sort the nodes that have a median, in the slots of these nodes.
+            let slots: Vec<usize> =
+                (0..row.len()).filter(|i| medians[*i].is_some()).collect();
+            let mut movable = slots.clone();
+            movable.sort_by(|a, b| {
+                medians[*a].unwrap().total_cmp(&medians[*b].unwrap())
+            });
+            let mut new_row = row.clone();
+            for (slot, idx) in slots.iter().zip(movable.iter()) {
+                new_row[*slot] = row[*idx];
+            }
+            for (i, node) in new_row.iter().enumerate() {
+                pos[node.get_index()] = i;
+            }
+            *self.dag.row_mut(level) = new_row;
+        }
+    }
+
+    /// Reduce the number of crossings without shuffling the ranks, to keep
+    /// the order of the nodes of a previous layout. Only pairs of adjacent
+    /// nodes are swapped, and only when the swap removes crossings.
+    pub fn optimize_locally(&mut self) {
+        self.dag.verify();
+        #[cfg(feature = "log")]
+        log::info!("Optimizing edge crossing locally.");
+        self.swap_crossed_edges(Direction::Both);
     }
 
     fn count_crossed_edges(&self) -> usize {
+        if self.dag.num_levels() == 0 {
+            return 0;
+        }
+        let pos = self.positions();
         let mut sum = 0;
         // Compare each row to the row afterwards.
         for row_idx in 0..self.dag.num_levels() - 1 {
-            let first_row = self.dag.row(row_idx);
-            let second_row = self.dag.row(row_idx + 1);
-            sum += self.count_crossing_in_rows(first_row, second_row);
+            sum += self.count_crossing_in_rows(row_idx, &pos);
         }
         sum
     }
 
-    fn count_crossing_in_rows(
-        &self,
-        first: &[NodeHandle],
-        second: &[NodeHandle],
-    ) -> usize {
+    /// \returns the number of crossings between the edges that connect the
+    /// row \p row_idx and the row after it, where \p pos holds the positions
+    /// of the nodes. The crossings are the inversions in the sequence of the
+    /// positions of the edge ends in the second row, which are counted with a
+    /// Fenwick tree.
+    fn count_crossing_in_rows(&self, row_idx: usize, pos: &[usize]) -> usize {
+        let first = self.dag.row(row_idx);
         if first.len() < 2 {
             return 0;
         }
+        let mut tree = vec![0; self.dag.row(row_idx + 1).len() + 1];
+        let mut inserted = 0;
         let mut sum = 0;
-        // Check for each pair of nodes a,b where b comes after a.
-        for i in 0..first.len() {
-            for j in i + 1..first.len() {
-                let a = first[i];
-                let b = first[j];
-                sum += self.num_crossing(a, b, second);
+        for node in first {
+            for p in self.neighbor_positions(*node, row_idx + 1, pos) {
+                // Count the edges that were inserted before this one, and end
+                // to the right of it.
+                let mut i = p + 1;
+                let mut at_or_before = 0;
+                while i > 0 {
+                    at_or_before += tree[i];
+                    i &= i - 1;
+                }
+                sum += inserted - at_or_before;
+
+                let mut i = p + 1;
+                while i < tree.len() {
+                    tree[i] += 1;
+                    i += i & i.wrapping_neg();
+                }
+                inserted += 1;
             }
         }
         sum
@@ -140,111 +341,296 @@ impl<'a> EdgeCrossOptimizer<'a> {
     /// edges. If \p allow_swap is set then swap the edges if it reduces the
     /// number of crossing.
     fn swap_crossed_edges(&mut self, dir: Direction) {
+        let num_levels = self.dag.num_levels();
+        let mut pos = self.positions();
+        let neighbors = RankNeighbors::new(self.dag);
+        // Rows are only scanned again if they, or the rows next to them,
+        // changed after the last scan. These are the step numbers of the
+        // last change and of the last scan of each row.
+        let mut step = 1;
+        let mut changed_at = vec![0; num_levels];
+        let mut scanned_at = vec![0; num_levels];
         let mut changed = true;
-        while changed {
+        let mut sweeps = 0;
+        while changed && sweeps < 32 {
+            sweeps += 1;
             changed = false;
-            if dir.is_down() {
-                for i in 0..self.dag.num_levels() {
-                    changed |= self.swap_crossed_edges_on_row(i, dir);
+            let down = (0..num_levels).filter(|_| dir.is_down());
+            let up = (0..num_levels).rev().filter(|_| dir.is_up());
+            for i in down.chain(up) {
+                let first = i.saturating_sub(1);
+                let last = (i + 1).min(num_levels - 1);
+                let last_change = changed_at[first..=last].iter().max();
+                if scanned_at[i] > *last_change.unwrap() {
+                    continue;
                 }
-            }
-            if dir.is_up() {
-                for i in (0..self.dag.num_levels()).rev() {
-                    changed |= self.swap_crossed_edges_on_row(i, dir);
+                step += 1;
+                scanned_at[i] = step;
+                if self.swap_crossed_edges_on_row(i, dir, &neighbors, &mut pos)
+                {
+                    changed_at[i] = step;
+                    changed = true;
                 }
             }
         }
     }
 
-    /// See swap_crossed_edges.
+    /// See swap_crossed_edges. The neighbors of the nodes are in
+    /// \p neighbors, and the positions of the nodes in \p pos are updated
+    /// when the row changes.
     fn swap_crossed_edges_on_row(
         &mut self,
         row_idx: usize,
         dir: Direction,
+        neighbors: &RankNeighbors,
+        pos: &mut [usize],
     ) -> bool {
         let mut changed = false;
 
         let num_rows = self.dag.num_levels();
 
-        let prev_row = if row_idx > 0 && dir.is_up() {
-            self.dag.row(row_idx - 1).clone()
-        } else {
-            Vec::new()
-        };
-        let next_row = if row_idx + 1 < num_rows && dir.is_down() {
-            self.dag.row(row_idx + 1).clone()
-        } else {
-            Vec::new()
-        };
-
         let mut row = self.dag.row(row_idx).clone();
 
         if row.len() < 2 {
             return false;
         }
 
+        // Record the sorted positions of the neighbors of each node in the
+        // rows above and below it, that don't change while this row is sorted.
+        // The positions of all of the nodes are stored in one array, and each
+        // node holds the ranges of its positions in the rows above and below.
+        let use_prev = row_idx > 0 && dir.is_up();
+        let use_next = row_idx + 1 < num_rows && dir.is_down();
+        let mut positions: Vec<usize> = Vec::new();
+        let mut ranges: Vec<(Range<usize>, Range<usize>)> = row
+            .iter()
+            .map(|node| {
+                let mut range = |list: &[NodeHandle], used: bool| {
+                    let start = positions.len();
+                    if used {
+                        positions
+                            .extend(list.iter().map(|x| pos[x.get_index()]));
+                        positions[start..].sort_unstable();
+                    }
+                    start..positions.len()
+                };
+                let prev = range(neighbors.above(*node), use_prev);
+                let next = range(neighbors.below(*node), use_next);
+                (prev, next)
+            })
+            .collect();
+
         // For each two consecutive elements in the row:
         for i in 0..row.len() - 1 {
-            let a = row[i];
-            let b = row[i + 1];
+            let a = &ranges[i];
+            let b = &ranges[i + 1];
+            let (a0, a1) = (&positions[a.0.clone()], &positions[a.1.clone()]);
+            let (b0, b1) = (&positions[b.0.clone()], &positions[b.1.clone()]);
 
-            let mut ab = 0;
-            let mut ba = 0;
             // Figure out if A crosses the edges of B, and vice versa, on both
             // the edges pointing up and down.
-            ab += self.num_crossing(a, b, &prev_row);
-            ba += self.num_crossing(b, a, &prev_row);
-            ab += self.num_crossing(a, b, &next_row);
-            ba += self.num_crossing(b, a, &next_row);
+            let ab = count_crossings(a0, b0) + count_crossings(a1, b1);
+            let ba = count_crossings(b0, a0) + count_crossings(b1, a1);
 
             // Swap the edges.
             if ab > ba {
-                row[i] = b;
-                row[i + 1] = a;
+                row.swap(i, i + 1);
+                ranges.swap(i, i + 1);
                 changed = true;
             }
         }
 
         if changed {
+            for (i, node) in row.iter().enumerate() {
+                pos[node.get_index()] = i;
+            }
             *self.dag.row_mut(row_idx) = row;
         }
         changed
     }
 }
 
+/// The nodes that each node is connected to, by its successors or its
+/// predecessors, in the rank above it and in the rank below it. The lists of
+/// all of the nodes are stored in one array.
+struct RankNeighbors {
+    // The lists of node 'i' start at 'start[2i]' (above) and 'start[2i+1]'
+    // (below), and end where the next list starts.
+    start: Vec<usize>,
+    nodes: Vec<NodeHandle>,
+}
+
+impl RankNeighbors {
+    fn new(dag: &DAG) -> Self {
+        let mut start = Vec::with_capacity(dag.len() * 2 + 1);
+        let mut nodes = Vec::new();
+        for node in dag.iter() {
+            let level = dag.level(node);
+            let succ = dag.successors(node).iter();
+            let pred = dag.predecessors(node).iter();
+            for target in [level.wrapping_sub(1), level + 1] {
+                let first = nodes.len();
+                start.push(first);
+                let adjacent = succ.clone().chain(pred.clone());
+                nodes.extend(adjacent.filter(|x| dag.level(**x) == target));
+                nodes[first..].sort_unstable();
+                let mut last = first;
+                for i in first..nodes.len() {
+                    if i == first || nodes[i] != nodes[last - 1] {
+                        nodes[last] = nodes[i];
+                        last += 1;
+                    }
+                }
+                nodes.truncate(last);
+            }
+        }
+        start.push(nodes.len());
+        Self { start, nodes }
+    }
+
+    fn above(&self, node: NodeHandle) -> &[NodeHandle] {
+        let i = node.get_index() * 2;
+        &self.nodes[self.start[i]..self.start[i + 1]]
+    }
+
+    fn below(&self, node: NodeHandle) -> &[NodeHandle] {
+        let i = node.get_index() * 2 + 1;
+        &self.nodes[self.start[i]..self.start[i + 1]]
+    }
+}
+
+/// \returns the number of crossings between the edges of two nodes, where
+/// the first node is placed to the left of the second node, and \p a and \p b
+/// are the sorted positions of the neighbors of the nodes in another row.
+///               A   B
+///             /   \/ \
+///            /    /\  \
+///  Row: [][][][][][][][][][]
+fn count_crossings(a: &[usize], b: &[usize]) -> usize {
+    // Count the neighbors of B that are to the left of each neighbor of A.
+    let mut sum = 0;
+    let mut j = 0;
+    for x in a {
+        while j < b.len() && b[j] < *x {
+            j += 1;
+        }
+        sum += j;
+    }
+    sum
+}
+
+/// \returns the weighted median of the sorted positions \p p of the neighbors
+/// of a node, or None if the node has no neighbors. When there is an even
+/// number of neighbors, the median leans toward the side where the neighbors
+/// are packed more tightly.
+fn get_weighted_median(p: &[usize]) -> Option<f64> {
+    let m = p.len() / 2;
+    match p.len() {
+        0 => None,
+        1 => Some(p[0] as f64),
+        2 => Some((p[0] + p[1]) as f64 / 2.),
+        n if n % 2 == 1 => Some(p[m] as f64),
+        n => {
+            let left = (p[m - 1] - p[0]) as f64;
+            let right = (p[n - 1] - p[m]) as f64;
+            if left + right == 0. {
+                return Some((p[m - 1] + p[m]) as f64 / 2.);
+            }
+            Some(
+                (p[m - 1] as f64 * right + p[m] as f64 * left) / (left + right),
+            )
+        }
+    }
+}
+
+#[test]
+fn test_weighted_median() {
+    assert_eq!(get_weighted_median(&[]), None);
+    assert_eq!(get_weighted_median(&[3]), Some(3.));
+    assert_eq!(get_weighted_median(&[1, 4]), Some(2.5));
+    assert_eq!(get_weighted_median(&[1, 2, 9]), Some(2.));
+    // The neighbors on the right are spread, so the median leans left.
+    assert_eq!(get_weighted_median(&[0, 1, 2, 5]), Some(1.25));
+}
+
+#[test]
+fn test_median_ordering() {
+    // Two rows, where the nodes of the second row are in the reverse order
+    // of their predecessors. The sort removes all of the crossings.
+    let mut dag = DAG::new();
+    dag.new_nodes(6);
+    let h: Vec<NodeHandle> = (0..6).map(NodeHandle::new).collect();
+    for i in 0..3 {
+        dag.add_edge(h[i], h[5 - i]);
+    }
+    dag.recompute_node_ranks();
+    let mut opt = EdgeCrossOptimizer::new(&mut dag);
+    assert_eq!(opt.num_crossings(), 3);
+    opt.sort_by_median(true);
+    assert_eq!(opt.num_crossings(), 0);
+    assert_eq!(dag.row(1), &vec![h[5], h[4], h[3]]);
+
+    let mut opt = EdgeCrossOptimizer::new(&mut dag);
+    assert_eq!(opt.optimize(), 0);
+}
+
 /// This optimization sinks nodes in an attempt to shorten the length of edges
 /// that run through the graph.
 #[derive(Debug)]
 pub struct RankOptimizer<'a> {
     dag: &'a mut DAG,
+    // Marks the nodes that must stay in their rank.
+    fixed: Vec<bool>,
 }
 
 impl<'a> RankOptimizer<'a> {
     pub fn new(dag: &'a mut DAG) -> Self {
-        Self { dag }
+        let fixed = vec![false; dag.len()];
+        Self { dag, fixed }
+    }
+
+    /// Don't move the nodes \p nodes to a different rank.
+    pub fn with_fixed_nodes(mut self, nodes: &[NodeHandle]) -> Self {
+        for node in nodes {
+            self.fixed[node.get_index()] = true;
+        }
+        self
     }
 
     pub fn try_to_sink_node(&mut self, node: NodeHandle) -> bool {
+        if self.fixed[node.get_index()] {
+            return false;
+        }
+
         let backs = self.dag.predecessors(node);
         let fwds = self.dag.successors(node);
 
-        // Don't try to sink if we increase the number of live edges,
+        // Don't try to sink if we increase the weight of the live edges,
         // or if there are no forward edges.
-        if backs.len() > fwds.len() || backs.len() + fwds.len() == 0 {
+        let back_weight: f64 = backs
+            .iter()
+            .map(|x| self.dag.edge_attr(*x, node).weight)
+            .sum();
+        let fwd_weight: f64 = fwds
+            .iter()
+            .map(|x| self.dag.edge_attr(node, *x).weight)
+            .sum();
+        if back_weight > fwd_weight || backs.len() + fwds.len() == 0 {
             return false;
         }
 
+        // Find the lowest rank that keeps the minimal length of the forward
+        // edges.
         let curr_rank = self.dag.level(node);
-        let mut highest_next = self.dag.len();
+        let mut lowest = self.dag.len();
         for elem in fwds {
-            let next_rank = self.dag.level(*elem);
-            highest_next = highest_next.min(next_rank);
+            let len = self.dag.edge_attr(node, *elem).min_len;
+            lowest = lowest.min(self.dag.level(*elem) - len);
         }
 
         // We found an opportunity to sink a node.
-        if highest_next > curr_rank + 1 {
-            self.dag
-                .update_node_rank_level(node, highest_next - 1, None);
+        if lowest > curr_rank {
+            self.dag.update_node_rank_level(node, lowest, None);
             return true;
         }
         false
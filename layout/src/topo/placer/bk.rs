@@ -2,9 +2,11 @@
 //! paper "Fast and Simple Horizontal Coordinate Assignment."
 
 use crate::adt::dag::NodeHandle;
+use crate::adt::HashSet;
 use crate::core::geometry::weighted_median;
 use crate::topo::layout::VisualGraph;
-use std::collections::HashSet;
+use alloc::vec;
+use alloc::vec::Vec;
 
 use super::simple;
 
@@ -142,21 +144,35 @@ impl<'a> Scheduler<'a> {
             self.verify_vertical(v);
         }
 
-        let mut to_place = self.vl.len();
+        // Maps each node to the vertical that contains it.
+        let mut vertical_of = vec![0; self.vg.num_nodes()];
+        for (i, v) in self.vl.iter().enumerate() {
+            for elem in v {
+                vertical_of[elem.get_index()] = i;
+            }
+        }
 
-        while to_place > 0 {
-            for i in 0..self.vl.len() {
-                if !self.is_vertical_ready(i) {
-                    continue;
+        // The verticals are placed when all of their nodes are the next
+        // available nodes in their rows. Placing a vertical can only make the
+        // verticals of the next nodes in its rows ready.
+        let mut worklist: Vec<usize> = (0..self.vl.len()).rev().collect();
+        while let Some(i) = worklist.pop() {
+            if !self.is_vertical_ready(i) {
+                continue;
+            }
+            // Place the nodes.
+            let x = self.first_schedule_x(&self.vl[i]);
+            self.place_vertical(i, x);
+            // Wipe the vertical.
+            let v = core::mem::take(&mut self.vl[i]);
+            for elem in v.iter().rev() {
+                let level = self.vg.dag.level(*elem);
+                if let Some(next) = self.next_avail_in_row(level) {
+                    worklist.push(vertical_of[next.get_index()]);
                 }
-                // Place the nodes.
-                let x = self.first_schedule_x(&self.vl[i]);
-                self.place_vertical(i, x);
-                // Wipe the vertical.
-                self.vl[i].clear();
-                to_place -= 1;
             }
         }
+        assert!(self.vl.iter().all(|v| v.is_empty()), "Unscheduled nodes");
     }
 
     // \returns the first possible schedule point.
@@ -214,20 +230,25 @@ impl<'a> Scheduler<'a> {
         }
     }
 
-    /// \returns True if \p node is the next available in the row \p row_idx.
-    fn is_next_avail_in_row(&self, node: NodeHandle, row_idx: usize) -> bool {
+    /// \returns the next available node in the row \p row_idx.
+    fn next_avail_in_row(&self, row_idx: usize) -> Option<NodeHandle> {
         let row = self.vg.dag.row(row_idx);
         let first_free = self.sched_idx[row_idx];
         let len = row.len();
 
         if first_free < len {
-            return if self.order.is_left_to_right() {
-                row[first_free] == node
+            return Some(if self.order.is_left_to_right() {
+                row[first_free]
             } else {
-                row[len - first_free - 1] == node
-            };
+                row[len - first_free - 1]
+            });
         }
-        false
+        None
+    }
+
+    /// \returns True if \p node is the next available in the row \p row_idx.
+    fn is_next_avail_in_row(&self, node: NodeHandle, row_idx: usize) -> bool {
+        self.next_avail_in_row(row_idx) == Some(node)
     }
 
     /// \returns True if the vertical \p idx is ready for scheduling (if all of
@@ -286,10 +307,12 @@ impl<'a> BK<'a> {
     /// internal edges (edges between connection nodes).
     fn get_valid_edges(&self) -> EdgeSet {
         let mut valid_edges: EdgeSet = EdgeSet::new();
+        let positions = self.positions();
         for i in 0..self.vg.dag.num_levels() - 1 {
             let r0 = self.vg.dag.row(i);
             let r1 = self.vg.dag.row(i + 1);
-            let edges = self.extract_edges_with_no_type2_conflict(r0, r1);
+            let edges =
+                self.extract_edges_with_no_type2_conflict(r0, r1, &positions);
             for e in edges {
                 valid_edges.insert(e);
             }
@@ -303,6 +326,7 @@ impl<'a> BK<'a> {
         &self,
         r0: &[NodeHandle],
         r1: &[NodeHandle],
+        positions: &[usize],
     ) -> Vec<(NodeHandle, NodeHandle)> {
         let mut regular_edges: Vec<EdgeIdxs> = Vec::new();
         let mut strong_edges: Vec<EdgeIdxs> = Vec::new();
@@ -312,8 +336,8 @@ impl<'a> BK<'a> {
             for succ in self.vg.succ(*elem) {
                 // Check if and where it points to in R1. (we could have
                 // same-row self-edges).
-                if let Option::Some(idx1) = r1.iter().position(|&r| r == *succ)
-                {
+                let idx1 = positions[succ.get_index()];
+                if idx1 < r1.len() && r1[idx1] == *succ {
                     // Figure out if this is a strong edge or a regular edge.
                     let c0 = self.vg.is_connector(*elem);
                     let c1 = self.vg.is_connector(*succ);
@@ -327,16 +351,44 @@ impl<'a> BK<'a> {
         }
         let mut res: Vec<(NodeHandle, NodeHandle)> = Vec::new();
 
-        'outer: for reg in regular_edges.iter() {
-            for strong in strong_edges.iter() {
-                // Check if there is no conflict.
-                if Self::are_edges_crossing(*reg, *strong) {
-                    // Continue to the next strong edges.
-                    continue;
-                }
+        // Sort the strong edges by their source, and record the edge with the
+        // lowest destination among the edges that start before each strong
+        // edge, and the edge with the highest destination among the edges
+        // that start after it. These are the only edges that can conflict
+        // with a regular edge.
+        strong_edges.sort();
+        let mut lowest: Vec<usize> = Vec::with_capacity(strong_edges.len());
+        for (i, strong) in strong_edges.iter().enumerate() {
+            match lowest.last() {
+                Some(&j) if strong_edges[j].1 <= strong.1 => lowest.push(j),
+                _ => lowest.push(i),
+            }
+        }
+        let mut highest = vec![0; strong_edges.len()];
+        for (i, strong) in strong_edges.iter().enumerate().rev() {
+            highest[i] = match highest.get(i + 1) {
+                Some(&j) if strong_edges[j].1 >= strong.1 => j,
+                _ => i,
+            };
+        }
 
+        for reg in regular_edges.iter() {
+            let first_at = strong_edges.partition_point(|x| x.0 < reg.0);
+            let first_after = strong_edges.partition_point(|x| x.0 <= reg.0);
+            let mut candidates = Vec::new();
+            if first_at > 0 {
+                candidates.push(strong_edges[lowest[first_at - 1]]);
+            }
+            if first_after < strong_edges.len() {
+                candidates.push(strong_edges[highest[first_after]]);
+            }
+            // Check if there is no conflict.
+            if !candidates
+                .iter()
+                .all(|strong| Self::are_edges_crossing(*reg, *strong))
+            {
                 // Found a conflict, we must not register this edge.
-                continue 'outer;
+                continue;
             }
             // None of the strong edges conflicted with the regular edge.
             res.push((r0[reg.0], r1[reg.1]));
@@ -385,9 +437,15 @@ impl<'a> BK<'a> {
         res
     }
 
-    /// \returns the index of \p elem in \p vec.
-    fn index_of(elem: NodeHandle, vec: &[NodeHandle]) -> Option<usize> {
-        (0..vec.len()).find(|&i| vec[i] == elem)
+    /// \returns the index of each node in its row.
+    fn positions(&self) -> Vec<usize> {
+        let mut res = vec![0; self.vg.num_nodes()];
+        for i in 0..self.vg.dag.num_levels() {
+            for (idx, node) in self.vg.dag.row(i).iter().enumerate() {
+                res[node.get_index()] = idx;
+            }
+        }
+        res
     }
 
     fn compute_alignment(&self, order: OrderLR) -> NodeAttachInfo {
@@ -399,14 +457,15 @@ impl<'a> BK<'a> {
 
         // The desired medians for each node in the graph.
         let medians: Vec<f64> = self.get_pred_medians(valid_edges);
+        let positions = self.positions();
 
         for i in 0..self.vg.dag.num_levels() - 1 {
             // The row above.
             let mut r0 = self.vg.dag.row(i).clone();
             // The current row.
             let mut r1 = self.vg.dag.row(i + 1).clone();
-            // Marks which nodes of r0 are available.
-            let mut used: Vec<bool> = vec![false; r0.len()];
+            // The nodes of r0 before this index are not available.
+            let mut first_free = 0;
 
             // Simulate searching from the right by reversing the order of the
             // edges, and the order of the collisions.
@@ -419,36 +478,41 @@ impl<'a> BK<'a> {
                 let node_x = medians[node.get_index()];
                 let mut best_idx: Option<usize> = None;
                 let mut best_delta = f64::INFINITY;
+                let mut best_weight = f64::NEG_INFINITY;
 
                 // Scan the predecessors:
                 for pred in self.vg.preds(node) {
-                    let idx;
-                    // Search for the index of the predecessor in the row.
-                    if let Some(idx_in_row) = Self::index_of(*pred, &r0) {
-                        idx = idx_in_row;
-                    } else {
+                    // Find the index of the predecessor in the row.
+                    if self.vg.dag.level(*pred) != i {
                         continue;
                     }
+                    let mut idx = positions[pred.get_index()];
+                    if !order.is_left_to_right() {
+                        idx = r0.len() - 1 - idx;
+                    }
 
                     // Don't mess with nodes that are taken.
-                    if used[idx] {
+                    if idx < first_free {
                         continue;
                     }
 
-                    // Of the remaining edges, select the closest one.
+                    // Of the remaining edges, select the heaviest one, and then
+                    // the closest one. Heavy edges are drawn straight.
+                    let weight = self.vg.dag.edge_attr(*pred, node).weight;
                     let delta = (self.vg.pos(*pred).center().x - node_x).abs();
-                    if delta < best_delta {
+                    if weight > best_weight
+                        || (weight == best_weight && delta < best_delta)
+                    {
                         best_idx = Some(idx);
                         best_delta = delta;
+                        best_weight = weight;
                     }
                 }
 
                 // Mark the current node as aligned to the 'best' node on the
                 // previous line.
                 if let Some(idx) = best_idx {
-                    for i in 0..(idx + 1) {
-                        used[i] = true;
-                    }
+                    first_free = first_free.max(idx + 1);
                     align_info.add(node, r0[idx]);
                 }
             }
@@ -0,0 +1,258 @@
+//! This module contains the circular placer, which assigns the (x,y)
+//! coordinates of the elements in the style of 'circo'. The graph is split
+//! into biconnected components (blocks), and the nodes of each block are
+//! placed on a circle. Blocks that share a node (an articulation point) are
+//! placed around the shared node, outside of the circle of the first block.
+//! The edges are drawn as chords of the circles.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::{
+    get_radius, get_undirected_graph, move_to_origin, place_parts,
+    place_self_edges,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// The space between neighboring nodes on a circle, and between the parts of
+/// the graph that are not connected.
+const NODE_GAP: f64 = 30.;
+
+#[derive(Debug)]
+pub struct CircularPlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> CircularPlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self, no_layout: bool) {
+        #[cfg(feature = "log")]
+        log::info!(
+            "Starting circular layout of {} nodes.",
+            self.vg.num_nodes()
+        );
+
+        let (nodes, edges) = get_undirected_graph(self.vg);
+        let radius = get_radius(self.vg, &nodes);
+
+        // Without the layout, place all of the nodes on one circle.
+        let blocks = if no_layout {
+            vec![(0..nodes.len()).collect()]
+        } else {
+            get_blocks(nodes.len(), &edges)
+        };
+        let locs = place_blocks(&blocks, &radius);
+
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            self.vg.pos_mut(*node).move_to(*loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+}
+
+/// \returns the biconnected components of the graph with \p n nodes and the
+/// edges \p edges. The nodes of each component are sorted in the order of the
+/// depth-first search, so cycles are listed in the order of the cycle. Nodes
+/// without edges are components of their own.
+fn get_blocks(n: usize, edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (a, b) in edges {
+        if !adj[*a].contains(b) {
+            adj[*a].push(*b);
+            adj[*b].push(*a);
+        }
+    }
+
+    const UNVISITED: usize = usize::MAX;
+    let mut disc = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut time = 0;
+    let mut blocks: Vec<Vec<usize>> = Vec::new();
+    let mut edge_stack: Vec<(usize, usize)> = Vec::new();
+
+    for root in 0..n {
+        if disc[root] != UNVISITED {
+            continue;
+        }
+        disc[root] = time;
+        low[root] = time;
+        time += 1;
+        if adj[root].is_empty() {
+            blocks.push(vec![root]);
+            continue;
+        }
+
+        // A stack of (node, parent, index of the next neighbor to visit).
+        let mut worklist: Vec<(usize, usize, usize)> = vec![(root, root, 0)];
+        while let Some((u, parent, next)) = worklist.pop() {
+            if next < adj[u].len() {
+                worklist.push((u, parent, next + 1));
+                let v = adj[u][next];
+                if disc[v] == UNVISITED {
+                    disc[v] = time;
+                    low[v] = time;
+                    time += 1;
+                    edge_stack.push((u, v));
+                    worklist.push((v, u, 0));
+                } else if v != parent && disc[v] < disc[u] {
+                    edge_stack.push((u, v));
+                    low[u] = low[u].min(disc[v]);
+                }
+                continue;
+            }
+
+            // We are done with 'u'. If the parent separates 'u' from the
+            // nodes above it then the edges from the parent form a block.
+            if u == parent {
+                continue;
+            }
+            low[parent] = low[parent].min(low[u]);
+            if low[u] >= disc[parent] {
+                let mut block: Vec<usize> = Vec::new();
+                while let Some((a, b)) = edge_stack.pop() {
+                    for x in [a, b] {
+                        if !block.contains(&x) {
+                            block.push(x);
+                        }
+                    }
+                    if (a, b) == (parent, u) {
+                        break;
+                    }
+                }
+                block.sort_by_key(|x| disc[*x]);
+                blocks.push(block);
+            }
+        }
+    }
+    blocks
+}
+
+/// \returns the radius of a circle that can hold the nodes \p block, where
+/// the radius of each node is in \p radius.
+fn get_circle_radius(block: &[usize], radius: &[f64]) -> f64 {
+    if block.len() < 2 {
+        return 0.;
+    }
+    let largest = block.iter().map(|x| radius[*x]).fold(0., f64::max);
+    let perimeter: f64 = block.iter().map(|x| 2. * radius[*x] + NODE_GAP).sum();
+    let step = PI / block.len() as f64;
+    (perimeter / (2. * PI)).max((2. * largest + NODE_GAP) / (2. * step.sin()))
+}
+
+/// \returns the locations of the nodes of the \p blocks, where the radius of
+/// each node is in \p radius. The connected parts of the graph are placed
+/// next to each other, from left to right.
+fn place_blocks(blocks: &[Vec<usize>], radius: &[f64]) -> Vec<Point> {
+    let n = radius.len();
+    let mut locs = vec![Point::zero(); n];
+    let mut node_blocks: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (i, block) in blocks.iter().enumerate() {
+        for x in block {
+            node_blocks[*x].push(i);
+        }
+    }
+
+    let mut placed_nodes = vec![false; n];
+    let mut placed_blocks = vec![false; blocks.len()];
+    let mut parts: Vec<Vec<usize>> = Vec::new();
+
+    // Start each connected part of the graph from its largest block.
+    let mut order: Vec<usize> = (0..blocks.len()).collect();
+    order.sort_by_key(|b| core::cmp::Reverse(blocks[*b].len()));
+    for root in order {
+        if blocks[root].iter().any(|x| placed_nodes[*x]) {
+            continue;
+        }
+
+        // Place the blocks, and then the blocks that hang from their nodes.
+        let mut part: Vec<usize> = Vec::new();
+        let mut worklist = vec![(root, Point::zero(), -PI / 2.)];
+        placed_blocks[root] = true;
+        while let Some((b, center, start_angle)) = worklist.pop() {
+            let block = &blocks[b];
+            let r = get_circle_radius(block, radius);
+
+            // Place the nodes on the circle, starting with the node that is
+            // already placed (the articulation point), if there is one.
+            let first = block.iter().position(|x| placed_nodes[*x]);
+            let first = first.unwrap_or(0);
+            let len = block.len();
+            for i in 0..len {
+                let x = block[(first + i) % len];
+                let angle = start_angle + 2. * PI * i as f64 / len as f64;
+                if !placed_nodes[x] {
+                    locs[x] = center
+                        .add(Point::new(angle.cos(), angle.sin()).scale(r));
+                    placed_nodes[x] = true;
+                    part.push(x);
+                }
+            }
+
+            // Hang the other blocks of each node outside of the circle,
+            // spread around the direction from the center to the node.
+            for x in block.iter() {
+                let children: Vec<usize> = node_blocks[*x]
+                    .iter()
+                    .cloned()
+                    .filter(|c| !placed_blocks[*c])
+                    .collect();
+                let dir = locs[*x].sub(center);
+                let base = if dir.length() > 0. {
+                    dir.y.atan2(dir.x)
+                } else {
+                    0.
+                };
+                let fan = PI / children.len().max(1) as f64;
+                for (i, c) in children.iter().enumerate() {
+                    placed_blocks[*c] = true;
+                    let offset =
+                        (i as f64 - (children.len() - 1) as f64 / 2.) * fan;
+                    let angle = base + offset;
+                    let child_r = get_circle_radius(&blocks[*c], radius);
+                    let dir = Point::new(angle.cos(), angle.sin());
+                    let child_center = locs[*x].add(dir.scale(child_r));
+                    worklist.push((*c, child_center, angle + PI));
+                }
+            }
+        }
+
+        parts.push(part);
+    }
+    place_parts(&mut locs, radius, &parts, NODE_GAP);
+    locs
+}
+
+#[test]
+fn test_biconnected_components() {
+    // Two triangles that share the node 2, a bridge to the node 5, and the
+    // node 6 that is not connected.
+    let edges = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2), (4, 5)];
+    let mut blocks = get_blocks(7, &edges);
+    for block in blocks.iter_mut() {
+        block.sort();
+    }
+    blocks.sort();
+    assert_eq!(
+        blocks,
+        vec![vec![0, 1, 2], vec![2, 3, 4], vec![4, 5], vec![6]]
+    );
+
+    // The nodes of each block are on a circle, and the circles don't overlap.
+    let radius = vec![10.; 7];
+    let locs = place_blocks(&get_blocks(7, &edges), &radius);
+    for i in 0..7 {
+        for j in i + 1..7 {
+            assert!(locs[i].distance_to(locs[j]) > 20.);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! This pass keeps the members of each cluster together. Before the placement
+//! it sorts the rows so that the members of a cluster are next to each other,
+//! and after the placement it moves the clusters apart, so that the box of
+//! each cluster does not cover nodes or edges that are not in the cluster.
+
+use crate::adt::dag::NodeHandle;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// The order of the nodes and clusters, as decided by the grouping pass. The
+/// keys are the average relative location in the rows, before the sort.
+#[derive(Debug)]
+pub(crate) struct ClusterOrder {
+    nodes: Vec<f64>,
+    clusters: Vec<f64>,
+}
+
+/// A node, or a cluster with everything in it.
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    Node(NodeHandle),
+    Cluster(usize),
+}
+
+/// Assign the connectors of each edge to the innermost cluster that contains
+/// both ends of the edge, so that edges inside a cluster stay in it.
+fn assign_connectors(vg: &mut VisualGraph) {
+    for (from, to, connectors) in vg.edge_connectors() {
+        let from_path = vg.cluster_path(from);
+        let to_path = vg.cluster_path(to);
+        let common = from_path
+            .iter()
+            .zip(to_path.iter())
+            .take_while(|(a, b)| a == b)
+            .last()
+            .map(|(a, _)| *a);
+        if let Option::Some(cluster) = common {
+            for conn in connectors {
+                vg.add_to_cluster(conn, cluster);
+            }
+        }
+    }
+}
+
+/// Sort the rows so that the members of each cluster are next to each other,
+/// and the clusters appear in the same order in all of the rows.
+pub(crate) fn group(vg: &mut VisualGraph) -> ClusterOrder {
+    let mut order = ClusterOrder {
+        nodes: vec![0.; vg.num_nodes()],
+        clusters: vec![0.; vg.num_clusters()],
+    };
+    if vg.num_clusters() == 0 {
+        return order;
+    }
+    assign_connectors(vg);
+
+    // The relative location of each node in its row.
+    let mut counts = vec![0; vg.num_clusters()];
+    for row in vg.dag.ranks() {
+        for (i, node) in row.iter().enumerate() {
+            let key = (i as f64 + 0.5) / row.len() as f64;
+            order.nodes[node.get_index()] = key;
+            for cluster in vg.cluster_path(*node) {
+                order.clusters[cluster] += key;
+                counts[cluster] += 1;
+            }
+        }
+    }
+    for (key, cnt) in order.clusters.iter_mut().zip(counts) {
+        *key /= cnt.max(1) as f64;
+    }
+
+    // Sort by the keys of the enclosing clusters, from the outermost, and
+    // then by the key of the node.
+    let sort_key = |node: NodeHandle| -> Vec<f64> {
+        let mut key: Vec<f64> = vg
+            .cluster_path(node)
+            .iter()
+            .map(|c| order.clusters[*c])
+            .collect();
+        key.push(order.nodes[node.get_index()]);
+        key
+    };
+    let mut ranks = vg.dag.ranks().clone();
+    for row in ranks.iter_mut() {
+        row.sort_by(|a, b| {
+            sort_key(*a)
+                .partial_cmp(&sort_key(*b))
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+    *vg.dag.ranks_mut() = ranks;
+    order
+}
+
+/// \returns the nodes in the cluster \p cluster and in its sub-clusters.
+fn get_nodes(vg: &VisualGraph, cluster: usize) -> Vec<NodeHandle> {
+    vg.iter_nodes()
+        .filter(|node| {
+            vg.cluster_of(*node)
+                .is_some_and(|c| vg.is_in_cluster(c, cluster))
+        })
+        .collect()
+}
+
+/// \returns the left and right edge, and the first and last row of \p item.
+fn get_extent(
+    vg: &VisualGraph,
+    item: Item,
+    transposed: bool,
+) -> Option<(f64, f64, usize, usize)> {
+    match item {
+        Item::Node(node) => {
+            let pos = vg.pos(node);
+            let level = vg.dag.level(node);
+            Some((pos.left(true), pos.right(true), level, level))
+        }
+        Item::Cluster(cluster) => {
+            let bbox = vg.cluster_bbox(cluster, transposed)?;
+            let levels: Vec<usize> = get_nodes(vg, cluster)
+                .iter()
+                .map(|node| vg.dag.level(*node))
+                .collect();
+            let first = *levels.iter().min()?;
+            let last = *levels.iter().max()?;
+            Some((bbox.0.x, bbox.1.x, first, last))
+        }
+    }
+}
+
+/// Move \p item, and everything in it, by \p dx along the x axis.
+fn shift(vg: &mut VisualGraph, item: Item, dx: f64) {
+    let nodes: Vec<NodeHandle> = match item {
+        Item::Node(node) => vec![node],
+        Item::Cluster(cluster) => get_nodes(vg, cluster),
+    };
+    for node in nodes {
+        vg.pos_mut(node).translate(Point::new(dx, 0.));
+    }
+}
+
+/// Move the items in \p parent (or the top level items) to the right, until
+/// no item overlaps the items before it in rows that both of them occupy.
+fn separate_items(
+    vg: &mut VisualGraph,
+    order: &ClusterOrder,
+    parent: Option<usize>,
+    transposed: bool,
+) {
+    let mut items: Vec<(f64, Item)> = Vec::new();
+    for cluster in 0..vg.num_clusters() {
+        if vg.cluster(cluster).parent == parent {
+            separate_items(vg, order, Some(cluster), transposed);
+            items.push((order.clusters[cluster], Item::Cluster(cluster)));
+        }
+    }
+    for node in vg.iter_nodes() {
+        if vg.cluster_of(node) == parent {
+            items.push((order.nodes[node.get_index()], Item::Node(node)));
+        }
+    }
+    items.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    let mut placed: Vec<(f64, f64, usize, usize)> = Vec::new();
+    for (_, item) in items {
+        let (mut left, mut right, first, last) =
+            match get_extent(vg, item, transposed) {
+                Option::Some(extent) => extent,
+                Option::None => continue,
+            };
+        let required = placed
+            .iter()
+            .filter(|other| other.2 <= last && first <= other.3)
+            .map(|other| other.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if left < required {
+            shift(vg, item, required - left);
+            right += required - left;
+            left = required;
+        }
+        placed.push((left, right, first, last));
+    }
+}
+
+/// Move the clusters apart, after the nodes were placed. The graph is
+/// \p transposed in left-to-right layouts.
+pub(crate) fn separate(
+    vg: &mut VisualGraph,
+    order: &ClusterOrder,
+    transposed: bool,
+) {
+    if vg.num_clusters() == 0 {
+        return;
+    }
+    separate_items(vg, order, None, transposed);
+
+    // The boxes and the labels of the clusters may stick out of the top left
+    // corner of the graph. Move the graph to make room for them.
+    let mut corner = Point::zero();
+    for cluster in 0..vg.num_clusters() {
+        if let Option::Some(bbox) = vg.cluster_bbox(cluster, transposed) {
+            corner = Point::new(corner.x.min(bbox.0.x), corner.y.min(bbox.0.y));
+        }
+    }
+    if corner.x < 0. || corner.y < 0. {
+        for node in vg.iter_nodes() {
+            vg.pos_mut(node).translate(corner.neg());
+        }
+    }
+}
@@ -5,6 +5,7 @@ use crate::adt::dag::NodeHandle;
 use crate::core::geometry::{in_range, segment_rect_intersection, Point};
 use crate::topo::layout::VisualGraph;
 use crate::topo::placer::simple::align_to_left;
+use alloc::vec::Vec;
 
 /// Return the leftmost and rightmost x coordinate that are taken by another
 /// shape.
@@ -0,0 +1,417 @@
+//! This module contains the fitting of the layout into a requested size, like
+//! the 'size', 'ratio' and 'page' attributes of Graphviz. The ratio stretches
+//! or compresses the locations of the nodes after they are placed, and the
+//! size scales the whole drawing, with its text and lines, when it is drawn.
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The space between the nodes that the compression keeps.
+const COMPRESS_GAP: f64 = 4.;
+
+/// Selects how the locations of the nodes are stretched or compressed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ratio {
+    /// Stretch the layout in one direction, until its height divided by its
+    /// width is the number.
+    Aspect(f64),
+    /// Stretch the layout in each direction, until it fills the size.
+    Fill,
+    /// Move the nodes closer together, until the layout fits in the size or
+    /// the nodes would overlap.
+    Compress,
+    /// Spread the layout in both directions, until it fills the size in one
+    /// of them.
+    Expand,
+    /// Keep the layout as it is.
+    Auto,
+}
+
+impl Ratio {
+    /// \returns the ratio of the Graphviz 'ratio' value \p name, such as
+    /// "fill" or "0.5".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fill" => Some(Ratio::Fill),
+            "compress" => Some(Ratio::Compress),
+            "expand" => Some(Ratio::Expand),
+            "auto" => Some(Ratio::Auto),
+            _ => match name.parse::<f64>() {
+                Result::Ok(x) if x > 0. => Some(Ratio::Aspect(x)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A side of the drawing, that a sequence of pages starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'T' => Some(Side::Top),
+            'B' => Some(Side::Bottom),
+            'L' => Some(Side::Left),
+            'R' => Some(Side::Right),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Side::Top => 'T',
+            Side::Bottom => 'B',
+            Side::Left => 'L',
+            Side::Right => 'R',
+        }
+    }
+
+    fn is_vertical(&self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+}
+
+/// The order of the pages of a drawing that doesn't fit on one page, like
+/// the 'pagedir' attribute of Graphviz. The pages go from the \p minor side
+/// to the other side first, and then the next row or column of pages starts,
+/// going from the \p major side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageDir {
+    pub major: Side,
+    pub minor: Side,
+}
+
+impl Default for PageDir {
+    fn default() -> Self {
+        Self {
+            major: Side::Bottom,
+            minor: Side::Left,
+        }
+    }
+}
+
+impl PageDir {
+    /// \returns the page order of the Graphviz 'pagedir' value \p name, such
+    /// as "BL" or "RT".
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        let major = Side::from_char(chars.next()?)?;
+        let minor = Side::from_char(chars.next()?)?;
+        if chars.next().is_some() || major.is_vertical() == minor.is_vertical()
+        {
+            return None;
+        }
+        Some(Self { major, minor })
+    }
+
+    /// \returns the Graphviz 'pagedir' value of the order, such as "BL".
+    pub fn to_name(&self) -> String {
+        [self.major.to_char(), self.minor.to_char()]
+            .iter()
+            .collect()
+    }
+
+    /// \returns the column and the row of the pages of a drawing with
+    /// \p columns columns and \p rows rows of pages, in the order of the
+    /// pages. The first row is at the top.
+    pub fn get_order(
+        &self,
+        columns: usize,
+        rows: usize,
+    ) -> Vec<(usize, usize)> {
+        // \returns the index along the axis of the side \p side, that is
+        // \p len long, of the \p i-th page from the side.
+        let index = |side: Side, i: usize, len: usize| match side {
+            Side::Top | Side::Left => i,
+            Side::Bottom | Side::Right => len - 1 - i,
+        };
+        let mut res = Vec::new();
+        let (major_len, minor_len) = if self.major.is_vertical() {
+            (rows, columns)
+        } else {
+            (columns, rows)
+        };
+        for i in 0..major_len {
+            for j in 0..minor_len {
+                let a = index(self.major, i, major_len);
+                let b = index(self.minor, j, minor_len);
+                res.push(if self.major.is_vertical() {
+                    (b, a)
+                } else {
+                    (a, b)
+                });
+            }
+        }
+        res
+    }
+}
+
+/// Controls the size of the drawing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeConfig {
+    /// The largest size of the drawing, in points. Larger drawings are
+    /// scaled down to fit.
+    pub size: Option<Point>,
+    /// Scale up the drawings that are smaller than the size too, until they
+    /// fill it in one direction.
+    pub fill: bool,
+    pub ratio: Option<Ratio>,
+    /// The size of the pages, in points. The canvas is grown to a whole
+    /// number of pages.
+    pub page: Option<Point>,
+    /// The order of the pages, when the drawing is split into pages.
+    pub page_dir: PageDir,
+}
+
+impl SizeConfig {
+    /// \returns the factor that scales a drawing with the size \p extent to
+    /// fit in the size of the config, or 1 if there is no size.
+    pub fn get_scale(&self, extent: Point) -> f64 {
+        let size = match self.size {
+            Option::Some(size) => size,
+            None => return 1.,
+        };
+        if extent.x <= 0. || extent.y <= 0. {
+            return 1.;
+        }
+        let scale = (size.x / extent.x).min(size.y / extent.y);
+        if scale < 1. || self.fill {
+            scale
+        } else {
+            1.
+        }
+    }
+
+    /// \returns the corner of the canvas that holds a drawing with the size
+    /// \p extent, scaled by \p scale, on a whole number of pages, in the
+    /// coordinates of the layout. Returns None if there are no pages.
+    pub fn get_page_corner(&self, extent: Point, scale: f64) -> Option<Point> {
+        let page = self.page?;
+        if page.x <= 0. || page.y <= 0. {
+            return None;
+        }
+        let pages = |len: f64, page: f64| (len * scale / page).ceil().max(1.);
+        Some(Point::new(
+            pages(extent.x, page.x) * page.x / scale,
+            pages(extent.y, page.y) * page.y / scale,
+        ))
+    }
+
+    /// \returns the factors that stretch the locations of the nodes of a
+    /// layout with the size \p extent, along the x and the y axis, or None
+    /// to keep the layout. The centers of the nodes span \p spread, and the
+    /// rest of the extent is the part of the nodes outside of their centers,
+    /// that doesn't stretch.
+    fn get_stretch(&self, extent: Point, spread: Point) -> Option<Point> {
+        if extent.x <= 0. || extent.y <= 0. {
+            return None;
+        }
+        let rim = extent.sub(spread);
+        // The factor that stretches the spread \p spread until the layout is
+        // \p len long, and never shrinks it.
+        let factor = |len: f64, rim: f64, spread: f64| {
+            if spread > 0. {
+                ((len - rim) / spread).max(1.)
+            } else {
+                1.
+            }
+        };
+        let aspect = |ratio: f64| {
+            if extent.y / extent.x < ratio {
+                Point::new(1., factor(extent.x * ratio, rim.y, spread.y))
+            } else {
+                Point::new(factor(extent.y / ratio, rim.x, spread.x), 1.)
+            }
+        };
+        match (self.ratio?, self.size) {
+            (Ratio::Aspect(ratio), _) => Some(aspect(ratio)),
+            (Ratio::Fill, Option::Some(size)) => {
+                if size.x >= extent.x && size.y >= extent.y {
+                    Some(Point::new(
+                        factor(size.x, rim.x, spread.x),
+                        factor(size.y, rim.y, spread.y),
+                    ))
+                } else {
+                    // The drawing is scaled down to the size when it's drawn.
+                    Some(aspect(size.y / size.x))
+                }
+            }
+            (Ratio::Expand, Option::Some(size)) => {
+                let scale = factor(size.x, rim.x, spread.x)
+                    .min(factor(size.y, rim.y, spread.y));
+                (scale > 1.).then_some(Point::splat(scale))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// \returns the smallest factor that the distances between the centers
+/// \p centers of the boxes with the sizes \p sizes can be scaled by along
+/// the x axis, or the y axis if \p vertical is set, before two boxes that
+/// are side by side overlap.
+fn get_min_compression(
+    centers: &[Point],
+    sizes: &[Point],
+    vertical: bool,
+) -> f64 {
+    // Swap the axes, to measure along the x axis.
+    let flip = |p: Point| if vertical { Point::new(p.y, p.x) } else { p };
+    let mut res: f64 = 0.;
+    for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+            let delta = flip(centers[i].sub(centers[j]));
+            let need = flip(sizes[i].add(sizes[j]).scale(0.5));
+            // Boxes that are not side by side can pass each other.
+            if delta.y.abs() >= need.y + COMPRESS_GAP || delta.x == 0. {
+                continue;
+            }
+            res = res.max((need.x + COMPRESS_GAP) / delta.x.abs());
+        }
+    }
+    res
+}
+
+/// Move the nodes of \p vg, that is \p extent large, closer together along
+/// each axis, until the layout fits in \p size or the nodes would overlap.
+fn compress(vg: &mut VisualGraph, top_left: Point, extent: Point, size: Point) {
+    for vertical in [false, true] {
+        let (len, max) = if vertical {
+            (extent.y, size.y)
+        } else {
+            (extent.x, size.x)
+        };
+        if len <= max {
+            continue;
+        }
+        let nodes: Vec<_> = vg.iter_nodes().collect();
+        let centers: Vec<Point> =
+            nodes.iter().map(|x| vg.pos(*x).center()).collect();
+        let sizes: Vec<Point> =
+            nodes.iter().map(|x| vg.pos(*x).size(false)).collect();
+        let min = get_min_compression(&centers, &sizes, vertical);
+        let factor = (max / len).max(min);
+        if factor >= 1. {
+            continue;
+        }
+        let stretch = if vertical {
+            Point::new(1., factor)
+        } else {
+            Point::new(factor, 1.)
+        };
+        stretch_layout(vg, top_left, stretch);
+    }
+}
+
+/// Scale the locations of the nodes of \p vg around \p top_left by the
+/// factors \p stretch. The sizes of the nodes don't change.
+fn stretch_layout(vg: &mut VisualGraph, top_left: Point, stretch: Point) {
+    let nodes: Vec<_> = vg.iter_nodes().collect();
+    for node in nodes {
+        let delta = vg.pos(node).center().sub(top_left);
+        let delta = Point::new(delta.x * stretch.x, delta.y * stretch.y);
+        vg.pos_mut(node).move_to(top_left.add(delta));
+    }
+}
+
+/// Stretch or compress the layout of \p vg with the ratio of its size
+/// config, after the nodes are placed.
+pub(crate) fn fit_layout(vg: &mut VisualGraph) {
+    let config = vg.size_config();
+    let (top_left, bottom_right) = match vg.get_drawing_bbox() {
+        Option::Some(bbox) => bbox,
+        None => return,
+    };
+    let extent = bottom_right.sub(top_left);
+    if let (Option::Some(Ratio::Compress), Option::Some(size)) =
+        (config.ratio, config.size)
+    {
+        compress(vg, top_left, extent, size);
+        return;
+    }
+    let centers: Vec<Point> =
+        vg.iter_nodes().map(|x| vg.pos(x).center()).collect();
+    let first = match centers.first() {
+        Option::Some(first) => *first,
+        None => return,
+    };
+    let (low, high) = centers.iter().fold((first, first), |(a, b), p| {
+        (
+            Point::new(a.x.min(p.x), a.y.min(p.y)),
+            Point::new(b.x.max(p.x), b.y.max(p.y)),
+        )
+    });
+    let spread = high.sub(low);
+    if let Option::Some(stretch) = config.get_stretch(extent, spread) {
+        stretch_layout(vg, top_left, stretch);
+    }
+}
+
+#[test]
+fn test_size_config() {
+    assert_eq!(Ratio::from_name("0.5"), Some(Ratio::Aspect(0.5)));
+    assert_eq!(Ratio::from_name("compress"), Some(Ratio::Compress));
+    assert_eq!(Ratio::from_name("-1"), None);
+
+    // Large drawings are scaled down, and small ones only with 'fill'.
+    let mut config = SizeConfig {
+        size: Some(Point::new(100., 100.)),
+        ..SizeConfig::default()
+    };
+    assert_eq!(config.get_scale(Point::new(400., 200.)), 0.25);
+    assert_eq!(config.get_scale(Point::new(50., 20.)), 1.);
+    config.fill = true;
+    assert_eq!(config.get_scale(Point::new(50., 20.)), 2.);
+
+    // A drawing of 2.5 by 0.5 pages takes 3 by 1 pages.
+    config.page = Some(Point::new(20., 40.));
+    let corner = config.get_page_corner(Point::new(100., 40.), 0.5);
+    assert_eq!(corner, Some(Point::new(120., 80.)));
+
+    // The ratio stretches the shorter side, and the nodes keep their size.
+    config.ratio = Some(Ratio::Aspect(1.));
+    let stretch = config.get_stretch(Point::new(200., 100.), Point::splat(80.));
+    assert_eq!(stretch, Some(Point::new(1., 2.25)));
+    config.ratio = Some(Ratio::Fill);
+    let stretch = config.get_stretch(Point::new(50., 25.), Point::splat(20.));
+    assert_eq!(stretch, Some(Point::new(3.5, 4.75)));
+}
+
+#[test]
+fn test_page_dir() {
+    assert_eq!(PageDir::from_name("BL"), Some(PageDir::default()));
+    assert_eq!(PageDir::from_name("TB"), None);
+    assert_eq!(PageDir::from_name("BLT"), None);
+    assert_eq!(PageDir::from_name("RT").unwrap().to_name(), "RT");
+
+    // Left to right in the rows, and the bottom row first.
+    let order = PageDir::default().get_order(2, 2);
+    assert_eq!(order, vec![(0, 1), (1, 1), (0, 0), (1, 0)]);
+    // Top to bottom in the columns, and the right column first.
+    let order = PageDir::from_name("RT").unwrap().get_order(2, 2);
+    assert_eq!(order, vec![(1, 0), (1, 1), (0, 0), (0, 1)]);
+}
+
+#[test]
+fn test_min_compression() {
+    // Two boxes side by side, and one far below them.
+    let centers = [
+        Point::new(0., 0.),
+        Point::new(100., 0.),
+        Point::new(0., 100.),
+    ];
+    let sizes = [Point::new(16., 16.); 3];
+    assert_eq!(get_min_compression(&centers, &sizes, false), 0.2);
+    assert_eq!(get_min_compression(&centers, &sizes, true), 0.2);
+}
@@ -0,0 +1,289 @@
+//! This module contains the force-directed placer, which assigns the (x,y)
+//! coordinates of the elements with the Fruchterman-Reingold algorithm. The
+//! edges pull the nodes that they connect together, and all of the nodes push
+//! each other away, until the graph settles. This placer ignores the direction
+//! of the edges, and is a good fit for undirected graphs.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+use crate::adt::dag::NodeHandle;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::overlap::remove_node_overlaps;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// The length of the edges, between the outlines of the nodes.
+const EDGE_LENGTH: f64 = 60.;
+
+/// The strength of the force that pulls the nodes to the middle of the graph,
+/// and keeps the disconnected parts of the graph together.
+const GRAVITY: f64 = 0.3;
+
+/// The number of steps of the simulation.
+const ITERATIONS: usize = 300;
+
+/// The number of steps of the simulation between the recorded frames.
+const FRAME_INTERVAL: usize = 10;
+
+/// The space between a node and its self edges.
+const SELF_EDGE_GAP: f64 = 15.;
+
+#[derive(Debug)]
+pub struct ForcePlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> ForcePlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self, no_layout: bool) {
+        #[cfg(feature = "log")]
+        log::info!("Starting force layout of {} nodes.", self.vg.num_nodes());
+
+        let (nodes, edges) = get_undirected_graph(self.vg);
+        let radius = get_radius(self.vg, &nodes);
+
+        let mut locs = Self::initial_locations(&radius);
+        if !no_layout {
+            let mut steps = Vec::new();
+            let record = self.vg.is_recording_frames();
+            Self::simulate(&mut locs, &radius, &edges, &mut |locs| {
+                if record {
+                    steps.push(locs.to_vec());
+                }
+            });
+            for step in steps.iter() {
+                self.vg.add_frame("force", &nodes, step);
+            }
+            remove_node_overlaps(self.vg, &nodes, &mut locs, &radius);
+        }
+
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            self.vg.pos_mut(*node).move_to(*loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+
+    /// \returns the initial locations of the nodes with the radius \p radius,
+    /// on a circle that is large enough to hold all of them.
+    fn initial_locations(radius: &[f64]) -> Vec<Point> {
+        let n = radius.len();
+        let perimeter: f64 =
+            radius.iter().map(|r| 2. * r + EDGE_LENGTH).sum::<f64>();
+        let r = perimeter / (2. * PI);
+        (0..n)
+            .map(|i| {
+                let angle = 2. * PI * i as f64 / n as f64;
+                Point::new(r * angle.cos(), r * angle.sin())
+            })
+            .collect()
+    }
+
+    /// Move the nodes at \p locs with the forces of the springs \p edges and
+    /// the repulsion between the nodes, for a fixed number of steps. The
+    /// distances are measured between the circles of \p radius around the
+    /// nodes. The locations are passed to \p on_step before the first step,
+    /// and every FRAME_INTERVAL steps.
+    fn simulate(
+        locs: &mut [Point],
+        radius: &[f64],
+        edges: &[(usize, usize)],
+        on_step: &mut dyn FnMut(&[Point]),
+    ) {
+        let n = locs.len();
+        let k = EDGE_LENGTH;
+        let mut temperature = k * (n as f64).sqrt();
+        let cooling = temperature / ITERATIONS as f64;
+
+        // \returns the direction from \p j to \p i, and the distance between
+        // their circles. Nodes at the same location are pushed apart in a
+        // fixed direction.
+        let get_delta = |locs: &[Point], i: usize, j: usize| {
+            let mut delta = locs[i].sub(locs[j]);
+            if delta.length() < 0.01 {
+                let angle = (i + j) as f64;
+                delta = Point::new(angle.cos(), angle.sin());
+            }
+            let len = delta.length();
+            let dist = (len - radius[i] - radius[j]).max(1.);
+            (delta.scale(1. / len), dist)
+        };
+
+        for step in 0..ITERATIONS {
+            if step % FRAME_INTERVAL == 0 {
+                on_step(locs);
+            }
+            let mut disp = vec![Point::zero(); n];
+
+            // All of the nodes push each other away.
+            for i in 0..n {
+                for j in i + 1..n {
+                    let (dir, dist) = get_delta(locs, i, j);
+                    let force = dir.scale(k * k / dist);
+                    disp[i] = disp[i].add(force);
+                    disp[j] = disp[j].sub(force);
+                }
+            }
+
+            // The edges pull the nodes together.
+            for (i, j) in edges {
+                let (dir, dist) = get_delta(locs, *i, *j);
+                let force = dir.scale(dist * dist / k);
+                disp[*i] = disp[*i].sub(force);
+                disp[*j] = disp[*j].add(force);
+            }
+
+            // Pull the nodes to the middle of the graph.
+            let sum = locs.iter().fold(Point::zero(), |acc, loc| acc.add(*loc));
+            let middle = sum.scale(1. / n as f64);
+            for (loc, d) in locs.iter().zip(disp.iter_mut()) {
+                *d = d.add(middle.sub(*loc).scale(GRAVITY));
+            }
+
+            // Move the nodes, but not further than the temperature.
+            for (loc, d) in locs.iter_mut().zip(disp.iter()) {
+                let len = d.length();
+                if len > 0. {
+                    *loc = loc.add(d.scale(len.min(temperature) / len));
+                }
+            }
+            temperature = (temperature - cooling).max(1.);
+        }
+    }
+}
+
+/// \returns the nodes of \p vg, without the connectors, and the edges between
+/// them as pairs of indices into the list of nodes. Self edges are dropped.
+pub(crate) fn get_undirected_graph(
+    vg: &VisualGraph,
+) -> (Vec<NodeHandle>, Vec<(usize, usize)>) {
+    let nodes: Vec<NodeHandle> = vg
+        .iter_nodes()
+        .filter(|node| !vg.is_connector(*node))
+        .collect();
+    let mut index = vec![usize::MAX; vg.num_nodes()];
+    for (i, node) in nodes.iter().enumerate() {
+        index[node.get_index()] = i;
+    }
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    for (from, to, _) in vg.edge_connectors() {
+        let from = index[from.get_index()];
+        let to = index[to.get_index()];
+        if from != to && from != usize::MAX && to != usize::MAX {
+            edges.push((from, to));
+        }
+    }
+    (nodes, edges)
+}
+
+/// \returns the radius of the circle around each of the nodes \p nodes.
+pub(crate) fn get_radius(vg: &VisualGraph, nodes: &[NodeHandle]) -> Vec<f64> {
+    nodes
+        .iter()
+        .map(|node| vg.pos(*node).size(false).length() / 2.)
+        .collect()
+}
+
+/// Push apart the nodes at \p locs whose circles of \p radius overlap.
+pub(crate) fn remove_overlaps(locs: &mut [Point], radius: &[f64]) {
+    let n = locs.len();
+    for _ in 0..ITERATIONS {
+        let mut changed = false;
+        for i in 0..n {
+            for j in i + 1..n {
+                let delta = locs[i].sub(locs[j]);
+                let len = delta.length();
+                let overlap = radius[i] + radius[j] - len;
+                if overlap <= 0. || len == 0. {
+                    continue;
+                }
+                let push = delta.scale(overlap / len / 2.);
+                locs[i] = locs[i].add(push);
+                locs[j] = locs[j].sub(push);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Move the \p parts of the graph, that are lists of indices into \p locs,
+/// next to each other from left to right, with the space \p gap between them.
+/// The radius of the circle around each node is in \p radius.
+pub(crate) fn place_parts(
+    locs: &mut [Point],
+    radius: &[f64],
+    parts: &[Vec<usize>],
+    gap: f64,
+) {
+    let mut next_x = 0.;
+    for part in parts {
+        let left = part
+            .iter()
+            .map(|x| locs[*x].x - radius[*x])
+            .fold(f64::INFINITY, f64::min);
+        let top = part
+            .iter()
+            .map(|x| locs[*x].y - radius[*x])
+            .fold(f64::INFINITY, f64::min);
+        let delta = Point::new(next_x - left, -top);
+        let mut right = next_x;
+        for x in part.iter() {
+            locs[*x] = locs[*x].add(delta);
+            right = right.max(locs[*x].x + radius[*x]);
+        }
+        next_x = right + gap;
+    }
+}
+
+/// Place the connectors of the self edges to the right of their node, one
+/// after the other, so that the loops are stacked around their labels.
+pub(crate) fn place_self_edges(vg: &mut VisualGraph) {
+    let mut next: Vec<Option<f64>> = vec![None; vg.num_nodes()];
+    for (from, to, connectors) in vg.edge_connectors() {
+        if from != to {
+            continue;
+        }
+        let pos = vg.pos(from);
+        let x = next[from.get_index()].unwrap_or(pos.right(false));
+        for conn in connectors {
+            let conn_pos = vg.pos_mut(conn);
+            conn_pos.align_to_left(x + SELF_EDGE_GAP);
+            let dy = pos.center().y - conn_pos.middle().y;
+            conn_pos.translate(Point::new(0., dy));
+            next[from.get_index()] = Some(conn_pos.right(true));
+        }
+    }
+}
+
+/// Move the graph so that the top left corner of the elements, and of
+/// the clusters, is at the origin.
+pub(crate) fn move_to_origin(vg: &mut VisualGraph) {
+    let mut corner = Point::new(f64::INFINITY, f64::INFINITY);
+    for node in vg.iter_nodes() {
+        let bbox = vg.pos(node).bbox(true);
+        corner = Point::new(corner.x.min(bbox.0.x), corner.y.min(bbox.0.y));
+    }
+    for cluster in 0..vg.num_clusters() {
+        if let Option::Some(bbox) = vg.cluster_bbox(cluster, false) {
+            let (x, y) = (corner.x.min(bbox.0.x), corner.y.min(bbox.0.y));
+            corner = Point::new(x, y);
+        }
+    }
+    if !corner.x.is_finite() {
+        return;
+    }
+    for node in vg.iter_nodes() {
+        vg.pos_mut(node).translate(corner.neg());
+    }
+}
@@ -0,0 +1,135 @@
+//! This module contains the grid placer, which places the nodes in the cells
+//! of a grid, in row-major order. The placer ignores the edges, and is useful
+//! for rendering sets of nodes where the order matters more than the
+//! connections, such as legends and inventories.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::{
+    get_undirected_graph, move_to_origin, place_self_edges,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Controls the shape of the grid.
+#[derive(Debug, Clone, Copy)]
+pub struct GridConfig {
+    /// The number of columns. When this is None the grid is about as wide as
+    /// it is tall.
+    pub columns: Option<usize>,
+    /// The space between the outline of each node and its cell.
+    pub padding: f64,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            padding: 10.,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GridPlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> GridPlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self) {
+        #[cfg(feature = "log")]
+        log::info!("Starting grid layout of {} nodes.", self.vg.num_nodes());
+
+        let (nodes, _) = get_undirected_graph(self.vg);
+        let config = self.vg.grid_config();
+        let sizes: Vec<Point> = nodes
+            .iter()
+            .map(|node| self.vg.pos(*node).size(false))
+            .collect();
+
+        let locs = place_in_grid(&sizes, &config);
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            self.vg.pos_mut(*node).move_to(*loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+}
+
+/// \returns the number of columns of a grid of \p n nodes with \p config.
+fn get_num_columns(n: usize, config: &GridConfig) -> usize {
+    let columns = match config.columns {
+        Some(columns) => columns,
+        None => (n as f64).sqrt().ceil() as usize,
+    };
+    columns.max(1)
+}
+
+/// \returns the centers of the cells of the nodes with the sizes \p sizes,
+/// in row-major order. The width of each column is the width of its widest
+/// node, and the height of each row is the height of its tallest node.
+pub(crate) fn place_in_grid(
+    sizes: &[Point],
+    config: &GridConfig,
+) -> Vec<Point> {
+    let columns = get_num_columns(sizes.len(), config);
+    let rows = sizes.len().div_ceil(columns);
+    let pad = 2. * config.padding;
+
+    let mut widths = vec![0.; columns];
+    let mut heights = vec![0.; rows];
+    for (i, size) in sizes.iter().enumerate() {
+        let (row, col) = (i / columns, i % columns);
+        widths[col] = (size.x + pad).max(widths[col]);
+        heights[row] = (size.y + pad).max(heights[row]);
+    }
+
+    // \returns the middle of each cell, given the size of the cells.
+    let get_middles = |lengths: &[f64]| {
+        let mut start = 0.;
+        let mut middles = Vec::new();
+        for len in lengths {
+            middles.push(start + len / 2.);
+            start += len;
+        }
+        middles
+    };
+    let xs = get_middles(&widths);
+    let ys = get_middles(&heights);
+
+    (0..sizes.len())
+        .map(|i| Point::new(xs[i % columns], ys[i / columns]))
+        .collect()
+}
+
+#[test]
+fn test_grid_cells() {
+    let config = GridConfig::default();
+    assert_eq!(get_num_columns(0, &config), 1);
+    assert_eq!(get_num_columns(9, &config), 3);
+    assert_eq!(get_num_columns(10, &config), 4);
+
+    // Two columns, where the first node is wide and the last node is tall.
+    let config = GridConfig {
+        columns: Some(2),
+        padding: 5.,
+    };
+    let sizes = [
+        Point::new(50., 10.),
+        Point::new(10., 10.),
+        Point::new(10., 30.),
+    ];
+    let locs = place_in_grid(&sizes, &config);
+    assert_eq!(locs[0], Point::new(30., 10.));
+    assert_eq!(locs[1], Point::new(70., 10.));
+    assert_eq!(locs[2], Point::new(30., 40.));
+}
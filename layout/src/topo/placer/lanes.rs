@@ -0,0 +1,111 @@
+//! This pass keeps the nodes of each lane in a band across the ranks. Before
+//! the placement it sorts the rows by the lanes of the nodes, and after the
+//! placement it moves the lanes apart, so that the bands of the lanes don't
+//! overlap.
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// The location of the nodes across the lanes, as decided by the grouping
+/// pass. Nodes are at the index of their lane, and the connectors of an edge
+/// are spread between the lanes of the ends of the edge.
+#[derive(Debug)]
+pub(crate) struct LaneOrder {
+    keys: Vec<f64>,
+}
+
+/// Sort the rows so that the nodes of each lane are next to each other, and
+/// the lanes appear in the same order in all of the rows.
+pub(crate) fn group(vg: &mut VisualGraph) -> LaneOrder {
+    let mut order = LaneOrder {
+        keys: vec![0.; vg.num_nodes()],
+    };
+    if vg.num_lanes() == 0 {
+        return order;
+    }
+    for node in vg.iter_nodes() {
+        if let Option::Some(lane) = vg.get_node_lane(node) {
+            order.keys[node.get_index()] = lane as f64;
+        }
+    }
+    // Connectors that several edges share follow the first edge.
+    let mut done = vec![false; vg.num_nodes()];
+    for (from, to, connectors) in vg.edge_connectors() {
+        let a = order.keys[from.get_index()];
+        let b = order.keys[to.get_index()];
+        for (i, conn) in connectors.iter().enumerate() {
+            if !done[conn.get_index()] {
+                let t = (i + 1) as f64 / (connectors.len() + 1) as f64;
+                order.keys[conn.get_index()] = a + (b - a) * t;
+                done[conn.get_index()] = true;
+            }
+        }
+    }
+
+    // The sort is stable, and keeps the order of the nodes in each lane.
+    let keys = &order.keys;
+    let mut ranks = vg.dag.ranks().clone();
+    for row in ranks.iter_mut() {
+        row.sort_by(|a, b| {
+            keys[a.get_index()]
+                .partial_cmp(&keys[b.get_index()])
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+    *vg.dag.ranks_mut() = ranks;
+    order
+}
+
+/// Move the lanes apart, after the nodes were placed, so that each lane
+/// starts after the lanes before it. The connectors between two lanes move
+/// by a part of the shift of each lane, and keep their order in the rows.
+pub(crate) fn separate(vg: &mut VisualGraph, order: &LaneOrder) {
+    if vg.num_lanes() == 0 {
+        return;
+    }
+    let extents = vg.get_lane_extents(false);
+    let mut shifts = vec![0.; extents.len()];
+    let mut offset: f64 = 0.;
+    let mut end: Option<f64> = None;
+    for (i, extent) in extents.iter().enumerate() {
+        if let Option::Some((lo, hi)) = extent {
+            if let Option::Some(end) = end {
+                offset = offset.max(end - lo);
+            }
+            end = Some(end.unwrap_or(f64::NEG_INFINITY).max(hi + offset));
+        }
+        shifts[i] = offset;
+    }
+
+    let last = shifts.len() - 1;
+    let nodes: Vec<_> = vg.iter_nodes().collect();
+    for node in nodes {
+        let key = order.keys[node.get_index()];
+        let i = (key.floor().max(0.) as usize).min(last);
+        let t = key - i as f64;
+        let dx = shifts[i] + (shifts[(i + 1).min(last)] - shifts[i]) * t;
+        vg.pos_mut(node).translate(Point::new(dx, 0.));
+    }
+
+    // The lanes hold all of the nodes, and the headers may stick out of the
+    // top of the graph. Move the lanes to the top left corner.
+    let boxes = vg.get_lane_boxes(false);
+    let mut corner: Option<Point> = None;
+    for (bbox, _) in boxes.iter().flatten() {
+        corner = Some(match corner {
+            Option::Some(c) => Point::new(c.x.min(bbox.0.x), c.y.min(bbox.0.y)),
+            None => bbox.0,
+        });
+    }
+    if let Option::Some(corner) = corner {
+        let nodes: Vec<_> = vg.iter_nodes().collect();
+        for node in nodes {
+            vg.pos_mut(node).translate(corner.neg());
+        }
+    }
+}
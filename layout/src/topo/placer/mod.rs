@@ -29,10 +29,30 @@ impl BlockKind {
 }
 
 mod bk;
+pub mod circular;
+mod clusters;
 mod edge_fixer;
+pub mod fit;
+pub mod force;
+pub mod grid;
+mod lanes;
 mod move_between_rows;
+pub mod overlap;
+pub mod pack;
 mod simple;
+pub mod stress;
+pub mod timeline;
+pub mod tree;
 mod verifier;
 
 pub mod place;
-pub use place::Placer;
+pub use circular::CircularPlacer;
+pub use fit::{PageDir, Ratio, SizeConfig};
+pub use force::ForcePlacer;
+pub use grid::{GridConfig, GridPlacer};
+pub use overlap::{OverlapConfig, OverlapMode};
+pub use pack::{PackConfig, PackMode};
+pub use place::{Placer, SpacingConfig};
+pub use stress::{StressConfig, StressPlacer};
+pub use timeline::{TimeAxis, TimelineConfig, TimelinePlacer};
+pub use tree::{RadialPlacer, TreePlacer};
@@ -0,0 +1,261 @@
+//! This module contains the removal of node overlaps, which runs after the
+//! force-directed and the stress placers, like the 'overlap' attribute of
+//! Graphviz. The placers keep the circles around the nodes apart by default,
+//! and the passes here work on the boxes of the nodes, so that nodes with
+//! wide labels are only pushed as far as they need to be.
+
+use crate::adt::dag::NodeHandle;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::remove_overlaps;
+use crate::topo::placer::EPSILON;
+use alloc::vec::Vec;
+
+/// The number of sweeps of the push-apart pass, before the layout is scaled
+/// to remove the overlaps that are left.
+const MAX_PASSES: usize = 100;
+
+/// Selects how the overlaps between the nodes are removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlapMode {
+    /// Push apart the circles around the nodes.
+    #[default]
+    Circles,
+    /// Keep the nodes where the placer put them, even if they overlap.
+    Keep,
+    /// Push apart the boxes of the nodes that overlap, along the axis that
+    /// moves them the least.
+    Remove,
+    /// Scale the whole layout, until the boxes of the nodes don't overlap.
+    Scale,
+}
+
+impl OverlapMode {
+    /// \returns the mode of the Graphviz 'overlap' value \p name, such as
+    /// "false" or "scale". The Graphviz removal algorithms, such as "prism"
+    /// and "voronoi", push the boxes apart.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "true" | "yes" | "1" => Some(OverlapMode::Keep),
+            "false" | "no" | "0" | "voronoi" | "compress" | "vpsc"
+            | "ortho" => Some(OverlapMode::Remove),
+            "scale" | "scalexy" => Some(OverlapMode::Scale),
+            _ => {
+                // The prism algorithm may have a number of attempts.
+                let rest = name.strip_prefix("prism")?;
+                if rest.chars().all(|c| c.is_ascii_digit()) {
+                    Some(OverlapMode::Remove)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Controls the removal of the overlaps between the nodes.
+#[derive(Debug, Clone, Copy)]
+pub struct OverlapConfig {
+    pub mode: OverlapMode,
+    /// The space between the boxes of the nodes, after they are pushed
+    /// apart or scaled.
+    pub gap: f64,
+}
+
+impl Default for OverlapConfig {
+    fn default() -> Self {
+        Self {
+            mode: OverlapMode::Circles,
+            gap: 4.,
+        }
+    }
+}
+
+/// \returns the half sizes of the boxes with the sizes \p sizes, grown by
+/// half of the gap \p gap.
+fn get_half_sizes(sizes: &[Point], gap: f64) -> Vec<Point> {
+    sizes
+        .iter()
+        .map(|size| size.add(Point::splat(gap)).scale(0.5))
+        .collect()
+}
+
+/// Push apart the boxes with the centers \p centers and the sizes \p sizes,
+/// until there is the space \p gap between them. The boxes are swept from
+/// left to right, and each pair of boxes that overlap is moved apart along
+/// the axis where the overlap is smaller. The layout is scaled if some of the
+/// boxes still overlap after MAX_PASSES sweeps.
+pub fn remove_box_overlaps(centers: &mut [Point], sizes: &[Point], gap: f64) {
+    let n = centers.len();
+    let half = get_half_sizes(sizes, gap);
+    let mut order: Vec<usize> = (0..n).collect();
+    for _ in 0..MAX_PASSES {
+        let left = |i: usize| centers[i].x - half[i].x;
+        order.sort_by(|a, b| left(*a).total_cmp(&left(*b)));
+
+        // The boxes that the sweep line crosses.
+        let mut active: Vec<usize> = Vec::new();
+        let mut changed = false;
+        for i in order.iter().copied() {
+            let x = centers[i].x - half[i].x;
+            active.retain(|j| centers[*j].x + half[*j].x > x);
+            for j in active.iter().copied() {
+                let delta = centers[i].sub(centers[j]);
+                let need = half[i].add(half[j]);
+                let dx = need.x - delta.x.abs();
+                let dy = need.y - delta.y.abs();
+                if dx <= EPSILON || dy <= EPSILON {
+                    continue;
+                }
+                // Boxes at the same location are pushed in a fixed direction.
+                let push = if dx <= dy {
+                    let dir = if delta.x < 0. { -1. } else { 1. };
+                    Point::new(dir * (dx / 2. + EPSILON), 0.)
+                } else {
+                    let dir = if delta.y < 0. { -1. } else { 1. };
+                    Point::new(0., dir * (dy / 2. + EPSILON))
+                };
+                centers[i] = centers[i].add(push);
+                centers[j] = centers[j].sub(push);
+                changed = true;
+            }
+            active.push(i);
+        }
+        if !changed {
+            return;
+        }
+    }
+    scale_apart(centers, sizes, gap);
+}
+
+/// Scale the layout of the boxes with the centers \p centers and the sizes
+/// \p sizes around its middle, by the smallest factor that leaves the space
+/// \p gap between all of the boxes. The shape of the layout doesn't change,
+/// but it can grow a lot when two boxes are very close.
+pub fn scale_apart(centers: &mut [Point], sizes: &[Point], gap: f64) {
+    let n = centers.len();
+    if n < 2 {
+        return;
+    }
+    let half = get_half_sizes(sizes, gap);
+
+    // Boxes at the same location can't be scaled apart, so move them side
+    // by side first.
+    for i in 0..n {
+        for j in i + 1..n {
+            if centers[i].distance_to(centers[j]) < EPSILON {
+                let dx = half[i].x + half[j].x;
+                centers[j] = centers[j].add(Point::new(dx, 0.));
+            }
+        }
+    }
+
+    // Each pair of boxes is apart when one of the axes is scaled enough.
+    let mut factor: f64 = 1.;
+    for i in 0..n {
+        for j in i + 1..n {
+            let delta = centers[i].sub(centers[j]);
+            let need = half[i].add(half[j]);
+            let axis = |d: f64, need: f64| {
+                if d.abs() < EPSILON {
+                    f64::INFINITY
+                } else {
+                    need / d.abs()
+                }
+            };
+            let pair = axis(delta.x, need.x).min(axis(delta.y, need.y));
+            factor = factor.max(pair);
+        }
+    }
+    if factor <= 1. {
+        return;
+    }
+
+    let sum = centers.iter().fold(Point::zero(), |acc, p| acc.add(*p));
+    let middle = sum.scale(1. / n as f64);
+    for center in centers.iter_mut() {
+        *center = middle.add(center.sub(middle).scale(factor));
+    }
+}
+
+/// Remove the overlaps between the nodes \p nodes of \p vg, that are at
+/// \p locs, with the overlap mode of the graph. The radius of the circle
+/// around each node is in \p radius.
+pub(crate) fn remove_node_overlaps(
+    vg: &VisualGraph,
+    nodes: &[NodeHandle],
+    locs: &mut [Point],
+    radius: &[f64],
+) {
+    let config = vg.overlap_config();
+    let sizes = || -> Vec<Point> {
+        nodes.iter().map(|node| vg.pos(*node).size(false)).collect()
+    };
+    match config.mode {
+        OverlapMode::Circles => remove_overlaps(locs, radius),
+        OverlapMode::Keep => {}
+        OverlapMode::Remove => {
+            remove_box_overlaps(locs, &sizes(), config.gap);
+        }
+        OverlapMode::Scale => scale_apart(locs, &sizes(), config.gap),
+    }
+}
+
+#[cfg(test)]
+fn has_box_overlaps(centers: &[Point], sizes: &[Point]) -> bool {
+    for i in 0..centers.len() {
+        for j in i + 1..centers.len() {
+            let delta = centers[i].sub(centers[j]);
+            let need = sizes[i].add(sizes[j]).scale(0.5);
+            if delta.x.abs() < need.x && delta.y.abs() < need.y {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[test]
+fn test_remove_box_overlaps() {
+    // Wide boxes on top of each other, and two boxes at the same location.
+    let sizes = [
+        Point::new(100., 20.),
+        Point::new(100., 20.),
+        Point::new(30., 30.),
+        Point::new(30., 30.),
+    ];
+    let mut centers = [
+        Point::new(0., 0.),
+        Point::new(10., 5.),
+        Point::new(200., 0.),
+        Point::new(200., 0.),
+    ];
+    remove_box_overlaps(&mut centers, &sizes, 4.);
+    assert!(!has_box_overlaps(&centers, &sizes));
+    // The wide boxes move apart vertically, that is the shorter way.
+    assert_eq!(centers[0].x, 0.);
+    assert!(centers[1].y - centers[0].y >= 24.);
+
+    assert_eq!(OverlapMode::from_name("false"), Some(OverlapMode::Remove));
+    assert_eq!(
+        OverlapMode::from_name("prism1000"),
+        Some(OverlapMode::Remove)
+    );
+    assert_eq!(OverlapMode::from_name("true"), Some(OverlapMode::Keep));
+    assert_eq!(OverlapMode::from_name("scalexy"), Some(OverlapMode::Scale));
+    assert_eq!(OverlapMode::from_name("prism_x"), None);
+}
+
+#[test]
+fn test_scale_apart() {
+    let sizes = [Point::new(20., 20.); 3];
+    let mut centers =
+        [Point::new(0., 0.), Point::new(10., 0.), Point::new(0., 40.)];
+    scale_apart(&mut centers, &sizes, 0.);
+    assert!(!has_box_overlaps(&centers, &sizes));
+    // The layout keeps its shape.
+    let a = centers[1].sub(centers[0]);
+    let b = centers[2].sub(centers[0]);
+    assert!((a.x - 20.).abs() < 1e-9 && a.y.abs() < 1e-9);
+    assert!((b.y - 80.).abs() < 1e-9);
+}
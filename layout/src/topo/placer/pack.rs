@@ -0,0 +1,150 @@
+//! This module contains the packer, which arranges the bounding boxes of the
+//! connected components of a graph after they are laid out separately, like
+//! the 'pack' and 'packmode' attributes of Graphviz.
+
+use crate::core::geometry::Point;
+use crate::topo::placer::grid::{place_in_grid, GridConfig};
+use crate::topo::placer::EPSILON;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Selects how the components of the graph are arranged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PackMode {
+    /// Place the components in rows, from the tallest to the shortest, so
+    /// that the drawing is about as wide as it is tall.
+    #[default]
+    Graph,
+    /// Place the components in the cells of a grid, in the order of their
+    /// first nodes. When the number of columns is None the grid is about as
+    /// wide as it is tall.
+    Array(Option<usize>),
+}
+
+/// Controls the packing of the components of the graph.
+#[derive(Debug, Clone, Copy)]
+pub struct PackConfig {
+    /// The space between the components.
+    pub margin: f64,
+    pub mode: PackMode,
+}
+
+impl Default for PackConfig {
+    fn default() -> Self {
+        Self {
+            margin: 8.,
+            mode: PackMode::Graph,
+        }
+    }
+}
+
+/// \returns the top-left corner of each of the boxes with the sizes \p sizes,
+/// packed with \p config.
+pub fn pack_boxes(sizes: &[Point], config: &PackConfig) -> Vec<Point> {
+    match config.mode {
+        PackMode::Graph => pack_in_rows(sizes, config.margin),
+        PackMode::Array(columns) => {
+            let grid = GridConfig {
+                columns,
+                padding: config.margin / 2.,
+            };
+            let centers = place_in_grid(sizes, &grid);
+            centers
+                .iter()
+                .zip(sizes.iter())
+                .map(|(center, size)| center.sub(size.scale(0.5)))
+                .collect()
+        }
+    }
+}
+
+/// \returns the top-left corners of the boxes with the sizes \p sizes, placed
+/// in rows with the space \p margin between them. The tallest boxes are placed
+/// first, and the width of the rows is selected to make the drawing about as
+/// wide as it is tall.
+fn pack_in_rows(sizes: &[Point], margin: f64) -> Vec<Point> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| sizes[*b].y.total_cmp(&sizes[*a].y));
+
+    // Try the widths of the first rows that hold 1, 2, 3... boxes.
+    let mut best: Option<(f64, Vec<Point>)> = None;
+    let mut width = 0.;
+    for i in order.iter() {
+        width += sizes[*i].x + margin;
+        let (locs, size) = fill_rows(sizes, &order, margin, width);
+        let score = size.x.max(size.y);
+        if best.as_ref().is_none_or(|b| score < b.0) {
+            best = Some((score, locs));
+        }
+    }
+    best.map(|b| b.1).unwrap_or_default()
+}
+
+/// Place the boxes with the sizes \p sizes in the order \p order, in rows that
+/// are at most \p width wide. \returns the top-left corner of each box, and
+/// the size of the drawing.
+fn fill_rows(
+    sizes: &[Point],
+    order: &[usize],
+    margin: f64,
+    width: f64,
+) -> (Vec<Point>, Point) {
+    let mut res = vec![Point::zero(); sizes.len()];
+    let mut x = 0.;
+    let mut y = 0.;
+    let mut row_height: f64 = 0.;
+    let mut max_x: f64 = 0.;
+    for i in order.iter() {
+        let size = sizes[*i];
+        if x > 0. && x + size.x + margin > width + EPSILON {
+            x = 0.;
+            y += row_height;
+            row_height = 0.;
+        }
+        res[*i] = Point::new(x, y);
+        x += size.x + margin;
+        max_x = max_x.max(x);
+        row_height = row_height.max(size.y + margin);
+    }
+    (res, Point::new(max_x, y + row_height))
+}
+
+#[test]
+fn test_pack_boxes() {
+    let sizes = [
+        Point::new(100., 10.),
+        Point::new(10., 50.),
+        Point::new(30., 30.),
+        Point::new(20., 20.),
+    ];
+    let overlap = |locs: &[Point]| {
+        for i in 0..sizes.len() {
+            for j in i + 1..sizes.len() {
+                let (a0, a1) = (locs[i], locs[i].add(sizes[i]));
+                let (b0, b1) = (locs[j], locs[j].add(sizes[j]));
+                if a0.x < b1.x && b0.x < a1.x && a0.y < b1.y && b0.y < a1.y {
+                    return true;
+                }
+            }
+        }
+        false
+    };
+
+    // The tallest box comes first, and the widest box starts a new row.
+    let config = PackConfig::default();
+    let locs = pack_boxes(&sizes, &config);
+    assert!(!overlap(&locs));
+    assert_eq!(locs[1], Point::zero());
+    assert_eq!(locs[0].x, 0.);
+    assert!(locs[0].y >= 50.);
+
+    // Two columns, in the order of the boxes.
+    let config = PackConfig {
+        margin: 10.,
+        mode: PackMode::Array(Some(2)),
+    };
+    let locs = pack_boxes(&sizes, &config);
+    assert!(!overlap(&locs));
+    assert!(locs[0].x < locs[1].x && locs[2].x < locs[3].x);
+    assert!(locs[2].y >= locs[1].y + sizes[1].y);
+}
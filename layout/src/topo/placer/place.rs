@@ -4,12 +4,31 @@
 #[cfg(feature = "log")]
 extern crate log;
 
+use crate::core::format::Visible;
+use crate::core::geometry::Point;
 use crate::topo::layout::VisualGraph;
 use crate::topo::placer::bk::BK;
+use crate::topo::placer::clusters;
 use crate::topo::placer::edge_fixer;
+use crate::topo::placer::lanes;
 use crate::topo::placer::move_between_rows;
 use crate::topo::placer::simple;
 use crate::topo::placer::verifier;
+use alloc::vec::Vec;
+
+/// Controls the space between the nodes of the hierarchical layout.
+#[derive(Debug, Clone, Default)]
+pub struct SpacingConfig {
+    /// The space between neighboring nodes in a rank. When this is None the
+    /// nodes are spaced by their halo.
+    pub node_gap: Option<f64>,
+    /// The space between consecutive ranks. The last gap is used for the rest
+    /// of the ranks. When the list is empty the ranks are spaced by the halo
+    /// of their nodes.
+    pub rank_gaps: Vec<f64>,
+    /// Place the centers of the ranks equally apart.
+    pub equal_ranks: bool,
+}
 
 #[derive(Debug)]
 pub struct Placer<'a> {
@@ -36,8 +55,27 @@ impl<'a> Placer<'a> {
             log::info!("Placing nodes in Top-to-Bottom mode.");
         }
 
+        // Set the space between the nodes in each rank.
+        if let Option::Some(gap) = self.vg.spacing_config().node_gap {
+            let nodes: Vec<_> = self.vg.iter_nodes().collect();
+            for node in nodes {
+                if self.vg.element(node).is_connector() {
+                    continue;
+                }
+                let halo = self.vg.pos(node).halo();
+                self.vg.pos_mut(node).set_halo(Point::new(gap, halo.y));
+            }
+        }
+
         move_between_rows::do_it(self.vg);
 
+        // Place the members of each cluster next to each other in the rows.
+        let cluster_order = clusters::group(self.vg);
+
+        // Place the nodes of each lane next to each other, in the order of
+        // the lanes.
+        let lane_order = lanes::group(self.vg);
+
         // Adjust the boxes within the line (along y) and assign consecutive X
         // coordinates.
         simple::do_it(self.vg);
@@ -49,6 +87,8 @@ impl<'a> Placer<'a> {
         if no_layout {
             #[cfg(feature = "log")]
             log::info!("Skipping the layout phase.");
+            clusters::separate(self.vg, &cluster_order, need_transpose);
+            lanes::separate(self.vg, &lane_order);
             // Finalize left-to-right graphs.
             if need_transpose {
                 self.vg.transpose();
@@ -62,6 +102,14 @@ impl<'a> Placer<'a> {
 
         edge_fixer::do_it(self.vg);
 
+        // Move the clusters away from the nodes that are not in them.
+        clusters::separate(self.vg, &cluster_order, need_transpose);
+
+        // Move the lanes apart, so that their bands don't overlap.
+        lanes::separate(self.vg, &lane_order);
+
+        verifier::do_it(self.vg);
+
         // Finalize left-to-right graphs.
         if need_transpose {
             self.vg.transpose();
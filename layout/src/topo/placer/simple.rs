@@ -4,6 +4,7 @@
 use super::EPSILON;
 use crate::core::geometry::Point;
 use crate::topo::layout::VisualGraph;
+use alloc::vec::Vec;
 
 /// Move the whole graph all the way to the left.
 pub(crate) fn align_to_left(vg: &mut VisualGraph) {
@@ -23,25 +24,44 @@ pub(crate) fn align_to_left(vg: &mut VisualGraph) {
 
 /// Assign the initial Y coordinates.
 fn assign_y_coordinates(vg: &mut VisualGraph) {
-    let mut lowest_point = 0.;
-    for i in 0..vg.dag.num_levels() {
-        let current_row = vg.dag.row(i);
+    let config = vg.spacing_config();
+    // The ranks are spaced by the halo of their boxes, unless the gaps
+    // between the ranks are set.
+    let with_halo = config.rank_gaps.is_empty();
+    let get_gap = |i: usize| match config.rank_gaps.get(i) {
+        Option::Some(gap) => *gap,
+        None => config.rank_gaps.last().cloned().unwrap_or(0.),
+    };
 
-        // Find the tallest box in the row.
+    // Find the tallest box in each row.
+    let num_levels = vg.dag.num_levels();
+    let mut heights = Vec::new();
+    for i in 0..num_levels {
         let mut max_height: f64 = 0.;
-        for idx in current_row.iter() {
-            let height = vg.pos(*idx).size(true).y;
+        for idx in vg.dag.row(i).iter() {
+            let height = vg.pos(*idx).size(with_halo).y;
             max_height = max_height.max(height);
         }
+        heights.push(max_height);
+    }
+    let tallest = heights.iter().fold(0., |a: f64, b| a.max(*b));
+
+    let mut lowest_point = get_gap(0) / 2.;
+    for (i, max_height) in heights.iter().enumerate() {
+        let max_height = if config.equal_ranks {
+            tallest
+        } else {
+            *max_height
+        };
 
         // Align all of the boxes.
         let new_center = lowest_point + max_height / 2.;
-        for idx in current_row.clone().iter() {
+        for idx in vg.dag.row(i).clone().iter() {
             let height = vg.pos(*idx).size(true).y;
             vg.pos_mut(*idx).align_to_top(new_center - height / 2.);
         }
 
-        lowest_point += max_height;
+        lowest_point += max_height + get_gap(i);
     }
 }
 
@@ -0,0 +1,322 @@
+//! This module contains the stress placer, which assigns the (x,y) coordinates
+//! of the elements in the style of 'neato'. The ideal distance between every
+//! pair of nodes is the length of the shortest path between them, and the
+//! placer looks for the locations that minimize the stress, which is the
+//! weighted sum of the squared differences between the actual and the ideal
+//! distances. The nodes start at the locations of the classical
+//! multidimensional scaling (MDS) of the distances, and the stress is reduced
+//! with stress majorization. This gives a better global structure than the
+//! spring simulation of the force-directed placer.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::{
+    get_radius, get_undirected_graph, move_to_origin, place_parts,
+    place_self_edges,
+};
+use crate::topo::placer::overlap::remove_node_overlaps;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The length of the edges, between the outlines of the nodes.
+const EDGE_LENGTH: f64 = 60.;
+
+/// The space between the parts of the graph that are not connected.
+const NODE_GAP: f64 = 30.;
+
+/// The number of steps of the power iteration in the initial placement.
+const MDS_ITERATIONS: usize = 100;
+
+/// Controls the stress majorization.
+#[derive(Debug, Clone, Copy)]
+pub struct StressConfig {
+    /// The maximum number of steps of the majorization.
+    pub max_iterations: usize,
+    /// Stop when an iteration reduces the stress by less than this fraction.
+    pub epsilon: f64,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: 200,
+            epsilon: 0.0001,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StressPlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> StressPlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self, no_layout: bool) {
+        #[cfg(feature = "log")]
+        log::info!("Starting stress layout of {} nodes.", self.vg.num_nodes());
+
+        let (nodes, edges) = get_undirected_graph(self.vg);
+        let radius = get_radius(self.vg, &nodes);
+        let config = self.vg.stress_config();
+
+        let dist = get_distances(&radius, &edges);
+        let parts = get_components(&dist);
+        let mut locs = vec![Point::zero(); nodes.len()];
+        let mut steps = Vec::new();
+        let record = self.vg.is_recording_frames();
+        for part in parts.iter() {
+            place_with_mds(&mut locs, part, &dist);
+            if !no_layout {
+                majorize(&mut locs, part, &dist, &config, &mut |locs| {
+                    if record {
+                        steps.push(locs.to_vec());
+                    }
+                });
+            }
+        }
+        for step in steps.iter() {
+            self.vg.add_frame("stress", &nodes, step);
+        }
+        if !no_layout {
+            remove_node_overlaps(self.vg, &nodes, &mut locs, &radius);
+        }
+        place_parts(&mut locs, &radius, &parts, NODE_GAP);
+
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            self.vg.pos_mut(*node).move_to(*loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+}
+
+/// \returns the length of the shortest path between every pair of nodes, where
+/// the radius of each node is in \p radius and \p edges are the edges between
+/// them. The length of each edge is the distance between the centers of the
+/// nodes when the outlines are EDGE_LENGTH apart. Nodes that are not connected
+/// are at an infinite distance.
+fn get_distances(radius: &[f64], edges: &[(usize, usize)]) -> Vec<Vec<f64>> {
+    let n = radius.len();
+    let mut dist = vec![vec![f64::INFINITY; n]; n];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[i] = 0.;
+    }
+    for (a, b) in edges {
+        let len = radius[*a] + radius[*b] + EDGE_LENGTH;
+        dist[*a][*b] = dist[*a][*b].min(len);
+        dist[*b][*a] = dist[*b][*a].min(len);
+    }
+
+    // Floyd-Warshall.
+    for k in 0..n {
+        let row_k = dist[k].clone();
+        for row in dist.iter_mut() {
+            let dik = row[k];
+            if !dik.is_finite() {
+                continue;
+            }
+            for (dij, dkj) in row.iter_mut().zip(row_k.iter()) {
+                *dij = dij.min(dik + dkj);
+            }
+        }
+    }
+    dist
+}
+
+/// \returns the connected parts of the graph with the distances \p dist.
+fn get_components(dist: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = dist.len();
+    let mut placed = vec![false; n];
+    let mut parts = Vec::new();
+    for i in 0..n {
+        if placed[i] {
+            continue;
+        }
+        let part: Vec<usize> =
+            (i..n).filter(|j| dist[i][*j].is_finite()).collect();
+        for j in part.iter() {
+            placed[*j] = true;
+        }
+        parts.push(part);
+    }
+    parts
+}
+
+/// Place the nodes \p part at \p locs with classical multidimensional scaling
+/// of the distances \p dist. The coordinates are the two main eigenvectors of
+/// the double-centered matrix of the squared distances.
+fn place_with_mds(locs: &mut [Point], part: &[usize], dist: &[Vec<f64>]) {
+    let m = part.len();
+    if m < 2 {
+        return;
+    }
+
+    // Double-center the squared distances.
+    let sq = |i: usize, j: usize| dist[part[i]][part[j]].powi(2);
+    let row_mean: Vec<f64> = (0..m)
+        .map(|i| (0..m).map(|j| sq(i, j)).sum::<f64>() / m as f64)
+        .collect();
+    let mean = row_mean.iter().sum::<f64>() / m as f64;
+    let b: Vec<Vec<f64>> = (0..m)
+        .map(|i| {
+            (0..m)
+                .map(|j| -0.5 * (sq(i, j) - row_mean[i] - row_mean[j] + mean))
+                .collect()
+        })
+        .collect();
+
+    // Find the two main eigenvectors with power iteration. The second vector
+    // is kept orthogonal to the first. The initial vectors are fixed, to make
+    // the layout deterministic.
+    let mut axes: Vec<(Vec<f64>, f64)> = Vec::new();
+    for axis in 0..2 {
+        let mut v: Vec<f64> = (0..m)
+            .map(|i| ((i * (axis + 1)) as f64 + 1.).sin())
+            .collect();
+        let mut eigenvalue = 0.;
+        for _ in 0..MDS_ITERATIONS {
+            for (prev, _) in axes.iter() {
+                let dot: f64 = v.iter().zip(prev).map(|(a, b)| a * b).sum();
+                for (x, p) in v.iter_mut().zip(prev) {
+                    *x -= dot * p;
+                }
+            }
+            let mut next: Vec<f64> = b
+                .iter()
+                .map(|row| row.iter().zip(v.iter()).map(|(a, b)| a * b).sum())
+                .collect();
+            let norm = next.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm < 1e-9 {
+                break;
+            }
+            for x in next.iter_mut() {
+                *x /= norm;
+            }
+            eigenvalue = norm;
+            v = next;
+        }
+        axes.push((v, eigenvalue));
+    }
+
+    for (i, node) in part.iter().enumerate() {
+        let coord = |axis: usize| {
+            let (v, eigenvalue) = &axes[axis];
+            v[i] * eigenvalue.max(0.).sqrt()
+        };
+        locs[*node] = Point::new(coord(0), coord(1));
+    }
+}
+
+/// \returns the stress of the nodes \p part at \p locs, where the ideal
+/// distances are \p dist.
+fn get_stress(locs: &[Point], part: &[usize], dist: &[Vec<f64>]) -> f64 {
+    let mut stress = 0.;
+    for (idx, i) in part.iter().enumerate() {
+        for j in part[idx + 1..].iter() {
+            let d = dist[*i][*j];
+            let diff = locs[*i].distance_to(locs[*j]) - d;
+            stress += diff * diff / (d * d);
+        }
+    }
+    stress
+}
+
+/// Reduce the stress of the nodes \p part at \p locs, where the ideal
+/// distances are \p dist, with stress majorization. Each node is moved to the
+/// weighted average of the locations that the other nodes want it to be at.
+/// The weight of each pair of nodes is the inverse of the squared distance,
+/// which makes the distances between nearby nodes more important. The
+/// locations are passed to \p on_step before each iteration.
+fn majorize(
+    locs: &mut [Point],
+    part: &[usize],
+    dist: &[Vec<f64>],
+    config: &StressConfig,
+    on_step: &mut dyn FnMut(&[Point]),
+) {
+    let mut stress = get_stress(locs, part, dist);
+    for _ in 0..config.max_iterations {
+        on_step(locs);
+        for i in part.iter() {
+            let mut sum = Point::zero();
+            let mut total_weight = 0.;
+            for j in part.iter() {
+                if i == j {
+                    continue;
+                }
+                let d = dist[*i][*j];
+                let weight = 1. / (d * d);
+
+                // Nodes at the same location are pushed apart in a fixed
+                // direction.
+                let mut delta = locs[*i].sub(locs[*j]);
+                if delta.length() < 0.01 {
+                    let angle = (i + j) as f64;
+                    delta = Point::new(angle.cos(), angle.sin());
+                }
+                let target = locs[*j].add(delta.scale(d / delta.length()));
+                sum = sum.add(target.scale(weight));
+                total_weight += weight;
+            }
+            if total_weight > 0. {
+                locs[*i] = sum.scale(1. / total_weight);
+            }
+        }
+
+        let next = get_stress(locs, part, dist);
+        let done = stress <= 0. || (stress - next) / stress < config.epsilon;
+        stress = next;
+        if done {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_stress_majorization() {
+    // A path of three nodes, and a node that is not connected.
+    let radius = vec![10.; 4];
+    let dist = get_distances(&radius, &[(0, 1), (1, 2)]);
+    let len = 20. + EDGE_LENGTH;
+    assert_eq!(dist[0][2], 2. * len);
+    assert!(!dist[0][3].is_finite());
+
+    let parts = get_components(&dist);
+    assert_eq!(parts, vec![vec![0, 1, 2], vec![3]]);
+
+    // The scaling of a path is exact.
+    let mut locs = vec![Point::zero(); 4];
+    place_with_mds(&mut locs, &parts[0], &dist);
+    assert!((locs[0].distance_to(locs[1]) - len).abs() < 0.01);
+    assert!((locs[0].distance_to(locs[2]) - 2. * len).abs() < 0.01);
+
+    // A cycle of four nodes is placed on a square.
+    let dist = get_distances(&radius, &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+    let mut locs = vec![Point::zero(); 4];
+    let part = vec![0, 1, 2, 3];
+    place_with_mds(&mut locs, &part, &dist);
+    majorize(
+        &mut locs,
+        &part,
+        &dist,
+        &StressConfig::default(),
+        &mut |_| {},
+    );
+    let side = locs[0].distance_to(locs[1]);
+    for i in 1..4 {
+        let next = (i + 1) % 4;
+        assert!((locs[i].distance_to(locs[next]) - side).abs() < 1.);
+    }
+    let diagonal = locs[0].distance_to(locs[2]);
+    assert!((locs[1].distance_to(locs[3]) - diagonal).abs() < 1.);
+    assert!(side > len * 0.9 && diagonal < 2. * len);
+}
@@ -0,0 +1,287 @@
+//! This module contains the timeline placer, which places the nodes on a
+//! horizontal time axis. Each node spans the interval between its start and
+//! its end, and the nodes are stacked in rows, so that the nodes in a row
+//! don't overlap. The placer ignores the edges, and is useful for rendering
+//! traces and schedules.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::core::utils::format_number;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::{
+    get_undirected_graph, move_to_origin, place_self_edges,
+};
+use crate::topo::placer::EPSILON;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The width of the axis when the scale is selected automatically, unless
+/// the labels need more room.
+const MAX_AUTO_WIDTH: f64 = 1500.;
+
+/// The width of one unit of time when no node has a duration.
+const DEFAULT_SCALE: f64 = 10.;
+
+/// The minimal distance between the ticks of the axis.
+const MIN_TICK_SPACING: f64 = 60.;
+
+/// The length of the ticks of the axis, that is shorter than half of the
+/// height of the labels of the ticks.
+pub(crate) const TICK_SIZE: f64 = 4.;
+
+/// Controls the scale of the time axis and the space between the rows.
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineConfig {
+    /// The width of one unit of time. When this is None the scale is chosen
+    /// so that the labels of the nodes fit in their bars.
+    pub scale: Option<f64>,
+    /// The space between the rows, and between the nodes without an
+    /// interval.
+    pub gap: f64,
+}
+
+impl Default for TimelineConfig {
+    fn default() -> Self {
+        Self {
+            scale: None,
+            gap: 10.,
+        }
+    }
+}
+
+/// The time axis of a timeline after layout, with the gridlines at its
+/// ticks.
+#[derive(Debug, Clone)]
+pub struct TimeAxis {
+    /// The x coordinates of the start and of the end of the axis.
+    pub left: f64,
+    pub right: f64,
+    /// The y coordinate of the axis, one line of text above the nodes, and
+    /// of the middle of the labels of the ticks, one line above the axis.
+    pub y: f64,
+    pub label_y: f64,
+    /// The y coordinate of the bottom of the gridlines.
+    pub bottom: f64,
+    /// The x coordinate and the label of each tick.
+    pub ticks: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct TimelinePlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> TimelinePlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self) {
+        #[cfg(feature = "log")]
+        log::info!(
+            "Starting timeline layout of {} nodes.",
+            self.vg.num_nodes()
+        );
+
+        let (nodes, _) = get_undirected_graph(self.vg);
+        let config = self.vg.timeline_config();
+        let spans: Vec<Option<(f64, f64)>> =
+            nodes.iter().map(|node| self.vg.node_span(*node)).collect();
+        let sizes: Vec<Point> = nodes
+            .iter()
+            .map(|node| self.vg.pos(*node).size(false))
+            .collect();
+
+        let scale = match config.scale {
+            Some(scale) => scale.max(EPSILON),
+            None => get_auto_scale(&spans, &sizes),
+        };
+        let boxes = place_on_timeline(&spans, &sizes, scale, config.gap);
+        for (node, (loc, size)) in nodes.iter().zip(boxes.iter()) {
+            let pos = self.vg.pos_mut(*node);
+            pos.set_size(*size);
+            pos.move_to(*loc);
+        }
+        self.vg.set_time_scale(Some(scale));
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+
+        // Make room for the labels of the axis above the nodes.
+        if let Option::Some((top_left, _)) = self.vg.get_time_axis_bbox() {
+            let delta = Point::new(top_left.x.min(0.), top_left.y.min(0.));
+            for node in self.vg.iter_nodes() {
+                self.vg.pos_mut(node).translate(delta.neg());
+            }
+        }
+    }
+}
+
+/// \returns the width of one unit of time that fits the labels of the nodes
+/// with the sizes \p sizes in the bars of their intervals \p spans. Axes
+/// that would be wider than MAX_AUTO_WIDTH are narrowed to it, and the
+/// labels of the short nodes overflow their bars.
+fn get_auto_scale(spans: &[Option<(f64, f64)>], sizes: &[Point]) -> f64 {
+    let mut scale: Option<f64> = None;
+    for (span, size) in spans.iter().zip(sizes.iter()) {
+        if let Option::Some((start, end)) = span {
+            if end - start > EPSILON {
+                let fit = size.x / (end - start);
+                scale = Some(scale.map_or(fit, |x| x.max(fit)));
+            }
+        }
+    }
+    let first = spans.iter().flatten().map(|x| x.0).fold(f64::MAX, f64::min);
+    let last = spans.iter().flatten().map(|x| x.1).fold(f64::MIN, f64::max);
+    let scale = scale.unwrap_or(DEFAULT_SCALE);
+    if last - first > EPSILON {
+        scale.min(MAX_AUTO_WIDTH / (last - first))
+    } else {
+        scale
+    }
+}
+
+/// \returns the center and the size of the nodes with the sizes \p sizes and
+/// the intervals \p spans, with \p scale points per unit of time. Nodes are
+/// placed, by their start time, in the first row where they don't overlap
+/// the nodes before them. Nodes with an empty interval are milestones, that
+/// keep their size and are centered on their time. Nodes without an interval
+/// are placed in a row below the other rows, \p gap apart.
+pub(crate) fn place_on_timeline(
+    spans: &[Option<(f64, f64)>],
+    sizes: &[Point],
+    scale: f64,
+    gap: f64,
+) -> Vec<(Point, Point)> {
+    let height = sizes.iter().fold(0., |acc, x| x.y.max(acc)) + gap;
+    let get_y = |row: usize| height * row as f64 + height / 2.;
+
+    let mut order: Vec<usize> =
+        (0..spans.len()).filter(|i| spans[*i].is_some()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (spans[*a].unwrap(), spans[*b].unwrap());
+        a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+    });
+
+    let mut res: Vec<(Point, Point)> =
+        sizes.iter().map(|size| (Point::zero(), *size)).collect();
+    // The right side of the last node in each row.
+    let mut rows: Vec<f64> = Vec::new();
+    let mut first = 0.;
+    for i in order {
+        let (start, end) = spans[i].unwrap();
+        let mut size = sizes[i];
+        let left = if end - start > EPSILON {
+            size.x = (end - start) * scale;
+            start * scale
+        } else {
+            start * scale - size.x / 2.
+        };
+        first = if rows.is_empty() {
+            left
+        } else {
+            left.min(first)
+        };
+        let row = match rows.iter().position(|x| *x <= left + EPSILON) {
+            Some(row) => row,
+            None => {
+                rows.push(left);
+                rows.len() - 1
+            }
+        };
+        rows[row] = left + size.x;
+        res[i] = (Point::new(left + size.x / 2., get_y(row)), size);
+    }
+
+    let mut left = first;
+    for (i, span) in spans.iter().enumerate() {
+        if span.is_none() {
+            let size = sizes[i];
+            res[i].0 = Point::new(left + size.x / 2., get_y(rows.len()));
+            left += size.x + gap;
+        }
+    }
+    res
+}
+
+/// \returns a step between the ticks of an axis that is at least \p min, and
+/// is 1, 2 or 5 times a power of ten.
+fn get_tick_step(min: f64) -> f64 {
+    let mut power = 1.;
+    while power * 10. <= min {
+        power *= 10.;
+    }
+    while power > min {
+        power /= 10.;
+    }
+    for factor in [1., 2., 5.] {
+        if factor * power >= min - EPSILON * power {
+            return factor * power;
+        }
+    }
+    10. * power
+}
+
+/// \returns the time and the label of the ticks of an axis from \p first to
+/// \p last, with \p scale points per unit of time.
+pub(crate) fn get_ticks(
+    first: f64,
+    last: f64,
+    scale: f64,
+) -> Vec<(f64, String)> {
+    let step = get_tick_step(MIN_TICK_SPACING / scale);
+    let mut res = Vec::new();
+    let mut i = (first / step - EPSILON).ceil();
+    while i * step <= last + EPSILON * step {
+        res.push((i * step, format_number(i * step)));
+        i += 1.;
+    }
+    res
+}
+
+#[test]
+fn test_timeline_rows() {
+    let size = Point::new(20., 10.);
+    let spans = [
+        Some((0., 4.)),
+        Some((4., 6.)),
+        Some((2., 5.)),
+        None,
+        Some((5., 5.)),
+        Some((1., 3.)),
+    ];
+    let boxes = place_on_timeline(&spans, &[size; 6], 10., 10.);
+    let row = |i: usize| ((boxes[i].0.y - 10.) / 20.) as usize;
+    // The bars span their intervals, and touching bars share a row.
+    assert_eq!(boxes[0].1, Point::new(40., 10.));
+    assert_eq!(boxes[0].0.x, 20.);
+    assert_eq!(row(0), 0);
+    assert_eq!(row(5), 1);
+    assert_eq!(row(2), 2);
+    assert_eq!(row(1), 0);
+    // The milestone keeps its size, and the node without an interval is
+    // placed below the rows.
+    assert_eq!(boxes[4].0.x, 50.);
+    assert_eq!(boxes[4].1, size);
+    assert_eq!(row(4), 1);
+    assert_eq!(row(3), 3);
+
+    let scale = get_auto_scale(&spans, &[size; 6]);
+    assert_eq!(scale, 10.);
+}
+
+#[test]
+fn test_ticks() {
+    assert_eq!(get_tick_step(0.3), 0.5);
+    assert_eq!(get_tick_step(2.), 2.);
+    assert_eq!(get_tick_step(60.), 100.);
+    let ticks = get_ticks(3., 25., 10.);
+    let labels: Vec<&str> = ticks.iter().map(|x| x.1.as_str()).collect();
+    assert_eq!(labels, ["10", "20"]);
+    let ticks = get_ticks(0., 1., 200.);
+    assert_eq!(ticks.len(), 3);
+    assert_eq!(ticks[1], (0.5, "0.5".to_string()));
+}
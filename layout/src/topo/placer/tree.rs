@@ -0,0 +1,358 @@
+//! This module contains the placers for trees. The tree placer draws the tree
+//! from the top down with the Reingold-Tilford algorithm, that packs each
+//! subtree as close as possible to its siblings and centers the parents above
+//! their children. The radial placer ('twopi') places the root in the middle,
+//! and the rest of the nodes on rings, by their distance from the root. Graphs
+//! that are not trees are laid out along their breadth-first spanning tree.
+
+#[cfg(feature = "log")]
+extern crate log;
+
+use crate::adt::dag::NodeHandle;
+#[cfg(not(feature = "std"))]
+use crate::core::float::FloatExt;
+use crate::core::geometry::Point;
+use crate::topo::layout::VisualGraph;
+use crate::topo::placer::force::{
+    get_radius, get_undirected_graph, move_to_origin, place_parts,
+    place_self_edges,
+};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// The space between neighboring nodes in a row or on a ring.
+const NODE_GAP: f64 = 20.;
+
+/// The space between the rows of the tree, or between the rings.
+const RANK_GAP: f64 = 40.;
+
+/// A spanning forest of the graph.
+#[derive(Debug)]
+struct Forest {
+    roots: Vec<usize>,
+    children: Vec<Vec<usize>>,
+    depth: Vec<usize>,
+}
+
+/// \returns the breadth-first spanning forest of the graph with \p n nodes
+/// and the edges \p edges. The trees grow from the nodes \p roots, in order,
+/// and from other nodes if some nodes can't be reached from the roots. The
+/// edges are followed in both directions.
+fn get_forest(n: usize, edges: &[(usize, usize)], roots: &[usize]) -> Forest {
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (a, b) in edges {
+        adj[*a].push(*b);
+        adj[*b].push(*a);
+    }
+
+    let mut forest = Forest {
+        roots: Vec::new(),
+        children: vec![Vec::new(); n],
+        depth: vec![0; n],
+    };
+    let mut visited = vec![false; n];
+    for root in roots.iter().cloned().chain(0..n) {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        forest.roots.push(root);
+        let mut worklist = VecDeque::from([root]);
+        while let Some(u) = worklist.pop_front() {
+            for v in adj[u].iter() {
+                if !visited[*v] {
+                    visited[*v] = true;
+                    forest.children[u].push(*v);
+                    forest.depth[*v] = forest.depth[u] + 1;
+                    worklist.push_back(*v);
+                }
+            }
+        }
+    }
+    forest
+}
+
+/// \returns the root of the graph that the user selected, as an index into
+/// the list of nodes \p nodes.
+fn get_selected_root(vg: &VisualGraph, nodes: &[NodeHandle]) -> Option<usize> {
+    let root = vg.root()?;
+    nodes.iter().position(|x| *x == root)
+}
+
+/// The extent of a subtree in each of its rows, relative to its root.
+type Contour = Vec<(f64, f64)>;
+
+/// Place the subtrees with the contours \p contours next to each other, as
+/// close as possible. \returns the offset of each subtree and the contour of
+/// all of them together.
+fn place_side_by_side(contours: &[Contour]) -> (Vec<f64>, Contour) {
+    let mut offsets = Vec::new();
+    let mut merged: Contour = Vec::new();
+    for contour in contours {
+        let offset = merged
+            .iter()
+            .zip(contour.iter())
+            .map(|(prev, curr)| prev.1 - curr.0 + NODE_GAP)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let offset = if offset.is_finite() { offset } else { 0. };
+        for (i, (left, right)) in contour.iter().enumerate() {
+            let (left, right) = (left + offset, right + offset);
+            if i < merged.len() {
+                merged[i] = (merged[i].0.min(left), merged[i].1.max(right));
+            } else {
+                merged.push((left, right));
+            }
+        }
+        offsets.push(offset);
+    }
+    (offsets, merged)
+}
+
+/// Place the subtree of \p node, where \p width is the width of each node.
+/// Saves the location of each child, relative to its parent, in \p rel_x.
+/// \returns the contour of the subtree.
+fn layout_subtree(
+    node: usize,
+    forest: &Forest,
+    width: &[f64],
+    rel_x: &mut [f64],
+) -> Contour {
+    let half = width[node] / 2.;
+    let children = &forest.children[node];
+    let contours: Vec<Contour> = children
+        .iter()
+        .map(|child| layout_subtree(*child, forest, width, rel_x))
+        .collect();
+    let (offsets, merged) = place_side_by_side(&contours);
+
+    // Center the parent above its first and last child.
+    let mut contour = vec![(-half, half)];
+    if let (Some(first), Some(last)) = (offsets.first(), offsets.last()) {
+        let mid = (first + last) / 2.;
+        for (child, offset) in children.iter().zip(offsets.iter()) {
+            rel_x[*child] = offset - mid;
+        }
+        contour.extend(merged.iter().map(|(l, r)| (l - mid, r - mid)));
+    }
+    contour
+}
+
+/// Places the nodes of the graph as a top-down tidy tree.
+#[derive(Debug)]
+pub struct TreePlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> TreePlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self, no_layout: bool) {
+        #[cfg(feature = "log")]
+        log::info!("Starting tree layout of {} nodes.", self.vg.num_nodes());
+
+        let (nodes, edges) = get_undirected_graph(self.vg);
+        let n = nodes.len();
+
+        // Grow the trees from the selected root, and from the nodes without
+        // incoming edges.
+        let mut has_preds = vec![false; n];
+        for (_, to) in edges.iter() {
+            has_preds[*to] = true;
+        }
+        let mut roots: Vec<usize> =
+            get_selected_root(self.vg, &nodes).into_iter().collect();
+        roots.extend((0..n).filter(|x| !has_preds[*x]));
+        // Without the layout, place all of the nodes in one row.
+        let edges = if no_layout { Vec::new() } else { edges };
+        let forest = get_forest(n, &edges, &roots);
+
+        // Lay out the tree top down, and transpose it in left-to-right graphs.
+        let is_tb = self.vg.orientation().is_top_to_bottom();
+        let sizes: Vec<Point> = nodes
+            .iter()
+            .map(|node| self.vg.pos(*node).size(false))
+            .map(|size| if is_tb { size } else { size.transpose() })
+            .collect();
+        let width: Vec<f64> = sizes.iter().map(|size| size.x).collect();
+
+        // Place the trees next to each other, as if they had a common parent.
+        let mut rel_x = vec![0.; n];
+        let contours: Vec<Contour> = forest
+            .roots
+            .iter()
+            .map(|root| layout_subtree(*root, &forest, &width, &mut rel_x))
+            .collect();
+        let (offsets, _) = place_side_by_side(&contours);
+        for (root, offset) in forest.roots.iter().zip(offsets.iter()) {
+            rel_x[*root] = *offset;
+        }
+
+        // The height of each row is the height of the tallest node in it.
+        let num_rows = forest.depth.iter().max().map_or(0, |d| d + 1);
+        let mut heights = vec![0.; num_rows];
+        for (size, depth) in sizes.iter().zip(forest.depth.iter()) {
+            heights[*depth] = size.y.max(heights[*depth]);
+        }
+        let mut row_y = vec![0.; num_rows];
+        for i in 1..num_rows {
+            let dist = (heights[i - 1] + heights[i]) / 2. + RANK_GAP;
+            row_y[i] = row_y[i - 1] + dist;
+        }
+
+        // Accumulate the relative locations from the roots down.
+        let mut x = vec![0.; n];
+        for root in forest.roots.iter() {
+            for u in get_subtree(&forest, *root) {
+                x[u] += rel_x[u];
+                for child in forest.children[u].iter() {
+                    x[*child] = x[u];
+                }
+            }
+        }
+
+        for (i, node) in nodes.iter().enumerate() {
+            let loc = Point::new(x[i], row_y[forest.depth[i]]);
+            let loc = if is_tb { loc } else { loc.transpose() };
+            self.vg.pos_mut(*node).move_to(loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+}
+
+/// Places the nodes of the graph on rings around a root.
+#[derive(Debug)]
+pub struct RadialPlacer<'a> {
+    vg: &'a mut VisualGraph,
+}
+
+impl<'a> RadialPlacer<'a> {
+    pub fn new(vg: &'a mut VisualGraph) -> Self {
+        Self { vg }
+    }
+
+    pub fn layout(&mut self, no_layout: bool) {
+        #[cfg(feature = "log")]
+        log::info!("Starting radial layout of {} nodes.", self.vg.num_nodes());
+
+        let (nodes, edges) = get_undirected_graph(self.vg);
+        let n = nodes.len();
+
+        // Without the layout, place all of the nodes in one row.
+        let edges = if no_layout { Vec::new() } else { edges };
+        let radius = get_radius(self.vg, &nodes);
+
+        // Use the selected root, and the most central node of each of the
+        // other parts of the graph.
+        let mut roots: Vec<usize> =
+            get_selected_root(self.vg, &nodes).into_iter().collect();
+        let parts = get_forest(n, &edges, &roots);
+        for part_root in parts.roots.iter() {
+            if roots.contains(part_root) {
+                continue;
+            }
+            let part = get_subtree(&parts, *part_root);
+            let center = part.iter().min_by_key(|x| {
+                let forest = get_forest(n, &edges, &[**x]);
+                part.iter().map(|y| forest.depth[*y]).max()
+            });
+            roots.extend(center);
+        }
+        let forest = get_forest(n, &edges, &roots);
+
+        // Each subtree gets a slice of the ring that is proportional to the
+        // number of leaves in it.
+        let mut leaves = vec![0.; n];
+        let mut order = Vec::new();
+        for root in forest.roots.iter() {
+            order.extend(get_subtree(&forest, *root));
+        }
+        for u in order.iter().rev() {
+            let sum: f64 = forest.children[*u].iter().map(|c| leaves[*c]).sum();
+            leaves[*u] = sum.max(1.);
+        }
+
+        let mut locs = vec![Point::zero(); n];
+        let mut parts: Vec<Vec<usize>> = Vec::new();
+        for root in forest.roots.iter() {
+            let part = get_subtree(&forest, *root);
+
+            // The ring of each depth must be outside of the previous ring,
+            // and long enough to hold the leaves with the gaps between them.
+            let num_rings =
+                part.iter().map(|x| forest.depth[*x]).max().unwrap();
+            let mut largest = vec![0.; num_rings + 1];
+            for x in part.iter() {
+                let d = forest.depth[*x];
+                largest[d] = radius[*x].max(largest[d]);
+            }
+            let mut rings = vec![0.; num_rings + 1];
+            for d in 1..=num_rings {
+                let dist = largest[d - 1] + largest[d] + RANK_GAP;
+                let len = (2. * largest[d] + NODE_GAP) * leaves[*root];
+                rings[d] = (rings[d - 1] + dist).max(len / (2. * PI));
+            }
+
+            // Assign the angles from the root down.
+            let mut angles = vec![(0., 2. * PI); n];
+            for u in part.iter() {
+                let (start, end) = angles[*u];
+                let mid = (start + end) / 2.;
+                let r = rings[forest.depth[*u]];
+                locs[*u] = Point::new(mid.cos(), mid.sin()).scale(r);
+                let mut angle = start;
+                for child in forest.children[*u].iter() {
+                    let slice = (end - start) * leaves[*child] / leaves[*u];
+                    angles[*child] = (angle, angle + slice);
+                    angle += slice;
+                }
+            }
+            parts.push(part);
+        }
+        place_parts(&mut locs, &radius, &parts, NODE_GAP);
+
+        for (node, loc) in nodes.iter().zip(locs.iter()) {
+            self.vg.pos_mut(*node).move_to(*loc);
+        }
+        place_self_edges(self.vg);
+        move_to_origin(self.vg);
+    }
+}
+
+/// \returns the nodes of the subtree of \p root in \p forest, in breadth
+/// first order.
+fn get_subtree(forest: &Forest, root: usize) -> Vec<usize> {
+    let mut res = vec![root];
+    let mut i = 0;
+    while i < res.len() {
+        res.extend(forest.children[res[i]].iter());
+        i += 1;
+    }
+    res
+}
+
+#[test]
+fn test_tidy_tree() {
+    // A root with two children, where the first child has two children.
+    let edges = [(0, 1), (0, 2), (1, 3), (1, 4)];
+    let forest = get_forest(5, &edges, &[0]);
+    assert_eq!(forest.roots, vec![0]);
+    assert_eq!(forest.depth, vec![0, 1, 1, 2, 2]);
+
+    let width = vec![10.; 5];
+    let mut rel_x = vec![0.; 5];
+    let contour = layout_subtree(0, &forest, &width, &mut rel_x);
+    assert_eq!(contour.len(), 3);
+
+    // The leaves under node 1 are one gap apart, and node 1 is above them.
+    assert_eq!(rel_x[4] - rel_x[3], 10. + NODE_GAP);
+    assert_eq!(rel_x[3] + rel_x[4], 0.);
+
+    // Node 2 is placed next to node 1, and the root is above them.
+    assert_eq!(rel_x[2] - rel_x[1], 10. + NODE_GAP);
+    assert_eq!(rel_x[1] + rel_x[2], 0.);
+}
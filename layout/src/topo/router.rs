@@ -0,0 +1,243 @@
+//! This module contains the edge router, which moves the edges that go
+//! through the boxes of other nodes around them. The router builds a
+//! visibility graph over the corners of the boxes, and finds the shortest
+//! path from one end of the edge to the other with A*. Edges that don't cross
+//! any box keep the path of the spline router.
+
+use crate::core::geometry::{
+    flatten_bezier_segments, get_bezier_segments, Point,
+};
+use crate::std_shapes::render::get_polyline_path;
+use crate::topo::hit::segment_crosses;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The space between the routed edges and the boxes that they go around.
+pub const ROUTE_MARGIN: f64 = 6.;
+
+/// The number of straight lines that approximate each bezier segment, when
+/// the path of the edge is tested against the boxes.
+const PATH_STEPS: usize = 8;
+
+/// \returns the box \p bbox, grown by \p margin on all sides.
+fn grow(bbox: (Point, Point), margin: f64) -> (Point, Point) {
+    let margin = Point::splat(margin);
+    (bbox.0.sub(margin), bbox.1.add(margin))
+}
+
+/// \returns true if the point \p p is inside of the box \p bbox.
+fn is_inside(p: Point, bbox: (Point, Point)) -> bool {
+    bbox.0.x < p.x && p.x < bbox.1.x && bbox.0.y < p.y && p.y < bbox.1.y
+}
+
+/// \returns true if the line from \p a to \p b crosses one of the boxes
+/// \p boxes.
+fn is_blocked(a: Point, b: Point, boxes: &[(Point, Point)]) -> bool {
+    boxes.iter().any(|bbox| segment_crosses(a, b, *bbox))
+}
+
+/// \returns true if the polyline \p points crosses one of the boxes \p boxes.
+fn polyline_crosses(points: &[Point], boxes: &[(Point, Point)]) -> bool {
+    points.windows(2).any(|x| is_blocked(x[0], x[1], boxes))
+}
+
+/// \returns the indices of the boxes \p boxes that the polyline \p points
+/// crosses.
+fn get_crossed(points: &[Point], boxes: &[(Point, Point)]) -> Vec<usize> {
+    (0..boxes.len())
+        .filter(|i| polyline_crosses(points, &boxes[*i..*i + 1]))
+        .collect()
+}
+
+/// \returns the shortest path from \p from to \p to, that goes around the
+/// boxes \p boxes, or None if there is no such path. The path keeps the
+/// space \p margin from the boxes. Boxes that contain one of the ends are
+/// ignored.
+fn find_route_around(
+    from: Point,
+    to: Point,
+    boxes: &[(Point, Point)],
+    margin: f64,
+) -> Option<Vec<Point>> {
+    let blocks: Vec<(Point, Point)> = boxes
+        .iter()
+        .map(|x| grow(*x, margin))
+        .filter(|x| !is_inside(from, *x) && !is_inside(to, *x))
+        .collect();
+
+    // The corners are a little further out than the blocks, so that the
+    // lines along the sides of a block don't touch it.
+    let mut points = vec![from, to];
+    for bbox in blocks.iter() {
+        let (a, b) = grow(*bbox, 1.);
+        for corner in [a, Point::new(b.x, a.y), b, Point::new(a.x, b.y)] {
+            if !blocks.iter().any(|x| is_inside(corner, *x)) {
+                points.push(corner);
+            }
+        }
+    }
+
+    // A* over the visibility graph, with the straight distance to the end
+    // as the estimate.
+    let n = points.len();
+    let mut dist = vec![f64::INFINITY; n];
+    let mut prev = vec![usize::MAX; n];
+    let mut done = vec![false; n];
+    dist[0] = 0.;
+    loop {
+        let estimate = |i: usize| dist[i] + points[i].distance_to(to);
+        let next = (0..n)
+            .filter(|i| !done[*i] && dist[*i].is_finite())
+            .min_by(|a, b| estimate(*a).total_cmp(&estimate(*b)))?;
+        if next == 1 {
+            break;
+        }
+        done[next] = true;
+        for i in 0..n {
+            if done[i] || is_blocked(points[next], points[i], &blocks) {
+                continue;
+            }
+            let d = dist[next] + points[next].distance_to(points[i]);
+            if d < dist[i] {
+                dist[i] = d;
+                prev[i] = next;
+            }
+        }
+    }
+
+    let mut res = vec![to];
+    let mut i = 1;
+    while prev[i] != usize::MAX {
+        i = prev[i];
+        res.push(points[i]);
+    }
+    res.reverse();
+    Some(res)
+}
+
+/// \returns the shortest path from \p from to \p to around the boxes
+/// \p boxes. The router starts with the boxes near the straight line, and
+/// adds the boxes that the path crosses until the path is clear, so that the
+/// far away boxes are not in the visibility graph.
+pub fn find_route(
+    from: Point,
+    to: Point,
+    boxes: &[(Point, Point)],
+    margin: f64,
+) -> Option<Vec<Point>> {
+    let mut active = get_crossed(&[from, to], boxes);
+    loop {
+        let near: Vec<(Point, Point)> =
+            active.iter().map(|i| boxes[*i]).collect();
+        let route = find_route_around(from, to, &near, margin)?;
+        let crossed: Vec<usize> = get_crossed(&route, boxes)
+            .into_iter()
+            .filter(|i| !active.contains(i))
+            .collect();
+        if crossed.is_empty() {
+            return Some(route);
+        }
+        active.extend(crossed);
+    }
+}
+
+/// \returns a smooth path through \p points, in the form that is passed to
+/// RenderBackend::draw_arrow. The curve passes through the points, and its
+/// direction at each inner point is the direction from the previous point to
+/// the next one.
+fn get_smooth_path(points: &[Point]) -> Vec<(Point, Point)> {
+    let last = points.len() - 1;
+    if last < 2 {
+        return get_polyline_path(points);
+    }
+    let first_ctrl = points[0].add(points[1].sub(points[0]).scale(1. / 3.));
+    let mut path = vec![(points[0], first_ctrl)];
+    for i in 1..last {
+        let (a, b, c) = (points[i - 1], points[i], points[i + 1]);
+        let dir = c.sub(a);
+        let len = dir.length();
+        let reach = a.distance_to(b).min(b.distance_to(c)) / 3.;
+        let ctrl = if len > 0. {
+            b.sub(dir.scale(reach / len))
+        } else {
+            b
+        };
+        path.push((ctrl, b));
+    }
+    let (a, b) = (points[last - 1], points[last]);
+    path.push((b.sub(b.sub(a).scale(1. / 3.)), b));
+    path
+}
+
+/// \returns the path \p path of an edge, moved around the boxes \p boxes of
+/// the nodes that it crosses. The edge goes through the points \p waypoints,
+/// from the first one to the last one, and the parts between the waypoints
+/// are routed separately. The path is smooth if \p smooth is set, and made
+/// of straight lines otherwise. Paths that don't cross the boxes are
+/// returned as they are.
+pub fn route_edge(
+    path: &[(Point, Point)],
+    waypoints: &[Point],
+    boxes: &[(Point, Point)],
+    smooth: bool,
+) -> Vec<(Point, Point)> {
+    let lines = flatten_bezier_segments(&get_bezier_segments(path), PATH_STEPS);
+    if waypoints.len() < 2 || !lines.iter().any(|x| polyline_crosses(x, boxes))
+    {
+        return path.to_vec();
+    }
+
+    let mut points = vec![waypoints[0]];
+    for leg in waypoints.windows(2) {
+        let (from, to) = (leg[0], leg[1]);
+        match find_route(from, to, boxes, ROUTE_MARGIN) {
+            Option::Some(route) => points.extend_from_slice(&route[1..]),
+            None => points.push(to),
+        }
+    }
+    points.dedup();
+    if smooth {
+        get_smooth_path(&points)
+    } else {
+        get_polyline_path(&points)
+    }
+}
+
+#[test]
+fn test_find_route() {
+    // A box between the ends, and a box that blocks the way around the top.
+    let boxes = [
+        (Point::new(40., -20.), Point::new(60., 20.)),
+        (Point::new(30., -40.), Point::new(70., -22.)),
+    ];
+    let from = Point::new(0., 0.);
+    let to = Point::new(100., 0.);
+    let route = find_route(from, to, &boxes, 5.).unwrap();
+    assert_eq!(route[0], from);
+    assert_eq!(route[route.len() - 1], to);
+    assert!(!polyline_crosses(&route, &boxes));
+    // The route goes below the boxes, that is the shorter way.
+    assert!(route.iter().all(|p| p.y >= 0.));
+    assert!(route.iter().any(|p| p.y >= 25.));
+
+    // The straight line is kept when nothing is in the way.
+    let route = find_route(from, to, &boxes[1..], 5.).unwrap();
+    assert_eq!(route, vec![from, to]);
+}
+
+#[test]
+fn test_route_edge() {
+    let boxes = [(Point::new(40., -20.), Point::new(60., 20.))];
+    let from = Point::new(0., 0.);
+    let to = Point::new(100., 0.);
+    let path = get_polyline_path(&[from, to]);
+    let routed = route_edge(&path, &[from, to], &boxes, true);
+    assert_eq!(routed[0].0, from);
+    assert_eq!(routed[routed.len() - 1].1, to);
+    let lines = flatten_bezier_segments(&get_bezier_segments(&routed), 16);
+    assert!(!lines.iter().any(|x| polyline_crosses(x, &boxes)));
+
+    // Paths that are clear don't change.
+    let clear = [(Point::new(40., 30.), Point::new(60., 50.))];
+    assert_eq!(route_edge(&path, &[from, to], &clear, true), path);
+}
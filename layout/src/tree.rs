@@ -0,0 +1,133 @@
+//! Draws recursive data structures, such as syntax trees or directory
+//! listings, with the tidy tree layout. Types that implement TreeNode are
+//! drawn as boxes, with their children in the row below them, in the order
+//! of the list of children.
+//!
+//! ```rust
+//! use layout::tree::{render_tree_ascii, TreeNode};
+//!
+//! struct Expr {
+//!     op: String,
+//!     args: Vec<Expr>,
+//! }
+//!
+//! impl TreeNode for Expr {
+//!     fn label(&self) -> String {
+//!         self.op.clone()
+//!     }
+//!     fn children(&self) -> Vec<&Self> {
+//!         self.args.iter().collect()
+//!     }
+//! }
+//!
+//! let leaf = |op: &str| Expr { op: op.to_string(), args: Vec::new() };
+//! let root = Expr { op: "+".to_string(), args: vec![leaf("1"), leaf("x")] };
+//! println!("{}", render_tree_ascii(&root));
+//! ```
+
+use crate::backends::ascii_art::ASCIIWriter;
+use crate::backends::svg::SVGWriter;
+use crate::builder::{GraphBuilder, Shape};
+use crate::core::base::LayoutEngine;
+use crate::core::text::TextMetrics;
+use crate::topo::layout::VisualGraph;
+
+/// A node of a tree that can be drawn.
+pub trait TreeNode {
+    /// \returns the text that is drawn in the node.
+    fn label(&self) -> String;
+    /// \returns the children of the node, from left to right.
+    fn children(&self) -> Vec<&Self>;
+}
+
+/// \returns the name of the \p idx-th node of the tree, in preorder.
+fn node_name(idx: usize) -> String {
+    format!("n{}", idx)
+}
+
+/// \returns a graph builder with the nodes of the tree \p root, in preorder,
+/// and the edges from each node to its children. The nodes are named "n0",
+/// "n1", and so on, because the labels of the nodes don't have to be unique.
+pub fn tree_to_builder<T: TreeNode>(root: &T) -> GraphBuilder {
+    let mut graph = GraphBuilder::new();
+    // The nodes that wait to be visited, with the name of their parent. The
+    // children are pushed in reverse, to visit them from left to right.
+    let mut stack: Vec<(&T, Option<String>)> = vec![(root, None)];
+    let mut count = 0;
+    while let Option::Some((node, parent)) = stack.pop() {
+        let name = node_name(count);
+        count += 1;
+        graph.node(&name).shape(Shape::Box).label(&node.label());
+        if let Option::Some(parent) = parent {
+            graph.edge(&parent, &name);
+        }
+        for child in node.children().into_iter().rev() {
+            stack.push((child, Some(name.clone())));
+        }
+    }
+    graph
+}
+
+/// \returns the graph that draws the tree \p root with the tree layout, with
+/// labels that are measured with \p metrics.
+pub fn build_tree<T: TreeNode>(root: &T, metrics: TextMetrics) -> VisualGraph {
+    let mut vg = tree_to_builder(root).text_metrics(metrics).build();
+    vg.set_layout_engine(LayoutEngine::Tree);
+    vg
+}
+
+/// \returns the drawing of the tree \p root as SVG text.
+pub fn render_tree_svg<T: TreeNode>(root: &T) -> String {
+    let mut vg = build_tree(root, TextMetrics::Font);
+    let mut svg = SVGWriter::new();
+    vg.do_it(false, false, false, &mut svg);
+    svg.finalize()
+}
+
+/// \returns the drawing of the tree \p root as ASCII art.
+pub fn render_tree_ascii<T: TreeNode>(root: &T) -> String {
+    let mut vg = build_tree(root, TextMetrics::Grid);
+    let mut ascii = ASCIIWriter::new();
+    vg.do_it(false, false, false, &mut ascii);
+    ascii.finalize()
+}
+
+#[cfg(test)]
+struct TestNode(&'static str, Vec<TestNode>);
+
+#[cfg(test)]
+impl TreeNode for TestNode {
+    fn label(&self) -> String {
+        self.0.to_string()
+    }
+    fn children(&self) -> Vec<&Self> {
+        self.1.iter().collect()
+    }
+}
+
+#[test]
+fn test_tree_to_builder() {
+    use crate::adt::dag::NodeHandle;
+
+    let leaf = |label| TestNode(label, Vec::new());
+    let root = TestNode(
+        "+",
+        vec![TestNode("*", vec![leaf("x"), leaf("y")]), leaf("x")],
+    );
+    let text = tree_to_builder(&root).to_dot(None);
+    // The nodes are named in preorder, and the repeated labels are kept.
+    assert!(text.contains("  n0 [label=\"+\", shape=box];\n"));
+    assert!(text.contains("  n2 [label=x, shape=box];\n"));
+    assert!(text.contains("  n4 [label=x, shape=box];\n"));
+    assert!(text.contains("  n1 -> n3;\n"));
+    assert!(text.contains("  n0 -> n4;\n"));
+
+    let mut vg = build_tree(&root, TextMetrics::Grid);
+    let mut ascii = ASCIIWriter::new();
+    vg.do_it(false, false, false, &mut ascii);
+    let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+    // The children are below their parent, from left to right.
+    assert!(center(1).y > center(0).y);
+    assert!(center(1).x < center(4).x);
+    assert!(center(2).x < center(3).x);
+}
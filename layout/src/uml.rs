@@ -0,0 +1,194 @@
+//! Draws the classes of UML class diagrams. A class is a box with three
+//! compartments, for its name, its fields and its methods, that is drawn as a
+//! record node. The box grows to fit the longest line of the compartments,
+//! and each compartment justifies its lines on its own. The relations between
+//! classes select the arrow heads and the lines of the edges.
+//!
+//! ```rust
+//! use layout::builder::GraphBuilder;
+//! use layout::uml::{ClassNode, Relation};
+//!
+//! let mut graph = GraphBuilder::new();
+//! let mut shape = ClassNode::new("Shape");
+//! shape.field("+ name: String").method("+ area(): f64");
+//! graph.node("Shape").class(&shape);
+//! graph.node("Circle").class(ClassNode::new("Circle").field("- r: f64"));
+//! graph.edge("Shape", "Circle").relation(Relation::Inheritance);
+//! let vg = graph.build();
+//! ```
+
+use crate::core::base::Orientation;
+use crate::core::style::{ArrowHeadKind, LineStyleKind};
+use crate::core::text::Justification;
+
+/// The parts of the box of a class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compartment {
+    Name,
+    Fields,
+    Methods,
+}
+
+/// The name, the fields and the methods of a class, and the justification of
+/// the lines of each compartment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassNode {
+    name: String,
+    fields: Vec<String>,
+    methods: Vec<String>,
+    // The justification of the name, the fields and the methods.
+    justification: [Justification; 3],
+}
+
+impl ClassNode {
+    /// Create the class \p name, without fields and methods. The name is
+    /// centered, and the fields and the methods are justified to the left.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            justification: [
+                Justification::Center,
+                Justification::Left,
+                Justification::Left,
+            ],
+        }
+    }
+
+    /// Add the line \p field, such as "- size: usize", to the fields.
+    pub fn field(&mut self, field: &str) -> &mut Self {
+        self.fields.push(field.to_string());
+        self
+    }
+
+    /// Add the line \p method, such as "+ len(): usize", to the methods.
+    pub fn method(&mut self, method: &str) -> &mut Self {
+        self.methods.push(method.to_string());
+        self
+    }
+
+    /// Justify the lines of the compartment \p part with \p justification.
+    pub fn justify(
+        &mut self,
+        part: Compartment,
+        justification: Justification,
+    ) -> &mut Self {
+        self.justification[part as usize] = justification;
+        self
+    }
+
+    /// \returns the record label that draws the class in a graph with the
+    /// orientation \p dir. The compartments are stacked from top to bottom
+    /// in both orientations.
+    pub fn to_record_label(&self, dir: Orientation) -> String {
+        let name = vec![self.name.clone()];
+        let parts = [&name, &self.fields, &self.methods];
+        let fields: Vec<String> = parts
+            .iter()
+            .zip(self.justification.iter())
+            .map(|(lines, justification)| {
+                get_compartment_label(lines, *justification)
+            })
+            .collect();
+        let fields = fields.join("|");
+        // The fields of records are placed across the ranks, and the fields
+        // in braces are placed along the ranks.
+        if dir.is_top_to_bottom() {
+            format!("{{{}}}", fields)
+        } else {
+            fields
+        }
+    }
+}
+
+/// \returns the record field that holds the lines \p lines with the
+/// justification \p justification.
+fn get_compartment_label(
+    lines: &[String],
+    justification: Justification,
+) -> String {
+    let lines: Vec<String> = lines.iter().map(|x| escape_record(x)).collect();
+    match justification {
+        Justification::Center => lines.join("\n"),
+        Justification::Left => {
+            lines.iter().map(|x| x.clone() + "\\l").collect()
+        }
+        Justification::Right => {
+            lines.iter().map(|x| x.clone() + "\\r").collect()
+        }
+    }
+}
+
+/// \returns the text \p text with the characters that have a meaning in
+/// record labels escaped, such as the '<' of generic types.
+fn escape_record(text: &str) -> String {
+    let mut res = String::new();
+    for ch in text.chars() {
+        if matches!(ch, '{' | '}' | '|' | '<' | '>' | '\\') {
+            res.push('\\');
+        }
+        res.push(ch);
+    }
+    res
+}
+
+/// The relations between the classes of a diagram. The edges of the
+/// relations go from the general class to the specific class, and from the
+/// whole to its part, so that the general classes are placed above. The
+/// head of the relation is drawn at the start of the edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// The class extends the class at the start of the edge: a hollow
+    /// triangle.
+    Inheritance,
+    /// The class implements the interface at the start of the edge: a
+    /// hollow triangle on a dashed line.
+    Realization,
+    /// The class at the start of the edge owns the part: a filled diamond.
+    Composition,
+    /// The class at the start of the edge holds the part: a hollow diamond.
+    Aggregation,
+}
+
+impl Relation {
+    /// \returns the heads at the start and at the end of the edge.
+    pub fn arrow_heads(&self) -> (ArrowHeadKind, ArrowHeadKind) {
+        let start = match self {
+            Relation::Inheritance | Relation::Realization => {
+                ArrowHeadKind::ONormal
+            }
+            Relation::Composition => ArrowHeadKind::Diamond,
+            Relation::Aggregation => ArrowHeadKind::ODiamond,
+        };
+        (start, ArrowHeadKind::None)
+    }
+
+    /// \returns the style of the line of the edge.
+    pub fn line_style(&self) -> LineStyleKind {
+        match self {
+            Relation::Realization => LineStyleKind::Dashed,
+            _ => LineStyleKind::Normal,
+        }
+    }
+}
+
+#[test]
+fn test_class_label() {
+    let mut class = ClassNode::new("List<T>");
+    class.field("- len: usize").field("- items: Vec<T>");
+    class.method("+ push(T)");
+    let label = class.to_record_label(Orientation::TopToBottom);
+    assert_eq!(
+        label,
+        "{List\\<T\\>|- len: usize\\l- items: Vec\\<T\\>\\l|+ push(T)\\l}"
+    );
+
+    // The compartments are stacked in graphs that grow to the right, and
+    // empty compartments are kept.
+    let mut class = ClassNode::new("A");
+    class.justify(Compartment::Methods, Justification::Right);
+    class.justify(Compartment::Fields, Justification::Center);
+    class.field("x").field("y");
+    assert_eq!(class.to_record_label(Orientation::LeftToRight), "A|x\ny|");
+}
@@ -0,0 +1,67 @@
+//! WebAssembly bindings that lay out DOT text in the browser or in Node.js.
+//! This module is enabled by the `wasm` feature, and the crate is built for
+//! the `wasm32-unknown-unknown` target with wasm-pack or wasm-bindgen:
+//!
+//! ```txt
+//! wasm-pack build layout --target web --features wasm
+//! ```
+//!
+//! The functions take the DOT text and throw the error message of the parser
+//! when the text is not valid.
+//!
+//! ```js
+//! import init, { render_svg, layout_json } from "./pkg/layout.js";
+//!
+//! await init();
+//! const svg = render_svg("digraph { a -> b; }");
+//! const positions = JSON.parse(layout_json("digraph { a -> b; }"));
+//! ```
+
+use crate::backends::svg::SVGWriter;
+use crate::gv::jgf::write_jgf;
+use crate::gv::{DotParser, GraphBuilder};
+use crate::topo::layout::VisualGraph;
+use wasm_bindgen::prelude::*;
+
+/// \returns the builder of the DOT text \p dot and its laid out graph, or the
+/// error message of the parser.
+fn layout_dot(dot: &str) -> Result<(GraphBuilder, VisualGraph), String> {
+    let graph = DotParser::new(dot).process().map_err(|e| e.to_string())?;
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(&graph);
+    let mut vg = gb.get();
+    vg.layout(false, false);
+    Result::Ok((gb, vg))
+}
+
+/// \returns the SVG drawing of the DOT text \p dot.
+#[wasm_bindgen]
+pub fn render_svg(dot: &str) -> Result<String, String> {
+    let (_, vg) = layout_dot(dot)?;
+    let mut svg = SVGWriter::new();
+    vg.draw(false, &mut svg);
+    Result::Ok(svg.finalize())
+}
+
+/// \returns the graph of the DOT text \p dot in the JSON Graph Format, with
+/// the position and the size of the nodes and the paths of the edges in the
+/// metadata, like the `--jgf` flag of the command line tool.
+#[wasm_bindgen]
+pub fn layout_json(dot: &str) -> Result<String, String> {
+    let (gb, vg) = layout_dot(dot)?;
+    Result::Ok(write_jgf(&gb, Option::Some(&vg.layout_result())))
+}
+
+#[test]
+fn test_wasm_bindings() {
+    let svg = render_svg("digraph { a -> b; }").unwrap();
+    assert!(svg.contains("<svg"));
+    assert!(svg.contains(">b<"));
+
+    let json = layout_json("digraph { a -> b; }").unwrap();
+    assert!(json.contains("\"source\": \"a\""));
+    assert!(json.contains("\"layout\": {"));
+
+    assert!(render_svg("digraph {").is_err());
+    assert!(layout_json("digraph {").is_err());
+}
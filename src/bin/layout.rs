@@ -1,5 +1,6 @@
 //! This is the command line tool that loads '.dot' files, renders the graph,
-//!  and saves the output.
+//!  and saves the output. Like `dot`, it reads the standard input when there
+//! is no input file, and `layout -Tsvg in.dot -o out.svg` renders a file.
 
 extern crate clap;
 extern crate env_logger;
@@ -8,19 +9,78 @@ extern crate log;
 use clap::{Arg, ArgAction, Command};
 use gv::parser::DotParser;
 use gv::GraphBuilder;
-use layout::backends::ascii_art::ASCIIWriter;
-use layout::backends::svg::SVGWriter;
-use layout::core::utils::save_to_file;
+use layout::backends::animation::{AnimationFormat, AnimationWriter};
+use layout::backends::ascii_art::{ASCIIWriter, CharSet};
+use layout::backends::eps::EPSWriter;
+use layout::backends::html::HTMLWriter;
+use layout::backends::pdf::PDFWriter;
+use layout::backends::png::PNGWriter;
+use layout::backends::scaled::{ScaledBackend, Transform};
+use layout::backends::svg::{write_svg, SVGWriter};
+use layout::backends::terminal::TerminalWriter;
+use layout::backends::tikz::TikZWriter;
+use layout::backends::tiles::TileConfig;
+use layout::core::base::{LayoutEngine, LayoutQuality};
+use layout::core::format::{RenderBackend, Stylesheet};
+use layout::core::geometry::Point;
+use layout::core::style::StyleAttr;
+use layout::core::text::TextMetrics;
+use layout::core::theme::{Theme, THEME_NAMES};
 use layout::gv;
+use layout::gv::batch::{BatchFormat, BatchRenderer};
+use layout::gv::diff::GraphDiff;
+use layout::gv::focus::{find_nodes, focus_graph, ContextMode, FocusConfig};
+use layout::gv::jgf::{parse_jgf, write_jgf};
+use layout::gv::matrix::AdjacencyMatrix;
 use layout::topo::layout::VisualGraph;
 use std::fs;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+/// The time that each frame of the --animate output is shown, in milliseconds.
+const ANIMATION_DELAY_MS: u16 = 100;
 
 struct CLIOptions {
     disable_opt: bool,
     disable_layout: bool,
     ascii_output_path: Option<String>,
     svg_output_path: Option<String>,
+    png_output_path: Option<String>,
+    animation_output_path: Option<String>,
+    pdf_output_path: Option<String>,
+    tikz_output_path: Option<String>,
+    eps_output_path: Option<String>,
+    html_output_path: Option<String>,
+    jgf_output_path: Option<String>,
+    dot_output_path: Option<String>,
+    xdot_output_path: Option<String>,
+    plain_output_path: Option<String>,
+    plain_ext_output_path: Option<String>,
+    terminal_output: bool,
     debug_mode: bool,
+    seed: Option<u64>,
+    quality: LayoutQuality,
+    engine: Option<LayoutEngine>,
+    scale: Option<f64>,
+    dpi: Option<f64>,
+    flip_y: bool,
+    translate: Option<Point>,
+    tile_overlap: f64,
+    crop_marks: bool,
+    use_colors: Option<bool>,
+    unicode: bool,
+    // Draws the SVG and the ASCII outputs as an adjacency matrix, or None to
+    // draw only the dense graphs as a matrix.
+    matrix: Option<bool>,
+    lenient: bool,
+    embed_images: bool,
+    stylesheets: Vec<Stylesheet>,
+    theme: Theme,
+    diff_base_path: Option<String>,
+    focus: Vec<String>,
+    focus_config: FocusConfig,
 }
 
 impl CLIOptions {
@@ -30,52 +90,604 @@ impl CLIOptions {
             disable_layout: false,
             ascii_output_path: None,
             svg_output_path: None,
+            png_output_path: None,
+            animation_output_path: None,
+            pdf_output_path: None,
+            tikz_output_path: None,
+            eps_output_path: None,
+            html_output_path: None,
+            jgf_output_path: None,
+            dot_output_path: None,
+            xdot_output_path: None,
+            plain_output_path: None,
+            plain_ext_output_path: None,
+            terminal_output: false,
             debug_mode: false,
+            seed: None,
+            quality: LayoutQuality::Normal,
+            engine: None,
+            scale: None,
+            dpi: None,
+            flip_y: false,
+            translate: None,
+            tile_overlap: 0.,
+            crop_marks: false,
+            use_colors: None,
+            unicode: false,
+            matrix: Some(false),
+            lenient: false,
+            embed_images: false,
+            stylesheets: Vec::new(),
+            theme: Theme::light(),
+            diff_base_path: None,
+            focus: Vec::new(),
+            focus_config: FocusConfig::new(1),
         }
     }
-}
 
-fn generate_ascii_art(graph: &mut VisualGraph, options: &CLIOptions) {
-    let mut ascii = ASCIIWriter::new_with_terminal_setting(true);
-    graph.do_it(
-        options.debug_mode,
-        options.disable_opt,
-        options.disable_layout,
-        &mut ascii,
-    );
-    let content = ascii.finalize();
+    /// \returns true if one of the outputs is written to the standard output.
+    fn writes_to_stdout(&self) -> bool {
+        let paths = [
+            &self.ascii_output_path,
+            &self.svg_output_path,
+            &self.png_output_path,
+            &self.animation_output_path,
+            &self.pdf_output_path,
+            &self.tikz_output_path,
+            &self.eps_output_path,
+            &self.html_output_path,
+            &self.jgf_output_path,
+            &self.dot_output_path,
+            &self.xdot_output_path,
+            &self.plain_output_path,
+            &self.plain_ext_output_path,
+        ];
+        self.terminal_output
+            || paths.iter().any(|x| x.as_deref() == Option::Some("-"))
+    }
+}
 
-    let pa = options.ascii_output_path.as_ref().unwrap();
-    let res = save_to_file(pa, &content);
+/// Write \p content to the file \p pa, or to the standard output if \p pa is
+/// "-".
+fn write_output(pa: &str, content: &[u8]) -> Result<(), String> {
+    let res = if pa == "-" {
+        let mut out = io::stdout().lock();
+        out.write_all(content).and_then(|_| out.flush())
+    } else {
+        fs::File::create(pa).and_then(|mut file| file.write_all(content))
+    };
     if let Result::Err(err) = res {
-        log::error!("Could not write the file {}", pa);
-        log::error!("Error {}", err);
-        return;
+        return Result::Err(format!(
+            "Could not write the file {}: {}",
+            pa, err
+        ));
     }
     log::info!("Wrote {}", pa);
+    Result::Ok(())
+}
+
+/// Parse the point \p s, written as "X,Y".
+fn parse_point(s: &str) -> Result<Point, String> {
+    let parts: Vec<&str> = s.split(',').collect();
+    if let [x, y] = parts[..] {
+        if let (Result::Ok(x), Result::Ok(y)) =
+            (x.trim().parse::<f64>(), y.trim().parse::<f64>())
+        {
+            return Result::Ok(Point::new(x, y));
+        }
+    }
+    Result::Err(format!("Expected X,Y, got '{}'", s))
 }
 
-fn generate_svg(graph: &mut VisualGraph, options: &CLIOptions) {
-    let mut svg = SVGWriter::new();
-    graph.do_it(
-        options.debug_mode,
-        options.disable_opt,
-        options.disable_layout,
-        &mut svg,
-    );
-    let content = svg.finalize();
+/// \returns the transform of the output that \p options select for the
+/// layout of \p graph, or None to draw the layout as it is.
+fn get_transform(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Option<Transform> {
+    if options.scale.is_none()
+        && options.dpi.is_none()
+        && !options.flip_y
+        && options.translate.is_none()
+    {
+        return None;
+    }
+    let mut transform = Transform::scale(options.scale.unwrap_or(1.));
+    if let Option::Some(dpi) = options.dpi {
+        transform.dpi = dpi;
+    }
+    if options.flip_y {
+        // Flip the drawing in place, so that it keeps its margins. The
+        // drawing is scaled to its 'size' before it is transformed.
+        let (a, b) = graph.layout_result().bbox;
+        let scale = graph.output_scale();
+        transform = transform.flip_in_place((a.scale(scale), b.scale(scale)));
+    }
+    if let Option::Some(translate) = options.translate {
+        transform.translate = transform.translate.add(translate);
+    }
+    Some(transform)
+}
+
+/// \returns the config and the corners of the pages of the laid out \p graph,
+/// when it sets the size of the pages and doesn't fit on one page, or None to
+/// write one page. The pages are transformed by \p transform, like the
+/// drawing.
+fn get_tiles(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+    transform: Option<Transform>,
+) -> Option<(TileConfig, Vec<Point>)> {
+    let fit = graph.size_config();
+    let factor = transform.map_or(1., |x| x.factor());
+    let mut config = TileConfig::new(fit.page?.scale(factor));
+    config.overlap = options.tile_overlap;
+    config.order = fit.page_dir;
+    config.crop_marks = options.crop_marks;
+    let tiles = config.get_tiles(graph.output_size()?.scale(factor));
+    if tiles.len() < 2 {
+        return None;
+    }
+    Some((config, tiles))
+}
+
+/// \returns the path of the \p i-th page of the output file \p pa, such as
+/// "out_2.svg" for "out.svg".
+fn get_page_path(pa: &str, i: usize) -> String {
+    let path = std::path::Path::new(pa);
+    let stem = path.file_stem().map_or("".into(), |x| x.to_string_lossy());
+    let name = match path.extension() {
+        Option::Some(ext) => {
+            format!("{}_{}.{}", stem, i + 1, ext.to_string_lossy())
+        }
+        None => format!("{}_{}", stem, i + 1),
+    };
+    path.with_file_name(name).to_string_lossy().to_string()
+}
+
+/// Draw the laid out \p graph on \p rb, transformed by \p transform.
+fn draw_transformed(
+    graph: &VisualGraph,
+    debug_mode: bool,
+    transform: Option<Transform>,
+    rb: &mut dyn RenderBackend,
+) {
+    match transform {
+        Option::Some(transform) => {
+            let mut scaled = ScaledBackend::with_transform(rb, transform);
+            graph.draw(debug_mode, &mut scaled)
+        }
+        None => graph.draw(debug_mode, rb),
+    }
+}
+
+/// Draw the laid out \p graph on \p rb, transformed by the scale, the DPI,
+/// the flip and the translation of \p options.
+fn draw_graph(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+    rb: &mut dyn RenderBackend,
+) {
+    let transform = get_transform(graph, options);
+    draw_transformed(graph, options.debug_mode, transform, rb);
+}
+
+fn generate_ascii_art(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let pa = options.ascii_output_path.as_ref().unwrap();
+    // Colors and filled shapes are written to files, and to the standard
+    // output if it's a terminal. The --color flag overrides the colors.
+    let is_terminal = pa != "-" || io::stdout().is_terminal();
+    let mut ascii = ASCIIWriter::new_with_terminal_setting(is_terminal);
+    if let Option::Some(use_colors) = options.use_colors {
+        ascii.set_use_colors(use_colors);
+    }
+    if options.unicode {
+        ascii.set_char_set(CharSet::Unicode);
+    }
+    draw_graph(graph, options, &mut ascii);
+    let content = ascii.finalize();
+
+    write_output(pa, content.as_bytes())
+}
 
+fn generate_svg(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let transform = get_transform(graph, options);
     let pa = options.svg_output_path.as_ref().unwrap();
-    let res = save_to_file(pa, &content);
+
+    // Write a file for each page of drawings that don't fit on one page.
+    if let Option::Some((config, tiles)) = get_tiles(graph, options, transform)
+    {
+        if pa != "-" {
+            let mut svg = SVGWriter::new();
+            draw_transformed(graph, options.debug_mode, transform, &mut svg);
+            for (i, page) in
+                svg.finalize_tiles(&config, &tiles).iter().enumerate()
+            {
+                write_output(&get_page_path(pa, i), page.as_bytes())?;
+            }
+            return Result::Ok(());
+        }
+        log::warn!("Writing the pages to the standard output as one page");
+    }
+
+    let draw = |rb: &mut dyn RenderBackend| {
+        draw_transformed(graph, options.debug_mode, transform, rb)
+    };
+
+    // Write the document as it is drawn, because the drawings of large graphs
+    // can be very large.
+    let res = if pa == "-" {
+        let mut out = BufWriter::new(io::stdout().lock());
+        write_svg(&mut out, draw).and_then(|_| out.flush())
+    } else {
+        fs::File::create(pa).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            write_svg(&mut out, draw)?;
+            out.flush()
+        })
+    };
     if let Result::Err(err) = res {
-        log::error!("Could not write the file {}", pa);
-        log::error!("Error {}", err);
-        return;
+        return Result::Err(format!(
+            "Could not write the file {}: {}",
+            pa, err
+        ));
     }
     log::info!("Wrote {}", pa);
+    Result::Ok(())
+}
+
+fn generate_png(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut png = PNGWriter::new();
+    draw_graph(graph, options, &mut png);
+    let content = png.finalize();
+
+    let pa = options.png_output_path.as_ref().unwrap();
+    write_output(pa, &content)
+}
+
+fn generate_animation(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut anim = AnimationWriter::new();
+    anim.add_layout(graph, ANIMATION_DELAY_MS);
+    let pa = options.animation_output_path.as_ref().unwrap();
+    let content = anim.finalize(AnimationFormat::from_path(pa));
+    write_output(pa, &content)
+}
+
+fn generate_pdf(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut pdf = PDFWriter::new();
+    let transform = get_transform(graph, options);
+    draw_transformed(graph, options.debug_mode, transform, &mut pdf);
+    // Drawings that don't fit on one page are split into pages.
+    let content = match get_tiles(graph, options, transform) {
+        Option::Some((config, tiles)) => pdf.finalize_tiles(&config, &tiles),
+        None => pdf.finalize(),
+    };
+
+    let pa = options.pdf_output_path.as_ref().unwrap();
+    write_output(pa, &content)
+}
+
+fn generate_tikz(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut tikz = TikZWriter::new();
+    draw_graph(graph, options, &mut tikz);
+    let content = tikz.finalize();
+
+    let pa = options.tikz_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
 }
 
-fn main() {
+fn generate_eps(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut eps = EPSWriter::new();
+    draw_graph(graph, options, &mut eps);
+    let content = eps.finalize();
+
+    let pa = options.eps_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
+}
+
+fn generate_html(
+    graph: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut html = HTMLWriter::new();
+    draw_graph(graph, options, &mut html);
+    let content = html.finalize();
+
+    let pa = options.html_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
+}
+
+fn generate_jgf(
+    graph: &gv::parser::ast::Graph,
+    vg: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    let content = write_jgf(&gb, Some(&vg.layout_result()));
+
+    let pa = options.jgf_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
+}
+
+fn generate_dot(
+    graph: &gv::parser::ast::Graph,
+    vg: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    let content = gb.to_dot(Some(&vg.layout_result()));
+
+    let pa = options.dot_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
+}
+
+fn generate_xdot(
+    graph: &gv::parser::ast::Graph,
+    vg: &VisualGraph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    let content = gb.to_xdot(vg);
+
+    let pa = options.xdot_output_path.as_ref().unwrap();
+    write_output(pa, content.as_bytes())
+}
+
+/// Write the layout of \p graph in the 'plain' format of Graphviz, or in the
+/// 'plain-ext' format if \p extended is set.
+fn generate_plain(
+    graph: &gv::parser::ast::Graph,
+    vg: &VisualGraph,
+    options: &CLIOptions,
+    extended: bool,
+) -> Result<(), String> {
+    let mut gb = GraphBuilder::new();
+    gb.visit_graph(graph);
+    let content = gb.to_plain(&vg.layout_result(), extended);
+
+    let pa = if extended {
+        options.plain_ext_output_path.as_ref().unwrap()
+    } else {
+        options.plain_output_path.as_ref().unwrap()
+    };
+    write_output(pa, content.as_bytes())
+}
+
+/// Write the adjacency matrix \p matrix of the graph to the SVG and the ASCII
+/// outputs.
+fn generate_matrix(
+    matrix: &AdjacencyMatrix,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    if let Option::Some(pa) = &options.svg_output_path {
+        let mut look = StyleAttr::simple();
+        look.line_color = options.theme.node_line;
+        look.font_color = options.theme.node_font;
+        let mut svg = SVGWriter::new();
+        if let Option::Some(color) = options.theme.background {
+            svg.set_background(color);
+        }
+        matrix.render(&look, &mut svg);
+        write_output(pa, svg.finalize().as_bytes())?;
+    }
+    if let Option::Some(pa) = &options.ascii_output_path {
+        write_output(pa, matrix.to_text(options.unicode).as_bytes())?;
+    }
+    Result::Ok(())
+}
+
+fn generate_terminal(
+    graph: &gv::parser::ast::Graph,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut term = TerminalWriter::new();
+    let mut graph = build_graph(graph, term.text_metrics(), options);
+    graph.layout(options.disable_opt, options.disable_layout);
+    draw_graph(&graph, options, &mut term);
+    print!("{}", term.finalize());
+    Result::Ok(())
+}
+
+/// Parse the graph in \p contents, that was read from \p input_path. Files
+/// that end with '.json' are in the JSON Graph Format, and other files are
+/// DOT files.
+fn parse_graph(
+    input_path: &str,
+    contents: &str,
+    options: &CLIOptions,
+) -> Result<gv::parser::ast::Graph, String> {
+    if input_path.ends_with(".json") {
+        return parse_jgf(contents);
+    }
+    let mut parser = DotParser::new(contents);
+    parser.set_lenient(options.lenient);
+    // Print all of the errors, with the lines that contain them, to the
+    // standard error, because the standard output may be the output.
+    let tree = parser.process().map_err(|_| {
+        for err in parser.errors() {
+            eprintln!("{}", err);
+        }
+        format!("Could not parse {}", input_path)
+    });
+    // Report the deviations that the lenient mode accepted, with the file
+    // that has them, because --diff reads two files.
+    for warning in parser.warnings() {
+        log::warn!("{}:{}", input_path, warning);
+    }
+    tree
+}
+
+/// Draw the graph in the file \p input_path as text in the terminal, and draw
+/// it again every time that the file or the width of the terminal changes,
+/// until the program is interrupted.
+fn watch(input_path: &str, options: &CLIOptions) {
+    let mut last_state = None;
+    loop {
+        let modified = fs::metadata(input_path).and_then(|x| x.modified());
+        let state = (modified.ok(), ASCIIWriter::terminal_width());
+        if Some(&state) != last_state.as_ref() {
+            last_state = Some(state);
+            // Clear the screen and move the cursor to the top-left corner.
+            print!("\x1b[2J\x1b[H");
+            let tree = fs::read_to_string(input_path)
+                .map_err(|err| format!("Can't open {}: {}", input_path, err))
+                .and_then(|contents| {
+                    parse_graph(input_path, &contents, options)
+                });
+            match tree {
+                Result::Ok(g) => {
+                    let mut vg = build_graph(&g, TextMetrics::Grid, options);
+                    let mut ascii = ASCIIWriter::new();
+                    ascii.fit_to_terminal();
+                    if let Option::Some(use_colors) = options.use_colors {
+                        ascii.set_use_colors(use_colors);
+                    }
+                    if options.unicode {
+                        ascii.set_char_set(CharSet::Unicode);
+                    }
+                    vg.layout(options.disable_opt, options.disable_layout);
+                    draw_graph(&vg, options, &mut ascii);
+                    print!("{}", ascii.finalize());
+                }
+                Result::Err(err) => println!("{}", err),
+            }
+            let _ = io::stdout().flush();
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+}
+
+/// Render the DOT files in the directory \p input_path, or the graphs in the
+/// standard input if it's "-", in \p format, to the directory \p output_dir.
+/// The outputs are named after the input files, and the graphs of files that
+/// have several graphs are numbered.
+fn render_batch(
+    input_path: &str,
+    output_dir: &str,
+    format: BatchFormat,
+    options: &CLIOptions,
+) -> Result<(), String> {
+    let mut docs: Vec<(String, String)> = Vec::new();
+    if input_path == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(|err| format!("Can't read the standard input: {}", err))?;
+        docs.push(("graph".to_string(), contents));
+    } else {
+        let entries = fs::read_dir(input_path)
+            .map_err(|err| format!("Can't open {}: {}", input_path, err))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|x| x.ok().map(|x| x.path()))
+            .filter(|x| x.extension().is_some_and(|x| x == "dot" || x == "gv"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let contents = fs::read_to_string(&path).map_err(|err| {
+                format!("Can't open {}: {}", path.display(), err)
+            })?;
+            docs.push((name, contents));
+        }
+    }
+
+    fs::create_dir_all(output_dir)
+        .map_err(|err| format!("Can't create {}: {}", output_dir, err))?;
+    let mut batch = BatchRenderer::new(format);
+    batch.set_lenient(options.lenient);
+    batch.set_theme(options.theme);
+    let mut num_errors = 0;
+    for out in batch.render(&docs) {
+        match out.output {
+            Result::Ok(content) => {
+                let name = if out.count > 1 {
+                    format!("{}-{}", out.name, out.index + 1)
+                } else {
+                    out.name
+                };
+                let pa =
+                    format!("{}/{}.{}", output_dir, name, format.extension());
+                write_output(&pa, content.as_bytes())?;
+            }
+            Result::Err(err) => {
+                log::error!("Could not parse {}:\n{}", out.name, err);
+                num_errors += 1;
+            }
+        }
+    }
+    if num_errors > 0 {
+        return Result::Err(format!(
+            "{} files could not be parsed",
+            num_errors
+        ));
+    }
+    Result::Ok(())
+}
+
+/// \returns the differences between the graph in the file \p path and the
+/// graph \p graph.
+fn read_diff(
+    path: &str,
+    graph: &gv::parser::ast::Graph,
+    options: &CLIOptions,
+) -> Result<GraphDiff, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Can't read {}: {}", path, err))?;
+    let old = parse_graph(path, &contents, options)?;
+    Ok(GraphDiff::new(&old, graph))
+}
+
+/// Build the visual graph of \p graph, with the labels measured by
+/// \p metrics, and the layout seed, quality and engine of \p options.
+fn build_graph(
+    graph: &gv::parser::ast::Graph,
+    metrics: TextMetrics,
+    options: &CLIOptions,
+) -> VisualGraph {
+    let mut gb = GraphBuilder::new();
+    gb.set_text_metrics(metrics);
+    gb.set_embed_images(options.embed_images);
+    gb.set_theme(options.theme);
+    gb.visit_graph(graph);
+    let mut vg = gb.get();
+    if let Option::Some(seed) = options.seed {
+        vg.set_seed(seed);
+    }
+    vg.set_quality(options.quality);
+    if let Option::Some(engine) = options.engine {
+        vg.set_layout_engine(engine);
+    }
+    for sheet in &options.stylesheets {
+        vg.add_stylesheet(sheet.clone());
+    }
+    vg
+}
+
+fn main() -> ExitCode {
     let matches = Command::new("Layout")
         .version("1.x")
         .arg(
@@ -97,11 +709,75 @@ fn main() {
                 .help("Disable the graph optimizations")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .help("Accept the common deviations from the DOT grammar, and warn about them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("embed-images")
+                .long("embed-images")
+                .help("Embed the images of the nodes in the SVG output")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("stylesheet")
+                .long("stylesheet")
+                .value_name("URL")
+                .help("Link the SVG and HTML output to a CSS stylesheet")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("css")
+                .long("css")
+                .value_name("FILE")
+                .help("Embed the CSS rules of FILE in the SVG and HTML output")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .value_name("NAME")
+                .help("Draw the graph with the default colors of a theme")
+                .value_parser(THEME_NAMES.to_vec())
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .value_name("FILE")
+                .help("Highlight the differences from the graph in FILE")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("focus")
+                .long("focus")
+                .value_name("NODES")
+                .help("Draw the neighborhood of the comma separated NODES")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("hops")
+                .long("hops")
+                .value_name("N")
+                .help("Draw the nodes up to N edges away from --focus")
+                .value_parser(clap::value_parser!(usize))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("context")
+                .long("context")
+                .value_name("MODE")
+                .help("Hide, fade or collapse the nodes outside of the focus")
+                .value_parser(["hide", "fade", "collapse"])
+                .num_args(1),
+        )
         .arg(
             Arg::new("a")
                 .short('a')
                 .long("ast")
-                .help("Dump the graph AST")
+                .help("Dump the graph AST to the standard output")
                 .action(ArgAction::SetTrue),
         )
         .arg(
@@ -111,6 +787,83 @@ fn main() {
                 .help("Path of the output file")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("png")
+                .long("png")
+                .value_name("FILE")
+                .help("Path of the output file")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("animate")
+                .long("animate")
+                .value_name("FILE")
+                .help("Animate the layout, as GIF for .gif files or as APNG")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("pdf")
+                .long("pdf")
+                .value_name("FILE")
+                .help("Path of the output file")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tikz")
+                .long("tikz")
+                .value_name("FILE")
+                .help("Path of the output file")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("eps")
+                .long("eps")
+                .value_name("FILE")
+                .help("Path of the output file")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("html")
+                .long("html")
+                .value_name("FILE")
+                .help("Path of the output file")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("jgf")
+                .long("jgf")
+                .value_name("FILE")
+                .help("Path of the output file, in the JSON Graph Format")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("dot")
+                .long("dot")
+                .value_name("FILE")
+                .help("Path of the output file, in DOT with the positions")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("xdot")
+                .long("xdot")
+                .value_name("FILE")
+                .help("Path of the output file, in DOT with xdot drawings")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("plain")
+                .long("plain")
+                .value_name("FILE")
+                .help("Path of the output file, in the plain text format")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("plain-ext")
+                .long("plain-ext")
+                .value_name("FILE")
+                .help("Path of the output file, in the plain format with ports")
+                .num_args(1),
+        )
         .arg(
             Arg::new("ascii")
                 .long("ascii")
@@ -118,15 +871,167 @@ fn main() {
                 .help("Path of the output file")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("terminal")
+                .short('t')
+                .long("terminal")
+                .help("Display the graph in the terminal")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help("Redraw the graph in the terminal when the file changes")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("batch")
+                .long("batch")
+                .value_name("DIR")
+                .help("Render the DOT files of the input directory to DIR")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Seed the heuristics that order the nodes")
+                .value_parser(clap::value_parser!(u64))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("quality")
+                .long("quality")
+                .value_name("LEVEL")
+                .help("Trade the speed of the layout for its quality")
+                .value_parser(["fast", "normal", "high"])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("format")
+                .short('T')
+                .long("format")
+                .value_name("FORMAT")
+                .help("Write the graph in FORMAT to the output of -o")
+                .value_parser([
+                    "svg",
+                    "png",
+                    "pdf",
+                    "eps",
+                    "tikz",
+                    "html",
+                    "json",
+                    "dot",
+                    "xdot",
+                    "plain",
+                    "plain-ext",
+                    "ascii",
+                ])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Path of the output file of -T, or - for stdout")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("engine")
+                .short('K')
+                .long("engine")
+                .value_name("ENGINE")
+                .help("Override the layout engine of the graph")
+                .value_parser([
+                    "dot", "fdp", "sfdp", "neato", "grid", "circo", "twopi",
+                    "tree", "timeline",
+                ])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .value_name("FACTOR")
+                .help("Scale the drawing")
+                .value_parser(clap::value_parser!(f64))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("dpi")
+                .long("dpi")
+                .value_name("DPI")
+                .help("Draw at DPI dots per inch, such as 96 for CSS pixels")
+                .value_parser(clap::value_parser!(f64))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("flip-y")
+                .long("flip-y")
+                .help("Flip the drawing, so that the y axis points up")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("translate")
+                .long("translate")
+                .value_name("X,Y")
+                .help("Move the drawing by X,Y, after scaling and flipping")
+                .value_parser(parse_point)
+                .allow_hyphen_values(true)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("tile-overlap")
+                .long("tile-overlap")
+                .value_name("POINTS")
+                .help("Overlap the pages of drawings that set 'page' by POINTS")
+                .value_parser(clap::value_parser!(f64))
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("crop-marks")
+                .long("crop-marks")
+                .help("Draw the crop marks on the pages of the drawing")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Use colors in the ASCII output")
+                .value_parser(["auto", "always", "never"])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("matrix")
+                .long("matrix")
+                .value_name("WHEN")
+                .help("Draw the SVG and ASCII outputs as an adjacency matrix")
+                .value_parser(["auto", "always", "never"])
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("unicode")
+                .short('u')
+                .long("unicode")
+                .help("Draw the ASCII output with box-drawing characters")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("INPUT")
-                .help("Sets the input file to use")
-                .required(true)
+                .help("Sets the input file to use, or - for stdin")
+                .default_value("-")
                 .index(1),
         )
         .get_matches();
 
-    env_logger::builder().format_timestamp(None).init();
+    // Show the warnings by default, and let RUST_LOG override the level.
+    env_logger::Builder::new()
+        .format_timestamp(None)
+        .filter_level(log::LevelFilter::Warn)
+        .parse_default_env()
+        .init();
 
     let dump_ast = matches.get_flag("a");
 
@@ -135,32 +1040,276 @@ fn main() {
     cli.disable_opt = matches.get_flag("no-optz");
     cli.disable_layout = matches.get_flag("no-layout");
     cli.svg_output_path = matches.get_one::<String>("svg").cloned();
+    cli.png_output_path = matches.get_one::<String>("png").cloned();
+    cli.animation_output_path = matches.get_one::<String>("animate").cloned();
+    cli.pdf_output_path = matches.get_one::<String>("pdf").cloned();
+    cli.tikz_output_path = matches.get_one::<String>("tikz").cloned();
+    cli.eps_output_path = matches.get_one::<String>("eps").cloned();
+    cli.html_output_path = matches.get_one::<String>("html").cloned();
+    cli.jgf_output_path = matches.get_one::<String>("jgf").cloned();
+    cli.dot_output_path = matches.get_one::<String>("dot").cloned();
+    cli.xdot_output_path = matches.get_one::<String>("xdot").cloned();
+    cli.plain_output_path = matches.get_one::<String>("plain").cloned();
+    cli.plain_ext_output_path = matches.get_one::<String>("plain-ext").cloned();
     cli.ascii_output_path = matches.get_one::<String>("ascii").cloned();
-    let input_path = matches.get_one::<String>("INPUT").unwrap();
-    let contents = fs::read_to_string(input_path).expect("Can't open the file");
-    let mut parser = DotParser::new(&contents);
+    cli.terminal_output = matches.get_flag("terminal");
+    cli.seed = matches.get_one::<u64>("seed").cloned();
+    cli.quality = match matches.get_one::<String>("quality").map(|x| x.as_str())
+    {
+        Option::Some("fast") => LayoutQuality::Fast,
+        Option::Some("high") => LayoutQuality::High,
+        _ => LayoutQuality::Normal,
+    };
+    cli.engine = matches
+        .get_one::<String>("engine")
+        .and_then(|x| LayoutEngine::from_name(x));
+    cli.scale = matches.get_one::<f64>("scale").cloned();
+    cli.dpi = matches.get_one::<f64>("dpi").cloned();
+    cli.flip_y = matches.get_flag("flip-y");
+    cli.translate = matches.get_one::<Point>("translate").cloned();
+    if let Option::Some(overlap) = matches.get_one::<f64>("tile-overlap") {
+        cli.tile_overlap = *overlap;
+    }
+    cli.crop_marks = matches.get_flag("crop-marks");
+    cli.use_colors =
+        match matches.get_one::<String>("color").map(|x| x.as_str()) {
+            Option::Some("always") => Some(true),
+            Option::Some("never") => Some(false),
+            _ => None,
+        };
+    cli.unicode = matches.get_flag("unicode");
+    cli.matrix = match matches.get_one::<String>("matrix").map(|x| x.as_str()) {
+        Option::Some("always") => Some(true),
+        Option::Some("auto") => None,
+        _ => Some(false),
+    };
+    if let Option::Some(name) = matches.get_one::<String>("theme") {
+        cli.theme = Theme::from_name(name).unwrap_or_default();
+    }
+    if let Option::Some(href) = matches.get_one::<String>("stylesheet") {
+        cli.stylesheets.push(Stylesheet::Link(href.clone()));
+    }
+    if let Option::Some(path) = matches.get_one::<String>("css") {
+        match fs::read_to_string(path) {
+            Result::Ok(css) => cli.stylesheets.push(Stylesheet::Inline(css)),
+            Result::Err(err) => {
+                log::error!("Error: Can't read {}: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
 
-    let tree = parser.process();
+    // The -T flag writes one of the formats to the output of -o, or to the
+    // standard output. The output of -o is SVG by default.
+    let output = matches.get_one::<String>("output").cloned();
+    let format = match matches.get_one::<String>("format") {
+        Option::Some(format) => Some(format.as_str()),
+        None => output.as_ref().map(|_| "svg"),
+    };
+    let output = Some(output.unwrap_or_else(|| "-".to_string()));
+    match format {
+        Option::Some("svg") => cli.svg_output_path = output,
+        Option::Some("png") => cli.png_output_path = output,
+        Option::Some("pdf") => cli.pdf_output_path = output,
+        Option::Some("eps") => cli.eps_output_path = output,
+        Option::Some("tikz") => cli.tikz_output_path = output,
+        Option::Some("html") => cli.html_output_path = output,
+        Option::Some("json") => cli.jgf_output_path = output,
+        Option::Some("dot") => cli.dot_output_path = output,
+        Option::Some("xdot") => cli.xdot_output_path = output,
+        Option::Some("plain") => cli.plain_output_path = output,
+        Option::Some("plain-ext") => cli.plain_ext_output_path = output,
+        Option::Some("ascii") => cli.ascii_output_path = output,
+        _ => {}
+    }
 
-    match tree {
+    cli.lenient = matches.get_flag("lenient");
+    cli.diff_base_path = matches.get_one::<String>("diff").cloned();
+    if let Option::Some(names) = matches.get_one::<String>("focus") {
+        cli.focus = names.split(',').map(|x| x.trim().to_string()).collect();
+    }
+    if let Option::Some(hops) = matches.get_one::<usize>("hops") {
+        cli.focus_config.hops = *hops;
+    }
+    if let Option::Some(mode) = matches.get_one::<String>("context") {
+        cli.focus_config.context = ContextMode::from_name(mode).unwrap();
+    }
+    cli.embed_images = matches.get_flag("embed-images");
+
+    // The AST is dumped to the standard output, and can't share it.
+    if dump_ast && cli.writes_to_stdout() {
+        log::error!("Error: -a can't be used with an output to stdout");
+        return ExitCode::FAILURE;
+    }
+
+    let input_path = matches.get_one::<String>("INPUT").unwrap();
+    if matches.get_flag("watch") {
+        if input_path == "-" {
+            log::error!("Error: --watch needs an input file");
+            return ExitCode::FAILURE;
+        }
+        watch(input_path, &cli);
+        return ExitCode::SUCCESS;
+    }
+    if let Option::Some(output_dir) = matches.get_one::<String>("batch") {
+        let format = matches.get_one::<String>("format").map_or("svg", |x| x);
+        let format = match BatchFormat::from_name(format) {
+            Option::Some(format) => format,
+            None => {
+                log::error!("Error: --batch can't write {} files", format);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Result::Err(err) =
+            render_batch(input_path, output_dir, format, &cli)
+        {
+            log::error!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    let contents = if input_path == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .map(|_| contents)
+            .map_err(|err| format!("Can't read the standard input: {}", err))
+    } else {
+        fs::read_to_string(input_path)
+            .map_err(|err| format!("Can't read {}: {}", input_path, err))
+    };
+    let contents = match contents {
+        Result::Ok(contents) => contents,
+        Result::Err(err) => {
+            log::error!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    match parse_graph(input_path, &contents, &cli) {
         Result::Err(err) => {
-            parser.print_error();
             log::error!("Error: {}", err);
+            ExitCode::FAILURE
         }
 
         Result::Ok(g) => {
             if dump_ast {
                 gv::dump_ast(&g);
             }
-            let mut gb = GraphBuilder::new();
-            gb.visit_graph(&g);
-            let mut vg = gb.get();
-            if cli.svg_output_path.is_some() {
-                generate_svg(&mut vg, &cli);
+            // Tell the user about the attributes that have no effect.
+            for ignored in gv::validate_attributes(&g) {
+                log::warn!("Ignored attribute: {}", ignored);
+            }
+            // Draw the graph with the differences from the old graph, and
+            // list them on the standard error.
+            let g = match &cli.diff_base_path {
+                Option::Some(path) => match read_diff(path, &g, &cli) {
+                    Result::Ok(diff) => {
+                        eprint!("{}", diff);
+                        diff.graph().clone()
+                    }
+                    Result::Err(err) => {
+                        log::error!("Error: {}", err);
+                        return ExitCode::FAILURE;
+                    }
+                },
+                None => g,
+            };
+            // Draw the neighborhood of the focused nodes.
+            let g = if cli.focus.is_empty() {
+                g
+            } else {
+                let names = find_nodes(&g, |name, _| {
+                    cli.focus.contains(&name.to_string())
+                });
+                for name in cli.focus.iter() {
+                    if !names.contains(name) {
+                        log::warn!("No node named {}", name);
+                    }
+                }
+                focus_graph(&g, &cli.focus, &cli.focus_config)
+            };
+            // Dense graphs are drawn as their adjacency matrix.
+            let matrix = AdjacencyMatrix::from_graph(&g);
+            let use_matrix = cli.matrix.unwrap_or_else(|| matrix.is_dense());
+            // Write all of the outputs, and report the ones that failed.
+            let mut results = Vec::new();
+            if use_matrix {
+                results.push(generate_matrix(&matrix, &cli));
+            }
+            // Lay out the graph once, and draw it on each of the backends,
+            // because the layout can't run again on the same graph. The
+            // animation shows the snapshots of the layout.
+            let mut vg = build_graph(&g, TextMetrics::Font, &cli);
+            let needs_layout = [
+                &cli.png_output_path,
+                &cli.pdf_output_path,
+                &cli.animation_output_path,
+                &cli.tikz_output_path,
+                &cli.eps_output_path,
+                &cli.html_output_path,
+                &cli.jgf_output_path,
+                &cli.dot_output_path,
+                &cli.xdot_output_path,
+                &cli.plain_output_path,
+                &cli.plain_ext_output_path,
+            ]
+            .iter()
+            .any(|x| x.is_some());
+            if needs_layout || (cli.svg_output_path.is_some() && !use_matrix) {
+                vg.set_record_frames(cli.animation_output_path.is_some());
+                vg.layout(cli.disable_opt, cli.disable_layout);
+            }
+            if cli.svg_output_path.is_some() && !use_matrix {
+                results.push(generate_svg(&vg, &cli));
+            }
+            if cli.png_output_path.is_some() {
+                results.push(generate_png(&vg, &cli));
+            }
+            if cli.pdf_output_path.is_some() {
+                results.push(generate_pdf(&vg, &cli));
+            }
+            if cli.animation_output_path.is_some() {
+                results.push(generate_animation(&vg, &cli));
+            }
+            if cli.tikz_output_path.is_some() {
+                results.push(generate_tikz(&vg, &cli));
+            }
+            if cli.eps_output_path.is_some() {
+                results.push(generate_eps(&vg, &cli));
+            }
+            if cli.html_output_path.is_some() {
+                results.push(generate_html(&vg, &cli));
+            }
+            if cli.jgf_output_path.is_some() {
+                results.push(generate_jgf(&g, &vg, &cli));
+            }
+            if cli.dot_output_path.is_some() {
+                results.push(generate_dot(&g, &vg, &cli));
+            }
+            if cli.xdot_output_path.is_some() {
+                results.push(generate_xdot(&g, &vg, &cli));
+            }
+            if cli.plain_output_path.is_some() {
+                results.push(generate_plain(&g, &vg, &cli, false));
+            }
+            if cli.plain_ext_output_path.is_some() {
+                results.push(generate_plain(&g, &vg, &cli, true));
+            }
+            // The text backends draw each character in a cell of the grid.
+            if cli.ascii_output_path.is_some() && !use_matrix {
+                let mut vg = build_graph(&g, TextMetrics::Grid, &cli);
+                vg.layout(cli.disable_opt, cli.disable_layout);
+                results.push(generate_ascii_art(&vg, &cli));
+            }
+            if cli.terminal_output {
+                results.push(generate_terminal(&g, &cli));
             }
-            if cli.ascii_output_path.is_some() {
-                generate_ascii_art(&mut vg, &cli);
+            let mut code = ExitCode::SUCCESS;
+            for err in results.into_iter().filter_map(Result::err) {
+                log::error!("Error: {}", err);
+                code = ExitCode::FAILURE;
             }
+            code
         }
     }
 }
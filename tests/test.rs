@@ -3,13 +3,43 @@ use layout::core::geometry::Point;
 #[cfg(test)]
 mod tests {
 
-    use layout::core::geometry::weighted_median;
+    use layout::adt::dag::NodeHandle;
+    use layout::backends::animation::{AnimationFormat, AnimationWriter};
+    use layout::backends::ascii_art::ASCIIWriter;
+    use layout::backends::html::HTMLWriter;
+    use layout::backends::pdf::PDFWriter;
+    use layout::backends::recording::{DrawCommand, RecordingBackend};
+    use layout::backends::scaled::{ScaledBackend, Transform};
+    use layout::backends::svg::{write_svg, SVGWriter};
+    use layout::backends::tiles::TileConfig;
+    use layout::core::base::{LayoutEngine, LayoutQuality, SplineKind};
+    use layout::core::color::Color;
+    use layout::core::format::{RenderBackend, Stylesheet};
+    use layout::core::geometry::{
+        cubic_bezier, do_boxes_intersect, get_bezier_segments, weighted_median,
+        Point,
+    };
+    use layout::core::style::{ArrowHeadKind, StyleAttr};
+    use layout::core::text::TextMetrics;
+    use layout::core::theme::Theme;
+    use layout::core::utils::format_number;
+    use layout::gv::diff::{DiffKind, GraphDiff};
+    use layout::gv::focus::{
+        find_nodes, focus_graph, ContextMode, FocusConfig, FocusDirection,
+    };
+    use layout::gv::jgf::{parse_jgf, write_jgf};
     use layout::gv::record::parse_record_string;
     use layout::gv::record::print_record;
     use layout::gv::DotParser;
+    use layout::gv::GraphBuilder;
     use layout::gv::Lexer;
     use layout::gv::Token;
-    use layout::std_shapes::shapes::RecordDef;
+    use layout::std_shapes::shapes::{
+        Element, PolygonKind, RecordDef, ShapeKind,
+    };
+    use layout::topo::layout::{ElementRef, VisualGraph};
+    use layout::topo::optimizer::OrderingConfig;
+    use layout::topo::placer::{OverlapMode, PageDir, Ratio};
 
     fn is_identifier(t: Token, target: &str) -> bool {
         match t {
@@ -17,6 +47,16 @@ mod tests {
             _ => false,
         }
     }
+    fn render_svg(program: &str) -> String {
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        svg.finalize()
+    }
+
     fn get_sample_program2() -> String {
         r##"/* ancestor graph from Caroline Bouvier Kennedy */
         graph G {
@@ -179,6 +219,87 @@ mod tests {
         panic!();
     }
 
+    #[test]
+    fn parse_program_errors() {
+        let program = "digraph {\n  a -> ;\n  b [color red];\n  c -> d;\n}";
+        let mut parser = DotParser::new(program);
+        let err = parser.process().unwrap_err();
+        assert_eq!((err.line, err.column, err.offset), (2, 8, 17));
+        assert_eq!(err.message, "Expected an identifier, found ';'");
+        assert_eq!(err.snippet, "  a -> ;\n       ^");
+
+        // The parser skips the statements with errors, and reports the
+        // errors that follow them.
+        let errors = parser.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], err);
+        assert_eq!((errors[1].line, errors[1].column), (3, 12));
+        assert_eq!(errors[1].expected, vec!["'='".to_string()]);
+        assert_eq!(
+            errors[1].to_string(),
+            "3:12: Expected '=', found 'red'\n  b [color red];\n           ^"
+        );
+
+        // Scopes that are not closed are reported at the end of the file.
+        let mut parser = DotParser::new("digraph { a -> b; subgraph { c");
+        let err = parser.process().unwrap_err();
+        assert_eq!(err.column, 31);
+        assert!(err.message.ends_with("found the end of the file"));
+        assert_eq!(parser.errors().len(), 1);
+
+        // Characters that are not tokens are skipped.
+        let mut parser =
+            DotParser::new("digraph { a -> b; $ c; d [x=\"1\" ? ]; }");
+        assert!(parser.process().is_err());
+        assert_eq!(parser.errors().len(), 2);
+        assert!(parser.errors()[0].message.ends_with("an invalid token"));
+    }
+
+    #[test]
+    fn parse_program_lenient() {
+        use layout::gv::parser::ast;
+        let program = "digraph {\n  a [shape=box,, filled];\n  b -> node;\n  \
+                       c -> ;\n  d [label=edge]\n";
+        let mut parser = DotParser::new(program);
+        assert!(parser.process().is_err());
+
+        // The lenient mode accepts the deviations, and reports them.
+        let mut parser = DotParser::new(program);
+        parser.set_lenient(true);
+        let graph = parser.process().unwrap();
+        assert!(parser.errors().is_empty());
+        let messages: Vec<String> = parser
+            .warnings()
+            .iter()
+            .map(|x| x.message.clone())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unexpected ','",
+                "The attribute 'filled' has no value, and is true",
+                "The keyword 'node' is used as an identifier",
+                "Expected an identifier, found ';'",
+                "The keyword 'edge' is used as an identifier",
+                "Expected '}' at the end of the file",
+            ]
+        );
+        assert_eq!(parser.warnings()[0].line, 2);
+
+        // The statement with the error is skipped.
+        assert_eq!(graph.list.list.len(), 3);
+        if let ast::Stmt::Node(node) = &graph.list.list[0] {
+            assert_eq!(node.list.list[1], ("filled".into(), "true".into()));
+        } else {
+            panic!("Expected a node statement");
+        }
+        if let ast::Stmt::Edge(edge) = &graph.list.list[1] {
+            assert_eq!(edge.to[0].0.name, "node");
+        } else {
+            panic!("Expected an edge statement");
+        }
+    }
+
     #[test]
     fn parse_record0() {
         let desc = "hello&#92;nworld |{ b |{c|<here> d|e}| f}| g | h";
@@ -221,6 +342,2983 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_record_fields() {
+        let text = |s: &str| RecordDef::Text(s.to_string(), None);
+        // Empty fields are kept, and escaped characters are part of the label.
+        let res = parse_record_string("a\\|b||{c|}|\\<d\\>");
+        let expected = RecordDef::Array(vec![
+            text("a|b"),
+            text(""),
+            RecordDef::Array(vec![text("c"), text("")]),
+            text("<d>"),
+        ]);
+        assert_eq!(format!("{:?}", res), format!("{:?}", expected));
+        let res = parse_record_string("");
+        let expected = RecordDef::Array(vec![text("")]);
+        assert_eq!(format!("{:?}", res), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn parse_record_escapes_and_compass_ports() {
+        let program = r#"digraph {
+            a [shape=record, label="<p> x\{y\}|z"];
+            b [label="\{b\}"];
+            a:p:s -> b:n;
+        }"#;
+        let svg = render_svg(program);
+        assert!(svg.contains(">x{y}</tspan>"));
+        assert!(svg.contains(">{b}</tspan>"));
+    }
+
+    #[test]
+    fn test_compass_ports() {
+        let program = "digraph { a:s -> b:n; b:e -> c:w; \
+            r [shape=record, label=\"<f0> x|<f1> y\"]; c:_ -> r:f1:s; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        let paths: Vec<&Vec<(Point, Point)>> = rec
+            .commands()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Arrow { path, .. } => Some(path),
+                _ => None,
+            })
+            .collect();
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        let close = |p: Point, q: Point| p.distance_to(q) < 0.01;
+
+        // a:s -> b:n leaves the bottom of a, and enters the top of b.
+        let path = paths[0];
+        assert!(close(path[0].0, Point::new(center(0).x, bbox(0).1.y)));
+        let end = path.last().unwrap();
+        assert!(close(end.1, Point::new(center(1).x, bbox(1).0.y)));
+        assert!(end.0.y < end.1.y);
+
+        // b:e -> c:w connects the sides.
+        let path = paths[1];
+        assert!(close(path[0].0, Point::new(bbox(1).1.x, center(1).y)));
+        let end = path.last().unwrap();
+        assert!(close(end.1, Point::new(bbox(2).0.x, center(2).y)));
+
+        // The compass point of a record port applies to the field.
+        let end = paths[2].last().unwrap().1;
+        assert!(end.x > center(3).x && (end.y - bbox(3).1.y).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_rank_constraints() {
+        let program = "digraph { a -> b -> c; a -> x; x -> y; \
+            { rank=same; c; x; } { rank=source; s; } { rank=sink; z; } \
+            { rank=same; b; q; } b -> q [label=\"l\"]; q -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // The nodes are created in the order a, b, c, x, y, s, z, q.
+        let y = |i: usize| vg.pos(NodeHandle::new(i)).center().y;
+        assert_eq!(y(2), y(3));
+        assert_eq!(y(1), y(7));
+        for i in (0..8).filter(|i| *i != 5) {
+            assert!(y(5) < y(i));
+        }
+        for i in (0..7).filter(|i| *i != 6) {
+            assert!(y(i) < y(6));
+        }
+
+        // The label of the edge between nodes on the same rank stays on the
+        // edge.
+        assert!(rec.commands().iter().any(|cmd| {
+            matches!(cmd, DrawCommand::Arrow { text, .. } if text == "l")
+        }));
+    }
+
+    #[test]
+    fn test_self_loops() {
+        let program = "digraph { a -> a [label=\"first\"]; a -> a; a -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // The arrows are drawn in the groups of the edges.
+        let mut group: Option<&String> = None;
+        let mut loops: Vec<&Vec<(Point, Point)>> = Vec::new();
+        for cmd in rec.commands() {
+            match cmd {
+                DrawCommand::BeginGroup { properties } => {
+                    group = Some(properties)
+                }
+                DrawCommand::EndGroup => group = None,
+                DrawCommand::Arrow { path, .. }
+                    if group.is_some_and(|p| p.contains("data-to=\"a\"")) =>
+                {
+                    loops.push(path)
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(loops.len(), 2);
+        let label = rec
+            .commands()
+            .iter()
+            .find_map(|cmd| match cmd {
+                DrawCommand::Text { xy, text, .. } if text == "first" => {
+                    Some(*xy)
+                }
+                _ => None,
+            })
+            .unwrap();
+
+        // The loops leave and enter the right side of the node, and the
+        // arrow heads point back into the node.
+        let bbox = vg.pos(NodeHandle::new(0)).bbox(false);
+        let center = vg.pos(NodeHandle::new(0)).center();
+        for path in &loops {
+            let start = path[0].0;
+            let end = path.last().unwrap();
+            assert!(start.x > center.x && start.x <= bbox.1.x + 0.01);
+            assert!(start.y < center.y && end.1.y > center.y);
+            assert!(end.0.x > end.1.x);
+        }
+
+        // The second loop goes around the label of the first loop.
+        let tip = |path: &Vec<(Point, Point)>| path[0].1.x;
+        assert!(tip(loops[0]) < tip(loops[1]));
+        assert!(bbox.1.x < label.x);
+        assert!(label.x < tip(loops[1]));
+    }
+
+    #[test]
+    fn test_state_machine() {
+        use layout::fsm::StateMachine;
+
+        let mut fsm = StateMachine::new();
+        fsm.initial("q0").accepting("q2");
+        fsm.transition("q0", "q1", "a");
+        fsm.transition("q0", "q2", "b");
+        fsm.transition("q1", "q1", "a");
+        fsm.transition("q1", "q1", "b");
+        fsm.transition("q1", "q2", "b");
+        let mut vg = fsm.build();
+        vg.layout(false, false);
+        let res = vg.layout_result();
+
+        // The invisible node of the initial arrow is before the initial
+        // state, and the accepting state is a double circle.
+        let center = |i: usize| res.nodes[i].0.add(res.nodes[i].1).scale(0.5);
+        assert!(center(0).x < center(1).x);
+        assert!(matches!(
+            vg.element(NodeHandle::new(2)).shape,
+            ShapeKind::DoubleCircle(_)
+        ));
+
+        // The symbols of the loop share one edge, that goes around the free
+        // side of the state, away from the next node in the rank.
+        assert_eq!(res.edges.len(), 5);
+        let edge = &res.edges[3];
+        assert!(matches!(
+            &vg.element(NodeHandle::new(3)).shape,
+            ShapeKind::Circle(name) if name == "q1"
+        ));
+        let top = edge
+            .path
+            .iter()
+            .flat_map(|s| [s.0, s.1, s.2, s.3])
+            .fold(f64::INFINITY, |acc, p| acc.min(p.y));
+        assert!(top < res.nodes[3].0.y);
+        let mut svg = SVGWriter::new();
+        vg.draw(false, &mut svg);
+        assert!(svg.finalize().contains("a, b"));
+    }
+
+    #[test]
+    fn test_render_tree() {
+        use layout::tree::{render_tree_ascii, render_tree_svg, TreeNode};
+
+        struct Dir(&'static str, Vec<Dir>);
+        impl TreeNode for Dir {
+            fn label(&self) -> String {
+                self.0.to_string()
+            }
+            fn children(&self) -> Vec<&Self> {
+                self.1.iter().collect()
+            }
+        }
+        let file = |name| Dir(name, Vec::new());
+        let root = Dir(
+            "src",
+            vec![Dir("bin", vec![file("main.rs")]), file("lib.rs")],
+        );
+
+        let svg = render_tree_svg(&root);
+        assert_eq!(svg.matches("class=\"node\"").count(), 4);
+        assert!(svg.contains("id=\"n0-&gt;n1\""));
+        assert!(svg.contains("main.rs"));
+
+        // The root is in the first rows of the ASCII drawing, and the leaves
+        // are in the last rows.
+        let text = render_tree_ascii(&root);
+        let row = |name: &str| text.lines().position(|x| x.contains(name));
+        assert!(row("src").is_some());
+        assert!(row("src") < row("lib.rs"));
+        assert!(row("lib.rs") < row("main.rs"));
+    }
+
+    #[test]
+    fn test_er_diagrams() {
+        use layout::builder;
+        use layout::er::{Cardinality, Key, TableNode};
+        use layout::gv::validate_attributes;
+
+        let program = "digraph { rankdir=LR; \
+            users [shape=table, columns=\"id int PK; email text UK\"]; \
+            posts [shape=table, label=Posts, \
+                columns=\"id int PK; title text; user_id int FK\"]; \
+            users:id -> posts:user_id [cardinality=\"1:0..*\"]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        assert!(validate_attributes(&graph).is_empty());
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let content = svg.finalize();
+        assert!(content.contains("id=\"startteetee100\""));
+        assert!(content.contains("id=\"endcrowodot100\""));
+        assert!(content.contains(">Posts<"));
+
+        // The edge joins the rows of the columns, and the row of the foreign
+        // key is the last row of the table.
+        let res = vg.layout_result();
+        let path = &res.edges[0].path;
+        let end = path[path.len() - 1].3;
+        let posts = res.nodes[1];
+        assert!(end.y > (posts.0.y + posts.1.y) / 2.);
+        assert!((end.x - posts.0.x).abs() < 2.);
+
+        // The tables and the edges of the builder are written as DOT.
+        let mut table = TableNode::new("users");
+        table.column("id", "int").key("id", Key::Primary);
+        let mut graph = builder::GraphBuilder::new();
+        graph.node("users").table(&table);
+        graph
+            .edge("users", "posts")
+            .cardinality(Cardinality::One, Cardinality::OneOrMany);
+        let text = graph.to_dot(None);
+        assert!(text.contains("users [shape=table, columns=\"id int PK\"];"));
+        assert!(text.contains(
+            "users -> posts [dir=both, arrowtail=teetee, arrowhead=crowtee];"
+        ));
+        let vg = graph.build();
+        let elem = vg.element(NodeHandle::new(0));
+        assert!(matches!(elem.shape, ShapeKind::Html(_)));
+    }
+
+    #[test]
+    fn test_uml_classes() {
+        use layout::builder;
+        use layout::core::base::Orientation;
+        use layout::uml::{ClassNode, Relation};
+
+        for dir in [Orientation::TopToBottom, Orientation::LeftToRight] {
+            let mut graph = builder::GraphBuilder::new();
+            graph.orientation(dir);
+            let mut shape = ClassNode::new("Shape");
+            shape.field("+ name: String").method("+ area(): f64");
+            graph.node("Shape").class(&shape);
+            let mut circle = ClassNode::new("Circle");
+            circle.field("- radius: f64");
+            graph.node("Circle").class(&circle);
+            graph.node("Canvas").class(&ClassNode::new("Canvas"));
+            graph
+                .edge("Shape", "Circle")
+                .relation(Relation::Inheritance);
+            graph
+                .edge("Canvas", "Shape")
+                .relation(Relation::Composition);
+
+            let mut vg = graph.build();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+
+            // The compartments are stacked from top to bottom, in the width
+            // of the box.
+            let rects: Vec<(Point, Point)> = rec
+                .commands()
+                .iter()
+                .filter_map(|x| match x {
+                    DrawCommand::Rect { xy, size, .. } => Some((*xy, *size)),
+                    _ => None,
+                })
+                .collect();
+            let bbox = vg.pos(NodeHandle::new(0)).bbox(false);
+            let parts: Vec<_> = rects
+                .iter()
+                .filter(|x| x.0.x == bbox.0.x && x.0.y >= bbox.0.y)
+                .filter(|x| x.0.y + x.1.y <= bbox.1.y + 0.01)
+                .filter(|x| x.1.y < bbox.1.y - bbox.0.y)
+                .collect();
+            assert_eq!(parts.len(), 3);
+
+            // The heads of the relations are at the general class and at the
+            // whole.
+            let mut svg = SVGWriter::new();
+            vg.draw(false, &mut svg);
+            let content = svg.finalize();
+            assert!(content.contains("id=\"startonormal100\""));
+            assert!(content.contains("id=\"startdiamond100\""));
+            assert!(content.contains("+ area(): f64"));
+        }
+    }
+
+    #[test]
+    fn test_spline_kinds() {
+        let render = |splines: &str| {
+            let program = format!(
+                "digraph {{ splines={}; a -> b -> c; a -> c; }}",
+                splines
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+            let paths: Vec<Vec<Point>> = rec
+                .commands()
+                .iter()
+                .filter_map(|cmd| match cmd {
+                    DrawCommand::Arrow { path, .. } => Some(path),
+                    _ => None,
+                })
+                .map(|path| {
+                    get_bezier_segments(path)
+                        .iter()
+                        .flat_map(|seg| [seg.0, seg.1, seg.2, seg.3])
+                        .collect()
+                })
+                .collect();
+            (vg.splines(), paths)
+        };
+        let is_straight = |seg: &[Point]| {
+            let d = seg[3].sub(seg[0]);
+            seg.iter().all(|p| {
+                let v = p.sub(seg[0]);
+                (v.x * d.y - v.y * d.x).abs() < 0.01
+            })
+        };
+
+        let (kind, paths) = render("ortho");
+        assert_eq!(kind, SplineKind::Ortho);
+        for path in &paths {
+            for seg in path.chunks(4) {
+                assert!(is_straight(seg));
+                let d = seg[3].sub(seg[0]);
+                assert!(d.x.abs() < 0.01 || d.y.abs() < 0.01);
+            }
+        }
+
+        // The long edge a -> c goes through a connector in polyline mode,
+        // and straight to c in line mode.
+        let (kind, paths) = render("polyline");
+        assert_eq!(kind, SplineKind::Polyline);
+        assert!(paths.iter().all(|p| p.chunks(4).all(is_straight)));
+        assert_eq!(paths[2].len(), 8);
+        let (kind, paths) = render("line");
+        assert_eq!(kind, SplineKind::Line);
+        assert!(paths.iter().all(|p| p.len() == 4 && is_straight(p)));
+
+        let (kind, paths) = render("curved");
+        assert_eq!(kind, SplineKind::Curved);
+        assert!(paths.iter().all(|p| p.len() == 4 && !is_straight(p)));
+        assert_eq!(render("true").0, SplineKind::Spline);
+    }
+
+    #[test]
+    fn test_zero_size_nodes() {
+        // Nodes are at least 0.01in wide and high, and every engine connects
+        // the edges to them.
+        for engine in [
+            "dot", "fdp", "neato", "twopi", "circo", "grid", "tree", "timeline",
+        ] {
+            let program = format!(
+                "digraph {{ layout={}; \
+                 a [width=0, height=0, fixedsize=true]; a -> b; }}",
+                engine
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let a = vg.iter_nodes().next().unwrap();
+            let size = vg.pos(a).size(false);
+            assert!((size.x - 0.72).abs() < 1e-9, "{}", engine);
+            assert!((size.y - 0.72).abs() < 1e-9, "{}", engine);
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            assert!(!svg.finalize().contains("NaN"), "{}", engine);
+        }
+    }
+
+    #[test]
+    fn test_force_layout() {
+        let program = "graph { layout=fdp; a -- b -- c -- a; c -- d; \
+            d -- d [label=\"self\"]; e; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::ForceDirected);
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // The nodes don't overlap, and the graph starts at the origin.
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..5 {
+            assert!(bbox(i).0.x >= 0. && bbox(i).0.y >= 0.);
+            for j in i + 1..5 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+
+        // Connected nodes are closer than the node that is not connected.
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        let dist = |i: usize, j: usize| center(i).distance_to(center(j));
+        assert!(dist(0, 1) < dist(0, 4).max(dist(1, 4)));
+        assert!(dist(2, 3) < 200.);
+
+        // All of the edges are drawn, and the label stays on the edge.
+        let arrows: Vec<&String> = rec
+            .commands()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Arrow { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arrows.len(), 5);
+        assert!(rec.commands().iter().any(|cmd| {
+            matches!(cmd, DrawCommand::Text { text, .. } if text == "self")
+        }));
+    }
+
+    #[test]
+    fn test_stress_layout() {
+        // A grid of 3x3 nodes, and a node that is not connected.
+        let program = "graph { layout=neato; maxiter=500; epsilon=0.00001; \
+            a -- b -- c; d -- e -- f; g -- h -- i; \
+            a -- d -- g; b -- e -- h; c -- f -- i; j; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Stress);
+        assert_eq!(vg.stress_config().max_iterations, 500);
+        assert_eq!(vg.stress_config().epsilon, 0.00001);
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // The grid keeps its shape: the edges have about the same length, and
+        // the middle node is in the middle.
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        let dist = |i: usize, j: usize| center(i).distance_to(center(j));
+        let len = dist(0, 1);
+        for (i, j) in [(1, 2), (3, 4), (6, 7), (0, 3), (4, 7), (5, 8)] {
+            assert!((dist(i, j) - len).abs() < len * 0.25);
+        }
+        assert!((dist(0, 4) - dist(2, 4)).abs() < len * 0.25);
+        assert!((dist(6, 4) - dist(8, 4)).abs() < len * 0.25);
+        assert!(dist(0, 8) > dist(0, 4) * 1.5);
+
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..10 {
+            assert!(bbox(i).0.x >= 0. && bbox(i).0.y >= 0.);
+            for j in i + 1..10 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlap_removal() {
+        // Nodes with wide labels, in a stress layout.
+        let make = |attrs: &str| {
+            let program = format!(
+                "graph {{ layout=neato; {} node [shape=box]; \
+                 a [label=\"a node with a very long label\"]; \
+                 b [label=\"another node with a long label\"]; \
+                 c [label=\"and a third wide node\"]; \
+                 a -- b -- c -- a; c -- d; }}",
+                attrs
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            vg.layout(false, false);
+            vg
+        };
+        let has_overlaps = |vg: &VisualGraph| {
+            let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+            (0..4).any(|i| {
+                (i + 1..4).any(|j| do_boxes_intersect(bbox(i), bbox(j)))
+            })
+        };
+        for attrs in ["", "overlap=false;", "overlap=prism;", "overlap=scale;"]
+        {
+            assert!(!has_overlaps(&make(attrs)), "{}", attrs);
+        }
+        let mode = |attrs: &str| make(attrs).overlap_config().mode;
+        assert_eq!(mode(""), OverlapMode::Circles);
+        assert_eq!(mode("overlap=scale;"), OverlapMode::Scale);
+        assert_eq!(mode("overlap=true;"), OverlapMode::Keep);
+        assert_eq!(mode("overlap=false;"), OverlapMode::Remove);
+    }
+
+    #[test]
+    fn test_size_and_ratio() {
+        let make = |attrs: &str| {
+            let program = format!(
+                "digraph {{ {} node [shape=box]; a -> b -> c; a -> c; \
+                 d -> c; e -> c; }}",
+                attrs
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            gb.get()
+        };
+        let draw = |attrs: &str| {
+            let mut vg = make(attrs);
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            let svg = svg.finalize();
+            let number = |name: &str| -> f64 {
+                let start = svg.find(name).unwrap() + name.len();
+                let end = start + svg[start..].find('"').unwrap();
+                svg[start..end].parse().unwrap()
+            };
+            Point::new(number("width=\""), number("height=\""))
+        };
+        let extent = |vg: &VisualGraph| {
+            let (top_left, bottom_right) = vg.layout_result().bbox;
+            bottom_right.sub(top_left)
+        };
+
+        // Large drawings are scaled down to the size, that is in inches. The
+        // SVG writer adds 5 points around the drawing.
+        let full = draw("");
+        let small = draw("size=\"1,1\";");
+        assert!(small.x <= 77.01 && small.y <= 77.01);
+        assert!(small.x.max(small.y) > 70.);
+        let scale = (small.y - 5.) / (full.y - 5.);
+        assert!(((small.x - 5.) / (full.x - 5.) - scale).abs() < 0.01);
+        // Small drawings are only scaled up with '!'.
+        assert_eq!(draw("size=\"100,100\";"), full);
+        let large = draw("size=\"10,10!\";");
+        assert!(large.x > 700. && large.x <= 725.01);
+        // The pages round the canvas up.
+        assert_eq!(draw("page=\"5,5\";"), Point::new(365., 365.));
+
+        // The ratio stretches the layout.
+        let mut vg = make("ratio=2;");
+        vg.layout(false, false);
+        let size = extent(&vg);
+        assert!((size.y / size.x - 2.).abs() < 0.02);
+        let mut vg = make("ratio=0.5;");
+        vg.layout(false, false);
+        let size = extent(&vg);
+        assert!((size.y / size.x - 0.5).abs() < 0.02);
+
+        // The compression moves the nodes closer, but they don't overlap.
+        let mut vg = make("");
+        vg.layout(false, false);
+        let before = extent(&vg);
+        let mut vg = make("ratio=compress; size=\"0.5,0.5\";");
+        vg.layout(false, false);
+        let after = extent(&vg);
+        assert!(after.y < before.y);
+        assert!(after.x <= before.x);
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..5 {
+            for j in i + 1..5 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+        assert_eq!(vg.size_config().ratio, Some(Ratio::Compress));
+    }
+
+    #[test]
+    fn test_page_tiles() {
+        let program = "digraph { page=\"1.5,1\"; pagedir=TL; \
+            node [shape=box]; a -> b -> c -> d; a -> e; a -> f; a -> g; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(
+            vg.size_config().page_dir,
+            PageDir::from_name("TL").unwrap()
+        );
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+
+        // The canvas is a whole number of pages, that the tiles cover in
+        // rows from the top left.
+        let page = Point::new(108., 72.);
+        let canvas = vg.output_size().unwrap();
+        let columns = (canvas.x / page.x).round();
+        let rows = (canvas.y / page.y).round();
+        assert!(rows > 1.);
+        let mut config = TileConfig::new(page);
+        config.order = vg.size_config().page_dir;
+        config.crop_marks = true;
+        let tiles = config.get_tiles(canvas);
+        assert_eq!(tiles.len() as f64, columns * rows);
+        assert_eq!(tiles[0], Point::zero());
+        assert_eq!(tiles[1], Point::new(108., 0.));
+
+        // Each page shows its part of the drawing, with the crop marks.
+        let pages = svg.finalize_tiles(&config, &tiles);
+        assert_eq!(pages.len(), tiles.len());
+        assert!(pages[1].contains("viewBox=\"108 0 108 72\""));
+        assert_eq!(pages[1].matches("stroke-width=\"0.5\"").count(), 8);
+        assert!(pages.iter().all(|x| x.contains("<rect")));
+
+        // The PDF writer puts the pages in one document.
+        let mut pdf = PDFWriter::new();
+        vg.draw(false, &mut pdf);
+        let bytes = pdf.finalize_tiles(&config, &tiles);
+        let text = String::from_utf8_lossy(&bytes);
+        let count = format!("/Count {} >>", tiles.len());
+        assert!(text.contains(&count));
+        assert_eq!(text.matches("/MediaBox [0 0 108 72]").count(), tiles.len());
+    }
+
+    #[test]
+    fn test_layout_animation() {
+        let programs = [
+            ("digraph { a -> b -> c; a -> c; b -> d; }", "ordering"),
+            ("graph { layout=fdp; a -- b -- c -- a; c -- d; }", "force"),
+            (
+                "graph { layout=neato; a -- b -- c -- a; c -- d; }",
+                "stress",
+            ),
+        ];
+        for (program, stage) in programs {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+
+            // The graph records nothing by default.
+            let mut vg = gb.get();
+            vg.layout(false, false);
+            assert!(vg.frames().is_empty());
+
+            let mut vg = gb.get();
+            vg.set_record_frames(true);
+            vg.layout(false, false);
+            let frames = vg.frames();
+            assert!(frames.len() > 2, "{}", stage);
+            assert!(frames.iter().any(|x| x.stage == stage));
+            assert_eq!(frames.last().unwrap().stage, "final");
+            assert!(frames.iter().all(|x| x.centers.len() == 4));
+
+            // A frame for each step, and the final drawing.
+            let mut anim = AnimationWriter::new();
+            anim.add_layout(&vg, 50);
+            assert_eq!(anim.num_frames(), frames.len());
+
+            let apng = anim.finalize(AnimationFormat::Apng);
+            assert!(apng.starts_with(b"\x89PNG"));
+            let actl = apng.windows(4).position(|x| x == b"acTL").unwrap();
+            let count = &apng[actl + 4..actl + 8];
+            assert_eq!(count, (frames.len() as u32).to_be_bytes());
+            let gif = anim.finalize(AnimationFormat::Gif);
+            assert!(gif.starts_with(b"GIF89a"));
+            assert_eq!(gif.last(), Some(&b';'));
+        }
+    }
+
+    #[test]
+    fn test_graph_diff() {
+        let old = "digraph { a -> b; b -> c; subgraph cluster_x { c; } }";
+        let new = "digraph { a -> b [label=\"x\"]; a -> d; \
+            subgraph cluster_x { d; } }";
+        let old = DotParser::new(old).process().unwrap();
+        let new = DotParser::new(new).process().unwrap();
+        let diff = GraphDiff::new(&old, &new);
+        let kinds: Vec<DiffKind> = diff.nodes.iter().map(|x| x.kind).collect();
+        assert_eq!(kinds, vec![DiffKind::Added, DiffKind::Removed]);
+        assert_eq!(diff.edges.len(), 3);
+        assert_eq!(diff.edges[0].changes[0].name, "label");
+
+        // The combined graph keeps the clusters of the new graph, and draws
+        // the elements that differ in their colors.
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(diff.graph());
+        let mut vg = gb.get();
+        assert_eq!(vg.num_nodes(), 4);
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let content = svg.finalize();
+        for kind in [DiffKind::Added, DiffKind::Removed, DiffKind::Changed] {
+            assert!(content.contains(kind.color()));
+        }
+        assert!(content.contains("stroke-dasharray"));
+    }
+
+    #[test]
+    fn test_layout_metrics() {
+        let get_metrics = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            vg.layout(false, false);
+            vg.metrics()
+        };
+
+        // Four ranks, and the ordering removes the crossings.
+        let program = "digraph { a -> b; a -> c; b -> d; c -> e; b -> e; \
+            c -> d; d -> f; }";
+        let metrics = get_metrics(program);
+        assert_eq!(metrics.num_nodes, 6);
+        assert_eq!(metrics.num_edges, 7);
+        assert_eq!(metrics.num_layers, Some(4));
+        assert!(metrics.total_edge_length > 0.);
+        assert!(metrics.aspect_ratio < 1.);
+        assert_eq!(metrics.bends, 0);
+
+        // The edges of K3,3 cross in any drawing.
+        let program = "graph { layout=neato; a -- x; a -- y; a -- z; \
+            b -- x; b -- y; b -- z; c -- x; c -- y; c -- z; }";
+        let metrics = get_metrics(program);
+        assert_eq!(metrics.num_layers, None);
+        assert!(metrics.edge_crossings > 0);
+
+        // Orthogonal edges turn at right angles.
+        let program = "digraph { splines=ortho; a -> b; a -> c; a -> d; }";
+        let metrics = get_metrics(program);
+        assert!(metrics.bends >= 4);
+    }
+
+    #[test]
+    fn test_focus_graph() {
+        // A chain of 20 nodes, with a branch at n10.
+        let mut program = String::from("digraph {");
+        for i in 0..19 {
+            program.push_str(&format!("n{} -> n{}; ", i, i + 1));
+        }
+        program.push_str("n10 -> leaf [color=red]; }");
+        let graph = DotParser::new(&program).process().unwrap();
+        let roots = find_nodes(&graph, |_, attrs| attrs.is_empty());
+        assert_eq!(roots.len(), 21);
+        let roots = vec!["n10".to_string()];
+
+        // Only the neighborhood is laid out.
+        let mut config = FocusConfig::new(2);
+        let focus = focus_graph(&graph, &roots, &config);
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&focus);
+        let mut vg = gb.get();
+        assert_eq!(vg.num_nodes(), 6);
+        vg.layout(false, false);
+        assert_eq!(vg.layout_result().edges.len(), 5);
+
+        // The successors of the node, and the rest of the graph collapsed.
+        config.direction = FocusDirection::Out;
+        config.context = ContextMode::Collapse;
+        let focus = focus_graph(&graph, &roots, &config);
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&focus);
+        let mut svg = SVGWriter::new();
+        let mut vg = gb.get();
+        assert_eq!(vg.num_nodes(), 6);
+        vg.do_it(false, false, false, &mut svg);
+        let content = svg.finalize();
+        assert!(content.contains("10 more nodes"));
+        assert!(content.contains("7 more nodes"));
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let program = "digraph { layout=grid; columns=3; cellpadding=5; \
+            a; b; c; d [label=\"a wide node\"]; e; a -> e; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Grid);
+        assert_eq!(vg.grid_config().columns, Some(3));
+        assert_eq!(vg.grid_config().padding, 5.);
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // The nodes are placed in rows of three, and the columns are aligned.
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        assert_eq!(center(0).y, center(1).y);
+        assert_eq!(center(1).y, center(2).y);
+        assert_eq!(center(3).y, center(4).y);
+        assert!(center(3).y > center(0).y);
+        assert_eq!(center(0).x, center(3).x);
+        assert_eq!(center(1).x, center(4).x);
+        assert!(center(0).x < center(1).x && center(1).x < center(2).x);
+
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..5 {
+            for j in i + 1..5 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+        assert!(bbox(1).0.x - bbox(3).1.x >= 10.);
+    }
+
+    #[test]
+    fn test_timeline_layout() {
+        use layout::builder;
+        use layout::gv::validate_attributes;
+
+        let program = "digraph { layout=timeline; node [shape=box]; \
+            parse [start=0, end=4]; lex [start=0, end=2]; \
+            check [start=3, end=9]; release [start=9]; notes; \
+            parse -> check; }";
+        let graph = DotParser::new(program).process().unwrap();
+        assert!(validate_attributes(&graph).is_empty());
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Timeline);
+        assert_eq!(vg.node_span(NodeHandle::new(3)), Some((9., 9.)));
+        assert_eq!(vg.node_span(NodeHandle::new(4)), None);
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let content = svg.finalize();
+
+        // The bars are as long as their intervals, and the overlapping bars
+        // are in different rows.
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        let width = |i: usize| bbox(i).1.x - bbox(i).0.x;
+        assert!((width(0) - 2. * width(1)).abs() < 0.001);
+        assert!((bbox(0).0.x - bbox(1).0.x).abs() < 0.001);
+        assert!(bbox(0).0.y != bbox(1).0.y);
+        assert!(bbox(0).0.y != bbox(2).0.y);
+        for i in 0..5 {
+            for j in i + 1..5 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+        // The milestone is centered on its time, and the node without an
+        // interval is below the others.
+        let axis = vg.time_axis().unwrap();
+        let scale = width(1) / 2.;
+        let release = vg.pos(NodeHandle::new(3)).center().x;
+        assert!((release - (axis.left + 9. * scale)).abs() < 0.001);
+        assert!((0..4).all(|i| bbox(4).0.y > bbox(i).1.y));
+
+        // The axis is above the nodes, with a gridline at each tick.
+        assert!((0..5).all(|i| axis.y < bbox(i).0.y));
+        assert_eq!(axis.ticks[0].1, "0");
+        for (x, label) in axis.ticks.iter() {
+            assert!(content.contains(&format!(">{}<", label)));
+            assert!(*x >= axis.left - 0.001 && *x <= axis.right + 0.001);
+        }
+
+        // The text drawing has the labels of the ticks above the nodes.
+        gb.set_text_metrics(TextMetrics::Grid);
+        let mut vg = gb.get();
+        let mut ascii = ASCIIWriter::new();
+        vg.do_it(false, false, false, &mut ascii);
+        let text = ascii.finalize();
+        let row = |name: &str| text.lines().position(|x| x.contains(name));
+        assert!(row("0").is_some());
+        assert!(row("0") < row("lex") && row("lex") < row("notes"));
+
+        // The intervals of the builder are written as DOT.
+        let mut graph = builder::GraphBuilder::new();
+        graph.node("a").span(2., 1.);
+        graph.node("b").span(3., 3.);
+        let text = graph.to_dot(None);
+        assert!(text.contains("  a [start=1, end=2];\n"));
+        assert!(text.contains("  b [start=3];\n"));
+        assert_eq!(graph.build().node_span(NodeHandle::new(0)), Some((1., 2.)));
+    }
+
+    #[test]
+    fn test_adjacency_matrix() {
+        use layout::gv::matrix::AdjacencyMatrix;
+
+        // Every node is connected to the next four nodes.
+        let mut program = String::from("digraph { ");
+        for i in 0..10 {
+            for j in 1..5 {
+                program += &format!("n{} -> n{}; ", i, (i + j) % 10);
+            }
+        }
+        program += "n0 -> n1 [weight=3]; }";
+        let graph = DotParser::new(&program).process().unwrap();
+        let matrix = AdjacencyMatrix::from_graph(&graph);
+        assert_eq!(matrix.num_nodes(), 10);
+        assert_eq!(matrix.weight(0, 1), 4.);
+        assert_eq!(matrix.weight(1, 0), 0.);
+        assert!(matrix.is_dense());
+
+        // A cell for each pair of nodes, shaded by the weight of its edges.
+        let mut rec = RecordingBackend::new();
+        matrix.render(&StyleAttr::simple(), &mut rec);
+        let cells: Vec<&StyleAttr> = rec
+            .commands()
+            .iter()
+            .filter_map(|x| match x {
+                DrawCommand::Rect { look, .. } => Some(look),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(cells.len(), 100);
+        assert!(cells[0].fill_color.is_none());
+        assert_eq!(cells[1].fill_color, Color::from_name("/blues9/9"));
+        assert_eq!(cells[2].fill_color, Color::from_name("/blues9/3"));
+
+        let text = matrix.to_text(true);
+        let row = text.lines().find(|x| x.starts_with("n9 ")).unwrap();
+        assert_eq!(row, "n9 ░░░░░░░░············");
+    }
+
+    #[test]
+    fn test_legend() {
+        use layout::builder::{self, Shape};
+        use layout::std_shapes::shapes::LegendPosition;
+
+        let mut graph = builder::GraphBuilder::new();
+        graph.node("api").shape(Shape::Box).fill("#ddf");
+        graph.node("db").shape(Shape::Circle);
+        graph.edge("api", "db").color("red");
+        graph
+            .legend("Legend", LegendPosition::TopLeft)
+            .legend_node("Service", "api")
+            .legend_node("Store", "db")
+            .legend_edge("Query", "api", "db")
+            .legend_node("Missing", "nothing");
+        let mut vg = graph.build();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let legend = vg.legend().unwrap();
+        assert_eq!(legend.entries.len(), 3);
+
+        // The legend is on the left of the nodes, and inside of the drawing.
+        let result = vg.layout_result();
+        let texts: Vec<(Point, &str)> = rec
+            .commands()
+            .iter()
+            .filter_map(|x| match x {
+                DrawCommand::Text { xy, text, .. } => {
+                    Some((*xy, text.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        let find = |name: &str| texts.iter().find(|x| x.1 == name).unwrap().0;
+        for (top_left, _) in result.nodes.iter() {
+            assert!(find("Service").x < top_left.x);
+        }
+        assert!(find("Legend").y < find("Service").y);
+        assert!(find("Service").y < find("Store").y);
+        assert!(find("Store").y < find("Query").y);
+        assert!(result.bbox.0.x <= find("Legend").x);
+
+        // The swatches have the styles of the nodes and of the edge.
+        let fill = Color::from_name("#ddf");
+        let rects = rec.commands().iter().filter(|x| {
+            matches!(x, DrawCommand::Rect { look, .. } if look.fill_color == fill)
+        });
+        assert_eq!(rects.count(), 2);
+        let red = Color::from_name("red").unwrap();
+        let arrows = rec.commands().iter().filter(|x| {
+            matches!(x, DrawCommand::Arrow { look, .. } if look.line_color == red)
+        });
+        assert_eq!(arrows.count(), 2);
+
+        // The legend is drawn by the text backends too.
+        let mut vg = graph.text_metrics(TextMetrics::Grid).build();
+        let mut ascii = ASCIIWriter::new();
+        vg.do_it(false, false, false, &mut ascii);
+        let text = ascii.finalize();
+        assert!(text.contains("Legend") && text.contains("Query"));
+    }
+
+    #[test]
+    fn test_annotations() {
+        use layout::std_shapes::shapes::{Annotation, AnnotationAnchor};
+
+        let program = "digraph { a -> b; a -> c; b -> d; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let bbox = vg.layout_result().bbox;
+        let nodes = vg.layout_result().nodes;
+
+        let mut look = StyleAttr::simple();
+        look.fill_color = Color::from_name("lightyellow");
+        let a = NodeHandle::new(0);
+        let anchor = AnnotationAnchor::Node(a, Point::new(100., 0.));
+        let target = AnnotationAnchor::Node(a, Point::zero());
+        vg.add_annotation(Annotation::callout("check", anchor, target, &look));
+        let corner = AnnotationAnchor::Point(Point::new(10., 10.));
+        vg.add_annotation(Annotation::text("v1", corner, &look));
+        vg.add_annotation(Annotation::watermark("DRAFT", &look));
+        assert_eq!(vg.annotations().len(), 3);
+        // Annotations don't move the drawing.
+        assert_eq!(vg.layout_result().bbox, bbox);
+        assert_eq!(vg.layout_result().nodes, nodes);
+
+        // The annotations are drawn last, over the diagram.
+        let mut rec = RecordingBackend::new();
+        vg.draw(false, &mut rec);
+        let commands = rec.commands();
+        let texts: Vec<(Point, &str, usize)> = commands
+            .iter()
+            .filter_map(|x| match x {
+                DrawCommand::Text { xy, text, look } => {
+                    Some((*xy, text.as_str(), look.font_size))
+                }
+                _ => None,
+            })
+            .collect();
+        let n = texts.len();
+        assert_eq!(texts[n - 3].1, "check");
+        assert_eq!(texts[n - 2].1, "v1");
+        assert_eq!(texts[n - 1].1, "DRAFT");
+        let center = vg.pos(a).center();
+        assert_eq!(texts[n - 3].0, center.add(Point::new(100., 0.)));
+        assert_eq!(texts[n - 2].0, Point::new(10., 10.));
+        assert!(texts[n - 1].2 > look.font_size);
+
+        // The callout points to the border of the node.
+        let arrow = commands.iter().rev().find_map(|x| match x {
+            DrawCommand::Arrow { path, .. } => Some(path.clone()),
+            _ => None,
+        });
+        let end = arrow.unwrap().last().unwrap().0;
+        assert!((end.x - vg.pos(a).right(false)).abs() < 0.001);
+
+        // Annotations that are anchored to a node follow it.
+        vg.pos_mut(a).translate(Point::new(0., 50.));
+        let mut rec = RecordingBackend::new();
+        vg.draw(false, &mut rec);
+        let moved = rec.commands().iter().find_map(|x| match x {
+            DrawCommand::Text { xy, text, .. } if text == "check" => Some(*xy),
+            _ => None,
+        });
+        assert_eq!(moved, Some(texts[n - 3].0.add(Point::new(0., 50.))));
+        vg.clear_annotations();
+        assert!(vg.annotations().is_empty());
+    }
+
+    #[test]
+    fn test_highlight_path() {
+        use layout::core::style::HighlightStyle;
+
+        let program = "digraph { a -> b; b -> c; c -> d; a -> d; e -> a; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let node = |i: usize| NodeHandle::new(i);
+
+        // The shortest path follows the direction of the edges, unless the
+        // edges are walked both ways.
+        let path = vg.shortest_path(node(0), node(3), true).unwrap();
+        let expected = [
+            ElementRef::Node(node(0)),
+            ElementRef::Edge(3),
+            ElementRef::Node(node(3)),
+        ];
+        assert_eq!(path, expected);
+        assert_eq!(vg.shortest_path(node(3), node(4), true), None);
+        let path = vg.shortest_path(node(3), node(4), false).unwrap();
+        assert_eq!(path.len(), 5);
+
+        // A path of nodes highlights the edges between them.
+        let style = HighlightStyle::new(Color::fast("blue"), 4.);
+        let nodes = [node(0), node(1), node(2)].map(ElementRef::Node);
+        vg.highlight_path(&nodes, style);
+        assert_eq!(vg.highlight(ElementRef::Edge(0)), Some(style));
+        assert_eq!(vg.highlight(ElementRef::Edge(1)), Some(style));
+        assert_eq!(vg.highlight(ElementRef::Edge(2)), None);
+        assert_eq!(vg.highlight(ElementRef::Node(node(3))), None);
+
+        // The highlight changes the drawing, but not the style of the graph.
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let blue = Color::fast("blue");
+        let count = |rec: &RecordingBackend| {
+            rec.commands()
+                .iter()
+                .filter(|x| match x {
+                    DrawCommand::Arrow { look, .. }
+                    | DrawCommand::Circle { look, .. } => {
+                        look.line_color == blue && look.penwidth == 4.
+                    }
+                    _ => false,
+                })
+                .count()
+        };
+        assert_eq!(count(&rec), 5);
+        assert!(vg.element(node(0)).look.line_color != blue);
+
+        vg.clear_highlights();
+        let mut rec = RecordingBackend::new();
+        vg.draw(false, &mut rec);
+        assert_eq!(count(&rec), 0);
+    }
+
+    #[test]
+    fn test_circular_layout() {
+        let program = "graph { layout=circo; a -- b -- c -- d -- a; \
+            c -- e -- f -- c; g; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Circular);
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        let middle = |nodes: &[usize]| {
+            let sum = nodes
+                .iter()
+                .fold(Point::zero(), |acc, i| acc.add(center(*i)));
+            sum.scale(1. / nodes.len() as f64)
+        };
+
+        // The nodes of each block are at the same distance from the middle
+        // of the block.
+        for block in [vec![0, 1, 2, 3], vec![2, 4, 5]] {
+            let mid = middle(&block);
+            let r = center(block[0]).distance_to(mid);
+            for i in block.iter() {
+                assert!((center(*i).distance_to(mid) - r).abs() < 0.01);
+            }
+        }
+
+        // The blocks are on different sides of the shared node.
+        let shared = center(2);
+        let d0 = middle(&[0, 1, 2, 3]).sub(shared);
+        let d1 = middle(&[2, 4, 5]).sub(shared);
+        assert!(d0.x * d1.x + d0.y * d1.y < 0.);
+
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..7 {
+            for j in i + 1..7 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tree_layouts() {
+        let program = "digraph { layout=tree; a -> b; a -> c; b -> d; \
+            b -> e; c -> f; g; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Tree);
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+
+        // The children are in the row below their parent, and the parent is
+        // centered above its first and last child.
+        for (parent, first, last) in [(0, 1, 2), (1, 3, 4), (2, 5, 5)] {
+            assert!(center(first).y > center(parent).y);
+            assert!((center(first).y - center(last).y).abs() < 0.01);
+            let mid = (center(first).x + center(last).x) / 2.;
+            assert!((center(parent).x - mid).abs() < 0.01);
+        }
+        assert!((center(0).y - center(6).y).abs() < 0.01);
+
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..7 {
+            for j in i + 1..7 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+
+        // The radial layout places the root in the middle, and the nodes at
+        // the same depth at the same distance from it.
+        let program = "graph { layout=twopi; root=c; a -- c; b -- c; \
+            c -- d; d -- e; a -- f; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.layout_engine(), LayoutEngine::Radial);
+        assert_eq!(vg.root(), Some(NodeHandle::new(1)));
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+        let dist = |i: usize| center(i).distance_to(center(1));
+        assert!((dist(0) - dist(2)).abs() < 0.01);
+        assert!((dist(0) - dist(3)).abs() < 0.01);
+        assert!((dist(4) - dist(5)).abs() < 0.01);
+        assert!(dist(4) > dist(0));
+
+        let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+        for i in 0..6 {
+            for j in i + 1..6 {
+                assert!(!do_boxes_intersect(bbox(i), bbox(j)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_head_and_tail_labels() {
+        let render = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+            let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+            let (a, b) = (center(0), center(1));
+            let texts: Vec<(String, Point)> = rec
+                .commands()
+                .iter()
+                .filter_map(|cmd| match cmd {
+                    DrawCommand::Text { xy, text, .. } => {
+                        Some((text.clone(), *xy))
+                    }
+                    _ => None,
+                })
+                .collect();
+            (a, b, texts)
+        };
+        let find = |texts: &[(String, Point)], label: &str| {
+            texts.iter().find(|x| x.0 == label).unwrap().1
+        };
+
+        // The head label is next to the head, and the tail label is next to
+        // the tail, also when the edge is reversed to break a cycle.
+        let (a, b, texts) = render(
+            "digraph { a -> b [headlabel=\"1\", taillabel=\"*\"]; \
+            b -> a [headlabel=\"h\", taillabel=\"t\"]; }",
+        );
+        for (label, near, far) in [("1", b, a), ("*", a, b), ("h", a, b)] {
+            let xy = find(&texts, label);
+            assert!(xy.distance_to(near) < xy.distance_to(far));
+        }
+        assert!(
+            find(&texts, "t").distance_to(b) < find(&texts, "t").distance_to(a)
+        );
+
+        // The label distance and the label angle move the labels.
+        let program = |attrs: &str| {
+            format!("digraph {{ a -> b [headlabel=\"1\" {}]; }}", attrs)
+        };
+        let (_, b, texts) = render(&program(""));
+        let near = find(&texts, "1").distance_to(b);
+        let (_, b, texts) = render(&program("labeldistance=3"));
+        let far = find(&texts, "1").distance_to(b);
+        assert!(far > near + 20.);
+        // Positive angles are counterclockwise, so from the head at the
+        // bottom the label moves to the left.
+        let (_, _, left) = render(&program("labelangle=40"));
+        let (_, _, right) = render(&program("labelangle=-40"));
+        let (left, right) = (find(&left, "1"), find(&right, "1"));
+        assert!((left.y - right.y).abs() < 0.01);
+        assert!(left.x < right.x);
+    }
+
+    #[test]
+    fn test_arrow_head_kinds() {
+        // Each kind of arrow head has its own marker, and the normal head
+        // uses the default marker.
+        let svg = render_svg(
+            "digraph { a -> b [arrowhead=odiamond]; a -> c; \
+            a -> d [arrowhead=dot, arrowsize=2]; a -> e [arrowhead=none]; }",
+        );
+        assert!(svg.contains("<marker id=\"endodiamond100\""));
+        assert!(svg.contains("marker-end=\"url(#endodiamond100)\""));
+        assert!(svg.contains("marker-end=\"url(#endarrow)\""));
+        assert!(svg.contains("marker-end=\"url(#enddot200)\""));
+        assert!(svg.contains("<circle"));
+        assert_eq!(svg.matches("marker-end=").count(), 3);
+
+        // Reversed edges keep the heads at the right ends.
+        let program = "digraph { a -> b; b -> a [arrowhead=vee, \
+            arrowtail=crow]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let a = vg.pos(NodeHandle::new(0)).center();
+        let found = rec.commands().iter().any(|cmd| match cmd {
+            DrawCommand::Arrow {
+                path, head, look, ..
+            } => {
+                let start = path[0].0;
+                let end = path[path.len() - 1].1;
+                match (head, look.arrow_heads) {
+                    ((true, false), (ArrowHeadKind::Vee, _)) => {
+                        start.distance_to(a) < end.distance_to(a)
+                    }
+                    ((false, true), (_, ArrowHeadKind::Vee)) => {
+                        end.distance_to(a) < start.distance_to(a)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        });
+        assert!(found);
+    }
+
+    #[test]
+    fn test_node_shapes() {
+        let program = "digraph { a [shape=diamond]; b [shape=point];
+            c [shape=cylinder]; d [shape=plaintext]; e [shape=parallelogram];
+            a -> b; a -> c; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let shape = |i: usize| vg.element(NodeHandle::new(i)).shape.clone();
+        assert!(matches!(
+            shape(0),
+            ShapeKind::Polygon(PolygonKind::Diamond, _)
+        ));
+        assert!(matches!(shape(1), ShapeKind::Point));
+        assert!(matches!(
+            shape(2),
+            ShapeKind::Polygon(PolygonKind::Cylinder, _)
+        ));
+        assert!(matches!(
+            shape(3),
+            ShapeKind::Polygon(PolygonKind::Plaintext, _)
+        ));
+        assert_eq!(
+            PolygonKind::from_name("parallelogram"),
+            Some(PolygonKind::Parallelogram)
+        );
+
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let a = vg.pos(NodeHandle::new(0));
+        let (center, half) = (a.center(), a.size(false).scale(0.5));
+
+        // The diamond is drawn as a polygon, and the edges start on its sides.
+        let polygons = rec
+            .commands()
+            .iter()
+            .filter(|cmd| matches!(cmd, DrawCommand::Polygon { .. }))
+            .count();
+        assert_eq!(polygons, 2);
+        let mut num_arrows = 0;
+        for cmd in rec.commands() {
+            if let DrawCommand::Arrow { path, .. } = cmd {
+                let start = path[0].0;
+                let dist = (start.x - center.x).abs() / half.x
+                    + (start.y - center.y).abs() / half.y;
+                assert!((dist - 1.).abs() < 0.01, "{:?}", start);
+                num_arrows += 1;
+            }
+        }
+        assert_eq!(num_arrows, 2);
+
+        // Points are small circles that are filled with the line color.
+        let b = vg.pos(NodeHandle::new(1));
+        assert!(b.size(false).x < 20.);
+        let point = rec.commands().iter().find_map(|cmd| match cmd {
+            DrawCommand::Circle { xy, look, .. } if *xy == b.center() => {
+                Some(look.clone())
+            }
+            _ => None,
+        });
+        let point = point.unwrap();
+        assert_eq!(point.fill_color, Some(point.line_color));
+    }
+
+    #[test]
+    fn test_peripheries() {
+        let program = "digraph { a [peripheries=3]; b [shape=doublecircle];
+            c [shape=box, peripheries=2]; d [shape=plaintext]; a -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let peripheries: Vec<usize> = (0..4)
+            .map(|i| vg.element(NodeHandle::new(i)).peripheries)
+            .collect();
+        assert_eq!(peripheries, vec![3, 2, 2, 0]);
+
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        // Each border is drawn around the previous one.
+        let a = vg.element(NodeHandle::new(0));
+        let mut sizes: Vec<f64> = rec
+            .commands()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Circle { xy, size, .. }
+                    if *xy == a.pos.center() =>
+                {
+                    Some(size.x)
+                }
+                _ => None,
+            })
+            .collect();
+        sizes.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes[1] - sizes[0], 8.);
+        assert_eq!(sizes[2] - sizes[1], 8.);
+
+        // The edge stops at the outer borders.
+        let on_outline = |elem: &Element, p: Point| {
+            let d = p.sub(elem.pos.center());
+            let half = elem.outline_size().scale(0.5);
+            let dist = (d.x / half.x).powi(2) + (d.y / half.y).powi(2);
+            (dist - 1.).abs() < 0.01
+        };
+        let b = vg.element(NodeHandle::new(1));
+        let path = rec
+            .commands()
+            .iter()
+            .find_map(|cmd| match cmd {
+                DrawCommand::Arrow { path, .. } => Some(path.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(on_outline(a, path[0].0));
+        assert!(on_outline(b, path[path.len() - 1].1));
+    }
+
+    #[test]
+    fn test_styles() {
+        let program = "digraph { a [shape=box, style=\"rounded,filled,bold\"];
+            b [label=hidden, style=invis]; c [shape=box, style=\"dashed\"];
+            a -> b [style=\"bold, dotted\"]; b -> c [style=invis]; a -> c; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let look = |i: usize| vg.element(NodeHandle::new(i)).look.clone();
+        assert_eq!(look(0).rounded, 15);
+        assert_eq!(look(0).penwidth, 2.);
+        assert_eq!(look(0).fill_color, Some(Color::fast("lightgray")));
+        assert!(look(0).visible && !look(1).visible);
+        assert!(look(2).is_dashed());
+
+        // The invisible node and edge take space, but are not drawn.
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let b = vg.pos(NodeHandle::new(1));
+        assert!(b.size(false).x > 0.);
+        let mut arrows = Vec::new();
+        for cmd in rec.commands() {
+            match cmd {
+                DrawCommand::Text { text, .. } => assert_ne!(text, "hidden"),
+                DrawCommand::Arrow { look, dashed, .. } => {
+                    arrows.push((look.penwidth, *dashed));
+                }
+                _ => {}
+            }
+        }
+        arrows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert_eq!(arrows, vec![(1., false), (2., true)]);
+    }
+
+    #[test]
+    fn test_penwidth() {
+        let svg = render_svg(
+            "digraph { edge [penwidth=2]; a [penwidth=0.5];
+                       a -> b [penwidth=1.5]; a -> c; }",
+        );
+        assert!(svg.contains("stroke-width=\"0.5\""));
+        assert!(svg.contains("stroke-width=\"1.5\""));
+        assert!(svg.contains("stroke-width=\"2\""));
+    }
+
+    #[test]
+    fn test_fonts() {
+        let svg = render_svg(
+            "digraph { a [fontname=\"Helvetica-Bold\", fontcolor=red];
+                       a -> b [label=x, fontname=\"Times-Italic\"]; }",
+        );
+        assert!(svg.contains(
+            "font-family=\"Helvetica\" font-weight=\"bold\" \
+            fill=\"#ff0000\">"
+        ));
+        assert!(svg.contains("font-style=\"italic\"><tspan"));
+
+        // Wide fonts make wider nodes.
+        let program = "digraph { a [label=hello, shape=box];
+            b [label=hello, shape=box, fontname=Courier]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let vg = gb.get();
+        let width = |i: usize| vg.element(NodeHandle::new(i)).pos.size(false).x;
+        assert!(width(1) > width(0));
+    }
+
+    #[test]
+    fn test_text_metrics() {
+        let program = "digraph { a [label=iiii, shape=box];
+            b [label=mmmm, shape=box]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let width = |metrics: TextMetrics| {
+            let mut gb = GraphBuilder::new();
+            gb.set_text_metrics(metrics);
+            gb.visit_graph(&graph);
+            let vg = gb.get();
+            let size =
+                |i: usize| vg.element(NodeHandle::new(i)).pos.size(false);
+            (size(0).x, size(1).x)
+        };
+        // Proportional fonts have narrow and wide letters.
+        let (narrow, wide) = width(TextMetrics::Font);
+        assert!(narrow < wide);
+        // The text backends draw each letter in a cell.
+        let (narrow, wide) = width(TextMetrics::Grid);
+        assert_eq!(narrow, wide);
+        assert_eq!(narrow, 4. * 14. + 10.);
+    }
+
+    #[test]
+    fn test_label_escapes() {
+        let program = r#"digraph G {
+            a [shape=box, label="wide center line\nleft\lright\r"];
+            b [label="\N of \G"];
+            a -> b [label="\E", headlabel="\H", taillabel="\T"];
+            c [label="C:\\lib"];
+        }"#;
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+
+        let mut texts = Vec::new();
+        for cmd in rec.commands() {
+            if let DrawCommand::Text { xy, text, .. } = cmd {
+                texts.push((text.clone(), *xy));
+            }
+        }
+        let find = |text: &str| {
+            texts.iter().find(|x| x.0 == text).unwrap_or_else(|| {
+                panic!("Can't find the text {} in {:?}", text, texts)
+            })
+        };
+        let center = find("wide center line").1;
+        let left = find("left").1;
+        let right = find("right").1;
+        assert!(left.x < center.x && center.x < right.x);
+        assert!(center.y < left.y && left.y < right.y);
+
+        // The escapes stand for the names of the elements.
+        find("b of G");
+        find("a->b");
+        find("a");
+        find("b");
+        find("C:\\lib");
+    }
+
+    #[test]
+    fn test_node_size() {
+        let program = r#"digraph {
+            a [shape=box, width=2, height=1];
+            b [shape=box, width=0.5, height=0.25, fixedsize=true,
+                label="a very long label"];
+            c [shape=box, width=0.5, label="a very long label"];
+            d [shape=square, width=1];
+            e [shape=box, width=0.5, height=0.25, fixedsize=true,
+                labeloverflow=clip, label="a very long label"];
+        }"#;
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let size = |i: usize| vg.element(NodeHandle::new(i)).pos.size(false);
+        // The sizes are in inches.
+        assert_eq!(size(0), Point::new(144., 72.));
+        // Fixed sizes ignore the label.
+        assert_eq!(size(1), Point::new(36., 18.));
+        // The width and the height are the minimal size of the node.
+        assert!(size(2).x > 36.);
+        assert_eq!(size(3), Point::new(72., 72.));
+
+        // Labels overflow the shapes, unless they are clipped.
+        let mut rec = RecordingBackend::new();
+        vg.do_it(false, false, false, &mut rec);
+        let texts: Vec<String> = rec
+            .commands()
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DrawCommand::Text { text, .. } => Some(text.clone()),
+                _ => None,
+            })
+            .collect();
+        let long = texts.iter().filter(|x| *x == "a very long label");
+        assert_eq!(long.count(), 2);
+        assert!(texts
+            .iter()
+            .any(|x| x.starts_with("a ") && x.ends_with('…')));
+    }
+
+    #[test]
+    fn test_spacing() {
+        let layout = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            let bbox = |i: usize| vg.pos(NodeHandle::new(i)).bbox(false);
+            (bbox(0), bbox(1), bbox(2), bbox(3))
+        };
+        let (a, b, c, d) = layout(
+            "digraph { nodesep=0.1; ranksep=\"0.2:1\"; margin=0.5;
+            a -> b; a -> c; b -> d; }",
+        );
+        // The gaps are in inches.
+        assert!((c.0.x - b.1.x - 7.2).abs() < 0.01);
+        assert!((b.0.y - a.1.y - 14.4).abs() < 0.01);
+        assert!((d.0.y - b.1.y - 72.).abs() < 0.01);
+        // The margin is on the top and on the left of the drawing.
+        assert_eq!(a.0.y, 36.);
+        assert_eq!(b.0.x.min(c.0.x).min(d.0.x), 36.);
+
+        // The centers of the ranks are equally apart.
+        let (a, b, _, d) = layout(
+            "digraph { ranksep=\"0.5 equally\";
+            a [shape=box, height=2]; a -> b; a -> c; b -> d; }",
+        );
+        let center = |x: (Point, Point)| (x.0.y + x.1.y) / 2.;
+        let first = center(b) - center(a);
+        assert!((center(d) - center(b) - first).abs() < 0.01);
+
+        // Node margins are in inches.
+        let (a, b, _, _) = layout(
+            "digraph { a [shape=box, label=\"\", margin=\"0.5,0\"];
+            b [shape=box, label=\"\"]; a -> b; a -> c; b -> d; }",
+        );
+        let width = |x: (Point, Point)| x.1.x - x.0.x;
+        assert_eq!(width(a), width(b) + 72. - 10.);
+    }
+
+    #[test]
+    fn test_edge_constraints() {
+        let layout = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            let center = |i: usize| vg.pos(NodeHandle::new(i)).center();
+            (0..vg.num_nodes()).map(center).collect::<Vec<Point>>()
+        };
+        // The minimal length of edges is in ranks.
+        let short = layout("digraph { a -> b; }");
+        let long = layout("digraph { a -> b [minlen=3]; }");
+        assert!(long[1].y - long[0].y > short[1].y - short[0].y);
+        let same = layout("digraph { a -> b [minlen=0]; }");
+        assert_eq!(same[0].y, same[1].y);
+
+        // Heavy edges are drawn straight.
+        let pos = layout("digraph { a; x; y; a -> c; x -> c; y -> c; }");
+        assert_eq!(pos[3].x, pos[1].x);
+        let pos =
+            layout("digraph { a; x; y; a -> c; x -> c; y -> c [weight=5]; }");
+        assert_eq!(pos[3].x, pos[2].x);
+
+        // Heavy edges are kept short.
+        let program = "digraph { a -> b -> c -> d -> e; a -> n; x -> n;
+            n -> e [weight=WEIGHT]; }";
+        let light = layout(&program.replace("WEIGHT", "1"));
+        let heavy = layout(&program.replace("WEIGHT", "5"));
+        assert!(heavy[5].y > light[5].y);
+    }
+
+    #[test]
+    fn test_unconstrained_edges() {
+        let layout = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+            let arrows = rec
+                .commands()
+                .iter()
+                .filter(|cmd| matches!(cmd, DrawCommand::Arrow { .. }))
+                .count();
+            let y = |i: usize| vg.pos(NodeHandle::new(i)).center().y;
+            (arrows, y(0), y(1), y(2))
+        };
+        let (arrows, a, _, c) = layout("digraph { a -> b; c -> b; a -> c; }");
+        assert_eq!(arrows, 3);
+        assert!(c > a);
+
+        // The edge is drawn, but it doesn't push 'c' below 'a'.
+        let program = "digraph { a -> b; c -> b; a -> c [constraint=false]; }";
+        let (arrows, a, _, c) = layout(program);
+        assert_eq!(arrows, 3);
+        assert_eq!(c, a);
+
+        // Edges that point up are drawn in reverse.
+        let program =
+            "digraph { a -> b -> c; c -> a [constraint=false, label=x]; }";
+        let (arrows, a, b, c) = layout(program);
+        assert_eq!(arrows, 3);
+        assert!(a < b && b < c);
+    }
+
+    #[test]
+    fn test_fluent_builder() {
+        use layout::builder::{self, Shape};
+        use layout::core::style::LineStyleKind;
+
+        let mut graph = builder::GraphBuilder::new();
+        graph.label("title").background("lightyellow");
+        graph.node("a").shape(Shape::Box).fill("#ddddff");
+        graph.node("d").shape(Shape::Polygon(PolygonKind::Diamond));
+        graph.edge("a", "b").label("x");
+        graph.edge("a", "d").style(LineStyleKind::Dashed);
+        let mut vg = graph.build();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("title"));
+        assert!(svg.contains("fill=\"#ddddff\""));
+
+        // The graph is laid out like the same graph in a DOT file.
+        let program = "digraph { label=title; bgcolor=lightyellow;
+            a [shape=box, fillcolor=\"#ddddff\", style=filled];
+            d [shape=diamond]; a -> b [label=x]; a -> d [style=dashed]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut dot = gb.get();
+        let mut svg = SVGWriter::new();
+        dot.do_it(false, false, false, &mut svg);
+        assert_eq!(vg.num_nodes(), dot.num_nodes());
+        for i in 0..vg.num_nodes() {
+            let node = NodeHandle::new(i);
+            assert_eq!(vg.pos(node).bbox(false), dot.pos(node).bbox(false));
+        }
+    }
+
+    #[test]
+    fn test_layout_result() {
+        let program = "digraph { a -> b [label=x]; b -> c; c -> a; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let res = vg.layout_result();
+
+        // The result has the nodes and the edges that the user added.
+        assert_eq!(res.nodes.len(), 3);
+        assert_eq!(res.edges.len(), 3);
+        let inside = |p: Point, bbox: (Point, Point)| {
+            p.x >= bbox.0.x - 0.01
+                && p.y >= bbox.0.y - 0.01
+                && p.x <= bbox.1.x + 0.01
+                && p.y <= bbox.1.y + 0.01
+        };
+        for node in res.nodes.iter() {
+            assert!(inside(node.0, res.bbox) && inside(node.1, res.bbox));
+        }
+        assert!(res.edges[0].label.is_some());
+        assert!(res.edges[1].label.is_none());
+
+        // The back edge goes from 'c' to 'a', even though it's reversed in
+        // the layout.
+        let back = &res.edges[2];
+        assert_eq!(back.from, NodeHandle::new(2));
+        assert_eq!(back.to, NodeHandle::new(0));
+        let start = back.path.first().unwrap().0;
+        let end = back.path.last().unwrap().3;
+        assert!(start.y > end.y);
+        let center = |b: (Point, Point)| b.0.add(b.1).scale(0.5);
+        assert!(start.distance_to(center(res.nodes[2])) < 50.);
+        assert!(end.distance_to(center(res.nodes[0])) < 50.);
+    }
+
+    #[test]
+    fn test_incremental_layout() {
+        use layout::builder;
+
+        let mut graph = builder::GraphBuilder::new();
+        let names = ["a", "b", "c", "d", "e", "f", "g"];
+        for (from, to) in [("a", "b"), ("a", "c"), ("a", "d"), ("b", "e")] {
+            graph.edge(from, to);
+        }
+        for (from, to) in [("c", "e"), ("d", "f"), ("b", "g"), ("d", "g")] {
+            graph.edge(from, to);
+        }
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let before = vg.warm_start();
+        graph.set_previous_layout(&vg);
+
+        // Add a node that crosses the graph, and remove another node.
+        graph.edge("a", "h");
+        graph.edge("h", "g");
+        graph.edge("h", "e");
+        graph.remove_node("c");
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let after = vg.warm_start();
+
+        // The nodes that were not removed keep their ranks and their order.
+        let mut kept = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            if *name != "c" {
+                let j = if i > 2 { i - 1 } else { i };
+                kept.push((before.nodes[i].unwrap(), after.nodes[j].unwrap()));
+            }
+        }
+        for (old, new) in kept.iter() {
+            assert_eq!(old.0, new.0);
+        }
+        for (old1, new1) in kept.iter() {
+            for (old2, new2) in kept.iter() {
+                if old1.0 == old2.0 && old1.1 < old2.1 {
+                    assert!(new1.1 < new2.1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_swimlanes() {
+        for rankdir in ["TB", "LR"] {
+            let text = format!(
+                "digraph {{ rankdir={}; a [lane=x]; b [lane=y]; c [lane=x]; \
+                 d; a -> b -> c; a -> c; b -> d; a -> d; }}",
+                rankdir
+            );
+            let graph = DotParser::new(&text).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            assert_eq!(vg.num_lanes(), 2);
+            assert_eq!(vg.lane(1).label, "y");
+            assert_eq!(vg.lane_of(NodeHandle::new(3)), None);
+            vg.layout(false, false);
+
+            // Each node is inside of its lane, and nodes without a lane are
+            // in the first lane. The lanes don't overlap.
+            let res = vg.layout_result();
+            let x = vg.lane_bbox(0).unwrap();
+            let y = vg.lane_bbox(1).unwrap();
+            let inside = |node: usize, lane: (Point, Point)| {
+                let (a, b) = res.nodes[node];
+                lane.0.x <= a.x
+                    && lane.0.y <= a.y
+                    && b.x <= lane.1.x
+                    && b.y <= lane.1.y
+            };
+            for (node, lane) in [(0, x), (1, y), (2, x), (3, x)] {
+                assert!(inside(node, lane), "{} {}", rankdir, node);
+            }
+            if rankdir == "TB" {
+                assert_eq!(x.1.x, y.0.x);
+                assert_eq!(x.0.y, y.0.y);
+            } else {
+                assert_eq!(x.1.y, y.0.y);
+                assert_eq!(x.0.x, y.0.x);
+            }
+
+            let mut svg = SVGWriter::new();
+            vg.draw(false, &mut svg);
+            let content = svg.finalize();
+            assert!(content.contains("class=\"lane\" data-lane=\"y\""));
+        }
+
+        // Graphs that are built in code select the lanes of the nodes.
+        let mut graph = layout::builder::GraphBuilder::new();
+        graph.node("a").lane("first");
+        graph.node("b").lane("second");
+        graph.edge("a", "b");
+        let vg = graph.build();
+        assert_eq!(vg.num_lanes(), 2);
+        assert!(graph.to_dot(None).contains("  b [lane=second];\n"));
+    }
+
+    #[test]
+    fn test_collapsible_groups() {
+        use layout::builder;
+
+        let mut graph = builder::GraphBuilder::new();
+        for (from, to) in [("a", "b"), ("a", "c"), ("b", "d"), ("c", "d")] {
+            graph.edge(from, to);
+        }
+        graph.edge("d", "e");
+        graph.group("inner", &["b", "c", "d"]);
+
+        // The expanded group is drawn as a cluster around its nodes.
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let res = vg.layout_result();
+        assert_eq!(res.nodes.len(), 5);
+        assert_eq!(res.clusters.len(), 1);
+        assert!(res.clusters[0].is_some());
+        graph.set_previous_layout(&vg);
+
+        // Collapse the group, and lay out the graph again.
+        assert_eq!(graph.toggle_group("inner"), Some(true));
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let res = vg.layout_result();
+        assert_eq!(res.nodes.len(), 3);
+        assert_eq!(res.edges.len(), 2);
+        assert!(res.clusters.is_empty());
+        let inner = graph.node_handle("d").unwrap();
+        assert_eq!(inner.get_index(), 1);
+
+        let mut svg = SVGWriter::new();
+        vg.draw(false, &mut svg);
+        let content = svg.finalize();
+        assert!(content.contains("inner (3)"));
+        assert!(content.contains("id=\"inner-&gt;e\""));
+    }
+
+    #[test]
+    fn test_deterministic_layout() {
+        let program = "digraph { a [fontsize=9]; b [fontsize=20]; c; d;
+            e [fontsize=12]; a -> b; a -> c; b -> d; c -> d; a -> e;
+            e -> d [label=x, fontsize=30]; b -> c; e -> b; }";
+        let render = |seed: Option<u64>| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            if let Some(seed) = seed {
+                vg.set_seed(seed);
+            }
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            svg.finalize()
+        };
+
+        // The same graph always produces the same output.
+        for seed in [None, Some(1), Some(2)] {
+            let first = render(seed);
+            for _ in 0..5 {
+                assert_eq!(first, render(seed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack_components() {
+        let layout = |pack: &str| {
+            let program = format!(
+                "digraph {{ {} a -> b -> c; d -> e -> f; g -> h [label=x];
+                i; j -> j; k -> l -> m -> n; o -> p [constraint=false];
+                subgraph cluster_x {{ q; r; }} }}",
+                pack
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            vg.layout(false, false);
+            vg.layout_result()
+        };
+        let size = |res: &layout::topo::layout::LayoutResult| {
+            res.bbox.1.sub(res.bbox.0)
+        };
+        let overlap = |a: (Point, Point), b: (Point, Point)| {
+            a.0.x < b.1.x && b.0.x < a.1.x && a.0.y < b.1.y && b.0.y < a.1.y
+        };
+
+        // The components are placed in one long row without packing.
+        let row = layout("");
+        assert!(size(&row).x > 2. * size(&row).y);
+
+        for pack in ["pack=true;", "pack=20; packmode=array_c3;"] {
+            let res = layout(pack);
+            assert_eq!(res.nodes.len(), 18);
+            assert_eq!(res.edges.len(), 10);
+            assert!(size(&res).x < 2. * size(&res).y);
+            for i in 0..res.nodes.len() {
+                for j in i + 1..res.nodes.len() {
+                    assert!(!overlap(res.nodes[i], res.nodes[j]));
+                }
+            }
+
+            // The edges still connect their nodes.
+            let center = |b: (Point, Point)| b.0.add(b.1).scale(0.5);
+            for edge in res.edges.iter() {
+                let start = edge.path.first().unwrap().0;
+                let from = center(res.nodes[edge.from.get_index()]);
+                assert!(start.distance_to(from) < 40.);
+            }
+            assert!(res.edges[4].label.is_some());
+        }
+    }
+
+    #[test]
+    fn test_crossing_reduction() {
+        let program = "digraph { n14 -> n18; n3 -> n17; n14 -> n22;
+            n6 -> n11; n13 -> n20; n6 -> n8; n9 -> n20; n3 -> n10; n9 -> n10;
+            n3 -> n6; n7 -> n10; n10 -> n22; n5 -> n8; n2 -> n12; n9 -> n17;
+            n11 -> n21; n0 -> n1; n0 -> n1; n1 -> n10; }";
+        let count = |config: Option<OrderingConfig>, attr: &str| {
+            let program =
+                program.replace("digraph {", &format!("digraph {{ {}", attr));
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            if let Some(config) = config {
+                vg.set_ordering_config(config);
+            }
+            vg.layout(false, false);
+            vg.num_crossings().unwrap()
+        };
+
+        // The median passes remove the crossings that the swaps leave.
+        assert_eq!(count(None, ""), 0);
+        let swaps_only = OrderingConfig {
+            passes: 0,
+            ..OrderingConfig::default()
+        };
+        assert!(count(Some(swaps_only), "") > 0);
+        assert!(count(None, "mclimit=0;") > 0);
+    }
+
+    #[test]
+    fn test_layout_quality() {
+        let program = "digraph { a; b; c; d; e; g;
+            a -> c -> d -> e; b -> g; b -> d; }";
+        let levels = |quality: LayoutQuality| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            vg.set_quality(quality);
+            vg.layout(false, false);
+            let levels: Vec<usize> = vg.warm_start().nodes[..6]
+                .iter()
+                .map(|x| x.unwrap().0)
+                .collect();
+            levels
+        };
+
+        // The longest path places 'b' on the first rank, and the edge b->d
+        // spans two ranks. Network simplex moves 'b' down to shorten it.
+        assert_eq!(levels(LayoutQuality::Fast), vec![0, 0, 1, 2, 3, 1]);
+        assert_eq!(levels(LayoutQuality::Normal), vec![0, 0, 1, 2, 3, 1]);
+        assert_eq!(levels(LayoutQuality::High), vec![0, 1, 1, 2, 3, 2]);
+    }
+
+    #[test]
+    fn test_large_graph() {
+        // A graph with many nodes, long edges and cycles is laid out quickly.
+        let mut program = String::from("digraph {\n");
+        let n = 200;
+        let mut seed: usize = 1;
+        for i in 0..n * 4 {
+            seed = (seed * 1103515245 + 12345) % (1 << 31);
+            let from = i % n;
+            let to = (from + 1 + seed % 20) % n;
+            program.push_str(&format!("n{} -> n{};\n", from, to));
+        }
+        program.push('}');
+
+        let graph = DotParser::new(&program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        assert!(vg.num_crossings().is_some());
+        assert!(svg.finalize().contains("n199"));
+    }
+
+    #[test]
+    fn test_concentrate() {
+        let count_nodes = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut svg = SVGWriter::new();
+            vg.do_it(false, false, false, &mut svg);
+            vg.num_nodes()
+        };
+        // Edges that leave the same node share their first connector.
+        let program = "digraph { a -> b -> c -> d; a -> d; a -> c; }";
+        assert_eq!(count_nodes(program), 7);
+        let program = "digraph { concentrate=true;
+            a -> b -> c -> d; a -> d; a -> c; }";
+        assert_eq!(count_nodes(program), 6);
+
+        // Edges that enter the same node share their last connector.
+        let program = "digraph { concentrate=true;
+            a -> b -> c -> d; a -> d; b -> d; }";
+        assert_eq!(count_nodes(program), 6);
+
+        // Edges with labels are not merged.
+        let program = "digraph { concentrate=true;
+            a -> b -> c -> d; a -> d [label=x]; a -> d [label=y]; }";
+        assert_eq!(count_nodes(program), 8);
+    }
+
+    #[test]
+    fn test_graph_label() {
+        let svg = render_svg(
+            "digraph G { bgcolor=lightyellow; label=\"Title of \\G\"; a -> b; }",
+        );
+        assert!(svg
+            .contains("<rect width=\"100%\" height=\"100%\" fill=\"#ffffe0\""));
+        assert!(svg.contains("Title of G"));
+
+        // Find the location of the title and of the nodes.
+        let layout = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+            let title = rec
+                .commands()
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DrawCommand::Text { xy, text, .. } if text == "title" => {
+                        Some(*xy)
+                    }
+                    _ => None,
+                })
+                .unwrap();
+            let a = vg.pos(NodeHandle::new(0)).bbox(false);
+            let b = vg.pos(NodeHandle::new(1)).bbox(false);
+            (title, a, b)
+        };
+        // The title is below the graph by default.
+        let (title, _, b) = layout("digraph { label=title; a -> b; }");
+        assert!(title.y > b.1.y);
+        let (title, a, _) =
+            layout("digraph { label=title; labelloc=t; a -> b; }");
+        assert!(title.y < a.0.y);
+        assert!(title.y > 0.);
+
+        // The title is justified to the sides of the drawing.
+        let program = "digraph { label=title; labeljust=l; a -> b; c; d; }";
+        let (left, _, _) = layout(program);
+        let (center, _, _) = layout("digraph { label=title; a -> b; c; d; }");
+        assert!(left.x < center.x);
+    }
+
+    #[test]
+    fn test_edge_dir() {
+        let heads = |program: &str| {
+            let graph = DotParser::new(program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            let mut rec = RecordingBackend::new();
+            vg.do_it(false, false, false, &mut rec);
+            let a = vg.pos(NodeHandle::new(0)).center();
+
+            rec.commands()
+                .iter()
+                .find_map(|cmd| match cmd {
+                    DrawCommand::Arrow { path, head, .. } => {
+                        let start = path[0].0;
+                        let end = path[path.len() - 1].1;
+                        if start.distance_to(a) < end.distance_to(a) {
+                            Some(*head)
+                        } else {
+                            Some((head.1, head.0))
+                        }
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(heads("digraph { a -> b; }"), (false, true));
+        assert_eq!(heads("digraph { a -> b [dir=back]; }"), (true, false));
+        assert_eq!(heads("digraph { a -> b [dir=both]; }"), (true, true));
+        assert_eq!(heads("digraph { a -> b [dir=none]; }"), (false, false));
+        assert_eq!(heads("graph { a -- b; }"), (false, false));
+        assert_eq!(heads("graph { a -- b [dir=forward]; }"), (false, true));
+        assert_eq!(heads("digraph { edge [dir=both]; a -> b; }"), (true, true));
+    }
+
+    #[test]
+    fn test_dash_patterns() {
+        let svg = render_svg("digraph { a [style=dotted]; a -> b; }");
+        assert_eq!(svg.matches("stroke-dasharray=\"1,3\"").count(), 1);
+        let svg = render_svg("digraph { a -> b [style=dashed]; }");
+        assert_eq!(svg.matches("stroke-dasharray=\"5,5\"").count(), 1);
+    }
+
+    #[test]
+    fn test_gradient_fill() {
+        let svg =
+            render_svg("digraph { a [style=filled, fillcolor=\"red:blue\"]; }");
+        assert!(svg.contains("<linearGradient id=\"G0\""));
+        assert!(svg.contains("stop-color=\"#0000ff\""));
+        assert!(svg.contains("fill=\"url(#G0)\""));
+        let svg = render_svg("digraph { a [style=radial, fillcolor=red]; }");
+        assert!(svg.contains("<radialGradient id=\"G0\""));
+        let svg = render_svg(
+            "digraph { a [style=\"filled,radial\", fillcolor=red]; }",
+        );
+        assert!(svg.contains("<radialGradient id=\"G0\""));
+
+        // Documents that are inlined in one page have their own ids.
+        use layout::core::style::{Gradient, GradientKind};
+        let mut look = StyleAttr::simple();
+        look.fill_color = Option::Some(Color::fast("red"));
+        look.gradient = Option::Some(Gradient::with_colors(
+            GradientKind::Radial,
+            &[Color::fast("white"), Color::fast("red")],
+        ));
+        let mut svg = SVGWriter::new();
+        svg.set_id_prefix("b_");
+        let clip = svg.create_clip(Point::zero(), Point::new(10., 10.), 0);
+        svg.draw_rect(
+            Point::zero(),
+            Point::new(10., 10.),
+            &look,
+            None,
+            Some(clip),
+        );
+        let svg = svg.finalize();
+        assert!(svg.contains("<radialGradient id=\"b_G0\""));
+        assert!(svg.contains("fill=\"url(#b_G0)\""));
+        assert!(svg.contains("<clipPath id=\"b_C0\""));
+        assert!(svg.contains("clip-path=\"url(#b_C0)\""));
+    }
+
+    #[test]
+    fn test_weighted_color_lists() {
+        // Weighted colors fill bands that don't fade into each other.
+        let svg = render_svg(
+            "digraph { a [style=filled, fillcolor=\"red;0.3:blue\"]; }",
+        );
+        assert!(svg.contains("<linearGradient id=\"G0\""));
+        assert_eq!(svg.matches("<stop offset=\"0.3\"").count(), 2);
+
+        let svg = render_svg(
+            "digraph { a [shape=box, style=striped, fillcolor=\"red:blue\"]; }",
+        );
+        assert!(svg.contains("<linearGradient id=\"G0\""));
+        assert_eq!(svg.matches("<stop offset=\"0.5\"").count(), 2);
+
+        // Wedges are drawn as pie slices.
+        let svg = render_svg(
+            "digraph { a [style=wedged, fillcolor=\"red;0.5:blue:green\"]; }",
+        );
+        assert!(!svg.contains("Gradient"));
+        assert_eq!(svg.matches("<path d=\"M ").count(), 3);
+        assert!(svg.contains("fill=\"#ff0000\""));
+        assert!(svg.contains("fill=\"#008000\""));
+    }
+
+    #[test]
+    fn test_color_names() {
+        let svg = render_svg(
+            "digraph { a [style=filled, fillcolor=\"/blues9/9\"];
+                       b [style=filled, fillcolor=\"0.482 0.714 0.878\"];
+                       c [style=filled, fillcolor=LightGoldenrod3]; }",
+        );
+        assert!(svg.contains("fill=\"#08306b\""));
+        assert!(svg.contains("fill=\"#40e0cf\""));
+        assert!(svg.contains("fill=\"#cdbe70\""));
+    }
+
+    #[test]
+    fn test_alpha_colors() {
+        // Translucent colors are emitted with opacity attributes.
+        let svg = render_svg(
+            "digraph { a [style=filled, fillcolor=\"#ff000080\",
+                          color=\"#0000ff40\"]; b [color=transparent]; }",
+        );
+        assert!(svg.contains("fill=\"#ff0000\" fill-opacity=\"0.502\""));
+        assert!(svg.contains("stroke=\"#0000ff\" stroke-opacity=\"0.251\""));
+        assert!(svg.contains("stroke=\"#000000\" stroke-opacity=\"0\""));
+        assert!(!svg.contains("stroke=\"#ffffff\""));
+    }
+
+    #[test]
+    fn test_streaming_svg() {
+        // Records use clip regions, and the colors lists use gradients.
+        let program = "digraph { bgcolor=lightyellow; label=title;
+            a [shape=record, label=\"{x|<p>y}\", style=rounded];
+            b [style=filled, fillcolor=\"red:blue\"];
+            a:p -> b [arrowhead=odot, label=\"e\"]; b -> c; }";
+        let expected = render_svg(program);
+
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let mut out = Vec::new();
+        write_svg(&mut out, |rb| vg.draw(false, rb)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        // The errors of the sink are returned.
+        struct Full;
+        impl std::io::Write for Full {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::StorageFull.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        assert!(write_svg(&mut Full, |rb| vg.draw(false, rb)).is_err());
+    }
+
+    #[test]
+    fn test_jgf() {
+        let text = r#"{"graph": {"id": "G", "label": "title",
+            "metadata": {"rankdir": "LR"},
+            "nodes": {
+                "a": {"label": "A \"quoted\"", "metadata": {"shape": "box"}},
+                "b": {"metadata": {"width": 2, "layout": {"x": 1}}}},
+            "edges": [
+                {"source": "a", "target": "b", "label": "e",
+                 "metadata": {"color": "red", "headport": "n"}},
+                {"source": "b", "target": "c", "directed": false}]}}"#;
+        let graph = parse_jgf(text).unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let result = vg.layout_result();
+        assert_eq!(result.nodes.len(), 3);
+        assert_eq!(result.edges.len(), 2);
+        // The graph grows from left to right, and 'b' is 2 inches wide.
+        assert!(result.nodes[1].0.x > result.nodes[0].1.x);
+        assert!(result.nodes[1].1.x - result.nodes[1].0.x >= 144.);
+
+        let json = write_jgf(&gb, Some(&result));
+        assert!(json.contains("\"id\": \"G\""));
+        assert!(json.contains("\"directed\": false"));
+        assert!(json.contains("\"label\": \"A \\\"quoted\\\"\""));
+        assert!(json.contains("\"headport\": \"n\""));
+        assert!(json.contains("\"path\": ["));
+
+        // The written graph reads back into the same graph.
+        let mut gb2 = GraphBuilder::new();
+        gb2.visit_graph(&parse_jgf(&json).unwrap());
+        assert_eq!(write_jgf(&gb2, None), write_jgf(&gb, None));
+
+        // The first version of the format lists the nodes, and a DOT graph
+        // can be written as JGF.
+        let text = r#"{"graphs": [{"directed": false,
+            "nodes": [{"id": "x"}, {"id": 7}],
+            "edges": [{"source": "x", "target": 7}]}]}"#;
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&parse_jgf(text).unwrap());
+        let json = write_jgf(&gb, None);
+        assert!(json.contains("\"7\": {}"));
+        assert!(json.contains("\"directed\": false"));
+        let program = "digraph { a -> b -> c; }";
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&DotParser::new(program).process().unwrap());
+        assert!(write_jgf(&gb, None).contains("\"target\": \"c\""));
+
+        assert!(parse_jgf("{\"nodes\": {}}").is_err());
+        assert!(parse_jgf("{\"graph\": {\"edges\": [{}]}}").is_err());
+        assert!(parse_jgf("{\"graph\": ").is_err());
+    }
+
+    #[test]
+    fn test_dot_output() {
+        let program = "digraph G { node [shape=box]; edge [color=blue]; \
+            subgraph cluster_a { label=A; a; subgraph cluster_b { b; } } \
+            {rank=same; c; d} a:s -> b [label=\"x\\ny\"]; c -> d; d -> a; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let text = gb.to_dot(None);
+        assert!(text.starts_with("digraph G {\n"));
+        assert!(text.contains("  a [shape=box];\n"));
+        assert!(text.contains("  subgraph cluster_a {\n    graph [label=A];"));
+        assert!(text.contains("    subgraph cluster_b {\n      b;\n    }\n"));
+        assert!(text.contains("    graph [rank=same];\n    c;\n    d;\n"));
+        assert!(text.contains("  a:s -> b [color=blue, label=\"x\\ny\"];\n"));
+
+        // The written graph reads back into the same graph, and is drawn in
+        // the same way.
+        let mut gb2 = GraphBuilder::new();
+        gb2.visit_graph(&DotParser::new(&text).process().unwrap());
+        assert_eq!(gb2.to_dot(None), text);
+        let render = |gb: &GraphBuilder| {
+            let mut svg = SVGWriter::new();
+            gb.get().do_it(false, false, false, &mut svg);
+            svg.finalize()
+        };
+        assert_eq!(render(&gb2), render(&gb));
+
+        // The positions are in points, with the y axis pointing up.
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let result = vg.layout_result();
+        let text = gb.to_dot(Some(&result));
+        assert!(text.contains("  graph [bb=\""));
+        let (p0, p1) = result.nodes[0];
+        let y = result.bbox.0.y + result.bbox.1.y - (p0.y + p1.y) / 2.;
+        let x = (p0.x + p1.x) / 2.;
+        let pos = format!("pos=\"{},{}\"", format_number(x), format_number(y));
+        assert!(text.contains(&pos));
+        assert!(text.contains("label=\"x\\ny\", pos=\""));
+        assert!(text.contains(", lp=\""));
+    }
+
+    #[test]
+    fn test_xdot_output() {
+        use layout::builder;
+
+        let program =
+            "digraph { label=T; subgraph cluster_a { a [shape=box]; } \
+            a -> b [label=x, dir=both]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let text = gb.to_xdot(&vg);
+        assert!(text.contains("xdotversion=1.7"));
+        // The box of the cluster and the title are drawn by the graph.
+        assert!(text.contains("  graph [label=T, bb=\""));
+        assert!(text.contains(" -T\", xdotversion"));
+        assert!(text.contains("  a [shape=box, pos=\""));
+        assert!(text.contains("_draw_=\"c 7 -#000000 S 5 -solid"));
+        assert!(text.contains(" P 4 "));
+        assert!(text.contains(" E "));
+        assert!(text.contains("1 -a\"];\n"));
+        assert!(text.contains("1 -x\", _hdraw_=\""));
+        assert!(text.contains("_tdraw_=\""));
+
+        // The text is valid DOT, and the xdot attributes are ignored.
+        let mut gb2 = GraphBuilder::new();
+        gb2.visit_graph(&DotParser::new(&text).process().unwrap());
+        assert_eq!(gb2.get().num_nodes(), 2);
+
+        // Graphs that are built in code are written in the same way.
+        let mut graph = builder::GraphBuilder::new();
+        graph.edge("a", "b");
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let text = graph.to_xdot(&vg);
+        assert!(text.contains("  a [pos=\""));
+        assert!(text.contains("B 4 "));
+    }
+
+    #[test]
+    fn test_plain_output() {
+        use layout::builder;
+
+        let program = "digraph { a:p -> b [label=x]; b -> \"c d\"; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let text = gb.to_plain(&vg.layout_result(), false);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("graph 1 "));
+        assert!(lines[1].starts_with("node a "));
+        assert!(lines[1].ends_with(" a solid ellipse black lightgrey"));
+        assert!(lines[3].starts_with("node \"c d\" "));
+        assert!(lines[4].starts_with("edge a b "));
+        assert!(lines[4].contains(" x "));
+        assert!(lines[5].starts_with("edge b \"c d\" 4 "));
+        assert_eq!(lines[6], "stop");
+
+        // The node lines have the center of the node, in inches and with the
+        // y axis pointing up.
+        let result = vg.layout_result();
+        let (n0, n1) = result.nodes[0];
+        let x = (n0.x + n1.x) / 2. - result.bbox.0.x;
+        let fields: Vec<&str> = lines[1].split(' ').collect();
+        assert_eq!(fields[2], format_number(x / 72.));
+
+        let text = gb.to_plain(&vg.layout_result(), true);
+        assert!(text.contains("edge a:p b "));
+
+        // Graphs that are built in code are written in the same way.
+        let mut graph = builder::GraphBuilder::new();
+        graph.edge("a", "b");
+        let mut vg = graph.build();
+        vg.layout(false, false);
+        let text = graph.to_plain(&vg.layout_result(), false);
+        assert!(text.contains("edge a b 4 "));
+    }
+
+    #[test]
+    fn test_html_output() {
+        let program =
+            "digraph { a [tooltip=\"<hi>\", URL=\"x.html\"]; a -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.add_stylesheet(Stylesheet::Link("style.css".into()));
+        vg.add_stylesheet(Stylesheet::Inline(".node { opacity: 0.5; }".into()));
+        let mut html = HTMLWriter::new();
+        vg.do_it(false, false, false, &mut html);
+        let html = html.finalize();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<link rel=\"stylesheet\" href=\"style.css\">"));
+        assert!(html.contains(".node { opacity: 0.5; }\n</style>"));
+        assert!(!html.contains("<?xml"));
+        assert!(html.contains("data-node=\"a\""));
+        assert!(html.contains("data-tooltip=\"&lt;hi&gt;\""));
+        assert!(html.contains("data-url=\"x.html\""));
+        assert!(html.contains("data-from=\"a\" data-to=\"b\""));
+        assert!(html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_html_table_label() {
+        let program = "digraph { node [shape=plaintext]; \
+            a [label=<<TABLE BORDER=\"2\"><TR><TD PORT=\"p\" \
+            BGCOLOR=\"yellow\">one</TD><TD><FONT POINT-SIZE=\"30\">\
+            big</FONT></TD></TR></TABLE>>]; b [label=<x<BR/>y>]; \
+            a:p -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("fill=\"#ffff00\""));
+        assert!(svg.contains("stroke-width=\"2\""));
+        assert!(svg.contains(">one</tspan>"));
+        assert!(svg.contains(">big</tspan>"));
+        assert!(svg.contains("font-size: 30px"));
+        assert!(svg.contains(">x</tspan>"));
+        assert!(!svg.contains("TABLE"));
+    }
+
+    #[test]
+    fn test_cluster_layout() {
+        let program = "digraph { start -> a0; start -> b0; x -> b1; \
+            subgraph cluster_a { label=\"A\"; bgcolor=yellow; a0 -> a1; } \
+            subgraph cluster_b { b0 -> b1 -> b2; \
+            subgraph cluster_c { style=dashed; b2 -> c0; } } \
+            a0 -> b2; a1 -> end; c0 -> end; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        assert_eq!(vg.num_clusters(), 3);
+        assert_eq!(vg.cluster(2).parent, Some(1));
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("data-cluster=\"cluster_a\""));
+        assert!(svg.contains("fill=\"#ffff00\""));
+
+        // The members of each cluster are in its box, and the other nodes
+        // are outside of it.
+        for cluster in 0..vg.num_clusters() {
+            let bbox = vg.cluster_bbox(cluster, false).unwrap();
+            for node in vg.iter_nodes() {
+                let pos = vg.pos(node).bbox(false);
+                let inside = vg
+                    .cluster_of(node)
+                    .is_some_and(|c| vg.is_in_cluster(c, cluster));
+                if inside {
+                    assert!(bbox.0.x <= pos.0.x && pos.1.x <= bbox.1.x);
+                    assert!(bbox.0.y <= pos.0.y && pos.1.y <= bbox.1.y);
+                } else {
+                    assert!(!do_boxes_intersect(bbox, pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_svg_path_and_polygon() {
+        let look = StyleAttr::simple();
+        let mut svg = SVGWriter::new();
+        let (a, b) = (Point::new(0., 0.), Point::new(10., 20.));
+        svg.draw_path(&[(a, a, b, b), (b, b, a, a)], &look, None);
+        svg.draw_polygon(&[a, b, Point::new(20., 0.)], &look, None, None);
+        let svg = svg.finalize();
+        assert!(svg.contains("<path d=\"M 0 0 C 0 0, 10 20, 10 20 C 10 20"));
+        assert!(svg.contains("<polygon points=\"0,0 10,20 20,0\""));
+    }
+
+    #[test]
+    fn test_svg_links() {
+        let program = "digraph { subgraph cluster_x { URL=\"c.html\"; a; } \
+            a [URL=\"a?x=1&y=2\", target=_blank, tooltip=\"A <node>\"]; \
+            a -> b [href=\"e.html\"]; b [tooltip=B]; }";
+        let svg = render_svg(program);
+        assert!(svg.contains("<a href=\"c.html\">"));
+        assert!(svg.contains("<a href=\"a?x=1&amp;y=2\" target=\"_blank\">"));
+        assert!(svg.contains("<title>A &lt;node&gt;</title>"));
+        assert!(svg.contains("<a href=\"e.html\">"));
+        assert!(svg.contains("<g>\n<title>B</title>"));
+        assert_eq!(svg.matches("<a ").count(), svg.matches("</a>").count());
+
+        // The label of the node is inside of the link.
+        let start = svg.find("target=\"_blank\"").unwrap();
+        let end = start + svg[start..].find("</a>").unwrap();
+        assert!(svg[start..end].contains(">a</tspan>"));
+    }
+
+    #[test]
+    fn test_svg_ids_and_classes() {
+        let program = "digraph { stylesheet=\"style.css\"; \
+            subgraph cluster_x { class=group; a; } \
+            a [id=first, class=\"big red\"]; a -> b; a -> b [class=hot]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.add_stylesheet(Stylesheet::Inline(".node { opacity: 0.5; }".into()));
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("id=\"cluster_x\" class=\"cluster group\""));
+        assert!(svg.contains("id=\"first\" class=\"node big red\""));
+        assert!(svg.contains("id=\"b\" class=\"node\""));
+        assert!(svg.contains("id=\"a-&gt;b\" class=\"edge\""));
+        assert!(svg.contains("id=\"a-&gt;b:1\" class=\"edge hot\""));
+        assert!(svg.contains(
+            "<?xml-stylesheet href=\"style.css\" type=\"text/css\"?>"
+        ));
+        assert!(svg.contains(".node { opacity: 0.5; }\n</style>"));
+    }
+
+    #[test]
+    fn test_svg_groups_and_layers() {
+        let program = "digraph { outputorder=edgesfirst; \
+            a -> b [label=\"x\"]; b -> c [zorder=1]; c [zorder=2]; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        let pos = |id: &str| svg.find(&format!("<g id=\"{}\"", id)).unwrap();
+        // The edges come first, and then the elements with a higher z-order.
+        assert!(pos("a-&gt;b") < pos("a"));
+        assert!(pos("b") < pos("b-&gt;c"));
+        assert!(pos("b-&gt;c") < pos("c"));
+        // The label of the edge is in the group of the edge.
+        let label = svg.find(">x</tspan>").unwrap();
+        assert!(pos("a-&gt;b") < label && label < pos("a"));
+
+        let program = "digraph { layers=\"back:front\"; \
+            a [layer=back]; b [layer=2]; c; a -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("<g id=\"layer_back\" class=\"layer\">"));
+        assert!(svg.contains("<g id=\"back_a\""));
+        assert!(!svg.contains("<g id=\"back_b\""));
+        assert!(svg.contains("<g id=\"front_b\""));
+        // Nodes without a layer are in all of the layers, and edges are in
+        // the layers of their ends.
+        assert!(svg.contains("<g id=\"back_c\""));
+        assert!(svg.contains("<g id=\"front_c\""));
+        assert!(svg.contains("<g id=\"back_a-&gt;b\""));
+        assert!(svg.contains("<g id=\"front_a-&gt;b\""));
+    }
+
+    #[test]
+    fn test_themes() {
+        let program = "digraph { a -> b [label=x]; c [fillcolor=red]; \
+            subgraph cluster_x { d [style=filled]; } }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.set_theme(Theme::dark());
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        // The background, the nodes and the labels take the dark colors.
+        assert!(svg.contains(
+            "<rect width=\"100%\" height=\"100%\" \
+            fill=\"#1e1e1e\""
+        ));
+        assert!(svg.contains("fill=\"#2d2d2d\""));
+        assert!(svg.contains("fill=\"#3c3c3c\""));
+        assert!(svg.contains("stroke=\"#a0a0a0\""));
+        assert!(svg.contains("class=\"a14\" fill=\"#d4d4d4\""));
+        // The attributes of the elements override the theme.
+        assert!(svg.contains("fill=\"#ff0000\""));
+
+        // The 'bgcolor' attribute overrides the background of the theme.
+        let program = "digraph { bgcolor=ivory; a; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.set_theme(Theme::high_contrast());
+        gb.visit_graph(&graph);
+        let vg = gb.get();
+        assert_eq!(vg.background(), Some(Color::fast("ivory")));
+        assert_eq!(vg.element(NodeHandle::new(0)).look.penwidth, 2.);
+    }
+
+    #[test]
+    fn test_style_hook() {
+        let program = "digraph { a -> b [label=x]; b -> c; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let b = gb.node_handle("b").unwrap();
+        assert_eq!(gb.node_handle("d"), None);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+
+        // Color the node b and the first edge, and count the elements.
+        let mut seen = Vec::new();
+        let mut svg = SVGWriter::new();
+        vg.draw_with_style_hook(false, &mut svg, &mut |elem, look| {
+            seen.push(elem);
+            if elem == ElementRef::Node(b) {
+                look.fill_color = Some(Color::fast("red"));
+            }
+            if elem == ElementRef::Edge(0) {
+                look.line_color = Color::fast("blue");
+                look.font_color = Color::fast("green");
+            }
+        });
+        let svg = svg.finalize();
+        assert_eq!(seen.len(), 5);
+        assert!(seen.contains(&ElementRef::Edge(1)));
+        assert!(svg.contains("fill=\"#ff0000\""));
+        assert!(svg.contains("stroke=\"#0000ff\""));
+        assert!(svg.contains("fill=\"#008000\""));
+
+        // The style of the graph doesn't change.
+        let mut svg = SVGWriter::new();
+        vg.draw(false, &mut svg);
+        let svg = svg.finalize();
+        assert!(!svg.contains("#ff0000") && !svg.contains("#0000ff"));
+    }
+
+    #[test]
+    fn test_avoid_nodes() {
+        // The straight edge from a to c goes through the wide node b, unless
+        // it's routed.
+        let make = |attrs: &str| {
+            let program = format!(
+                "digraph {{ {} a -> b -> c; a -> c; \
+                 b [label=\"a node with a very wide label\"]; }}",
+                attrs
+            );
+            let graph = DotParser::new(&program).process().unwrap();
+            let mut gb = GraphBuilder::new();
+            gb.visit_graph(&graph);
+            let mut vg = gb.get();
+            vg.layout(false, false);
+            vg
+        };
+        let crosses_b = |vg: &VisualGraph| {
+            let layout = vg.layout_result();
+            let (top, bottom) = layout.nodes[1];
+            let segments = &layout.edges[2].path;
+            (0..=100).any(|i| {
+                let t = i as f64 / 100.;
+                segments.iter().any(|(p0, p1, p2, p3)| {
+                    let p = cubic_bezier(*p0, *p1, *p2, *p3, t);
+                    top.x < p.x
+                        && p.x < bottom.x
+                        && top.y < p.y
+                        && p.y < bottom.y
+                })
+            })
+        };
+        let vg = make("splines=line;");
+        assert!(!vg.avoid_nodes());
+        assert!(crosses_b(&vg));
+        let vg = make("splines=line; avoidnodes=true;");
+        assert!(vg.avoid_nodes());
+        assert!(!crosses_b(&vg));
+        assert!(!crosses_b(&make("avoidnodes=true;")));
+
+        // The other edges don't change.
+        let before = make("splines=line;").layout_result();
+        let after = vg.layout_result();
+        assert_eq!(before.edges[0].path, after.edges[0].path);
+    }
+
+    #[test]
+    fn test_hit_testing() {
+        let program =
+            "digraph { subgraph cluster_x { a; b; } a -> b; b -> c; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let layout = vg.layout_result();
+        let index = vg.hit_index();
+        let center = |i: usize| {
+            let (p0, p1) = layout.nodes[i];
+            p0.add(p1).scale(0.5)
+        };
+
+        // The nodes, the edges and the clusters under a point.
+        let a = NodeHandle::new(0);
+        assert_eq!(index.element_at(center(0)), Some(ElementRef::Node(a)));
+        let (p0, p1, p2, p3) = layout.edges[1].path[0];
+        let mid = cubic_bezier(p0, p1, p2, p3, 0.5);
+        let near = mid.add(Point::new(2., 0.));
+        assert_eq!(index.element_at(near), Some(ElementRef::Edge(1)));
+        let corner = layout.clusters[0].unwrap().0.add(Point::splat(2.));
+        assert_eq!(index.element_at(corner), Some(ElementRef::Cluster(0)));
+        let far = layout.bbox.1.add(Point::splat(100.));
+        assert_eq!(index.element_at(far), None);
+
+        // The elements in a rectangle around the nodes a and b.
+        let (top, bottom) = layout.clusters[0].unwrap();
+        let inside = index.elements_in_rect(top, bottom, true);
+        assert_eq!(
+            inside,
+            vec![
+                ElementRef::Node(a),
+                ElementRef::Node(NodeHandle::new(1)),
+                ElementRef::Edge(0),
+                ElementRef::Cluster(0),
+            ]
+        );
+        let touching = index.elements_in_rect(top, bottom, false);
+        assert!(touching.contains(&ElementRef::Edge(1)));
+        assert!(!touching.contains(&ElementRef::Node(NodeHandle::new(2))));
+    }
+
+    #[test]
+    fn test_output_transform() {
+        let program = "digraph { node [shape=box]; a -> b; }";
+        let graph = DotParser::new(program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        vg.layout(false, false);
+        let layout = vg.layout_result();
+        let (top, bottom) = layout.bbox;
+        let transform = Transform {
+            dpi: 96.,
+            translate: Point::new(5., 0.),
+            ..Transform::default()
+        }
+        .flip_in_place(layout.bbox);
+
+        // The flipped drawing has the node b on top, at 4/3 of the size.
+        let mut rec = RecordingBackend::new();
+        vg.draw(
+            false,
+            &mut ScaledBackend::with_transform(&mut rec, transform),
+        );
+        let rects: Vec<(Point, Point)> = rec
+            .commands()
+            .iter()
+            .filter_map(|x| match x {
+                DrawCommand::Rect { xy, size, .. } => Some((*xy, *size)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rects.len(), 2);
+        let (a, b) = (layout.nodes[0], layout.nodes[1]);
+        let scale = 96. / 72.;
+        assert!(rects[1].0.y < rects[0].0.y);
+        let a_top = (top.y + bottom.y - a.1.y) * scale;
+        assert!((rects[0].0.y - a_top).abs() < 1e-9);
+        assert!((rects[0].0.x - (a.0.x * scale + 5.)).abs() < 1e-9);
+        let size = b.1.sub(b.0).scale(scale);
+        assert!((rects[1].1.x - size.x).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_image_nodes() {
+        // A 200x80 pixel PNG header, that is 150x60 points.
+        let dir = std::env::temp_dir().join("layout_test_image_nodes");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 200, 0, 0, 0, 80]);
+        std::fs::write(dir.join("logo.png"), &png).unwrap();
+
+        let program = format!(
+            "digraph {{ imagepath=\"{}\"; a [image=\"logo.png\", label=\"\"]; \
+             b [image=\"missing.png\"]; a -> b; }}",
+            dir.display()
+        );
+        let graph = DotParser::new(&program).process().unwrap();
+        let mut gb = GraphBuilder::new();
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let a = vg.iter_nodes().next().unwrap();
+        let size = vg.pos(a).size(false);
+        assert!(size.x >= 150. && size.y >= 60.);
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        let svg = svg.finalize();
+        assert!(svg.contains("width=\"150\" height=\"60\""));
+        assert!(svg.contains("logo.png\" preserveAspectRatio=\"none\""));
+        assert_eq!(svg.matches("<image").count(), 1);
+
+        // Embedded images carry their data, and the text backends draw a
+        // box with the name of the file.
+        let mut gb = GraphBuilder::new();
+        gb.set_embed_images(true);
+        gb.visit_graph(&graph);
+        let mut vg = gb.get();
+        let mut svg = SVGWriter::new();
+        vg.do_it(false, false, false, &mut svg);
+        assert!(svg.finalize().contains("href=\"data:image/png;base64,"));
+        let mut rec = RecordingBackend::new();
+        let size = Point::new(120., 40.);
+        rec.draw_image(Point::zero(), size, "a/b.png", None);
+        assert!(matches!(rec.commands()[0], DrawCommand::Image { .. }));
+        let mut ascii = ASCIIWriter::new_with_terminal_setting(false);
+        rec.replay(&mut ascii);
+        assert!(ascii.finalize().contains("b.png"));
+    }
+
     #[test]
     fn test_median() {
         let k = weighted_median(&[1.]);
@@ -243,6 +3341,40 @@ mod tests {
             let _ = weighted_median(&data);
         }
     }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_cli_several_outputs() {
+        // The graph is laid out once, and drawn in each of the formats.
+        let dir = std::env::temp_dir().join("layout_test_cli_outputs");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/inputs/1.dot");
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_layout"))
+            .arg(input)
+            .arg("--png")
+            .arg(dir.join("a.png"))
+            .arg("--svg")
+            .arg(dir.join("a.svg"))
+            .arg("-T")
+            .arg("json")
+            .arg("-o")
+            .arg(dir.join("a.json"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let svg = std::fs::read_to_string(dir.join("a.svg")).unwrap();
+        assert!(svg.contains("</svg>"));
+        let png = std::fs::read(dir.join("a.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(std::fs::metadata(dir.join("a.json")).unwrap().len() > 0);
+
+        // The failures are reported with the exit code.
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_layout"))
+            .arg(dir.join("missing.dot"))
+            .status()
+            .unwrap();
+        assert!(!status.success());
+    }
 }
 
 #[test]